@@ -2,7 +2,7 @@
 use std::convert::TryFrom;
 use std::ffi::OsStr;
 use std::fmt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use rustc_data_structures::fx::FxHashMap;
@@ -22,6 +22,7 @@
 
 use crate::core::new_handler;
 use crate::externalfiles::ExternalHtml;
+use crate::fuzz_target::fuzz_target_options::FuzzTargetOptions;
 use crate::html;
 use crate::html::markdown::IdMap;
 use crate::html::render::StylePath;
@@ -279,6 +280,8 @@ pub(crate) struct RenderOptions {
     pub(crate) call_locations: AllCallLocations,
     /// If `true`, Context::init will not emit shared files.
     pub(crate) no_emit_shared: bool,
+    /// Options of the fuzz target generator.
+    pub(crate) fuzz_target_options: FuzzTargetOptions,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -447,17 +450,31 @@ fn println_condition(condition: Condition) {
 
         let (lint_opts, describe_lints, lint_cap) = get_cmd_lint_options(matches, error_format);
 
-        let input = PathBuf::from(if describe_lints {
-            "" // dummy, this won't be used
-        } else if matches.free.is_empty() {
-            diag.struct_err("missing file operand").emit();
-            return Err(1);
+        let fuzz_target_options = FuzzTargetOptions::new(matches, &diag)?;
+        let input = if describe_lints {
+            PathBuf::new() // dummy, this won't be used
         } else if matches.free.len() > 1 {
             diag.struct_err("too many file operands").emit();
             return Err(1);
+        } else if let Some(lib_target) = &fuzz_target_options.lib_target {
+            // The lib root may not be `src/lib.rs` (custom `[lib] path`), and `#[path]`
+            // attributes are resolved relative to it, so always use the real one.
+            if let Some(file) = matches.free.first() {
+                if Path::new(file).canonicalize().ok() != lib_target.src_path.canonicalize().ok() {
+                    println!(
+                        "note: use lib root {} from cargo metadata instead of {}",
+                        lib_target.src_path.display(),
+                        file
+                    );
+                }
+            }
+            lib_target.src_path.clone()
+        } else if matches.free.is_empty() {
+            diag.struct_err("missing file operand").emit();
+            return Err(1);
         } else {
-            &matches.free[0]
-        });
+            PathBuf::from(&matches.free[0])
+        };
 
         let libs = matches
             .opt_strs("L")
@@ -543,7 +560,9 @@ fn println_condition(condition: Condition) {
             (None, None) => PathBuf::from("doc"),
         };
 
-        let cfgs = matches.opt_strs("cfg");
+        let mut cfgs = matches.opt_strs("cfg");
+        // Items behind `#[cfg(feature = "..")]` are only documented with their features enabled.
+        cfgs.extend(crate::fuzz_target::features::feature_cfgs(&fuzz_target_options.features));
         let check_cfgs = matches.opt_strs("check-cfg");
 
         let extension_css = matches.opt_str("e").map(|s| PathBuf::from(&s));
@@ -665,7 +684,9 @@ fn println_condition(condition: Condition) {
             },
             None => OutputFormat::default(),
         };
-        let crate_name = matches.opt_str("crate-name");
+        let crate_name = matches.opt_str("crate-name").or_else(|| {
+            fuzz_target_options.lib_target.as_ref().map(|lib_target| lib_target.name.clone())
+        });
         let proc_macro_crate = crate_types.contains(&CrateType::ProcMacro);
         let playground_url = matches.opt_str("playground-url");
         let maybe_sysroot = matches.opt_str("sysroot").map(PathBuf::from);
@@ -693,7 +714,9 @@ fn println_condition(condition: Condition) {
         let runtool_args = matches.opt_strs("runtool-arg");
         let enable_per_target_ignores = matches.opt_present("enable-per-target-ignores");
         let document_private = matches.opt_present("document-private-items");
-        let document_hidden = matches.opt_present("document-hidden-items");
+        //hidden items are stripped before the fuzz targets see them, unless they are fuzzed
+        let document_hidden = matches.opt_present("document-hidden-items")
+            || fuzz_target_options.include_hidden;
         let run_check = matches.opt_present("check");
         let generate_redirect_map = matches.opt_present("generate-redirect-map");
         let show_type_layout = matches.opt_present("show-type-layout");
@@ -784,6 +807,7 @@ fn println_condition(condition: Condition) {
             generate_link_to_definition,
             call_locations,
             no_emit_shared: false,
+            fuzz_target_options,
         };
         Ok((options, render_options))
     }
@@ -460,6 +460,11 @@ fn report_deprecated_attr(name: &str, diag: &rustc_errors::Handler, sp: Span) {
         rustc_errors::FatalError.raise();
     }
 
+    //apis of the dependencies given by `--include-deps`, for the fuzz target renderer
+    crate::fuzz_target::dependency::load_dependency_apis(&mut ctxt);
+    //apis defined by macros, the renderer adds the ones missing from the doc tree
+    crate::fuzz_target::macro_api::load_macro_apis(&mut ctxt);
+
     krate = tcx.sess.time("create_format_cache", || Cache::populate(&mut ctxt, krate));
 
     (krate, ctxt.render_options, ctxt.cache)
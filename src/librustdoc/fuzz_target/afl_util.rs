@@ -1,5 +1,7 @@
 use crate::clean::PrimitiveType;
+use crate::fuzz_target::code_ast::{Block, Expr, Pat, Stmt};
 use crate::fuzz_target::fuzzable_type::FuzzableType;
+use crate::fuzz_target::rust_version::{self, RustVersion};
 use rustc_data_structures::fx::FxHashSet;
 #[derive(Debug, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
 pub(crate) enum _AflHelpers {
@@ -23,6 +25,11 @@ pub(crate) enum _AflHelpers {
     _Str,
     _Slice(Box<_AflHelpers>),
     _Tuple(Vec<Box<_AflHelpers>>),
+    _Array(Box<_AflHelpers>, usize),
+    //每个variant的字段的helper
+    _Enum(Vec<Vec<_AflHelpers>>),
+    //字段的helper
+    _Struct(Vec<_AflHelpers>),
 }
 
 impl _AflHelpers {
@@ -60,6 +67,21 @@ pub(crate) fn _new_from_fuzzable(fuzzable: &FuzzableType) -> Self {
                     .collect();
                 _AflHelpers::_Tuple(inner_afl_helpers)
             }
+            FuzzableType::Array(inner_fuzzable, length) => {
+                let inner_afl_helper = _AflHelpers::_new_from_fuzzable(inner_fuzzable);
+                _AflHelpers::_Array(Box::new(inner_afl_helper), *length)
+            }
+            FuzzableType::Enum(_, variants) => _AflHelpers::_Enum(
+                variants
+                    .iter()
+                    .map(|variant| {
+                        variant.fields.iter().map(_AflHelpers::_new_from_fuzzable).collect()
+                    })
+                    .collect(),
+            ),
+            FuzzableType::Struct(variant) => _AflHelpers::_Struct(
+                variant.fields.iter().map(_AflHelpers::_new_from_fuzzable).collect(),
+            ),
         }
     }
 
@@ -67,6 +89,7 @@ pub(crate) fn _new_from_fuzzable(fuzzable: &FuzzableType) -> Self {
     //TODO：注意到这个函数在处理slice的时候会有些问题，不同的slice会有多个afl helpers，
     //但实际上我们只需要定义一个函数，但这个函数很难继续调整，所以我们在别的地方需要注意这里面的逻辑
     //Tuple在这一步已经全部排除掉了，所以接下来不会再有tuple的问题
+    //数组没有对应的函数，只依赖元素的helper，u8数组直接从data复制
     pub(crate) fn _get_all_dependent_afl_helpers(&self) -> Vec<_AflHelpers> {
         let mut helpers = Vec::new();
         if let _AflHelpers::_Tuple(inner_helpers) = self {
@@ -74,16 +97,35 @@ pub(crate) fn _get_all_dependent_afl_helpers(&self) -> Vec<_AflHelpers> {
                 let mut inner_dependent = afl_helper._get_all_dependent_afl_helpers();
                 helpers.append(&mut inner_dependent);
             }
+        } else if let _AflHelpers::_Array(inner_helper, _) = self {
+            if **inner_helper != _AflHelpers::_U8 {
+                helpers.append(&mut inner_helper._get_all_dependent_afl_helpers());
+            }
+        } else if let _AflHelpers::_Enum(variants) = self {
+            //用一个u8选择variant
+            helpers.append(&mut _AflHelpers::_U8._get_all_dependent_afl_helpers());
+            for field_helper in variants.iter().flatten() {
+                helpers.append(&mut field_helper._get_all_dependent_afl_helpers());
+            }
+        } else if let _AflHelpers::_Struct(field_helpers) = self {
+            for field_helper in field_helpers {
+                helpers.append(&mut field_helper._get_all_dependent_afl_helpers());
+            }
         } else {
             helpers.push(self.clone());
             match self {
                 _AflHelpers::_U8
                 | _AflHelpers::_I8
                 | _AflHelpers::_NoHelper
-                | _AflHelpers::_Slice(..)
                 | _AflHelpers::_Str
                 | _AflHelpers::_F32
                 | _AflHelpers::_F64 => {}
+                //除了u8以外的slice逐个元素解码
+                _AflHelpers::_Slice(inner_helper) => {
+                    if !inner_helper._is_u8() {
+                        helpers.append(&mut inner_helper._get_all_dependent_afl_helpers());
+                    }
+                }
                 _AflHelpers::_Bool => {
                     let mut u8_dependency = _AflHelpers::_U8._get_all_dependent_afl_helpers();
                     helpers.append(&mut u8_dependency);
@@ -132,13 +174,17 @@ pub(crate) fn _get_all_dependent_afl_helpers(&self) -> Vec<_AflHelpers> {
                     let mut u32_dependency = _AflHelpers::_U32._get_all_dependent_afl_helpers();
                     helpers.append(&mut u32_dependency);
                 }
-                _AflHelpers::_Tuple(..) => {}
+                _AflHelpers::_Tuple(..)
+                | _AflHelpers::_Array(..)
+                | _AflHelpers::_Enum(..)
+                | _AflHelpers::_Struct(..) => {}
             }
         }
         helpers
     }
 
-    pub(crate) fn _to_full_function(&self) -> &'static str {
+    //msrv较低时选择只用旧版本std api的实现
+    pub(crate) fn _to_full_function(&self, msrv: Option<RustVersion>) -> &'static str {
         match self {
             _AflHelpers::_NoHelper => "afl no helper",
             _AflHelpers::_U8 => _data_to_u8(),
@@ -147,19 +193,46 @@ pub(crate) fn _to_full_function(&self) -> &'static str {
             _AflHelpers::_I16 => _data_to_i16(),
             _AflHelpers::_U32 => _data_to_u32(),
             _AflHelpers::_I32 => _data_to_i32(),
-            _AflHelpers::_F32 => _data_to_f32(),
+            _AflHelpers::_F32 => {
+                if rust_version::supports(msrv, rust_version::FLOAT_FROM_BYTES) {
+                    _data_to_f32()
+                } else {
+                    _data_to_f32_from_bits()
+                }
+            }
             _AflHelpers::_U64 => _data_to_u64(),
             _AflHelpers::_I64 => _data_to_i64(),
-            _AflHelpers::_F64 => _data_to_f64(),
+            _AflHelpers::_F64 => {
+                if rust_version::supports(msrv, rust_version::FLOAT_FROM_BYTES) {
+                    _data_to_f64()
+                } else {
+                    _data_to_f64_from_bits()
+                }
+            }
             _AflHelpers::_U128 => _data_to_u128(),
             _AflHelpers::_I128 => _data_to_i128(),
             _AflHelpers::_Usize => _data_to_usize(),
             _AflHelpers::_Isize => _data_to_isize(),
-            _AflHelpers::_Char => _data_to_char(),
+            _AflHelpers::_Char => {
+                if rust_version::supports(msrv, rust_version::ASSOC_CHAR_FUNCS) {
+                    _data_to_char()
+                } else {
+                    _data_to_std_char()
+                }
+            }
             _AflHelpers::_Bool => _data_to_bool(),
             _AflHelpers::_Str => _data_to_str(),
-            _AflHelpers::_Slice(..) => _data_to_slice(),
-            _AflHelpers::_Tuple(..) => "",
+            _AflHelpers::_Slice(inner_helper) => {
+                if inner_helper._is_u8() {
+                    _data_to_slice()
+                } else {
+                    _data_to_vec()
+                }
+            }
+            _AflHelpers::_Tuple(..)
+            | _AflHelpers::_Array(..)
+            | _AflHelpers::_Enum(..)
+            | _AflHelpers::_Struct(..) => "",
         }
     }
 
@@ -195,9 +268,17 @@ pub(crate) fn _type_name(&self) -> String {
                     }
                     type_name.push_str(inner_type_name.as_str());
                 }
+                if inner_afl_helpers_length == 1 {
+                    type_name.push_str(",");
+                }
                 type_name.push_str(")");
                 return type_name;
             }
+            _AflHelpers::_Array(inner_afl_helper, length) => {
+                format!("[{}; {}]", inner_afl_helper._type_name(), length)
+            }
+            _AflHelpers::_Enum(..) => "enum".to_string(),
+            _AflHelpers::_Struct(..) => "struct".to_string(),
         }
     }
 
@@ -206,6 +287,9 @@ pub(crate) fn _to_function_name(&self) -> String {
             _AflHelpers::_Slice(inner_afl_helpers) => {
                 //不考虑内部还是slice或者str的情况,这种函数在构建graph的时候就已经作为多维动态长度被删掉了
                 //tuple里面也不会出现slice或者
+                if !inner_afl_helpers._is_u8() {
+                    return "_to_vec".to_string();
+                }
                 let inner_type_name = inner_afl_helpers._type_name();
                 format!(
                     "_to_{type_name}::<{inner_type_name}>",
@@ -213,7 +297,10 @@ pub(crate) fn _to_function_name(&self) -> String {
                     inner_type_name = inner_type_name
                 )
             }
-            _AflHelpers::_Tuple(..) => String::new(),
+            _AflHelpers::_Tuple(..)
+            | _AflHelpers::_Array(..)
+            | _AflHelpers::_Enum(..)
+            | _AflHelpers::_Struct(..) => String::new(),
             _ => {
                 format!("_to_{type_name}", type_name = self._type_name())
             }
@@ -238,12 +325,17 @@ pub(crate) fn _print_all() {
         println!("{}", _data_to_bool());
         println!("{}", _data_to_str());
         println!("{}", _data_to_slice());
+        println!("{}", _data_to_vec());
         println!("{}", _data_to_f32());
         println!("{}", _data_to_f64());
     }
 
     //may remove later
-    pub(crate) fn _feature_gate(&self) -> Option<String> {
+    //指定了msrv时生成的代码要用stable编译，不能开启feature gate
+    pub(crate) fn _feature_gate(&self, msrv: Option<RustVersion>) -> Option<String> {
+        if msrv.is_some() {
+            return None;
+        }
         match self {
             _AflHelpers::_Char => {
                 let s = "#![feature(assoc_char_funcs)]".to_string();
@@ -260,6 +352,13 @@ pub(crate) fn _is_slice(&self) -> bool {
         }
     }
 
+    pub(crate) fn _is_u8(&self) -> bool {
+        match self {
+            _AflHelpers::_U8 => true,
+            _ => false,
+        }
+    }
+
     pub(crate) fn _is_tuple(&self) -> bool {
         match self {
             _AflHelpers::_Tuple(..) => return true,
@@ -320,31 +419,56 @@ pub(crate) fn _generate_param_initial_rhs(
             | _AflHelpers::_Usize
             | _AflHelpers::_Isize
             | _AflHelpers::_F32
-            | _AflHelpers::_F64 => {
+            | _AflHelpers::_F64
+            | _AflHelpers::_Array(..)
+            | _AflHelpers::_Enum(..)
+            | _AflHelpers::_Struct(..) => {
+                self._generate_fixed_rhs("", fixed_start_index, origin_fuzzable_type)
+            }
+            _AflHelpers::_Slice(inner_afl_helper) if !inner_afl_helper._is_u8() => {
+                //&_to_vec(..)[..]，临时的Vec的生命周期会延长到整个let语句所在的块
+                let inner_fuzzable = match origin_fuzzable_type {
+                    FuzzableType::RefSlice(inner_fuzzable) => inner_fuzzable,
+                    _ => return "Type not match in afl_util".to_string(),
+                };
+                let (start_index, end_index) = Self::_dynamic_range(
+                    dynamic_start_index,
+                    dynamic_param_index,
+                    total_dynamic_param_numbers,
+                    dynamic_param_length,
+                );
+                let element_function = match &**inner_afl_helper {
+                    _AflHelpers::_Tuple(..)
+                    | _AflHelpers::_Array(..)
+                    | _AflHelpers::_Enum(..)
+                    | _AflHelpers::_Struct(..) => {
+                        format!(
+                            "|data: &[u8], index: usize| {}",
+                            inner_afl_helper._generate_fixed_rhs("index", 0, inner_fuzzable)
+                        )
+                    }
+                    _ => inner_afl_helper._to_function_name(),
+                };
                 format!(
-                    "{afl_function_name}(data, {fixed_start_index})",
-                    afl_function_name = self._to_function_name(),
-                    fixed_start_index = fixed_start_index
+                    "&_to_vec(data, {}, {}, {}, {})[..]",
+                    start_index,
+                    end_index,
+                    inner_fuzzable._min_length(),
+                    element_function
                 )
             }
             _AflHelpers::_Str | _AflHelpers::_Slice(..) => {
-                let latter_index = if dynamic_param_index == total_dynamic_param_numbers - 1 {
-                    format!("data.len()")
-                } else {
-                    format!(
-                        "{dynamic_start_index} + {dynamic_param_index} * {dynamic_param_length}",
-                        dynamic_start_index = dynamic_start_index,
-                        dynamic_param_index = dynamic_param_index + 1,
-                        dynamic_param_length = dynamic_param_length
-                    )
-                };
+                let (start_index, end_index) = Self::_dynamic_range(
+                    dynamic_start_index,
+                    dynamic_param_index,
+                    total_dynamic_param_numbers,
+                    dynamic_param_length,
+                );
                 format!(
-                    "{afl_function_name}(data, {dynamic_start_index} + {dynamic_param_index} * {dynamic_param_length}, {latter_index})",
+                    "{afl_function_name}(data, {start_index}, {end_index})",
                     afl_function_name = self._to_function_name(),
-                    dynamic_start_index = dynamic_start_index,
-                    dynamic_param_index = dynamic_param_index,
-                    dynamic_param_length = dynamic_param_length,
-                    latter_index = latter_index
+                    start_index = start_index,
+                    end_index = end_index
                 )
             }
             _AflHelpers::_Tuple(inner_afl_helpers) => {
@@ -385,6 +509,132 @@ pub(crate) fn _generate_param_initial_rhs(
             }
         }
     }
+
+    //第dynamic_param_index个动态长度的变量在data中的起止位置，最后一个变量一直到data的结尾
+    pub(crate) fn _dynamic_range(
+        dynamic_start_index: usize,
+        dynamic_param_index: usize,
+        total_dynamic_param_numbers: usize,
+        dynamic_param_length: &String,
+    ) -> (String, String) {
+        let start_index = format!(
+            "{dynamic_start_index} + {dynamic_param_index} * {dynamic_param_length}",
+            dynamic_start_index = dynamic_start_index,
+            dynamic_param_index = dynamic_param_index,
+            dynamic_param_length = dynamic_param_length
+        );
+        let end_index = if dynamic_param_index == total_dynamic_param_numbers - 1 {
+            format!("data.len()")
+        } else {
+            format!(
+                "{dynamic_start_index} + {dynamic_param_index} * {dynamic_param_length}",
+                dynamic_start_index = dynamic_start_index,
+                dynamic_param_index = dynamic_param_index + 1,
+                dynamic_param_length = dynamic_param_length
+            )
+        };
+        (start_index, end_index)
+    }
+
+    //定长的变量，从`base + offset`开始解码，base为空时直接用offset
+    pub(crate) fn _generate_fixed_rhs(
+        &self,
+        base: &str,
+        offset: usize,
+        origin_fuzzable_type: &FuzzableType,
+    ) -> String {
+        let index = match (base, offset) {
+            ("", _) => offset.to_string(),
+            (_, 0) => base.to_string(),
+            _ => format!("{} + {}", base, offset),
+        };
+        match (self, origin_fuzzable_type) {
+            (_AflHelpers::_Tuple(inner_afl_helpers), FuzzableType::Tuple(inner_fuzzables)) => {
+                let mut inner_rhs = Vec::new();
+                let mut inner_offset = offset;
+                for (inner_afl_helper, inner_fuzzable) in
+                    inner_afl_helpers.iter().zip(inner_fuzzables.iter())
+                {
+                    inner_rhs.push(inner_afl_helper._generate_fixed_rhs(
+                        base,
+                        inner_offset,
+                        inner_fuzzable,
+                    ));
+                    inner_offset = inner_offset + inner_fuzzable._min_length();
+                }
+                if inner_rhs.len() == 1 {
+                    format!("({},)", inner_rhs[0])
+                } else {
+                    format!("({})", inner_rhs.join(", "))
+                }
+            }
+            (
+                _AflHelpers::_Array(inner_afl_helper, length),
+                FuzzableType::Array(inner_fuzzable, _),
+            ) => {
+                //u8数组直接从data复制，避免生成很长的数组表达式
+                if inner_afl_helper._is_u8() {
+                    return format!(
+                        "{{ let mut _array = [0u8; {length}]; \
+                        _array.copy_from_slice(&data[{index}..{index} + {length}]); _array }}",
+                        length = length,
+                        index = index
+                    );
+                }
+                let element_length = inner_fuzzable._min_length();
+                let elements: Vec<String> = (0..*length)
+                    .map(|k| {
+                        inner_afl_helper._generate_fixed_rhs(
+                            base,
+                            offset + k * element_length,
+                            inner_fuzzable,
+                        )
+                    })
+                    .collect();
+                format!("[{}]", elements.join(", "))
+            }
+            (_AflHelpers::_Enum(variant_helpers), FuzzableType::Enum(full_name, variants)) => {
+                //match _to_u8(data, index) % n { 0 => E::A, 1 => E::B(..), _ => E::C { .. } }
+                let mut arms = Vec::new();
+                for (k, (field_helpers, variant)) in
+                    variant_helpers.iter().zip(variants.iter()).enumerate()
+                {
+                    let mut fields = Vec::new();
+                    let mut field_offset = offset + 1;
+                    for (field_helper, field) in field_helpers.iter().zip(variant.fields.iter()) {
+                        fields.push(field_helper._generate_fixed_rhs(base, field_offset, field));
+                        field_offset = field_offset + field._min_length();
+                    }
+                    let path = format!("{}::{}", full_name, variant.name);
+                    let constructor = variant._to_constructor(&path, &fields);
+                    let pattern =
+                        if k == variants.len() - 1 { "_".to_string() } else { k.to_string() };
+                    arms.push(format!("{} => {}", pattern, constructor));
+                }
+                format!(
+                    "match _to_u8(data, {}) % {} {{ {} }}",
+                    index,
+                    variants.len(),
+                    arms.join(", ")
+                )
+            }
+            (_AflHelpers::_Struct(field_helpers), FuzzableType::Struct(variant)) => {
+                //S { x: .., y: .. }，字段依次排列
+                let mut fields = Vec::new();
+                let mut field_offset = offset;
+                for (field_helper, field) in field_helpers.iter().zip(variant.fields.iter()) {
+                    fields.push(field_helper._generate_fixed_rhs(base, field_offset, field));
+                    field_offset = field_offset + field._min_length();
+                }
+                variant._to_constructor(&variant.name, &fields)
+            }
+            (_AflHelpers::_Tuple(..), _)
+            | (_AflHelpers::_Array(..), _)
+            | (_AflHelpers::_Enum(..), _)
+            | (_AflHelpers::_Struct(..), _) => "Type not match in afl_util".to_string(),
+            _ => format!("{}(data, {})", self._to_function_name(), index),
+        }
+    }
 }
 
 //使用FxHashset去重
@@ -405,6 +655,7 @@ pub(crate) fn _get_all_dependent_afl_helpers_of_sequence(
 //获得所有的函数的定义，对于slice的话，由于采用了范型，只需要加入一次
 pub(crate) fn _get_afl_helpers_functions_of_sequence(
     fuzzable_params: &Vec<FuzzableType>,
+    msrv: Option<RustVersion>,
 ) -> Option<Vec<String>> {
     let afl_helpers = _get_all_dependent_afl_helpers_of_sequence(fuzzable_params);
     if afl_helpers.len() < 1 {
@@ -412,24 +663,25 @@ pub(crate) fn _get_afl_helpers_functions_of_sequence(
     }
     let mut afl_helper_functions = Vec::new();
 
-    let mut contains_slice_flag = false;
+    //不同元素类型的slice共用一个范型函数，只需要定义一次
     for afl_helper in afl_helpers {
-        if !contains_slice_flag && afl_helper._is_slice() {
-            contains_slice_flag = true;
-            afl_helper_functions.push(afl_helper._to_full_function().to_string());
-            continue;
+        let afl_helper_function = afl_helper._to_full_function(msrv).to_string();
+        if !afl_helper_functions.contains(&afl_helper_function) {
+            afl_helper_functions.push(afl_helper_function);
         }
-        afl_helper_functions.push(afl_helper._to_full_function().to_string())
     }
     Some(afl_helper_functions)
 }
 
 //获得可能的feature gate,
-pub(crate) fn _get_feature_gates_of_sequence(fuzzable_params: &Vec<FuzzableType>) -> Option<Vec<String>> {
+pub(crate) fn _get_feature_gates_of_sequence(
+    fuzzable_params: &Vec<FuzzableType>,
+    msrv: Option<RustVersion>,
+) -> Option<Vec<String>> {
     let all_afl_helpers = _get_all_dependent_afl_helpers_of_sequence(fuzzable_params);
     let mut feature_gates = FxHashSet::default();
     for afl_helper in all_afl_helpers {
-        let feature_gate = afl_helper._feature_gate();
+        let feature_gate = afl_helper._feature_gate(msrv);
         if feature_gate.is_some() {
             let feature_gate = feature_gate.unwrap();
             feature_gates.insert(feature_gate);
@@ -445,6 +697,47 @@ pub(crate) fn _get_feature_gates_of_sequence(fuzzable_params: &Vec<FuzzableType>
     Some(features)
 }
 
+//afl crate的fuzz!本身就是persistent mode，每次fork之后循环执行AFL_FUZZER_LOOPCOUNT个输入，
+//调用它之前执行的代码就是deferred init。没有设置循环次数和deferred init时仍然只生成fuzz!
+pub(crate) fn _afl_main_body(
+    fuzz_closure: Expr,
+    persistent_iterations: Option<usize>,
+    deferred_init_len: Option<usize>,
+) -> Block {
+    let mut body = Block::new();
+    if persistent_iterations.is_none() && deferred_init_len.is_none() {
+        let fuzz = Expr::MacroCall { name: "fuzz".to_string(), args: vec![fuzz_closure] };
+        body.push(Stmt::Expr(fuzz));
+        return body;
+    }
+    body.push(Stmt::Let { pat: Pat::ident("target", false), init: fuzz_closure });
+    //在全零的输入上执行一次，初始化crate中lazy的部分，这次执行的panic不算crash
+    if let Some(len) = deferred_init_len {
+        let zeroed = Expr::Raw(format!("&[0u8; {}]", len));
+        let init = Expr::Closure {
+            params: Vec::new(),
+            body: Block { stmts: vec![Stmt::Expr(Expr::call(Expr::path("target"), vec![zeroed]))] },
+        };
+        body.push(Stmt::Let {
+            pat: Pat::Wild,
+            init: Expr::call(Expr::path("std::panic::catch_unwind"), vec![init]),
+        });
+    }
+    //afl crate在调用fuzz时读取循环次数
+    if let Some(iterations) = persistent_iterations {
+        body.push(Stmt::Expr(Expr::call(
+            Expr::path("std::env::set_var"),
+            vec![
+                Expr::Lit("\"AFL_FUZZER_LOOPCOUNT\"".to_string()),
+                Expr::Lit(format!("\"{}\"", iterations)),
+            ],
+        )));
+    }
+    let fuzz = Expr::call(Expr::path("afl::fuzz"), vec![Expr::path("true"), Expr::path("target")]);
+    body.push(Stmt::Expr(fuzz));
+    body
+}
+
 pub(crate) fn _data_to_u8() -> &'static str {
     "fn _to_u8(data:&[u8], index:usize)->u8 {
     data[index]
@@ -498,6 +791,17 @@ pub(crate) fn _data_to_f32() -> &'static str {
 }\n"
 }
 
+//f32::from_le_bytes需要1.40，按little endian拼出bits
+pub(crate) fn _data_to_f32_from_bits() -> &'static str {
+    "fn _to_f32(data:&[u8], index: usize) -> f32 {
+    let mut bits: u32 = 0;
+    for i in 0..4 {
+        bits |= (data[index+i] as u32) << (8*i);
+    }
+    f32::from_bits(bits)
+}\n"
+}
+
 pub(crate) fn _data_to_u64() -> &'static str {
     "fn _to_u64(data:&[u8], index:usize)->u64 {
     let data0 = _to_u32(data, index) as u64;
@@ -523,6 +827,16 @@ pub(crate) fn _data_to_f64() -> &'static str {
 }\n"
 }
 
+pub(crate) fn _data_to_f64_from_bits() -> &'static str {
+    "fn _to_f64(data:&[u8], index: usize) -> f64 {
+    let mut bits: u64 = 0;
+    for i in 0..8 {
+        bits |= (data[index+i] as u64) << (8*i);
+    }
+    f64::from_bits(bits)
+}\n"
+}
+
 pub(crate) fn _data_to_u128() -> &'static str {
     "fn _to_u128(data:&[u8], index:usize)->u128 {
     let data0 = _to_u64(data, index) as u128;
@@ -551,16 +865,21 @@ pub(crate) fn _data_to_isize() -> &'static str {
 }\n"
 }
 
+//u32映射到合法的unicode标量值，跳过代理区0xD800..0xE000，这样所有的输入都能得到char
 pub(crate) fn _data_to_char() -> &'static str {
     "fn _to_char(data:&[u8], index: usize)->char {
-    let char_value = _to_u32(data,index);
-    match char::from_u32(char_value) {
-        Some(c)=>c,
-        None=>{
-            use std::process;
-            process::exit(0);
-        }
-    }
+    let char_value = _to_u32(data,index) % 0x10F800;
+    let char_value = if char_value >= 0xD800 { char_value + 0x800 } else { char_value };
+    char::from_u32(char_value).unwrap()
+}\n"
+}
+
+//char::from_u32在1.52之前需要feature gate
+pub(crate) fn _data_to_std_char() -> &'static str {
+    "fn _to_char(data:&[u8], index: usize)->char {
+    let char_value = _to_u32(data,index) % 0x10F800;
+    let char_value = if char_value >= 0xD800 { char_value + 0x800 } else { char_value };
+    std::char::from_u32(char_value).unwrap()
 }\n"
 }
 
@@ -589,6 +908,25 @@ pub(crate) fn _data_to_str() -> &'static str {
 }\n"
 }
 
+//非u8的元素用align_to会丢掉对齐之前的字节，bool和char还可能得到非法的值，所以逐个元素解码
+pub(crate) fn _data_to_vec() -> &'static str {
+    "fn _to_vec<T, F: Fn(&[u8], usize) -> T>(
+    data:&[u8],
+    start_index: usize,
+    end_index: usize,
+    element_length: usize,
+    element: F,
+)->Vec<T> {
+    let mut res = Vec::new();
+    let mut index = start_index;
+    while index + element_length <= end_index {
+        res.push(element(data, index));
+        index += element_length;
+    }
+    res
+}\n"
+}
+
 //会有big endian和 little endian的问题，不过只是去fuzz的话，应该没啥影响
 pub(crate) fn _data_to_slice() -> &'static str {
     "fn _to_slice<T>(data:&[u8], start_index: usize, end_index: usize)->&[T] {
@@ -0,0 +1,219 @@
+//Choosing the apis to fuzz by their full path. `--include-api` and `--exclude-api` limit the apis
+//added to the graph, and `--entry-api` makes sure that every matching api is the last call of
+//at least one target. A pattern is a path glob, where `*` matches within a segment and `**`
+//across segments (e.g. `url::Url::set_*`), or a regex between slashes (e.g. `/^url::.*parse/`).
+//Parsers taking a single `&str` (`from_str`, `try_from`, `parse`, `new`, ..) are entry apis without
+//a pattern, and each of them always gets a target calling only the parser on the fuzzed string.
+use crate::fuzz_target::api_graph::{ApiGraph, ApiType};
+use crate::fuzz_target::api_sequence::ApiSequence;
+use regex::Regex;
+
+#[derive(Debug, Clone)]
+pub(crate) struct ApiPattern {
+    pub(crate) pattern: String,
+    regex: Regex,
+}
+
+impl ApiPattern {
+    pub(crate) fn parse(pattern: &str) -> Result<Self, String> {
+        let regex = match pattern.strip_prefix('/').and_then(|rest| rest.strip_suffix('/')) {
+            Some(regex) => regex.to_string(),
+            None => glob_to_regex(pattern),
+        };
+        match Regex::new(&regex) {
+            Ok(regex) => Ok(ApiPattern { pattern: pattern.to_string(), regex }),
+            Err(e) => Err(format!("{}: {}", pattern, e)),
+        }
+    }
+
+    pub(crate) fn matches(&self, full_name: &str) -> bool {
+        self.regex.is_match(full_name)
+    }
+}
+
+fn glob_to_regex(glob: &str) -> String {
+    let mut res = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                res.push_str(".*");
+            }
+            '*' => res.push_str("[^:]*"),
+            '?' => res.push_str("[^:]"),
+            c => res.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    res.push('$');
+    res
+}
+
+//only the apis matching `--include-api`, if given, and none of `--exclude-api`
+pub(crate) fn filter_api_functions_by_patterns(api_graph: &mut ApiGraph<'_>) {
+    let options = &api_graph.cx.options;
+    if options.include_apis.is_empty() && options.exclude_apis.is_empty() {
+        return;
+    }
+    let include_apis = options.include_apis.clone();
+    let exclude_apis = options.exclude_apis.clone();
+    let before = api_graph.api_functions.len();
+    api_graph.api_functions.retain(|api_function| {
+        let full_name = &api_function.full_name;
+        (include_apis.is_empty() || include_apis.iter().any(|pattern| pattern.matches(full_name)))
+            && !exclude_apis.iter().any(|pattern| pattern.matches(full_name))
+    });
+    println!("{} of {} apis are kept by the api filters", api_graph.api_functions.len(), before);
+}
+
+//the indexes of the apis matching `--entry-api`, and of the const fns with `--const-probes`
+pub(crate) fn entry_api_indexes(api_graph: &ApiGraph<'_>) -> Vec<usize> {
+    let entry_apis = &api_graph.cx.options.entry_apis;
+    let mut res: Vec<usize> = api_graph
+        .api_functions
+        .iter()
+        .enumerate()
+        .filter(|(_, api_function)| api_graph.const_apis.contains(&api_function.full_name))
+        .map(|(index, _)| index)
+        .collect();
+    for pattern in entry_apis {
+        let matched: Vec<usize> = api_graph
+            .api_functions
+            .iter()
+            .enumerate()
+            .filter(|(_, api_function)| pattern.matches(&api_function.full_name))
+            .map(|(index, _)| index)
+            .collect();
+        for index in matched {
+            if !res.contains(&index) {
+                res.push(index);
+            }
+        }
+    }
+    res
+}
+
+//every entry api ends some generated sequence: the shortest sequence it can be added to is
+//extended with it, when none of the sequences ends with it
+pub(crate) fn add_entry_sequences(api_graph: &mut ApiGraph<'_>) {
+    for pattern in &api_graph.cx.options.entry_apis {
+        if !api_graph
+            .api_functions
+            .iter()
+            .any(|api_function| pattern.matches(&api_function.full_name))
+        {
+            println!("warning: no api matches the entry api `{}`", pattern.pattern);
+        }
+    }
+    let entry_indexes = entry_api_indexes(api_graph);
+    if entry_indexes.is_empty() {
+        return;
+    }
+    let max_len = api_graph.cx.options.max_seq_len;
+    let mut new_sequences = Vec::new();
+    {
+        let extender = api_graph.extender();
+        let mut prefixes: Vec<&ApiSequence> = api_graph.api_sequences.iter().collect();
+        prefixes.sort_by_key(|sequence| sequence.len());
+        let empty_sequence = ApiSequence::new();
+        prefixes.insert(0, &empty_sequence);
+        for entry_index in entry_indexes {
+            if ends_with(&api_graph.api_sequences, entry_index) {
+                continue;
+            }
+            let new_sequence = prefixes
+                .iter()
+                .filter(|prefix| prefix.len() < max_len)
+                .filter_map(|prefix| {
+                    extender.is_fun_satisfied(&ApiType::BareFunction, entry_index, prefix)
+                })
+                .find(|sequence| !sequence._has_no_fuzzables());
+            match new_sequence {
+                Some(new_sequence) => new_sequences.push(new_sequence),
+                None => println!(
+                    "warning: no sequence ends with the entry api {}",
+                    api_graph.api_functions[entry_index].full_name
+                ),
+            }
+        }
+    }
+    for sequence in new_sequences {
+        for function_index in sequence._get_contained_api_functions() {
+            api_graph.api_functions_visited[function_index] = true;
+        }
+        api_graph.api_sequences.push(sequence);
+    }
+}
+
+//a sequence calling only the parser, for every parser api that the strategy didn't call alone
+pub(crate) fn add_parser_sequences(api_graph: &mut ApiGraph<'_>) {
+    let parser_indexes = api_graph.parser_api_indexes();
+    let mut new_sequences = Vec::new();
+    {
+        let extender = api_graph.extender();
+        let empty_sequence = ApiSequence::new();
+        for parser_index in parser_indexes.iter().copied() {
+            if api_graph.api_sequences.iter().any(|sequence| is_single_call(sequence, parser_index))
+            {
+                continue;
+            }
+            if let Some(new_sequence) =
+                extender.is_fun_satisfied(&ApiType::BareFunction, parser_index, &empty_sequence)
+            {
+                new_sequences.push(new_sequence);
+            }
+        }
+    }
+    println!(
+        "{} parser apis are found, {} single call sequences are added",
+        parser_indexes.len(),
+        new_sequences.len()
+    );
+    for sequence in new_sequences {
+        for function_index in sequence._get_contained_api_functions() {
+            api_graph.api_functions_visited[function_index] = true;
+        }
+        api_graph.api_sequences.push(sequence);
+    }
+}
+
+//the chosen targets get the shortest sequence ending with each entry api they miss, and the single
+//call of each parser api, even past `--max-targets`
+pub(crate) fn add_entry_targets(
+    api_graph: &ApiGraph<'_>,
+    mut chosen_sequences: Vec<ApiSequence>,
+) -> Vec<ApiSequence> {
+    for entry_index in entry_api_indexes(api_graph) {
+        if ends_with(&chosen_sequences, entry_index) {
+            continue;
+        }
+        let shortest = api_graph
+            .api_sequences
+            .iter()
+            .filter(|sequence| sequence._last_api_func_index() == Some(entry_index))
+            .filter(|sequence| !sequence._has_no_fuzzables())
+            .min_by_key(|sequence| sequence.len());
+        if let Some(sequence) = shortest {
+            chosen_sequences.push(sequence.clone());
+        }
+    }
+    for parser_index in api_graph.parser_api_indexes() {
+        if chosen_sequences.iter().any(|sequence| is_single_call(sequence, parser_index)) {
+            continue;
+        }
+        let single_call =
+            api_graph.api_sequences.iter().find(|sequence| is_single_call(sequence, parser_index));
+        if let Some(sequence) = single_call {
+            chosen_sequences.push(sequence.clone());
+        }
+    }
+    chosen_sequences
+}
+
+fn is_single_call(sequence: &ApiSequence, api_index: usize) -> bool {
+    sequence.len() == 1 && sequence._last_api_func_index() == Some(api_index)
+}
+
+fn ends_with(sequences: &Vec<ApiSequence>, api_index: usize) -> bool {
+    sequences.iter().any(|sequence| sequence._last_api_func_index() == Some(api_index))
+}
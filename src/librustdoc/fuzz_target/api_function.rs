@@ -6,8 +6,9 @@
 use crate::fuzz_target::fuzzable_type::{self, FuzzableType};
 use crate::fuzz_target::impl_util::FullNameMap;
 use rustc_hir::{self, Mutability};
+use rustc_span::symbol::{Symbol, kw};
 
-use crate::clean;
+use crate::clean::{self, PrimitiveType};
 
 #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd)]
 pub(crate) enum ApiUnsafety {
@@ -15,6 +16,12 @@ pub(crate) enum ApiUnsafety {
     Normal,
 }
 
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub(crate) enum ApiAsyncness {
+    Async,
+    NotAsync,
+}
+
 //#[derive(Clone, Debug)]
 #[derive(Clone)]
 pub(crate) struct ApiFunction {
@@ -24,6 +31,7 @@ pub(crate) struct ApiFunction {
     pub(crate) output: Option<clean::Type>,
     pub(crate) _trait_full_path: Option<String>, //Trait的全限定路径,因为使用trait::fun来调用函数的时候，需要将trait的全路径引入
     pub(crate) _unsafe_tag: ApiUnsafety,
+    pub(crate) _async_tag: ApiAsyncness, //async函数需要在async块中调用并.await
 }
 
 impl ApiUnsafety {
@@ -42,6 +50,22 @@ pub(crate) fn _is_unsafe(&self) -> bool {
     }
 }
 
+impl ApiAsyncness {
+    pub(crate) fn _get_asyncness_from_fnheader(fn_header: &rustc_hir::FnHeader) -> Self {
+        match fn_header.asyncness {
+            rustc_hir::IsAsync::Async => ApiAsyncness::Async,
+            rustc_hir::IsAsync::NotAsync => ApiAsyncness::NotAsync,
+        }
+    }
+
+    pub(crate) fn _is_async(&self) -> bool {
+        match self {
+            ApiAsyncness::Async => true,
+            ApiAsyncness::NotAsync => false,
+        }
+    }
+}
+
 impl ApiFunction {
     pub(crate) fn _is_end_function(&self, full_name_map: &FullNameMap, cache: &Cache) -> bool {
         if self.contains_mut_borrow() {
@@ -80,6 +104,58 @@ pub(crate) fn contains_mut_borrow(&self) -> bool {
         return false;
     }
 
+    //如果返回值中含有引用，返回值会借用哪些输入参数(参数的位置，是否是可变借用，是否借用参数本身)。
+    //返回值的生命周期来自提到同一个具名生命周期的参数；省略的生命周期按省略规则，
+    //来自唯一带生命周期的参数，有多个时来自&self。生命周期只出现在参数引用的类型里时，
+    //例如`&'b Foo<'a>`或者按值传入的`Foo<'a>`，返回值不借用参数本身，只继承参数借用的值。
+    //对不上时保守地认为借用了所有引用类型的参数
+    pub(crate) fn _borrowed_inputs(&self) -> Vec<(usize, bool, bool)> {
+        let mut res = Vec::new();
+        let output_lifetimes = match &self.output {
+            Some(output) if api_util::_contains_reference(output) => {
+                api_util::_type_lifetimes(output)
+            }
+            _ => return res,
+        };
+        let lifetime_inputs: Vec<usize> = (0..self.inputs.len())
+            .filter(|i| !api_util::_type_lifetimes(&self.inputs[*i]).is_empty())
+            .collect();
+        for (i, input_type) in self.inputs.iter().enumerate() {
+            let (outer_lifetime, inner_lifetimes, mutable) = match input_type {
+                clean::Type::BorrowedRef { lifetime, mutability, type_ } => (
+                    Some(lifetime.as_ref().and_then(api_util::_lifetime_name)),
+                    api_util::_type_lifetimes(type_),
+                    *mutability == Mutability::Mut,
+                ),
+                _ => (None, api_util::_type_lifetimes(input_type), false),
+            };
+            let from_input = |input_lifetime: &Option<Symbol>, is_outer: bool| {
+                output_lifetimes.iter().any(|output_lifetime| match output_lifetime {
+                    Some(name) if *name == kw::StaticLifetime => false,
+                    Some(name) => *input_lifetime == Some(*name),
+                    None if lifetime_inputs.len() == 1 => lifetime_inputs[0] == i,
+                    None => i == 0 && is_outer,
+                })
+            };
+            if outer_lifetime.map_or(false, |outer_lifetime| from_input(&outer_lifetime, true)) {
+                res.push((i, mutable, true));
+            } else if inner_lifetimes.iter().any(|inner_lifetime| from_input(inner_lifetime, false))
+            {
+                res.push((i, false, false));
+            }
+        }
+        let has_local_lifetime =
+            output_lifetimes.iter().any(|lifetime| *lifetime != Some(kw::StaticLifetime));
+        if res.is_empty() && has_local_lifetime {
+            for (i, input_type) in self.inputs.iter().enumerate() {
+                if let clean::Type::BorrowedRef { mutability, .. } = input_type {
+                    res.push((i, *mutability == Mutability::Mut, true));
+                }
+            }
+        }
+        res
+    }
+
     pub(crate) fn is_defined_on_prelude_type(&self, prelude_types: &FxHashSet<String>) -> bool {
         let function_name_contains_prelude_type =
             prelude_types.iter().any(|prelude_type| self.full_name.starts_with(prelude_type));
@@ -103,6 +179,84 @@ pub(crate) fn _is_start_function(&self, full_name_map: &FullNameMap, cache: &Cac
         flag
     }
 
+    //把参数位置的`impl Trait`换成满足约束的具体类型，优先选择可以fuzz的类型，例如`impl AsRef<str>`换成`&str`。
+    //迭代器和std::io的参数保留`impl Trait`，由fuzzable_type从输入构造。只作为参数或参数的引用出现的
+    //类型参数，例如`I: IntoIterator<Item = T>`和`&mut R`中的`R: Read`，和`impl Trait`一样处理。
+    //所有`impl Trait`参数都能处理时，去掉对应的泛型参数，函数没有别的泛型参数就不再是泛型函数
+    pub(crate) fn _resolve_impl_trait_inputs(&mut self) {
+        let mut removed_params: Vec<Symbol> = self
+            .generics
+            .params
+            .iter()
+            .filter(|param| param.is_synthetic_type_param())
+            .map(|param| param.name)
+            .collect();
+        let mut inputs = self.inputs.clone();
+        for param in &self.generics.params {
+            let mut bounds = match &param.kind {
+                clean::GenericParamDefKind::Type { bounds, synthetic: false, .. } => bounds.clone(),
+                _ => continue,
+            };
+            for predicate in &self.generics.where_predicates {
+                if let clean::WherePredicate::BoundPredicate {
+                    ty: clean::Type::Generic(name),
+                    bounds: predicate_bounds,
+                    ..
+                } = predicate
+                {
+                    if *name == param.name {
+                        bounds.extend(predicate_bounds.iter().cloned());
+                    }
+                }
+            }
+            let generic = clean::Type::Generic(param.name);
+            let used_elsewhere = self
+                .output
+                .iter()
+                .chain(self.inputs.iter().filter(|input| *_referent(input) != generic))
+                .any(|ty_| api_util::_mentions_generic(ty_, param.name));
+            if used_elsewhere || !_is_kept_impl_trait(&bounds) {
+                continue;
+            }
+            for input in inputs.iter_mut() {
+                match input {
+                    clean::Type::BorrowedRef { type_, .. } if **type_ == generic => {
+                        **type_ = clean::Type::ImplTrait(bounds.clone());
+                    }
+                    _ if *input == generic => *input = clean::Type::ImplTrait(bounds.clone()),
+                    _ => {}
+                }
+            }
+            removed_params.push(param.name);
+        }
+        if !inputs.iter().any(|input| _referent(input).is_impl_trait()) {
+            return;
+        }
+        let mut resolved_inputs = Vec::new();
+        for input in inputs {
+            match (&input, _referent(&input)) {
+                //`impl Read + Debug`不能替换成满足Debug的&str
+                (_, clean::Type::ImplTrait(bounds)) if _is_kept_impl_trait(bounds) => {
+                    resolved_inputs.push(input.clone())
+                }
+                (clean::Type::ImplTrait(bounds), _) => match _impl_trait_substitute(bounds) {
+                    Some(substitute) => resolved_inputs.push(substitute),
+                    None => return,
+                },
+                (_, clean::Type::ImplTrait(_)) => return,
+                _ => resolved_inputs.push(input.clone()),
+            }
+        }
+        self.generics.params.retain(|param| !removed_params.contains(&param.name));
+        self.generics.where_predicates.retain(|predicate| match predicate {
+            clean::WherePredicate::BoundPredicate { ty: clean::Type::Generic(name), .. } => {
+                !removed_params.contains(name)
+            }
+            _ => true,
+        });
+        self.inputs = resolved_inputs;
+    }
+
     //TODO:判断一个函数是否是泛型函数
     pub(crate) fn _is_generic_function(&self) -> bool {
         !self.generics.is_empty()
@@ -167,3 +321,75 @@ pub(crate) fn contains_unsupported_fuzzable_type(
         return false;
     }
 }
+
+//引用指向的类型，不是引用时就是类型自身
+fn _referent(ty_: &clean::Type) -> &clean::Type {
+    match ty_ {
+        clean::Type::BorrowedRef { type_, .. } => &**type_,
+        _ => ty_,
+    }
+}
+
+//不替换成具体类型，由fuzzable_type从输入构造的`impl Trait`参数
+fn _is_kept_impl_trait(bounds: &Vec<clean::GenericBound>) -> bool {
+    api_util::_iterator_item_type(bounds).is_some() || api_util::_io_adapter(bounds).is_some()
+}
+
+//满足`impl Trait`的一个具体类型，取第一个能处理的约束
+pub(crate) fn _impl_trait_substitute(bounds: &Vec<clean::GenericBound>) -> Option<clean::Type> {
+    bounds.iter().find_map(|bound| match bound {
+        clean::GenericBound::TraitBound(poly_trait, _) => {
+            _trait_bound_substitute(&poly_trait.trait_)
+        }
+        clean::GenericBound::Outlives(_) => None,
+    })
+}
+
+fn _trait_bound_substitute(trait_: &clean::Path) -> Option<clean::Type> {
+    let trait_args = trait_.generics().unwrap_or_default();
+    let trait_arg = trait_args.first().cloned();
+    match (trait_.last().as_str(), trait_arg) {
+        //str, Path和OsStr都实现了AsRef<Path>和AsRef<OsStr>
+        ("AsRef", Some(clean::Type::Primitive(PrimitiveType::Str))) => Some(_ref_str()),
+        ("AsRef", Some(clean::Type::Path { path }))
+            if ["Path", "OsStr"].contains(&path.last().as_str()) =>
+        {
+            Some(_ref_str())
+        }
+        ("AsRef" | "Borrow", Some(clean::Type::Slice(element))) => Some(_ref_slice(element)),
+        ("Borrow", Some(clean::Type::Primitive(PrimitiveType::Str))) => Some(_ref_str()),
+        ("Borrow", Some(clean::Type::Primitive(primitive))) => {
+            Some(clean::Type::Primitive(*primitive))
+        }
+        //String, PathBuf和OsString都实现了From<&str>，Vec<T>实现了From<&[T]>
+        ("Into", Some(clean::Type::Path { path }))
+            if ["String", "PathBuf", "OsString"].contains(&path.last().as_str()) =>
+        {
+            Some(_ref_str())
+        }
+        ("Into", Some(clean::Type::Path { path })) if path.last().as_str() == "Vec" => {
+            let element = path.generics().and_then(|args| args.first().cloned().cloned())?;
+            Some(_ref_slice(&Box::new(element)))
+        }
+        //Into<T>对T自身总是成立，T可以由别的api产生
+        ("Into", Some(ty_)) if !api_util::_is_generic_type(ty_) => Some(ty_.clone()),
+        ("ToString" | "Display" | "Debug", _) => Some(_ref_str()),
+        _ => None,
+    }
+}
+
+fn _ref_str() -> clean::Type {
+    clean::Type::BorrowedRef {
+        lifetime: None,
+        mutability: Mutability::Not,
+        type_: Box::new(clean::Type::Primitive(PrimitiveType::Str)),
+    }
+}
+
+fn _ref_slice(element: &Box<clean::Type>) -> clean::Type {
+    clean::Type::BorrowedRef {
+        lifetime: None,
+        mutability: Mutability::Not,
+        type_: Box::new(clean::Type::Slice(element.clone())),
+    }
+}
@@ -1,18 +1,36 @@
+use crate::clean::cfg::Cfg;
 use crate::formats::cache::Cache;
+use crate::fuzz_target::api_filter;
 use crate::fuzz_target::api_function::ApiFunction;
-use crate::fuzz_target::api_sequence::{ApiCall, ApiSequence, ParamType};
+use crate::fuzz_target::api_sequence::{ApiSequence, ParamType};
 use crate::fuzz_target::api_util;
+use crate::fuzz_target::assert_guard;
+use crate::fuzz_target::beam_search;
 use crate::fuzz_target::call_type::CallType;
+use crate::fuzz_target::features;
+use crate::fuzz_target::ffi;
+use crate::fuzz_target::fuzz_target_options::PanicPolicy;
 use crate::fuzz_target::fuzz_target_renderer::FuzzTargetContext;
-use crate::fuzz_target::fuzzable_type;
-use crate::fuzz_target::fuzzable_type::FuzzableType;
+use crate::fuzz_target::generic_function;
+use crate::fuzz_target::genetic_algorithm;
+use crate::fuzz_target::graph_traverse::{self, SequenceExtender, SequenceStrategy};
 use crate::fuzz_target::impl_util::FullNameMap;
 use crate::fuzz_target::mod_visibility::ModVisibity;
+use crate::fuzz_target::panic_doc::{GuardPiece, PanicPrecondition};
+use crate::fuzz_target::param_bounds::{self, ParamBound};
 use crate::fuzz_target::prelude_type;
+use crate::fuzz_target::risk_rank::{self, RiskSignals};
+use crate::fuzz_target::rust_version;
+use crate::fuzz_target::where_clause;
 use crate::TyCtxt;
+use crate::clean;
 use lazy_static::lazy_static;
 use rustc_data_structures::fx::{FxHashMap, FxHashSet};
+use rustc_hir::Mutability;
+use rustc_span::def_id::DefId;
+use rustc_span::symbol::{sym, Symbol};
 use std::rc::Rc;
+use std::time::Instant;
 use std::fmt;
 //use crate::clean::{PrimitiveType};
 use rand::{self, Rng};
@@ -21,6 +39,9 @@
 
 use super::generic_function::GenericFunction;
 
+//解析字符串的api的名字前缀
+static PARSER_NAMES: [&'static str; 5] = ["from_str", "try_from", "from", "parse", "new"];
+
 lazy_static! {
     static ref RANDOM_WALK_STEPS: FxHashMap<&'static str, usize> = {
         let mut m = FxHashMap::default();
@@ -52,6 +73,19 @@ pub(crate) struct ApiGraph<'tcx> {
     pub(crate) mod_visibility: ModVisibity, //the visibility of mods，to fix the problem of `pub(crate) use`
     pub(crate) generic_functions: Vec<GenericFunction>,
     pub(crate) functions_with_unsupported_fuzzable_types: FxHashSet<String>,
+    pub(crate) functions_newer_than_msrv: FxHashSet<String>, //需要比msrv更新的语法，比如async
+    pub(crate) api_features: FxHashMap<String, Vec<String>>, //full_name to the features it needs
+    pub(crate) api_panics: FxHashMap<String, PanicPrecondition>, //full_name to its `# Panics` doc
+    pub(crate) api_assertions: FxHashMap<String, Vec<Vec<GuardPiece>>>, //guards of its assertions
+    pub(crate) api_param_bounds: FxHashMap<String, Vec<ParamBound>>, //bounds stated in its doc
+    pub(crate) call_counts: FxHashMap<String, usize>, //name to the number of calls in the crate
+    pub(crate) ffi_apis: FxHashSet<String>,           //apis calling extern functions, with `--ffi`
+    pub(crate) const_apis: FxHashSet<String>,         //const fns, with `--const-probes`
+    pub(crate) api_risks: FxHashMap<String, RiskSignals>, //risky operations, with `--rank-targets`
+    pub(crate) deadline: Option<Instant>,             //end of the generation, with `--time-budget`
+    pub(crate) must_use_apis: FxHashSet<String>,      //apis whose result is `#[must_use]`
+    pub(crate) api_def_ids: FxHashMap<String, Option<DefId>>, //None if several items share the name
+    pub(crate) mined_sequences: Vec<ApiSequence>, //from `--usage-from`, extended by the strategy
     pub(crate) cx: Rc<FuzzTargetContext<'tcx>>, //pub(crate) _sequences_of_all_algorithm : FxHashMap<GraphTraverseAlgorithm, Vec<ApiSequence>>
 }
 
@@ -72,6 +106,22 @@ pub(crate) enum GraphTraverseAlgorithm {
     _RandomWalkEndPoint,
     _TryDeepBfs,
     _DirectBackwardSearch,
+    _GeneticAlgorithm,
+}
+
+impl GraphTraverseAlgorithm {
+    //registered as strategies in this order
+    pub(crate) const ALL: [GraphTraverseAlgorithm; 9] = [
+        GraphTraverseAlgorithm::_Bfs,
+        GraphTraverseAlgorithm::_FastBfs,
+        GraphTraverseAlgorithm::_BfsEndPoint,
+        GraphTraverseAlgorithm::_FastBfsEndPoint,
+        GraphTraverseAlgorithm::_RandomWalk,
+        GraphTraverseAlgorithm::_RandomWalkEndPoint,
+        GraphTraverseAlgorithm::_TryDeepBfs,
+        GraphTraverseAlgorithm::_DirectBackwardSearch,
+        GraphTraverseAlgorithm::_GeneticAlgorithm,
+    ];
 }
 
 #[derive(Debug, Clone, Hash, Eq, PartialEq, Copy)]
@@ -87,6 +137,9 @@ pub(crate) struct ApiDependency {
     pub(crate) input_fun: (ApiType, usize),  //the index of second func
     pub(crate) input_param_index: usize,
     pub(crate) call_type: CallType,
+    //how often the output function is called in the crate, producers with higher weights are
+    //preferred
+    pub(crate) weight: usize,
 }
 
 impl<'tcx> ApiGraph<'tcx> {
@@ -101,6 +154,19 @@ pub(crate) fn new(_crate_name: String, cx: Rc<FuzzTargetContext<'tcx>>) -> Self
             mod_visibility: ModVisibity::new(&_crate_name),
             generic_functions: Vec::new(),
             functions_with_unsupported_fuzzable_types: FxHashSet::default(),
+            functions_newer_than_msrv: FxHashSet::default(),
+            api_features: FxHashMap::default(),
+            api_panics: FxHashMap::default(),
+            api_assertions: FxHashMap::default(),
+            api_param_bounds: FxHashMap::default(),
+            call_counts: FxHashMap::default(),
+            ffi_apis: FxHashSet::default(),
+            const_apis: FxHashSet::default(),
+            api_risks: FxHashMap::default(),
+            deadline: None,
+            must_use_apis: FxHashSet::default(),
+            api_def_ids: FxHashMap::default(),
+            mined_sequences: Vec::new(),
             _crate_name,
             cx,
         }
@@ -114,24 +180,313 @@ pub(crate) fn tcx(&self) -> TyCtxt<'tcx> {
         self.cx.tcx
     }
 
-    pub(crate) fn add_api_function(&mut self, api_fun: ApiFunction) {
+    //只接受一个&str的解析函数，包括FromStr和TryFrom<&str>的实现，以及parse、new等函数。
+    //解析器是最值得fuzz的api，每个都有单独调用它的target
+    pub(crate) fn parser_api_indexes(&self) -> Vec<usize> {
+        let mut res = Vec::new();
+        for (index, api_function) in self.api_functions.iter().enumerate() {
+            let takes_str = match api_function.inputs.as_slice() {
+                [clean::Type::BorrowedRef { mutability: Mutability::Not, type_, .. }] => {
+                    **type_ == clean::Type::Primitive(clean::PrimitiveType::Str)
+                }
+                _ => false,
+            };
+            let name =
+                api_function.full_name.rsplit("::").next().unwrap_or(&api_function.full_name);
+            if takes_str
+                && api_function.output.is_some()
+                && !api_function._is_generic_function()
+                && PARSER_NAMES.iter().any(|prefix| name.starts_with(prefix))
+            {
+                res.push(index);
+            }
+        }
+        res
+    }
+
+    //类型是否实现了某个std trait，例如sym::Debug，只能看到cache中记录的impl
+    pub(crate) fn implements_trait(&self, ty: &clean::Type, trait_name: Symbol) -> bool {
+        let path = match ty {
+            clean::Type::Path { path } => path,
+            clean::Type::BorrowedRef { type_, .. } => {
+                return self.implements_trait(type_, trait_name);
+            }
+            _ => return false,
+        };
+        match self.tcx().get_diagnostic_item(trait_name) {
+            Some(trait_def_id) => self.implements_trait_def_id(path, trait_def_id),
+            None => false,
+        }
+    }
+
+    //类型是否有这个trait的impl，只能看到cache中记录的impl
+    pub(crate) fn implements_trait_def_id(&self, path: &clean::Path, trait_def_id: DefId) -> bool {
+        path.res
+            .opt_def_id()
+            .map_or(false, |def_id| self.def_id_implements_trait(def_id, trait_def_id))
+    }
+
+    //可以从fuzz数据构造的enum或struct是否实现了trait，fuzzable类型只记录了它们的全名
+    pub(crate) fn local_type_implements_trait(&self, full_name: &str, trait_name: Symbol) -> bool {
+        let local_types = &self.full_name_map.local_types;
+        let enum_def_id = local_types
+            .enums
+            .iter()
+            .find(|(_, enum_definition)| enum_definition.full_name == full_name)
+            .map(|(def_id, _)| *def_id);
+        let def_id = enum_def_id.or_else(|| {
+            local_types
+                .structs
+                .iter()
+                .find(|(_, struct_definition)| struct_definition.full_name == full_name)
+                .map(|(def_id, _)| *def_id)
+        });
+        match (def_id, self.tcx().get_diagnostic_item(trait_name)) {
+            (Some(def_id), Some(trait_def_id)) => {
+                self.def_id_implements_trait(def_id, trait_def_id)
+            }
+            _ => false,
+        }
+    }
+
+    fn def_id_implements_trait(&self, def_id: DefId, trait_def_id: DefId) -> bool {
+        let impls = match self.cache().impls.get(&def_id) {
+            Some(impls) => impls,
+            None => return false,
+        };
+        impls.iter().any(|impl_| match &impl_.inner_impl().trait_ {
+            Some(trait_) => trait_.res.opt_def_id() == Some(trait_def_id),
+            None => false,
+        })
+    }
+
+    //扩展序列时用到的部分，不包含类型上下文，可以在线程之间共享
+    pub(crate) fn extender(&self) -> SequenceExtender<'_> {
+        SequenceExtender {
+            api_functions: &self.api_functions,
+            api_dependencies: &self.api_dependencies,
+            full_name_map: &self.full_name_map,
+            cache: self.cache(),
+            deadline: self.deadline,
+            must_use_apis: &self.must_use_apis,
+            mined_sequences: &self.mined_sequences,
+        }
+    }
+
+    //超过--time-budget之后，各个算法不再扩展序列，保留已经找到的序列
+    pub(crate) fn is_out_of_time(&self) -> bool {
+        self.extender().is_out_of_time()
+    }
+
+    //number of calls to the api in the source of the crate, by the last segment of its name
+    pub(crate) fn api_weight(&self, index: usize) -> usize {
+        let full_name = &self.api_functions[index].full_name;
+        let name = full_name.rsplit("::").next().unwrap_or(full_name);
+        self.call_counts.get(name).cloned().unwrap_or(0)
+    }
+
+    //生成的代码中使用的crate名字
+    pub(crate) fn lib_name(&self) -> &String {
+        &self.cx.lib_name
+    }
+
+    pub(crate) fn add_api_function(&mut self, mut api_fun: ApiFunction) {
+        generic_function::resolve_into_bounds(&mut api_fun, self);
+        api_fun._resolve_impl_trait_inputs();
         if api_fun._is_generic_function() {
             let generic_function = GenericFunction::from(api_fun);
             self.generic_functions.push(generic_function);
         } else if api_fun.contains_unsupported_fuzzable_type(&self.full_name_map, self.cache()) {
             self.functions_with_unsupported_fuzzable_types.insert(api_fun.full_name.clone());
+        } else if api_fun._async_tag._is_async()
+            && !rust_version::supports(self.cx.options.msrv, rust_version::ASYNC_AWAIT)
+        {
+            self.functions_newer_than_msrv.insert(api_fun.full_name.clone());
         } else {
             self.api_functions.push(api_fun);
         }
     }
 
+    //generic apis whose type parameters all have a hint in `[generic-hints]` are added as apis
+    //with the hinted types
+    pub(crate) fn monomorphize_generic_functions(&mut self) {
+        if self.cx.options.generic_hints.is_empty() {
+            return;
+        }
+        let hint_types = generic_function::resolve_hints(self);
+        let tcx = self.tcx();
+        let mut monomorphized_functions = Vec::new();
+        let mut unsatisfied_number = 0;
+        for generic_function in self.generic_functions.iter_mut() {
+            if let Some(substitutes) = generic_function.find_substitutes(tcx, &hint_types) {
+                let def_id = self.api_def_ids.get(&generic_function.api_function.full_name);
+                if let Some(Some(def_id)) = def_id {
+                    if !where_clause::satisfies_where_clauses(tcx, *def_id, &substitutes) {
+                        unsatisfied_number += 1;
+                        continue;
+                    }
+                }
+                if let Some(api_function) =
+                    generic_function.monomorphized(&substitutes, &hint_types)
+                {
+                    generic_function.generic_substitute = substitutes;
+                    monomorphized_functions.push(api_function);
+                }
+            }
+        }
+        println!(
+            "{} of {} generic apis are monomorphized by the hints",
+            monomorphized_functions.len(),
+            self.generic_functions.len()
+        );
+        if unsatisfied_number > 0 {
+            println!(
+                "{} generic apis are skipped, their hints break a where clause",
+                unsatisfied_number
+            );
+        }
+        for api_function in monomorphized_functions {
+            self.add_api_function(api_function);
+        }
+    }
+
+    //`#[doc(hidden)]` and `#[deprecated]` items are not meant to be called by users, they are only
+    //added with `--include-hidden` and `--include-deprecated`
+    pub(crate) fn is_excluded_item(&self, item: &clean::Item) -> bool {
+        let options = &self.cx.options;
+        (!options.include_hidden && item.attrs.has_doc_flag(sym::hidden))
+            || (!options.include_deprecated && item.deprecation(self.tcx()).is_some())
+    }
+
+    //features the api is gated behind by `#[cfg(feature = "..")]`
+    pub(crate) fn add_api_features(&mut self, full_name: &String, cfg: Option<&Cfg>) {
+        let required_features = features::required_features(cfg, &self.cx.options.features);
+        if !required_features.is_empty() {
+            self.api_features.insert(full_name.clone(), required_features);
+        }
+    }
+
+    //the precondition of the `# Panics` section in the doc of the api
+    pub(crate) fn add_api_panics(
+        &mut self,
+        full_name: &String,
+        doc: Option<String>,
+        inputs: &clean::Arguments,
+    ) {
+        if self.cx.options.panic_policy == PanicPolicy::Ignore {
+            return;
+        }
+        let param_names = inputs.values.iter().map(|input| input.name.to_string()).collect();
+        let float_params =
+            inputs.values.iter().map(|input| api_util::_is_float_type(&input.type_)).collect();
+        let precondition =
+            doc.and_then(|doc| PanicPrecondition::parse(&doc, &param_names, &float_params));
+        if let Some(precondition) = precondition {
+            self.api_panics.insert(full_name.clone(), precondition);
+        }
+    }
+
+    //the conditions asserted at the top of the body of the api
+    pub(crate) fn add_api_assertions(
+        &mut self,
+        full_name: &String,
+        def_id: Option<DefId>,
+        inputs: &clean::Arguments,
+    ) {
+        if !self.cx.options.assert_guards {
+            return;
+        }
+        let def_id = match def_id {
+            Some(def_id) => def_id,
+            None => return,
+        };
+        let param_names = inputs.values.iter().map(|input| input.name.to_string()).collect();
+        let guards = assert_guard::leading_assertions(self.tcx(), def_id, &param_names);
+        if !guards.is_empty() {
+            self.api_assertions.insert(full_name.clone(), guards);
+        }
+    }
+
+    //the bounds of the integer parameters stated in the doc of the api
+    pub(crate) fn add_api_param_bounds(
+        &mut self,
+        full_name: &String,
+        doc: Option<String>,
+        inputs: &clean::Arguments,
+    ) {
+        let param_names = inputs.values.iter().map(|input| input.name.to_string()).collect();
+        let bounds = doc.map(|doc| param_bounds::parse(&doc, &param_names)).unwrap_or_default();
+        if !bounds.is_empty() {
+            self.api_param_bounds.insert(full_name.clone(), bounds);
+        }
+    }
+
+    //extern functions and the apis calling them are built with a sanitizer
+    pub(crate) fn add_ffi_api(&mut self, full_name: &String, def_id: Option<DefId>) {
+        if !self.cx.options.ffi {
+            return;
+        }
+        if def_id.map_or(false, |def_id| ffi::is_ffi_function(self.tcx(), def_id)) {
+            self.ffi_apis.insert(full_name.clone());
+        }
+    }
+
+    //const fns are probed in a const context besides their targets
+    pub(crate) fn add_const_api(&mut self, full_name: &String, def_id: Option<DefId>) {
+        if !self.cx.options.const_probes {
+            return;
+        }
+        if def_id.map_or(false, |def_id| self.tcx().is_const_fn_raw(def_id)) {
+            self.const_apis.insert(full_name.clone());
+        }
+    }
+
+    //the risky operations reached from the body of the api, the targets are ranked by them
+    pub(crate) fn add_api_risks(&mut self, full_name: &String, def_id: Option<DefId>) {
+        if !self.cx.options.rank_targets {
+            return;
+        }
+        if let Some(def_id) = def_id {
+            let signals = risk_rank::api_signals(self.tcx(), def_id);
+            self.api_risks.insert(full_name.clone(), signals);
+        }
+    }
+
+    //`#[must_use]` on the api or on the type it returns, e.g. Result or an iterator
+    pub(crate) fn add_must_use_api(&mut self, full_name: &String, def_id: Option<DefId>) {
+        let tcx = self.tcx();
+        let is_must_use = def_id.map_or(false, |def_id| {
+            let output = tcx.fn_sig(def_id).skip_binder().output();
+            tcx.has_attr(def_id, sym::must_use)
+                || output.ty_adt_def().map_or(false, |adt| tcx.has_attr(adt.did(), sym::must_use))
+        });
+        if is_must_use {
+            self.must_use_apis.insert(full_name.clone());
+        }
+    }
+
+    //the item of the api, for the checks of the compiler. The methods of a trait implemented by
+    //several types are named after the trait, they have no item
+    pub(crate) fn add_api_def_id(&mut self, full_name: &String, def_id: Option<DefId>) {
+        self.api_def_ids
+            .entry(full_name.clone())
+            .and_modify(|known| {
+                if *known != def_id {
+                    *known = None;
+                }
+            })
+            .or_insert(def_id);
+    }
+
     pub(crate) fn add_mod_visibility(&mut self, mod_name: &String, visibility: &Visibility) {
         self.mod_visibility.add_one_mod(mod_name, visibility);
     }
 
     pub(crate) fn filter_functions(&mut self) {
+        self.mod_visibility.resolve_visible_mods();
         self.filter_functions_defined_on_prelude_type();
         self.filter_api_functions_by_mod_visibility();
+        api_filter::filter_api_functions_by_patterns(self);
     }
 
     /// functions of prelude type. These functions are not in current crate
@@ -158,14 +513,18 @@ pub(crate) fn filter_api_functions_by_mod_visibility(&mut self) {
         let mut new_api_functions = Vec::new();
         for api_func in &self.api_functions {
             let api_func_name = &api_func.full_name;
-            let trait_name = &api_func._trait_full_path;
+            //a trait of a private mod can be used through a public re-export
+            let trait_name = api_func
+                ._trait_full_path
+                .as_ref()
+                .map(|trait_name| self.mod_visibility.canonical_path(trait_name));
             let mut invisible_flag = false;
             for invisible_mod in &invisible_mods {
                 if api_func_name.as_str().starts_with(invisible_mod.as_str()) {
                     invisible_flag = true;
                     break;
                 }
-                if let Some(trait_name_) = trait_name {
+                if let Some(trait_name_) = &trait_name {
                     if trait_name_.as_str().starts_with(invisible_mod) {
                         invisible_flag = true;
                         break;
@@ -179,6 +538,11 @@ pub(crate) fn filter_api_functions_by_mod_visibility(&mut self) {
         self.api_functions = new_api_functions;
     }
 
+    //the path the targets `use` an item with
+    pub(crate) fn canonical_path(&self, path: &String) -> String {
+        self.mod_visibility.canonical_path(path)
+    }
+
     pub(crate) fn set_full_name_map(&mut self, full_name_map: &FullNameMap) {
         self.full_name_map = full_name_map.clone();
     }
@@ -225,6 +589,7 @@ pub(crate) fn find_all_dependencies(&mut self) {
                                     input_fun: (ApiType::BareFunction, j),
                                     input_param_index: k,
                                     call_type: call_type.clone(),
+                                    weight: self.api_weight(i),
                                 };
                                 self.api_dependencies.push(one_dependency);
                             }
@@ -235,6 +600,70 @@ pub(crate) fn find_all_dependencies(&mut self) {
         }
     }
 
+    //restore the dependencies found before, given as (output function, input function, param).
+    //Only the call types of these edges are computed again
+    pub(crate) fn restore_dependencies(&mut self, edges: &Vec<(usize, usize, usize)>) {
+        self.api_dependencies.clear();
+        for (i, j, k) in edges {
+            let output_type = match &self.api_functions[*i].output {
+                Some(ty_) => ty_,
+                None => continue,
+            };
+            let input_param = match self.api_functions[*j].inputs.get(*k) {
+                Some(input_param) => input_param,
+                None => continue,
+            };
+            let call_type = api_util::_same_type(
+                output_type,
+                input_param,
+                true,
+                &self.full_name_map,
+                self.cache(),
+            );
+            if let CallType::_NotCompatible = call_type {
+                continue;
+            }
+            let one_dependency = ApiDependency {
+                output_fun: (ApiType::BareFunction, *i),
+                input_fun: (ApiType::BareFunction, *j),
+                input_param_index: *k,
+                call_type,
+                weight: self.api_weight(*i),
+            };
+            self.api_dependencies.push(one_dependency);
+        }
+    }
+
+    //the sequences of the strategy replace the current ones, and the apis they call are covered.
+    //The strategies start from the mined sequences too, the ones that don't keep them as they are
+    pub(crate) fn generate_sequences(&mut self, strategy: &dyn SequenceStrategy) {
+        let time_budget = self.cx.options.time_budget;
+        self.deadline = time_budget.map(|time_budget| Instant::now() + time_budget);
+        let mut sequences = strategy.generate(self);
+        let found: FxHashSet<Vec<usize>> =
+            sequences.iter().map(|sequence| sequence.function_indexes()).collect();
+        for mined_sequence in &self.mined_sequences {
+            if !found.contains(&mined_sequence.function_indexes()) {
+                sequences.push(mined_sequence.clone());
+            }
+        }
+        if let Some(time_budget) = time_budget.filter(|_| self.is_out_of_time()) {
+            println!(
+                "note: the time budget of {}s is spent, the {} sequences found so far are kept",
+                time_budget.as_secs(),
+                sequences.len()
+            );
+        }
+        self.deadline = None;
+        self.reset_visited();
+        for sequence in &sequences {
+            for api_func_index in sequence._get_contained_api_functions() {
+                self.api_functions_visited[api_func_index] = true;
+            }
+        }
+        self.api_sequences = sequences;
+    }
+
     pub(crate) fn default_generate_sequences(&mut self) {
         //BFS + backward search
         self.generate_all_possoble_sequences(GraphTraverseAlgorithm::_BfsEndPoint);
@@ -247,7 +676,7 @@ pub(crate) fn default_generate_sequences(&mut self) {
     pub(crate) fn generate_all_possoble_sequences(&mut self, algorithm: GraphTraverseAlgorithm) {
         //BFS序列的最大长度：即为函数的数量,或者自定义
         //let bfs_max_len = self.api_functions.len();
-        let bfs_max_len = 3;
+        let bfs_max_len = self.cx.options.max_seq_len;
         //random walk的最大步数
 
         let random_walk_max_size = if RANDOM_WALK_STEPS.contains_key(self._crate_name.as_str()) {
@@ -296,6 +725,10 @@ pub(crate) fn generate_all_possoble_sequences(&mut self, algorithm: GraphTravers
                 self.reset_visited();
                 self._try_to_cover_unvisited_nodes();
             }
+            GraphTraverseAlgorithm::_GeneticAlgorithm => {
+                println!("using genetic algorithm");
+                genetic_algorithm::genetic_algorithm(self);
+            }
         }
     }
 
@@ -350,14 +783,44 @@ pub(crate) fn bfs(&mut self, max_len: usize, stop_at_end_function: bool, fast_mo
             return;
         }
 
-        let api_function_num = self.api_functions.len();
+        //非fast模式下，从每个入口函数开始的序列互不影响，可以并行生成
+        if !fast_mode {
+            let jobs = self.cx.options.jobs;
+            //有--beam-width时，每一层只扩展最好的beam_width个序列
+            let sequences = match self.cx.options.beam_width {
+                Some(beam_width) => {
+                    beam_search::beam_bfs(self, max_len, stop_at_end_function, beam_width)
+                }
+                None => graph_traverse::parallel_bfs(
+                    &self.extender(),
+                    max_len,
+                    stop_at_end_function,
+                    jobs,
+                ),
+            };
+            for sequence in &sequences {
+                for api_func_index in sequence._get_contained_api_functions() {
+                    self.api_functions_visited[api_func_index] = true;
+                }
+            }
+            self.api_sequences = sequences;
+            if !stop_at_end_function {
+                std::process::exit(0);
+            }
+            return;
+        }
 
         //无需加入长度为1的，从空序列开始即可，加入一个长度为0的序列作为初始
         let api_sequence = ApiSequence::new();
         self.api_sequences.push(api_sequence);
+        //挖掘出的序列在它们的长度上继续扩展
+        self.api_sequences.extend(self.mined_sequences.iter().cloned());
 
         //接下来开始从长度1一直到max_len遍历
         for len in 0..max_len {
+            if self.is_out_of_time() {
+                break;
+            }
             let mut tmp_sequences = Vec::new();
             for sequence in &self.api_sequences {
                 if stop_at_end_function && self.is_sequence_ended(sequence) {
@@ -369,9 +832,12 @@ pub(crate) fn bfs(&mut self, max_len: usize, stop_at_end_function: bool, fast_mo
                 }
             }
             for sequence in &tmp_sequences {
+                if self.is_out_of_time() {
+                    break;
+                }
                 //长度为len的序列，去匹配每一个函数，如果可以加入的话，就生成一个新的序列
                 let api_type = ApiType::BareFunction;
-                for api_func_index in 0..api_function_num {
+                for api_func_index in self.extender().successors(sequence) {
                     //bfs fast, 访问过的函数不再访问
                     if fast_mode && self.api_functions_visited[api_func_index] {
                         continue;
@@ -409,11 +875,11 @@ pub(crate) fn _try_deep_bfs(&mut self, max_sequence_number: usize) {
             return;
         }
 
-        let api_function_num = self.api_functions.len();
-
         //无需加入长度为1的，从空序列开始即可，加入一个长度为0的序列作为初始
         let api_sequence = ApiSequence::new();
         self.api_sequences.push(api_sequence);
+        //挖掘出的序列在它们的长度上继续扩展
+        self.api_sequences.extend(self.mined_sequences.iter().cloned());
 
         let mut already_covered_nodes = FxHashSet::default();
         let mut already_covered_edges = FxHashSet::default();
@@ -425,6 +891,9 @@ pub(crate) fn _try_deep_bfs(&mut self, max_sequence_number: usize) {
             if len > 2 && current_sequence_number * covered_nodes >= max_sequence_number {
                 break;
             }
+            if self.is_out_of_time() {
+                break;
+            }
 
             let mut tmp_sequences = Vec::new();
             for sequence in &self.api_sequences {
@@ -437,9 +906,12 @@ pub(crate) fn _try_deep_bfs(&mut self, max_sequence_number: usize) {
                 }
             }
             for sequence in &tmp_sequences {
+                if self.is_out_of_time() {
+                    break;
+                }
                 //长度为len的序列，去匹配每一个函数，如果可以加入的话，就生成一个新的序列
                 let api_type = ApiType::BareFunction;
-                for api_func_index in 0..api_function_num {
+                for api_func_index in self.extender().successors(sequence) {
                     if let Some(new_sequence) =
                         self.is_fun_satisfied(&api_type, api_func_index, sequence)
                     {
@@ -488,11 +960,15 @@ pub(crate) fn random_walk(
         //加入一个长度为0的序列
         let api_sequence = ApiSequence::new();
         self.api_sequences.push(api_sequence);
+        self.api_sequences.extend(self.mined_sequences.iter().cloned());
 
         //start random work
         let function_len = self.api_functions.len();
         let mut rng = rand::thread_rng();
         for i in 0..max_size {
+            if self.is_out_of_time() {
+                break;
+            }
             let current_sequence_len = self.api_sequences.len();
             let chosen_sequence_index = rng.gen_range(0, current_sequence_len);
             let chosen_sequence = &self.api_sequences[chosen_sequence_index];
@@ -503,7 +979,13 @@ pub(crate) fn random_walk(
             if max_depth > 0 && chosen_sequence.len() >= max_depth {
                 continue;
             }
-            let chosen_fun_index = rng.gen_range(0, function_len);
+            //序列中有没被使用的#[must_use]返回值时，一半的概率选择使用它的函数
+            let consumers = self.extender().must_use_consumers(chosen_sequence);
+            let chosen_fun_index = if !consumers.is_empty() && rng.gen_bool(0.5) {
+                consumers[rng.gen_range(0, consumers.len())]
+            } else {
+                rng.gen_range(0, function_len)
+            };
             //let chosen_fun = &self.api_functions[chosen_fun_index];
             let fun_type = ApiType::BareFunction;
             if let Some(new_sequence) =
@@ -565,11 +1047,17 @@ pub(crate) fn _try_to_cover_unvisited_nodes(&mut self) {
         let mut covered_node_this_iteration = FxHashSet::default();
         //最多循环没访问到的节点的数量
         for _ in 0..unvisited_nodes.len() {
+            if self.is_out_of_time() {
+                break;
+            }
             covered_node_this_iteration.clear();
             let candidate_sequences = self._choose_candidate_sequence_for_merge();
             //println!("sequence number, {}", self.api_sequences.len());
             //println!("candidate sequence number, {}", candidate_sequences.len());
             for unvisited_node in &unvisited_nodes {
+                if self.is_out_of_time() {
+                    break;
+                }
                 let unvisited_api_func = &self.api_functions[*unvisited_node];
                 let inputs = &unvisited_api_func.inputs;
                 let mut dependent_sequence_indexes = Vec::new();
@@ -586,15 +1074,22 @@ pub(crate) fn _try_to_cover_unvisited_nodes(&mut self) {
                         let output_type = ApiType::BareFunction;
                         let input_type = ApiType::BareFunction;
                         let candidate_sequence = &self.api_sequences[*candidate_sequence_index];
-                        let output_index = candidate_sequence._last_api_func_index().unwrap();
-
-                        if let Some(_) = self.check_dependency(
-                            &output_type,
-                            output_index,
-                            &input_type,
-                            *unvisited_node,
-                            i,
-                        ) {
+                        //不只是最后一个返回值，序列中没有被move掉的返回值都可以继续使用
+                        let has_dependency =
+                            candidate_sequence._usable_api_func_indexes().iter().any(
+                                |output_index| {
+                                    self.check_dependency(
+                                        &output_type,
+                                        *output_index,
+                                        &input_type,
+                                        *unvisited_node,
+                                        i,
+                                    )
+                                    .is_some()
+                                },
+                            );
+
+                        if has_dependency {
                             can_find_dependency_flag = true;
                             //dependent_sequence_indexes.push(*candidate_sequence_index);
                             tmp_dependent_index = *candidate_sequence_index as i32;
@@ -822,248 +1317,6 @@ pub(crate) fn _first_choose(&self, max_size: usize) -> Vec<ApiSequence> {
         res
     }
 
-    pub(crate) fn _heuristic_choose(
-        &self,
-        max_size: usize,
-        stop_at_visit_all_nodes: bool,
-    ) -> Vec<ApiSequence> {
-        let mut res = Vec::new();
-        let mut to_cover_nodes = Vec::new();
-
-        let mut fixed_covered_nodes = FxHashSet::default();
-        for fixed_sequence in &self.api_sequences {
-            //let covered_nodes = fixed_sequence._get_contained_api_functions();
-            //for covered_node in &covered_nodes {
-            //    fixed_covered_nodes.insert(*covered_node);
-            //}
-
-            if !fixed_sequence._has_no_fuzzables()
-                && !fixed_sequence._contains_dead_code_except_last_one(self)
-            {
-                let covered_nodes = fixed_sequence._get_contained_api_functions();
-                for covered_node in &covered_nodes {
-                    fixed_covered_nodes.insert(*covered_node);
-                }
-            }
-        }
-
-        for fixed_covered_node in fixed_covered_nodes {
-            to_cover_nodes.push(fixed_covered_node);
-        }
-
-        let to_cover_nodes_number = to_cover_nodes.len();
-        //println!("There are total {} nodes need to be covered.", to_cover_nodes_number);
-        let to_cover_dependency_number = self.api_dependencies.len();
-        //println!("There are total {} edges need to be covered.", to_cover_dependency_number);
-        let total_sequence_number = self.api_sequences.len();
-
-        //println!("There are toatl {} sequences.", total_sequence_number);
-        let mut valid_fuzz_sequence_count = 0;
-        for sequence in &self.api_sequences {
-            if !sequence._has_no_fuzzables() && !sequence._contains_dead_code_except_last_one(self)
-            {
-                valid_fuzz_sequence_count = valid_fuzz_sequence_count + 1;
-            }
-        }
-        //println!("There are toatl {} valid sequences for fuzz.", valid_fuzz_sequence_count);
-        if valid_fuzz_sequence_count <= 0 {
-            return res;
-        }
-
-        let mut already_covered_nodes = FxHashSet::default();
-        let mut already_covered_edges = FxHashSet::default();
-        let mut already_chosen_sequences = FxHashSet::default();
-        let mut sorted_chosen_sequences = Vec::new();
-        let mut dynamic_fuzzable_length_sequences_count = 0;
-        let mut fixed_fuzzale_length_sequences_count = 0;
-
-        let mut try_to_find_dynamic_length_flag = true;
-        for _ in 0..max_size + 1 {
-            let mut current_chosen_sequence_index = 0;
-            let mut current_max_covered_nodes = 0;
-            let mut current_max_covered_edges = 0;
-            let mut current_chosen_sequence_len = 0;
-
-            for j in 0..total_sequence_number {
-                if already_chosen_sequences.contains(&j) {
-                    continue;
-                }
-                let api_sequence = &self.api_sequences[j];
-
-                if api_sequence._has_no_fuzzables()
-                    || api_sequence._contains_dead_code_except_last_one(self)
-                {
-                    continue;
-                }
-
-                if try_to_find_dynamic_length_flag && api_sequence._is_fuzzables_fixed_length() {
-                    //优先寻找fuzzable部分具有动态长度的情况
-                    continue;
-                }
-
-                if !try_to_find_dynamic_length_flag && !api_sequence._is_fuzzables_fixed_length() {
-                    //再寻找fuzzable部分具有静态长度的情况
-                    continue;
-                }
-
-                let covered_nodes = api_sequence._get_contained_api_functions();
-                let mut uncovered_nodes_by_former_sequence_count = 0;
-                for covered_node in &covered_nodes {
-                    if !already_covered_nodes.contains(covered_node) {
-                        uncovered_nodes_by_former_sequence_count =
-                            uncovered_nodes_by_former_sequence_count + 1;
-                    }
-                }
-
-                if uncovered_nodes_by_former_sequence_count < current_max_covered_nodes {
-                    continue;
-                }
-                let covered_edges = &api_sequence._covered_dependencies;
-                let mut uncovered_edges_by_former_sequence_count = 0;
-                for covered_edge in covered_edges {
-                    if !already_covered_edges.contains(covered_edge) {
-                        uncovered_edges_by_former_sequence_count =
-                            uncovered_edges_by_former_sequence_count + 1;
-                    }
-                }
-                if uncovered_nodes_by_former_sequence_count == current_max_covered_nodes
-                    && uncovered_edges_by_former_sequence_count < current_max_covered_edges
-                {
-                    continue;
-                }
-                let sequence_len = api_sequence.len();
-                if (uncovered_nodes_by_former_sequence_count > current_max_covered_nodes)
-                    || (uncovered_nodes_by_former_sequence_count == current_max_covered_nodes
-                        && uncovered_edges_by_former_sequence_count > current_max_covered_edges)
-                    || (uncovered_nodes_by_former_sequence_count == current_max_covered_nodes
-                        && uncovered_edges_by_former_sequence_count == current_max_covered_edges
-                        && sequence_len < current_chosen_sequence_len)
-                {
-                    current_chosen_sequence_index = j;
-                    current_max_covered_nodes = uncovered_nodes_by_former_sequence_count;
-                    current_max_covered_edges = uncovered_edges_by_former_sequence_count;
-                    current_chosen_sequence_len = sequence_len;
-                }
-            }
-
-            if try_to_find_dynamic_length_flag && current_max_covered_nodes <= 0 {
-                //println!("sequences with dynamic length can not cover more nodes");
-                try_to_find_dynamic_length_flag = false;
-                continue;
-            }
-
-            if !try_to_find_dynamic_length_flag
-                && current_max_covered_edges <= 0
-                && current_max_covered_nodes <= 0
-            {
-                //println!("can't cover more edges or nodes");
-                break;
-            }
-            already_chosen_sequences.insert(current_chosen_sequence_index);
-            sorted_chosen_sequences.push(current_chosen_sequence_index);
-
-            if try_to_find_dynamic_length_flag {
-                dynamic_fuzzable_length_sequences_count =
-                    dynamic_fuzzable_length_sequences_count + 1;
-            } else {
-                fixed_fuzzale_length_sequences_count = fixed_fuzzale_length_sequences_count + 1;
-            }
-
-            let chosen_sequence = &self.api_sequences[current_chosen_sequence_index];
-
-            let covered_nodes = chosen_sequence._get_contained_api_functions();
-            for cover_node in covered_nodes {
-                already_covered_nodes.insert(cover_node);
-            }
-            let covered_edges = &chosen_sequence._covered_dependencies;
-            //println!("covered_edges = {:?}", covered_edges);
-            for cover_edge in covered_edges {
-                already_covered_edges.insert(*cover_edge);
-            }
-
-            if already_chosen_sequences.len() == valid_fuzz_sequence_count {
-                //println!("all sequence visited");
-                break;
-            }
-            if to_cover_dependency_number != 0
-                && already_covered_edges.len() == to_cover_dependency_number
-            {
-                //println!("all edges visited");
-                //should we stop at visit all edges?
-                break;
-            }
-            if stop_at_visit_all_nodes && already_covered_nodes.len() == to_cover_nodes_number {
-                //println!("all nodes visited");
-                break;
-            }
-            //println!("no fuzzable count = {}", no_fuzzable_count);
-        }
-
-        let total_functions_number = self.api_functions.len();
-        println!("-----------STATISTICS-----------");
-        println!("total nodes: {}", total_functions_number);
-
-        let mut valid_api_number = 0;
-        for api_function_ in &self.api_functions {
-            if !api_function_.contains_unsupported_fuzzable_type(&self.full_name_map, self.cache())
-            {
-                valid_api_number = valid_api_number + 1;
-            } //else {
-              //    println!("{}", api_function_._pretty_print(&self.full_name_map));
-              //}
-        }
-        //println!("total valid nodes: {}", valid_api_number);
-
-        let total_dependencies_number = self.api_dependencies.len();
-        println!("total edges: {}", total_dependencies_number);
-
-        let covered_node_num = already_covered_nodes.len();
-        let covered_edges_num = already_covered_edges.len();
-        println!("covered nodes: {}", covered_node_num);
-        println!("covered edges: {}", covered_edges_num);
-
-        let node_coverage = (already_covered_nodes.len() as f64) / (valid_api_number as f64);
-        let edge_coverage =
-            (already_covered_edges.len() as f64) / (total_dependencies_number as f64);
-        println!("node coverage: {}", node_coverage);
-        println!("edge coverage: {}", edge_coverage);
-        //println!("sequence with dynamic fuzzable length: {}", dynamic_fuzzable_length_sequences_count);
-        //println!("sequence with fixed fuzzable length: {}",fixed_fuzzale_length_sequences_count);
-
-        let mut sequnce_covered_by_reverse_search = 0;
-        let mut max_length = 0;
-        for sequence_index in sorted_chosen_sequences {
-            let api_sequence = self.api_sequences[sequence_index].clone();
-
-            if api_sequence.len() > 3 {
-                sequnce_covered_by_reverse_search = sequnce_covered_by_reverse_search + 1;
-                if api_sequence.len() > max_length {
-                    max_length = api_sequence.len();
-                }
-            }
-
-            res.push(api_sequence);
-        }
-
-        println!("targets covered by reverse search: {}", sequnce_covered_by_reverse_search);
-        println!("total targets: {}", res.len());
-        println!("max length = {}", max_length);
-
-        let mut total_length = 0;
-        for selected_sequence in &res {
-            total_length = total_length + selected_sequence.len();
-        }
-
-        println!("total length = {}", total_length);
-        let average_time_to_fuzz_each_api =
-            (total_length as f64) / (already_covered_nodes.len() as f64);
-        println!("average time to fuzz each api = {}", average_time_to_fuzz_each_api);
-
-        println!("--------------------------------");
-
-        res
-    }
-
     //判断一个函数能否加入给定的序列中,如果可以加入，返回Some(new_sequence),new_sequence是将新的调用加进去之后的情况，否则返回None
     pub(crate) fn is_fun_satisfied(
         &self,
@@ -1071,166 +1324,68 @@ pub(crate) fn is_fun_satisfied(
         input_fun_index: usize,
         sequence: &ApiSequence,
     ) -> Option<ApiSequence> {
-        //判断一个给定的函数能否加入到一个sequence中去
-        match input_type {
-            ApiType::BareFunction => {
-                let mut new_sequence = sequence.clone();
-                let mut api_call = ApiCall::_new(input_fun_index);
-                let mut _moved_indexes = FxHashSet::default(); //用来保存发生move的那些语句的index
-                                                         //用来保存会被多次可变引用的情况
-                let mut _multi_mut = FxHashSet::default();
-                let mut _immutable_borrow = FxHashSet::default();
-
-                let input_function = &self.api_functions[input_fun_index];
-                //如果是个unsafe函数，给sequence添加unsafe标记
-                if input_function._unsafe_tag._is_unsafe() {
-                    new_sequence.set_unsafe();
-                }
-                if input_function._trait_full_path.is_some() {
-                    let trait_full_path = input_function._trait_full_path.as_ref().unwrap();
-                    new_sequence.add_trait(trait_full_path);
-                }
-                let input_params = &input_function.inputs;
-                let input_params_num = input_params.len();
-                if input_params_num == 0 {
-                    //无需输入参数，直接是可满足的
-                    new_sequence._add_fn(api_call);
-                    return Some(new_sequence);
-                }
+        self.extender().is_fun_satisfied(input_type, input_fun_index, sequence)
+    }
 
-                for i in 0..input_params_num {
-                    let current_ty = &input_params[i];
-                    if api_util::is_fuzzable_type(current_ty, &self.full_name_map, self.cache()) {
-                        //如果当前参数是fuzzable的
-                        let current_fuzzable_index = new_sequence.fuzzable_params.len();
-                        let fuzzable_call_type = fuzzable_type::fuzzable_call_type(
-                            current_ty,
-                            &self.full_name_map,
-                            self.cache(),
-                        );
-                        let (fuzzable_type, call_type) =
-                            fuzzable_call_type.generate_fuzzable_type_and_call_type();
-
-                        //如果出现了下面这段话，说明出现了Fuzzable参数但不知道如何参数化的
-                        //典型例子是tuple里面出现了引用（&usize），这种情况不再去寻找dependency，直接返回无法添加即可
-                        match &fuzzable_type {
-                            FuzzableType::NoFuzzable => {
-                                //println!("Fuzzable Type Error Occurs!");
-                                //println!("type = {:?}", current_ty);
-                                //println!("fuzzable_call_type = {:?}", fuzzable_call_type);
-                                //println!("fuzzable_type = {:?}", fuzzable_type);
-                                return None;
-                            }
-                            _ => {}
-                        }
+    pub(crate) fn is_fun_satisfied_by_distinct_objects(
+        &self,
+        input_type: &ApiType,
+        input_fun_index: usize,
+        sequence: &ApiSequence,
+    ) -> Option<ApiSequence> {
+        self.extender().is_fun_satisfied_by_distinct_objects(input_type, input_fun_index, sequence)
+    }
 
-                        //判断要不要加mut tag
-                        if api_util::_need_mut_tag(&call_type) {
-                            new_sequence._insert_fuzzable_mut_tag(current_fuzzable_index);
-                        }
+    //按照给定的函数顺序重新构造一个序列，如果某个函数无法加入则返回None
+    pub(crate) fn rebuild_sequence(&self, function_indexes: &[usize]) -> Option<ApiSequence> {
+        let api_type = ApiType::BareFunction;
+        let mut sequence = ApiSequence::new();
+        for function_index in function_indexes {
+            sequence = self.is_fun_satisfied(&api_type, *function_index, &sequence)?;
+        }
+        Some(sequence)
+    }
 
-                        //添加到sequence中去
-                        new_sequence.fuzzable_params.push(fuzzable_type);
-                        api_call._add_param(
-                            ParamType::_FuzzableType,
-                            current_fuzzable_index,
-                            call_type,
-                        );
-                        continue;
-                    }
-                    //如果当前参数不是fuzzable的，那么就去api sequence寻找是否有这个依赖
-                    //TODO:处理move的情况
-                    let functions_in_sequence_len = sequence.functions.len();
-                    let mut dependency_flag = false;
-
-                    for function_index in 0..functions_in_sequence_len {
-                        //如果这个sequence里面的该函数返回值已经被move掉了，那么就跳过，不再能被使用了
-                        if new_sequence._is_moved(function_index)
-                            || _moved_indexes.contains(&function_index)
-                        {
-                            continue;
-                        }
-                        let found_function = &new_sequence.functions[function_index];
-                        let (api_type, index) = &found_function.func;
-                        if let Some(dependency_index) =
-                            self.check_dependency(api_type, *index, input_type, input_fun_index, i)
-                        {
-                            let dependency_ = self.api_dependencies[dependency_index].clone();
-                            //将覆盖到的边加入到新的sequence中去
-                            new_sequence._add_dependency(dependency_index);
-                            //找到了依赖，当前参数是可以被满足的，设置flag并退出循环
-                            dependency_flag = true;
-                            //如果满足move发生的条件，那么
-                            if api_util::_move_condition(current_ty, &dependency_.call_type) {
-                                if _multi_mut.contains(&function_index)
-                                    || _immutable_borrow.contains(&function_index)
-                                {
-                                    dependency_flag = false;
-                                    continue;
-                                } else {
-                                    _moved_indexes.insert(function_index);
-                                }
-                            }
-                            //如果当前调用是可变借用
-                            if api_util::_is_mutable_borrow_occurs(
-                                current_ty,
-                                &dependency_.call_type,
-                            ) {
-                                //如果之前已经被借用过了
-                                if _multi_mut.contains(&function_index)
-                                    || _immutable_borrow.contains(&function_index)
-                                {
-                                    dependency_flag = false;
-                                    continue;
-                                } else {
-                                    _multi_mut.insert(function_index);
-                                }
-                            }
-                            //如果当前调用是引用，且之前已经被可变引用过，那么这个引用是非法的
-                            if api_util::_is_immutable_borrow_occurs(
-                                current_ty,
-                                &dependency_.call_type,
-                            ) {
-                                if _multi_mut.contains(&function_index) {
-                                    dependency_flag = false;
-                                    continue;
-                                } else {
-                                    _immutable_borrow.insert(function_index);
-                                }
-                            }
-                            //参数需要加mut 标记的话
-                            if api_util::_need_mut_tag(&dependency_.call_type) {
-                                new_sequence._insert_function_mut_tag(function_index);
-                            }
-                            //如果call type是unsafe的，那么给sequence加上unsafe标记
-                            if dependency_.call_type.unsafe_call_type()._is_unsafe() {
-                                new_sequence.set_unsafe();
-                            }
-                            api_call._add_param(
-                                ParamType::_FunctionReturn,
-                                function_index,
-                                dependency_.call_type,
-                            );
-                            break;
-                        }
-                    }
-                    if !dependency_flag {
-                        //如果这个参数没有寻找到依赖，则这个函数不可以被加入到序列中
-                        return None;
-                    }
-                }
-                //所有参数都可以找到依赖，那么这个函数就可以加入序列
-                new_sequence._add_fn(api_call);
-                for move_index in _moved_indexes {
-                    new_sequence._insert_move_index(move_index);
-                }
-                if new_sequence._contains_multi_dynamic_length_fuzzable() {
-                    //如果新生成的序列包含多维可变的参数，就不把这个序列加进去
-                    return None;
+    //保持序列的前缀不变，只替换最后一个调用，得到至多max_variants个兄弟序列
+    //替换的调用必须使用前缀中某个函数的返回值，优先选择前缀中还没有调用过的函数
+    pub(crate) fn tail_variants(
+        &self,
+        sequence: &ApiSequence,
+        max_variants: usize,
+    ) -> Vec<ApiSequence> {
+        let mut res = Vec::new();
+        let sequence_len = sequence.len();
+        if max_variants <= 0 || sequence_len < 2 {
+            return res;
+        }
+        let function_indexes = sequence.function_indexes();
+        let last_index = function_indexes[sequence_len - 1];
+        let prefix = match self.rebuild_sequence(&function_indexes[..sequence_len - 1]) {
+            Some(prefix) => prefix,
+            None => return res,
+        };
+        let api_type = ApiType::BareFunction;
+        let mut candidates: Vec<usize> = (0..self.api_functions.len())
+            .filter(|api_index| *api_index != last_index)
+            .collect();
+        //stable sort: functions not in the prefix come first
+        candidates.sort_by_key(|api_index| function_indexes.contains(api_index));
+        for api_index in candidates {
+            if res.len() >= max_variants {
+                break;
+            }
+            if let Some(variant) = self.is_fun_satisfied(&api_type, api_index, &prefix) {
+                let tail_call = variant.functions.last().unwrap();
+                let uses_prefix = tail_call
+                    .params
+                    .iter()
+                    .any(|(param_type, ..)| *param_type == ParamType::_FunctionReturn);
+                if uses_prefix && !variant._has_no_fuzzables() {
+                    res.push(variant);
                 }
-                return Some(new_sequence);
             }
         }
+        res
     }
 
     //判断一个依赖是否存在,存在的话返回Some(ApiDependency),否则返回None
@@ -1242,45 +1397,17 @@ pub(crate) fn check_dependency(
         input_index: usize,
         input_param_index_: usize,
     ) -> Option<usize> {
-        let dependency_num = self.api_dependencies.len();
-        for index in 0..dependency_num {
-            let dependency = &self.api_dependencies[index];
-            //TODO:直接比较每一项内容是否可以节省点时间？
-            let tmp_dependency = ApiDependency {
-                output_fun: (*output_type, output_index),
-                input_fun: (*input_type, input_index),
-                input_param_index: input_param_index_,
-                call_type: dependency.call_type.clone(),
-            };
-            if tmp_dependency == *dependency {
-                //存在依赖
-                return Some(index);
-            }
-        }
-        //没找到依赖
-        return None;
+        self.extender().check_dependency(
+            output_type,
+            output_index,
+            input_type,
+            input_index,
+            input_param_index_,
+        )
     }
 
     //判断一个调用序列是否已经到达终止端点
     fn is_sequence_ended(&self, api_sequence: &ApiSequence) -> bool {
-        let functions = &api_sequence.functions;
-        let last_fun = functions.last();
-        let cache = self.cache();
-        match last_fun {
-            None => false,
-            Some(api_call) => {
-                let (api_type, index) = &api_call.func;
-                match api_type {
-                    ApiType::BareFunction => {
-                        let last_func = &self.api_functions[*index];
-                        if last_func._is_end_function(&self.full_name_map, cache) {
-                            return true;
-                        } else {
-                            return false;
-                        }
-                    }
-                }
-            }
-        }
+        self.extender().is_sequence_ended(api_sequence)
     }
 }
@@ -1,12 +1,33 @@
+use crate::clean::{self, types::PrimitiveType};
 use crate::formats::cache::Cache;
 use crate::fuzz_target::afl_util::{self, _AflHelpers};
 use crate::fuzz_target::api_graph::{ApiGraph, ApiType};
 use crate::fuzz_target::api_util;
 use crate::fuzz_target::call_type::CallType;
+use crate::fuzz_target::char_mutator;
+use crate::fuzz_target::clone_check;
+use crate::fuzz_target::code_ast::{self, Block, Expr, FnItem, Item, Param, Pat, Stmt};
+use crate::fuzz_target::contract::ContractHarness;
+use crate::fuzz_target::dependency;
+use crate::fuzz_target::differential::DifferentialHarness;
+use crate::fuzz_target::drop_order::DropHarness;
+use crate::fuzz_target::features;
+use crate::fuzz_target::format_harness::{self, FormatHarness, FORMAT_PARAMS};
+use crate::fuzz_target::fuzz_target_options::{AsyncRuntime, ErrorPolicy};
 use crate::fuzz_target::fuzzable_type::FuzzableType;
+use crate::fuzz_target::invariant;
+use crate::fuzz_target::no_std;
+use crate::fuzz_target::operator::{Operator, OperatorHarness};
+use crate::fuzz_target::panic_doc;
+use crate::fuzz_target::param_bounds;
 use crate::fuzz_target::prelude_type;
 use crate::fuzz_target::replay_util;
+use crate::fuzz_target::round_trip::RoundTripHarness;
+use crate::fuzz_target::runtime_crate::RUNTIME_IMPORT;
+use crate::fuzz_target::sequence_ir::{SequenceIr, ValueSource};
+use crate::fuzz_target::serde_producer;
 use rustc_data_structures::fx::{FxHashMap, FxHashSet};
+use rustc_span::symbol::sym;
 
 #[derive(Clone, Debug, Hash, Eq, PartialEq)]
 pub(crate) enum ParamType {
@@ -51,10 +72,18 @@ pub(crate) struct ApiSequence {
     pub(crate) fuzzable_params: Vec<FuzzableType>,  //需要传入的fuzzable变量
     pub(crate) _using_traits: Vec<String>,          //需要use引入的traits的路径
     pub(crate) _unsafe_tag: bool,                   //标志这个调用序列是否需要加上unsafe标记
+    pub(crate) _async_tag: bool,                    //标志这个调用序列是否调用了async函数
     pub(crate) _moved: FxHashSet<usize>,            //表示哪些返回值已经被move掉，不再能被使用
     pub(crate) _fuzzable_mut_tag: FxHashSet<usize>, //表示哪些fuzzable的变量需要带上mut标记
     pub(crate) _function_mut_tag: FxHashSet<usize>, //表示哪些function的返回值需要带上mut标记
     pub(crate) _covered_dependencies: FxHashSet<usize>, //表示用到了哪些dependency,即边覆盖率
+    pub(crate) _borrows: FxHashMap<usize, Vec<(usize, bool)>>, //表示哪些function的返回值借用了之前的哪些返回值，以及是否是可变借用
+    pub(crate) _format_harness: Option<FormatHarness>, //是否用fuzz的宽度、精度和格式格式化某个返回值
+    pub(crate) _differential: Option<DifferentialHarness>, //是否和另一个契约相同的api比较某个返回值
+    pub(crate) _round_trip: Option<RoundTripHarness>,  //是否编码再解码某个返回值，并和原来的值比较
+    pub(crate) _operator_harness: Option<OperatorHarness>, //是否排序、哈希和最后的返回值类型相同的返回值
+    pub(crate) _drop_harness: Option<DropHarness>,         //是否按fuzz数据选择的顺序drop返回值
+    pub(crate) _contract_harness: Option<ContractHarness>, //是否检查三个返回值满足Eq、Ord和Hash约定
 }
 
 impl ApiSequence {
@@ -63,19 +92,35 @@ pub(crate) fn new() -> Self {
         let fuzzable_params = Vec::new();
         let _using_traits = Vec::new();
         let _unsafe_tag = false;
+        let _async_tag = false;
         let _moved = FxHashSet::default();
         let _fuzzable_mut_tag = FxHashSet::default();
         let _function_mut_tag = FxHashSet::default();
         let _covered_dependencies = FxHashSet::default();
+        let _borrows = FxHashMap::default();
+        let _format_harness = None;
+        let _differential = None;
+        let _round_trip = None;
+        let _operator_harness = None;
+        let _drop_harness = None;
+        let _contract_harness = None;
         ApiSequence {
             functions,
             fuzzable_params,
             _using_traits,
             _unsafe_tag,
+            _async_tag,
             _moved,
             _fuzzable_mut_tag,
             _function_mut_tag,
             _covered_dependencies,
+            _borrows,
+            _format_harness,
+            _differential,
+            _round_trip,
+            _operator_harness,
+            _drop_harness,
+            _contract_harness,
         }
     }
 
@@ -138,6 +183,8 @@ pub(crate) fn _merge_another_sequence(&self, other: &ApiSequence) -> Self {
         //unsafe tag
         res._unsafe_tag =
             if other_sequence._unsafe_tag { other_sequence._unsafe_tag } else { res._unsafe_tag };
+        //async tag
+        res._async_tag = res._async_tag || other_sequence._async_tag;
         //move tag
         for move_tag in other_sequence._moved {
             res._moved.insert(move_tag + first_func_number);
@@ -150,6 +197,14 @@ pub(crate) fn _merge_another_sequence(&self, other: &ApiSequence) -> Self {
         for function_mut_tag in other_sequence._function_mut_tag {
             res._function_mut_tag.insert(function_mut_tag + first_func_number);
         }
+        //borrows
+        for (borrower, owners) in other_sequence._borrows {
+            let owners = owners
+                .into_iter()
+                .map(|(owner, mutable)| (owner + first_func_number, mutable))
+                .collect();
+            res._borrows.insert(borrower + first_func_number, owners);
+        }
         res
     }
 
@@ -188,6 +243,11 @@ pub(crate) fn _get_contained_api_functions(&self) -> Vec<usize> {
         res
     }
 
+    //the index of the called api function of each api call, in calling order
+    pub(crate) fn function_indexes(&self) -> Vec<usize> {
+        self.functions.iter().map(|api_call| api_call.func.1).collect()
+    }
+
     pub(crate) fn _is_moved(&self, index: usize) -> bool {
         if self._moved.contains(&index) {
             true
@@ -200,6 +260,170 @@ pub(crate) fn _insert_move_index(&mut self, index: usize) {
         self._moved.insert(index);
     }
 
+    //没有被move掉的返回值对应的api函数,这些返回值可以被后续的调用继续使用(例如反复使用同一个可变的receiver)
+    pub(crate) fn _usable_api_func_indexes(&self) -> Vec<usize> {
+        let mut res = Vec::new();
+        for (i, api_call) in self.functions.iter().enumerate() {
+            let (_, func_index) = &api_call.func;
+            if !self._is_moved(i) && !res.contains(func_index) {
+                res.push(*func_index);
+            }
+        }
+        res
+    }
+
+    pub(crate) fn _add_borrow(&mut self, borrower: usize, owner: usize, mutable: bool) {
+        let owners = self._borrows.entry(borrower).or_default();
+        if !owners.contains(&(owner, mutable)) {
+            owners.push((owner, mutable));
+        }
+    }
+
+    pub(crate) fn _borrowed_owners(&self, borrower: usize) -> Vec<(usize, bool)> {
+        self._borrows.get(&borrower).cloned().unwrap_or_default()
+    }
+
+    //使用owner会使哪些借用失效:独占使用时是所有借用，共享使用时只有可变借用
+    pub(crate) fn _conflicting_borrowers(&self, owner: usize, exclusive: bool) -> Vec<usize> {
+        let mut res = Vec::new();
+        for (borrower, owners) in &self._borrows {
+            if owners.iter().any(|(index, mutable)| *index == owner && (exclusive || *mutable)) {
+                res.push(*borrower);
+            }
+        }
+        res
+    }
+
+    //去掉一个调用以及直接或间接使用了它的返回值的调用，重新编号剩下的返回值和fuzzable变量
+    pub(crate) fn _remove_call_with_dependents(
+        &self,
+        removed_call: usize,
+        _api_graph: &ApiGraph<'_>,
+    ) -> Self {
+        let other_calls: Vec<usize> = (0..self.len()).filter(|i| *i != removed_call).collect();
+        let kept_calls = self._calls_without_dependents(&other_calls, removed_call);
+        self._with_calls(&kept_calls, _api_graph)
+    }
+
+    //把一个调用移动到position处的调用之前，被移动的调用只能使用position之前的返回值
+    pub(crate) fn _move_call_before(
+        &self,
+        moved_call: usize,
+        position: usize,
+        _api_graph: &ApiGraph<'_>,
+    ) -> Self {
+        let mut calls: Vec<usize> = (0..self.len()).filter(|i| *i != moved_call).collect();
+        calls.insert(position, moved_call);
+        self._with_calls(&calls, _api_graph)
+    }
+
+    //按给定的顺序保留调用，重新编号返回值和fuzzable变量。每个调用使用的返回值必须在它之前
+    fn _with_calls(&self, kept_calls: &[usize], _api_graph: &ApiGraph<'_>) -> Self {
+        let mut res = ApiSequence::new();
+        res._using_traits = self._using_traits.clone();
+        res._unsafe_tag = self._unsafe_tag;
+        res._async_tag = self._async_tag;
+        let mut function_map = FxHashMap::default();
+        let mut fuzzable_map = FxHashMap::default();
+        for old_index in kept_calls.iter().cloned() {
+            let old_call = &self.functions[old_index];
+            let mut new_call = ApiCall { func: old_call.func.clone(), params: Vec::new() };
+            for (k, (param_type, index, call_type)) in old_call.params.iter().enumerate() {
+                let new_index = match param_type {
+                    ParamType::_FuzzableType => {
+                        if !fuzzable_map.contains_key(index) {
+                            let new_fuzzable_index = res.fuzzable_params.len();
+                            res.fuzzable_params.push(self.fuzzable_params[*index].clone());
+                            if self._is_fuzzable_need_mut_tag(*index) {
+                                res._insert_fuzzable_mut_tag(new_fuzzable_index);
+                            }
+                            fuzzable_map.insert(*index, new_fuzzable_index);
+                        }
+                        fuzzable_map[index]
+                    }
+                    ParamType::_FunctionReturn => {
+                        let output_fun = &self.functions[*index].func;
+                        let dependency = _api_graph.api_dependencies.iter().position(|dependency| {
+                            dependency.output_fun == *output_fun
+                                && dependency.input_fun == old_call.func
+                                && dependency.input_param_index == k
+                                && dependency.call_type == *call_type
+                        });
+                        if let Some(dependency_index) = dependency {
+                            res._add_dependency(dependency_index);
+                        }
+                        function_map[index]
+                    }
+                };
+                new_call._add_param(param_type.clone(), new_index, call_type.clone());
+            }
+            let new_index = res.len();
+            function_map.insert(old_index, new_index);
+            res._add_fn(new_call);
+            if self._is_moved(old_index) {
+                res._insert_move_index(new_index);
+            }
+            if self._is_function_need_mut_tag(old_index) {
+                res._insert_function_mut_tag(new_index);
+            }
+            for (owner, mutable) in self._borrowed_owners(old_index) {
+                if let Some(new_owner) = function_map.get(&owner) {
+                    res._add_borrow(new_index, *new_owner, mutable);
+                }
+            }
+        }
+        //各种harness只有在用到的调用都保留时才保留，调用重新编号，harness自己的fuzzable变量放到最后
+        let remap = |calls: &Vec<usize>| -> Option<Vec<usize>> {
+            calls.iter().map(|call| function_map.get(call).cloned()).collect()
+        };
+        if let Some(differential) = self._differential {
+            if let Some(call) = function_map.get(&differential.call) {
+                res._differential = Some(DifferentialHarness { call: *call, ..differential });
+            }
+        }
+        if let Some(round_trip) = self._round_trip {
+            if let Some(call) = function_map.get(&round_trip.call) {
+                res._round_trip = Some(RoundTripHarness { call: *call, ..round_trip });
+            }
+        }
+        if let Some(format_harness) = self._format_harness {
+            if let Some(call) = function_map.get(&format_harness.call) {
+                let first_param = res.fuzzable_params.len();
+                let params = format_harness.first_param..format_harness.first_param + FORMAT_PARAMS;
+                res.fuzzable_params.extend_from_slice(&self.fuzzable_params[params]);
+                res._format_harness =
+                    Some(FormatHarness { call: *call, first_param, ..format_harness });
+            }
+        }
+        if let Some(operator_harness) = &self._operator_harness {
+            if let (Some(call), Some(operands)) =
+                (function_map.get(&operator_harness.call), remap(&operator_harness.operands))
+            {
+                res._operator_harness =
+                    Some(OperatorHarness { call: *call, operands, ..operator_harness.clone() });
+            }
+        }
+        if let Some(contract_harness) = &self._contract_harness {
+            if let (Some(call), Some(operands)) =
+                (function_map.get(&contract_harness.call), remap(&contract_harness.operands))
+            {
+                res._contract_harness =
+                    Some(ContractHarness { call: *call, operands, ..contract_harness.clone() });
+            }
+        }
+        if let Some(drop_harness) = &self._drop_harness {
+            let orders: Option<Vec<Vec<usize>>> = drop_harness.orders.iter().map(remap).collect();
+            if let (Some(call), Some(values), Some(orders)) =
+                (function_map.get(&drop_harness.call), remap(&drop_harness.values), orders)
+            {
+                let order_param = res.fuzzable_params.len();
+                res.fuzzable_params.push(self.fuzzable_params[drop_harness.order_param].clone());
+                res._drop_harness = Some(DropHarness { call: *call, values, orders, order_param });
+            }
+        }
+        res
+    }
+
     pub(crate) fn _add_fn(&mut self, api_call: ApiCall) {
         self.functions.push(api_call);
     }
@@ -220,6 +444,53 @@ pub(crate) fn _insert_function_mut_tag(&mut self, index: usize) {
         self._function_mut_tag.insert(index);
     }
 
+    pub(crate) fn _is_formatted(&self, index: usize) -> bool {
+        self._format_harness.map_or(false, |format_harness| format_harness.call == index)
+    }
+
+    pub(crate) fn _is_compared(&self, index: usize) -> bool {
+        self._differential.map_or(false, |differential| differential.call == index)
+            || self._round_trip.map_or(false, |round_trip| round_trip.call == index)
+    }
+
+    pub(crate) fn _is_operand(&self, index: usize) -> bool {
+        self._operator_harness
+            .as_ref()
+            .map_or(false, |operator_harness| operator_harness.operands.contains(&index))
+            || self
+                ._contract_harness
+                .as_ref()
+                .map_or(false, |contract_harness| contract_harness.operands.contains(&index))
+    }
+
+    pub(crate) fn _is_dropped(&self, index: usize) -> bool {
+        self._drop_harness
+            .as_ref()
+            .map_or(false, |drop_harness| drop_harness.values.contains(&index))
+    }
+
+    //格式化、比较等附加在序列之后的检查，每个序列最多一个
+    pub(crate) fn _has_harness(&self) -> bool {
+        self._format_harness.is_some()
+            || self._differential.is_some()
+            || self._round_trip.is_some()
+            || self._operator_harness.is_some()
+            || self._drop_harness.is_some()
+            || self._contract_harness.is_some()
+    }
+
+    //检查中调用的其他api
+    pub(crate) fn _harness_apis(&self) -> Vec<usize> {
+        let mut res = Vec::new();
+        if let Some(differential) = &self._differential {
+            res.push(differential.partner);
+        }
+        if let Some(round_trip) = &self._round_trip {
+            res.extend(round_trip.apis());
+        }
+        res
+    }
+
     pub(crate) fn _is_function_need_mut_tag(&self, index: usize) -> bool {
         if self._function_mut_tag.contains(&index) {
             true
@@ -232,6 +503,10 @@ pub(crate) fn set_unsafe(&mut self) {
         self._unsafe_tag = true;
     }
 
+    pub(crate) fn set_async(&mut self) {
+        self._async_tag = true;
+    }
+
     pub(crate) fn add_trait(&mut self, trait_full_path: &String) {
         self._using_traits.push(trait_full_path.clone());
     }
@@ -352,15 +627,20 @@ pub(crate) fn _to_replay_crash_file(
     ) -> String {
         let mut res = self._to_afl_except_main(_api_graph, test_index);
         res = res.replace("#[macro_use]\nextern crate afl;\n", "");
-        res.push_str(replay_util::_read_crash_file_data());
-        res.push('\n');
-        res.push_str(self._reproduce_main_function(test_index).as_str());
+        if !_api_graph.cx.options.runtime_crate {
+            res.push_str(replay_util::_read_crash_file_data());
+            res.push('\n');
+        }
+        res.push_str(self._reproduce_main_function(_api_graph, test_index).as_str());
         res
     }
 
     pub(crate) fn _to_afl_test_file(&self, _api_graph: &ApiGraph<'_>, test_index: usize) -> String {
         let mut res = self._to_afl_except_main(_api_graph, test_index);
-        res.push_str(self._afl_main_function(test_index).as_str());
+        res.push_str(self._afl_main_function(_api_graph, test_index).as_str());
+        if _api_graph.cx.options.no_std {
+            res = no_std::to_no_std_target(res);
+        }
         res
     }
 
@@ -374,16 +654,26 @@ pub(crate) fn _to_libfuzzer_test_file(
             "#[macro_use]\nextern crate afl;\n",
             format!("#![no_main]\n#[macro_use]\nextern crate libfuzzer_sys;\n").as_str(),
         );
-        res.push_str(self._libfuzzer_fuzz_main(test_index).as_str());
+        res.push_str(self._libfuzzer_fuzz_main(_api_graph, test_index).as_str());
+        if _api_graph.cx.options.char_mutator && char_mutator::takes_text(self) {
+            res.push_str(char_mutator::mutator_code(self).as_str());
+        }
+        if _api_graph.cx.options.no_std {
+            res = no_std::to_no_std_target(res);
+        }
         res
     }
 
-    pub(crate) fn _libfuzzer_fuzz_main(&self, test_index: usize) -> String {
-        let mut res = String::new();
-        res.push_str("fuzz_target!(|data: &[u8]| {\n");
-        res.push_str(self._afl_closure_body(0, test_index).as_str());
-        res.push_str("});\n");
-        res
+    pub(crate) fn _libfuzzer_fuzz_main(
+        &self,
+        _api_graph: &ApiGraph<'_>,
+        test_index: usize,
+    ) -> String {
+        let fuzz_target = Expr::MacroCall {
+            name: "fuzz_target".to_string(),
+            args: vec![self._fuzz_closure(_api_graph, test_index)],
+        };
+        Item::MacroCall(fuzz_target).to_code(0)
     }
 
     pub(crate) fn _to_afl_except_main(
@@ -392,8 +682,18 @@ pub(crate) fn _to_afl_except_main(
         test_index: usize,
     ) -> String {
         let mut res = String::new();
-        //加入可能需要开启的feature gate
-        let feature_gates = afl_util::_get_feature_gates_of_sequence(&self.fuzzable_params);
+        let required_features = features::sequence_features(self, _api_graph);
+        if !required_features.is_empty() {
+            let features_line = format!("//required features: {}\n", required_features.join(", "));
+            res.push_str(features_line.as_str());
+        }
+        for expected_panic in panic_doc::expected_panics(self, _api_graph) {
+            res.push_str(format!("//expected panic: {}\n", expected_panic).as_str());
+        }
+        //加入可能需要开启的feature gate，使用runtime crate时由runtime crate开启
+        let msrv = _api_graph.cx.options.msrv;
+        let feature_gates = afl_util::_get_feature_gates_of_sequence(&self.fuzzable_params, msrv)
+            .filter(|_| !_api_graph.cx.options.runtime_crate);
 
         if feature_gates.is_some() {
             for feature_gate in &feature_gates.unwrap() {
@@ -404,14 +704,14 @@ pub(crate) fn _to_afl_except_main(
 
         res.push_str("#[macro_use]\n");
         res.push_str("extern crate afl;\n");
-        res.push_str(format!("extern crate {};\n", _api_graph._crate_name).as_str());
+        res.push_str(self._extern_crates(_api_graph).as_str());
 
-        let prelude_helper_functions = self._prelude_helper_functions();
+        let prelude_helper_functions = self._prelude_helper_functions(_api_graph);
         if let Some(prelude_functions) = prelude_helper_functions {
             res.push_str(prelude_functions.as_str());
         }
 
-        let afl_helper_functions = self._afl_helper_functions();
+        let afl_helper_functions = self._afl_helper_functions(_api_graph);
         if let Some(afl_functions) = afl_helper_functions {
             res.push_str(afl_functions.as_str());
         }
@@ -420,7 +720,28 @@ pub(crate) fn _to_afl_except_main(
         res
     }
 
-    pub(crate) fn _prelude_helper_functions(&self) -> Option<String> {
+    //被测的库，以及调用async函数时需要的runtime
+    pub(crate) fn _extern_crates(&self, _api_graph: &ApiGraph<'_>) -> String {
+        let mut res = format!("extern crate {};\n", _api_graph.lib_name());
+        if self._async_tag {
+            let async_runtime = _api_graph.cx.options.async_runtime;
+            res.push_str(format!("extern crate {};\n", async_runtime.crate_name()).as_str());
+        }
+        if serde_producer::uses_serde_producer(self, _api_graph) {
+            let serde_format = _api_graph.cx.options.serde_producer.unwrap();
+            res.push_str(format!("extern crate {};\n", serde_format.crate_name()).as_str());
+        }
+        for crate_name in dependency::used_crates(self, _api_graph) {
+            res.push_str(format!("extern crate {};\n", crate_name).as_str());
+        }
+        res
+    }
+
+    pub(crate) fn _prelude_helper_functions(&self, _api_graph: &ApiGraph<'_>) -> Option<String> {
+        //只有early return需要辅助函数，其他策略直接生成unwrap或者match
+        if _api_graph.cx.options.error_policy != ErrorPolicy::EarlyReturn {
+            return None;
+        }
         let mut prelude_helpers = FxHashSet::default();
         for api_call in &self.functions {
             let params = &api_call.params;
@@ -442,9 +763,15 @@ pub(crate) fn _prelude_helper_functions(&self) -> Option<String> {
         Some(res)
     }
 
-    pub(crate) fn _afl_helper_functions(&self) -> Option<String> {
-        let afl_helper_functions =
-            afl_util::_get_afl_helpers_functions_of_sequence(&self.fuzzable_params);
+    pub(crate) fn _afl_helper_functions(&self, _api_graph: &ApiGraph<'_>) -> Option<String> {
+        //所有的helper都在runtime crate中定义
+        if _api_graph.cx.options.runtime_crate {
+            return Some(RUNTIME_IMPORT.to_string());
+        }
+        let afl_helper_functions = afl_util::_get_afl_helpers_functions_of_sequence(
+            &self.fuzzable_params,
+            _api_graph.cx.options.msrv,
+        );
         match afl_helper_functions {
             None => None,
             Some(afl_helpers) => {
@@ -457,79 +784,163 @@ pub(crate) fn _afl_helper_functions(&self) -> Option<String> {
         }
     }
 
-    pub(crate) fn _afl_main_function(&self, test_index: usize) -> String {
-        let mut res = String::new();
-        let indent = _generate_indent(4);
-        res.push_str("fn main() {\n");
-        res.push_str(indent.as_str());
-        res.push_str("fuzz!(|data: &[u8]| {\n");
-        res.push_str(self._afl_closure_body(4, test_index).as_str());
-        res.push_str(indent.as_str());
-        res.push_str("});\n");
-        res.push_str("}\n");
+    pub(crate) fn _afl_main_function(
+        &self,
+        _api_graph: &ApiGraph<'_>,
+        test_index: usize,
+    ) -> String {
+        let options = &_api_graph.cx.options;
+        let deferred_init_len =
+            if options.afl_deferred_init { Some(self._fuzzables_min_length()) } else { None };
+        let body = afl_util::_afl_main_body(
+            self._fuzz_closure(_api_graph, test_index),
+            options.afl_persistent_iterations,
+            deferred_init_len,
+        );
+        Item::Fn(FnItem { attrs: Vec::new(), name: "main".to_string(), params: Vec::new(), body })
+            .to_code(0)
+    }
+
+    pub(crate) fn _reproduce_main_function(
+        &self,
+        _api_graph: &ApiGraph<'_>,
+        test_index: usize,
+    ) -> String {
+        let mut body = Block::new();
+        body.push(Stmt::Let {
+            pat: Pat::ident("_content", false),
+            init: Expr::call(Expr::path("_read_data"), Vec::new()),
+        });
+        body.push(Stmt::Let {
+            pat: Pat::ident("data", false),
+            init: Expr::reference(false, Expr::path("_content")),
+        });
+        let data_len = Expr::method_call(Expr::path("data"), "len", Vec::new());
+        for (message, value) in [("data", Expr::path("data")), ("data len", data_len)] {
+            body.push(Stmt::Expr(Expr::MacroCall {
+                name: "println".to_string(),
+                args: vec![Expr::Lit(format!("\"{} = {{:?}}\"", message)), value],
+            }));
+        }
+        //在调用测试函数之前打印解码出的参数，方便分析crash。没有实现Debug的参数打印解码它的数据
+        let mut closure_body = self._afl_closure_body(test_index);
+        let test_function_call = closure_body.stmts.pop();
+        let param_bytes = self._fuzzable_param_bytes();
+        for (i, fuzzable_param) in self.fuzzable_params.iter().enumerate() {
+            let param_name = format!("_param{}", i);
+            let is_debug = fuzzable_param
+                ._local_type_names()
+                .iter()
+                .all(|full_name| _api_graph.local_type_implements_trait(full_name, sym::Debug));
+            let (message, value) = if is_debug {
+                (format!("\"{} = {{:?}}\"", param_name), Expr::Path(param_name))
+            } else {
+                (format!("\"{} is decoded from {{:?}}\"", param_name), param_bytes[i].clone())
+            };
+            closure_body.push(Stmt::Expr(Expr::MacroCall {
+                name: "println".to_string(),
+                args: vec![Expr::Lit(message), value],
+            }));
+        }
+        closure_body.stmts.extend(test_function_call);
+        body.stmts.extend(closure_body.stmts);
+        Item::Fn(FnItem { attrs: Vec::new(), name: "main".to_string(), params: Vec::new(), body })
+            .to_code(0)
+    }
+
+    //每个fuzzable变量解码自哪段数据，由定长和变长的几段数据组成的变量是整个输入
+    fn _fuzzable_param_bytes(&self) -> Vec<Expr> {
+        let dynamic_param_start_index = self._fuzzable_fixed_part_length();
+        let dynamic_param_number = self._dynamic_length_param_number();
+        let mut fixed_start_index = 0;
+        let mut dynamic_param_index = 0;
+        let mut res = Vec::new();
+        for fuzzable_param in &self.fuzzable_params {
+            let fixed_length = fuzzable_param._fixed_part_length();
+            let dynamic_number = fuzzable_param._dynamic_length_param_number();
+            let range = if dynamic_number == 0 {
+                let fixed_end_index = fixed_start_index + fixed_length;
+                Some((fixed_start_index.to_string(), fixed_end_index.to_string()))
+            } else if dynamic_number == 1 && fixed_length == 0 {
+                Some(_AflHelpers::_dynamic_range(
+                    dynamic_param_start_index,
+                    dynamic_param_index,
+                    dynamic_param_number,
+                    &"dynamic_length".to_string(),
+                ))
+            } else {
+                None
+            };
+            res.push(match range {
+                Some((start, end)) => Expr::Raw(format!("&data[{}..{}]", start, end)),
+                None => Expr::path("data"),
+            });
+            fixed_start_index += fixed_length;
+            dynamic_param_index += dynamic_number;
+        }
         res
     }
 
-    pub(crate) fn _reproduce_main_function(&self, test_index: usize) -> String {
-        format!(
-            "fn main() {{
-    let _content = _read_data();
-    let data = &_content;
-    println!(\"data = {{:?}}\", data);
-    println!(\"data len = {{:?}}\", data.len());
-{}
-}}",
-            self._afl_closure_body(0, test_index)
-        )
+    //|data: &[u8]| { ... }
+    pub(crate) fn _fuzz_closure(&self, _api_graph: &ApiGraph<'_>, test_index: usize) -> Expr {
+        let mut body = self._afl_closure_body(test_index);
+        //afl和libfuzzer的panic hook会直接abort，被捕获的panic也会被当作crash。
+        //换成什么都不做的hook，没有被捕获的panic仍然会被fuzzer当作crash。
+        //fuzzer在运行第一个输入之前才设置自己的hook，所以在闭包中设置，并且只设置一次
+        if _api_graph.cx.options.ignore_panics {
+            let hook = Expr::call(Expr::path("Box::new"), vec![Expr::Raw("|_| {}".to_string())]);
+            let set_hook = Expr::call(Expr::path("std::panic::set_hook"), vec![hook]);
+            let once = "static PANIC_HOOK: std::sync::Once = std::sync::Once::new()";
+            let once = Expr::Raw(once.to_string());
+            let set_once = Expr::method_call(
+                Expr::path("PANIC_HOOK"),
+                "call_once",
+                vec![Expr::Raw(format!("|| {}", set_hook.to_code(0)))],
+            );
+            body.stmts.insert(1, Stmt::Expr(once));
+            body.stmts.insert(2, Stmt::Expr(set_once));
+        }
+        Expr::Closure { params: vec![Param::new("data", false, "&[u8]")], body }
     }
 
-    pub(crate) fn _afl_closure_body(&self, outer_indent: usize, test_index: usize) -> String {
-        let extra_indent = 4;
-        let mut res = String::new();
-        let indent = _generate_indent(outer_indent + extra_indent);
-        res.push_str(format!("{indent}//actual body emit\n", indent = indent).as_str());
+    pub(crate) fn _afl_closure_body(&self, test_index: usize) -> Block {
+        let mut body = Block::new();
+        body.push(Stmt::Comment("actual body emit".to_string()));
 
         let op = if self._is_fuzzables_fixed_length() { "!=" } else { "<" };
         let min_len = self._fuzzables_min_length();
-        res.push_str(
-            format!(
-                "{indent}if data.len() {op} {min_len} {{return;}}\n",
-                indent = indent,
-                op = op,
-                min_len = min_len
-            )
-            .as_str(),
-        );
+        let data_len = Expr::method_call(Expr::path("data"), "len", Vec::new());
+        body.push(Stmt::If {
+            cond: Expr::binary(data_len.clone(), op, Expr::Lit(min_len.to_string())),
+            then: Block { stmts: vec![Stmt::Return] },
+        });
 
         let dynamic_param_start_index = self._fuzzable_fixed_part_length();
         let dynamic_param_number = self._dynamic_length_param_number();
         let dynamic_length_name = "dynamic_length";
-        let every_dynamic_length = format!(
-            "let {dynamic_length_name} = (data.len() - {dynamic_param_start_index}) / {dynamic_param_number}",
-            dynamic_length_name = dynamic_length_name,
-            dynamic_param_start_index = dynamic_param_start_index,
-            dynamic_param_number = dynamic_param_number
-        );
         if !self._is_fuzzables_fixed_length() {
-            res.push_str(
-                format!(
-                    "{indent}{every_dynamic_length};\n",
-                    indent = indent,
-                    every_dynamic_length = every_dynamic_length
-                )
-                .as_str(),
-            );
+            //let dynamic_length = (data.len() - dynamic_param_start_index) / dynamic_param_number;
+            let dynamic_part_len =
+                Expr::binary(data_len, "-", Expr::Lit(dynamic_param_start_index.to_string()));
+            body.push(Stmt::Let {
+                pat: Pat::ident(dynamic_length_name, false),
+                init: Expr::binary(
+                    dynamic_part_len,
+                    "/",
+                    Expr::Lit(dynamic_param_number.to_string()),
+                ),
+            });
         }
 
         let mut fixed_start_index = 0; //当前固定长度的变量开始分配的位置
         let mut dynamic_param_index = 0; //当前这是第几个动态长度的变量
 
         let fuzzable_param_number = self.fuzzable_params.len();
+        let mut test_function_args = Vec::new();
         for i in 0..fuzzable_param_number {
             let fuzzable_param = &self.fuzzable_params[i];
             let afl_helper = _AflHelpers::_new_from_fuzzable(fuzzable_param);
-            let param_initial_line = afl_helper._generate_param_initial_statement(
-                i,
+            let param_initial_rhs = afl_helper._generate_param_initial_rhs(
                 fixed_start_index,
                 dynamic_param_start_index,
                 dynamic_param_index,
@@ -537,31 +948,20 @@ pub(crate) fn _afl_closure_body(&self, outer_indent: usize, test_index: usize) -
                 &dynamic_length_name.to_string(),
                 fuzzable_param,
             );
-            res.push_str(
-                format!(
-                    "{indent}{param_initial_line}\n",
-                    indent = indent,
-                    param_initial_line = param_initial_line
-                )
-                .as_str(),
-            );
+            let param_name = format!("_param{}", i);
+            body.push(Stmt::Let {
+                pat: Pat::ident(param_name.as_str(), false),
+                init: Expr::Raw(param_initial_rhs),
+            });
+            test_function_args.push(Expr::Path(param_name));
             fixed_start_index = fixed_start_index + fuzzable_param._fixed_part_length();
             dynamic_param_index =
                 dynamic_param_index + fuzzable_param._dynamic_length_param_number();
         }
 
-        let mut test_function_call =
-            format!("{indent}test_function{test_index}(", indent = indent, test_index = test_index);
-        for i in 0..fuzzable_param_number {
-            if i != 0 {
-                test_function_call.push_str(" ,");
-            }
-            test_function_call.push_str(format!("_param{}", i).as_str());
-        }
-        test_function_call.push_str(");\n");
-        res.push_str(test_function_call.as_str());
-
-        res
+        let test_function = Expr::Path(format!("test_function{}", test_index));
+        body.push(Stmt::Expr(Expr::call(test_function, test_function_args)));
+        body
     }
 
     pub(crate) fn _to_well_written_function(
@@ -570,250 +970,524 @@ pub(crate) fn _to_well_written_function(
         test_index: usize,
         indent_size: usize,
     ) -> String {
-        let test_function_title = "fn test_function";
+        let mut res = String::new();
+        for item in self._to_test_function_items(_api_graph, test_index) {
+            res.push_str(item.to_code(indent_size).as_str());
+        }
+        res
+    }
+
+    //trait引用以及测试函数本身
+    pub(crate) fn _to_test_function_items(
+        &self,
+        _api_graph: &ApiGraph<'_>,
+        test_index: usize,
+    ) -> Vec<Item> {
         let param_prefix = "_param";
         let local_param_prefix = "_local";
-        let mut res = String::new();
         //生成对trait的引用
-        let using_traits = self._generate_using_traits_string(indent_size);
-        res.push_str(using_traits.as_str());
-        //生成函数头
-        let function_header = self._generate_function_header_string(
+        let mut items = self._generate_using_traits(_api_graph);
+        //生成函数签名
+        let params = self._generate_function_params(param_prefix);
+        //生成函数体
+        let function_body = self._generate_function_body(
             _api_graph,
-            test_index,
-            indent_size,
-            0,
-            test_function_title,
+            _api_graph.cache(),
             param_prefix,
+            local_param_prefix,
         );
-        res.push_str(function_header.as_str());
-
-        //加入函数体开头的大括号
-        res.push_str("{\n");
-
-        //加入函数体
-        if self._unsafe_tag {
-            let unsafe_indent = _generate_indent(indent_size + 4);
-            res.push_str(unsafe_indent.as_str());
-            res.push_str("unsafe {\n");
-            let unsafe_function_body = self._generate_function_body_string(
-                _api_graph,
-                _api_graph.cache(),
-                indent_size + 4,
-                param_prefix,
-                local_param_prefix,
-            );
-            res.push_str(unsafe_function_body.as_str());
-            res.push_str(unsafe_indent.as_str());
-            res.push_str("}\n");
+        let body = if self._unsafe_tag {
+            Block { stmts: vec![Stmt::Unsafe(function_body)] }
         } else {
-            let function_body = self._generate_function_body_string(
-                _api_graph,
-                _api_graph.cache(),
-                indent_size,
-                param_prefix,
-                local_param_prefix,
-            );
-            res.push_str(function_body.as_str());
+            function_body
+        };
+        //调用了async函数时，整个函数体放到async块中，由runtime执行
+        let body = if self._async_tag {
+            let future = Expr::AsyncBlock(body);
+            let block_on = _block_on(_api_graph.cx.options.async_runtime, future);
+            Block { stmts: vec![Stmt::Expr(block_on)] }
+        } else {
+            body
+        };
+        let name = format!("test_function{}", test_index);
+        items.push(Item::Fn(FnItem { attrs: Vec::new(), name, params, body }));
+        //路径都是以rustdoc看到的crate名开头的，需要换成lib target的名字
+        if _api_graph.lib_name() != &_api_graph._crate_name {
+            code_ast::rename_crate_root(&mut items, &_api_graph._crate_name, _api_graph.lib_name());
         }
-
-        //加入函数体结尾的大括号
-        let braket_indent = _generate_indent(indent_size);
-        res.push_str(braket_indent.as_str());
-        res.push_str("}\n");
-
-        res
+        items
     }
 
-    pub(crate) fn _generate_using_traits_string(&self, indent_size: usize) -> String {
-        let indent = _generate_indent(indent_size);
-        let mut res = String::new();
+    pub(crate) fn _generate_using_traits(&self, _api_graph: &ApiGraph<'_>) -> Vec<Item> {
+        let mut res = Vec::new();
         //using trait需要去重
         let mut has_used_traits = FxHashSet::default();
         for using_trait_ in &self._using_traits {
-            if has_used_traits.contains(using_trait_) {
+            //trait定义所在的mod可能是私有的，使用公开的重导出路径
+            let using_trait_ = _api_graph.canonical_path(using_trait_);
+            if has_used_traits.contains(&using_trait_) {
                 continue;
             } else {
                 has_used_traits.insert(using_trait_.clone());
             }
-            res.push_str(indent.as_str());
-            res.push_str("use ");
-            res.push_str(using_trait_.as_str());
-            res.push_str(";\n");
+            res.push(Item::Use(using_trait_));
         }
-        res.push('\n');
         res
     }
 
-    //outer_indent:上层的缩进
-    //extra_indent:本块需要的额外缩进
-    pub(crate) fn _generate_function_header_string(
-        &self,
-        _api_graph: &ApiGraph<'_>,
-        test_index: usize,
-        outer_indent: usize,
-        extra_indent: usize,
-        test_function_title: &str,
-        param_prefix: &str,
-    ) -> String {
-        let indent_size = outer_indent + extra_indent;
-        let indent = _generate_indent(indent_size);
-
-        //生成具体的函数签名
-        let mut res = String::new();
-        res.push_str(indent.as_str());
-        res.push_str(test_function_title);
-        res.push_str(test_index.to_string().as_str());
-        res.push_str("(");
-
-        //加入所有的fuzzable变量
-        //第一个参数特殊处理
-        let first_param = self.fuzzable_params.first();
-        if let Some(first_param_) = first_param {
-            if self._is_fuzzable_need_mut_tag(0) {
-                res.push_str("mut ");
-            }
-            res.push_str(param_prefix);
-            res.push('0');
-            res.push_str(" :");
-            res.push_str(first_param_._to_type_string().as_str());
-        }
-
-        let param_size = self.fuzzable_params.len();
-        for i in 1..param_size {
-            res.push_str(" ,");
-            if self._is_fuzzable_need_mut_tag(i) {
-                res.push_str("mut ");
-            }
-            let param = &self.fuzzable_params[i];
-            res.push_str(param_prefix);
-            res.push_str(i.to_string().as_str());
-            res.push_str(" :");
-            res.push_str(param._to_type_string().as_str());
-        }
-        res.push_str(") ");
+    //所有的fuzzable变量作为测试函数的参数
+    pub(crate) fn _generate_function_params(&self, param_prefix: &str) -> Vec<Param> {
+        let mut res = Vec::new();
+        for (i, param) in self.fuzzable_params.iter().enumerate() {
+            let param_name = format!("{}{}", param_prefix, i);
+            let mut_tag = self._is_fuzzable_need_mut_tag(i);
+            res.push(Param::new(param_name.as_str(), mut_tag, param._to_type_string().as_str()));
+        }
         res
     }
 
-    pub(crate) fn _generate_function_body_string(
+    pub(crate) fn _generate_function_body(
         &self,
         _api_graph: &ApiGraph<'_>,
         cache: &Cache,
-        outer_indent: usize,
         param_prefix: &str,
         local_param_prefix: &str,
-    ) -> String {
-        let extra_indent = 4;
-        let mut res = String::new();
-        let body_indent = _generate_indent(outer_indent + extra_indent);
-
-        let dead_code = self._dead_code(_api_graph);
-
-        //api_calls
-        let api_calls_num = self.functions.len();
-        let full_name_map = &_api_graph.full_name_map;
-        for i in 0..api_calls_num {
-            let api_call = &self.functions[i];
-
-            //准备参数
-            let param_size = api_call.params.len();
-            let mut param_strings = Vec::new();
-            for j in 0..param_size {
-                let (param_type, index, call_type) = &api_call.params[j];
-                let call_type_array = call_type._split_at_unwrap_call_type();
-                //println!("call_type_array = {:?}",call_type_array);
-                let param_name = match param_type {
-                    ParamType::_FuzzableType => {
-                        let mut s1 = param_prefix.to_string();
-                        s1 += &(index.to_string());
-                        s1
-                    }
-                    ParamType::_FunctionReturn => {
-                        let mut s1 = local_param_prefix.to_string();
-                        s1 += &(index.to_string());
-                        s1
-                    }
-                };
-                let call_type_array_len = call_type_array.len();
-                if call_type_array_len == 1 {
-                    let call_type = &call_type_array[0];
-                    let param_string = call_type._to_call_string(&param_name, full_name_map, cache);
-                    param_strings.push(param_string);
-                } else {
-                    let mut former_param_name = param_name.clone();
-                    let mut helper_index = 1;
-                    let mut former_helper_line = String::new();
-                    for k in 0..call_type_array_len - 1 {
-                        let call_type = &call_type_array[k];
-                        let helper_name = format!(
-                            "{}{}_param{}_helper{}",
-                            local_param_prefix, i, j, helper_index
-                        );
-                        let helper_line = format!(
-                            "{}let mut {} = {};\n",
-                            body_indent,
-                            helper_name,
-                            call_type._to_call_string(&former_param_name, full_name_map, cache)
-                        );
-                        if helper_index > 1 {
-                            if !api_util::_need_mut_tag(call_type) {
-                                former_helper_line = former_helper_line.replace("let mut ", "let ");
-                            }
-                            res.push_str(former_helper_line.as_str());
-                        }
-                        helper_index = helper_index + 1;
-                        former_param_name = helper_name;
-                        former_helper_line = helper_line;
+    ) -> Block {
+        let ir = SequenceIr::lower(self, _api_graph, param_prefix, local_param_prefix);
+        let mut call_steps = Vec::new();
+        for i in 0..self.functions.len() {
+            call_steps.push(self._generate_call_steps(
+                _api_graph,
+                cache,
+                &ir,
+                i,
+                local_param_prefix,
+            ));
+        }
+        //每个调用本身是最后一个语句，插入guard之后位置会变化
+        let mut call_positions: Vec<usize> =
+            call_steps.iter().map(|steps| steps.len() - 1).collect();
+        //文档中写明范围的整数参数限制在范围内
+        for (i, api_call) in self.functions.iter().enumerate() {
+            let api_function = &_api_graph.api_functions[api_call.func.1];
+            let bounds = match _api_graph.api_param_bounds.get(&api_function.full_name) {
+                Some(bounds) => bounds,
+                None => continue,
+            };
+            if let Some(CallStep::Stmt(Stmt::Let { init, .. })) = call_steps[i].last_mut() {
+                if let Some(args) = _call_args_mut(init) {
+                    param_bounds::clamp_args(bounds, &api_function.inputs, args);
+                }
+            }
+        }
+        //调用之前检查文档中写明会导致panic的条件以及函数开头断言的条件，条件成立时直接返回
+        let panic_guards = _api_graph.cx.options.panic_policy.guards();
+        for (i, api_call) in self.functions.iter().enumerate() {
+            let full_name = &_api_graph.api_functions[api_call.func.1].full_name;
+            let mut guards = Vec::new();
+            if let Some(precondition) = _api_graph.api_panics.get(full_name) {
+                if panic_guards {
+                    guards.extend(precondition.guards.iter().cloned());
+                }
+            }
+            if let Some(assertion_guards) = _api_graph.api_assertions.get(full_name) {
+                guards.extend(assertion_guards.iter().cloned());
+            }
+            let guard = match call_steps[i].last() {
+                Some(CallStep::Stmt(Stmt::Let { init, .. })) => {
+                    _call_args(init).and_then(|args| panic_doc::guard_expr(&guards, &args))
+                }
+                _ => None,
+            };
+            if let Some(cond) = guard {
+                let mut then = Block::new();
+                then.push(Stmt::Return);
+                let call_index = call_steps[i].len() - 1;
+                call_steps[i].insert(call_index, CallStep::Stmt(Stmt::If { cond, then }));
+                call_positions[i] += 1;
+            }
+        }
+        //格式化的语句紧跟在调用之后，这样在match的分支中也能使用返回值
+        if let Some(format_harness) = &self._format_harness {
+            if let Some(value) = ir.binding(format_harness.call) {
+                let stmts = format_harness.to_stmts(value.name.as_str(), param_prefix);
+                call_steps[format_harness.call].extend(stmts.into_iter().map(CallStep::Stmt));
+            }
+        }
+        //比较的语句同样紧跟在调用之后，复用调用的参数
+        if let Some(differential) = &self._differential {
+            let args = match call_steps[differential.call].last() {
+                Some(CallStep::Stmt(Stmt::Let { init: Expr::Call { args, .. }, .. })) => {
+                    Some(args.clone())
+                }
+                _ => None,
+            };
+            if let (Some(value), Some(args)) = (ir.binding(differential.call), args) {
+                let stmts = differential.to_stmts(value.name.as_str(), &args, _api_graph);
+                call_steps[differential.call].extend(stmts.into_iter().map(CallStep::Stmt));
+            }
+        }
+        if let Some(round_trip) = &self._round_trip {
+            if let Some(value) = ir.binding(round_trip.call) {
+                let stmts = round_trip.to_stmts(value.name.as_str(), _api_graph);
+                call_steps[round_trip.call].extend(stmts.into_iter().map(CallStep::Stmt));
+            }
+        }
+        if let Some(operator_harness) = &self._operator_harness {
+            let stmts = operator_harness.to_stmts(&ir);
+            call_steps[operator_harness.call].extend(stmts.into_iter().map(CallStep::Stmt));
+        }
+        if let Some(drop_harness) = &self._drop_harness {
+            let stmts = drop_harness.to_stmts(&ir, param_prefix);
+            call_steps[drop_harness.call].extend(stmts.into_iter().map(CallStep::Stmt));
+        }
+        if let Some(contract_harness) = &self._contract_harness {
+            let stmts = contract_harness.to_stmts(&ir);
+            call_steps[contract_harness.call].extend(stmts.into_iter().map(CallStep::Stmt));
+        }
+        //检查返回值的断言紧跟在调用之后，返回值没有被使用时给它一个名字
+        let options = &_api_graph.cx.options;
+        for (i, position) in call_positions.iter().enumerate() {
+            let value = match ir.binding(i) {
+                Some(value) => value.name.clone(),
+                None => format!("{}{}_result", local_param_prefix, i),
+            };
+            let mut stmts = Vec::new();
+            if options.assert_buffer_bounds {
+                stmts.extend(self._buffer_bound_assertion(_api_graph, &ir, i, value.as_str()));
+            }
+            if options.check_invariants {
+                let api_call = &self.functions[i];
+                stmts.extend(invariant::invariant_stmts(_api_graph, api_call, &ir, value.as_str()));
+            }
+            if options.exercise_format {
+                let api_function = &_api_graph.api_functions[self.functions[i].func.1];
+                if let Some(output) = &api_function.output {
+                    let formats = format_harness::exercise_stmts(output, &value, _api_graph);
+                    stmts.extend(formats);
+                }
+            }
+            if options.exercise_clone && i == self.len() - 1 {
+                stmts.extend(clone_check::clone_stmts(self, _api_graph, &ir, value.as_str()));
+            }
+            if stmts.is_empty() {
+                continue;
+            }
+            if let CallStep::Stmt(Stmt::Let { pat, .. }) = &mut call_steps[i][*position] {
+                if ir.binding(i).is_none() {
+                    *pat = Pat::ident(value.as_str(), false);
+                    if self._drops_renamed_result(_api_graph, i) {
+                        let dropped =
+                            Expr::call(Expr::path("drop"), vec![Expr::path(value.as_str())]);
+                        stmts.push(Stmt::Expr(dropped));
                     }
-                    let last_call_type = call_type_array.last().unwrap();
-                    if !api_util::_need_mut_tag(last_call_type) {
-                        former_helper_line = former_helper_line.replace("let mut ", "let ");
+                }
+                call_steps[i].extend(stmts.into_iter().map(CallStep::Stmt));
+            }
+        }
+        //捕获每个调用以及unwrap参数的panic，panic之后继续执行不依赖于这个调用的后续调用。
+        //无法捕获的调用在生成的代码中注明原因
+        if _api_graph.cx.options.ignore_panics {
+            for (i, position) in call_positions.into_iter().enumerate() {
+                if let Some(reason) = self._uncaught_panic_reason(_api_graph, i) {
+                    let comment = format!("panics of the call are not caught, {}", reason);
+                    call_steps[i].insert(position, CallStep::Stmt(Stmt::Comment(comment)));
+                    continue;
+                }
+                for step in call_steps[i][..=position].iter_mut() {
+                    if let Some(caught_step) = _catch_unwind_step(step) {
+                        *step = caught_step;
                     }
-                    res.push_str(former_helper_line.as_str());
-                    let param_string =
-                        last_call_type._to_call_string(&former_param_name, full_name_map, cache);
-                    param_strings.push(param_string);
                 }
             }
-            res.push_str(body_indent.as_str());
-            //如果不是最后一个调用
-            let api_function_index = api_call.func.1;
-            let api_function = &_api_graph.api_functions[api_function_index];
-            if dead_code[i] || api_function._has_no_output() {
-                res.push_str("let _ = ");
-            } else {
-                let mut_tag = if self._is_function_need_mut_tag(i) { "mut " } else { "" };
-                res.push_str(format!("let {}{}{} = ", mut_tag, local_param_prefix, i).as_str());
+        }
+        let calls: Vec<usize> = (0..self.functions.len()).collect();
+        self._assemble_call_steps(&call_steps, &calls, 0)
+    }
+
+    //起了名字的返回值在原来`let _`的位置drop，不会延长它持有的借用。
+    //只drop拥有所有权并且不是Copy的值，引用和Copy的值drop没有作用
+    fn _drops_renamed_result(&self, _api_graph: &ApiGraph<'_>, index: usize) -> bool {
+        let api_function = &_api_graph.api_functions[self.functions[index].func.1];
+        match &api_function.output {
+            None | Some(clean::Type::BorrowedRef { .. }) | Some(clean::Type::RawPointer(..)) => {
+                false
             }
-            let (api_type, function_index) = &api_call.func;
-            match api_type {
-                ApiType::BareFunction => {
-                    let api_function_full_name =
-                        &_api_graph.api_functions[*function_index].full_name;
-                    res.push_str(api_function_full_name.as_str());
-                }
+            Some(ty @ clean::Type::Path { .. }) => !_api_graph.implements_trait(ty, sym::Copy),
+            Some(ty) => !api_util::_copy_type(ty),
+        }
+    }
+
+    //返回usize并且只写入一个缓冲区的调用，断言返回的长度不超过缓冲区的长度
+    fn _buffer_bound_assertion(
+        &self,
+        _api_graph: &ApiGraph<'_>,
+        ir: &SequenceIr,
+        i: usize,
+        written: &str,
+    ) -> Option<Stmt> {
+        let api_call = &self.functions[i];
+        let api_function = &_api_graph.api_functions[api_call.func.1];
+        if api_function.output != Some(clean::Type::Primitive(PrimitiveType::Usize)) {
+            return None;
+        }
+        let mut buffers = api_call.params.iter().filter(|(param_type, _, call_type)| {
+            matches!(param_type, ParamType::_FuzzableType)
+                && matches!(call_type, CallType::_ScratchBuffer(..))
+        });
+        let length = match (buffers.next(), buffers.next()) {
+            (Some((_, index, _)), None) => ir.value(&ValueSource::FuzzableParam(*index))?,
+            _ => return None,
+        };
+        let bound = api_util::_scratch_buffer_length(Expr::path(length.name.as_str()));
+        let message =
+            format!("{} reports more than the length of its buffer", api_function.full_name);
+        Some(Stmt::Expr(Expr::MacroCall {
+            name: "assert".to_string(),
+            args: vec![
+                Expr::binary(Expr::path(written), "<=", bound),
+                Expr::Lit(format!("{:?}", message)),
+            ],
+        }))
+    }
+
+    //async调用不能放到闭包中，返回值借用可变参数时，闭包无法返回这个借用，这两种调用的panic无法捕获
+    fn _uncaught_panic_reason(&self, _api_graph: &ApiGraph<'_>, i: usize) -> Option<&'static str> {
+        let api_function = &_api_graph.api_functions[self.functions[i].func.1];
+        if api_function._async_tag._is_async() {
+            Some("an async call can't be run in a closure")
+        } else if api_function._borrowed_inputs().iter().any(|(_, mutable, _)| *mutable) {
+            Some("its result mutably borrows an argument, which can't be returned from a closure")
+        } else {
+            None
+        }
+    }
+
+    //第i个调用的语句，包括准备参数的辅助变量以及调用本身
+    fn _generate_call_steps(
+        &self,
+        _api_graph: &ApiGraph<'_>,
+        cache: &Cache,
+        ir: &SequenceIr,
+        i: usize,
+        local_param_prefix: &str,
+    ) -> Vec<CallStep> {
+        let mut steps = Vec::new();
+        let full_name_map = &_api_graph.full_name_map;
+        let error_policy = _api_graph.cx.options.error_policy;
+        let api_call = &self.functions[i];
+
+        //准备参数
+        let param_size = api_call.params.len();
+        let mut param_exprs = Vec::new();
+        for j in 0..param_size {
+            let (param_type, index, call_type) = &api_call.params[j];
+            let call_type_array = call_type._split_at_unwrap_call_type();
+            //println!("call_type_array = {:?}",call_type_array);
+            let source = match param_type {
+                ParamType::_FuzzableType => ValueSource::FuzzableParam(*index),
+                ParamType::_FunctionReturn => ValueSource::CallResult(*index),
+            };
+            let param_name = match ir.value(&source) {
+                Some(value) => value.name.clone(),
+                None => format!("{}{}", local_param_prefix, index),
+            };
+            //unwrap之前的部分需要用辅助变量保存，每个辅助变量是否需要mut取决于下一次转换
+            let call_type_array_len = call_type_array.len();
+            let mut former_param = Expr::Path(param_name);
+            for k in 0..call_type_array_len - 1 {
+                let helper_name = format!("{}{}_param{}_helper{}", local_param_prefix, i, j, k + 1);
+                let helper_mut_tag = api_util::_need_mut_tag(&call_type_array[k + 1]);
+                let pat = Pat::ident(helper_name.as_str(), helper_mut_tag);
+                let step = match (&call_type_array[k], error_policy) {
+                    (CallType::_UnwrapResult(inner_), ErrorPolicy::Unwrap)
+                    | (CallType::_UnwrapOption(inner_), ErrorPolicy::Unwrap) => {
+                        let inner_expr = inner_._to_call_expr(former_param, full_name_map, cache);
+                        let init = Expr::method_call(inner_expr, "unwrap", Vec::new());
+                        CallStep::Stmt(Stmt::Let { pat, init })
+                    }
+                    (CallType::_UnwrapResult(inner_), ErrorPolicy::MatchBoth) => CallStep::Unwrap {
+                        pat,
+                        scrutinee: inner_._to_call_expr(former_param, full_name_map, cache),
+                        is_option: false,
+                    },
+                    (CallType::_UnwrapOption(inner_), ErrorPolicy::MatchBoth) => CallStep::Unwrap {
+                        pat,
+                        scrutinee: inner_._to_call_expr(former_param, full_name_map, cache),
+                        is_option: true,
+                    },
+                    (call_type_, _) => CallStep::Stmt(Stmt::Let {
+                        pat,
+                        init: call_type_._to_call_expr(former_param, full_name_map, cache),
+                    }),
+                };
+                steps.push(step);
+                former_param = Expr::Path(helper_name);
             }
-            res.push('(');
+            let last_call_type = call_type_array.last().unwrap();
+            param_exprs.push(last_call_type._to_call_expr(former_param, full_name_map, cache));
+        }
+        //返回值没有被使用或者没有返回值时，使用let _
+        let pat = match ir.binding(i) {
+            None => Pat::Wild,
+            Some(value) => Pat::ident(value.name.as_str(), value.mutable),
+        };
+        let (api_type, function_index) = &api_call.func;
+        let api_function = match api_type {
+            ApiType::BareFunction => &_api_graph.api_functions[*function_index],
+        };
+        //运算符trait的方法用运算符调用，例如a + b
+        let operator_call =
+            Operator::of(api_function).and_then(|operator| operator.to_expr(param_exprs.clone()));
+        let mut init = match operator_call {
+            Some(operator_call) => operator_call,
+            None => Expr::call(Expr::Path(api_function.full_name.clone()), param_exprs),
+        };
+        if api_function._async_tag._is_async() {
+            init = Expr::awaited(init);
+        }
+        steps.push(CallStep::Stmt(Stmt::Let { pat, init }));
+        steps
+    }
 
-            let param_size = param_strings.len();
-            for k in 0..param_size {
-                if k != 0 {
-                    res.push_str(" ,");
+    //把calls中的调用依次拼接起来，calls[0]从第first_step个语句开始。
+    //遇到需要match的unwrap时，之后的语句都放到Ok分支中，
+    //Err分支中继续执行不依赖于这个调用的后续调用
+    fn _assemble_call_steps(
+        &self,
+        call_steps: &Vec<Vec<CallStep>>,
+        calls: &[usize],
+        first_step: usize,
+    ) -> Block {
+        let mut block = Block::new();
+        let i = match calls.first() {
+            Some(i) => *i,
+            None => return block,
+        };
+        for (s, step) in call_steps[i].iter().enumerate().skip(first_step) {
+            match step {
+                CallStep::Stmt(stmt) => block.push(stmt.clone()),
+                CallStep::Unwrap { pat, scrutinee, is_option } => {
+                    let ok_block = self._assemble_call_steps(call_steps, calls, s + 1);
+                    let err_calls = self._calls_without_dependents(&calls[1..], i);
+                    let err_block = self._assemble_call_steps(call_steps, &err_calls, 0);
+                    let (ok_pat, err_pat) = if *is_option {
+                        (Pat::variant("Some", vec![pat.clone()]), Pat::variant("None", Vec::new()))
+                    } else {
+                        (
+                            Pat::variant("Ok", vec![pat.clone()]),
+                            Pat::variant("Err", vec![Pat::Wild]),
+                        )
+                    };
+                    block.push(Stmt::Match {
+                        scrutinee: scrutinee.clone(),
+                        arms: vec![(ok_pat, ok_block), (err_pat, err_block)],
+                    });
+                    return block;
                 }
+            }
+        }
+        block.stmts.extend(self._assemble_call_steps(call_steps, &calls[1..], 0).stmts);
+        block
+    }
 
-                let param_string = &param_strings[k];
-                res.push_str(param_string.as_str());
+    //去掉直接或间接使用了skipped_call返回值的调用
+    fn _calls_without_dependents(&self, calls: &[usize], skipped_call: usize) -> Vec<usize> {
+        let mut skipped = FxHashSet::default();
+        skipped.insert(skipped_call);
+        let mut res = Vec::new();
+        for call in calls {
+            let params = &self.functions[*call].params;
+            let depends_on_skipped = params.iter().any(|(param_type, index, _)| {
+                *param_type == ParamType::_FunctionReturn && skipped.contains(index)
+            });
+            if depends_on_skipped {
+                skipped.insert(*call);
+            } else {
+                res.push(*call);
             }
-            res.push_str(");\n");
         }
         res
     }
 }
 
-pub(crate) fn _generate_indent(indent_size: usize) -> String {
-    let mut indent = String::new();
-    for _ in 0..indent_size {
-        indent.push(' ');
+//a statement of a call. With `--error-policy=match-both`, an unwrap becomes a match, and the
+//statements after it are generated in both branches
+#[derive(Debug, Clone)]
+enum CallStep {
+    Stmt(Stmt),
+    Unwrap { pat: Pat, scrutinee: Expr, is_option: bool },
+}
+
+//the arguments of a call, awaited or written as an operator, in the order of the parameters
+fn _call_args(init: &Expr) -> Option<Vec<Expr>> {
+    match init {
+        Expr::Call { args, .. } => Some(args.clone()),
+        Expr::Await(call) => _call_args(call),
+        Expr::Binary { lhs, rhs, .. } => Some(vec![(**lhs).clone(), (**rhs).clone()]),
+        Expr::Unary { expr, .. } => Some(vec![(**expr).clone()]),
+        Expr::Ref { expr: box Expr::Index { expr, index }, .. } => {
+            Some(vec![(**expr).clone(), (**index).clone()])
+        }
+        _ => None,
+    }
+}
+
+//the arguments of `_call_args`, to be replaced
+fn _call_args_mut(init: &mut Expr) -> Option<Vec<&mut Expr>> {
+    match init {
+        Expr::Call { args, .. } => Some(args.iter_mut().collect()),
+        Expr::Await(call) => _call_args_mut(call),
+        Expr::Binary { lhs, rhs, .. } => Some(vec![&mut **lhs, &mut **rhs]),
+        Expr::Unary { expr, .. } => Some(vec![&mut **expr]),
+        Expr::Ref { expr: box Expr::Index { expr, index }, .. } => {
+            Some(vec![&mut **expr, &mut **index])
+        }
+        _ => None,
+    }
+}
+
+//match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| call)) { Ok(x) => .. }
+//the call itself, written as a function call or an operator (`&a[i]` for Index), and unwraps
+fn _catch_unwind_step(call_step: &CallStep) -> Option<CallStep> {
+    let (pat, init) = match call_step {
+        CallStep::Stmt(Stmt::Let {
+            pat,
+            init:
+                init @ (Expr::Call { .. }
+                | Expr::Binary { .. }
+                | Expr::Unary { .. }
+                | Expr::Ref { expr: box Expr::Index { .. }, .. }),
+        }) => (pat, init),
+        CallStep::Stmt(Stmt::Let { pat, init: init @ Expr::MethodCall { method, .. } })
+            if method == "unwrap" =>
+        {
+            (pat, init)
+        }
+        _ => return None,
+    };
+    let closure = Expr::Raw(format!("|| {}", init.to_code(0)));
+    let unwind_safe = Expr::call(Expr::path("std::panic::AssertUnwindSafe"), vec![closure]);
+    Some(CallStep::Unwrap {
+        pat: pat.clone(),
+        scrutinee: Expr::call(Expr::path("std::panic::catch_unwind"), vec![unwind_safe]),
+        is_option: false,
+    })
+}
+
+//run the future to completion on the chosen runtime
+fn _block_on(async_runtime: AsyncRuntime, future: Expr) -> Expr {
+    match async_runtime {
+        AsyncRuntime::Futures => {
+            Expr::call(Expr::path("futures::executor::block_on"), vec![future])
+        }
+        AsyncRuntime::Tokio => {
+            let builder =
+                Expr::call(Expr::path("tokio::runtime::Builder::new_current_thread"), Vec::new());
+            let builder = Expr::method_call(builder, "enable_all", Vec::new());
+            let runtime = Expr::method_call(builder, "build", Vec::new());
+            let runtime = Expr::method_call(runtime, "unwrap", Vec::new());
+            Expr::method_call(runtime, "block_on", vec![future])
+        }
     }
-    indent
 }
@@ -0,0 +1,140 @@
+//Balance how many targets each api appears in. The selection is greedy on coverage, so a hub
+//api (e.g. a constructor that every other api depends on) ends up in almost every target.
+//A cap limits the targets of each api, and an optional minimum adds targets for the apis that
+//are reachable but called by too few of the chosen ones. The variants and the splits of runtime
+//borrows come after the selection and call the same apis again, so the cap is applied once more
+//to the targets that are written.
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::ApiSequence;
+use rustc_data_structures::fx::FxHashMap;
+use std::collections::BTreeMap;
+
+pub(crate) fn balance(
+    api_graph: &ApiGraph<'_>,
+    chosen_sequences: Vec<ApiSequence>,
+    max_size: usize,
+) -> Vec<ApiSequence> {
+    let max_targets = api_graph.cx.options.max_targets_per_api;
+    let min_targets = api_graph.cx.options.min_targets_per_api;
+    let mut usage = FxHashMap::default();
+    let mut res = Vec::new();
+
+    for sequence in chosen_sequences {
+        if res.len() >= max_size {
+            break;
+        }
+        if admits(&sequence, &usage, max_targets, min_targets) {
+            record(&sequence, &mut usage);
+            res.push(sequence);
+        }
+    }
+
+    //reachable apis below the minimum get the shortest sequences that call them
+    let mut candidates: Vec<&ApiSequence> = api_graph
+        .api_sequences
+        .iter()
+        .filter(|sequence| is_valid_target(sequence, api_graph))
+        .collect();
+    candidates.sort_by_key(|sequence| sequence.len());
+    let min_targets = match min_targets {
+        Some(min_targets) => min_targets,
+        None => return res,
+    };
+    for api_index in 0..api_graph.api_functions.len() {
+        for sequence in &candidates {
+            if res.len() >= max_size || usage_of(&usage, api_index) >= min_targets {
+                break;
+            }
+            if !sequence._contains_api_function(api_index) || res.contains(*sequence) {
+                continue;
+            }
+            if admits(sequence, &usage, max_targets, Some(min_targets)) {
+                record(sequence, &mut usage);
+                res.push((*sequence).clone());
+            }
+        }
+    }
+    res
+}
+
+//drop the targets exceeding the cap, in order, after the variants are added
+pub(crate) fn cap(api_graph: &ApiGraph<'_>, sequences: Vec<ApiSequence>) -> Vec<ApiSequence> {
+    let max_targets = api_graph.cx.options.max_targets_per_api;
+    let min_targets = api_graph.cx.options.min_targets_per_api;
+    if max_targets.is_none() {
+        return sequences;
+    }
+    let total_number = sequences.len();
+    let mut usage = FxHashMap::default();
+    let mut res = Vec::new();
+    for sequence in sequences {
+        if admits(&sequence, &usage, max_targets, min_targets) {
+            record(&sequence, &mut usage);
+            res.push(sequence);
+        }
+    }
+    if res.len() < total_number {
+        println!("{} targets exceed the cap of their apis", total_number - res.len());
+    }
+    res
+}
+
+//the cap is only exceeded by a sequence that brings an api still below the minimum
+fn admits(
+    sequence: &ApiSequence,
+    usage: &FxHashMap<usize, usize>,
+    max_targets: Option<usize>,
+    min_targets: Option<usize>,
+) -> bool {
+    let api_indexes = sequence._get_contained_api_functions();
+    if let Some(min_targets) = min_targets {
+        if api_indexes.iter().any(|api_index| usage_of(usage, *api_index) < min_targets) {
+            return true;
+        }
+    }
+    match max_targets {
+        None => true,
+        Some(max_targets) => {
+            api_indexes.iter().all(|api_index| usage_of(usage, *api_index) < max_targets)
+        }
+    }
+}
+
+//same condition as the heuristic selection
+fn is_valid_target(sequence: &ApiSequence, api_graph: &ApiGraph<'_>) -> bool {
+    !sequence._has_no_fuzzables() && !sequence._contains_dead_code_except_last_one(api_graph)
+}
+
+fn record(sequence: &ApiSequence, usage: &mut FxHashMap<usize, usize>) {
+    for api_index in sequence._get_contained_api_functions() {
+        *usage.entry(api_index).or_insert(0) += 1;
+    }
+}
+
+fn usage_of(usage: &FxHashMap<usize, usize>, api_index: usize) -> usize {
+    usage.get(&api_index).cloned().unwrap_or(0)
+}
+
+//number of apis by the number of targets they appear in
+pub(crate) fn histogram(
+    api_graph: &ApiGraph<'_>,
+    sequences: &Vec<ApiSequence>,
+) -> BTreeMap<usize, usize> {
+    let mut usage = FxHashMap::default();
+    for sequence in sequences {
+        record(sequence, &mut usage);
+    }
+    let mut res = BTreeMap::new();
+    for api_index in 0..api_graph.api_functions.len() {
+        *res.entry(usage_of(&usage, api_index)).or_insert(0) += 1;
+    }
+    res
+}
+
+pub(crate) fn print_histogram(histogram: &BTreeMap<usize, usize>) {
+    println!("-----------API USAGE-----------");
+    for (target_number, api_number) in histogram {
+        println!("{} apis appear in {} targets", api_number, target_number);
+    }
+    println!("-------------------------------");
+}
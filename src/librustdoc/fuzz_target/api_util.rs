@@ -1,10 +1,33 @@
 use crate::clean::{self, types::PrimitiveType};
 use crate::formats::cache::Cache;
 use crate::fuzz_target::call_type::CallType;
-use crate::fuzz_target::fuzzable_type::{self, FuzzableCallType};
+use crate::fuzz_target::code_ast::Expr;
+use crate::fuzz_target::conversion;
+use crate::fuzz_target::fuzzable_type::{self, Collection, FuzzableCallType, FuzzableVariant};
 use crate::fuzz_target::impl_util::FullNameMap;
-use crate::fuzz_target::prelude_type::{self, PreludeType};
+use crate::fuzz_target::local_type::StructDefinition;
+use crate::fuzz_target::prelude_type::{self, PreludeType, Wrapper};
 use rustc_hir::{self, Mutability};
+use rustc_span::symbol::{Symbol, kw};
+
+//迭代器参数最多产生的元素个数，元素从输入的切片中依次取出。需要转换元素的切片和集合也一样
+pub(crate) static MAX_ITERATOR_ELEMENTS: usize = 64;
+//写入结果的缓冲区最大的长度，长度从输入中的u16得到
+pub(crate) static MAX_SCRATCH_BUFFER_LENGTH: usize = 4096;
+//std::io::Cursor<Vec<u8>>和Vec<u8>实现的trait，只有这些约束的参数可以用它们构造
+static IO_BOUNDS: [&'static str; 9] =
+    ["Read", "BufRead", "Write", "Seek", "Send", "Sync", "Unpin", "Sized", "Debug"];
+
+//`impl Read`, `impl BufRead`和`impl Write`参数的构造方式
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub(crate) enum IoAdapter {
+    //从输入的字节构造的Cursor<Vec<u8>>
+    Reader,
+    //空的Vec<u8>，写入的内容被丢弃
+    Writer,
+    //还需要Seek的writer，用空的Cursor<Vec<u8>>
+    SeekableWriter,
+}
 
 pub(crate) fn _extract_input_types(inputs: &clean::Arguments) -> Vec<clean::Type> {
     /* let mut input_types = Vec::new();
@@ -23,6 +46,15 @@ pub(crate) fn _extract_output_type(output: &clean::FnRetTy) -> Option<clean::Typ
     }
 }
 
+//浮点数以及浮点数的引用
+pub(crate) fn _is_float_type(ty: &clean::Type) -> bool {
+    match ty {
+        clean::Type::Primitive(PrimitiveType::F32 | PrimitiveType::F64) => true,
+        clean::Type::BorrowedRef { type_, .. } => _is_float_type(type_),
+        _ => false,
+    }
+}
+
 pub(crate) fn _is_generic_type(ty: &clean::Type) -> bool {
     //TODO：self不需要考虑，因为在产生api function的时候就已经完成转换，但需要考虑类型嵌套的情况
     match ty {
@@ -177,7 +209,13 @@ pub(crate) fn _same_type(
     cache: &Cache
 ) -> CallType {
     if hard_mode {
-        _same_type_hard_mode(output_type, input_type, full_name_map, cache)
+        match _same_type_hard_mode(output_type, input_type, full_name_map, cache) {
+            //类型不同时，尝试通过std的转换trait转换
+            CallType::_NotCompatible => {
+                conversion::conversion_call_type(output_type, input_type, cache)
+            }
+            call_type => call_type,
+        }
     } else {
         //TODO:soft mode
         CallType::_NotCompatible
@@ -254,9 +292,8 @@ pub(crate) fn _same_type_hard_mode(
             //TODO:有需要的时候再考虑
             CallType::_NotCompatible
         }
-        clean::Type::ImplTrait(_) => {
-            //TODO:有需要的时候在考虑
-            CallType::_NotCompatible
+        clean::Type::ImplTrait(bounds) => {
+            _same_type_impl_trait(bounds, input_type, full_name_map, cache)
         }
         _ => {
             unimplemented!();
@@ -264,6 +301,60 @@ pub(crate) fn _same_type_hard_mode(
     }
 }
 
+//输出类型是`impl Trait`的情况，只知道不透明类型的约束。可以交给约束都被满足的`impl Trait`参数，
+//迭代器的元素类型要相同；迭代器还可以collect成Vec，或者collect之后借用为切片
+fn _same_type_impl_trait(
+    bounds: &Vec<clean::GenericBound>,
+    input_type: &clean::Type,
+    full_name_map: &FullNameMap,
+    cache: &Cache,
+) -> CallType {
+    let item = _iterator_item_type(bounds);
+    let same_item = |element: &clean::Type| match &item {
+        Some(item) => {
+            _same_type_hard_mode(item, element, full_name_map, cache) == CallType::_DirectCall
+        }
+        None => false,
+    };
+    match input_type {
+        clean::Type::ImplTrait(input_bounds) => {
+            let output_traits = _bound_trait_names(bounds);
+            let satisfied = _bound_trait_names(input_bounds).iter().all(|trait_| {
+                output_traits.contains(trait_)
+                    || (trait_.as_str() == "IntoIterator" && item.is_some())
+            });
+            let same_items = match _iterator_item_type(input_bounds) {
+                Some(input_item) => same_item(&input_item),
+                None => true,
+            };
+            if satisfied && same_items { CallType::_DirectCall } else { CallType::_NotCompatible }
+        }
+        clean::Type::Slice(element) if same_item(element) => {
+            CallType::_CollectIter(Box::new(CallType::_DirectCall))
+        }
+        clean::Type::Path { path } if path.last().as_str() == "Vec" => {
+            match path.generics().and_then(|args| args.first().cloned().cloned()) {
+                Some(element) if same_item(&element) => {
+                    CallType::_CollectIter(Box::new(CallType::_DirectCall))
+                }
+                _ => CallType::_NotCompatible,
+            }
+        }
+        _ => CallType::_NotCompatible,
+    }
+}
+
+//约束中trait的名字，和_io_adapter一样只看名字
+fn _bound_trait_names(bounds: &Vec<clean::GenericBound>) -> Vec<Symbol> {
+    bounds
+        .iter()
+        .filter_map(|bound| match bound {
+            clean::GenericBound::TraitBound(poly_trait, _) => Some(poly_trait.trait_.last()),
+            clean::GenericBound::Outlives(_) => None,
+        })
+        .collect()
+}
+
 //test if types are the same type
 //输出类型是Path的情况
 fn _same_type_resolved_path(
@@ -451,6 +542,12 @@ pub(crate) fn _borrowed_ref_in_same_type(
     cache: &Cache
 ) -> CallType {
     let inner_type = &**type_;
+    //借用Box、Rc等类型里面的值，&*x
+    if let Some(deref_call_type) =
+        _deref_wrapper_call_type(mutability, inner_type, output_type, full_name_map, cache)
+    {
+        return deref_call_type;
+    }
     let inner_compatible = _same_type_hard_mode(output_type, inner_type, full_name_map, cache);
     match &inner_compatible {
         CallType::_NotCompatible => {
@@ -467,6 +564,36 @@ pub(crate) fn _borrowed_ref_in_same_type(
     }
 }
 
+//输出类型是Box、Rc、Arc或者Cow，输入类型是里面的值的引用。只有Box可以可变借用
+fn _deref_wrapper_call_type(
+    mutability: &Mutability,
+    inner_type: &clean::Type,
+    output_type: &clean::Type,
+    full_name_map: &FullNameMap,
+    cache: &Cache,
+) -> Option<CallType> {
+    let (wrapper, wrapped_type) = match PreludeType::from_type(output_type, full_name_map, cache) {
+        PreludeType::PreludeWrapper(wrapper, wrapped_type) if wrapper.derefs() => {
+            (wrapper, wrapped_type)
+        }
+        _ => return None,
+    };
+    //Cow<B>里面是&B
+    let wrapped_type = match (wrapper, wrapped_type) {
+        (Wrapper::Cow, clean::Type::BorrowedRef { type_, .. }) => *type_,
+        (_, wrapped_type) => wrapped_type,
+    };
+    if wrapped_type != *inner_type {
+        return None;
+    }
+    let deref = Box::new(CallType::_Deref(Box::new(CallType::_DirectCall)));
+    match mutability {
+        Mutability::Mut if wrapper == Wrapper::Box => Some(CallType::_MutBorrowedRef(deref)),
+        Mutability::Mut => Some(CallType::_NotCompatible),
+        Mutability::Not => Some(CallType::_BorrowedRef(deref)),
+    }
+}
+
 //处理输入类型是裸指针的情况
 pub(crate) fn _raw_pointer_in_same_type(
     mutability: &Mutability,
@@ -601,6 +728,174 @@ pub(crate) fn _move_condition(input_type: &clean::Type, call_type: &CallType) ->
     return false;
 }
 
+//`impl Iterator<Item = T>`或`impl IntoIterator<Item = T>`的元素类型T，这样的参数用从输入中得到的元素构造
+pub(crate) fn _iterator_item_type(bounds: &Vec<clean::GenericBound>) -> Option<clean::Type> {
+    bounds.iter().find_map(|bound| {
+        let trait_ = match bound {
+            clean::GenericBound::TraitBound(poly_trait, _) => &poly_trait.trait_,
+            clean::GenericBound::Outlives(_) => return None,
+        };
+        if !["Iterator", "IntoIterator"].contains(&trait_.last().as_str()) {
+            return None;
+        }
+        trait_.bindings()?.iter().find_map(|binding| match &binding.kind {
+            clean::TypeBindingKind::Equality { term } if binding.assoc.name.as_str() == "Item" => {
+                term.ty().cloned()
+            }
+            _ => None,
+        })
+    })
+}
+
+//只有std::io的约束的`impl Trait`由输入的字节或者空的缓冲区构造，例如`impl Read`, `impl BufRead + Seek`
+//和`impl Write`。和_trait_bound_substitute一样只看trait的名字
+pub(crate) fn _io_adapter(bounds: &Vec<clean::GenericBound>) -> Option<IoAdapter> {
+    let mut traits = Vec::new();
+    for bound in bounds {
+        match bound {
+            clean::GenericBound::TraitBound(poly_trait, _) => {
+                let name = poly_trait.trait_.last();
+                if !IO_BOUNDS.contains(&name.as_str()) {
+                    return None;
+                }
+                traits.push(name);
+            }
+            clean::GenericBound::Outlives(_) => {}
+        }
+    }
+    let has_trait = |name: &str| traits.iter().any(|trait_| trait_.as_str() == name);
+    if has_trait("Read") || has_trait("BufRead") {
+        Some(IoAdapter::Reader)
+    } else if has_trait("Write") && has_trait("Seek") {
+        Some(IoAdapter::SeekableWriter)
+    } else if has_trait("Write") {
+        Some(IoAdapter::Writer)
+    } else {
+        None
+    }
+}
+
+//reader读的是输入的字节，writer不用输入
+pub(crate) fn _io_adapter_expr(bytes: Expr, adapter: IoAdapter) -> Expr {
+    let empty_vec = Expr::call(Expr::path("Vec::<u8>::new"), Vec::new());
+    match adapter {
+        IoAdapter::Reader => {
+            let vec = Expr::method_call(bytes, "to_vec", Vec::new());
+            Expr::call(Expr::path("std::io::Cursor::new"), vec![vec])
+        }
+        IoAdapter::Writer => empty_vec,
+        IoAdapter::SeekableWriter => {
+            Expr::call(Expr::path("std::io::Cursor::new"), vec![empty_vec])
+        }
+    }
+}
+
+//类型中是否用到了类型参数name，QPath和impl Trait保守地认为用到了
+pub(crate) fn _mentions_generic(ty: &clean::Type, name: Symbol) -> bool {
+    match ty {
+        clean::Type::Generic(generic) => *generic == name,
+        clean::Type::Path { path } => path.segments.iter().any(|segment| match &segment.args {
+            clean::GenericArgs::AngleBracketed { args, bindings } => {
+                args.iter().any(|arg| match arg {
+                    clean::GenericArg::Type(inner_ty) => _mentions_generic(inner_ty, name),
+                    _ => false,
+                }) || bindings.iter().any(|binding| match &binding.kind {
+                    clean::TypeBindingKind::Equality { term } => {
+                        term.ty().map_or(false, |inner_ty| _mentions_generic(inner_ty, name))
+                    }
+                    clean::TypeBindingKind::Constraint { .. } => true,
+                })
+            }
+            clean::GenericArgs::Parenthesized { inputs, output } => {
+                inputs.iter().any(|input| _mentions_generic(input, name))
+                    || output.as_ref().map_or(false, |output| _mentions_generic(output, name))
+            }
+        }),
+        clean::Type::Tuple(types) => types.iter().any(|inner_ty| _mentions_generic(inner_ty, name)),
+        clean::Type::Slice(type_)
+        | clean::Type::Array(type_, ..)
+        | clean::Type::RawPointer(_, type_)
+        | clean::Type::BorrowedRef { type_, .. } => _mentions_generic(type_, name),
+        clean::Type::QPath(..) | clean::Type::ImplTrait(..) => true,
+        _ => false,
+    }
+}
+
+//把从输入中得到的切片转换为参数需要的迭代器，元素个数不超过MAX_ITERATOR_ELEMENTS。
+//按值迭代时先复制到Vec中再into_iter
+pub(crate) fn _slice_to_iterator(slice: Expr, by_ref: bool) -> Expr {
+    let iter = Expr::method_call(slice, "iter", Vec::new());
+    let limit = Expr::Lit(MAX_ITERATOR_ELEMENTS.to_string());
+    let taken = Expr::method_call(iter, "take", vec![limit]);
+    if by_ref {
+        return taken;
+    }
+    let cloned = Expr::method_call(taken, "cloned", Vec::new());
+    let collected = Expr::method_call(cloned, "collect::<Vec<_>>", Vec::new());
+    Expr::method_call(collected, "into_iter", Vec::new())
+}
+
+//转换切片元素时元素的表达式，元素是复制出来的
+pub(crate) fn _slice_element() -> Expr {
+    Expr::deref(Expr::path("_element"))
+}
+
+//切片的元素需要转换时，逐个转换后收集到Vec中再借用，元素个数不超过MAX_ITERATOR_ELEMENTS。
+//`&x.iter().take(64).map(|_element| Some(*_element)).collect::<Vec<_>>()`
+pub(crate) fn _collect_converted_slice(slice: Expr, element: Expr) -> Expr {
+    let iter = _slice_to_iterator(slice, true);
+    let convert = Expr::Raw(format!("|_element| {}", element.to_code(0)));
+    let mapped = Expr::method_call(iter, "map", vec![convert]);
+    let collected = Expr::method_call(mapped, "collect::<Vec<_>>", Vec::new());
+    Expr::reference(false, collected)
+}
+
+//从输入的切片构造集合，元素个数不超过MAX_ITERATOR_ELEMENTS，重复的元素只保留一个。
+//map的元素是key和value
+pub(crate) fn _slice_to_collection(
+    slice: Expr,
+    collection: Collection,
+    elements: Vec<Expr>,
+) -> Expr {
+    let element_codes: Vec<String> = elements.iter().map(|element| element.to_code(0)).collect();
+    let element = if element_codes.len() == 1 {
+        element_codes[0].clone()
+    } else {
+        format!("({})", element_codes.join(", "))
+    };
+    let iter = _slice_to_iterator(slice, true);
+    let convert = Expr::Raw(format!("|_element| {}", element));
+    let mapped = Expr::method_call(iter, "map", vec![convert]);
+    Expr::method_call(mapped, format!("collect::<{}>", collection.type_name()).as_str(), Vec::new())
+}
+
+//String的集合每行是一个元素，map的一行用第一个`=`分成key和value
+pub(crate) fn _text_to_collection(text: Expr, collection: Collection) -> Expr {
+    let lines = Expr::method_call(text, "lines", Vec::new());
+    let limit = Expr::Lit(MAX_ITERATOR_ELEMENTS.to_string());
+    let taken = Expr::method_call(lines, "take", vec![limit]);
+    let convert = if collection.is_map() {
+        "|_line| { let mut _parts = _line.splitn(2, '='); \
+         (_parts.next().unwrap_or(\"\").to_string(), _parts.next().unwrap_or(\"\").to_string()) }"
+    } else {
+        "|_line| _line.to_string()"
+    };
+    let mapped = Expr::method_call(taken, "map", vec![Expr::Raw(convert.to_string())]);
+    Expr::method_call(mapped, format!("collect::<{}>", collection.type_name()).as_str(), Vec::new())
+}
+
+//缓冲区的长度，不超过MAX_SCRATCH_BUFFER_LENGTH。`(x as usize).min(4096)`
+pub(crate) fn _scratch_buffer_length(length: Expr) -> Expr {
+    let limit = Expr::Lit(MAX_SCRATCH_BUFFER_LENGTH.to_string());
+    Expr::method_call(Expr::cast(length, "usize"), "min", vec![limit])
+}
+
+//写入结果的缓冲区，元素都是默认值。`&mut vec![u8::default(); (x as usize).min(4096)][..]`
+pub(crate) fn _scratch_buffer_expr(length: Expr, element: PrimitiveType) -> Expr {
+    let length = _scratch_buffer_length(length);
+    Expr::Raw(format!("&mut vec![{}::default(); {}][..]", element.as_sym(), length.to_code(0)))
+}
+
 pub(crate) fn is_fuzzable_type(ty_: &clean::Type, full_name_map: &FullNameMap, cache: &Cache) -> bool {
     let fuzzable = fuzzable_type::fuzzable_call_type(ty_, full_name_map, cache);
     match fuzzable {
@@ -609,6 +904,50 @@ pub(crate) fn is_fuzzable_type(ty_: &clean::Type, full_name_map: &FullNameMap, c
     }
 }
 
+//字段都是public且fuzzable的struct，直接用struct字面量构造，不需要图中有返回这个struct的api。
+//字段中的struct递归构造，超过最大层数的struct（包括递归定义的struct）不会被构造。
+//`#[non_exhaustive]`的struct在别的crate中不能用字面量构造，只能由图中返回它的api产生
+pub(crate) fn construct_struct(
+    struct_definition: &StructDefinition,
+    full_name_map: &FullNameMap,
+    cache: &Cache,
+    depth: usize,
+) -> FuzzableCallType {
+    if struct_definition.non_exhaustive || depth >= full_name_map.max_struct_depth {
+        return FuzzableCallType::NoFuzzable;
+    }
+    let mut fields = Vec::new();
+    for field in &struct_definition.fields {
+        let field_call_type =
+            fuzzable_type::fuzzable_call_type_at_depth(field, full_name_map, cache, depth + 1);
+        if field_call_type == FuzzableCallType::NoFuzzable {
+            return FuzzableCallType::NoFuzzable;
+        }
+        fields.push(field_call_type);
+    }
+    FuzzableCallType::Struct(FuzzableVariant {
+        name: struct_definition.full_name.clone(),
+        kind: struct_definition.kind.clone(),
+        fields,
+    })
+}
+
+//参数类型（或者它引用的类型）是`#[non_exhaustive]`的struct时，返回struct的名字
+pub(crate) fn non_exhaustive_struct<'a>(
+    ty: &clean::Type,
+    full_name_map: &'a FullNameMap,
+) -> Option<&'a String> {
+    match ty {
+        clean::Type::BorrowedRef { type_, .. } => non_exhaustive_struct(type_, full_name_map),
+        clean::Type::Path { path } => {
+            let structs = &full_name_map.local_types.structs;
+            let struct_definition = structs.get(&path.res.opt_def_id()?)?;
+            struct_definition.non_exhaustive.then(|| &struct_definition.full_name)
+        }
+        _ => None,
+    }
+}
+
 pub(crate) fn _is_mutable_borrow_occurs(input_type_: &clean::Type, call_type: &CallType) -> bool {
     //TODO:暂时先这样处理，后面等调整了result处理的逻辑再进行处理
     if call_type._contains_move_call_type() {
@@ -640,7 +979,8 @@ pub(crate) fn _is_immutable_borrow_occurs(input_type: &clean::Type, call_type: &
                 match call_type {
                     CallType::_DirectCall
                     | CallType::_BorrowedRef(..)
-                    | CallType::_ConstRawPointer(..) => {
+                    | CallType::_ConstRawPointer(..)
+                    | CallType::_AsRef(..) => {
                         return true;
                     }
                     _ => {}
@@ -652,6 +992,87 @@ pub(crate) fn _is_immutable_borrow_occurs(input_type: &clean::Type, call_type: &
     return false;
 }
 
+//返回值中是否含有引用，含有引用的返回值会借用输入的参数
+pub(crate) fn _contains_reference(ty: &clean::Type) -> bool {
+    match ty {
+        clean::Type::BorrowedRef { .. } => true,
+        clean::Type::Path { path } => {
+            for segment in &path.segments {
+                match &segment.args {
+                    clean::GenericArgs::AngleBracketed { args, .. } => {
+                        for generic_arg in args.iter() {
+                            match generic_arg {
+                                clean::GenericArg::Lifetime(..) => return true,
+                                clean::GenericArg::Type(inner_ty) => {
+                                    if _contains_reference(inner_ty) {
+                                        return true;
+                                    }
+                                }
+                                clean::GenericArg::Const(..) | clean::GenericArg::Infer => {}
+                            }
+                        }
+                    }
+                    clean::GenericArgs::Parenthesized { .. } => {}
+                }
+            }
+            false
+        }
+        clean::Type::Tuple(types) => types.iter().any(|ty_| _contains_reference(ty_)),
+        clean::Type::Slice(type_) | clean::Type::Array(type_, ..) => _contains_reference(type_),
+        _ => false,
+    }
+}
+
+//类型中出现的生命周期，省略的生命周期(包括`'_`)是None
+pub(crate) fn _type_lifetimes(ty: &clean::Type) -> Vec<Option<Symbol>> {
+    let mut res = Vec::new();
+    match ty {
+        clean::Type::BorrowedRef { lifetime, type_, .. } => {
+            res.push(lifetime.as_ref().and_then(_lifetime_name));
+            res.extend(_type_lifetimes(type_));
+        }
+        clean::Type::Path { path } => {
+            for segment in &path.segments {
+                if let clean::GenericArgs::AngleBracketed { args, .. } = &segment.args {
+                    for generic_arg in args.iter() {
+                        match generic_arg {
+                            clean::GenericArg::Lifetime(lifetime) => {
+                                res.push(_lifetime_name(lifetime))
+                            }
+                            clean::GenericArg::Type(inner_ty) => {
+                                res.extend(_type_lifetimes(inner_ty))
+                            }
+                            clean::GenericArg::Const(..) | clean::GenericArg::Infer => {}
+                        }
+                    }
+                }
+            }
+        }
+        clean::Type::Tuple(types) => {
+            for ty_ in types {
+                res.extend(_type_lifetimes(ty_));
+            }
+        }
+        clean::Type::Slice(type_)
+        | clean::Type::Array(type_, ..)
+        | clean::Type::RawPointer(_, type_) => res.extend(_type_lifetimes(type_)),
+        _ => {}
+    }
+    res
+}
+
+pub(crate) fn _lifetime_name(lifetime: &clean::Lifetime) -> Option<Symbol> {
+    if lifetime.0 == kw::UnderscoreLifetime { None } else { Some(lifetime.0) }
+}
+
+//使用一个变量时是否需要独占它：move或者可变借用。独占使用会使之前对它的所有借用失效，
+//共享使用只会使之前的可变借用失效
+pub(crate) fn _is_exclusive_use(input_type: &clean::Type, call_type: &CallType) -> bool {
+    _move_condition(input_type, call_type)
+        || _is_mutable_borrow_occurs(input_type, call_type)
+        || _need_mut_tag(call_type)
+}
+
 pub(crate) fn _need_mut_tag(call_type: &CallType) -> bool {
     match call_type {
         CallType::_MutBorrowedRef(..) | CallType::_MutRawPointer(..) => true,
@@ -0,0 +1,150 @@
+//Preconditions asserted at the top of the apis. An api starting with `assert!(n > 0)` or
+//`debug_assert!(index < len)` rejects most random arguments with a panic before it does any work,
+//and every such panic is reported as a crash. The conditions of the leading assertions that only
+//use the parameters and what users can name, e.g. no private method or field, are turned into
+//guards returning before the call when the assertion would fail, like the guards of documented
+//panics. With `--no-assert-guards` the calls are not guarded, so that the assertions themselves
+//are fuzzed.
+use crate::TyCtxt;
+use crate::fuzz_target::panic_doc::{self, GuardPiece};
+use rustc_hir as hir;
+use rustc_hir::def::Res;
+use rustc_hir::intravisit::{self, Visitor};
+use rustc_middle::ty::TypeckResults;
+use rustc_span::def_id::DefId;
+use rustc_span::hygiene::{ExpnKind, MacroKind};
+
+//the guards of the assertions before the first other statement of the body
+pub(crate) fn leading_assertions(
+    tcx: TyCtxt<'_>,
+    def_id: DefId,
+    param_names: &Vec<String>,
+) -> Vec<Vec<GuardPiece>> {
+    let body_id = def_id.as_local().and_then(|def_id| tcx.hir().maybe_body_owned_by(def_id));
+    let body_id = match body_id {
+        Some(body_id) => body_id,
+        None => return Vec::new(),
+    };
+    let block = match tcx.hir().body(body_id).value.kind {
+        hir::ExprKind::Block(block, _) => block,
+        _ => return Vec::new(),
+    };
+    let typeck_results = tcx.typeck_body(body_id);
+    let mut res = Vec::new();
+    for stmt in block.stmts {
+        let expr = match stmt.kind {
+            hir::StmtKind::Semi(expr) | hir::StmtKind::Expr(expr) => expr,
+            _ => break,
+        };
+        let expn_data = expr.span.ctxt().outer_expn_data();
+        let is_assertion = match expn_data.kind {
+            ExpnKind::Macro(MacroKind::Bang, name) => {
+                matches!(name.as_str(), "assert" | "debug_assert")
+            }
+            _ => false,
+        };
+        if !is_assertion {
+            break;
+        }
+        //`assert!(cond)` is expanded to `if !cond { panic }`
+        let asserted = match expr.kind {
+            hir::ExprKind::If(cond, ..) => cond,
+            _ => expr,
+        };
+        let mut finder = PrivateUseFinder { tcx, typeck_results, found: false };
+        finder.visit_expr(asserted);
+        if finder.found {
+            continue;
+        }
+        let condition = tcx
+            .sess
+            .source_map()
+            .span_to_snippet(expn_data.call_site)
+            .ok()
+            .and_then(|snippet| asserted_condition(&snippet));
+        let guard = condition
+            .and_then(|condition| panic_doc::guard_pieces(condition.as_str(), param_names));
+        //the guard holds when the assertion fails
+        if let Some(guard) = guard.filter(|guard| !reads_field(guard)) {
+            let mut negated = vec![GuardPiece::Code("!(".to_string())];
+            negated.extend(guard);
+            negated.push(GuardPiece::Code(")".to_string()));
+            res.push(negated);
+        }
+    }
+    res
+}
+
+//the first argument of `assert!(cond, "message", ..)`
+fn asserted_condition(snippet: &str) -> Option<String> {
+    let args_start = snippet.find(|c| c == '(' || c == '[' || c == '{')? + 1;
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in snippet[args_start..].char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' if depth == 0 => {
+                return Some(snippet[args_start..args_start + i].trim().to_string());
+            }
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                return Some(snippet[args_start..args_start + i].trim().to_string());
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+//methods, functions and constants of the crate that users can't name, e.g. `self.check()`
+struct PrivateUseFinder<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    typeck_results: &'tcx TypeckResults<'tcx>,
+    found: bool,
+}
+
+impl<'tcx> Visitor<'tcx> for PrivateUseFinder<'tcx> {
+    fn visit_expr(&mut self, ex: &'tcx hir::Expr<'tcx>) {
+        let def_id = match &ex.kind {
+            hir::ExprKind::MethodCall(..) => self.typeck_results.type_dependent_def_id(ex.hir_id),
+            hir::ExprKind::Path(qpath) => match self.typeck_results.qpath_res(qpath, ex.hir_id) {
+                Res::Def(_, def_id) => Some(def_id),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(def_id) = def_id.and_then(|def_id| def_id.as_local()) {
+            if !self.tcx.effective_visibilities(()).is_exported(def_id) {
+                self.found = true;
+            }
+        }
+        intravisit::walk_expr(self, ex);
+    }
+}
+
+//a field of a parameter, e.g. `self.len`, is usually private to the crate
+fn reads_field(guard: &Vec<GuardPiece>) -> bool {
+    guard.windows(2).any(|pieces| match pieces {
+        [GuardPiece::Param(..), GuardPiece::Code(code)] => {
+            let rest = match code.strip_prefix('.') {
+                Some(rest) => rest,
+                None => return false,
+            };
+            let name_end =
+                rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+            name_end > 0 && !rest[name_end..].starts_with(|c| c == '(' || c == ':')
+        }
+        _ => false,
+    })
+}
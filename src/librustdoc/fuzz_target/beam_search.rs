@@ -0,0 +1,191 @@
+//Beam search instead of the bfs, with `--beam-width N`. On crates with thousands of apis, the
+//levels of the bfs grow like the number of apis to the power of the length, and the sequences
+//don't fit in memory. Only the N best sequences of a level are extended to the next one: a
+//sequence is better when it calls more apis the kept sequences don't call, then when it covers
+//more dependencies they don't cover. The candidates of a level are made from a chunk of the
+//frontier at a time, and those falling out of the beam are spilled to
+//`<target dir>/rulf-spill/<crate>.txt` as the indexes of the apis they call, so the memory holds
+//the kept sequences and one chunk of candidates. After the last level, the spilled candidates
+//calling an api no kept sequence calls are read back and added, so the pruning loses sequences
+//but not the apis the bfs reaches.
+use crate::fuzz_target::api_graph::{ApiGraph, ApiType};
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::fuzz_target::graph_cache;
+use rayon::prelude::*;
+use rustc_data_structures::fx::FxHashSet;
+use std::cmp::Reverse;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+
+static SPILL_DIR: &'static str = "rulf-spill";
+//sequences of the frontier extended at once
+static CHUNK_SIZE: usize = 64;
+
+//candidates that fell out of the beam, one line of api indexes each
+struct Spill {
+    path: PathBuf,
+    //None if the file can't be written, the candidates are dropped then
+    writer: Option<BufWriter<File>>,
+    number: usize,
+}
+
+impl Spill {
+    fn create(path: PathBuf) -> Self {
+        let file = match path.parent() {
+            Some(dir) => fs::create_dir_all(dir).and_then(|_| File::create(&path)),
+            None => File::create(&path),
+        };
+        let writer = match file {
+            Ok(file) => Some(BufWriter::new(file)),
+            Err(e) => {
+                println!("warning: the pruned sequences are dropped, {}: {}", path.display(), e);
+                None
+            }
+        };
+        Spill { path, writer, number: 0 }
+    }
+
+    fn write(&mut self, sequence: &ApiSequence) {
+        self.number += 1;
+        let line: Vec<String> =
+            sequence.function_indexes().iter().map(|index| index.to_string()).collect();
+        if let Some(writer) = &mut self.writer {
+            if let Err(e) = writeln!(writer, "{}", line.join(" ")) {
+                println!("warning: the pruned sequences are dropped, {}", e);
+                self.writer = None;
+            }
+        }
+    }
+
+    //the spilled candidates, the file is removed once they are read
+    fn read(mut self, mut f: impl FnMut(Vec<usize>)) {
+        let file = match self.writer.take().map(|mut writer| writer.flush()) {
+            Some(Ok(_)) => File::open(&self.path),
+            Some(Err(e)) => Err(e),
+            None => return,
+        };
+        match file {
+            Ok(file) => {
+                for line in BufReader::new(file).lines().filter_map(|line| line.ok()) {
+                    let function_indexes: Option<Vec<usize>> =
+                        line.split_whitespace().map(|index| index.parse().ok()).collect();
+                    if let Some(function_indexes) = function_indexes {
+                        f(function_indexes);
+                    }
+                }
+            }
+            Err(e) => println!("warning: the pruned sequences are dropped, {}", e),
+        }
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+//the sequences of each level kept in the beam, starting with the empty one and the mined ones,
+//then the spilled sequences calling new apis
+pub(crate) fn beam_bfs(
+    api_graph: &ApiGraph<'_>,
+    max_len: usize,
+    stop_at_end_function: bool,
+    beam_width: usize,
+) -> Vec<ApiSequence> {
+    let extender = api_graph.extender();
+    let mut res = vec![ApiSequence::new()];
+    res.extend(api_graph.mined_sequences.iter().cloned());
+    let mut covered_apis = FxHashSet::default();
+    let mut covered_dependencies = FxHashSet::default();
+    let mut spill = Spill::create(spill_path(api_graph));
+    let thread_pool =
+        rayon::ThreadPoolBuilder::new().num_threads(api_graph.cx.options.jobs.unwrap_or(0)).build();
+    let thread_pool = match thread_pool {
+        Ok(thread_pool) => Some(thread_pool),
+        Err(e) => {
+            println!("warning: the sequences are enumerated on one thread: {}", e);
+            None
+        }
+    };
+    let api_type = ApiType::BareFunction;
+    let extend = |sequence: &ApiSequence| -> Vec<ApiSequence> {
+        if sequence.len() >= max_len || stop_at_end_function && extender.is_sequence_ended(sequence)
+        {
+            return Vec::new();
+        }
+        extender
+            .successors(sequence)
+            .into_iter()
+            .filter_map(|index| extender.is_fun_satisfied(&api_type, index, sequence))
+            .collect()
+    };
+
+    //the mined sequences are longer than the others of the frontier, and stop at the max length
+    let mut frontier = res.clone();
+    for _ in 0..max_len {
+        if frontier.is_empty() || extender.is_out_of_time() {
+            break;
+        }
+        //the candidates are scored against the sequences kept before this level
+        let mut beam: Vec<((usize, usize), ApiSequence)> = Vec::new();
+        for chunk in frontier.chunks(CHUNK_SIZE) {
+            if extender.is_out_of_time() {
+                break;
+            }
+            //collect keeps the order of the frontier
+            let candidates: Vec<Vec<ApiSequence>> = match &thread_pool {
+                Some(thread_pool) => thread_pool.install(|| chunk.par_iter().map(extend).collect()),
+                None => chunk.iter().map(extend).collect(),
+            };
+            for candidate in candidates.into_iter().flatten() {
+                let new_apis = candidate
+                    ._get_contained_api_functions()
+                    .iter()
+                    .filter(|index| !covered_apis.contains(*index))
+                    .count();
+                let new_dependencies = candidate
+                    ._covered_dependencies
+                    .iter()
+                    .filter(|index| !covered_dependencies.contains(*index))
+                    .count();
+                beam.push(((new_apis, new_dependencies), candidate));
+            }
+            //stable, candidates of the same score keep the order of the bfs
+            beam.sort_by_key(|(score, _)| Reverse(*score));
+            if beam.len() > beam_width {
+                for (_, candidate) in beam.drain(beam_width..) {
+                    spill.write(&candidate);
+                }
+            }
+        }
+        frontier = beam.into_iter().map(|(_, sequence)| sequence).collect();
+        for sequence in &frontier {
+            covered_apis.extend(sequence._get_contained_api_functions());
+            covered_dependencies.extend(sequence._covered_dependencies.iter().cloned());
+        }
+        res.extend(frontier.iter().cloned());
+    }
+
+    let spilled_number = spill.number;
+    let kept_number = res.len();
+    spill.read(|function_indexes| {
+        if function_indexes.iter().all(|index| covered_apis.contains(index)) {
+            return;
+        }
+        if let Some(sequence) = api_graph.rebuild_sequence(&function_indexes) {
+            covered_apis.extend(sequence._get_contained_api_functions());
+            res.push(sequence);
+        }
+    });
+    println!(
+        "beam search kept {} sequences, {} of the {} pruned ones are added for their apis",
+        kept_number,
+        res.len() - kept_number,
+        spilled_number
+    );
+    res
+}
+
+//`<target dir>/rulf-spill/<crate>.txt`
+fn spill_path(api_graph: &ApiGraph<'_>) -> PathBuf {
+    graph_cache::target_dir(api_graph)
+        .join(SPILL_DIR)
+        .join(format!("{}.txt", api_graph._crate_name))
+}
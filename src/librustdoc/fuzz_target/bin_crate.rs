@@ -0,0 +1,91 @@
+//Bin crates, with `--crate-type bin` and `--manifest-path`. Some packages keep their lib thin and
+//declare the modules with most of the functionality as `pub mod`s of `main.rs`, so their apis are
+//not in the lib the targets link against. The root of the bin target is documented instead, which
+//rustdoc does like the root of a lib, and the targets link against a lib crate built from the same
+//root: a package in `target/rulf-bin/<bin>` whose `[lib] path` is the root of the bin, with the
+//dependencies of the package and a dependency on its lib if it has one, so that `use <lib>::..` in
+//the bin resolves. `fn main` is an unused private function of that lib. The features of the
+//package are forwarded by features of the same names, so the Cargo.toml of the targets enables
+//them like for a lib. Optional dependencies are only enabled through the lib of the package.
+use crate::docfs::PathError;
+use crate::error::Error;
+use crate::fuzz_target::cargo_metadata::LibTarget;
+use crate::fuzz_target::features;
+use crate::try_err;
+use std::fs;
+
+static DEFAULT_FEATURE: &'static str = "default";
+
+//the package of the lib crate, nothing for the lib target of a package
+pub(crate) fn write_bin_lib(lib_target: &LibTarget) -> Result<(), Error> {
+    let bin = match &lib_target.bin {
+        Some(bin) => bin,
+        None => return Ok(()),
+    };
+    let mut manifest = format!(
+        "[package]\nname = {:?}\nversion = \"0.0.0\"\nedition = {:?}\npublish = false\n\n",
+        lib_target.package_name, bin.edition
+    );
+    manifest.push_str(
+        format!(
+            "[lib]\nname = {:?}\npath = {:?}\n\n",
+            lib_target.name,
+            lib_target.src_path.display().to_string()
+        )
+        .as_str(),
+    );
+
+    //the features of the lib of the package are enabled through the ones of the bin, the default
+    //ones too. Without a lib, they only enable each other
+    manifest.push_str("[features]\n");
+    for (feature, implied_features) in &lib_target.features {
+        let enabled_features: Vec<String> = if bin.has_lib {
+            vec![format!("{}/{}", bin.package_name, feature)]
+        } else {
+            implied_features
+                .iter()
+                .filter(|feature| !feature.starts_with("dep:") && !feature.contains('/'))
+                .cloned()
+                .collect()
+        };
+        manifest.push_str(
+            format!("{} = {}\n", feature, features::toml_list(&enabled_features)).as_str(),
+        );
+    }
+    if !lib_target.features.contains_key(DEFAULT_FEATURE) {
+        manifest.push_str(format!("{} = []\n", DEFAULT_FEATURE).as_str());
+    }
+
+    manifest.push_str("\n[dependencies]\n");
+    if bin.has_lib {
+        manifest.push_str(
+            format!(
+                "{} = {{ path = {:?}, default-features = false }}\n",
+                bin.package_name,
+                bin.package_dir.display().to_string()
+            )
+            .as_str(),
+        );
+    }
+    for (platform, dependencies) in &bin.dependencies {
+        if let Some(platform) = platform {
+            manifest.push_str(format!("\n[target.{:?}.dependencies]\n", platform).as_str());
+        }
+        for dependency in dependencies {
+            manifest.push_str(format!("{}\n", dependency).as_str());
+        }
+    }
+    //keep the package out of the workspace of the package under test
+    manifest.push_str("\n[workspace]\n");
+
+    let lib_dir = lib_target.manifest_path.parent().unwrap();
+    try_err!(fs::create_dir_all(lib_dir), lib_dir);
+    try_err!(fs::write(&lib_target.manifest_path, manifest), &lib_target.manifest_path);
+    println!(
+        "bin target `{}` of package `{}` is built as a lib by {}",
+        lib_target.name,
+        bin.package_name,
+        lib_target.manifest_path.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,195 @@
+//A lightweight borrow checker over the lowered sequence. It tracks moves, shared and mutable
+//borrows between values, and the point where every borrow ends: the last use of the borrower,
+//or the end of the body if the borrower runs a destructor that may still use the borrow.
+//Sequences it rejects would not compile, so they are repaired by removing the offending call
+//together with the calls that use its result.
+use crate::clean;
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::fuzz_target::api_util;
+use crate::fuzz_target::sequence_ir::{IrError, IrType, SequenceIr, ValueSource};
+use rustc_data_structures::fx::FxHashMap;
+use rustc_hir::def::DefKind;
+use rustc_middle::ty::DefIdTree;
+
+//`borrower` borrows `owner` from call `start` up to and including call `end`
+#[derive(Debug, Clone)]
+struct Loan {
+    owner: ValueSource,
+    borrower: ValueSource,
+    mutable: bool,
+    start: usize,
+    end: usize,
+}
+
+pub(crate) fn check(ir: &SequenceIr, api_graph: &ApiGraph<'_>) -> Result<(), IrError> {
+    let loans = loans(ir, api_graph);
+    let body_end = ir.calls.len();
+    let name_of = |source: &ValueSource| ir.value(source).map_or(String::new(), |v| v.name.clone());
+    let mut moved: FxHashMap<ValueSource, usize> = FxHashMap::default();
+
+    for (i, call) in ir.calls.iter().enumerate() {
+        let function = &api_graph.api_functions[call.function];
+        let error = |message: String| IrError {
+            call_index: i,
+            function: function.full_name.clone(),
+            message,
+            is_borrow_error: true,
+        };
+
+        let mut uses: Vec<(ValueSource, bool)> = Vec::new();
+        for (k, arg) in call.args.iter().enumerate() {
+            if let Some(moved_call) = moved.get(&arg.source) {
+                return Err(error(format!(
+                    "argument {} uses `{}` after it was moved by call #{}",
+                    k,
+                    name_of(&arg.source),
+                    moved_call
+                )));
+            }
+            let expected = &function.inputs[k];
+            let exclusive = api_util::_is_exclusive_use(expected, &arg.adaptation);
+            //all arguments of a call are alive at the same time
+            let conflicting = uses.iter().any(|(source, other_exclusive)| {
+                *source == arg.source && (exclusive || *other_exclusive)
+            });
+            if conflicting {
+                return Err(error(format!(
+                    "argument {} uses `{}`, which is used exclusively by another argument",
+                    k,
+                    name_of(&arg.source)
+                )));
+            }
+            if api_util::_move_condition(expected, &arg.adaptation) {
+                moved.insert(arg.source, i);
+            }
+            uses.push((arg.source, exclusive));
+        }
+
+        for (source, exclusive) in &uses {
+            for loan in &loans {
+                if loan.owner != *source || loan.start >= i || i > loan.end {
+                    continue;
+                }
+                if !*exclusive && !loan.mutable {
+                    continue;
+                }
+                let borrow_end = if loan.end == body_end {
+                    "dropped at the end of the body".to_string()
+                } else {
+                    format!("still used by call #{}", loan.end)
+                };
+                return Err(error(format!(
+                    "`{}` is used while it is borrowed by `{}`, which is {}",
+                    name_of(source),
+                    name_of(&loan.borrower),
+                    borrow_end
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn loans(ir: &SequenceIr, api_graph: &ApiGraph<'_>) -> Vec<Loan> {
+    let body_end = ir.calls.len();
+    let mut last_use: FxHashMap<ValueSource, usize> = FxHashMap::default();
+    for (i, call) in ir.calls.iter().enumerate() {
+        for arg in &call.args {
+            last_use.insert(arg.source, i);
+        }
+    }
+
+    //a value has to stay alive as long as the values borrowing from it. Borrowers are defined
+    //after their owners, so walking backwards sees every borrower before its owner
+    let mut ends: FxHashMap<ValueSource, usize> = FxHashMap::default();
+    let mut loans = Vec::new();
+    for value in ir.values.iter().rev() {
+        let start = match value.source {
+            ValueSource::CallResult(i) => i,
+            ValueSource::FuzzableParam(_) => continue,
+        };
+        //values discarded with `let _ = ` are dropped at the end of the statement
+        let own_end = if !value.bound {
+            start
+        } else if has_significant_drop(&value.ty, api_graph) {
+            body_end
+        } else {
+            last_use.get(&value.source).cloned().unwrap_or(start)
+        };
+        let end = ends.get(&value.source).cloned().unwrap_or(start).max(own_end);
+        for (owner, mutable) in &value.borrows {
+            let owner_end = ends.entry(*owner).or_insert(end);
+            *owner_end = (*owner_end).max(end);
+            loans.push(Loan {
+                owner: *owner,
+                borrower: value.source,
+                mutable: *mutable,
+                start,
+                end,
+            });
+        }
+    }
+    loans
+}
+
+fn has_significant_drop(ty: &IrType, api_graph: &ApiGraph<'_>) -> bool {
+    match ty {
+        IrType::Fuzzable(_) => false,
+        IrType::Api(ty) => _type_has_significant_drop(ty, api_graph),
+    }
+}
+
+//whether dropping a value of the type may use the borrows it holds. Destructors with
+//`#[may_dangle]` parameters, like the ones of the std collections, don't
+fn _type_has_significant_drop(ty: &clean::Type, api_graph: &ApiGraph<'_>) -> bool {
+    match ty {
+        clean::Type::Path { path } => {
+            if let Some(generics) = path.generics() {
+                if generics.iter().any(|generic| _type_has_significant_drop(generic, api_graph)) {
+                    return true;
+                }
+            }
+            let def_id = match path.res.opt_def_id() {
+                Some(def_id) => def_id,
+                None => return false,
+            };
+            let tcx = api_graph.tcx();
+            match tcx.def_kind(def_id) {
+                DefKind::Struct | DefKind::Enum | DefKind::Union => {}
+                _ => return false,
+            }
+            match tcx.adt_def(def_id).destructor(tcx) {
+                None => false,
+                Some(destructor) => {
+                    let drop_impl = tcx.parent(destructor.did);
+                    !tcx.generics_of(drop_impl).params.iter().any(|param| param.pure_wrt_drop)
+                }
+            }
+        }
+        clean::Type::Tuple(types) => {
+            types.iter().any(|ty| _type_has_significant_drop(ty, api_graph))
+        }
+        clean::Type::Array(ty, _) => _type_has_significant_drop(ty, api_graph),
+        _ => false,
+    }
+}
+
+//remove the call a borrow error is reported at, along with the calls depending on it, until the
+//sequence passes the check. None if the sequence has other errors or no call is left
+pub(crate) fn repair(sequence: &ApiSequence, api_graph: &ApiGraph<'_>) -> Option<ApiSequence> {
+    let mut sequence = sequence.clone();
+    loop {
+        let ir = SequenceIr::lower(&sequence, api_graph, "_param", "_local");
+        match ir.check(api_graph) {
+            Ok(()) => return Some(sequence),
+            Err(e) if e.is_borrow_error => {
+                sequence = sequence._remove_call_with_dependents(e.call_index, api_graph);
+                if sequence.len() == 0 {
+                    return None;
+                }
+            }
+            Err(_) => return None,
+        }
+    }
+}
@@ -0,0 +1,59 @@
+//How often the apis are called in the source of the crate, including its unit tests and examples
+//in the source files. An api the crate calls a lot is likely the way users get the values it
+//returns, so the dependencies whose producer is called more are preferred when a sequence is
+//extended. Calls are found by name only: `name(`, `.name(` and `name::<..>(` count for every
+//api with that last path segment, which is rough but needs no type information.
+use crate::TyCtxt;
+use rustc_data_structures::fx::FxHashMap;
+use rustc_lexer::TokenKind;
+
+//number of calls by the called name
+pub(crate) fn count_calls(tcx: TyCtxt<'_>) -> FxHashMap<String, usize> {
+    let mut res = FxHashMap::default();
+    for source_file in tcx.sess.source_map().files().iter() {
+        if source_file.is_imported() {
+            continue;
+        }
+        if let Some(src) = &source_file.src {
+            add_calls(src.as_str(), &mut res);
+        }
+    }
+    res
+}
+
+fn add_calls(code: &str, res: &mut FxHashMap<String, usize>) {
+    //tokens except whitespace and comments
+    let mut tokens = Vec::new();
+    let mut position = 0;
+    for token in rustc_lexer::tokenize(code) {
+        let text = &code[position..position + token.len as usize];
+        position += token.len as usize;
+        match token.kind {
+            TokenKind::Whitespace
+            | TokenKind::LineComment { .. }
+            | TokenKind::BlockComment { .. } => {}
+            kind => tokens.push((kind, text)),
+        }
+    }
+    for k in 0..tokens.len() {
+        let (kind, name) = tokens[k];
+        if kind != TokenKind::Ident {
+            continue;
+        }
+        //`fn name(` is the definition
+        if k > 0 && tokens[k - 1].1 == "fn" {
+            continue;
+        }
+        let next = tokens.get(k + 1).map(|token| token.0);
+        let turbofish = tokens.get(k + 1..k + 4).map_or(false, |turbofish| {
+            turbofish.iter().map(|token| token.0).eq([
+                TokenKind::Colon,
+                TokenKind::Colon,
+                TokenKind::Lt,
+            ])
+        });
+        if next == Some(TokenKind::OpenParen) || turbofish {
+            *res.entry(name.to_string()).or_insert(0) += 1;
+        }
+    }
+}
@@ -1,8 +1,12 @@
 use crate::clean::{self};
 use crate::formats::cache::Cache;
 use crate::fuzz_target::api_function::ApiUnsafety;
-use crate::fuzz_target::api_util::_type_name;
+use crate::fuzz_target::api_util::{self, _type_name, IoAdapter};
+use crate::fuzz_target::code_ast::Expr;
+use crate::fuzz_target::fuzzable_type::Collection;
 use crate::fuzz_target::impl_util::FullNameMap;
+use crate::fuzz_target::prelude_type::Wrapper;
+use crate::fuzz_target::std_value::StdValue;
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub(crate) enum CallType {
@@ -19,78 +23,140 @@ pub(crate) enum CallType {
     _ToResult(Box<CallType>),                     //产生一个result类型, never used
     _UnwrapOption(Box<CallType>),                 //获得option变量的值
     _ToOption(Box<CallType>),                     //产生一个option类型
+    _ToWrapper(Box<CallType>, Wrapper),           //放到Box、Rc等包装类型中
+    _AsRef(Box<CallType>),                        //通过AsRef转换为引用
+    _Into(Box<CallType>),                         //通过From或者Into转换
+    _TryInto(Box<CallType>),                      //通过TryFrom或者TryInto转换，得到result
+    _Iter(Box<CallType>),                         //把切片转换为元素引用的迭代器
+    _IntoIter(Box<CallType>),                     //把切片转换为元素的迭代器
+    _ToIo(Box<CallType>, IoAdapter),              //构造reader或者writer
+    _ToStdValue(Box<CallType>, StdValue),         //由整数构造Duration等std类型
+    _CollectSlice(Box<CallType>),                 //切片的元素逐个转换后收集到Vec中
+    _CollectIter(Box<CallType>),                  //把返回的迭代器收集到Vec中
+    _ToCollection(Collection, Vec<CallType>),     //切片的元素逐个转换后收集到集合中
+    _ToTextCollection(Collection),                //文本逐行收集到String的集合中
+    _ScratchBuffer(clean::PrimitiveType),         //由输入中的长度构造清零的可写缓冲区
 }
 
 impl CallType {
-    pub(crate) fn _to_call_string(&self, variable_name: &String, full_name_map: &FullNameMap, cache: &Cache) -> String {
+    //将变量按照call type转换为参数表达式
+    pub(crate) fn _to_call_expr(
+        &self,
+        variable: Expr,
+        full_name_map: &FullNameMap,
+        cache: &Cache,
+    ) -> Expr {
         match self {
-            CallType::_NotCompatible => String::new(),
-            CallType::_DirectCall => variable_name.clone(),
+            CallType::_NotCompatible => Expr::Raw(String::new()),
+            CallType::_DirectCall => variable,
             CallType::_BorrowedRef(inner_) => {
-                let mut call_string = "&(".to_string();
-                let inner_call_string = inner_._to_call_string(variable_name, full_name_map, cache);
-                call_string.push_str(inner_call_string.as_str());
-                call_string.push_str(")");
-                call_string
+                Expr::reference(false, inner_._to_call_expr(variable, full_name_map, cache))
             }
             CallType::_MutBorrowedRef(inner_) => {
-                let mut call_string = "&mut (".to_string();
-                let inner_call_string = inner_._to_call_string(variable_name, full_name_map, cache);
-                call_string.push_str(inner_call_string.as_str());
-                call_string.push_str(")");
-                call_string
+                Expr::reference(true, inner_._to_call_expr(variable, full_name_map, cache))
             }
             CallType::_ConstRawPointer(inner_, ty_) => {
                 //TODO:需要转换之后的类型名
-                let mut call_string = "&(".to_string();
-                let inner_call_string = inner_._to_call_string(variable_name, full_name_map, cache);
-                call_string.push_str(inner_call_string.as_str());
-                call_string.push_str(") as *const ");
-                call_string.push_str(_type_name(ty_, full_name_map, cache).as_str());
-                call_string
+                let inner_expr = inner_._to_call_expr(variable, full_name_map, cache);
+                let pointer_type = format!("*const {}", _type_name(ty_, full_name_map, cache));
+                Expr::cast(Expr::reference(false, inner_expr), pointer_type.as_str())
             }
             CallType::_MutRawPointer(inner_, ty_) => {
                 //TODO:需要转换之后的类型名
-                let mut call_string = "&(".to_string();
-                let inner_call_string = inner_._to_call_string(variable_name, full_name_map, cache);
-                call_string.push_str(inner_call_string.as_str());
-                call_string.push_str(") as *mut ");
-                call_string.push_str(_type_name(ty_, full_name_map, cache).as_str());
-                call_string
+                let inner_expr = inner_._to_call_expr(variable, full_name_map, cache);
+                let pointer_type = format!("*mut {}", _type_name(ty_, full_name_map, cache));
+                Expr::cast(Expr::reference(false, inner_expr), pointer_type.as_str())
             }
             CallType::_AsConvert(str_) => {
                 //TODO:需要转换之后的类型名
-                let mut call_string = variable_name.to_string();
-                call_string.push_str(" as ");
-                call_string.push_str(str_.as_str());
-                call_string
+                Expr::cast(variable, str_.as_str())
             }
             CallType::_UnsafeDeref(inner_) | CallType::_Deref(inner_) => {
                 //TODO:unsafe deref需要考虑unsafe标记
-                let mut call_string = "*(".to_string();
-                let inner_call_string = inner_._to_call_string(variable_name, full_name_map, cache);
-                call_string.push_str(inner_call_string.as_str());
-                call_string.push_str(")");
-                call_string
+                Expr::deref(inner_._to_call_expr(variable, full_name_map, cache))
             }
             CallType::_UnwrapResult(inner_) => {
                 //TODO:暂时先unwrap，后面再想办法处理逻辑
-                let inner_call_string = inner_._to_call_string(variable_name, full_name_map, cache);
-                format!("_unwrap_result({})", inner_call_string)
+                let inner_expr = inner_._to_call_expr(variable, full_name_map, cache);
+                Expr::call(Expr::path("_unwrap_result"), vec![inner_expr])
             }
             CallType::_UnwrapOption(inner_) => {
                 //TODO:暂时先unwrap,后面在想办法处理
-                let inner_call_string = inner_._to_call_string(variable_name, full_name_map, cache);
-                format!("_unwrap_option({})", inner_call_string)
+                let inner_expr = inner_._to_call_expr(variable, full_name_map, cache);
+                Expr::call(Expr::path("_unwrap_option"), vec![inner_expr])
             }
             CallType::_ToOption(inner_) => {
-                let inner_call_string = inner_._to_call_string(variable_name, full_name_map, cache);
-                format!("Some({})", inner_call_string)
+                let inner_expr = inner_._to_call_expr(variable, full_name_map, cache);
+                Expr::call(Expr::path("Some"), vec![inner_expr])
             }
             CallType::_ToResult(inner_) => {
-                let inner_call_string = inner_._to_call_string(variable_name, full_name_map, cache);
-                format!("Ok({})", inner_call_string)
+                let inner_expr = inner_._to_call_expr(variable, full_name_map, cache);
+                Expr::call(Expr::path("Ok"), vec![inner_expr])
             }
+            CallType::_ToWrapper(inner_, wrapper) => {
+                let inner_expr = inner_._to_call_expr(variable, full_name_map, cache);
+                Expr::call(Expr::path(wrapper.constructor()), vec![inner_expr])
+            }
+            CallType::_AsRef(inner_) => {
+                let inner_expr = inner_._to_call_expr(variable, full_name_map, cache);
+                Expr::method_call(inner_expr, "as_ref", Vec::new())
+            }
+            CallType::_Into(inner_) => {
+                let inner_expr = inner_._to_call_expr(variable, full_name_map, cache);
+                Expr::method_call(inner_expr, "into", Vec::new())
+            }
+            CallType::_TryInto(inner_) => {
+                //TryInto在2018 edition中不在prelude里，core的路径在no_std的target中也能用
+                let inner_expr = inner_._to_call_expr(variable, full_name_map, cache);
+                Expr::call(Expr::path("core::convert::TryInto::try_into"), vec![inner_expr])
+            }
+            CallType::_Iter(inner_) => {
+                let inner_expr = inner_._to_call_expr(variable, full_name_map, cache);
+                api_util::_slice_to_iterator(inner_expr, true)
+            }
+            CallType::_IntoIter(inner_) => {
+                let inner_expr = inner_._to_call_expr(variable, full_name_map, cache);
+                api_util::_slice_to_iterator(inner_expr, false)
+            }
+            CallType::_ToIo(inner_, adapter) => {
+                let inner_expr = inner_._to_call_expr(variable, full_name_map, cache);
+                api_util::_io_adapter_expr(inner_expr, *adapter)
+            }
+            CallType::_ToStdValue(inner_, std_value) => {
+                let inner_expr = inner_._to_call_expr(variable, full_name_map, cache);
+                std_value.to_expr(inner_expr)
+            }
+            CallType::_CollectSlice(inner_) => {
+                let element_expr =
+                    inner_._to_call_expr(api_util::_slice_element(), full_name_map, cache);
+                api_util::_collect_converted_slice(variable, element_expr)
+            }
+            CallType::_CollectIter(inner_) => {
+                let inner_expr = inner_._to_call_expr(variable, full_name_map, cache);
+                Expr::method_call(inner_expr, "collect::<Vec<_>>", Vec::new())
+            }
+            CallType::_ToCollection(collection, element_call_types) => {
+                //map的元素是key和value组成的tuple
+                let elements = if element_call_types.len() == 1 {
+                    vec![api_util::_slice_element()]
+                } else {
+                    (0..element_call_types.len())
+                        .map(|k| Expr::field(Expr::path("_element"), k.to_string().as_str()))
+                        .collect()
+                };
+                let element_exprs = element_call_types
+                    .iter()
+                    .zip(elements)
+                    .map(|(call_type, element)| {
+                        call_type._to_call_expr(element, full_name_map, cache)
+                    })
+                    .collect();
+                api_util::_slice_to_collection(variable, *collection, element_exprs)
+            }
+            CallType::_ToTextCollection(collection) => {
+                api_util::_text_to_collection(variable, *collection)
+            }
+            CallType::_ScratchBuffer(element) => api_util::_scratch_buffer_expr(variable, *element),
         }
     }
 
@@ -102,7 +168,22 @@ pub(crate) fn unsafe_call_type(&self) -> ApiUnsafety {
     }
 
     pub(crate) fn _contains_move_call_type(&self) -> bool {
+        //into、try_into和collect会消耗变量，放到Box、Rc等类型中也会
         self._contains_unwrap_call_type()
+            || self._call_type_to_array().iter().any(|call_type| match call_type {
+                CallType::_Into(..) | CallType::_TryInto(..) | CallType::_CollectIter(..) => true,
+                CallType::_ToWrapper(_, wrapper) => wrapper.moves_value(),
+                _ => false,
+            })
+    }
+
+    //由复制出的切片元素构造一个新的值，不借用元素
+    pub(crate) fn _is_element_conversion(&self) -> bool {
+        self._call_type_to_array().iter().all(|call_type| match call_type {
+            CallType::_DirectCall | CallType::_ToOption(..) | CallType::_ToStdValue(..) => true,
+            CallType::_ToWrapper(_, wrapper) => wrapper.moves_value(),
+            _ => false,
+        })
     }
 
     pub(crate) fn _is_unwrap_call_type(&self) -> bool {
@@ -113,7 +194,12 @@ pub(crate) fn _is_unwrap_call_type(&self) -> bool {
     }
     pub(crate) fn _contains_unwrap_call_type(&self) -> bool {
         match self {
-            CallType::_NotCompatible | CallType::_DirectCall | CallType::_AsConvert(..) => false,
+            CallType::_NotCompatible
+            | CallType::_DirectCall
+            | CallType::_AsConvert(..)
+            | CallType::_ToCollection(..)
+            | CallType::_ToTextCollection(..)
+            | CallType::_ScratchBuffer(..) => false,
             CallType::_UnwrapOption(..) | CallType::_UnwrapResult(..) => true,
             CallType::_BorrowedRef(call_type)
             | CallType::_MutBorrowedRef(call_type)
@@ -122,15 +208,28 @@ pub(crate) fn _contains_unwrap_call_type(&self) -> bool {
             | CallType::_UnsafeDeref(call_type)
             | CallType::_Deref(call_type)
             | CallType::_ToOption(call_type)
-            | CallType::_ToResult(call_type) => call_type._contains_move_call_type(),
+            | CallType::_ToResult(call_type)
+            | CallType::_ToWrapper(call_type, _)
+            | CallType::_AsRef(call_type)
+            | CallType::_Into(call_type)
+            | CallType::_TryInto(call_type)
+            | CallType::_Iter(call_type)
+            | CallType::_IntoIter(call_type)
+            | CallType::_ToIo(call_type, _)
+            | CallType::_ToStdValue(call_type, _)
+            | CallType::_CollectSlice(call_type)
+            | CallType::_CollectIter(call_type) => call_type._contains_unwrap_call_type(),
         }
     }
 
     pub(crate) fn _call_type_to_array(&self) -> Vec<CallType> {
         match self {
-            CallType::_NotCompatible | CallType::_DirectCall | CallType::_AsConvert(..) => {
-                vec![self.clone()]
-            }
+            CallType::_NotCompatible
+            | CallType::_DirectCall
+            | CallType::_AsConvert(..)
+            | CallType::_ToCollection(..)
+            | CallType::_ToTextCollection(..)
+            | CallType::_ScratchBuffer(..) => vec![self.clone()],
             CallType::_UnwrapOption(call_type)
             | CallType::_UnwrapResult(call_type)
             | CallType::_BorrowedRef(call_type)
@@ -140,7 +239,17 @@ pub(crate) fn _call_type_to_array(&self) -> Vec<CallType> {
             | CallType::_UnsafeDeref(call_type)
             | CallType::_Deref(call_type)
             | CallType::_ToOption(call_type)
-            | CallType::_ToResult(call_type) => {
+            | CallType::_ToResult(call_type)
+            | CallType::_ToWrapper(call_type, _)
+            | CallType::_AsRef(call_type)
+            | CallType::_Into(call_type)
+            | CallType::_TryInto(call_type)
+            | CallType::_Iter(call_type)
+            | CallType::_IntoIter(call_type)
+            | CallType::_ToIo(call_type, _)
+            | CallType::_ToStdValue(call_type, _)
+            | CallType::_CollectSlice(call_type)
+            | CallType::_CollectIter(call_type) => {
                 let mut call_types = vec![self.clone()];
                 let mut inner_call_types = call_type._call_type_to_array();
                 call_types.append(&mut inner_call_types);
@@ -209,7 +318,12 @@ fn _inner_array_to_call_type(call_type_array: &Vec<CallType>, start: usize) -> S
         let current_type = call_type_array[start].clone();
         let inner_type = CallType::_inner_array_to_call_type(call_type_array, start + 1);
         match current_type {
-            CallType::_DirectCall | CallType::_AsConvert(..) | CallType::_NotCompatible => {
+            CallType::_DirectCall
+            | CallType::_AsConvert(..)
+            | CallType::_NotCompatible
+            | CallType::_ToCollection(..)
+            | CallType::_ToTextCollection(..)
+            | CallType::_ScratchBuffer(..) => {
                 println!("should not go to here in inner array to call type 2");
                 return CallType::_NotCompatible;
             }
@@ -227,6 +341,18 @@ fn _inner_array_to_call_type(call_type_array: &Vec<CallType>, start: usize) -> S
             CallType::_ToOption(..) => CallType::_ToOption(Box::new(inner_type)),
             CallType::_UnwrapResult(..) => CallType::_UnwrapResult(Box::new(inner_type)),
             CallType::_ToResult(..) => CallType::_ToResult(Box::new(inner_type)),
+            CallType::_ToWrapper(_, wrapper) => CallType::_ToWrapper(Box::new(inner_type), wrapper),
+            CallType::_AsRef(..) => CallType::_AsRef(Box::new(inner_type)),
+            CallType::_Into(..) => CallType::_Into(Box::new(inner_type)),
+            CallType::_TryInto(..) => CallType::_TryInto(Box::new(inner_type)),
+            CallType::_Iter(..) => CallType::_Iter(Box::new(inner_type)),
+            CallType::_IntoIter(..) => CallType::_IntoIter(Box::new(inner_type)),
+            CallType::_ToIo(_, adapter) => CallType::_ToIo(Box::new(inner_type), adapter),
+            CallType::_ToStdValue(_, std_value) => {
+                CallType::_ToStdValue(Box::new(inner_type), std_value)
+            }
+            CallType::_CollectSlice(..) => CallType::_CollectSlice(Box::new(inner_type)),
+            CallType::_CollectIter(..) => CallType::_CollectIter(Box::new(inner_type)),
         }
     }
 }
@@ -0,0 +1,278 @@
+//Read the real target layout of the crate from `cargo metadata`, instead of assuming that the
+//lib is named after the package and lives in `src/lib.rs`. With `--crate-type bin`, the bin target
+//of the documented file is read instead, see `bin_crate`.
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Debug, Clone, Deserialize)]
+struct Metadata {
+    packages: Vec<Package>,
+    target_directory: PathBuf,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Package {
+    name: String,
+    manifest_path: PathBuf,
+    targets: Vec<Target>,
+    #[serde(default)]
+    features: BTreeMap<String, Vec<String>>,
+    repository: Option<String>,
+    homepage: Option<String>,
+    edition: String,
+    #[serde(default)]
+    dependencies: Vec<Dependency>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Dependency {
+    name: String,
+    //the source of a registry or git dependency, None for a path dependency
+    source: Option<String>,
+    req: String,
+    //None for a normal dependency, `dev` or `build` otherwise
+    kind: Option<String>,
+    rename: Option<String>,
+    optional: bool,
+    uses_default_features: bool,
+    features: Vec<String>,
+    //the `cfg(..)` or target triple of a platform specific dependency
+    target: Option<String>,
+    path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Target {
+    name: String,
+    kind: Vec<String>,
+    src_path: PathBuf,
+}
+
+//the lib target of a package
+#[derive(Debug, Clone)]
+pub(crate) struct LibTarget {
+    pub(crate) package_name: String,
+    pub(crate) manifest_path: PathBuf,
+    //the name used in `extern crate` and paths, i.e. `[lib] name` with dashes replaced
+    pub(crate) name: String,
+    pub(crate) src_path: PathBuf,
+    //the `[features]` table of the package, with the features each feature enables
+    pub(crate) features: BTreeMap<String, Vec<String>>,
+    //the `target` directory of the workspace
+    pub(crate) target_directory: PathBuf,
+    //`repository` and `homepage` of the package
+    pub(crate) repository: Option<String>,
+    pub(crate) homepage: Option<String>,
+    //the normal dependencies of the package as lines of a `[dependencies]` table, by the name of
+    //the crate in code, see `dependency::extern_name`
+    pub(crate) dependencies: BTreeMap<String, String>,
+    //the edition of the package, the crates of the targets are written in it too
+    pub(crate) edition: String,
+    //a bin target, the package above is the lib crate built from it
+    pub(crate) bin: Option<BinTarget>,
+}
+
+//a bin target fuzzed through a lib crate with the root of the bin, written by `bin_crate`
+#[derive(Debug, Clone)]
+pub(crate) struct BinTarget {
+    //the package the bin belongs to
+    pub(crate) package_name: String,
+    pub(crate) package_dir: PathBuf,
+    pub(crate) edition: String,
+    //the package has a lib too, which the bin may use
+    pub(crate) has_lib: bool,
+    //the normal dependencies of the package as lines of the `[dependencies]` table, by the
+    //platform they are specific to
+    pub(crate) dependencies: BTreeMap<Option<String>, Vec<String>>,
+}
+
+static LIB_KINDS: [&'static str; 5] = ["lib", "rlib", "dylib", "staticlib", "proc-macro"];
+static BIN_KIND: &'static str = "bin";
+//the directory of the lib crates built from bin targets, in the `target` directory
+static BIN_LIB_DIR: &'static str = "rulf-bin";
+
+//the lib target of the package, or with `bin_src_path` the bin target whose root it is
+pub(crate) fn lib_target(
+    manifest_path: &Path,
+    bin_src_path: Option<&Path>,
+) -> Result<LibTarget, String> {
+    let output = Command::new("cargo")
+        .args(["metadata", "--no-deps", "--format-version", "1", "--manifest-path"])
+        .arg(manifest_path)
+        .output()
+        .map_err(|e| format!("failed to run `cargo metadata`: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "`cargo metadata` failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let metadata: Metadata = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("failed to parse `cargo metadata` output: {}", e))?;
+
+    //in a workspace, choose the package of the given manifest
+    let manifest_path = manifest_path.canonicalize().unwrap_or(manifest_path.to_path_buf());
+    let package = match metadata.packages.iter().find(|package| {
+        package.manifest_path.canonicalize().unwrap_or(package.manifest_path.clone())
+            == manifest_path
+    }) {
+        Some(package) => package,
+        None if metadata.packages.len() == 1 => &metadata.packages[0],
+        None => return Err(format!("no package found for {}", manifest_path.display())),
+    };
+    if let Some(bin_src_path) = bin_src_path {
+        return bin_target(&metadata, package, bin_src_path, package.targets.iter().any(is_lib));
+    }
+    let target = package.targets.iter().find(|target| is_lib(target));
+    match target {
+        Some(target) => Ok(LibTarget {
+            package_name: package.name.clone(),
+            manifest_path: package.manifest_path.clone(),
+            name: target.name.replace("-", "_"),
+            src_path: target.src_path.clone(),
+            features: package.features.clone(),
+            target_directory: metadata.target_directory.clone(),
+            repository: package.repository.clone(),
+            homepage: package.homepage.clone(),
+            dependencies: dependency_lines(package),
+            edition: package.edition.clone(),
+            bin: None,
+        }),
+        None => Err(format!("package `{}` has no lib target", package.name)),
+    }
+}
+
+//the bin target of the documented root, or the only bin target of the package. The lib crate
+//built from it is a package of its own in the `target` directory, and the targets depend on it
+//like on the lib of a package
+fn bin_target(
+    metadata: &Metadata,
+    package: &Package,
+    bin_src_path: &Path,
+    has_lib: bool,
+) -> Result<LibTarget, String> {
+    let bin_src_path = bin_src_path.canonicalize().unwrap_or(bin_src_path.to_path_buf());
+    let bins: Vec<&Target> = package
+        .targets
+        .iter()
+        .filter(|target| target.kind.iter().any(|kind| kind == BIN_KIND))
+        .collect();
+    let target = bins.iter().find(|target| {
+        target.src_path.canonicalize().unwrap_or(target.src_path.clone()) == bin_src_path
+    });
+    let target = match (target, bins.as_slice()) {
+        (Some(target), _) | (None, [target]) => target,
+        (None, []) => return Err(format!("package `{}` has no bin target", package.name)),
+        (None, _) => {
+            return Err(format!(
+                "no bin target of package `{}` has the root {}",
+                package.name,
+                bin_src_path.display()
+            ));
+        }
+    };
+    let name = target.name.replace("-", "_");
+    let mut dependencies: BTreeMap<Option<String>, Vec<String>> = BTreeMap::new();
+    for dependency in &package.dependencies {
+        //optional dependencies are enabled by the features of the package, not of the bin
+        if dependency.kind.is_some() || dependency.optional {
+            continue;
+        }
+        dependencies
+            .entry(dependency.target.clone())
+            .or_default()
+            .push(dependency_line(dependency));
+    }
+    let lib_dir = metadata.target_directory.join(BIN_LIB_DIR).join(&name);
+    Ok(LibTarget {
+        package_name: format!("{}-{}-rulf-lib", package.name, target.name),
+        manifest_path: lib_dir.join("Cargo.toml"),
+        name,
+        src_path: target.src_path.clone(),
+        features: package.features.clone(),
+        target_directory: metadata.target_directory.clone(),
+        repository: package.repository.clone(),
+        homepage: package.homepage.clone(),
+        dependencies: dependency_lines(package),
+        edition: package.edition.clone(),
+        bin: Some(BinTarget {
+            package_name: package.name.clone(),
+            package_dir: package.manifest_path.parent().unwrap_or(Path::new(".")).to_path_buf(),
+            edition: package.edition.clone(),
+            has_lib,
+            dependencies,
+        }),
+    })
+}
+
+fn is_lib(target: &Target) -> bool {
+    target.kind.iter().any(|kind| LIB_KINDS.contains(&kind.as_str()))
+}
+
+//optional dependencies too, a crate depending on the package may use them directly. They are
+//keyed by the name the code uses for them, the rename of the dependency or the lib of its package
+fn dependency_lines(package: &Package) -> BTreeMap<String, String> {
+    let lib_names = dependency_lib_names(&package.manifest_path);
+    package
+        .dependencies
+        .iter()
+        .filter(|dependency| dependency.kind.is_none())
+        .map(|dependency| {
+            let key = match (&dependency.rename, lib_names.get(&dependency.name)) {
+                (Some(rename), _) => rename.replace("-", "_"),
+                (None, Some(lib_name)) => lib_name.clone(),
+                (None, None) => dependency.name.replace("-", "_"),
+            };
+            (key, dependency_line(dependency))
+        })
+        .collect()
+}
+
+//the lib names of the packages by package name, from the metadata with the dependencies, which
+//is only read when cargo resolves them offline. The package name stands for the lib otherwise
+fn dependency_lib_names(manifest_path: &Path) -> BTreeMap<String, String> {
+    let output = Command::new("cargo")
+        .args(["metadata", "--offline", "--format-version", "1", "--manifest-path"])
+        .arg(manifest_path)
+        .output();
+    let metadata: Option<Metadata> = match output {
+        Ok(output) if output.status.success() => serde_json::from_slice(&output.stdout).ok(),
+        _ => None,
+    };
+    let mut res = BTreeMap::new();
+    for package in metadata.map_or(Vec::new(), |metadata| metadata.packages) {
+        if let Some(target) = package.targets.iter().find(|target| is_lib(target)) {
+            res.insert(package.name.clone(), target.name.replace("-", "_"));
+        }
+    }
+    res
+}
+
+//e.g. `url = { version = "^2.2", features = ["serde"] }`
+fn dependency_line(dependency: &Dependency) -> String {
+    let mut fields = Vec::new();
+    match (&dependency.path, &dependency.source) {
+        (Some(path), _) => fields.push(format!("path = {:?}", path.display().to_string())),
+        (None, Some(source)) if source.starts_with("git+") => {
+            //`git+<url>?<reference>#<commit>`, the commit of the lock file is not pinned
+            let url = source["git+".len()..].split(|c| c == '?' || c == '#').next().unwrap_or("");
+            fields.push(format!("git = {:?}", url));
+        }
+        _ => fields.push(format!("version = {:?}", dependency.req)),
+    }
+    if dependency.rename.is_some() {
+        fields.push(format!("package = {:?}", dependency.name));
+    }
+    if !dependency.uses_default_features {
+        fields.push("default-features = false".to_string());
+    }
+    if !dependency.features.is_empty() {
+        let features: Vec<String> =
+            dependency.features.iter().map(|feature| format!("{:?}", feature)).collect();
+        fields.push(format!("features = [{}]", features.join(", ")));
+    }
+    let key = dependency.rename.as_ref().unwrap_or(&dependency.name);
+    format!("{} = {{ {} }}", key, fields.join(", "))
+}
@@ -0,0 +1,78 @@
+//Custom libFuzzer mutators for the targets taking strings, with `--char-mutator`. The `&str`
+//params are cut from the input after the fixed length params, and a target returns early when a
+//piece is not UTF-8, so most byte level mutations of a text input are wasted. The mutator inserts,
+//deletes and replaces whole chars in the UTF-8 prefix of that part of the input, and leaves the
+//other half of the mutations to libFuzzer so the fixed length params still change.
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::fuzz_target::fuzzable_type::FuzzableType;
+
+static CHAR_MUTATOR: &'static str = "fn _mutate_chars(
+    data: &mut [u8],
+    size: usize,
+    max_size: usize,
+    seed: u32,
+    text_start: usize,
+) -> usize {
+    //chars that often take text apis down other paths
+    const CHARS: [char; 10] =
+        ['\\0', ' ', '\\n', '\"', '\\\\', 'é', '\\u{200b}', '\\u{feff}', '中', '😀'];
+    let mut state = seed.max(1);
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state as usize
+    };
+    if size < text_start || next() % 2 == 0 {
+        return libfuzzer_sys::fuzzer_mutate(data, size, max_size);
+    }
+    let text = &data[text_start..size];
+    let valid_len = match std::str::from_utf8(text) {
+        Ok(_) => text.len(),
+        Err(e) => e.valid_up_to(),
+    };
+    let mut chars: Vec<char> = std::str::from_utf8(&text[..valid_len]).unwrap().chars().collect();
+    let position = next() % (chars.len() + 1);
+    match next() % 3 {
+        0 => chars.insert(position, CHARS[next() % CHARS.len()]),
+        1 if position < chars.len() => {
+            chars.remove(position);
+        }
+        _ if position < chars.len() => {
+            chars[position] = std::char::from_u32((next() % 0x800) as u32).unwrap_or('a');
+        }
+        _ => chars.push(CHARS[next() % CHARS.len()]),
+    }
+    let mutated: String = chars.into_iter().collect();
+    let rest = data[text_start + valid_len..size].to_vec();
+    let new_size = (text_start + mutated.len() + rest.len()).min(max_size);
+    let mut res = data[..text_start].to_vec();
+    res.extend_from_slice(mutated.as_bytes());
+    res.extend_from_slice(&rest);
+    data[..new_size].copy_from_slice(&res[..new_size]);
+    new_size
+}
+";
+
+//the target has a `&str` param
+pub(crate) fn takes_text(sequence: &ApiSequence) -> bool {
+    sequence.fuzzable_params.iter().any(contains_str)
+}
+
+//the mutator of a libFuzzer target, after the `fuzz_target!`
+pub(crate) fn mutator_code(sequence: &ApiSequence) -> String {
+    let text_start = sequence._fuzzable_fixed_part_length();
+    format!(
+        "{}fuzz_mutator!(|data: &mut [u8], size: usize, max_size: usize, seed: u32| {{\n    \
+         _mutate_chars(data, size, max_size, seed, {})\n}});\n",
+        CHAR_MUTATOR, text_start
+    )
+}
+
+fn contains_str(ty: &FuzzableType) -> bool {
+    match ty {
+        FuzzableType::RefStr => true,
+        FuzzableType::Tuple(inner_types) => inner_types.iter().any(|inner| contains_str(inner)),
+        _ => false,
+    }
+}
@@ -0,0 +1,81 @@
+//Clone impls checked against PartialEq, with `--exercise-clone`. A derived Clone is right, but a
+//hand-written one may forget a field, share state it should copy or panic, and a plain sequence
+//never compares a clone with its original. At the end of a target, the result of the last call
+//and the values it was built from are cloned and compared, `let c = v.clone(); assert!(c == v);`,
+//when their type is a type of the crate implementing Clone and PartialEq. Values that are moved,
+//or borrowed mutably by another value, are skipped. The values the last call depends on are
+//defined in every branch the call is generated in, with `--error-policy=match-both` too.
+use crate::clean;
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::{ApiSequence, ParamType};
+use crate::fuzz_target::api_util;
+use crate::fuzz_target::code_ast::{Expr, Pat, Stmt};
+use crate::fuzz_target::differential;
+use crate::fuzz_target::sequence_ir::SequenceIr;
+use rustc_span::symbol::sym;
+
+//the statements after the last call, `last_value` is the name its result is bound to
+pub(crate) fn clone_stmts(
+    sequence: &ApiSequence,
+    api_graph: &ApiGraph<'_>,
+    ir: &SequenceIr,
+    last_value: &str,
+) -> Vec<Stmt> {
+    let mut res = Vec::new();
+    let last_call = match sequence.len().checked_sub(1) {
+        Some(last_call) if !sequence._has_harness() => last_call,
+        _ => return res,
+    };
+    for call in last_call_sources(sequence, last_call) {
+        let value = if call == last_call {
+            last_value.to_string()
+        } else {
+            match ir.binding(call) {
+                Some(value) => value.name.clone(),
+                None => continue,
+            }
+        };
+        let api_function = &api_graph.api_functions[sequence.functions[call].func.1];
+        let ty = match &api_function.output {
+            Some(ty @ clean::Type::Path { .. }) => ty,
+            _ => continue,
+        };
+        let is_alive =
+            !sequence._is_moved(call) && sequence._conflicting_borrowers(call, false).is_empty();
+        if !is_alive
+            || !api_graph.implements_trait(ty, sym::Clone)
+            || !differential::implements_partial_eq(ty, api_graph)
+        {
+            continue;
+        }
+        let clone = format!("{}_clone", value);
+        res.push(Stmt::Let {
+            pat: Pat::ident(clone.as_str(), false),
+            init: Expr::method_call(Expr::path(value.as_str()), "clone", Vec::new()),
+        });
+        let type_name = api_util::_type_name(ty, &api_graph.full_name_map, api_graph.cache());
+        res.push(differential::assertion(
+            Expr::path(clone.as_str()),
+            Expr::path(value.as_str()),
+            format!("a clone of {} is not equal to the original", type_name).as_str(),
+        ));
+    }
+    res
+}
+
+//the last call and the calls whose results it uses, directly or not, in order
+fn last_call_sources(sequence: &ApiSequence, last_call: usize) -> Vec<usize> {
+    let mut is_source = vec![false; last_call + 1];
+    is_source[last_call] = true;
+    for call in (0..=last_call).rev() {
+        if !is_source[call] {
+            continue;
+        }
+        for (param_type, index, _) in &sequence.functions[call].params {
+            if *param_type == ParamType::_FunctionReturn {
+                is_source[*index] = true;
+            }
+        }
+    }
+    (0..=last_call).filter(|call| is_source[*call]).collect()
+}
@@ -0,0 +1,447 @@
+//A small AST of the rust code emitted in fuzz targets.
+//Generated code is constructed as AST nodes and then pretty printed, instead of concatenating
+//strings by hand. The printer is responsible for separators, parentheses and indentation,
+//so the output is always well formed, and the AST can be transformed before printing.
+
+static INDENT_SIZE: usize = 4;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Expr {
+    //variable or function path, e.g. `_local0`, `url::Url::parse`
+    Path(String),
+    Lit(String),
+    //code fragment already rendered by helper tables (e.g. afl_util), printed as is
+    Raw(String),
+    Call { func: Box<Expr>, args: Vec<Expr> },
+    MethodCall { receiver: Box<Expr>, method: String, args: Vec<Expr> },
+    MacroCall { name: String, args: Vec<Expr> },
+    Ref { mutable: bool, expr: Box<Expr> },
+    Deref(Box<Expr>),
+    //`-x`, `!x`
+    Unary { op: &'static str, expr: Box<Expr> },
+    Index { expr: Box<Expr>, index: Box<Expr> },
+    //`x.0`, `x.len`
+    Field { expr: Box<Expr>, field: String },
+    Cast { expr: Box<Expr>, ty: String },
+    Binary { lhs: Box<Expr>, op: &'static str, rhs: Box<Expr> },
+    Closure { params: Vec<Param>, body: Block },
+    Await(Box<Expr>),
+    //`async move { ... }`
+    AsyncBlock(Block),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Pat {
+    Wild,
+    Ident { name: String, mutable: bool },
+    //enum variant, e.g. `Ok(_local0)`, `None`
+    Variant { path: String, fields: Vec<Pat> },
+    //literal pattern, e.g. `0`
+    Lit(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Param {
+    pub(crate) pat: Pat,
+    pub(crate) ty: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Stmt {
+    Let { pat: Pat, init: Expr },
+    //expression statement, ended with `;`
+    Expr(Expr),
+    If { cond: Expr, then: Block },
+    Match { scrutinee: Expr, arms: Vec<(Pat, Block)> },
+    Unsafe(Block),
+    Loop(Block),
+    Return,
+    Comment(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Block {
+    pub(crate) stmts: Vec<Stmt>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct FnItem {
+    //outer attributes without `#[]`, e.g. `test`
+    pub(crate) attrs: Vec<String>,
+    pub(crate) name: String,
+    pub(crate) params: Vec<Param>,
+    pub(crate) body: Block,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Item {
+    Use(String),
+    Fn(FnItem),
+    //item level macro call, e.g. `fuzz_target!(...)`
+    MacroCall(Expr),
+}
+
+impl Expr {
+    pub(crate) fn path(path: &str) -> Self {
+        Expr::Path(path.to_string())
+    }
+
+    pub(crate) fn call(func: Expr, args: Vec<Expr>) -> Self {
+        Expr::Call { func: Box::new(func), args }
+    }
+
+    pub(crate) fn method_call(receiver: Expr, method: &str, args: Vec<Expr>) -> Self {
+        Expr::MethodCall { receiver: Box::new(receiver), method: method.to_string(), args }
+    }
+
+    pub(crate) fn reference(mutable: bool, expr: Expr) -> Self {
+        Expr::Ref { mutable, expr: Box::new(expr) }
+    }
+
+    pub(crate) fn deref(expr: Expr) -> Self {
+        Expr::Deref(Box::new(expr))
+    }
+
+    pub(crate) fn cast(expr: Expr, ty: &str) -> Self {
+        Expr::Cast { expr: Box::new(expr), ty: ty.to_string() }
+    }
+
+    pub(crate) fn binary(lhs: Expr, op: &'static str, rhs: Expr) -> Self {
+        Expr::Binary { lhs: Box::new(lhs), op, rhs: Box::new(rhs) }
+    }
+
+    pub(crate) fn field(expr: Expr, field: &str) -> Self {
+        Expr::Field { expr: Box::new(expr), field: field.to_string() }
+    }
+
+    pub(crate) fn awaited(expr: Expr) -> Self {
+        Expr::Await(Box::new(expr))
+    }
+
+    //expressions that never need parentheses when used as operands
+    fn is_atom(&self) -> bool {
+        match self {
+            Expr::Path(..)
+            | Expr::Lit(..)
+            | Expr::Call { .. }
+            | Expr::MethodCall { .. }
+            | Expr::MacroCall { .. }
+            | Expr::Index { .. }
+            | Expr::Field { .. }
+            | Expr::Await(..) => true,
+            Expr::Raw(..)
+            | Expr::Ref { .. }
+            | Expr::Deref(..)
+            | Expr::Unary { .. }
+            | Expr::Cast { .. }
+            | Expr::Binary { .. }
+            | Expr::Closure { .. }
+            | Expr::AsyncBlock(..) => false,
+        }
+    }
+
+    fn is_unary(&self) -> bool {
+        match self {
+            Expr::Ref { .. } | Expr::Deref(..) | Expr::Unary { .. } => true,
+            _ => false,
+        }
+    }
+
+    pub(crate) fn to_code(&self, indent: usize) -> String {
+        match self {
+            Expr::Path(path) => path.clone(),
+            Expr::Lit(lit) => lit.clone(),
+            Expr::Raw(code) => code.clone(),
+            Expr::Call { func, args } => {
+                format!("{}({})", func.operand_code(indent, false), args_to_code(args, indent))
+            }
+            Expr::MethodCall { receiver, method, args } => format!(
+                "{}.{}({})",
+                receiver.operand_code(indent, false),
+                method,
+                args_to_code(args, indent)
+            ),
+            Expr::MacroCall { name, args } => {
+                format!("{}!({})", name, args_to_code(args, indent))
+            }
+            Expr::Ref { mutable, expr } => {
+                let prefix = if *mutable { "&mut " } else { "&" };
+                format!("{}{}", prefix, expr.operand_code(indent, true))
+            }
+            Expr::Deref(expr) => format!("*{}", expr.operand_code(indent, true)),
+            Expr::Unary { op, expr } => format!("{}{}", op, expr.operand_code(indent, true)),
+            Expr::Index { expr, index } => {
+                format!("{}[{}]", expr.operand_code(indent, false), index.to_code(indent))
+            }
+            Expr::Field { expr, field } => {
+                format!("{}.{}", expr.operand_code(indent, false), field)
+            }
+            Expr::Cast { expr, ty } => format!("{} as {}", expr.operand_code(indent, true), ty),
+            Expr::Binary { lhs, op, rhs } => format!(
+                "{} {} {}",
+                lhs.operand_code(indent, true),
+                op,
+                rhs.operand_code(indent, true)
+            ),
+            Expr::Closure { params, body } => format!(
+                "|{}| {}",
+                params.iter().map(|param| param.to_code()).collect::<Vec<_>>().join(", "),
+                body.to_code(indent)
+            ),
+            Expr::Await(expr) => format!("{}.await", expr.operand_code(indent, false)),
+            Expr::AsyncBlock(body) => format!("async move {}", body.to_code(indent)),
+        }
+    }
+
+    //print an operand, adding parentheses when needed
+    fn operand_code(&self, indent: usize, allow_unary: bool) -> String {
+        if self.is_atom() || (allow_unary && self.is_unary()) {
+            self.to_code(indent)
+        } else {
+            format!("({})", self.to_code(indent))
+        }
+    }
+}
+
+fn args_to_code(args: &Vec<Expr>, indent: usize) -> String {
+    args.iter().map(|arg| arg.to_code(indent)).collect::<Vec<_>>().join(", ")
+}
+
+impl Pat {
+    pub(crate) fn ident(name: &str, mutable: bool) -> Self {
+        Pat::Ident { name: name.to_string(), mutable }
+    }
+
+    pub(crate) fn variant(path: &str, fields: Vec<Pat>) -> Self {
+        Pat::Variant { path: path.to_string(), fields }
+    }
+
+    pub(crate) fn to_code(&self) -> String {
+        match self {
+            Pat::Wild => "_".to_string(),
+            Pat::Ident { name, mutable } => {
+                if *mutable {
+                    format!("mut {}", name)
+                } else {
+                    name.clone()
+                }
+            }
+            Pat::Lit(lit) => lit.clone(),
+            Pat::Variant { path, fields } => {
+                if fields.is_empty() {
+                    path.clone()
+                } else {
+                    let fields = fields.iter().map(|field| field.to_code()).collect::<Vec<_>>();
+                    format!("{}({})", path, fields.join(", "))
+                }
+            }
+        }
+    }
+}
+
+impl Param {
+    pub(crate) fn new(name: &str, mutable: bool, ty: &str) -> Self {
+        Param { pat: Pat::ident(name, mutable), ty: ty.to_string() }
+    }
+
+    pub(crate) fn to_code(&self) -> String {
+        format!("{}: {}", self.pat.to_code(), self.ty)
+    }
+}
+
+impl Stmt {
+    pub(crate) fn to_code(&self, indent: usize) -> String {
+        let indent_string = _generate_indent(indent);
+        match self {
+            Stmt::Let { pat, init } => {
+                format!("{}let {} = {};\n", indent_string, pat.to_code(), init.to_code(indent))
+            }
+            Stmt::Expr(expr) => format!("{}{};\n", indent_string, expr.to_code(indent)),
+            Stmt::If { cond, then } => {
+                format!("{}if {} {}\n", indent_string, cond.to_code(indent), then.to_code(indent))
+            }
+            Stmt::Match { scrutinee, arms } => {
+                let arm_indent_string = _generate_indent(indent + INDENT_SIZE);
+                let mut res = format!("{}match {} {{\n", indent_string, scrutinee.to_code(indent));
+                for (pat, block) in arms {
+                    res.push_str(
+                        format!(
+                            "{}{} => {}\n",
+                            arm_indent_string,
+                            pat.to_code(),
+                            block.to_code(indent + INDENT_SIZE)
+                        )
+                        .as_str(),
+                    );
+                }
+                res.push_str(format!("{}}}\n", indent_string).as_str());
+                res
+            }
+            Stmt::Unsafe(block) => format!("{}unsafe {}\n", indent_string, block.to_code(indent)),
+            Stmt::Loop(block) => format!("{}loop {}\n", indent_string, block.to_code(indent)),
+            Stmt::Return => format!("{}return;\n", indent_string),
+            Stmt::Comment(comment) => format!("{}//{}\n", indent_string, comment),
+        }
+    }
+}
+
+impl Block {
+    pub(crate) fn new() -> Self {
+        Block { stmts: Vec::new() }
+    }
+
+    pub(crate) fn push(&mut self, stmt: Stmt) {
+        self.stmts.push(stmt);
+    }
+
+    //print the block, the closing brace is aligned with `indent`
+    pub(crate) fn to_code(&self, indent: usize) -> String {
+        let mut res = "{\n".to_string();
+        for stmt in &self.stmts {
+            res.push_str(stmt.to_code(indent + INDENT_SIZE).as_str());
+        }
+        res.push_str(_generate_indent(indent).as_str());
+        res.push('}');
+        res
+    }
+}
+
+impl Item {
+    pub(crate) fn to_code(&self, indent: usize) -> String {
+        let indent_string = _generate_indent(indent);
+        match self {
+            Item::Use(path) => format!("{}use {};\n", indent_string, path),
+            Item::Fn(fn_item) => {
+                let mut res = String::new();
+                for attr in &fn_item.attrs {
+                    res.push_str(format!("{}#[{}]\n", indent_string, attr).as_str());
+                }
+                res.push_str(
+                    format!(
+                        "{}fn {}({}) {}\n",
+                        indent_string,
+                        fn_item.name,
+                        fn_item
+                            .params
+                            .iter()
+                            .map(|param| param.to_code())
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                        fn_item.body.to_code(indent)
+                    )
+                    .as_str(),
+                );
+                res
+            }
+            Item::MacroCall(expr) => format!("{}{};\n", indent_string, expr.to_code(indent)),
+        }
+    }
+}
+
+//replace the crate root `from` of every path in the AST with `to`,
+//e.g. when the lib is named differently from the crate seen by rustdoc
+pub(crate) fn rename_crate_root(items: &mut Vec<Item>, from: &str, to: &str) {
+    for item in items.iter_mut() {
+        match item {
+            Item::Use(path) => *path = rename_root_in_code(path, from, to),
+            Item::Fn(fn_item) => {
+                for param in fn_item.params.iter_mut() {
+                    param.ty = rename_root_in_code(&param.ty, from, to);
+                }
+                rename_root_in_block(&mut fn_item.body, from, to);
+            }
+            Item::MacroCall(expr) => rename_root_in_expr(expr, from, to),
+        }
+    }
+}
+
+fn rename_root_in_block(block: &mut Block, from: &str, to: &str) {
+    for stmt in block.stmts.iter_mut() {
+        match stmt {
+            Stmt::Let { init, .. } => rename_root_in_expr(init, from, to),
+            Stmt::Expr(expr) => rename_root_in_expr(expr, from, to),
+            Stmt::If { cond, then } => {
+                rename_root_in_expr(cond, from, to);
+                rename_root_in_block(then, from, to);
+            }
+            Stmt::Match { scrutinee, arms } => {
+                rename_root_in_expr(scrutinee, from, to);
+                for (_, block) in arms.iter_mut() {
+                    rename_root_in_block(block, from, to);
+                }
+            }
+            Stmt::Unsafe(block) | Stmt::Loop(block) => rename_root_in_block(block, from, to),
+            Stmt::Return | Stmt::Comment(..) => {}
+        }
+    }
+}
+
+fn rename_root_in_expr(expr: &mut Expr, from: &str, to: &str) {
+    match expr {
+        Expr::Path(code) | Expr::Raw(code) => *code = rename_root_in_code(code, from, to),
+        Expr::Lit(..) => {}
+        Expr::Call { func, args } => {
+            rename_root_in_expr(func, from, to);
+            args.iter_mut().for_each(|arg| rename_root_in_expr(arg, from, to));
+        }
+        Expr::MethodCall { receiver, args, .. } => {
+            rename_root_in_expr(receiver, from, to);
+            args.iter_mut().for_each(|arg| rename_root_in_expr(arg, from, to));
+        }
+        Expr::MacroCall { args, .. } => {
+            args.iter_mut().for_each(|arg| rename_root_in_expr(arg, from, to));
+        }
+        Expr::Ref { expr, .. }
+        | Expr::Deref(expr)
+        | Expr::Unary { expr, .. }
+        | Expr::Field { expr, .. } => rename_root_in_expr(expr, from, to),
+        Expr::Index { expr, index } => {
+            rename_root_in_expr(expr, from, to);
+            rename_root_in_expr(index, from, to);
+        }
+        Expr::Cast { expr, ty } => {
+            rename_root_in_expr(expr, from, to);
+            *ty = rename_root_in_code(ty, from, to);
+        }
+        Expr::Binary { lhs, rhs, .. } => {
+            rename_root_in_expr(lhs, from, to);
+            rename_root_in_expr(rhs, from, to);
+        }
+        Expr::Closure { params, body } => {
+            for param in params.iter_mut() {
+                param.ty = rename_root_in_code(&param.ty, from, to);
+            }
+            rename_root_in_block(body, from, to);
+        }
+        Expr::Await(expr) => rename_root_in_expr(expr, from, to),
+        Expr::AsyncBlock(body) => rename_root_in_block(body, from, to),
+    }
+}
+
+//replace `from::` with `to::` when `from` is a whole path segment
+fn rename_root_in_code(code: &str, from: &str, to: &str) -> String {
+    let pattern = format!("{}::", from);
+    let mut res = String::new();
+    let mut rest = code;
+    while let Some(position) = rest.find(pattern.as_str()) {
+        let previous_char = rest[..position].chars().last().or(res.chars().last());
+        let is_segment_start = match previous_char {
+            Some(c) => !(c.is_alphanumeric() || c == '_' || c == ':'),
+            None => true,
+        };
+        res.push_str(&rest[..position]);
+        if is_segment_start {
+            res.push_str(to);
+            res.push_str("::");
+        } else {
+            res.push_str(pattern.as_str());
+        }
+        rest = &rest[position + pattern.len()..];
+    }
+    res.push_str(rest);
+    res
+}
+
+pub(crate) fn _generate_indent(indent_size: usize) -> String {
+    " ".repeat(indent_size)
+}
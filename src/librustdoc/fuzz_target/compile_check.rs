@@ -0,0 +1,356 @@
+//Verify the chosen sequences with `cargo check` before the targets are written, so that users
+//never have to delete broken targets by hand. Every sequence is checked as its replay file and
+//as the files of the emitters writing bins, e.g. the AFL and libFuzzer targets, in the edition of
+//the package. A target that doesn't compile is repaired by removing the call its first error
+//points at, along with the calls depending on it, and is dropped if the repaired target still
+//doesn't compile.
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::fuzz_target::cargo_metadata::LibTarget;
+use crate::fuzz_target::dependency;
+use crate::fuzz_target::features;
+use crate::fuzz_target::fuzz_target_options::AsyncRuntime;
+use crate::fuzz_target::harness_emitter::{EmitterRegistry, HarnessEmitter, ReplayEmitter};
+use crate::fuzz_target::runtime_crate::{self, RUNTIME_CRATE};
+use crate::fuzz_target::sequence_ir::SequenceIr;
+use crate::fuzz_target::serde_producer;
+use rustc_data_structures::fx::FxHashMap;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+static CHECK_DIR_NAME: &'static str = "rulf-compile-check";
+pub(crate) static CHECK_BIN_PREFIX: &'static str = "check";
+
+//a line of `cargo check --message-format=json`
+#[derive(Debug, Clone, Deserialize)]
+struct CargoMessage {
+    reason: String,
+    #[serde(default)]
+    target: Option<CargoTarget>,
+    #[serde(default)]
+    message: Option<Diagnostic>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct CargoTarget {
+    name: String,
+    kind: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Diagnostic {
+    level: String,
+    #[serde(default)]
+    rendered: Option<String>,
+    spans: Vec<DiagnosticSpan>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct DiagnosticSpan {
+    is_primary: bool,
+    line_start: usize,
+}
+
+//the first error of a target, with the file it is in
+#[derive(Debug, Clone)]
+struct CompileError {
+    line: Option<usize>,
+    rendered: String,
+    content: String,
+}
+
+pub(crate) fn check_sequences(
+    api_graph: &ApiGraph<'_>,
+    sequences: Vec<ApiSequence>,
+) -> Vec<ApiSequence> {
+    let lib_target = match &api_graph.cx.options.lib_target {
+        Some(lib_target) => lib_target,
+        None => {
+            println!("warning: compile check is skipped, it needs `--manifest-path`");
+            return sequences;
+        }
+    };
+    let check_dir = api_graph.cx.output_dir.join(CHECK_DIR_NAME);
+    let total_number = sequences.len();
+    let errors = match compile(api_graph, lib_target, &check_dir, &sequences) {
+        Ok(res) => res,
+        Err(e) => {
+            println!("warning: compile check is skipped, {}", e);
+            return sequences;
+        }
+    };
+
+    let mut checked_sequences = Vec::new();
+    let mut repaired_sequences = Vec::new();
+    for (i, sequence) in sequences.into_iter().enumerate() {
+        let error = match errors.get(&i) {
+            None => {
+                checked_sequences.push(Some(sequence));
+                continue;
+            }
+            Some(error) => error,
+        };
+        match repair(api_graph, &sequence, error) {
+            Some(repaired_sequence) => {
+                repaired_sequences.push((checked_sequences.len(), repaired_sequence));
+                checked_sequences.push(None);
+            }
+            None => {
+                println!("target {} does not compile and is removed:\n{}", i, error.rendered);
+                checked_sequences.push(None);
+            }
+        }
+    }
+
+    //the repaired targets are checked again
+    let mut repaired_number = 0;
+    if repaired_sequences.len() > 0 {
+        let sequences = repaired_sequences.iter().map(|(_, sequence)| sequence.clone()).collect();
+        match compile(api_graph, lib_target, &check_dir, &sequences) {
+            Ok(errors) => {
+                for (k, (position, repaired_sequence)) in repaired_sequences.into_iter().enumerate()
+                {
+                    match errors.get(&k) {
+                        None => {
+                            checked_sequences[position] = Some(repaired_sequence);
+                            repaired_number += 1;
+                        }
+                        Some(error) => println!(
+                            "repaired target {} does not compile and is removed:\n{}",
+                            position, error.rendered
+                        ),
+                    }
+                }
+            }
+            Err(e) => println!("warning: repaired targets are removed, {}", e),
+        }
+    }
+
+    let res: Vec<ApiSequence> = checked_sequences.into_iter().flatten().collect();
+    println!(
+        "compile check: {}/{} targets compile ({} repaired)",
+        res.len(),
+        total_number,
+        repaired_number
+    );
+    res
+}
+
+//write a crate with the bins of each sequence and check it. Returns the first error of each
+//sequence with a bin that doesn't compile
+fn compile(
+    api_graph: &ApiGraph<'_>,
+    lib_target: &LibTarget,
+    check_dir: &Path,
+    sequences: &Vec<ApiSequence>,
+) -> Result<FxHashMap<usize, CompileError>, String> {
+    let (manifest_path, bins) = write_crate(api_graph, lib_target, check_dir, sequences, true)?;
+    let mut command = Command::new("cargo");
+    //the targets have to build with the msrv toolchain
+    if let Some(msrv) = api_graph.cx.options.msrv {
+        command.arg(format!("+{}", msrv.toolchain()));
+    }
+    command
+        .args(["check", "--bins", "--message-format=json", "--manifest-path"])
+        .arg(&manifest_path);
+    let output = command.output().map_err(|e| format!("failed to run `cargo check`: {}", e))?;
+
+    let mut errors = FxHashMap::default();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for line in stdout.lines() {
+        let message: CargoMessage = match serde_json::from_str(line) {
+            Ok(message) => message,
+            Err(_) => continue,
+        };
+        let (target, diagnostic) = match (message.target, message.message) {
+            (Some(target), Some(diagnostic)) if message.reason == "compiler-message" => {
+                (target, diagnostic)
+            }
+            _ => continue,
+        };
+        if diagnostic.level != "error" {
+            continue;
+        }
+        let bin = if target.kind.iter().any(|kind| kind == "bin") {
+            bins.get(&target.name)
+        } else {
+            None
+        };
+        let (index, content) = match bin {
+            Some(bin) => bin,
+            None => return Err(format!("`{}` does not compile", target.name)),
+        };
+        errors.entry(*index).or_insert_with(|| CompileError {
+            line: diagnostic.spans.iter().find(|span| span.is_primary).map(|span| span.line_start),
+            rendered: diagnostic.rendered.unwrap_or_default(),
+            content: content.clone(),
+        });
+    }
+    if !output.status.success() && errors.is_empty() {
+        return Err(format!(
+            "`cargo check` failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(errors)
+}
+
+//a crate in `crate_dir` with the replay file of each sequence as a bin `check{i}`, and with
+//`harnesses` its files of the other emitters writing bins as `check{i}_{emitter}`. Returns its
+//manifest and the sequence and content of every bin by its name
+pub(crate) fn write_crate(
+    api_graph: &ApiGraph<'_>,
+    lib_target: &LibTarget,
+    crate_dir: &Path,
+    sequences: &Vec<ApiSequence>,
+    harnesses: bool,
+) -> Result<(PathBuf, FxHashMap<String, (usize, String)>), String> {
+    if crate_dir.exists() {
+        fs::remove_dir_all(crate_dir)
+            .map_err(|e| format!("failed to clean {:?}: {}", crate_dir, e))?;
+    }
+    let bin_dir = crate_dir.join("src").join("bin");
+    fs::create_dir_all(&bin_dir).map_err(|e| format!("failed to create {:?}: {}", bin_dir, e))?;
+    let registry = EmitterRegistry::new();
+    let emitters: Vec<&dyn HarnessEmitter> = if harnesses {
+        registry
+            .selected(api_graph)
+            .into_iter()
+            .filter(|emitter| emitter.is_bin() && emitter.name() != ReplayEmitter.name())
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let manifest_path = crate_dir.join("Cargo.toml");
+    let package_name =
+        crate_dir.file_name().and_then(|name| name.to_str()).unwrap_or(CHECK_DIR_NAME);
+    let uses_async = sequences.iter().any(|sequence| sequence._async_tag);
+    let serde_dependency = serde_producer::serde_dependency(api_graph, sequences);
+    let mut dependency_lines = dependency::manifest_lines(api_graph, sequences);
+    //the bins import the helpers of the runtime crate, which gets a copy in the crate
+    if api_graph.cx.options.runtime_crate {
+        runtime_crate::write_runtime_crate(api_graph, sequences, crate_dir)
+            .map_err(|e| e.to_string())?;
+        dependency_lines.push_str(runtime_crate::dependency(RUNTIME_CRATE).as_str());
+    }
+    for emitter in &emitters {
+        if let Some(dependency) = emitter.dependency() {
+            dependency_lines.push_str(format!("{}\n", dependency).as_str());
+        }
+    }
+    let manifest = manifest(
+        api_graph,
+        lib_target,
+        package_name,
+        uses_async,
+        serde_dependency,
+        &dependency_lines,
+    );
+    fs::write(&manifest_path, manifest)
+        .map_err(|e| format!("failed to write {:?}: {}", manifest_path, e))?;
+    let mut bins = FxHashMap::default();
+    for (i, sequence) in sequences.iter().enumerate() {
+        let mut files =
+            vec![(format!("{}{}", CHECK_BIN_PREFIX, i), &ReplayEmitter as &dyn HarnessEmitter)];
+        for emitter in &emitters {
+            files.push((format!("{}{}_{}", CHECK_BIN_PREFIX, i, emitter.name()), *emitter));
+        }
+        for (bin_name, emitter) in files {
+            let content = emitter.emit(sequence, api_graph, i);
+            let bin_path = bin_dir.join(format!("{}.rs", bin_name));
+            fs::write(&bin_path, &content)
+                .map_err(|e| format!("failed to write {:?}: {}", bin_path, e))?;
+            bins.insert(bin_name, (i, content));
+        }
+    }
+    Ok((manifest_path, bins))
+}
+
+pub(crate) fn manifest(
+    api_graph: &ApiGraph<'_>,
+    lib_target: &LibTarget,
+    package_name: &str,
+    uses_async: bool,
+    serde_dependency: Option<&'static str>,
+    dependency_lines: &str,
+) -> String {
+    let package_dir = lib_target.manifest_path.parent().unwrap_or(Path::new("."));
+    let mut res = format!(
+        "[package]\nname = \"{}\"\nversion = \"0.0.0\"\nedition = {:?}\npublish = false\n\n",
+        package_name, lib_target.edition
+    );
+    res.push_str("[dependencies]\n");
+    //cfg-gated apis need the features the crate is documented with
+    res.push_str(
+        format!(
+            "{} = {{ path = {:?}, features = {} }}\n",
+            lib_target.package_name,
+            package_dir.display().to_string(),
+            features::toml_list(&features::dependency_features(api_graph))
+        )
+        .as_str(),
+    );
+    if uses_async {
+        let dependency = match api_graph.cx.options.async_runtime {
+            AsyncRuntime::Futures => "futures = \"0.3\"",
+            AsyncRuntime::Tokio => "tokio = { version = \"1\", features = [\"rt\"] }",
+        };
+        res.push_str(dependency);
+        res.push('\n');
+    }
+    if let Some(serde_dependency) = serde_dependency {
+        res.push_str(serde_dependency);
+        res.push('\n');
+    }
+    res.push_str(dependency_lines);
+    //keep the crate out of any workspace around the output directory
+    res.push_str("\n[workspace]\n");
+    res
+}
+
+fn repair(
+    api_graph: &ApiGraph<'_>,
+    sequence: &ApiSequence,
+    error: &CompileError,
+) -> Option<ApiSequence> {
+    let call_index = call_at_line(api_graph, sequence, &error.content, error.line?)?;
+    let repaired_sequence = sequence._remove_call_with_dependents(call_index, api_graph);
+    if repaired_sequence.len() == 0 || repaired_sequence._has_no_fuzzables() {
+        return None;
+    }
+    let ir = SequenceIr::lower(&repaired_sequence, api_graph, "_param", "_local");
+    if ir.check(api_graph).is_err() {
+        return None;
+    }
+    Some(repaired_sequence)
+}
+
+//the call whose statements contain the line. The statements of the i-th call are the helper
+//variables `_local{i}_param..` followed by the call of the api
+fn call_at_line(
+    api_graph: &ApiGraph<'_>,
+    sequence: &ApiSequence,
+    content: &str,
+    line: usize,
+) -> Option<usize> {
+    let lines: Vec<&str> = content.lines().collect();
+    let text = lines.get(line.checked_sub(1)?)?;
+    for i in 0..sequence.len() {
+        if text.contains(format!("_local{}_param", i).as_str()) {
+            return Some(i);
+        }
+    }
+    let mut cursor = lines.iter().position(|line| line.contains("fn test_function"))?;
+    let mut call_lines = Vec::new();
+    for api_call in &sequence.functions {
+        let full_name = &api_graph.api_functions[api_call.func.1].full_name;
+        let pattern = format!("{}(", full_name.rsplit("::").next().unwrap_or(full_name));
+        let found = (cursor..lines.len()).find(|k| lines[*k].contains(pattern.as_str()))?;
+        //diagnostics count lines from 1
+        call_lines.push(found + 1);
+        cursor = found + 1;
+    }
+    call_lines.iter().rposition(|call_line| *call_line <= line)
+}
@@ -0,0 +1,194 @@
+//Const fns evaluated at compile time, with `--const-probes`. A const fn runs in the const
+//evaluator when it is called in a const context, and the evaluator can reject what the compiled
+//code accepts (overflow is an error even where the runtime wraps, some pointer operations are
+//unsupported) or the other way around. Every const fn becomes the last call of a target, which
+//covers its runtime behavior, and the const fns taking only primitives and strs get probes: for
+//boundary values of the params, `ctfe_<api>_<k>` calls the api in a `const` item, so it only
+//builds when the evaluation succeeds, and `runtime_<api>_<k>` makes the same call at runtime. The
+//probes are the bins of `const_probes/`, and `run_probes.sh` reports the calls where the two
+//disagree.
+use crate::clean::{self, PrimitiveType};
+use crate::docfs::PathError;
+use crate::error::Error;
+use crate::fuzz_target::api_function::ApiFunction;
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::compile_check;
+use crate::fuzz_target::file_util;
+use crate::try_err;
+use rustc_hir::Mutability;
+use std::fs;
+use std::path::Path;
+
+static PROBE_DIR_NAME: &'static str = "const_probes";
+static RUN_SCRIPT_NAME: &'static str = "run_probes.sh";
+
+pub(crate) fn write_const_probes(api_graph: &ApiGraph<'_>, test_dir: &Path) -> Result<(), Error> {
+    let lib_target = match &api_graph.cx.options.lib_target {
+        Some(lib_target) => lib_target,
+        None => {
+            println!("note: const probes are not written, they need `--manifest-path`");
+            return Ok(());
+        }
+    };
+    let probe_dir = test_dir.join(PROBE_DIR_NAME);
+    if probe_dir.exists() {
+        try_err!(fs::remove_dir_all(&probe_dir), &probe_dir);
+    }
+    let bin_dir = probe_dir.join("src").join("bin");
+    try_err!(fs::create_dir_all(&bin_dir), &bin_dir);
+    let mut probe_names = Vec::new();
+    for api_function in &api_graph.api_functions {
+        if !api_graph.const_apis.contains(&api_function.full_name)
+            || api_function._is_generic_function()
+        {
+            continue;
+        }
+        let param_values = match api_function
+            .inputs
+            .iter()
+            .map(probe_values)
+            .collect::<Option<Vec<Vec<String>>>>()
+        {
+            Some(param_values) => param_values,
+            None => continue,
+        };
+        let api_name = file_util::api_file_name(api_graph, &api_function.full_name);
+        let probe_number = param_values.iter().map(|values| values.len()).max().unwrap_or(1);
+        for k in 0..probe_number {
+            let args: Vec<String> =
+                param_values.iter().map(|values| values[k % values.len()].clone()).collect();
+            let probe_name = format!("{}_{}", api_name, k);
+            for (prefix, content) in [
+                ("ctfe", ctfe_probe(api_graph, api_function, &args)),
+                ("runtime", runtime_probe(api_graph, api_function, &args)),
+            ] {
+                let path = bin_dir.join(format!("{}_{}.rs", prefix, probe_name));
+                try_err!(fs::write(&path, content), &path);
+            }
+            probe_names.push(probe_name);
+        }
+    }
+    let manifest = compile_check::manifest(api_graph, lib_target, PROBE_DIR_NAME, false, None, "");
+    let manifest_path = probe_dir.join("Cargo.toml");
+    try_err!(fs::write(&manifest_path, manifest), &manifest_path);
+    let script_path = probe_dir.join(RUN_SCRIPT_NAME);
+    try_err!(fs::write(&script_path, run_script(&probe_names)), &script_path);
+    println!(
+        "{} const probes of {} const fns are written to {}",
+        probe_names.len(),
+        api_graph.const_apis.len(),
+        probe_dir.display()
+    );
+    Ok(())
+}
+
+//zero, one and the extremes of each primitive, none for the params that can't be literals
+fn probe_values(ty: &clean::Type) -> Option<Vec<String>> {
+    let primitive = match ty {
+        clean::Type::Primitive(primitive) => primitive,
+        clean::Type::BorrowedRef { mutability: Mutability::Not, type_, .. }
+            if **type_ == clean::Type::Primitive(PrimitiveType::Str) =>
+        {
+            return Some(vec![
+                "\"\"".to_string(),
+                "\"a\"".to_string(),
+                "\"\\u{10ffff}\"".to_string(),
+            ]);
+        }
+        _ => return None,
+    };
+    let name = primitive.as_sym();
+    let values = match primitive {
+        PrimitiveType::Bool => vec!["false".to_string(), "true".to_string()],
+        PrimitiveType::Char => {
+            vec!["'\\0'".to_string(), "'a'".to_string(), "char::MAX".to_string()]
+        }
+        PrimitiveType::F32 | PrimitiveType::F64 => vec![
+            format!("0.0{}", name),
+            format!("-1.0{}", name),
+            format!("{}::MAX", name),
+            format!("{}::NAN", name),
+        ],
+        PrimitiveType::I8
+        | PrimitiveType::I16
+        | PrimitiveType::I32
+        | PrimitiveType::I64
+        | PrimitiveType::I128
+        | PrimitiveType::Isize
+        | PrimitiveType::U8
+        | PrimitiveType::U16
+        | PrimitiveType::U32
+        | PrimitiveType::U64
+        | PrimitiveType::U128
+        | PrimitiveType::Usize => vec![
+            format!("0{}", name),
+            format!("1{}", name),
+            format!("{}::MIN", name),
+            format!("{}::MAX", name),
+        ],
+        _ => return None,
+    };
+    Some(values)
+}
+
+//`<lib>::path::api(args)`, in an unsafe block for an unsafe api
+fn call(api_graph: &ApiGraph<'_>, api_function: &ApiFunction, args: &[String]) -> String {
+    let crate_prefix = format!("{}::", api_graph._crate_name);
+    let path = match api_function.full_name.strip_prefix(crate_prefix.as_str()) {
+        Some(path) => format!("{}::{}", api_graph.lib_name(), path),
+        None => api_function.full_name.clone(),
+    };
+    let call = format!("{}({})", path, args.join(", "));
+    if api_function._unsafe_tag._is_unsafe() { format!("unsafe {{ {} }}", call) } else { call }
+}
+
+//the result is forgotten, dropping a value with a destructor isn't allowed in a const context
+fn ctfe_probe(api_graph: &ApiGraph<'_>, api_function: &ApiFunction, args: &[String]) -> String {
+    format!(
+        "extern crate {lib};\n\nconst _: () = {{\n    let _result = {call};\n    \
+         std::mem::forget(_result);\n}};\n\nfn main() {{}}\n",
+        lib = api_graph.lib_name(),
+        call = call(api_graph, api_function, args)
+    )
+}
+
+//the arguments are params of a function that is not inlined, so the call isn't evaluated by the
+//compiler's own constant propagation
+fn runtime_probe(api_graph: &ApiGraph<'_>, api_function: &ApiFunction, args: &[String]) -> String {
+    let params: Vec<String> = api_function
+        .inputs
+        .iter()
+        .enumerate()
+        .map(|(i, ty)| format!("_param{}: {}", i, runtime_param_type(ty)))
+        .collect();
+    let param_names: Vec<String> = (0..args.len()).map(|i| format!("_param{}", i)).collect();
+    format!(
+        "extern crate {lib};\n\n#[inline(never)]\nfn probe({params}) {{\n    \
+         let _result = {call};\n}}\n\nfn main() {{\n    probe({args});\n}}\n",
+        lib = api_graph.lib_name(),
+        params = params.join(", "),
+        call = call(api_graph, api_function, &param_names),
+        args = args.join(", ")
+    )
+}
+
+fn runtime_param_type(ty: &clean::Type) -> String {
+    match ty {
+        clean::Type::Primitive(primitive) => primitive.as_sym().to_string(),
+        _ => "&'static str".to_string(),
+    }
+}
+
+//the probes the evaluator and the runtime disagree on. A probe that fails on both sides agrees
+fn run_script(probe_names: &[String]) -> String {
+    format!(
+        "#!/bin/sh\ncd \"$(dirname \"$0\")\"\nfor probe in {probes}; do\n    \
+         cargo build -q --bin ctfe_$probe 2>/dev/null; ctfe=$?\n    \
+         cargo run -q --bin runtime_$probe >/dev/null 2>&1; runtime=$?\n    \
+         if [ $ctfe -eq 0 ] && [ $runtime -ne 0 ]; then\n        \
+         echo \"$probe: evaluates at compile time but fails at runtime\"\n    \
+         elif [ $ctfe -ne 0 ] && [ $runtime -eq 0 ]; then\n        \
+         echo \"$probe: fails at compile time but runs at runtime\"\n    fi\ndone\n",
+        probes = probe_names.join(" ")
+    )
+}
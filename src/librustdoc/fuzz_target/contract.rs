@@ -0,0 +1,206 @@
+//The contracts of Eq, Ord and Hash impls. A wrong impl rarely panics by itself: it makes a BTreeMap
+//lose entries or a HashSet keep duplicates far from the impl, which plain call sequences don't
+//check. With `--contract-harness`, every type implementing Eq, Ord or Hash that is the result of a
+//target gets one contract target: the target's sequence is run three times on different parts of
+//the fuzz data, and the three results are checked against the laws of the traits, e.g. `a == b`
+//implies equal hashes, and `a <= b && b <= c` implies `a <= c`.
+use crate::clean;
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::fuzz_target::code_ast::{Block, Expr, Pat, Stmt};
+use crate::fuzz_target::differential;
+use crate::fuzz_target::sequence_ir::{IrType, SequenceIr};
+use rustc_span::symbol::sym;
+
+//transitivity takes three values
+static OPERAND_NUMBER: usize = 3;
+
+//the results of `operands` are checked after `call`, the last call of the sequence
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub(crate) struct ContractHarness {
+    pub(crate) call: usize,
+    pub(crate) operands: Vec<usize>,
+    pub(crate) eq: bool,
+    pub(crate) ord: bool,
+    //the type implements PartialEq too, equal values must have equal hashes
+    pub(crate) hash: bool,
+}
+
+impl ContractHarness {
+    //the statements after the call, `ir` gives the names the results are bound to
+    pub(crate) fn to_stmts(&self, ir: &SequenceIr) -> Vec<Stmt> {
+        let names: Vec<String> = self
+            .operands
+            .iter()
+            .filter_map(|operand| ir.binding(*operand))
+            .map(|value| value.name.clone())
+            .collect();
+        if names.len() != OPERAND_NUMBER {
+            return Vec::new();
+        }
+        let value = |k: usize| Expr::path(names[k].as_str());
+        let mut res = Vec::new();
+        if self.eq {
+            res.push(assert(eq(value(0), value(0)), "`==` is not reflexive"));
+            res.push(differential::assertion(
+                eq(value(0), value(1)),
+                eq(value(1), value(0)),
+                "`==` is not symmetric",
+            ));
+            res.push(Stmt::If {
+                cond: Expr::binary(eq(value(0), value(1)), "&&", eq(value(1), value(2))),
+                then: Block {
+                    stmts: vec![assert(eq(value(0), value(2)), "`==` is not transitive")],
+                },
+            });
+        }
+        if self.ord {
+            res.push(differential::assertion(
+                cmp(value(0), value(1)),
+                Expr::method_call(cmp(value(1), value(0)), "reverse", Vec::new()),
+                "`Ord::cmp` is not antisymmetric",
+            ));
+            res.push(differential::assertion(
+                Expr::call(
+                    Expr::path("std::cmp::PartialOrd::partial_cmp"),
+                    vec![Expr::reference(false, value(0)), Expr::reference(false, value(1))],
+                ),
+                Expr::call(Expr::path("Some"), vec![cmp(value(0), value(1))]),
+                "`PartialOrd::partial_cmp` disagrees with `Ord::cmp`",
+            ));
+            if self.eq {
+                res.push(differential::assertion(
+                    Expr::binary(cmp(value(0), value(1)), "==", ordering("Equal")),
+                    eq(value(0), value(1)),
+                    "`Ord::cmp` disagrees with `==`",
+                ));
+            }
+            let not_greater = |lhs: usize, rhs: usize| {
+                Expr::binary(cmp(value(lhs), value(rhs)), "!=", ordering("Greater"))
+            };
+            res.push(Stmt::If {
+                cond: Expr::binary(not_greater(0, 1), "&&", not_greater(1, 2)),
+                then: Block {
+                    stmts: vec![assert(not_greater(0, 2), "`Ord::cmp` is not transitive")],
+                },
+            });
+        }
+        if self.hash {
+            for k in 0..OPERAND_NUMBER {
+                let hasher = format!("_hasher{}", k);
+                res.push(Stmt::Let {
+                    pat: Pat::ident(hasher.as_str(), true),
+                    init: Expr::call(
+                        Expr::path("std::collections::hash_map::DefaultHasher::new"),
+                        Vec::new(),
+                    ),
+                });
+                res.push(Stmt::Expr(Expr::call(
+                    Expr::path("std::hash::Hash::hash"),
+                    vec![
+                        Expr::reference(false, value(k)),
+                        Expr::reference(true, Expr::path(hasher.as_str())),
+                    ],
+                )));
+                res.push(Stmt::Let {
+                    pat: Pat::ident(format!("_hash{}", k).as_str(), false),
+                    init: Expr::call(
+                        Expr::path("std::hash::Hasher::finish"),
+                        vec![Expr::reference(false, Expr::path(hasher.as_str()))],
+                    ),
+                });
+            }
+            for (lhs, rhs) in [(0, 1), (1, 2), (0, 2)] {
+                let hash = |k: usize| Expr::path(format!("_hash{}", k).as_str());
+                res.push(Stmt::If {
+                    cond: eq(value(lhs), value(rhs)),
+                    then: Block {
+                        stmts: vec![differential::assertion(
+                            hash(lhs),
+                            hash(rhs),
+                            "equal values have different hashes",
+                        )],
+                    },
+                });
+            }
+        }
+        res
+    }
+}
+
+fn eq(lhs: Expr, rhs: Expr) -> Expr {
+    Expr::binary(lhs, "==", rhs)
+}
+
+fn cmp(lhs: Expr, rhs: Expr) -> Expr {
+    Expr::call(
+        Expr::path("std::cmp::Ord::cmp"),
+        vec![Expr::reference(false, lhs), Expr::reference(false, rhs)],
+    )
+}
+
+fn ordering(variant: &str) -> Expr {
+    Expr::path(format!("std::cmp::Ordering::{}", variant).as_str())
+}
+
+fn assert(cond: Expr, message: &str) -> Stmt {
+    Stmt::Expr(Expr::MacroCall {
+        name: "assert".to_string(),
+        args: vec![cond, Expr::Lit(format!("{:?}", message))],
+    })
+}
+
+//the first target producing each type with a contract is followed by its contract variant
+pub(crate) fn add_contract_variants(
+    api_graph: &ApiGraph<'_>,
+    sequences: Vec<ApiSequence>,
+) -> Vec<ApiSequence> {
+    if !api_graph.cx.options.contract_harness {
+        return sequences;
+    }
+    let mut checked_types = Vec::new();
+    let mut res = Vec::new();
+    for sequence in sequences {
+        let variant = contract_variant(&sequence, api_graph, &mut checked_types);
+        res.push(sequence);
+        if let Some(variant) = variant {
+            res.push(variant);
+        }
+    }
+    println!("{} contract harnesses are added", checked_types.len());
+    res
+}
+
+fn contract_variant(
+    sequence: &ApiSequence,
+    api_graph: &ApiGraph<'_>,
+    checked_types: &mut Vec<clean::Type>,
+) -> Option<ApiSequence> {
+    if sequence._has_harness() || api_graph.cx.options.no_std {
+        return None;
+    }
+    let last_call = sequence.len().checked_sub(1)?;
+    let ir = SequenceIr::lower(sequence, api_graph, "_param", "_local");
+    let ty = match &ir.values[ir.calls[last_call].result?].ty {
+        IrType::Api(ty @ clean::Type::Path { .. }) => ty.clone(),
+        _ => return None,
+    };
+    if checked_types.contains(&ty) {
+        return None;
+    }
+    let eq = api_graph.implements_trait(&ty, sym::Eq);
+    let ord = api_graph.implements_trait(&ty, sym::Ord);
+    let hash = api_graph.implements_trait(&ty, sym::Hash)
+        && differential::implements_partial_eq(&ty, api_graph);
+    if !eq && !ord && !hash {
+        return None;
+    }
+    checked_types.push(ty);
+    //the copies of the sequence share nothing, so the result of each copy is still alive at the
+    //end of the last one
+    let mut variant = ApiSequence::_merge_sequences(&vec![sequence.clone(); OPERAND_NUMBER]);
+    let operands: Vec<usize> = (1..=OPERAND_NUMBER).map(|copy| copy * sequence.len() - 1).collect();
+    let call = variant.len() - 1;
+    variant._contract_harness = Some(ContractHarness { call, operands, eq, ord, hash });
+    Some(variant)
+}
@@ -0,0 +1,86 @@
+//Dependencies between apis whose types only match after a std conversion. An output `A` is
+//passed to a parameter `B` with `.into()` when `B: From<A>` or `A: Into<B>`, with `try_into`
+//unwrapped like a result when `B: TryFrom<A>` or `A: TryInto<B>`, and to a parameter `&B` with
+//`.as_ref()` when `A: AsRef<B>`. Only the impls in the cache are seen, i.e. impls for the types
+//of the crate, and blanket impls are skipped, so conversions between std types are not added.
+use crate::clean;
+use crate::formats::cache::Cache;
+use crate::fuzz_target::api_util;
+use crate::fuzz_target::call_type::CallType;
+use rustc_hir::Mutability;
+
+//the call type converting the output to the input, `_NotCompatible` if there is no conversion
+pub(crate) fn conversion_call_type(
+    output_type: &clean::Type,
+    input_type: &clean::Type,
+    cache: &Cache,
+) -> CallType {
+    if let clean::Type::BorrowedRef { mutability: Mutability::Not, type_, .. } = input_type {
+        //`as_ref` borrows through references of the output as well
+        if has_conversion_impl(output_type.without_borrowed_ref(), "AsRef", type_, cache) {
+            return CallType::_AsRef(Box::new(CallType::_DirectCall));
+        }
+    }
+    if has_conversion_impl(input_type, "From", output_type, cache)
+        || has_conversion_impl(output_type, "Into", input_type, cache)
+    {
+        return CallType::_Into(Box::new(CallType::_DirectCall));
+    }
+    if has_conversion_impl(input_type, "TryFrom", output_type, cache)
+        || has_conversion_impl(output_type, "TryInto", input_type, cache)
+    {
+        let try_into = CallType::_TryInto(Box::new(CallType::_DirectCall));
+        return CallType::_UnwrapResult(Box::new(try_into));
+    }
+    CallType::_NotCompatible
+}
+
+//whether `self_type: trait_name<trait_arg>` is implemented by an impl of the std trait
+fn has_conversion_impl(
+    self_type: &clean::Type,
+    trait_name: &str,
+    trait_arg: &clean::Type,
+    cache: &Cache,
+) -> bool {
+    let impls = match self_type {
+        clean::Type::Path { path } => path.res.opt_def_id().and_then(|did| cache.impls.get(&did)),
+        _ => None,
+    };
+    let impls = match impls {
+        Some(impls) => impls,
+        None => return false,
+    };
+    impls.iter().any(|impl_| {
+        let impl_ = impl_.inner_impl();
+        let trait_ = match &impl_.trait_ {
+            Some(trait_) => trait_,
+            None => return false,
+        };
+        if trait_.last().as_str() != trait_name || trait_.def_id().is_local() {
+            return false;
+        }
+        //blanket impls and impls for all instances of a generic type
+        let has_type_params = impl_
+            .generics
+            .params
+            .iter()
+            .any(|param| !matches!(param.kind, clean::GenericParamDefKind::Lifetime { .. }));
+        if has_type_params || !same_type_without_lifetime(&impl_.for_, self_type) {
+            return false;
+        }
+        match trait_.generics() {
+            Some(args) if args.len() == 1 => same_type_without_lifetime(args[0], trait_arg),
+            _ => false,
+        }
+    })
+}
+
+fn same_type_without_lifetime(ltype: &clean::Type, rtype: &clean::Type) -> bool {
+    match (ltype, rtype) {
+        (
+            clean::Type::BorrowedRef { mutability: lmutability, type_: ltype_, .. },
+            clean::Type::BorrowedRef { mutability: rmutability, type_: rtype_, .. },
+        ) => lmutability == rmutability && same_type_without_lifetime(ltype_, rtype_),
+        _ => ltype == rtype || api_util::_resolved_path_equal_without_lifetime(ltype, rtype),
+    }
+}
@@ -0,0 +1,229 @@
+//Initial corpus from the string and byte literals in the doc examples and unit tests of the
+//crate. Inputs written by the authors (urls, versions, patterns, ...) take parser crates much
+//deeper in the first minutes of fuzzing than random bytes do.
+use crate::TyCtxt;
+use crate::clean;
+use crate::docfs::PathError;
+use crate::doctest;
+use crate::error::Error;
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::fuzz_target::file_util;
+use crate::fuzz_target::fuzz_target_options::Naming;
+use crate::fuzz_target::incremental::Regeneration;
+use crate::html::markdown::{self, ErrorCodes, LangString};
+use crate::try_err;
+use crate::visit::DocVisitor;
+use rustc_data_structures::fx::FxHashSet;
+use rustc_lexer::unescape::{self, Mode};
+use rustc_lexer::{LiteralKind, TokenKind};
+use std::fs;
+use std::path::Path;
+
+pub(crate) static CORPUS_DIR: &'static str = "corpus";
+static MAX_SEEDS_PER_TARGET: usize = 64;
+static MAX_LITERAL_LEN: usize = 4096;
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SeedLiterals {
+    literals: Vec<Vec<u8>>,
+    seen: FxHashSet<Vec<u8>>,
+}
+
+impl SeedLiterals {
+    pub(crate) fn collect(krate: &clean::Crate, tcx: TyCtxt<'_>) -> Self {
+        let mut collector = DocExampleCollector { literals: SeedLiterals::default() };
+        collector.visit_item(&krate.module);
+        let mut literals = collector.literals;
+        //`#[test]` functions are removed before rustdoc sees them, so unit tests are read from
+        //the source files of the crate
+        for source_file in tcx.sess.source_map().files().iter() {
+            if source_file.is_imported() {
+                continue;
+            }
+            if let Some(src) = &source_file.src {
+                literals.add_unit_tests(src.as_str());
+            }
+        }
+        println!("{} literals are collected for the seed corpus", literals.literals.len());
+        literals
+    }
+
+    fn add_code(&mut self, code: &str) {
+        let mut position = 0;
+        for token in rustc_lexer::tokenize(code) {
+            let text = &code[position..position + token.len as usize];
+            position += token.len as usize;
+            if let TokenKind::Literal { kind, suffix_start } = token.kind {
+                self.add_literal(text, kind, suffix_start as usize);
+            }
+        }
+    }
+
+    //literals inside the body of functions marked with `#[test]`
+    fn add_unit_tests(&mut self, code: &str) {
+        let mut position = 0;
+        //the last tokens that are not whitespace or comments, to find `#[test]`
+        let mut last_tokens: Vec<&str> = Vec::new();
+        let mut in_test = false;
+        let mut depth = 0;
+        for token in rustc_lexer::tokenize(code) {
+            let text = &code[position..position + token.len as usize];
+            position += token.len as usize;
+            match token.kind {
+                TokenKind::Whitespace | TokenKind::LineComment { .. } => continue,
+                TokenKind::BlockComment { .. } => continue,
+                TokenKind::Literal { kind, suffix_start } if depth > 0 => {
+                    self.add_literal(text, kind, suffix_start as usize);
+                }
+                TokenKind::OpenBrace if in_test => depth += 1,
+                TokenKind::CloseBrace if depth > 0 => {
+                    depth -= 1;
+                    if depth == 0 {
+                        in_test = false;
+                    }
+                }
+                _ => {}
+            }
+            last_tokens.push(text);
+            if last_tokens.len() > 4 {
+                last_tokens.remove(0);
+            }
+            if depth == 0 && last_tokens == ["#", "[", "test", "]"] {
+                in_test = true;
+            }
+        }
+    }
+
+    fn add_literal(&mut self, text: &str, kind: LiteralKind, suffix_start: usize) {
+        if let Some(literal) = string_literal_bytes(text, kind, suffix_start) {
+            if literal.len() > 0
+                && literal.len() <= MAX_LITERAL_LEN
+                && self.seen.insert(literal.clone())
+            {
+                self.literals.push(literal);
+            }
+        }
+    }
+}
+
+//the bytes of a string or byte string literal, None for other literals
+pub(crate) fn string_literal_bytes(
+    text: &str,
+    kind: LiteralKind,
+    suffix_start: usize,
+) -> Option<Vec<u8>> {
+    let text = &text[..suffix_start];
+    match kind {
+        LiteralKind::Str { terminated: true } => unescaped(&text[1..text.len() - 1], Mode::Str),
+        LiteralKind::ByteStr { terminated: true } => {
+            unescaped(&text[2..text.len() - 1], Mode::ByteStr)
+        }
+        LiteralKind::RawStr { n_hashes: Some(n_hashes) } => {
+            let n_hashes = n_hashes as usize;
+            Some(text[2 + n_hashes..text.len() - 1 - n_hashes].as_bytes().to_vec())
+        }
+        LiteralKind::RawByteStr { n_hashes: Some(n_hashes) } => {
+            let n_hashes = n_hashes as usize;
+            Some(text[3 + n_hashes..text.len() - 1 - n_hashes].as_bytes().to_vec())
+        }
+        _ => None,
+    }
+}
+
+//None if the literal has invalid escapes
+pub(crate) fn unescaped(body: &str, mode: Mode) -> Option<Vec<u8>> {
+    let mut res = Vec::new();
+    let mut valid = true;
+    unescape::unescape_literal(body, mode, &mut |_, c| match c {
+        Ok(c) if mode == Mode::ByteStr => res.push(c as u8),
+        Ok(c) => {
+            let mut buffer = [0; 4];
+            res.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+        }
+        Err(_) => valid = false,
+    });
+    if valid { Some(res) } else { None }
+}
+
+struct DocExampleCollector {
+    literals: SeedLiterals,
+}
+
+impl DocVisitor for DocExampleCollector {
+    fn visit_item(&mut self, item: &clean::Item) {
+        if let Some(doc) = item.attrs.collapsed_doc_value() {
+            markdown::find_testable_code(&doc, self, ErrorCodes::No, false, None);
+        }
+        self.visit_item_recur(item)
+    }
+}
+
+impl doctest::Tester for DocExampleCollector {
+    fn add_test(&mut self, test: String, _config: LangString, _line: usize) {
+        self.literals.add_code(&test);
+    }
+}
+
+//seeds of the i-th target are written to `corpus/fuzz_{target name}` in the test directory. The
+//directories of unchanged targets are kept with `--incremental`, the fuzzer may have added to them
+pub(crate) fn write_seeds(
+    literals: &SeedLiterals,
+    sequences: &Vec<ApiSequence>,
+    target_names: &[String],
+    regeneration: &Regeneration,
+    naming: Naming,
+    test_dir: &Path,
+) -> Result<(), Error> {
+    let corpus_dir = test_dir.join(CORPUS_DIR);
+    let target_dir_of = |target_name: &String| {
+        let file_name = file_util::test_file_name(naming, "test", target_name);
+        corpus_dir.join(file_name.trim_end_matches(".rs"))
+    };
+    if !regeneration.incremental {
+        if corpus_dir.exists() {
+            try_err!(fs::remove_dir_all(&corpus_dir), &corpus_dir);
+        }
+    } else {
+        let changed_targets = target_names
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !regeneration.is_unchanged(*i))
+            .map(|(_, target_name)| target_name);
+        for target_name in regeneration.removed.iter().chain(changed_targets) {
+            let target_dir = target_dir_of(target_name);
+            if target_dir.exists() {
+                try_err!(fs::remove_dir_all(&target_dir), &target_dir);
+            }
+        }
+    }
+    if literals.literals.is_empty() {
+        return Ok(());
+    }
+    for (i, sequence) in sequences.iter().enumerate() {
+        //literals only make sense for str and slice parameters
+        if sequence._is_fuzzables_fixed_length() || regeneration.is_unchanged(i) {
+            continue;
+        }
+        let target_dir = target_dir_of(&target_names[i]);
+        try_err!(fs::create_dir_all(&target_dir), &target_dir);
+        for (k, literal) in literals.literals.iter().take(MAX_SEEDS_PER_TARGET).enumerate() {
+            let seed_path = target_dir.join(format!("seed{}", k));
+            try_err!(fs::write(&seed_path, seed(sequence, literal)), &seed_path);
+        }
+    }
+    println!("seed corpus is written to {}", corpus_dir.display());
+    Ok(())
+}
+
+//the fixed length part is zeroed, and every dynamic length parameter gets the whole literal
+fn seed(sequence: &ApiSequence, literal: &[u8]) -> Vec<u8> {
+    let mut res = vec![0; sequence._fuzzable_fixed_part_length()];
+    for _ in 0..sequence._dynamic_length_param_number() {
+        res.extend_from_slice(literal);
+    }
+    let min_len = sequence._fuzzables_min_length();
+    if res.len() < min_len {
+        res.resize(min_len, 0);
+    }
+    res
+}
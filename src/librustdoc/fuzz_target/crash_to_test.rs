@@ -0,0 +1,358 @@
+//Convert a crash input of a generated target into a standalone `#[test]`.
+//The input is decoded at generation time in the same way as the afl helpers decode it at run
+//time, and the decoded arguments are inlined as literals. The test depends neither on the fuzzer
+//nor on the crash file, so it can be submitted in a bug report.
+use crate::docfs::PathError;
+use crate::error::Error;
+use crate::fuzz_target::afl_util::_AflHelpers;
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::fuzz_target::code_ast::{Block, Expr, FnItem, Item, Pat, Stmt};
+use crate::fuzz_target::file_util;
+use crate::fuzz_target::fuzz_target_options::CrashToTest;
+use crate::fuzz_target::fuzzable_type::FuzzableType;
+use crate::try_err;
+use std::convert::TryInto;
+use std::fs;
+use std::path::Path;
+
+//prefixes of the files a target is written to, the crash may come from any of them
+static TARGET_PREFIXES: [&'static str; 3] = ["test", "replay", "fuzz_target"];
+
+//how the dynamic length part of the input is split, see `_afl_closure_body`
+struct DynamicLayout {
+    start_index: usize,
+    param_number: usize,
+    length: usize,
+}
+
+pub(crate) fn write_crash_test(
+    graph: &ApiGraph<'_>,
+    sequences: &Vec<ApiSequence>,
+    crash_to_test: &CrashToTest,
+    output_dir: &Path,
+) -> Result<(), Error> {
+    let crash_file = &crash_to_test.crash_file;
+    let target = crash_to_test.target.trim_end_matches(".rs");
+    let target_names = file_util::target_names(graph, sequences);
+    let naming = graph.cx.options.naming;
+    let test_index = (0..sequences.len()).find(|i| {
+        TARGET_PREFIXES.iter().any(|prefix| {
+            let file_name = file_util::test_file_name(naming, prefix, &target_names[*i]);
+            file_name.trim_end_matches(".rs") == target
+        })
+    });
+    let test_index = match test_index {
+        Some(test_index) => test_index,
+        None => {
+            let message = format!("no target named `{}` is generated for this crate", target);
+            return Err(Error::new(message, crash_file));
+        }
+    };
+    let data = try_err!(fs::read(crash_file), crash_file);
+    let sequence = &sequences[test_index];
+    let test_file = match crash_test_file(graph, sequence, test_index, &data, crash_file) {
+        Ok(test_file) => test_file,
+        Err(e) => return Err(Error::new(e, crash_file)),
+    };
+
+    try_err!(fs::create_dir_all(output_dir), output_dir);
+    let test_path = output_dir.join(format!("{}_crash.rs", target));
+    try_err!(fs::write(&test_path, test_file), &test_path);
+    println!("crash test is written to {}", test_path.display());
+    Ok(())
+}
+
+fn crash_test_file(
+    graph: &ApiGraph<'_>,
+    sequence: &ApiSequence,
+    test_index: usize,
+    data: &[u8],
+    crash_file: &Path,
+) -> Result<String, String> {
+    let mut body = Block::new();
+    body.push(Stmt::Comment(format!("crash input: {}", crash_file.display())));
+    let mut test_function_args = Vec::new();
+    for (i, literal) in decode_params(sequence, data)?.into_iter().enumerate() {
+        let param_name = format!("_param{}", i);
+        body.push(Stmt::Let {
+            pat: Pat::ident(param_name.as_str(), false),
+            init: Expr::Lit(literal),
+        });
+        test_function_args.push(Expr::Path(param_name));
+    }
+    let test_function = Expr::Path(format!("test_function{}", test_index));
+    body.push(Stmt::Expr(Expr::call(test_function, test_function_args)));
+
+    let mut res = sequence._extern_crates(graph);
+    if let Some(prelude_functions) = sequence._prelude_helper_functions(graph) {
+        res.push_str(prelude_functions.as_str());
+    }
+    res.push_str(sequence._to_well_written_function(graph, test_index, 0).as_str());
+    res.push('\n');
+    let test = FnItem {
+        attrs: vec!["test".to_string()],
+        name: "crash_reproduce".to_string(),
+        params: Vec::new(),
+        body,
+    };
+    res.push_str(Item::Fn(test).to_code(0).as_str());
+    Ok(res)
+}
+
+//decode the input into literals of the fuzzable params, following `_afl_closure_body`
+fn decode_params(sequence: &ApiSequence, data: &[u8]) -> Result<Vec<String>, String> {
+    let min_len = sequence._fuzzables_min_length();
+    let fixed_length = sequence._is_fuzzables_fixed_length();
+    if (fixed_length && data.len() != min_len) || data.len() < min_len {
+        return Err(format!(
+            "the input has {} bytes, but the target expects {} {} bytes and returns early",
+            data.len(),
+            if fixed_length { "exactly" } else { "at least" },
+            min_len
+        ));
+    }
+    let start_index = sequence._fuzzable_fixed_part_length();
+    let param_number = sequence._dynamic_length_param_number();
+    let length = if fixed_length { 0 } else { (data.len() - start_index) / param_number };
+    let layout = DynamicLayout { start_index, param_number, length };
+
+    let mut res = Vec::new();
+    let mut fixed_start_index = 0;
+    let mut dynamic_param_index = 0;
+    for fuzzable_param in &sequence.fuzzable_params {
+        let afl_helper = _AflHelpers::_new_from_fuzzable(fuzzable_param);
+        let literal = decode_param(
+            &afl_helper,
+            fuzzable_param,
+            data,
+            fixed_start_index,
+            dynamic_param_index,
+            &layout,
+        )?;
+        res.push(literal);
+        fixed_start_index = fixed_start_index + fuzzable_param._fixed_part_length();
+        dynamic_param_index = dynamic_param_index + fuzzable_param._dynamic_length_param_number();
+    }
+    Ok(res)
+}
+
+fn decode_param(
+    afl_helper: &_AflHelpers,
+    fuzzable: &FuzzableType,
+    data: &[u8],
+    fixed_start_index: usize,
+    dynamic_param_index: usize,
+    layout: &DynamicLayout,
+) -> Result<String, String> {
+    match afl_helper {
+        _AflHelpers::_Str | _AflHelpers::_Slice(..) => {
+            let start = layout.start_index + dynamic_param_index * layout.length;
+            let end = if dynamic_param_index == layout.param_number - 1 {
+                data.len()
+            } else {
+                layout.start_index + (dynamic_param_index + 1) * layout.length
+            };
+            match (afl_helper, fuzzable) {
+                (_AflHelpers::_Slice(inner_afl_helper), FuzzableType::RefSlice(inner_fuzzable)) => {
+                    //`_to_slice` and `_to_vec` decode the elements one by one from `start`
+                    let element_length = inner_fuzzable._min_length();
+                    let mut literals = Vec::new();
+                    let mut index = start;
+                    while index + element_length <= end {
+                        literals.push(decode_param(
+                            inner_afl_helper,
+                            inner_fuzzable,
+                            data,
+                            index,
+                            0,
+                            layout,
+                        )?);
+                        index = index + element_length;
+                    }
+                    Ok(format!("&[{}]", literals.join(", ")))
+                }
+                (_AflHelpers::_Slice(..), _) => {
+                    Err("slice param does not match its fuzzable type".to_string())
+                }
+                _ => match std::str::from_utf8(&data[start..end]) {
+                    Ok(s) => Ok(format!("{:?}", s)),
+                    Err(_) => {
+                        Err("a str param is not valid utf-8, the target exits early".to_string())
+                    }
+                },
+            }
+        }
+        _AflHelpers::_Tuple(inner_afl_helpers) => {
+            let inner_fuzzables = match fuzzable {
+                FuzzableType::Tuple(inner_fuzzables) => inner_fuzzables,
+                _ => return Err("tuple param does not match its fuzzable type".to_string()),
+            };
+            let mut literals = Vec::new();
+            let mut inner_fixed_start_index = fixed_start_index;
+            let mut inner_dynamic_param_index = dynamic_param_index;
+            for (inner_afl_helper, inner_fuzzable) in inner_afl_helpers.iter().zip(inner_fuzzables)
+            {
+                literals.push(decode_param(
+                    inner_afl_helper,
+                    inner_fuzzable,
+                    data,
+                    inner_fixed_start_index,
+                    inner_dynamic_param_index,
+                    layout,
+                )?);
+                inner_fixed_start_index =
+                    inner_fixed_start_index + inner_fuzzable._fixed_part_length();
+                inner_dynamic_param_index =
+                    inner_dynamic_param_index + inner_fuzzable._dynamic_length_param_number();
+            }
+            if literals.len() == 1 {
+                Ok(format!("({},)", literals[0]))
+            } else {
+                Ok(format!("({})", literals.join(", ")))
+            }
+        }
+        _AflHelpers::_Array(inner_afl_helper, length) => {
+            let inner_fuzzable = match fuzzable {
+                FuzzableType::Array(inner_fuzzable, _) => inner_fuzzable,
+                _ => return Err("array param does not match its fuzzable type".to_string()),
+            };
+            let element_length = inner_fuzzable._min_length();
+            let mut literals = Vec::new();
+            for k in 0..*length {
+                literals.push(decode_param(
+                    inner_afl_helper,
+                    inner_fuzzable,
+                    data,
+                    fixed_start_index + k * element_length,
+                    dynamic_param_index,
+                    layout,
+                )?);
+            }
+            Ok(format!("[{}]", literals.join(", ")))
+        }
+        _AflHelpers::_Enum(variant_helpers) => {
+            let (full_name, variants) = match fuzzable {
+                FuzzableType::Enum(full_name, variants) => (full_name, variants),
+                _ => return Err("enum param does not match its fuzzable type".to_string()),
+            };
+            let k = to_u8(data, fixed_start_index) as usize % variants.len();
+            let variant = &variants[k];
+            let mut fields = Vec::new();
+            let mut field_start_index = fixed_start_index + 1;
+            for (field_helper, field) in variant_helpers[k].iter().zip(variant.fields.iter()) {
+                fields.push(decode_param(
+                    field_helper,
+                    field,
+                    data,
+                    field_start_index,
+                    dynamic_param_index,
+                    layout,
+                )?);
+                field_start_index = field_start_index + field._min_length();
+            }
+            Ok(variant._to_constructor(&format!("{}::{}", full_name, variant.name), &fields))
+        }
+        _AflHelpers::_Struct(field_helpers) => {
+            let variant = match fuzzable {
+                FuzzableType::Struct(variant) => variant,
+                _ => return Err("struct param does not match its fuzzable type".to_string()),
+            };
+            let mut fields = Vec::new();
+            let mut field_start_index = fixed_start_index;
+            for (field_helper, field) in field_helpers.iter().zip(variant.fields.iter()) {
+                fields.push(decode_param(
+                    field_helper,
+                    field,
+                    data,
+                    field_start_index,
+                    dynamic_param_index,
+                    layout,
+                )?);
+                field_start_index = field_start_index + field._min_length();
+            }
+            Ok(variant._to_constructor(&variant.name, &fields))
+        }
+        _AflHelpers::_NoHelper => {
+            Err(format!("no afl helper for `{}`", fuzzable._to_type_string()))
+        }
+        _ => decode_primitive(afl_helper, data, fixed_start_index),
+    }
+}
+
+fn decode_primitive(afl_helper: &_AflHelpers, data: &[u8], index: usize) -> Result<String, String> {
+    let literal = match afl_helper {
+        _AflHelpers::_U8 => format!("{}u8", to_u8(data, index)),
+        _AflHelpers::_I8 => format!("{}i8", to_i8(data, index)),
+        _AflHelpers::_U16 => format!("{}u16", to_u16(data, index)),
+        _AflHelpers::_I16 => format!("{}i16", to_i16(data, index)),
+        _AflHelpers::_U32 => format!("{}u32", to_u32(data, index)),
+        _AflHelpers::_I32 => format!("{}i32", to_i32(data, index)),
+        _AflHelpers::_U64 => format!("{}u64", to_u64(data, index)),
+        _AflHelpers::_I64 => format!("{}i64", to_i64(data, index)),
+        _AflHelpers::_U128 => format!("{}u128", to_u128(data, index)),
+        _AflHelpers::_I128 => format!("{}i128", to_i128(data, index)),
+        _AflHelpers::_Usize => format!("{}usize", to_u64(data, index)),
+        _AflHelpers::_Isize => format!("{}isize", to_i64(data, index)),
+        //bits are kept exactly, including NaN payloads
+        _AflHelpers::_F32 => {
+            let bits = u32::from_le_bytes(data[index..index + 4].try_into().unwrap());
+            format!("f32::from_bits({:#x})", bits)
+        }
+        _AflHelpers::_F64 => {
+            let bits = u64::from_le_bytes(data[index..index + 8].try_into().unwrap());
+            format!("f64::from_bits({:#x})", bits)
+        }
+        _AflHelpers::_Char => format!("{:?}", to_char(data, index)),
+        _AflHelpers::_Bool => format!("{}", to_u8(data, index) % 2 == 0),
+        _ => return Err(format!("`{}` is not a primitive", afl_helper._type_name())),
+    };
+    Ok(literal)
+}
+
+//the same decoding as the afl helper functions(`_data_to_u8` ...)
+fn to_char(data: &[u8], index: usize) -> char {
+    let char_value = to_u32(data, index) % 0x10F800;
+    let char_value = if char_value >= 0xD800 { char_value + 0x800 } else { char_value };
+    char::from_u32(char_value).unwrap()
+}
+
+fn to_u8(data: &[u8], index: usize) -> u8 {
+    data[index]
+}
+
+fn to_i8(data: &[u8], index: usize) -> i8 {
+    data[index] as i8
+}
+
+fn to_u16(data: &[u8], index: usize) -> u16 {
+    (to_u8(data, index) as u16) << 8 | to_u8(data, index + 1) as u16
+}
+
+fn to_i16(data: &[u8], index: usize) -> i16 {
+    (to_i8(data, index) as i16) << 8 | to_i8(data, index + 1) as i16
+}
+
+fn to_u32(data: &[u8], index: usize) -> u32 {
+    (to_u16(data, index) as u32) << 16 | to_u16(data, index + 2) as u32
+}
+
+fn to_i32(data: &[u8], index: usize) -> i32 {
+    (to_i16(data, index) as i32) << 16 | to_i16(data, index + 2) as i32
+}
+
+fn to_u64(data: &[u8], index: usize) -> u64 {
+    (to_u32(data, index) as u64) << 32 | to_u32(data, index + 4) as u64
+}
+
+fn to_i64(data: &[u8], index: usize) -> i64 {
+    (to_i32(data, index) as i64) << 32 | to_i32(data, index + 4) as i64
+}
+
+fn to_u128(data: &[u8], index: usize) -> u128 {
+    (to_u64(data, index) as u128) << 64 | to_u64(data, index + 8) as u128
+}
+
+fn to_i128(data: &[u8], index: usize) -> i128 {
+    (to_i64(data, index) as i128) << 64 | to_i64(data, index + 8) as i128
+}
@@ -0,0 +1,75 @@
+//`T::default()` as the producer of last resort, with `--allow-default-producer`. Config and options
+//structs usually have private fields and are only built by `Default`, often derived, and derived
+//impls are not apis of the graph, so every api taking such a struct is unreachable. A type gets a
+//`T::default` api when it implements Default without generic params, no api returns it and it
+//can't be built from the fuzz input. The default value is a valid start for the setters taking
+//`&mut self`, so the sequences go on from it.
+use crate::clean;
+use crate::fuzz_target::api_function::{ApiAsyncness, ApiFunction, ApiUnsafety};
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_util;
+use crate::fuzz_target::call_type::CallType;
+use rustc_span::symbol::sym;
+
+pub(crate) fn add_default_producers(api_graph: &mut ApiGraph<'_>) {
+    if !api_graph.cx.options.default_producer {
+        return;
+    }
+    let default_trait = match api_graph.tcx().get_diagnostic_item(sym::Default) {
+        Some(default_trait) => default_trait,
+        None => return,
+    };
+    let mut producers = Vec::new();
+    for (did, impls) in &api_graph.cache().impls {
+        let type_name = match api_graph.full_name_map._get_full_name(*did) {
+            Some(type_name) => type_name,
+            None => continue,
+        };
+        let default_impl = impls.iter().map(|impl_| impl_.inner_impl()).find(|impl_| {
+            impl_.trait_.as_ref().map_or(false, |trait_| trait_.def_id() == default_trait)
+                && impl_.generics.is_empty()
+        });
+        let default_impl = match default_impl {
+            Some(default_impl) => default_impl,
+            None => continue,
+        };
+        //a written `impl Default` is an api already, and returns the type
+        if has_producer(api_graph, &default_impl.for_) {
+            continue;
+        }
+        //Default is in the prelude of std and core
+        producers.push(ApiFunction {
+            full_name: format!("{}::default", type_name),
+            generics: clean::Generics::default(),
+            inputs: Vec::new(),
+            output: Some(default_impl.for_.clone()),
+            _trait_full_path: None,
+            _unsafe_tag: ApiUnsafety::Normal,
+            _async_tag: ApiAsyncness::NotAsync,
+        });
+    }
+    println!("{} types get a `T::default()` producer", producers.len());
+    for producer in producers {
+        api_graph.add_api_function(producer);
+    }
+}
+
+//an api returns the type, or it is built from the fuzz input
+pub(crate) fn has_producer(api_graph: &ApiGraph<'_>, ty_: &clean::Type) -> bool {
+    if api_util::is_fuzzable_type(ty_, &api_graph.full_name_map, api_graph.cache()) {
+        return true;
+    }
+    api_graph.api_functions.iter().any(|api_function| match &api_function.output {
+        Some(output) => {
+            let call_type = api_util::_same_type(
+                output,
+                ty_,
+                true,
+                &api_graph.full_name_map,
+                api_graph.cache(),
+            );
+            call_type != CallType::_NotCompatible
+        }
+        None => false,
+    })
+}
@@ -0,0 +1,317 @@
+//Apis of the direct dependencies given by `--include-deps`. Crates like url accept parameter
+//types of their dependencies (e.g. `form_urlencoded::Serializer`), and the producers of these
+//types are only found in the dependencies. The public functions and inherent methods of the
+//dependencies are cleaned from their metadata before the format cache is built, so the types
+//used in their signatures are recorded in the external paths as well. With
+//`--dependency-producers`, the dependencies are searched without naming them, and only for the
+//apis producing the types of dependencies the crate takes. The targets calling apis of a
+//dependency import it, and the Cargo.toml of the targets depends on it like the package does.
+//Dependencies are named like in the code of the crate, a renamed one by its rename.
+use crate::clean::{self, inline};
+use crate::core::DocContext;
+use crate::fuzz_target::api_function::{ApiAsyncness, ApiFunction, ApiUnsafety};
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::fuzz_target::api_util;
+use rustc_data_structures::fx::{FxHashMap, FxHashSet};
+use rustc_hir as hir;
+use rustc_hir::def::{DefKind, Res};
+use rustc_hir::def_id::{CrateNum, DefId};
+use rustc_middle::ty::subst::GenericArgKind;
+use rustc_middle::ty::{self, Ty, TyCtxt};
+use rustc_session::config::ExternLocation;
+use std::path::PathBuf;
+
+//the types of these crates are built by the generator itself, or not at all
+static STD_CRATES: [&'static str; 3] = ["std", "core", "alloc"];
+
+#[derive(Debug, Clone)]
+pub(crate) struct DependencyApi {
+    //public path of the api, e.g. `form_urlencoded::Serializer::new`
+    pub(crate) full_name: String,
+    pub(crate) function: Box<clean::Function>,
+    pub(crate) unsafety: ApiUnsafety,
+    pub(crate) asyncness: ApiAsyncness,
+}
+
+impl DependencyApi {
+    pub(crate) fn to_api_function(&self) -> ApiFunction {
+        ApiFunction {
+            full_name: self.full_name.clone(),
+            generics: self.function.generics.clone(),
+            inputs: api_util::_extract_input_types(&self.function.decl.inputs),
+            output: api_util::_extract_output_type(&self.function.decl.output),
+            _trait_full_path: None,
+            _unsafe_tag: self.unsafety,
+            _async_tag: self.asyncness,
+        }
+    }
+}
+
+pub(crate) fn load_dependency_apis(cx: &mut DocContext<'_>) {
+    let include_deps = cx.render_options.fuzz_target_options.include_deps.clone();
+    let dependency_producers = cx.render_options.fuzz_target_options.dependency_producers;
+    if include_deps.is_empty() && !dependency_producers {
+        return;
+    }
+    let tcx = cx.tcx;
+    let mut apis = Vec::new();
+    let mut dependency_crates = Vec::new();
+    let mut visited = FxHashSet::default();
+    for dependency in &include_deps {
+        //only the crates used by the crate are loaded
+        let crate_num = tcx.crates(()).iter().cloned().find(|crate_num| {
+            tcx.extern_crate(crate_num.as_def_id()).map_or(false, |krate| krate.is_direct())
+                && extern_name(tcx, *crate_num) == *dependency
+        });
+        match crate_num {
+            Some(crate_num) => {
+                let mut functions = Vec::new();
+                collect_module(
+                    tcx,
+                    crate_num.as_def_id(),
+                    dependency,
+                    &mut visited,
+                    &mut functions,
+                );
+                println!("{} apis are loaded from `{}`", functions.len(), dependency);
+                for (def_id, full_name) in functions {
+                    apis.push(dependency_api(cx, def_id, full_name));
+                }
+                dependency_crates.push(dependency.clone());
+            }
+            None => println!(
+                "warning: `{}` of `--include-deps` is not a direct dependency used by the crate",
+                dependency
+            ),
+        }
+    }
+    if dependency_producers {
+        load_producers(cx, &mut apis, &mut dependency_crates);
+    }
+    cx.render_options.fuzz_target_options.dependency_apis = apis;
+    cx.render_options.fuzz_target_options.dependency_crates = dependency_crates;
+}
+
+fn is_direct_dependency(tcx: TyCtxt<'_>, crate_num: CrateNum) -> bool {
+    tcx.extern_crate(crate_num.as_def_id()).map_or(false, |krate| krate.is_direct())
+        && !STD_CRATES.contains(&tcx.crate_name(crate_num).as_str())
+}
+
+//the name the crate is reached with from the crate, i.e. the name of its `--extern` option. Cargo
+//passes the rename of the dependency there, while `crate_name` is the name of the lib
+pub(crate) fn extern_name(tcx: TyCtxt<'_>, crate_num: CrateNum) -> String {
+    let source = tcx.used_crate_source(crate_num);
+    let source_paths: Vec<PathBuf> =
+        source.paths().map(|path| path.canonicalize().unwrap_or(path.clone())).collect();
+    for (name, entry) in tcx.sess.opts.externs.iter() {
+        if let ExternLocation::ExactPaths(paths) = &entry.location {
+            if paths.iter().any(|path| source_paths.contains(path.canonicalized())) {
+                return name.clone();
+            }
+        }
+    }
+    tcx.crate_name(crate_num).to_string()
+}
+
+//the producers of the types of direct dependencies that the exported apis of the crate take, e.g.
+//`idna::Errors`, instead of all apis of the dependencies. The producers may take types of their
+//crate in turn, so the search goes on with the types they take
+fn load_producers(
+    cx: &mut DocContext<'_>,
+    apis: &mut Vec<DependencyApi>,
+    dependency_crates: &mut Vec<String>,
+) {
+    let tcx = cx.tcx;
+    let effective_visibilities = tcx.effective_visibilities(());
+    let mut wanted_types = FxHashSet::default();
+    for (def_id, _) in effective_visibilities.iter() {
+        if effective_visibilities.is_exported(*def_id)
+            && matches!(tcx.def_kind(def_id.to_def_id()), DefKind::Fn | DefKind::AssocFn)
+        {
+            for input in tcx.fn_sig(def_id.to_def_id()).skip_binder().inputs() {
+                add_dependency_types(tcx, *input, &mut wanted_types);
+            }
+        }
+    }
+    //the public functions of a crate are only collected once
+    let mut crate_functions: FxHashMap<CrateNum, Vec<(DefId, String)>> = FxHashMap::default();
+    let mut loaded = FxHashSet::default();
+    let mut searched_types: FxHashSet<DefId> = FxHashSet::default();
+    loop {
+        let new_types: FxHashSet<DefId> =
+            wanted_types.difference(&searched_types).cloned().collect();
+        if new_types.is_empty() {
+            break;
+        }
+        searched_types.extend(new_types.iter().cloned());
+        let mut crate_nums: Vec<CrateNum> = new_types.iter().map(|def_id| def_id.krate).collect();
+        crate_nums.sort();
+        crate_nums.dedup();
+        for crate_num in crate_nums {
+            let crate_name = extern_name(tcx, crate_num);
+            //all apis of the crates of `--include-deps` are loaded already
+            if cx.render_options.fuzz_target_options.include_deps.contains(&crate_name) {
+                continue;
+            }
+            let functions = crate_functions.entry(crate_num).or_insert_with(|| {
+                let mut functions = Vec::new();
+                let mut visited = FxHashSet::default();
+                collect_module(
+                    tcx,
+                    crate_num.as_def_id(),
+                    &crate_name,
+                    &mut visited,
+                    &mut functions,
+                );
+                functions
+            });
+            let mut producer_number = 0;
+            for (def_id, full_name) in functions.iter() {
+                let output = tcx.fn_sig(*def_id).skip_binder().output();
+                let mut output_types = FxHashSet::default();
+                add_dependency_types(tcx, output, &mut output_types);
+                if output_types.is_disjoint(&new_types) || !loaded.insert(*def_id) {
+                    continue;
+                }
+                for input in tcx.fn_sig(*def_id).skip_binder().inputs() {
+                    add_dependency_types(tcx, *input, &mut wanted_types);
+                }
+                apis.push(dependency_api(cx, *def_id, full_name.clone()));
+                producer_number += 1;
+            }
+            if producer_number > 0 {
+                println!("{} producers are loaded from `{}`", producer_number, crate_name);
+                if !dependency_crates.contains(&crate_name) {
+                    dependency_crates.push(crate_name);
+                }
+            }
+        }
+    }
+}
+
+//the structs, enums and unions of direct dependencies in the type
+fn add_dependency_types<'tcx>(tcx: TyCtxt<'tcx>, ty: Ty<'tcx>, types: &mut FxHashSet<DefId>) {
+    for arg in ty.walk() {
+        if let GenericArgKind::Type(ty) = arg.unpack() {
+            if let ty::Adt(adt_def, _) = ty.kind() {
+                let def_id = adt_def.did();
+                if !def_id.is_local() && is_direct_dependency(tcx, def_id.krate) {
+                    types.insert(def_id);
+                }
+            }
+        }
+    }
+}
+
+//the public functions and inherent methods of the module, by the public path they are reached
+//with, which may be a re-export
+fn collect_module(
+    tcx: TyCtxt<'_>,
+    module: DefId,
+    path: &str,
+    visited: &mut FxHashSet<DefId>,
+    functions: &mut Vec<(DefId, String)>,
+) {
+    if !visited.insert(module) {
+        return;
+    }
+    for child in tcx.module_children(module).iter() {
+        if !child.vis.is_public() {
+            continue;
+        }
+        let child_path = format!("{}::{}", path, child.ident.name);
+        match child.res {
+            Res::Def(DefKind::Mod, def_id) => {
+                collect_module(tcx, def_id, &child_path, visited, functions)
+            }
+            Res::Def(DefKind::Fn, def_id) => {
+                if visited.insert(def_id) {
+                    functions.push((def_id, child_path));
+                }
+            }
+            Res::Def(DefKind::Struct | DefKind::Enum | DefKind::Union, def_id) => {
+                if !visited.insert(def_id) {
+                    continue;
+                }
+                for impl_def_id in tcx.inherent_impls(def_id).iter() {
+                    for assoc_item in tcx.associated_items(*impl_def_id).in_definition_order() {
+                        if assoc_item.kind != ty::AssocKind::Fn
+                            || !tcx.visibility(assoc_item.def_id).is_public()
+                        {
+                            continue;
+                        }
+                        let method_path = format!("{}::{}", child_path, assoc_item.name);
+                        functions.push((assoc_item.def_id, method_path));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+pub(crate) fn dependency_api(
+    cx: &mut DocContext<'_>,
+    def_id: DefId,
+    full_name: String,
+) -> DependencyApi {
+    let function = inline::build_external_function(cx, def_id);
+    let unsafety = match cx.tcx.fn_sig(def_id).unsafety() {
+        hir::Unsafety::Unsafe => ApiUnsafety::Unsafe,
+        hir::Unsafety::Normal => ApiUnsafety::Normal,
+    };
+    let asyncness = match cx.tcx.asyncness(def_id) {
+        hir::IsAsync::Async => ApiAsyncness::Async,
+        hir::IsAsync::NotAsync => ApiAsyncness::NotAsync,
+    };
+    DependencyApi { full_name, function, unsafety, asyncness }
+}
+
+//types of the included dependencies are named in the generated code like the local ones
+pub(crate) fn is_included_path(full_name: &str, include_deps: &Vec<String>) -> bool {
+    let crate_name = full_name.split("::").next().unwrap_or(full_name);
+    include_deps.iter().any(|dependency| dependency == crate_name)
+}
+
+//the dependencies whose apis the sequence calls
+pub(crate) fn used_crates<'a>(
+    sequence: &ApiSequence,
+    api_graph: &'a ApiGraph<'_>,
+) -> Vec<&'a String> {
+    let dependency_crates = &api_graph.cx.options.dependency_crates;
+    dependency_crates
+        .iter()
+        .filter(|crate_name| {
+            sequence.functions.iter().any(|api_call| {
+                let full_name = &api_graph.api_functions[api_call.func.1].full_name;
+                full_name.split("::").next() == Some(crate_name.as_str())
+            })
+        })
+        .collect()
+}
+
+//the lines of the `[dependencies]` table for the dependencies the sequences call, as the package
+//depends on them
+pub(crate) fn manifest_lines(api_graph: &ApiGraph<'_>, sequences: &[ApiSequence]) -> String {
+    let lib_target = match &api_graph.cx.options.lib_target {
+        Some(lib_target) => lib_target,
+        None => return String::new(),
+    };
+    let mut res = String::new();
+    for crate_name in &api_graph.cx.options.dependency_crates {
+        let is_used =
+            sequences.iter().any(|sequence| used_crates(sequence, api_graph).contains(&crate_name));
+        if !is_used {
+            continue;
+        }
+        match lib_target.dependencies.get(crate_name) {
+            Some(line) => {
+                res.push_str(line);
+                res.push('\n');
+            }
+            None => println!("warning: `{}` is not a dependency of the package", crate_name),
+        }
+    }
+    res
+}
@@ -0,0 +1,273 @@
+//Dictionaries for afl and libfuzzer from the source of the crate. Format parsers compare the
+//input with keywords, magic numbers and delimiters, which random mutations rarely hit. Tokens
+//are the string and byte string literals, the integer consts (e.g. `const MAGIC: u32 = ..`, in
+//both byte orders) and the literals matched in patterns (e.g. `b'{' =>`). With `--dictionaries`,
+//every target gets a `.dict` file in `dictionaries/`: targets with str or slice parameters get
+//all tokens, the others only the ones from integers and patterns, which fit fixed length
+//parameters.
+use crate::TyCtxt;
+use crate::docfs::PathError;
+use crate::error::Error;
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::fuzz_target::corpus;
+use crate::fuzz_target::file_util;
+use crate::fuzz_target::fuzz_target_options::Naming;
+use crate::try_err;
+use rustc_data_structures::fx::FxHashSet;
+use rustc_lexer::unescape;
+use rustc_lexer::{LiteralKind, TokenKind};
+use std::fs;
+use std::path::Path;
+
+pub(crate) static DICTIONARY_DIR: &'static str = "dictionaries";
+//the longest token afl reads from a dictionary
+static MAX_TOKEN_LEN: usize = 128;
+static MAX_TOKENS: usize = 256;
+//integer types of consts and the number of their bytes
+static INTEGER_TYPES: [(&'static str, usize); 12] = [
+    ("u8", 1),
+    ("i8", 1),
+    ("u16", 2),
+    ("i16", 2),
+    ("u32", 4),
+    ("i32", 4),
+    ("u64", 8),
+    ("i64", 8),
+    ("u128", 16),
+    ("i128", 16),
+    ("usize", 8),
+    ("isize", 8),
+];
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Dictionary {
+    //string and byte string literals
+    literals: Vec<Vec<u8>>,
+    //bytes of integer consts and of literal patterns
+    magic_values: Vec<Vec<u8>>,
+    seen: FxHashSet<Vec<u8>>,
+}
+
+impl Dictionary {
+    pub(crate) fn collect(tcx: TyCtxt<'_>) -> Self {
+        let mut dictionary = Dictionary::default();
+        for source_file in tcx.sess.source_map().files().iter() {
+            if source_file.is_imported() {
+                continue;
+            }
+            if let Some(src) = &source_file.src {
+                dictionary.add_source(src.as_str());
+            }
+        }
+        println!(
+            "{} tokens are collected for the dictionaries",
+            dictionary.literals.len() + dictionary.magic_values.len()
+        );
+        dictionary
+    }
+
+    fn add_source(&mut self, code: &str) {
+        //tokens except whitespace and comments
+        let mut tokens = Vec::new();
+        let mut position = 0;
+        for token in rustc_lexer::tokenize(code) {
+            let text = &code[position..position + token.len as usize];
+            position += token.len as usize;
+            match token.kind {
+                TokenKind::Whitespace
+                | TokenKind::LineComment { .. }
+                | TokenKind::BlockComment { .. } => {}
+                kind => tokens.push((kind, text)),
+            }
+        }
+        for (k, (kind, text)) in tokens.iter().enumerate() {
+            let (kind, suffix_start) = match kind {
+                TokenKind::Literal { kind, suffix_start } => (*kind, *suffix_start as usize),
+                TokenKind::Ident if *text == "const" => {
+                    if let Some(value) = const_value(&tokens[k..]) {
+                        self.add_token(value, true);
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            if let Some(literal) = corpus::string_literal_bytes(text, kind, suffix_start) {
+                let is_pattern = is_pattern(&tokens, k);
+                self.add_token(literal, is_pattern);
+            } else if is_pattern(&tokens, k) {
+                if let Some(value) = pattern_value(text, kind, suffix_start) {
+                    self.add_token(value, true);
+                }
+            }
+        }
+    }
+
+    fn add_token(&mut self, token: Vec<u8>, is_magic_value: bool) {
+        if token.is_empty()
+            || token.len() > MAX_TOKEN_LEN
+            || self.literals.len() + self.magic_values.len() >= MAX_TOKENS
+            || !self.seen.insert(token.clone())
+        {
+            return;
+        }
+        if is_magic_value {
+            self.magic_values.push(token);
+        } else {
+            self.literals.push(token);
+        }
+    }
+
+    //the tokens for a target, None if it gets no tokens
+    fn to_dict_file(&self, sequence: &ApiSequence) -> Option<String> {
+        let mut res = String::new();
+        if !self.magic_values.is_empty() {
+            res.push_str("# integer consts and patterns\n");
+            for token in &self.magic_values {
+                res.push_str(format!("\"{}\"\n", escaped(token)).as_str());
+            }
+        }
+        //literals only make sense for str and slice parameters
+        if !sequence._is_fuzzables_fixed_length() && !self.literals.is_empty() {
+            res.push_str("# string literals\n");
+            for token in &self.literals {
+                res.push_str(format!("\"{}\"\n", escaped(token)).as_str());
+            }
+        }
+        if res.is_empty() { None } else { Some(res) }
+    }
+}
+
+//`dictionaries/fuzz_{target name}.dict` in the test directory, for the afl and libfuzzer target
+pub(crate) fn dictionary_file_name(naming: Naming, target_name: &str) -> String {
+    let file_name = file_util::test_file_name(naming, "test", target_name);
+    format!("{}/{}.dict", DICTIONARY_DIR, file_name.trim_end_matches(".rs"))
+}
+
+pub(crate) fn write_dictionaries(
+    dictionary: &Dictionary,
+    sequences: &Vec<ApiSequence>,
+    target_names: &[String],
+    naming: Naming,
+    test_dir: &Path,
+) -> Result<(), Error> {
+    let dictionary_dir = test_dir.join(DICTIONARY_DIR);
+    if dictionary_dir.exists() {
+        try_err!(fs::remove_dir_all(&dictionary_dir), &dictionary_dir);
+    }
+    let mut written = 0;
+    for (i, sequence) in sequences.iter().enumerate() {
+        let dict_file = match dictionary.to_dict_file(sequence) {
+            Some(dict_file) => dict_file,
+            None => continue,
+        };
+        try_err!(fs::create_dir_all(&dictionary_dir), &dictionary_dir);
+        let dict_path = test_dir.join(dictionary_file_name(naming, &target_names[i]));
+        try_err!(fs::write(&dict_path, dict_file), &dict_path);
+        written += 1;
+    }
+    if written > 0 {
+        println!("{} dictionaries are written to {}", written, dictionary_dir.display());
+    }
+    Ok(())
+}
+
+//`const NAME: u32 = 0x..;`, the bytes of the value in both byte orders
+fn const_value(tokens: &[(TokenKind, &str)]) -> Option<Vec<u8>> {
+    let (ty, literal, suffix_start, negative) = match tokens {
+        [
+            _,
+            (TokenKind::Ident, _),
+            (TokenKind::Colon, _),
+            (TokenKind::Ident, ty),
+            (TokenKind::Eq, _),
+            (TokenKind::Literal { kind: LiteralKind::Int { .. }, suffix_start }, literal),
+            ..,
+        ] => (*ty, *literal, *suffix_start, false),
+        [
+            _,
+            (TokenKind::Ident, _),
+            (TokenKind::Colon, _),
+            (TokenKind::Ident, ty),
+            (TokenKind::Eq, _),
+            (TokenKind::Minus, _),
+            (TokenKind::Literal { kind: LiteralKind::Int { .. }, suffix_start }, literal),
+            ..,
+        ] => (*ty, *literal, *suffix_start, true),
+        _ => return None,
+    };
+    let width = INTEGER_TYPES.iter().find(|(name, _)| *name == ty)?.1;
+    let value = integer_value(&literal[..suffix_start as usize])?;
+    //small values are lengths and counts rather than magic numbers
+    if width > 1 && value < 256 {
+        return None;
+    }
+    let value = if negative { (value as i128).wrapping_neg() as u128 } else { value };
+    let little_endian = &value.to_le_bytes()[..width];
+    let mut res = little_endian.to_vec();
+    if width > 1 {
+        res.extend(little_endian.iter().rev());
+    }
+    Some(res)
+}
+
+//the literal is a pattern of a match arm: `lit =>`, `lit | ..`, `.. | lit` or a range
+fn is_pattern(tokens: &Vec<(TokenKind, &str)>, k: usize) -> bool {
+    let kind_at = |index: Option<usize>| index.and_then(|index| tokens.get(index)).map(|t| t.0);
+    let next = kind_at(Some(k + 1));
+    let after_next = kind_at(Some(k + 2));
+    let previous = kind_at(k.checked_sub(1));
+    matches!((next, after_next), (Some(TokenKind::Eq), Some(TokenKind::Gt)))
+        || matches!(next, Some(TokenKind::Or))
+        || matches!((next, after_next), (Some(TokenKind::Dot), Some(TokenKind::Dot)))
+        || matches!(previous, Some(TokenKind::Or))
+        //`a..=lit`, `a..lit`
+        || matches!(previous, Some(TokenKind::Eq | TokenKind::Dot))
+            && matches!(kind_at(k.checked_sub(2)), Some(TokenKind::Dot))
+}
+
+//bytes of a char, byte or integer literal. Integers are only taken when they fit a byte
+fn pattern_value(text: &str, kind: LiteralKind, suffix_start: usize) -> Option<Vec<u8>> {
+    let text = &text[..suffix_start];
+    match kind {
+        LiteralKind::Char { terminated: true } => {
+            let c = unescape::unescape_char(&text[1..text.len() - 1]).ok()?;
+            let mut buffer = [0; 4];
+            Some(c.encode_utf8(&mut buffer).as_bytes().to_vec())
+        }
+        LiteralKind::Byte { terminated: true } => {
+            Some(vec![unescape::unescape_byte(&text[2..text.len() - 1]).ok()?])
+        }
+        LiteralKind::Int { .. } => {
+            let value = integer_value(text)?;
+            if value < 256 { Some(vec![value as u8]) } else { None }
+        }
+        _ => None,
+    }
+}
+
+fn integer_value(text: &str) -> Option<u128> {
+    let text = text.replace('_', "");
+    let (digits, radix) = if let Some(digits) = text.strip_prefix("0x") {
+        (digits, 16)
+    } else if let Some(digits) = text.strip_prefix("0o") {
+        (digits, 8)
+    } else if let Some(digits) = text.strip_prefix("0b") {
+        (digits, 2)
+    } else {
+        (text.as_str(), 10)
+    };
+    u128::from_str_radix(digits, radix).ok()
+}
+
+//printable ascii is kept, other bytes and the quotes are escaped as `\xNN`
+fn escaped(token: &[u8]) -> String {
+    let mut res = String::new();
+    for byte in token {
+        match byte {
+            b'"' | b'\\' => res.push_str(format!("\\x{:02X}", byte).as_str()),
+            0x20..=0x7e => res.push(*byte as char),
+            _ => res.push_str(format!("\\x{:02X}", byte).as_str()),
+        }
+    }
+    res
+}
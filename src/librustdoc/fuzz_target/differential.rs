@@ -0,0 +1,461 @@
+//Differential targets for pairs of apis with the same contract. A crash only shows that an api
+//panics, while two apis that disagree on the same input show a semantic bug in one of them. With
+//`--differential`, a target ending with one api of a pair is followed by a variant that also
+//calls the other api and asserts that the two agree:
+//- equivalent apis take the same arguments and return equal values, e.g. `from_str` and `parse`
+//- the unchecked api returns the value of the checked one whenever the checked one succeeds,
+//  e.g. `from_utf8` and `from_utf8_unchecked`
+//- the decoder gives back the input of the encoder, e.g. `encode` and `decode`
+use crate::clean;
+use crate::fuzz_target::api_function::ApiFunction;
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::fuzz_target::api_util;
+use crate::fuzz_target::call_type::CallType;
+use crate::fuzz_target::code_ast::{Block, Expr, Pat, Stmt};
+use crate::fuzz_target::operator::Operator;
+use crate::fuzz_target::prelude_type::PreludeType;
+use crate::fuzz_target::sequence_ir::SequenceIr;
+use rustc_span::symbol::sym;
+
+//names of equivalent apis, a prefix of the name is replaced by its partner, so `from_str_radix`
+//and `parse_radix` are a pair as well
+static EQUIVALENT_NAMES: [(&'static str, &'static str); 3] =
+    [("from_str", "parse"), ("from_bytes", "from_slice"), ("new", "from")];
+//names of encoders and their decoders
+static ROUND_TRIP_NAMES: [(&'static str, &'static str); 7] = [
+    ("encode", "decode"),
+    ("serialize", "deserialize"),
+    ("to_string", "from_str"),
+    ("to_bytes", "from_bytes"),
+    ("to_vec", "from_slice"),
+    ("compress", "decompress"),
+    ("escape", "unescape"),
+];
+//std types that implement PartialEq when their type arguments do
+static PARTIAL_EQ_STD_TYPES: [&'static str; 6] =
+    ["String", "Vec", "Option", "Result", "Box", "Cow"];
+
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub(crate) enum DifferentialKind {
+    Equivalent,
+    Unchecked,
+    RoundTrip,
+}
+
+impl DifferentialKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            DifferentialKind::Equivalent => "equivalent",
+            DifferentialKind::Unchecked => "unchecked",
+            DifferentialKind::RoundTrip => "round trip",
+        }
+    }
+}
+
+//the result of `call` to `api` is compared with the result of `partner`. Both are indices into
+//the api functions of the graph
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub(crate) struct DifferentialHarness {
+    pub(crate) call: usize,
+    pub(crate) api: usize,
+    pub(crate) partner: usize,
+    pub(crate) kind: DifferentialKind,
+}
+
+impl DifferentialHarness {
+    //the statements after the call, `value` is the name the result is bound to and `args` are
+    //the arguments of the call, which are still alive after it
+    pub(crate) fn to_stmts(
+        &self,
+        value: &str,
+        args: &Vec<Expr>,
+        api_graph: &ApiGraph<'_>,
+    ) -> Vec<Stmt> {
+        let api = &api_graph.api_functions[self.api];
+        let partner = &api_graph.api_functions[self.partner];
+        let partner_path = Expr::path(partner.full_name.as_str());
+        let message = format!("`{}` and `{}` disagree", api.full_name, partner.full_name);
+        match self.kind {
+            DifferentialKind::Equivalent => vec![
+                Stmt::Let {
+                    pat: Pat::ident("_differential", false),
+                    init: Expr::call(partner_path, args.clone()),
+                },
+                assertion(Expr::path(value), Expr::path("_differential"), &message),
+            ],
+            DifferentialKind::Unchecked => {
+                let stmts = vec![
+                    Stmt::Let {
+                        pat: Pat::ident("_unchecked", false),
+                        init: Expr::call(partner_path, args.clone()),
+                    },
+                    assertion(Expr::path("_checked"), Expr::path("_unchecked"), &message),
+                ];
+                vec![unwrap_match(value, "_checked", api, api_graph, Block { stmts })]
+            }
+            DifferentialKind::RoundTrip => {
+                let input = compared_input(&args[0], &api.inputs[0]);
+                let encoded_type = final_output(api, api_graph);
+                let call_type = api_util::_same_type(
+                    &encoded_type,
+                    &partner.inputs[0],
+                    true,
+                    &api_graph.full_name_map,
+                    api_graph.cache(),
+                );
+                let encoded = call_type._to_call_expr(
+                    Expr::path("_encoded"),
+                    &api_graph.full_name_map,
+                    api_graph.cache(),
+                );
+                let decoded = Expr::call(partner_path, vec![encoded]);
+                let message = format!(
+                    "`{}` does not give back the input of `{}`",
+                    partner.full_name, api.full_name
+                );
+                let rejected =
+                    format!("`{}` rejects the output of `{}`", partner.full_name, api.full_name);
+                let decode_stmt = match output_prelude_type(partner, api_graph) {
+                    Some(PreludeType::NotPrelude(..)) | None => assertion(decoded, input, &message),
+                    Some(..) => {
+                        let stmts = vec![assertion(Expr::path("_decoded"), input, &message)];
+                        Stmt::Match {
+                            scrutinee: decoded,
+                            arms: vec![
+                                (ok_pat(partner, api_graph, "_decoded"), Block { stmts }),
+                                (Pat::Wild, Block { stmts: vec![panic_stmt(&rejected)] }),
+                            ],
+                        }
+                    }
+                };
+                let stmts = vec![decode_stmt];
+                match output_prelude_type(api, api_graph) {
+                    Some(PreludeType::NotPrelude(..)) | None => {
+                        let mut res = vec![Stmt::Let {
+                            pat: Pat::ident("_encoded", false),
+                            init: Expr::path(value),
+                        }];
+                        res.extend(stmts);
+                        res
+                    }
+                    Some(..) => {
+                        vec![unwrap_match(value, "_encoded", api, api_graph, Block { stmts })]
+                    }
+                }
+            }
+        }
+    }
+}
+
+//`assert!(lhs == rhs, "message")`
+pub(crate) fn assertion(lhs: Expr, rhs: Expr, message: &str) -> Stmt {
+    Stmt::Expr(Expr::MacroCall {
+        name: "assert".to_string(),
+        args: vec![Expr::binary(lhs, "==", rhs), Expr::Lit(format!("{:?}", message))],
+    })
+}
+
+pub(crate) fn panic_stmt(message: &str) -> Stmt {
+    Stmt::Expr(Expr::MacroCall {
+        name: "panic".to_string(),
+        args: vec![Expr::Lit(format!("{:?}", message))],
+    })
+}
+
+//`match value { Some(name) => { .. } _ => {} }`, or `Ok(name)` for a Result
+pub(crate) fn unwrap_match(
+    value: &str,
+    name: &str,
+    api: &ApiFunction,
+    api_graph: &ApiGraph<'_>,
+    ok_block: Block,
+) -> Stmt {
+    Stmt::Match {
+        scrutinee: Expr::path(value),
+        arms: vec![(ok_pat(api, api_graph, name), ok_block), (Pat::Wild, Block::new())],
+    }
+}
+
+pub(crate) fn ok_pat(api: &ApiFunction, api_graph: &ApiGraph<'_>, name: &str) -> Pat {
+    let variant = match output_prelude_type(api, api_graph) {
+        Some(PreludeType::PreludeOption(..)) => "Some",
+        _ => "Ok",
+    };
+    Pat::variant(variant, vec![Pat::ident(name, false)])
+}
+
+//an input taken by reference is compared by value, except for str and slices, which compare
+//with their owned versions
+fn compared_input(arg: &Expr, input_type: &clean::Type) -> Expr {
+    match input_type {
+        clean::Type::BorrowedRef { type_, .. } => match &**type_ {
+            clean::Type::Primitive(clean::PrimitiveType::Str) | clean::Type::Slice(..) => {
+                arg.clone()
+            }
+            _ => Expr::deref(arg.clone()),
+        },
+        _ => arg.clone(),
+    }
+}
+
+pub(crate) fn output_prelude_type(
+    api: &ApiFunction,
+    api_graph: &ApiGraph<'_>,
+) -> Option<PreludeType> {
+    let output = api.output.as_ref()?;
+    let prelude_type = PreludeType::from_type(output, &api_graph.full_name_map, api_graph.cache());
+    //only Option and Result are unwrapped, a Box or an Rc is used as it is
+    if prelude_type._is_wrapper() {
+        return Some(PreludeType::NotPrelude(output.clone()));
+    }
+    Some(prelude_type)
+}
+
+//the output without Option or Result
+pub(crate) fn final_output(api: &ApiFunction, api_graph: &ApiGraph<'_>) -> clean::Type {
+    match output_prelude_type(api, api_graph) {
+        Some(prelude_type) => prelude_type._get_final_type(),
+        None => clean::Type::Tuple(Vec::new()),
+    }
+}
+
+//each target whose last call has a partner is followed by its differential variant
+pub(crate) fn add_differential_variants(
+    api_graph: &ApiGraph<'_>,
+    sequences: Vec<ApiSequence>,
+) -> Vec<ApiSequence> {
+    if !api_graph.cx.options.differential {
+        return sequences;
+    }
+    let pairs = find_pairs(api_graph);
+    let mut res = Vec::new();
+    let mut variant_number = 0;
+    let mut skipped_number = 0;
+    for sequence in sequences {
+        let variant = differential_variant(&sequence, &pairs, api_graph);
+        res.push(sequence);
+        match variant {
+            Some(Some(variant)) => {
+                res.push(variant);
+                variant_number += 1;
+            }
+            Some(None) => skipped_number += 1,
+            None => {}
+        }
+    }
+    println!(
+        "{} api pairs are found, {} differential harnesses are added, {} are skipped",
+        pairs.len(),
+        variant_number,
+        skipped_number
+    );
+    res
+}
+
+//None if the last call has no partner, Some(None) if the comparison can't be written
+fn differential_variant(
+    sequence: &ApiSequence,
+    pairs: &Vec<(usize, usize, DifferentialKind)>,
+    api_graph: &ApiGraph<'_>,
+) -> Option<Option<ApiSequence>> {
+    if sequence._has_harness() {
+        return None;
+    }
+    let call = sequence.len().checked_sub(1)?;
+    let api_call = &sequence.functions[call];
+    let api_index = api_call.func.1;
+    let (_, partner, kind) = pairs.iter().find(|(api, _, _)| *api == api_index)?;
+    let api = &api_graph.api_functions[api_index];
+    //an operator has no arguments to pass to the partner
+    if Operator::of(api).is_some() {
+        return Some(None);
+    }
+    //the arguments are used again after the call
+    let reused_params =
+        if *kind == DifferentialKind::RoundTrip { 1 } else { api_call.params.len() };
+    for (k, (_, _, call_type)) in api_call.params.iter().enumerate().take(reused_params) {
+        if api_util::_move_condition(&api.inputs[k], call_type) {
+            return Some(None);
+        }
+    }
+    let partner_function = &api_graph.api_functions[*partner];
+    let mut variant = sequence.clone();
+    if partner_function._unsafe_tag._is_unsafe() {
+        variant.set_unsafe();
+    }
+    if let Some(trait_full_path) = &partner_function._trait_full_path {
+        variant.add_trait(trait_full_path);
+    }
+    variant._differential =
+        Some(DifferentialHarness { call, api: api_index, partner: *partner, kind: *kind });
+    //the result is compared after the call, so it needs a name
+    let ir = SequenceIr::lower(&variant, api_graph, "_param", "_local");
+    if ir.binding(call).is_none() {
+        return Some(None);
+    }
+    Some(Some(variant))
+}
+
+//(api, partner, kind) for every api that has a partner, the first partner found is taken
+pub(crate) fn find_pairs(api_graph: &ApiGraph<'_>) -> Vec<(usize, usize, DifferentialKind)> {
+    let mut res = Vec::new();
+    let functions = &api_graph.api_functions;
+    for (i, api) in functions.iter().enumerate() {
+        if api._async_tag._is_async() || api._is_generic_function() || api.output.is_none() {
+            continue;
+        }
+        let pair = functions.iter().enumerate().find_map(|(j, partner)| {
+            if i == j
+                || partner._async_tag._is_async()
+                || partner._is_generic_function()
+                || parent_path(&api.full_name) != parent_path(&partner.full_name)
+            {
+                return None;
+            }
+            pair_kind(api, partner, api_graph).map(|kind| (i, j, kind))
+        });
+        if let Some(pair) = pair {
+            println!(
+                "differential: `{}` and `{}` are a {} pair",
+                api.full_name,
+                functions[pair.1].full_name,
+                pair.2.as_str()
+            );
+            res.push(pair);
+        }
+    }
+    res
+}
+
+fn pair_kind(
+    api: &ApiFunction,
+    partner: &ApiFunction,
+    api_graph: &ApiGraph<'_>,
+) -> Option<DifferentialKind> {
+    let api_name = last_segment(&api.full_name);
+    let partner_name = last_segment(&partner.full_name);
+    let partner_output = partner.output.as_ref()?;
+    if api.inputs == partner.inputs {
+        if is_unchecked_pair(api_name, partner_name)
+            && output_prelude_type(api, api_graph).map_or(false, |t| !t._is_final_type())
+            && final_output(api, api_graph) == *partner_output
+            && implements_partial_eq(partner_output, api_graph)
+        {
+            return Some(DifferentialKind::Unchecked);
+        }
+        let is_equivalent = is_renamed(api_name, partner_name, &EQUIVALENT_NAMES)
+            || is_renamed(partner_name, api_name, &EQUIVALENT_NAMES);
+        if is_equivalent
+            && api.output.as_ref() == Some(partner_output)
+            && implements_partial_eq(partner_output, api_graph)
+        {
+            return Some(DifferentialKind::Equivalent);
+        }
+    }
+    if is_renamed(api_name, partner_name, &ROUND_TRIP_NAMES)
+        && api.inputs.len() == 1
+        && partner.inputs.len() == 1
+        && api_util::is_fuzzable_type(&api.inputs[0], &api_graph.full_name_map, api_graph.cache())
+    {
+        let call_type = api_util::_same_type(
+            &final_output(api, api_graph),
+            &partner.inputs[0],
+            true,
+            &api_graph.full_name_map,
+            api_graph.cache(),
+        );
+        let decoded = final_output(partner, api_graph);
+        if call_type != CallType::_NotCompatible
+            && !call_type._contains_unwrap_call_type()
+            && matches_input(&decoded, &api.inputs[0])
+            && implements_partial_eq(&decoded, api_graph)
+        {
+            return Some(DifferentialKind::RoundTrip);
+        }
+    }
+    None
+}
+
+//`name` and `name_unchecked`, `checked_name` and `unchecked_name`
+fn is_unchecked_pair(checked: &str, unchecked: &str) -> bool {
+    if let Some(base) = unchecked.strip_suffix("_unchecked") {
+        return checked == base || checked == format!("{}_checked", base);
+    }
+    if let Some(base) = unchecked.strip_prefix("unchecked_") {
+        return checked == base || checked == format!("checked_{}", base);
+    }
+    false
+}
+
+fn is_renamed(name: &str, partner_name: &str, pairs: &[(&'static str, &'static str)]) -> bool {
+    pairs.iter().any(|(prefix, partner_prefix)| {
+        match (name.strip_prefix(prefix), partner_name.strip_prefix(partner_prefix)) {
+            (Some(rest), Some(partner_rest)) => rest == partner_rest,
+            _ => false,
+        }
+    })
+}
+
+//the decoded value can be compared with the input of the encoder
+fn matches_input(decoded: &clean::Type, input_type: &clean::Type) -> bool {
+    if decoded == input_type {
+        return true;
+    }
+    let inner_type = match input_type {
+        clean::Type::BorrowedRef { type_, .. } => &**type_,
+        _ => return false,
+    };
+    match (inner_type, std_type_name(decoded)) {
+        (clean::Type::Primitive(clean::PrimitiveType::Str), Some("String")) => true,
+        (clean::Type::Slice(element_type), Some("Vec")) => {
+            decoded_generics(decoded).map_or(false, |generics| generics == vec![&**element_type])
+        }
+        _ => decoded == inner_type,
+    }
+}
+
+fn decoded_generics(ty: &clean::Type) -> Option<Vec<&clean::Type>> {
+    match ty {
+        clean::Type::Path { path } => path.generics(),
+        _ => None,
+    }
+}
+
+fn std_type_name(ty: &clean::Type) -> Option<&'static str> {
+    match ty {
+        clean::Type::Path { path } => {
+            let name = path.segments.last()?.name;
+            PARTIAL_EQ_STD_TYPES.iter().find(|std_type| **std_type == name.as_str()).cloned()
+        }
+        _ => None,
+    }
+}
+
+//types of the crate need an impl of PartialEq, std types are assumed to implement it
+pub(crate) fn implements_partial_eq(ty: &clean::Type, api_graph: &ApiGraph<'_>) -> bool {
+    match ty {
+        clean::Type::Primitive(..) => true,
+        clean::Type::BorrowedRef { type_, .. }
+        | clean::Type::Slice(type_)
+        | clean::Type::Array(type_, _) => implements_partial_eq(type_, api_graph),
+        clean::Type::Tuple(types) => types.iter().all(|ty| implements_partial_eq(ty, api_graph)),
+        clean::Type::Path { path } => {
+            if std_type_name(ty).is_some() {
+                return path.generics().map_or(true, |generics| {
+                    generics.into_iter().all(|ty| implements_partial_eq(ty, api_graph))
+                });
+            }
+            api_graph.implements_trait(ty, sym::PartialEq)
+        }
+        _ => false,
+    }
+}
+
+fn parent_path(full_name: &str) -> &str {
+    full_name.rsplit_once("::").map_or("", |(parent, _)| parent)
+}
+
+fn last_segment(full_name: &str) -> &str {
+    full_name.rsplit("::").next().unwrap_or(full_name)
+}
@@ -0,0 +1,144 @@
+//Targets that drop the results of the sequence in an order chosen by the fuzz data. Every value
+//is otherwise dropped at the end of the function in reverse order of creation, so Drop impls and
+//RAII guards that share state (a guard and its lock, an iterator and its collection, handles of
+//the same arena) are only ever run in one order. With `--drop-harness`, a target with several
+//live results is followed by a variant with an u8 fuzzable param choosing one of the orders in
+//which the results can be dropped with explicit `drop()` calls: a result borrowing another one
+//is always dropped first, since the other order doesn't compile.
+use crate::clean;
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::fuzz_target::code_ast::{Block, Expr, Pat, Stmt};
+use crate::fuzz_target::fuzzable_type::FuzzableType;
+use crate::fuzz_target::sequence_ir::{IrType, SequenceIr};
+
+//at most 4! orders
+static MAX_DROPPED_VALUES: usize = 4;
+
+//the results of `values` are dropped after `call` in the order chosen by the fuzzable param
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub(crate) struct DropHarness {
+    pub(crate) call: usize,
+    pub(crate) values: Vec<usize>,
+    //the orders allowed by the borrows, as calls
+    pub(crate) orders: Vec<Vec<usize>>,
+    pub(crate) order_param: usize,
+}
+
+impl DropHarness {
+    //the statements after the call, `ir` gives the names the results are bound to
+    pub(crate) fn to_stmts(&self, ir: &SequenceIr, param_prefix: &str) -> Vec<Stmt> {
+        let mut arms = Vec::new();
+        for (k, order) in self.orders.iter().enumerate() {
+            let mut block = Block::new();
+            for call in order {
+                if let Some(value) = ir.binding(*call) {
+                    let dropped = Expr::path(value.name.as_str());
+                    block.push(Stmt::Expr(Expr::call(Expr::path("drop"), vec![dropped])));
+                }
+            }
+            let pat = if k == self.orders.len() - 1 { Pat::Wild } else { Pat::Lit(k.to_string()) };
+            arms.push((pat, block));
+        }
+        let order_param = Expr::path(format!("{}{}", param_prefix, self.order_param).as_str());
+        let scrutinee = Expr::binary(order_param, "%", Expr::Lit(self.orders.len().to_string()));
+        vec![Stmt::Match { scrutinee, arms }]
+    }
+}
+
+//each target with several results that can be dropped is followed by its drop variant
+pub(crate) fn add_drop_variants(
+    api_graph: &ApiGraph<'_>,
+    sequences: Vec<ApiSequence>,
+) -> Vec<ApiSequence> {
+    if !api_graph.cx.options.drop_harness {
+        return sequences;
+    }
+    let mut res = Vec::new();
+    let mut variant_number = 0;
+    for sequence in sequences {
+        let variant = drop_variant(&sequence, api_graph);
+        res.push(sequence);
+        if let Some(variant) = variant {
+            res.push(variant);
+            variant_number += 1;
+        }
+    }
+    println!("{} drop harnesses are added", variant_number);
+    res
+}
+
+fn drop_variant(sequence: &ApiSequence, api_graph: &ApiGraph<'_>) -> Option<ApiSequence> {
+    if sequence._has_harness() {
+        return None;
+    }
+    let call = sequence.len().checked_sub(1)?;
+    let ir = SequenceIr::lower(sequence, api_graph, "_param", "_local");
+    //owned results that are not moved, references have nothing to drop
+    let droppable: Vec<usize> = (0..=call)
+        .filter(|i| !sequence._is_moved(*i))
+        .filter(|i| match ir.calls[*i].result.map(|value| &ir.values[value].ty) {
+            Some(IrType::Api(ty)) => !matches!(
+                ty,
+                clean::Type::BorrowedRef { .. }
+                    | clean::Type::RawPointer(..)
+                    | clean::Type::Primitive(..)
+            ),
+            _ => false,
+        })
+        .collect();
+    let latest = &droppable[droppable.len().saturating_sub(MAX_DROPPED_VALUES)..];
+    //a value borrowed by a result that is not dropped can't be moved into `drop`
+    let values: Vec<usize> = latest
+        .iter()
+        .cloned()
+        .filter(|owner| {
+            !droppable.iter().any(|borrower| {
+                !latest.contains(borrower)
+                    && sequence._borrowed_owners(*borrower).iter().any(|(index, _)| index == owner)
+            })
+        })
+        .collect();
+    if values.len() < 2 {
+        return None;
+    }
+    let mut orders = Vec::new();
+    add_orders(sequence, &values, &mut Vec::new(), &mut orders);
+    //the order of the end of the function is already covered by the target itself
+    if orders.len() < 2 {
+        return None;
+    }
+    let mut variant = sequence.clone();
+    let order_param = variant.fuzzable_params.len();
+    variant.fuzzable_params.push(FuzzableType::Primitive(clean::PrimitiveType::U8));
+    variant._drop_harness = Some(DropHarness { call, values, orders, order_param });
+    Some(variant)
+}
+
+//every permutation of the values where each borrower comes before the values it borrows
+fn add_orders(
+    sequence: &ApiSequence,
+    values: &Vec<usize>,
+    order: &mut Vec<usize>,
+    res: &mut Vec<Vec<usize>>,
+) {
+    if order.len() == values.len() {
+        res.push(order.clone());
+        return;
+    }
+    for value in values {
+        if order.contains(value) {
+            continue;
+        }
+        let is_borrowed = values.iter().any(|borrower| {
+            !order.contains(borrower)
+                && sequence._borrowed_owners(*borrower).iter().any(|(owner, _)| owner == value)
+        });
+        if is_borrowed {
+            continue;
+        }
+        order.push(*value);
+        add_orders(sequence, values, order, res);
+        order.pop();
+    }
+}
@@ -0,0 +1,159 @@
+//Answer `--explain <path>`: why an api is covered or not, from the data already in the graph. An
+//api can be missing from the graph (private, hidden, deprecated, in a private mod or filtered out
+//by `--include-api` and `--exclude-api`), refused when it was added (generic without a type
+//instantiation, a parameter type that can't be built, newer than the msrv), or be in the graph
+//but not reached by any sequence. For the last case every parameter is explained: built from the
+//fuzz input, or which apis produce it, and the producers that aren't covered are explained in
+//turn, so the chain ends at the type nothing can build.
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_util;
+use crate::fuzz_target::report;
+use crate::fuzz_target::rust_version;
+use rustc_data_structures::fx::FxHashSet;
+
+//the producers of the producers are explained up to this depth
+static MAX_EXPLAIN_DEPTH: usize = 3;
+
+pub(crate) fn print_explanation(graph: &ApiGraph<'_>, api_name: &str) {
+    println!("-----------EXPLAIN-----------");
+    let api_index =
+        graph.api_functions.iter().position(|api_function| api_function.full_name == api_name);
+    match api_index {
+        Some(api_index) => {
+            let mut explained = FxHashSet::default();
+            explain_api(graph, api_index, 0, &mut explained);
+        }
+        None => println!("{}", missing_reason(graph, api_name)),
+    }
+    println!("-----------------------------");
+}
+
+fn explain_api(
+    graph: &ApiGraph<'_>,
+    api_index: usize,
+    depth: usize,
+    explained: &mut FxHashSet<usize>,
+) {
+    let indent = "    ".repeat(depth);
+    let api_name = &graph.api_functions[api_index].full_name;
+    if is_covered(graph, api_index) {
+        let sequence_count = graph
+            .api_sequences
+            .iter()
+            .filter(|sequence| sequence._get_contained_api_functions().contains(&api_index))
+            .count();
+        println!("{}`{}` is covered, {} sequences call it", indent, api_name, sequence_count);
+        return;
+    }
+    if !explained.insert(api_index) {
+        println!("{}`{}` is not covered, see above", indent, api_name);
+        return;
+    }
+    println!("{}`{}` is not covered", indent, api_name);
+    let mut blocked = false;
+    for (k, input) in graph.api_functions[api_index].inputs.iter().enumerate() {
+        let type_name = api_util::_type_name(input, &graph.full_name_map, graph.cache());
+        let param = format!("{}  param {} `{}`", indent, k, type_name);
+        if api_util::is_fuzzable_type(input, &graph.full_name_map, graph.cache()) {
+            println!("{}: built from the fuzz input", param);
+            continue;
+        }
+        let mut producers = Vec::new();
+        for dependency in &graph.api_dependencies {
+            let producer = dependency.output_fun.1;
+            if dependency.input_fun.1 == api_index
+                && dependency.input_param_index == k
+                && !producers.contains(&producer)
+            {
+                producers.push(producer);
+            }
+        }
+        if producers.is_empty() {
+            blocked = true;
+            match api_util::non_exhaustive_struct(input, &graph.full_name_map) {
+                Some(_) => println!(
+                    "{}: no api produces it, and it is `#[non_exhaustive]` so it can't be built \
+                     by a struct literal",
+                    param
+                ),
+                None => println!("{}: no api produces it", param),
+            }
+            continue;
+        }
+        let producer_names: Vec<String> = producers
+            .iter()
+            .map(|producer| format!("`{}`", graph.api_functions[*producer].full_name))
+            .collect();
+        println!("{}: produced by {}", param, producer_names.join(", "));
+        if producers.iter().any(|producer| is_covered(graph, *producer)) {
+            continue;
+        }
+        blocked = true;
+        if depth + 1 >= MAX_EXPLAIN_DEPTH {
+            println!("{}      none of them is covered", indent);
+            continue;
+        }
+        for producer in producers {
+            explain_api(graph, producer, depth + 1, explained);
+        }
+    }
+    if !blocked {
+        println!(
+            "{}  every parameter can be built, the strategy didn't reach it: try a longer \
+             `--max-seq-len` or another `--strategy`",
+            indent
+        );
+    }
+}
+
+fn is_covered(graph: &ApiGraph<'_>, api_index: usize) -> bool {
+    graph.api_functions_visited.get(api_index).cloned().unwrap_or(false)
+}
+
+//why an api is not in the graph
+fn missing_reason(graph: &ApiGraph<'_>, api_name: &str) -> String {
+    if let Some(generic_function) =
+        graph.generic_functions.iter().find(|generic| generic.api_function.full_name == api_name)
+    {
+        return format!(
+            "`{}` is generic and its type parameters are unresolved: {}",
+            api_name,
+            report::generic_suggestion(&generic_function.api_function)
+        );
+    }
+    if graph.functions_with_unsupported_fuzzable_types.contains(api_name) {
+        return format!(
+            "`{}` has a parameter type that is neither built from the fuzz input nor supported \
+             as the return type of an api",
+            api_name
+        );
+    }
+    if graph.functions_newer_than_msrv.contains(api_name) {
+        return format!(
+            "`{}` is async, async apis need rust {}: raise `--msrv`",
+            api_name,
+            rust_version::ASYNC_AWAIT.toolchain()
+        );
+    }
+    let invisible_mods = graph.mod_visibility.get_invisible_mods();
+    if let Some(invisible_mod) = invisible_mods.iter().find(|name| api_name.starts_with(*name)) {
+        return format!(
+            "`{}` is removed by the visibility filter, mod `{}` is not public",
+            api_name, invisible_mod
+        );
+    }
+    let options = &graph.cx.options;
+    if !options.include_apis.is_empty()
+        && !options.include_apis.iter().any(|pattern| pattern.matches(api_name))
+    {
+        return format!("`{}` doesn't match any `--include-api`", api_name);
+    }
+    if options.exclude_apis.iter().any(|pattern| pattern.matches(api_name)) {
+        return format!("`{}` matches an `--exclude-api`", api_name);
+    }
+    format!(
+        "`{}` is not a public api of the crate, or it is `#[doc(hidden)]` or `#[deprecated]` \
+         (see `--include-hidden` and `--include-deprecated`)",
+        api_name
+    )
+}
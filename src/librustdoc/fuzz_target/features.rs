@@ -0,0 +1,277 @@
+//Cargo features of the crate. The crate is documented with the features given by `--features`
+//or `--all-features` (and the default features when the manifest is known), so that apis behind
+//`#[cfg(feature = "..")]` end up in the graph. Every target records the features its apis are
+//gated behind, and a Cargo.toml is written next to the targets that only builds a target when
+//its features are activated.
+use crate::clean::cfg::Cfg;
+use crate::docfs::PathError;
+use crate::error::Error;
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::fuzz_target::cargo_metadata::LibTarget;
+use crate::fuzz_target::dependency;
+use crate::fuzz_target::file_util::{self, FileHelper};
+use crate::fuzz_target::fuzz_target_options::AsyncRuntime;
+use crate::fuzz_target::harness_emitter::{self, HarnessEmitter, TESTS_DIR};
+use crate::fuzz_target::runtime_crate::{self, RUNTIME_CRATE};
+use crate::fuzz_target::serde_producer;
+use crate::try_err;
+use rustc_span::symbol::sym;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+static DEFAULT_FEATURE: &'static str = "default";
+
+//the features enabled by the command line, with the features they enable in turn. Optional
+//dependencies (`dep:x`) and features of dependencies (`x/y`) are not features of the crate
+pub(crate) fn enabled_features(
+    requested: &Vec<String>,
+    all_features: bool,
+    lib_target: Option<&LibTarget>,
+) -> Result<Vec<String>, String> {
+    let feature_table = match lib_target {
+        Some(lib_target) => &lib_target.features,
+        None => {
+            let res: BTreeSet<String> = requested.iter().cloned().collect();
+            return Ok(res.into_iter().collect());
+        }
+    };
+    let mut worklist: Vec<String> =
+        if all_features { feature_table.keys().cloned().collect() } else { requested.clone() };
+    for feature in &worklist {
+        if !feature_table.contains_key(feature) {
+            return Err(format!("package has no feature `{}`", feature));
+        }
+    }
+    if feature_table.contains_key(DEFAULT_FEATURE) {
+        worklist.push(DEFAULT_FEATURE.to_string());
+    }
+    let mut res = BTreeSet::new();
+    while let Some(feature) = worklist.pop() {
+        if feature.starts_with("dep:") || feature.contains('/') || !res.insert(feature.clone()) {
+            continue;
+        }
+        if let Some(implied_features) = feature_table.get(&feature) {
+            worklist.extend(implied_features.iter().cloned());
+        }
+    }
+    Ok(res.into_iter().collect())
+}
+
+//the `--cfg` options documenting the crate with the features
+pub(crate) fn feature_cfgs(features: &Vec<String>) -> Vec<String> {
+    features.iter().map(|feature| format!("feature=\"{}\"", feature)).collect()
+}
+
+//the features an item is gated behind. Of `any(..)`, the first alternative that is enabled is
+//taken, `not(..)` never requires a feature
+pub(crate) fn required_features(cfg: Option<&Cfg>, enabled_features: &Vec<String>) -> Vec<String> {
+    let mut res = BTreeSet::new();
+    if let Some(cfg) = cfg {
+        collect_required_features(cfg, enabled_features, &mut res);
+    }
+    res.into_iter().collect()
+}
+
+fn collect_required_features(
+    cfg: &Cfg,
+    enabled_features: &Vec<String>,
+    res: &mut BTreeSet<String>,
+) {
+    match cfg {
+        Cfg::Cfg(name, Some(value)) if *name == sym::feature => {
+            res.insert(value.to_string());
+        }
+        Cfg::All(cfgs) => {
+            for cfg in cfgs {
+                collect_required_features(cfg, enabled_features, res);
+            }
+        }
+        Cfg::Any(cfgs) => {
+            let alternatives: Vec<BTreeSet<String>> = cfgs
+                .iter()
+                .map(|cfg| {
+                    let mut features = BTreeSet::new();
+                    collect_required_features(cfg, enabled_features, &mut features);
+                    features
+                })
+                .collect();
+            let alternative = alternatives
+                .iter()
+                .find(|features| features.iter().all(|feature| enabled_features.contains(feature)))
+                .or(alternatives.first());
+            if let Some(alternative) = alternative {
+                res.extend(alternative.iter().cloned());
+            }
+        }
+        _ => {}
+    }
+}
+
+//the features the apis of the sequence are gated behind
+pub(crate) fn sequence_features(sequence: &ApiSequence, api_graph: &ApiGraph<'_>) -> Vec<String> {
+    let mut res = BTreeSet::new();
+    for api_call in &sequence.functions {
+        let full_name = &api_graph.api_functions[api_call.func.1].full_name;
+        if let Some(features) = api_graph.api_features.get(full_name) {
+            res.extend(features.iter().cloned());
+        }
+    }
+    //apis called by the differential or round trip checks after the sequence
+    for function_index in sequence._harness_apis() {
+        let full_name = &api_graph.api_functions[function_index].full_name;
+        if let Some(features) = api_graph.api_features.get(full_name) {
+            res.extend(features.iter().cloned());
+        }
+    }
+    res.into_iter().collect()
+}
+
+//features of the package activated by the dependency on it, the default ones are activated anyway
+pub(crate) fn dependency_features(api_graph: &ApiGraph<'_>) -> Vec<String> {
+    api_graph
+        .cx
+        .options
+        .features
+        .iter()
+        .filter(|feature| feature.as_str() != DEFAULT_FEATURE)
+        .cloned()
+        .collect()
+}
+
+//the dependency on the runtime the async apis are called with
+pub(crate) fn async_runtime_dependency(api_graph: &ApiGraph<'_>) -> &'static str {
+    match api_graph.cx.options.async_runtime {
+        AsyncRuntime::Futures => "futures = \"0.3\"",
+        AsyncRuntime::Tokio => "tokio = { version = \"1\", features = [\"rt\"] }",
+    }
+}
+
+//`features = ["a", "b"]`
+pub(crate) fn toml_list(items: &Vec<String>) -> String {
+    let items: Vec<String> = items.iter().map(|item| format!("{:?}", item)).collect();
+    format!("[{}]", items.join(", "))
+}
+
+//the Cargo.toml of the fuzz crate, with a bin for every target of the emitters writing bins, and
+//the crates they use. The files of an emitter written outside the test directory, like the
+//libFuzzer targets of a crate with a libFuzzer directory, belong to another crate. Every feature
+//of the package is forwarded by a feature of the fuzz crate, and all of them are enabled by default
+pub(crate) fn write_manifest(
+    api_graph: &ApiGraph<'_>,
+    helper: &FileHelper,
+    emitters: &[&dyn HarnessEmitter],
+) -> Result<(), Error> {
+    let lib_target = match &api_graph.cx.options.lib_target {
+        Some(lib_target) => lib_target,
+        None => {
+            println!("note: Cargo.toml of the targets is not written, it needs `--manifest-path`");
+            return Ok(());
+        }
+    };
+    let package_dir = lib_target.manifest_path.parent().unwrap_or(Path::new("."));
+    let mut res = format!("[package]\nname = \"{}-fuzz\"\n", lib_target.package_name);
+    res.push_str("version = \"0.0.0\"\nedition = \"2018\"\npublish = false\n\n");
+
+    let forwarded_features = dependency_features(api_graph);
+    res.push_str("[features]\n");
+    res.push_str(format!("default = {}\n", toml_list(&forwarded_features)).as_str());
+    for feature in &forwarded_features {
+        let package_feature = vec![format!("{}/{}", lib_target.package_name, feature)];
+        res.push_str(format!("{} = {}\n", feature, toml_list(&package_feature)).as_str());
+    }
+
+    let test_dir = Path::new(&helper.test_dir);
+    let sequences = &helper.chosen_sequences;
+    //the directory of the bins of every emitter, relative to the test directory
+    let bin_emitters: Vec<(&dyn HarnessEmitter, PathBuf)> = emitters
+        .iter()
+        .filter(|emitter| emitter.is_bin())
+        .filter_map(|emitter| {
+            let output_dir = emitter.output_dir(helper);
+            let dir = output_dir.strip_prefix(test_dir).ok()?.to_path_buf();
+            Some((*emitter, dir))
+        })
+        .collect();
+    res.push_str("\n[dependencies]\n");
+    for (emitter, _) in &bin_emitters {
+        if let Some(dependency) = emitter.dependency() {
+            res.push_str(format!("{}\n", dependency).as_str());
+        }
+    }
+    res.push_str(
+        format!(
+            "{} = {{ path = {:?} }}\n",
+            lib_target.package_name,
+            package_dir.display().to_string()
+        )
+        .as_str(),
+    );
+    if api_graph.cx.options.runtime_crate {
+        res.push_str(runtime_crate::dependency(RUNTIME_CRATE).as_str());
+    }
+    if sequences.iter().any(|sequence| sequence._async_tag) {
+        res.push_str(async_runtime_dependency(api_graph));
+        res.push('\n');
+    }
+    if let Some(serde_dependency) = serde_producer::serde_dependency(api_graph, sequences) {
+        res.push_str(serde_dependency);
+        res.push('\n');
+    }
+    res.push_str(dependency::manifest_lines(api_graph, sequences).as_str());
+    //the tests in `tests/` are found by cargo
+    let dev_dependencies: Vec<&str> = emitters
+        .iter()
+        .filter(|emitter| emitter.output_dir(helper) == test_dir.join(TESTS_DIR))
+        .filter_map(|emitter| emitter.dev_dependency())
+        .collect();
+    if !dev_dependencies.is_empty() {
+        res.push_str("\n[dev-dependencies]\n");
+        for dev_dependency in dev_dependencies {
+            res.push_str(format!("{}\n", dev_dependency).as_str());
+        }
+    }
+
+    let mut features_of_targets: BTreeMap<Vec<String>, usize> = BTreeMap::new();
+    let naming = api_graph.cx.options.naming;
+    for (i, sequence) in sequences.iter().enumerate() {
+        //features enabled by default are required anyway
+        let required_features: Vec<String> = sequence_features(sequence, api_graph)
+            .into_iter()
+            .filter(|feature| forwarded_features.contains(feature))
+            .collect();
+        *features_of_targets.entry(required_features.clone()).or_insert(0) += 1;
+        let target_name = &helper.target_names[i];
+        for (emitter, dir) in &bin_emitters {
+            let file_name = file_util::test_file_name(naming, emitter.file_prefix(), target_name);
+            let bin_name = harness_emitter::bin_name(*emitter, naming, target_name);
+            res.push_str("\n[[bin]]\n");
+            res.push_str(format!("name = {:?}\n", bin_name).as_str());
+            res.push_str(
+                format!("path = {:?}\n", dir.join(file_name).display().to_string()).as_str(),
+            );
+            if !required_features.is_empty() {
+                res.push_str(
+                    format!("required-features = {}\n", toml_list(&required_features)).as_str(),
+                );
+            }
+        }
+    }
+    //keep the crate out of any workspace around the test directory
+    res.push_str("\n[workspace]\n");
+
+    let manifest_path = test_dir.join("Cargo.toml");
+    try_err!(fs::write(&manifest_path, res), &manifest_path);
+    for (required_features, target_number) in features_of_targets {
+        if !required_features.is_empty() {
+            println!(
+                "{} targets require features: {}",
+                target_number,
+                required_features.join(", ")
+            );
+        }
+    }
+    println!("Cargo.toml of the targets is written to {}", manifest_path.display());
+    Ok(())
+}
@@ -0,0 +1,93 @@
+//Crates wrapping a C library, usually through a `-sys` crate. A memory error in the C code
+//doesn't panic, so the targets reaching it only find bugs when they are built with
+//AddressSanitizer. With `--ffi`, the extern functions re-exported by the crate are added to the
+//graph like other unsafe apis, and every api whose body, or the body of a function of the crate
+//it calls, refers to an extern function is an ffi api. The targets calling an ffi api are marked
+//in targets.toml, and the AFL commands of the run manifests build them with
+//`-Z sanitizer=address`. cargo fuzz builds every libFuzzer target with it already.
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::TyCtxt;
+use rustc_data_structures::fx::FxHashSet;
+use rustc_hir as hir;
+use rustc_hir::def::{DefKind, Res};
+use rustc_hir::intravisit::{self, Visitor};
+use rustc_middle::hir::nested_filter;
+use rustc_middle::ty::TypeckResults;
+use rustc_span::def_id::DefId;
+
+pub(crate) static SANITIZER: &'static str = "address";
+pub(crate) static SANITIZER_FLAGS: &'static str = "-Z sanitizer=address";
+
+//an extern function, or a function of the crate calling one, directly or through other functions
+//of the crate
+pub(crate) fn is_ffi_function(tcx: TyCtxt<'_>, def_id: DefId) -> bool {
+    tcx.is_foreign_item(def_id) || reaches_foreign_item(tcx, def_id, &mut FxHashSet::default())
+}
+
+fn reaches_foreign_item(tcx: TyCtxt<'_>, def_id: DefId, visited: &mut FxHashSet<DefId>) -> bool {
+    if !visited.insert(def_id) {
+        return false;
+    }
+    let body_id = def_id.as_local().and_then(|def_id| tcx.hir().maybe_body_owned_by(def_id));
+    let body_id = match body_id {
+        Some(body_id) => body_id,
+        None => return false,
+    };
+    let mut finder = ForeignItemFinder {
+        tcx,
+        typeck_results: tcx.typeck_body(body_id),
+        found: false,
+        callees: Vec::new(),
+    };
+    finder.visit_body(tcx.hir().body(body_id));
+    finder.found
+        || finder.callees.into_iter().any(|callee| reaches_foreign_item(tcx, callee, visited))
+}
+
+//the sequence, or the checks after it, call an ffi api
+pub(crate) fn calls_ffi(sequence: &ApiSequence, api_graph: &ApiGraph<'_>) -> bool {
+    sequence
+        .function_indexes()
+        .into_iter()
+        .chain(sequence._harness_apis())
+        .any(|index| api_graph.ffi_apis.contains(&api_graph.api_functions[index].full_name))
+}
+
+//paths to extern functions and statics in a body, closures included, and the functions of the
+//crate it calls
+struct ForeignItemFinder<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    typeck_results: &'tcx TypeckResults<'tcx>,
+    found: bool,
+    callees: Vec<DefId>,
+}
+
+impl<'tcx> Visitor<'tcx> for ForeignItemFinder<'tcx> {
+    type NestedFilter = nested_filter::OnlyBodies;
+
+    fn nested_visit_map(&mut self) -> Self::Map {
+        self.tcx.hir()
+    }
+
+    fn visit_expr(&mut self, ex: &'tcx hir::Expr<'tcx>) {
+        let def_id = match &ex.kind {
+            hir::ExprKind::Path(qpath) => match self.typeck_results.qpath_res(qpath, ex.hir_id) {
+                Res::Def(_, def_id) => Some(def_id),
+                _ => None,
+            },
+            hir::ExprKind::MethodCall(..) => self.typeck_results.type_dependent_def_id(ex.hir_id),
+            _ => None,
+        };
+        if let Some(def_id) = def_id {
+            if self.tcx.is_foreign_item(def_id) {
+                self.found = true;
+            } else if def_id.is_local()
+                && matches!(self.tcx.def_kind(def_id), DefKind::Fn | DefKind::AssocFn)
+            {
+                self.callees.push(def_id);
+            }
+        }
+        intravisit::walk_expr(self, ex);
+    }
+}
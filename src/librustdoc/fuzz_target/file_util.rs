@@ -1,10 +1,32 @@
+use crate::fuzz_target::api_filter;
 use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::fuzz_target::api_usage;
+use crate::fuzz_target::borrow_check;
+use crate::fuzz_target::compile_check;
+use crate::fuzz_target::contract;
+use crate::fuzz_target::differential;
+use crate::fuzz_target::drop_order;
+use crate::fuzz_target::format_harness;
+use crate::fuzz_target::fuzz_target_options::{Naming, Validation};
+use crate::fuzz_target::harness_emitter::HarnessEmitter;
+use crate::fuzz_target::incremental::{self, Regeneration};
+use crate::fuzz_target::miri_check;
+use crate::fuzz_target::operator;
+use crate::fuzz_target::risk_rank;
+use crate::fuzz_target::round_trip;
+use crate::fuzz_target::runtime_borrow;
+use crate::fuzz_target::sequence_ir::SequenceIr;
+use crate::fuzz_target::sequence_select;
 use lazy_static::lazy_static;
 use rustc_data_structures::fx::{FxHashMap};
 use std::fs;
 use std::io::Write;
 use std::path::PathBuf;
 
+#[cfg(test)]
+mod tests;
+
 lazy_static! {
     static ref CRATE_TEST_DIR: FxHashMap<&'static str, &'static str> = {
         let mut m = FxHashMap::default();
@@ -67,9 +89,9 @@
     };
 }
 
-static _TEST_FILE_DIR: &'static str = "test_files";
-static _REPRODUCE_FILE_DIR: &'static str = "replay_files";
-static _LIBFUZZER_DIR_NAME: &'static str = "libfuzzer_files";
+pub(crate) static _TEST_FILE_DIR: &'static str = "test_files";
+pub(crate) static _REPRODUCE_FILE_DIR: &'static str = "replay_files";
+pub(crate) static _LIBFUZZER_DIR_NAME: &'static str = "libfuzzer_files";
 static MAX_TEST_FILE_NUMBER: usize = 300;
 static DEFAULT_RANDOM_FILE_NUMBER: usize = 100;
 
@@ -93,13 +115,22 @@ pub(crate) fn can_generate_libfuzzer_target(crate_name: &String) -> bool {
     }
 }
 
+//libfuzzer的target写入的目录
+pub(crate) fn libfuzzer_files_dir(crate_name: &String) -> Option<PathBuf> {
+    let libfuzzer_dir = LIBFUZZER_FUZZ_TARGET_DIR.get(crate_name.as_str())?;
+    Some(PathBuf::from(libfuzzer_dir).join(_LIBFUZZER_DIR_NAME))
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct FileHelper {
     pub(crate) crate_name: String,
     pub(crate) test_dir: String,
-    pub(crate) test_files: Vec<String>,
-    pub(crate) reproduce_files: Vec<String>,
-    pub(crate) libfuzzer_files: Vec<String>,
+    //sequences written to files, the i-th sequence is written to the i-th file of every emitter
+    pub(crate) chosen_sequences: Vec<ApiSequence>,
+    //the name of the i-th sequence in the file names of every emitter, see `target_names`
+    pub(crate) target_names: Vec<String>,
+    //the targets that are the same as in the previous report, with `--incremental`
+    pub(crate) regeneration: Regeneration,
 }
 
 impl FileHelper {
@@ -110,78 +141,221 @@ pub(crate) fn new(api_graph: &ApiGraph<'_>, random_strategy: bool) -> Self {
         } else {
             RANDOM_TEST_DIR.get(crate_name.as_str()).unwrap().to_string()
         };
-        let mut sequence_count = 0;
-        let mut test_files = Vec::new();
-        let mut reproduce_files = Vec::new();
-        let mut libfuzzer_files = Vec::new();
-        //let chosen_sequences = api_graph._naive_choose_sequence(MAX_TEST_FILE_NUMBER);
-        let chosen_sequences = if !random_strategy {
-            api_graph._heuristic_choose(MAX_TEST_FILE_NUMBER, true)
-        } else {
-            let random_size = if RANDOM_TEST_FILE_NUMBERS.contains_key(crate_name.as_str()) {
-                (RANDOM_TEST_FILE_NUMBERS.get(crate_name.as_str()).unwrap()).clone()
-            } else {
-                DEFAULT_RANDOM_FILE_NUMBER
-            };
-            api_graph._first_choose(random_size)
-        };
-        //println!("chosen sequences number: {}", chosen_sequences.len());
+        let chosen_sequences = choose_sequences(api_graph, random_strategy);
+        let target_names = target_names(api_graph, &chosen_sequences);
+        let regeneration = incremental::compare(api_graph, &chosen_sequences, &target_names);
+        FileHelper { crate_name, test_dir, chosen_sequences, target_names, regeneration }
+    }
+
+    //the files of the chosen sequences in the code of the emitter
+    pub(crate) fn emit_files(
+        &self,
+        api_graph: &ApiGraph<'_>,
+        emitter: &dyn HarnessEmitter,
+    ) -> Vec<String> {
+        self.chosen_sequences
+            .iter()
+            .enumerate()
+            .map(|(i, sequence)| emitter.emit(sequence, api_graph, i))
+            .collect()
+    }
 
-        for sequence in &chosen_sequences {
-            if sequence_count >= MAX_TEST_FILE_NUMBER {
-                break;
+    //every emitter writes to a directory of its own, which is emptied first. With `--incremental`
+    //only the files of the removed targets are deleted, and the unchanged targets are not written
+    pub(crate) fn write_files(&self, api_graph: &ApiGraph<'_>, emitters: &[&dyn HarnessEmitter]) {
+        let naming = api_graph.cx.options.naming;
+        for emitter in emitters {
+            let output_path = emitter.output_dir(self);
+            if let Some(parent) = output_path.parent() {
+                if parent.is_file() {
+                    fs::remove_file(parent).unwrap();
+                }
+            }
+            if !self.regeneration.incremental {
+                ensure_empty_dir(&output_path);
+            } else {
+                fs::create_dir_all(&output_path).unwrap();
+                for target_name in &self.regeneration.removed {
+                    let file_name = test_file_name(naming, emitter.file_prefix(), target_name);
+                    let removed_file = output_path.join(file_name);
+                    if removed_file.is_file() {
+                        fs::remove_file(removed_file).unwrap();
+                    }
+                }
+            }
+            for (i, sequence) in self.chosen_sequences.iter().enumerate() {
+                let file_name =
+                    test_file_name(naming, emitter.file_prefix(), &self.target_names[i]);
+                let full_filename = output_path.join(file_name);
+                if self.regeneration.is_unchanged(i) && full_filename.is_file() {
+                    continue;
+                }
+                let mut file = fs::File::create(full_filename).unwrap();
+                file.write_all(emitter.emit(sequence, api_graph, i).as_bytes()).unwrap();
             }
-            let test_file = sequence._to_afl_test_file(api_graph, sequence_count);
-            test_files.push(test_file);
-            let reproduce_file = sequence._to_replay_crash_file(api_graph, sequence_count);
-            reproduce_files.push(reproduce_file);
-            let libfuzzer_file = sequence._to_libfuzzer_test_file(api_graph, sequence_count);
-            libfuzzer_files.push(libfuzzer_file);
-            sequence_count = sequence_count + 1;
         }
-        FileHelper { crate_name, test_dir, test_files, reproduce_files, libfuzzer_files }
     }
+}
+
+//选出要写入文件的序列，第i个序列会被写入第i个文件
+pub(crate) fn choose_sequences(
+    api_graph: &ApiGraph<'_>,
+    random_strategy: bool,
+) -> Vec<ApiSequence> {
+    let chosen_sequences = select_sequences(api_graph, random_strategy);
+    let chosen_sequences = add_tail_variants(api_graph, chosen_sequences);
+    let chosen_sequences = differential::add_differential_variants(api_graph, chosen_sequences);
+    let chosen_sequences = round_trip::add_round_trip_variants(api_graph, chosen_sequences);
+    let chosen_sequences = format_harness::add_format_variants(api_graph, chosen_sequences);
+    let chosen_sequences = operator::add_operator_variants(api_graph, chosen_sequences);
+    let chosen_sequences = drop_order::add_drop_variants(api_graph, chosen_sequences);
+    let chosen_sequences = contract::add_contract_variants(api_graph, chosen_sequences);
 
-    pub(crate) fn write_files(&self) {
-        let test_path = PathBuf::from(&self.test_dir);
-        if test_path.is_file() {
-            fs::remove_file(&test_path).unwrap();
+    let mut written_sequences = Vec::new();
+    for sequence in chosen_sequences {
+        //检查生成的序列，借用冲突去掉冲突的调用来修复，其他有问题的序列直接跳过，而不是生成无法编译的文件
+        let ir = SequenceIr::lower(&sequence, api_graph, "_param", "_local");
+        if let Err(e) = ir.check(api_graph) {
+            match borrow_check::repair(&sequence, api_graph) {
+                Some(repaired_sequence) => {
+                    println!("generator: sequence is repaired, {}", e);
+                    written_sequences.push(repaired_sequence);
+                }
+                None => println!("generator error: invalid sequence is skipped, {}", e),
+            }
+            continue;
+        }
+        //RefCell、Mutex等在运行时检查的借用冲突，调整顺序或者拆分成多个序列
+        for resolved_sequence in runtime_borrow::resolve(sequence, api_graph) {
+            if !written_sequences.contains(&resolved_sequence) {
+                written_sequences.push(resolved_sequence);
+            }
         }
-        let test_file_path = test_path.clone().join(_TEST_FILE_DIR);
-        ensure_empty_dir(&test_file_path);
-        let reproduce_file_path = test_path.clone().join(_REPRODUCE_FILE_DIR);
-        ensure_empty_dir(&reproduce_file_path);
+    }
+    //变体和拆分出的序列会再次调用同样的api，所以要在这之后再限制每个api的target数
+    let mut written_sequences = api_usage::cap(api_graph, written_sequences);
+    if api_graph.cx.options.compile_check {
+        written_sequences = compile_check::check_sequences(api_graph, written_sequences);
+    }
+    if api_graph.cx.options.validation == Some(Validation::Miri) {
+        written_sequences = miri_check::check_sequences(api_graph, written_sequences);
+    }
+    let written_sequences = risk_rank::rank(api_graph, written_sequences);
+    api_usage::print_histogram(&api_usage::histogram(api_graph, &written_sequences));
+    written_sequences
+}
 
-        write_to_files(&self.crate_name, &test_file_path, &self.test_files, "test");
-        //暂时用test file代替一下，后续改成真正的reproduce file
-        write_to_files(&self.crate_name, &reproduce_file_path, &self.reproduce_files, "replay");
+//只按覆盖率选出序列，不加变体，也不做编译和miri检查
+pub(crate) fn select_sequences(
+    api_graph: &ApiGraph<'_>,
+    random_strategy: bool,
+) -> Vec<ApiSequence> {
+    let crate_name = &api_graph._crate_name;
+    let max_targets = api_graph.cx.options.max_targets;
+    //let chosen_sequences = api_graph._naive_choose_sequence(MAX_TEST_FILE_NUMBER);
+    let chosen_sequences = if !random_strategy {
+        sequence_select::select_sequences(api_graph, max_targets.unwrap_or(MAX_TEST_FILE_NUMBER))
+    } else {
+        let random_size = if RANDOM_TEST_FILE_NUMBERS.contains_key(crate_name.as_str()) {
+            (RANDOM_TEST_FILE_NUMBERS.get(crate_name.as_str()).unwrap()).clone()
+        } else {
+            DEFAULT_RANDOM_FILE_NUMBER
+        };
+        api_graph._first_choose(max_targets.unwrap_or(random_size))
+    };
+    //println!("chosen sequences number: {}", chosen_sequences.len());
+    let chosen_sequences = api_usage::balance(
+        api_graph,
+        chosen_sequences,
+        max_targets.unwrap_or(MAX_TEST_FILE_NUMBER),
+    );
+    api_filter::add_entry_targets(api_graph, chosen_sequences)
+}
+
+//每个序列之后紧跟着它的兄弟序列(只有最后一个调用不同)
+fn add_tail_variants(api_graph: &ApiGraph<'_>, sequences: Vec<ApiSequence>) -> Vec<ApiSequence> {
+    let tail_variants = api_graph.cx.options.tail_variants;
+    if tail_variants <= 0 {
+        return sequences;
     }
+    let mut res = Vec::new();
+    for sequence in sequences {
+        let variants = api_graph.tail_variants(&sequence, tail_variants);
+        res.push(sequence);
+        res.extend(variants);
+    }
+    res
+}
 
-    pub(crate) fn write_libfuzzer_files(&self) {
-        let libfuzzer_dir = LIBFUZZER_FUZZ_TARGET_DIR.get(self.crate_name.as_str()).unwrap();
-        let libfuzzer_path = PathBuf::from(libfuzzer_dir);
-        if libfuzzer_path.is_file() {
-            fs::remove_file(&libfuzzer_path).unwrap();
-        }
-        let libfuzzer_files_path = libfuzzer_path.join(_LIBFUZZER_DIR_NAME);
-        ensure_empty_dir(&libfuzzer_files_path);
-        write_to_files(
-            &self.crate_name,
-            &libfuzzer_files_path,
-            &self.libfuzzer_files,
-            "fuzz_target",
+//目标文件名，默认命名时AFL和libFuzzer的目标都是`fuzz_<名字>.rs`，它们在不同的目录中；
+//`--naming legacy`时保留原来的前缀`test`和`fuzz_target`。其他文件的前缀不变
+pub(crate) fn test_file_name(naming: Naming, prefix: &str, target_name: &str) -> String {
+    let prefix = match prefix {
+        "test" | "fuzz_target" if naming == Naming::Stable => "fuzz",
+        _ => prefix,
+    };
+    format!("{}_{}.rs", prefix, target_name)
+}
+
+//目标文件名中序列的名字。默认是`<crate>_<入口api>_<hash>`，hash是序列代码的64位摘要，
+//序列不变名字就不变，重新生成时不会因为其他序列的增减而改名；`--naming legacy`时是`<crate><序号>`
+pub(crate) fn target_names(api_graph: &ApiGraph<'_>, sequences: &Vec<ApiSequence>) -> Vec<String> {
+    let crate_name = &api_graph._crate_name;
+    if api_graph.cx.options.naming == Naming::Legacy {
+        return (0..sequences.len()).map(|i| format!("{}{}", crate_name, i)).collect();
+    }
+    let mut names: Vec<String> = Vec::new();
+    for sequence in sequences {
+        let name = format!(
+            "{}_{}_{:016x}",
+            crate_name,
+            entry_api_name(api_graph, sequence),
+            stable_hash(sequence._to_well_written_function(api_graph, 0, 0).as_bytes())
         );
+        //不同的序列生成了相同的代码，加上后缀区分
+        let mut unique_name = name.clone();
+        let mut suffix = 1;
+        while names.contains(&unique_name) {
+            unique_name = format!("{}_{}", name, suffix);
+            suffix += 1;
+        }
+        names.push(unique_name);
+    }
+    names
+}
+
+//入口api，即序列的第一个调用，去掉crate名，只保留标识符中可以用的字符
+fn entry_api_name(api_graph: &ApiGraph<'_>, sequence: &ApiSequence) -> String {
+    let api_call = match sequence.functions.first() {
+        Some(api_call) => api_call,
+        None => return "empty".to_string(),
+    };
+    api_file_name(api_graph, &api_graph.api_functions[api_call.func.1].full_name)
+}
+
+//api的路径去掉crate名，转成小写，各段之间用`_`连接，可以用在文件名中
+pub(crate) fn api_file_name(api_graph: &ApiGraph<'_>, full_name: &String) -> String {
+    let crate_prefix = format!("{}::", api_graph._crate_name);
+    let name = full_name.strip_prefix(crate_prefix.as_str()).unwrap_or(full_name);
+    let mut res = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            res.push(c.to_ascii_lowercase());
+        } else if !res.is_empty() && !res.ends_with('_') {
+            res.push('_');
+        }
     }
+    res.trim_end_matches('_').to_string()
 }
 
-fn write_to_files(crate_name: &String, path: &PathBuf, contents: &Vec<String>, prefix: &str) {
-    let file_number = contents.len();
-    for i in 0..file_number {
-        let filename = format!("{}_{}{}.rs", prefix, crate_name, i);
-        let full_filename = path.join(filename);
-        let mut file = fs::File::create(full_filename).unwrap();
-        file.write_all(contents[i].as_bytes()).unwrap();
+//FNV-1a，和std的hasher不同，结果不随rustc版本和运行而变化
+pub(crate) fn stable_hash(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
     }
+    hash
 }
 
 fn ensure_empty_dir(path: &PathBuf) {
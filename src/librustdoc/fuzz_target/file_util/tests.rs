@@ -0,0 +1,22 @@
+use super::stable_hash;
+
+//the test vectors of FNV-1a 64
+#[test]
+fn fnv1a_vectors() {
+    assert_eq!(stable_hash(b""), 0xcbf29ce484222325);
+    assert_eq!(stable_hash(b"a"), 0xaf63dc4c8601ec8c);
+    assert_eq!(stable_hash(b"foobar"), 0x85944171f73967e8);
+}
+
+#[test]
+fn order_matters() {
+    assert_ne!(stable_hash(b"ab"), stable_hash(b"ba"));
+    assert_ne!(stable_hash(b"\0"), stable_hash(b""));
+}
+
+//the full 64 bits are used in the target names
+#[test]
+fn high_bits() {
+    assert!(stable_hash(b"a") > u32::MAX as u64);
+    assert_eq!(format!("{:016x}", stable_hash(b"")), "cbf29ce484222325");
+}
@@ -0,0 +1,144 @@
+//Targets that format the result of the sequence with fuzzed width, precision and flags, like
+//`format!("{:>w$.p$}", x)`. Display and Debug impls that honour the options of the `Formatter`
+//(padding, `{:#?}`, ...) have code paths that a plain `{}` never reaches. Three u8 fuzzable
+//params are appended to the sequence: the width, the precision and the format spec to use.
+//With `--exercise-format`, every value returned in a sequence is also formatted plainly, with
+//`{:?}` and `{}`, right after the call that builds it.
+use crate::clean;
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::fuzz_target::code_ast::{Block, Expr, Pat, Stmt};
+use crate::fuzz_target::fuzzable_type::FuzzableType;
+use crate::fuzz_target::sequence_ir::{IrType, SequenceIr};
+use rustc_span::symbol::sym;
+
+//fill, alignment, sign, `#` and `0` flags put before the width
+static FORMAT_SPECS: [&'static str; 8] = ["", "<", "^", ">", "+", "#", "0", "*^#"];
+//the width, the precision and the index of the format spec
+pub(crate) static FORMAT_PARAMS: usize = 3;
+
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub(crate) enum FormatTrait {
+    Display,
+    Debug,
+}
+
+impl FormatTrait {
+    fn type_suffix(&self) -> &'static str {
+        match self {
+            FormatTrait::Display => "",
+            FormatTrait::Debug => "?",
+        }
+    }
+}
+
+//the result of `call` is formatted with the fuzzable params from `first_param`
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub(crate) struct FormatHarness {
+    pub(crate) call: usize,
+    pub(crate) format_trait: FormatTrait,
+    pub(crate) first_param: usize,
+}
+
+impl FormatHarness {
+    //the statements after the call, `value` is the name the result is bound to
+    pub(crate) fn to_stmts(&self, value: &str, param_prefix: &str) -> Vec<Stmt> {
+        let param =
+            |k: usize| Expr::path(format!("{}{}", param_prefix, self.first_param + k).as_str());
+        let mut res = vec![
+            Stmt::Let { pat: Pat::ident("_width", false), init: Expr::cast(param(0), "usize") },
+            Stmt::Let { pat: Pat::ident("_precision", false), init: Expr::cast(param(1), "usize") },
+        ];
+        let mut arms = Vec::new();
+        for (k, spec) in FORMAT_SPECS.iter().enumerate() {
+            let format_string =
+                format!("\"{{:{}w$.p${}}}\"", spec, self.format_trait.type_suffix());
+            let formatted = Expr::MacroCall {
+                name: "format".to_string(),
+                args: vec![
+                    Expr::Lit(format_string),
+                    Expr::path(value),
+                    Expr::Raw("w = _width".to_string()),
+                    Expr::Raw("p = _precision".to_string()),
+                ],
+            };
+            let pat = if k == FORMAT_SPECS.len() - 1 { Pat::Wild } else { Pat::Lit(k.to_string()) };
+            arms.push((pat, Block { stmts: vec![Stmt::Let { pat: Pat::Wild, init: formatted }] }));
+        }
+        let scrutinee = Expr::binary(param(2), "%", Expr::Lit(FORMAT_SPECS.len().to_string()));
+        res.push(Stmt::Match { scrutinee, arms });
+        res
+    }
+}
+
+//`let _ = format!("{:?}", value);` and `let _ = format!("{}", value);`, for the format traits
+//the type implements
+pub(crate) fn exercise_stmts(ty: &clean::Type, value: &str, api_graph: &ApiGraph<'_>) -> Vec<Stmt> {
+    let mut res = Vec::new();
+    for (format_trait, name) in
+        [(FormatTrait::Debug, sym::Debug), (FormatTrait::Display, sym::Display)]
+    {
+        if !api_graph.implements_trait(ty, name) {
+            continue;
+        }
+        let formatted = Expr::MacroCall {
+            name: "format".to_string(),
+            args: vec![
+                Expr::Lit(format!("\"{{:{}}}\"", format_trait.type_suffix())),
+                Expr::path(value),
+            ],
+        };
+        res.push(Stmt::Let { pat: Pat::Wild, init: formatted });
+    }
+    res
+}
+
+//each target whose result can be formatted is followed by its format variant
+pub(crate) fn add_format_variants(
+    api_graph: &ApiGraph<'_>,
+    sequences: Vec<ApiSequence>,
+) -> Vec<ApiSequence> {
+    if !api_graph.cx.options.format_harness {
+        return sequences;
+    }
+    let mut res = Vec::new();
+    let mut variant_number = 0;
+    for sequence in sequences {
+        let variant = format_variant(&sequence, api_graph);
+        res.push(sequence);
+        if let Some(variant) = variant {
+            res.push(variant);
+            variant_number += 1;
+        }
+    }
+    println!("{} format harnesses are added", variant_number);
+    res
+}
+
+fn format_variant(sequence: &ApiSequence, api_graph: &ApiGraph<'_>) -> Option<ApiSequence> {
+    if sequence._has_harness() {
+        return None;
+    }
+    let call = sequence.len().checked_sub(1)?;
+    let ir = SequenceIr::lower(sequence, api_graph, "_param", "_local");
+    let value = &ir.values[ir.calls[call].result?];
+    let format_trait = match &value.ty {
+        IrType::Api(ty) => implemented_format_trait(ty, api_graph)?,
+        IrType::Fuzzable(_) => return None,
+    };
+    let mut variant = sequence.clone();
+    let first_param = variant.fuzzable_params.len();
+    for _ in 0..FORMAT_PARAMS {
+        variant.fuzzable_params.push(FuzzableType::Primitive(clean::PrimitiveType::U8));
+    }
+    variant._format_harness = Some(FormatHarness { call, format_trait, first_param });
+    Some(variant)
+}
+
+//Display is preferred, since Debug output is usually derived
+fn implemented_format_trait(ty: &clean::Type, api_graph: &ApiGraph<'_>) -> Option<FormatTrait> {
+    [(FormatTrait::Display, sym::Display), (FormatTrait::Debug, sym::Debug)]
+        .into_iter()
+        .find(|(_, name)| api_graph.implements_trait(ty, *name))
+        .map(|(format_trait, _)| format_trait)
+}
@@ -0,0 +1,1006 @@
+//Command line options of the fuzz target generator
+use crate::fuzz_target::api_filter::ApiPattern;
+use crate::fuzz_target::cargo_metadata::{self, LibTarget};
+use crate::fuzz_target::dependency::DependencyApi;
+use crate::fuzz_target::features;
+use crate::fuzz_target::generic_function::GenericHints;
+use crate::fuzz_target::graph_traverse::{DEFAULT_STRATEGY, StrategyRegistry};
+use crate::fuzz_target::harness_emitter::EmitterRegistry;
+use crate::fuzz_target::macro_api::MacroApi;
+use crate::fuzz_target::rulf_config;
+use crate::fuzz_target::rust_version::{self, RustVersion};
+use crate::fuzz_target::sink_search::SINK_STRATEGY;
+use rustc_session::getopts;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[cfg(test)]
+mod tests;
+
+static DEFAULT_MAX_STRUCT_DEPTH: usize = 3;
+static DEFAULT_MAX_SEQ_LEN: usize = 3;
+
+//the executor used to run sequences that call async apis
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) enum AsyncRuntime {
+    //futures::executor::block_on
+    Futures,
+    //a current thread tokio runtime
+    Tokio,
+}
+
+impl AsyncRuntime {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "futures" => Some(AsyncRuntime::Futures),
+            "tokio" => Some(AsyncRuntime::Tokio),
+            _ => None,
+        }
+    }
+
+    //the crate that has to be added to the dependencies of the fuzz targets
+    pub(crate) fn crate_name(&self) -> &'static str {
+        match self {
+            AsyncRuntime::Futures => "futures",
+            AsyncRuntime::Tokio => "tokio",
+        }
+    }
+}
+
+//how Result and Option values are unwrapped before they are passed to the next call
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) enum ErrorPolicy {
+    //return from the test function on Err and None
+    EarlyReturn,
+    //panic on Err and None, so that unexpected errors are reported as crashes
+    Unwrap,
+    //match on the value, the Err and None branch goes on with the calls that don't depend on it,
+    //so that the library keeps being exercised after an error
+    MatchBoth,
+}
+
+impl ErrorPolicy {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "early-return" => Some(ErrorPolicy::EarlyReturn),
+            "unwrap" => Some(ErrorPolicy::Unwrap),
+            "match-both" => Some(ErrorPolicy::MatchBoth),
+            _ => None,
+        }
+    }
+}
+
+//how a value is compared with the value it was encoded and decoded to
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub(crate) enum Equivalence {
+    //PartialEq if the type implements it, the Debug output otherwise
+    Auto,
+    Eq,
+    //the Debug output, for types without PartialEq or with a PartialEq that is too strict
+    Debug,
+}
+
+impl Equivalence {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(Equivalence::Auto),
+            "eq" => Some(Equivalence::Eq),
+            "debug" => Some(Equivalence::Debug),
+            _ => None,
+        }
+    }
+}
+
+//how the sequences written to target files are chosen among the generated ones
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) enum SelectionMode {
+    //the sequence covering the most uncovered apis first
+    Greedy,
+    //the fewest sequences covering all apis, for small crates
+    Exact,
+}
+
+impl SelectionMode {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "greedy" => Some(SelectionMode::Greedy),
+            "exact" => Some(SelectionMode::Exact),
+            _ => None,
+        }
+    }
+}
+
+//what is done with the preconditions of the `# Panics` section of the apis
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) enum PanicPolicy {
+    //return before the call when a precondition written as a condition on the parameters holds,
+    //targets calling apis with other preconditions are tagged as expected to panic
+    Guard,
+    //only tag the targets calling apis with a documented panic as expected to panic
+    Tag,
+    Ignore,
+}
+
+impl PanicPolicy {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "guard" => Some(PanicPolicy::Guard),
+            "tag" => Some(PanicPolicy::Tag),
+            "ignore" => Some(PanicPolicy::Ignore),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn guards(&self) -> bool {
+        *self == PanicPolicy::Guard
+    }
+}
+
+//sanitizers the workspace has a build profile for
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) enum Sanitizer {
+    Address,
+    Memory,
+    Leak,
+}
+
+impl Sanitizer {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "address" | "asan" => Some(Sanitizer::Address),
+            "memory" | "msan" => Some(Sanitizer::Memory),
+            "leak" | "lsan" => Some(Sanitizer::Leak),
+            _ => None,
+        }
+    }
+
+    //the cargo profile building the targets with the sanitizer
+    pub(crate) fn profile(&self) -> &'static str {
+        match self {
+            Sanitizer::Address => "asan",
+            Sanitizer::Memory => "msan",
+            Sanitizer::Leak => "lsan",
+        }
+    }
+
+    pub(crate) fn rustflags(&self) -> &'static [&'static str] {
+        match self {
+            Sanitizer::Address => &["-Zsanitizer=address"],
+            Sanitizer::Memory => &["-Zsanitizer=memory", "-Zsanitizer-memory-track-origins"],
+            Sanitizer::Leak => &["-Zsanitizer=leak"],
+        }
+    }
+
+    //memory sanitizer reports every read of memory written by uninstrumented code, so std has
+    //to be built with it too
+    pub(crate) fn needs_build_std(&self) -> bool {
+        *self == Sanitizer::Memory
+    }
+}
+
+//how the targets are run once before they are written
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) enum Validation {
+    //under Miri, to find undefined behavior in the generated code
+    Miri,
+}
+
+impl Validation {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "miri" => Some(Validation::Miri),
+            _ => None,
+        }
+    }
+}
+
+//the format the values of `--serde-producer` are deserialized from
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) enum SerdeFormat {
+    Json,
+    Bincode,
+}
+
+impl SerdeFormat {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "json" => Some(SerdeFormat::Json),
+            "bincode" => Some(SerdeFormat::Bincode),
+            _ => None,
+        }
+    }
+
+    //the crate that has to be added to the dependencies of the fuzz targets
+    pub(crate) fn crate_name(&self) -> &'static str {
+        match self {
+            SerdeFormat::Json => "serde_json",
+            SerdeFormat::Bincode => "bincode",
+        }
+    }
+
+    pub(crate) fn dependency(&self) -> &'static str {
+        match self {
+            SerdeFormat::Json => "serde_json = \"1\"",
+            SerdeFormat::Bincode => "bincode = \"1\"",
+        }
+    }
+
+    //the function deserializing a value from a `&[u8]`
+    pub(crate) fn deserialize_function(&self) -> &'static str {
+        match self {
+            SerdeFormat::Json => "serde_json::from_slice",
+            SerdeFormat::Bincode => "bincode::deserialize",
+        }
+    }
+}
+
+//how the target files are named
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) enum Naming {
+    //`fuzz_<crate>_<entry api>_<hash>.rs`, the entry api is the first call and the hash is a digest
+    //of the code of the sequence, so a target keeps its name when the others change
+    Stable,
+    //`test_<crate><index>.rs`, the index of the sequence
+    Legacy,
+}
+
+impl Naming {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "stable" => Some(Naming::Stable),
+            "legacy" => Some(Naming::Legacy),
+            _ => None,
+        }
+    }
+}
+
+//convert a crash input of a generated target into a standalone test
+#[derive(Debug, Clone)]
+pub(crate) struct CrashToTest {
+    //name of the target file, e.g. `fuzz_url_url_parse_1f2e3d4c5b6a7988`
+    pub(crate) target: String,
+    pub(crate) crash_file: PathBuf,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct FuzzTargetOptions {
+    //the name of a registered strategy, by default bfs + backward search
+    pub(crate) strategy: &'static str,
+    pub(crate) selection: SelectionMode,
+    //threads enumerating the sequences, None means one per cpu
+    pub(crate) jobs: Option<usize>,
+    //the longest sequence enumerated by bfs
+    pub(crate) max_seq_len: usize,
+    //number of targets chosen to cover the apis, variants come on top of them. None means the
+    //default of the strategy
+    pub(crate) max_targets: Option<usize>,
+    //merge the chosen sequences of every connected component of the graph into one long target
+    pub(crate) mega_sequence: bool,
+    //number of sibling targets generated for each sequence by replacing the last call
+    pub(crate) tail_variants: usize,
+    //None means an api may appear in any number of targets
+    pub(crate) max_targets_per_api: Option<usize>,
+    //reachable apis get at least this number of targets, even if it exceeds the cap of other apis.
+    //None means no targets are added for the apis called by too few of the chosen ones
+    pub(crate) min_targets_per_api: Option<usize>,
+    //lib target read from the Cargo.toml given by `--manifest-path` with cargo metadata
+    pub(crate) lib_target: Option<LibTarget>,
+    pub(crate) async_runtime: AsyncRuntime,
+    pub(crate) error_policy: ErrorPolicy,
+    pub(crate) crash_to_test: Option<CrashToTest>,
+    //None means the generated code is built with the nightly toolchain used by afl.rs
+    pub(crate) msrv: Option<RustVersion>,
+    //check the targets with `cargo check` and remove or repair the ones that don't compile
+    pub(crate) compile_check: bool,
+    //add a target formatting the result with fuzzed width, precision and flags for every target
+    //whose result implements Display or Debug
+    pub(crate) format_harness: bool,
+    //direct dependencies whose apis are added to the graph, e.g. `form_urlencoded`
+    pub(crate) include_deps: Vec<String>,
+    //search the direct dependencies for the producers of their types the apis of the crate take
+    pub(crate) dependency_producers: bool,
+    //apis of `include_deps` and the producers found in dependencies, filled in after the crate is
+    //cleaned
+    pub(crate) dependency_apis: Vec<DependencyApi>,
+    //the crates of `dependency_apis`, their paths are named in the targets
+    pub(crate) dependency_crates: Vec<String>,
+    //exported functions and methods defined by macro expansions, filled in after the crate is
+    //cleaned
+    pub(crate) macro_apis: Vec<MacroApi>,
+    //features the crate is documented with, including the ones enabled by other features
+    pub(crate) features: Vec<String>,
+    //how deep structs with public fields are built from the fuzz data inside other structs and
+    //enums, 0 means structs are only produced by apis
+    pub(crate) max_struct_depth: usize,
+    //add a target asserting that the last api agrees with its partner for every target ending
+    //with an api of an equivalent, checked/unchecked or encode/decode pair
+    pub(crate) differential: bool,
+    //add a target asserting that the result of the last call survives an encode and decode
+    //round trip, for every target whose result has an encoder and a decoder
+    pub(crate) round_trip: bool,
+    pub(crate) round_trip_equivalence: Equivalence,
+    pub(crate) panic_policy: PanicPolicy,
+    //catch the panics of every call, so that only aborts like memory errors and double panics
+    //are reported as crashes
+    pub(crate) ignore_panics: bool,
+    //reuse the dependencies of the api graph cached by a previous run on the same crate
+    pub(crate) graph_cache: bool,
+    //the targets are `#![no_std]`, the calls to the crate only use core and alloc
+    pub(crate) no_std: bool,
+    //write the targets as a cargo workspace with a crate for every target, instead of loose files
+    pub(crate) workspace: bool,
+    //write targets.toml and the commands running the targets with each fuzzer
+    pub(crate) run_manifest: bool,
+    //write the OSS-Fuzz project files for the libfuzzer targets
+    pub(crate) ossfuzz: bool,
+    //usage dumps of crates using the crate, their call sequences are extended by the strategy
+    pub(crate) usage_paths: Vec<PathBuf>,
+    //write the calls of the crate to this file for `--usage-from`, instead of generating targets
+    pub(crate) usage_dump: Option<PathBuf>,
+    //also fuzz `#[doc(hidden)]` items, which are skipped by default
+    pub(crate) include_hidden: bool,
+    //also fuzz `#[deprecated]` items, which are skipped by default
+    pub(crate) include_deprecated: bool,
+    //only the apis matching one of these patterns are added to the graph, if there are any
+    pub(crate) include_apis: Vec<ApiPattern>,
+    //the apis matching one of these patterns are not added to the graph
+    pub(crate) exclude_apis: Vec<ApiPattern>,
+    //every api matching one of these patterns is the last call of at least one target
+    pub(crate) entry_apis: Vec<ApiPattern>,
+    //concrete types of the type parameters of generic apis, from `[generic-hints]` of rulf.toml
+    pub(crate) generic_hints: GenericHints,
+    //add a target sorting and hashing the results of the type of the last result, for every
+    //target whose result implements Ord or Hash
+    pub(crate) operator_harness: bool,
+    //add a target dropping the results in an order chosen by the fuzz data, for every target
+    //with several results that can be dropped
+    pub(crate) drop_harness: bool,
+    //add a target checking the laws of Eq, Ord and Hash on three results of the same sequence, for
+    //the first target whose result implements one of them
+    pub(crate) contract_harness: bool,
+    //the crate wraps a C library: re-exported extern functions are fuzzed too, and the targets
+    //calling into C are built with AddressSanitizer
+    pub(crate) ffi: bool,
+    //the workspace gets a cargo profile building the targets with each of these sanitizers
+    pub(crate) sanitizers: Vec<Sanitizer>,
+    //run every target once before it is written, and remove the ones that fail
+    pub(crate) validation: Option<Validation>,
+    //write Kani proof harnesses with nondeterministic inputs for the targets with a few calls
+    pub(crate) kani: bool,
+    //return before a call whose arguments fail an assertion at the top of the api
+    pub(crate) assert_guards: bool,
+    //libFuzzer targets taking strings mutate their input at char boundaries
+    pub(crate) char_mutator: bool,
+    //only print the dependency analysis of this api, no files are written
+    pub(crate) explain: Option<String>,
+    //write a self-contained html page of the report next to the json one
+    pub(crate) html_report: bool,
+    //write the metrics of the paper as csv, with every strategy run for comparison
+    pub(crate) emit_metrics: bool,
+    //the names of the registered harness emitters writing the targets, empty for the default ones
+    pub(crate) harness_emitters: Vec<&'static str>,
+    //`T::default()` produces the params of types implementing Default that no api returns
+    pub(crate) default_producer: bool,
+    //the params of types implementing Deserialize that no api returns are deserialized from the
+    //fuzz input in this format
+    pub(crate) serde_producer: Option<SerdeFormat>,
+    //the inputs an AFL target runs in one process before it is forked again, the afl crate's
+    //default if None
+    pub(crate) afl_persistent_iterations: Option<usize>,
+    //run an AFL target once on a zeroed input before the fork server starts, so that the lazy
+    //initialization of the crate is done once
+    pub(crate) afl_deferred_init: bool,
+    //the targets import the decoding helpers from the `rulf_runtime` crate written next to them
+    pub(crate) runtime_crate: bool,
+    pub(crate) naming: Naming,
+    //only the targets that changed since the previous report in the output directory are written
+    pub(crate) incremental: bool,
+    //every const fn is the last call of a target, and gets probes evaluating it at compile time
+    pub(crate) const_probes: bool,
+    //order the targets by a score of the risky operations their apis reach
+    pub(crate) rank_targets: bool,
+    //the generation stops when it runs out, and keeps the sequences found so far
+    pub(crate) time_budget: Option<Duration>,
+    //the most sequences of a bfs level extended to the next one, the others are spilled to disk
+    pub(crate) beam_width: Option<usize>,
+    //the apis the sink strategy finds the shortest producer chains of
+    pub(crate) sink_apis: Vec<ApiPattern>,
+    //apis writing into a `&mut [T]` buffer and returning a usize get it checked against the length
+    pub(crate) assert_buffer_bounds: bool,
+    //returned indexes, strs and lengths are checked against what their signatures imply
+    pub(crate) check_invariants: bool,
+    //every returned value is formatted with Debug and Display, the ones its type implements
+    pub(crate) exercise_format: bool,
+    //the values at the end of a target are cloned and compared with the originals
+    pub(crate) exercise_clone: bool,
+    //every target gets a dictionary of the literals and magic numbers of the crate
+    pub(crate) dictionaries: bool,
+    //set by the fuzz target generator, plain rustdoc takes the same options but doesn't need the
+    //apis only the targets use
+    pub(crate) generate_targets: bool,
+}
+
+impl Default for FuzzTargetOptions {
+    fn default() -> Self {
+        FuzzTargetOptions {
+            strategy: DEFAULT_STRATEGY,
+            selection: SelectionMode::Greedy,
+            jobs: None,
+            max_seq_len: DEFAULT_MAX_SEQ_LEN,
+            max_targets: None,
+            mega_sequence: false,
+            tail_variants: 0,
+            max_targets_per_api: None,
+            min_targets_per_api: None,
+            lib_target: None,
+            async_runtime: AsyncRuntime::Futures,
+            error_policy: ErrorPolicy::EarlyReturn,
+            crash_to_test: None,
+            msrv: None,
+            compile_check: false,
+            format_harness: false,
+            include_deps: Vec::new(),
+            dependency_producers: false,
+            dependency_apis: Vec::new(),
+            dependency_crates: Vec::new(),
+            macro_apis: Vec::new(),
+            features: Vec::new(),
+            max_struct_depth: DEFAULT_MAX_STRUCT_DEPTH,
+            differential: false,
+            round_trip: false,
+            round_trip_equivalence: Equivalence::Auto,
+            panic_policy: PanicPolicy::Tag,
+            ignore_panics: false,
+            graph_cache: false,
+            no_std: false,
+            workspace: false,
+            run_manifest: false,
+            ossfuzz: false,
+            usage_paths: Vec::new(),
+            usage_dump: None,
+            include_hidden: false,
+            include_deprecated: false,
+            include_apis: Vec::new(),
+            exclude_apis: Vec::new(),
+            entry_apis: Vec::new(),
+            generic_hints: GenericHints::default(),
+            operator_harness: false,
+            drop_harness: false,
+            contract_harness: false,
+            ffi: false,
+            sanitizers: Vec::new(),
+            validation: None,
+            kani: false,
+            assert_guards: true,
+            char_mutator: false,
+            explain: None,
+            html_report: false,
+            emit_metrics: false,
+            harness_emitters: Vec::new(),
+            default_producer: false,
+            serde_producer: None,
+            afl_persistent_iterations: None,
+            afl_deferred_init: false,
+            runtime_crate: false,
+            naming: Naming::Stable,
+            incremental: false,
+            const_probes: false,
+            rank_targets: false,
+            time_budget: None,
+            beam_width: None,
+            sink_apis: Vec::new(),
+            assert_buffer_bounds: false,
+            check_invariants: false,
+            exercise_format: false,
+            exercise_clone: false,
+            dictionaries: false,
+            generate_targets: false,
+        }
+    }
+}
+
+impl FuzzTargetOptions {
+    pub(crate) fn new(
+        matches: &getopts::Matches,
+        diag: &rustc_errors::Handler,
+    ) -> Result<Self, i32> {
+        let strategy = match matches.opt_str("strategy") {
+            None if matches.opt_present("sink-api") => SINK_STRATEGY,
+            None => DEFAULT_STRATEGY,
+            Some(name) => {
+                let registry = StrategyRegistry::new();
+                match registry.get(&name) {
+                    Some(strategy) => strategy.name(),
+                    None => {
+                        diag.struct_err(&format!(
+                            "unknown sequence generation strategy `{}`",
+                            name
+                        ))
+                        .help(&format!("valid strategies are: {}", registry.names().join(", ")))
+                        .emit();
+                        return Err(1);
+                    }
+                }
+            }
+        };
+        let selection = match matches.opt_str("selection") {
+            None => SelectionMode::Greedy,
+            Some(name) => match SelectionMode::from_name(&name) {
+                Some(selection) => selection,
+                None => {
+                    diag.struct_err(&format!("unknown selection mode `{}`", name))
+                        .help("valid modes are: greedy, exact")
+                        .emit();
+                    return Err(1);
+                }
+            },
+        };
+        let jobs = parse_count(matches, diag, "jobs")?;
+        if jobs == Some(0) {
+            diag.struct_err("`--jobs` must be at least 1").emit();
+            return Err(1);
+        }
+        let max_seq_len = parse_count(matches, diag, "max-seq-len")?.unwrap_or(DEFAULT_MAX_SEQ_LEN);
+        if max_seq_len == 0 {
+            diag.struct_err("`--max-seq-len` must be at least 1").emit();
+            return Err(1);
+        }
+        let max_targets = parse_count(matches, diag, "max-targets")?;
+        if max_targets == Some(0) {
+            diag.struct_err("`--max-targets` must be at least 1").emit();
+            return Err(1);
+        }
+        let tail_variants = parse_count(matches, diag, "tail-variants")?.unwrap_or(0);
+        let max_targets_per_api = parse_count(matches, diag, "max-targets-per-api")?;
+        let min_targets_per_api = parse_count(matches, diag, "min-targets-per-api")?;
+        if let Some(max_targets_per_api) = max_targets_per_api {
+            if max_targets_per_api == 0 {
+                diag.struct_err("`--max-targets-per-api` must be at least 1").emit();
+                return Err(1);
+            }
+        }
+        //with `--crate-type bin`, the documented root is a bin target of the package
+        let is_bin = matches
+            .opt_strs("crate-type")
+            .iter()
+            .flat_map(|crate_types| crate_types.split(','))
+            .any(|crate_type| crate_type.trim() == "bin");
+        let bin_src_path = match matches.free.first() {
+            Some(file) if is_bin => Some(PathBuf::from(file)),
+            _ => None,
+        };
+        let lib_target = match matches.opt_str("manifest-path") {
+            None => None,
+            Some(manifest_path) => {
+                let manifest_path = PathBuf::from(manifest_path);
+                match cargo_metadata::lib_target(&manifest_path, bin_src_path.as_deref()) {
+                    Ok(lib_target) => Some(lib_target),
+                    Err(e) => {
+                        diag.struct_err(&format!("failed to read the lib target: {}", e)).emit();
+                        return Err(1);
+                    }
+                }
+            }
+        };
+        let async_runtime = match matches.opt_str("async-runtime") {
+            None => AsyncRuntime::Futures,
+            Some(name) => match AsyncRuntime::from_name(&name) {
+                Some(async_runtime) => async_runtime,
+                None => {
+                    diag.struct_err(&format!("unknown async runtime `{}`", name))
+                        .help("valid runtimes are: futures, tokio")
+                        .emit();
+                    return Err(1);
+                }
+            },
+        };
+        let no_std = matches.opt_present("no-std");
+        let error_policy = match matches.opt_str("error-policy") {
+            //early return exits the process, which needs std
+            None if no_std => ErrorPolicy::MatchBoth,
+            None => ErrorPolicy::EarlyReturn,
+            Some(name) => match ErrorPolicy::from_name(&name) {
+                Some(error_policy) => error_policy,
+                None => {
+                    diag.struct_err(&format!("unknown error policy `{}`", name))
+                        .help("valid policies are: early-return, unwrap, match-both")
+                        .emit();
+                    return Err(1);
+                }
+            },
+        };
+        if no_std && error_policy == ErrorPolicy::EarlyReturn {
+            diag.struct_err("`--error-policy early-return` can't be used with `--no-std`")
+                .help("use `--error-policy match-both` or `--error-policy unwrap`")
+                .emit();
+            return Err(1);
+        }
+        let ignore_panics = matches.opt_present("ignore-panics");
+        if no_std && ignore_panics {
+            diag.struct_err("`--ignore-panics` can't be used with `--no-std`")
+                .help("panics are caught with `std::panic::catch_unwind`")
+                .emit();
+            return Err(1);
+        }
+        let crash_to_test = match (matches.opt_str("crash-target"), matches.opt_str("crash-file")) {
+            (None, None) => None,
+            (Some(target), Some(crash_file)) => {
+                Some(CrashToTest { target, crash_file: PathBuf::from(crash_file) })
+            }
+            _ => {
+                diag.struct_err("`--crash-target` and `--crash-file` must be used together").emit();
+                return Err(1);
+            }
+        };
+        let msrv = match matches.opt_str("msrv") {
+            None => None,
+            Some(version) => match RustVersion::parse(&version) {
+                Some(msrv) if msrv >= rust_version::OLDEST_SUPPORTED => Some(msrv),
+                Some(_) => {
+                    diag.struct_err(&format!("`--msrv {}` is not supported", version))
+                        .help(&format!(
+                            "the generated code needs at least Rust {}",
+                            rust_version::OLDEST_SUPPORTED.toolchain()
+                        ))
+                        .emit();
+                    return Err(1);
+                }
+                None => {
+                    diag.struct_err(&format!("invalid Rust version `{}`", version))
+                        .help("the version is written as `1.56` or `1.56.1`")
+                        .emit();
+                    return Err(1);
+                }
+            },
+        };
+        let compile_check = matches.opt_present("compile-check");
+        if compile_check && lib_target.is_none() {
+            diag.struct_err("`--compile-check` needs `--manifest-path`")
+                .help("the targets are checked against the package of the manifest")
+                .emit();
+            return Err(1);
+        }
+        let validation = match matches.opt_str("validate") {
+            None => None,
+            Some(name) => match Validation::from_name(&name) {
+                Some(validation) => Some(validation),
+                None => {
+                    diag.struct_err(&format!("unknown validation `{}`", name))
+                        .help("valid validations are: miri")
+                        .emit();
+                    return Err(1);
+                }
+            },
+        };
+        if validation.is_some() && lib_target.is_none() {
+            diag.struct_err("`--validate` needs `--manifest-path`")
+                .help("the targets are run with the package of the manifest")
+                .emit();
+            return Err(1);
+        }
+        let workspace = matches.opt_present("emit-workspace");
+        if workspace && lib_target.is_none() {
+            diag.struct_err("`--emit-workspace` needs `--manifest-path`")
+                .help("the crates of the workspace depend on the package of the manifest")
+                .emit();
+            return Err(1);
+        }
+        let mut sanitizers = Vec::new();
+        if let Some(names) = matches.opt_str("sanitizer") {
+            for name in names.split(',').map(|name| name.trim()).filter(|name| !name.is_empty()) {
+                match Sanitizer::from_name(name) {
+                    Some(sanitizer) if !sanitizers.contains(&sanitizer) => {
+                        sanitizers.push(sanitizer)
+                    }
+                    Some(_) => {}
+                    None => {
+                        diag.struct_err(&format!("unknown sanitizer `{}`", name))
+                            .help("valid sanitizers are: address, memory, leak")
+                            .emit();
+                        return Err(1);
+                    }
+                }
+            }
+        }
+        if !sanitizers.is_empty() && !workspace {
+            diag.struct_err("`--sanitizer` needs `--emit-workspace`")
+                .help("the sanitizer builds are profiles of the workspace")
+                .emit();
+            return Err(1);
+        }
+        let ossfuzz = matches.opt_present("emit-ossfuzz");
+        if ossfuzz && lib_target.is_none() {
+            diag.struct_err("`--emit-ossfuzz` needs `--manifest-path`")
+                .help("the project is named after the package, and clones its repository")
+                .emit();
+            return Err(1);
+        }
+        let kani = matches.opt_present("emit-kani");
+        if kani && lib_target.is_none() {
+            diag.struct_err("`--emit-kani` needs `--manifest-path`")
+                .help("the harnesses are a crate depending on the package")
+                .emit();
+            return Err(1);
+        }
+        //crate names use `_` instead of `-`
+        let include_deps = match matches.opt_str("include-deps") {
+            None => Vec::new(),
+            Some(names) => names
+                .split(',')
+                .map(|name| name.trim().replace('-', "_"))
+                .filter(|name| !name.is_empty())
+                .collect(),
+        };
+        let all_features = matches.opt_present("all-features");
+        if all_features && lib_target.is_none() {
+            diag.struct_err("`--all-features` needs `--manifest-path`")
+                .help("the features are read from the manifest")
+                .emit();
+            return Err(1);
+        }
+        let requested_features = match matches.opt_str("features") {
+            None => Vec::new(),
+            Some(names) => names
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|name| !name.is_empty())
+                .map(|name| name.to_string())
+                .collect(),
+        };
+        let features = match features::enabled_features(
+            &requested_features,
+            all_features,
+            lib_target.as_ref(),
+        ) {
+            Ok(features) => features,
+            Err(e) => {
+                diag.struct_err(&format!("invalid `--features`: {}", e)).emit();
+                return Err(1);
+            }
+        };
+        let usage_paths = match matches.opt_str("usage-from") {
+            None => Vec::new(),
+            Some(paths) => paths
+                .split(',')
+                .filter(|path| !path.trim().is_empty())
+                .map(|path| PathBuf::from(path.trim()))
+                .collect(),
+        };
+        if let Some(path) = usage_paths.iter().find(|path| !path.exists()) {
+            diag.struct_err(&format!("`--usage-from` path {} does not exist", path.display()))
+                .emit();
+            return Err(1);
+        }
+        let include_apis = parse_api_patterns(matches, diag, "include-api")?;
+        let exclude_apis = parse_api_patterns(matches, diag, "exclude-api")?;
+        let entry_apis = parse_api_patterns(matches, diag, "entry-api")?;
+        let generic_hints = match rulf_config::generic_hints(matches) {
+            Ok(generic_hints) => generic_hints,
+            Err(e) => {
+                diag.struct_err(&e).emit();
+                return Err(1);
+            }
+        };
+        let max_struct_depth =
+            parse_count(matches, diag, "max-struct-depth")?.unwrap_or(DEFAULT_MAX_STRUCT_DEPTH);
+        let mut harness_emitters = Vec::new();
+        let emitters = EmitterRegistry::new();
+        for name in matches.opt_strs("emit-harness").iter().flat_map(|names| names.split(',')) {
+            match emitters.get(name.trim()) {
+                Some(emitter) if !harness_emitters.contains(&emitter.name()) => {
+                    harness_emitters.push(emitter.name())
+                }
+                Some(_) => {}
+                None => {
+                    diag.struct_err(&format!("unknown harness emitter `{}`", name.trim()))
+                        .help(&format!("valid emitters are: {}", emitters.names().join(", ")))
+                        .emit();
+                    return Err(1);
+                }
+            }
+        }
+        let afl_persistent_iterations = parse_count(matches, diag, "afl-persistent-iterations")?;
+        if afl_persistent_iterations == Some(0) {
+            diag.struct_err("`--afl-persistent-iterations` must be at least 1").emit();
+            return Err(1);
+        }
+        let serde_producer = match matches.opt_str("serde-producer") {
+            None => None,
+            Some(name) => match SerdeFormat::from_name(&name) {
+                Some(serde_format) => Some(serde_format),
+                None => {
+                    diag.struct_err(&format!("unknown serde format `{}`", name))
+                        .help("valid formats are: json, bincode")
+                        .emit();
+                    return Err(1);
+                }
+            },
+        };
+        let naming = match matches.opt_str("naming") {
+            None => Naming::Stable,
+            Some(name) => match Naming::from_name(&name) {
+                Some(naming) => naming,
+                None => {
+                    diag.struct_err(&format!("unknown naming `{}`", name))
+                        .help("valid namings are: stable, legacy")
+                        .emit();
+                    return Err(1);
+                }
+            },
+        };
+        let round_trip_equivalence = match matches.opt_str("round-trip-equivalence") {
+            None => Equivalence::Auto,
+            Some(name) => match Equivalence::from_name(&name) {
+                Some(equivalence) => equivalence,
+                None => {
+                    diag.struct_err(&format!("unknown round trip equivalence `{}`", name))
+                        .help("valid equivalences are: auto, eq, debug")
+                        .emit();
+                    return Err(1);
+                }
+            },
+        };
+        let panic_policy = match matches.opt_str("documented-panics") {
+            None => PanicPolicy::Tag,
+            Some(name) => match PanicPolicy::from_name(&name) {
+                Some(panic_policy) => panic_policy,
+                None => {
+                    diag.struct_err(&format!("unknown policy for documented panics `{}`", name))
+                        .help("valid policies are: guard, tag, ignore")
+                        .emit();
+                    return Err(1);
+                }
+            },
+        };
+        let time_budget = match matches.opt_str("time-budget") {
+            None => None,
+            Some(value) => match parse_duration(value.trim()) {
+                Some(time_budget) if !time_budget.is_zero() => Some(time_budget),
+                _ => {
+                    diag.struct_err(&format!("invalid `--time-budget` `{}`", value))
+                        .help("the budget is written as `90`, `90s`, `15m` or `2h`")
+                        .emit();
+                    return Err(1);
+                }
+            },
+        };
+        let beam_width = parse_count(matches, diag, "beam-width")?;
+        if beam_width == Some(0) {
+            diag.struct_err("`--beam-width` must be at least 1").emit();
+            return Err(1);
+        }
+        let sink_apis = parse_api_patterns(matches, diag, "sink-api")?;
+        if strategy == SINK_STRATEGY && sink_apis.is_empty() {
+            diag.struct_err("`--strategy sink` needs `--sink-api`")
+                .help("the strategy only generates sequences ending with the sink apis")
+                .emit();
+            return Err(1);
+        }
+        Ok(FuzzTargetOptions {
+            strategy,
+            selection,
+            jobs,
+            max_seq_len,
+            max_targets,
+            mega_sequence: matches.opt_present("mega-sequence"),
+            tail_variants,
+            max_targets_per_api,
+            min_targets_per_api,
+            lib_target,
+            async_runtime,
+            error_policy,
+            crash_to_test,
+            msrv,
+            compile_check,
+            format_harness: matches.opt_present("format-harness"),
+            include_deps,
+            dependency_producers: matches.opt_present("dependency-producers"),
+            dependency_apis: Vec::new(),
+            dependency_crates: Vec::new(),
+            macro_apis: Vec::new(),
+            features,
+            max_struct_depth,
+            differential: matches.opt_present("differential"),
+            round_trip: matches.opt_present("round-trip"),
+            round_trip_equivalence,
+            panic_policy,
+            ignore_panics,
+            graph_cache: matches.opt_present("graph-cache"),
+            no_std,
+            workspace,
+            run_manifest: matches.opt_present("emit-run-manifest"),
+            ossfuzz,
+            usage_paths,
+            usage_dump: matches.opt_str("dump-usage").map(PathBuf::from),
+            include_hidden: matches.opt_present("include-hidden"),
+            include_deprecated: matches.opt_present("include-deprecated"),
+            include_apis,
+            exclude_apis,
+            entry_apis,
+            generic_hints,
+            operator_harness: matches.opt_present("operator-harness"),
+            drop_harness: matches.opt_present("drop-harness"),
+            contract_harness: matches.opt_present("contract-harness"),
+            ffi: matches.opt_present("ffi"),
+            sanitizers,
+            validation,
+            kani,
+            assert_guards: !matches.opt_present("no-assert-guards"),
+            char_mutator: matches.opt_present("char-mutator"),
+            explain: matches.opt_str("explain"),
+            html_report: matches.opt_present("html-report"),
+            emit_metrics: matches.opt_present("emit-metrics"),
+            harness_emitters,
+            default_producer: matches.opt_present("allow-default-producer"),
+            serde_producer,
+            afl_persistent_iterations,
+            afl_deferred_init: matches.opt_present("afl-deferred-init"),
+            runtime_crate: matches.opt_present("runtime-crate"),
+            naming,
+            incremental: matches.opt_present("incremental"),
+            const_probes: matches.opt_present("const-probes"),
+            rank_targets: matches.opt_present("rank-targets"),
+            time_budget,
+            beam_width,
+            sink_apis,
+            assert_buffer_bounds: matches.opt_present("assert-buffer-bounds"),
+            check_invariants: matches.opt_present("check-invariants"),
+            exercise_format: matches.opt_present("exercise-format"),
+            exercise_clone: matches.opt_present("exercise-clone"),
+            dictionaries: matches.opt_present("dictionaries"),
+            generate_targets: false,
+        })
+    }
+}
+
+fn parse_count(
+    matches: &getopts::Matches,
+    diag: &rustc_errors::Handler,
+    name: &str,
+) -> Result<Option<usize>, i32> {
+    match matches.opt_str(name) {
+        None => Ok(None),
+        Some(value) => match value.parse::<usize>() {
+            Ok(count) => Ok(Some(count)),
+            Err(_) => {
+                diag.struct_err(&format!(
+                    "`--{}` expects a non-negative integer, found `{}`",
+                    name, value
+                ))
+                .emit();
+                Err(1)
+            }
+        },
+    }
+}
+
+//seconds, minutes or hours, seconds without a unit
+fn parse_duration(value: &str) -> Option<Duration> {
+    let (number, seconds_per_unit) = match value.char_indices().last()? {
+        (i, 's') => (&value[..i], 1),
+        (i, 'm') => (&value[..i], 60),
+        (i, 'h') => (&value[..i], 60 * 60),
+        _ => (value, 1),
+    };
+    let number = number.parse::<u64>().ok()?;
+    Some(Duration::from_secs(number.checked_mul(seconds_per_unit)?))
+}
+
+fn parse_api_patterns(
+    matches: &getopts::Matches,
+    diag: &rustc_errors::Handler,
+    name: &str,
+) -> Result<Vec<ApiPattern>, i32> {
+    let mut res = Vec::new();
+    for pattern in matches.opt_strs(name) {
+        match ApiPattern::parse(pattern.trim()) {
+            Ok(pattern) => res.push(pattern),
+            Err(e) => {
+                diag.struct_err(&format!("invalid `--{}` pattern {}", name, e))
+                    .help("patterns are path globs like `url::Url::set_*` or regexes like `/^url/`")
+                    .emit();
+                return Err(1);
+            }
+        }
+    }
+    Ok(res)
+}
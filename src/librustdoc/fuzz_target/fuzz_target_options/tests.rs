@@ -0,0 +1,40 @@
+use super::parse_duration;
+use std::time::Duration;
+
+#[test]
+fn duration_units() {
+    assert_eq!(parse_duration("90"), Some(Duration::from_secs(90)));
+    assert_eq!(parse_duration("90s"), Some(Duration::from_secs(90)));
+    assert_eq!(parse_duration("5m"), Some(Duration::from_secs(5 * 60)));
+    assert_eq!(parse_duration("2h"), Some(Duration::from_secs(2 * 60 * 60)));
+    assert_eq!(parse_duration("0m"), Some(Duration::from_secs(0)));
+}
+
+#[test]
+fn duration_empty() {
+    assert_eq!(parse_duration(""), None);
+}
+
+#[test]
+fn duration_lone_unit() {
+    assert_eq!(parse_duration("s"), None);
+    assert_eq!(parse_duration("m"), None);
+    assert_eq!(parse_duration("h"), None);
+}
+
+#[test]
+fn duration_invalid() {
+    assert_eq!(parse_duration("-5m"), None);
+    assert_eq!(parse_duration("1.5h"), None);
+    assert_eq!(parse_duration("5d"), None);
+    assert_eq!(parse_duration("5 m"), None);
+    assert_eq!(parse_duration("5ms"), None);
+}
+
+#[test]
+fn duration_overflow() {
+    assert_eq!(parse_duration(&format!("{}s", u64::MAX)), Some(Duration::from_secs(u64::MAX)));
+    assert_eq!(parse_duration(&format!("{}m", u64::MAX)), None);
+    assert_eq!(parse_duration(&format!("{}h", u64::MAX / 60)), None);
+    assert_eq!(parse_duration("18446744073709551616"), None);
+}
@@ -4,20 +4,53 @@
 use crate::error::Error;
 use crate::formats::cache::Cache;
 use crate::formats::renderer;
+use crate::fuzz_target::api_filter;
 use crate::fuzz_target::api_function;
 use crate::fuzz_target::api_graph::ApiGraph;
 use crate::fuzz_target::api_util;
+use crate::fuzz_target::bin_crate;
+use crate::fuzz_target::call_frequency;
+use crate::fuzz_target::const_probe;
+use crate::fuzz_target::corpus::{self, SeedLiterals};
+use crate::fuzz_target::crash_to_test;
+use crate::fuzz_target::default_producer;
+use crate::fuzz_target::dictionary::{self, Dictionary};
+use crate::fuzz_target::explain;
+use crate::fuzz_target::features;
 use crate::fuzz_target::file_util;
+use crate::fuzz_target::fuzz_target_options::FuzzTargetOptions;
+use crate::fuzz_target::graph_cache;
+use crate::fuzz_target::graph_traverse::StrategyRegistry;
+use crate::fuzz_target::harness_emitter::{EmitterRegistry, HarnessEmitter};
 use crate::fuzz_target::impl_util::{self, FullNameMap};
+use crate::fuzz_target::kani;
+use crate::fuzz_target::libafl_harness::{self, LibAflEmitter};
+use crate::fuzz_target::local_type;
+use crate::fuzz_target::macro_api;
+use crate::fuzz_target::metrics;
+use crate::fuzz_target::ossfuzz;
+use crate::fuzz_target::report;
+use crate::fuzz_target::run_manifest;
+use crate::fuzz_target::runtime_crate;
+use crate::fuzz_target::serde_producer;
+use crate::fuzz_target::usage_mining;
+use crate::fuzz_target::workspace;
 use crate::html::format::join_with_double_colon;
 use crate::TyCtxt;
 use rustc_span::symbol::Symbol;
 use std::cell::RefCell;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::time::Instant;
 
 pub(crate) struct FuzzTargetContext<'tcx> {
     pub cache: Cache,
     pub tcx: TyCtxt<'tcx>,
+    pub options: FuzzTargetOptions,
+    /// The directory where reports are written, i.e. `-o`
+    pub output_dir: PathBuf,
+    /// The name used to refer to the crate in generated targets, i.e. the name of the lib target
+    pub lib_name: String,
 }
 
 #[derive(Clone)]
@@ -28,6 +61,38 @@ pub struct FuzzTargetRenderer<'tcx> {
     current: Vec<Symbol>,
     api_dependency_graph: Rc<RefCell<ApiGraph<'tcx>>>,
     full_name_map: Rc<RefCell<FullNameMap>>,
+    /// String and byte literals of the doc examples and unit tests, used as the seed corpus
+    seed_literals: Rc<SeedLiterals>,
+    /// Literals and magic numbers of the source, used as the dictionaries of the fuzzers
+    dictionary: Rc<Dictionary>,
+}
+
+impl<'tcx> FuzzTargetRenderer<'tcx> {
+    /// Records the path an item is rendered at when it is not the path of the cache, i.e. the item
+    /// is a re-export rustdoc inlines, and the paths of `pub use` items
+    fn add_reexport(&self, item: &clean::Item) {
+        let mod_name = join_with_double_colon(&self.current);
+        let full_name_map = self.full_name_map.borrow();
+        let (def_id, reexport_path) = match &*item.kind {
+            ItemKind::ImportItem(import) => {
+                if !matches!(item.visibility, clean::Visibility::Public) {
+                    return;
+                }
+                let reexport_path = match import.kind {
+                    clean::ImportKind::Simple(name) => format!("{}::{}", mod_name, name),
+                    clean::ImportKind::Glob => mod_name,
+                };
+                (import.source.did, reexport_path)
+            }
+            _ => match item.name {
+                Some(name) => (item.item_id.as_def_id(), format!("{}::{}", mod_name, name)),
+                None => return,
+            },
+        };
+        if let Some(path) = def_id.and_then(|def_id| full_name_map._get_full_name(def_id)) {
+            self.api_dependency_graph.borrow_mut().mod_visibility.add_reexport(path, reexport_path);
+        }
+    }
 }
 
 impl<'tcx> renderer::FormatRenderer<'tcx> for FuzzTargetRenderer<'tcx> {
@@ -39,19 +104,46 @@ fn descr() -> &'static str {
 
     fn init(
         krate: clean::Crate,
-        _options: RenderOptions,
+        options: RenderOptions,
         cache: Cache,
         tcx: TyCtxt<'tcx>,
     ) -> Result<(Self, clean::Crate), Error> {
         println!("Fuzz Target Renderer Init");
         println!("crate: {}", krate.module.name.unwrap().as_str());
-        let rcx = Rc::new(FuzzTargetContext { cache, tcx });
-        let mut api_dependency_graph = ApiGraph::new(krate.name(tcx).to_string(), rcx.clone());
+        let crate_name = krate.name(tcx).to_string();
+        let lib_name = lib_name(&crate_name, &options.fuzz_target_options);
+        if let Some(usage_dump) = &options.fuzz_target_options.usage_dump {
+            usage_mining::dump_usage(tcx, usage_dump)?;
+        }
+        if let Some(lib_target) = &options.fuzz_target_options.lib_target {
+            bin_crate::write_bin_lib(lib_target)?;
+        }
+        let rcx = Rc::new(FuzzTargetContext {
+            cache,
+            tcx,
+            options: options.fuzz_target_options,
+            output_dir: options.output,
+            lib_name,
+        });
+        let mut api_dependency_graph = ApiGraph::new(crate_name, rcx.clone());
+        api_dependency_graph.call_counts = call_frequency::count_calls(tcx);
         //从cache中提出def_id与full_name的对应关系，存入full_name_map来进行调用
         //同时提取impl块中的内容，存入api_dependency_graph
         let mut full_name_map = FullNameMap::new();
+        full_name_map.local_types = local_type::collect_local_types(
+            &krate,
+            &rcx.cache,
+            &api_dependency_graph._crate_name,
+            &rcx.lib_name,
+        );
+        full_name_map.max_struct_depth = rcx.options.max_struct_depth;
         impl_util::extract_impls_from_cache(&mut full_name_map, &mut api_dependency_graph);
-
+        for dependency_api in &rcx.options.dependency_apis {
+            api_dependency_graph.add_api_function(dependency_api.to_api_function());
+        }
+        let seed_literals = SeedLiterals::collect(&krate, tcx);
+        let dictionary =
+            if rcx.options.dictionaries { Dictionary::collect(tcx) } else { Dictionary::default() };
 
         Ok((
             FuzzTargetRenderer {
@@ -59,6 +151,8 @@ fn init(
                 current: Vec::new(),
                 api_dependency_graph: Rc::new(RefCell::new(api_dependency_graph)),
                 full_name_map: Rc::new(RefCell::new(full_name_map)),
+                seed_literals: Rc::new(seed_literals),
+                dictionary: Rc::new(dictionary),
             },
             krate,
         ))
@@ -81,17 +175,29 @@ fn item(&mut self, item: clean::Item) -> Result<(), Error> {
         debug_str.push_str(&format!("\n item kind: {:?}", item.kind));
         //println!("{}", debug_str);
         let full_name: String = join_with_double_colon(&self.current) + item.name.unwrap().as_str();
-        if let ItemKind::FunctionItem(ref func) = *item.kind {
+        self.add_reexport(&item);
+        if let ItemKind::FunctionItem(ref func) | ItemKind::ForeignFunctionItem(ref func) =
+            *item.kind
+        {
             //println!("func = {:?}", func);
+            if self.api_dependency_graph.borrow().is_excluded_item(&item) {
+                return Ok(());
+            }
+            //extern functions re-exported by ffi wrappers are only fuzzed with `--ffi`
+            let is_foreign = matches!(*item.kind, ItemKind::ForeignFunctionItem(_));
+            if is_foreign && !self.context.options.ffi {
+                return Ok(());
+            }
             let decl = func.decl.clone();
-            let clean::FnDecl { inputs, output, .. } = decl;
+            let clean::FnDecl { inputs: arguments, output, .. } = decl;
             let generics = func.generics.clone();
-            let inputs = api_util::_extract_input_types(&inputs);
+            let inputs = api_util::_extract_input_types(&arguments);
             let output = api_util::_extract_output_type(&output);
 
-            let api_unsafety = api_function::ApiUnsafety::_get_unsafety_from_fnheader(
-                &item.fn_header(self.context.tcx).unwrap(),
-            );
+            let fn_header = item.fn_header(self.context.tcx).unwrap();
+            let api_unsafety = api_function::ApiUnsafety::_get_unsafety_from_fnheader(&fn_header);
+            let api_asyncness =
+                api_function::ApiAsyncness::_get_asyncness_from_fnheader(&fn_header);
             let api_fun = api_function::ApiFunction {
                 full_name,
                 generics,
@@ -99,8 +205,31 @@ fn item(&mut self, item: clean::Item) -> Result<(), Error> {
                 output,
                 _trait_full_path: None,
                 _unsafe_tag: api_unsafety,
+                _async_tag: api_asyncness,
             };
-            self.api_dependency_graph.borrow_mut().add_api_function(api_fun);
+            let mut api_dependency_graph = self.api_dependency_graph.borrow_mut();
+            api_dependency_graph.add_api_features(&api_fun.full_name, item.cfg.as_deref());
+            api_dependency_graph.add_ffi_api(&api_fun.full_name, item.item_id.as_def_id());
+            api_dependency_graph.add_const_api(&api_fun.full_name, item.item_id.as_def_id());
+            api_dependency_graph.add_api_risks(&api_fun.full_name, item.item_id.as_def_id());
+            api_dependency_graph.add_must_use_api(&api_fun.full_name, item.item_id.as_def_id());
+            api_dependency_graph.add_api_def_id(&api_fun.full_name, item.item_id.as_def_id());
+            api_dependency_graph.add_api_panics(
+                &api_fun.full_name,
+                item.collapsed_doc_value(),
+                &arguments,
+            );
+            api_dependency_graph.add_api_param_bounds(
+                &api_fun.full_name,
+                item.collapsed_doc_value(),
+                &arguments,
+            );
+            api_dependency_graph.add_api_assertions(
+                &api_fun.full_name,
+                item.item_id.as_def_id(),
+                &arguments,
+            );
+            api_dependency_graph.add_api_function(api_fun);
         }
 
         Ok(())
@@ -134,21 +263,44 @@ fn mod_item_out(&mut self) -> Result<(), Error> {
     /// Post processing hook for cleanup and dumping output to files.
     fn after_krate(&mut self) -> Result<(), Error> {
         //println!("==== run after krate ====");
+        //the crate is a user of another one, its calls are all that is needed
+        if self.context.options.usage_dump.is_some() {
+            return Ok(());
+        }
         let mut api_dependency_graph = self.api_dependency_graph.borrow_mut();
         //println!("ModVisibility: {:?}", api_dependency_graph.mod_visibility);
 
+        macro_api::add_macro_apis(&mut api_dependency_graph);
+        api_dependency_graph.monomorphize_generic_functions();
+        serde_producer::add_serde_producers(&mut api_dependency_graph);
+        default_producer::add_default_producers(&mut api_dependency_graph);
         //根据mod可见性和预包含类型过滤function
         api_dependency_graph.filter_functions();
         //寻找所有依赖，并且构建序列
-        api_dependency_graph.find_all_dependencies();
+        graph_cache::find_all_dependencies(&mut api_dependency_graph);
         //api_dependency_graph._print_pretty_dependencies();
 
-        let random_strategy = false;
-        if !random_strategy {
-            api_dependency_graph.default_generate_sequences();
+        let strategies = StrategyRegistry::new();
+        //the name is checked when the options are parsed
+        let strategy = strategies.get(self.context.options.strategy).unwrap();
+        let random_strategy = strategy.is_random();
+        //the strategies start from the mined sequences
+        usage_mining::add_mined_sequences(&mut api_dependency_graph);
+        //the other strategies run on copies of the graph before it gets any sequence
+        let strategy_breakdown = if self.context.options.emit_metrics {
+            metrics::strategy_breakdown(&api_dependency_graph, &strategies)
         } else {
-            use crate::fuzz_target::api_graph::GraphTraverseAlgorithm::_RandomWalk;
-            api_dependency_graph.generate_all_possoble_sequences(_RandomWalk);
+            Vec::new()
+        };
+        let generation_start = Instant::now();
+        api_dependency_graph.generate_sequences(strategy);
+        api_filter::add_entry_sequences(&mut api_dependency_graph);
+        api_filter::add_parser_sequences(&mut api_dependency_graph);
+        let generation_time = generation_start.elapsed();
+        //a query about one api, nothing is written
+        if let Some(api_name) = &self.context.options.explain {
+            explain::print_explanation(&api_dependency_graph, api_name);
+            return Ok(());
         }
         //api_dependency_graph._print_generated_libfuzzer_file();
         //api_dependency_graph._print_pretty_functions(false);
@@ -168,15 +320,118 @@ fn after_krate(&mut self) -> Result<(), Error> {
         //println!("total test sequences : {:?}", api_dependency_graph.api_sequences.len());
         //use crate::html::afl_util;
         //afl_util::_AflHelpers::_print_all();
+        let mut file_helper = None;
         if file_util::can_write_to_file(&api_dependency_graph._crate_name, random_strategy) {
             //whether to use random strategy
-            let file_helper = file_util::FileHelper::new(&api_dependency_graph, random_strategy);
+            let helper = file_util::FileHelper::new(&api_dependency_graph, random_strategy);
             // println!("file_helper:{:?}", file_helper);
-            file_helper.write_files();
-            if file_util::can_generate_libfuzzer_target(&api_dependency_graph._crate_name) {
-                // println!("libfuzzer file_helper:{:?}", file_helper);
-                file_helper.write_libfuzzer_files();
+            let registry = EmitterRegistry::new();
+            let emitters = registry.selected(&api_dependency_graph);
+            if self.context.options.workspace {
+                workspace::write_workspace(
+                    &api_dependency_graph,
+                    &helper,
+                    &emitters,
+                    Path::new(&helper.test_dir),
+                )?;
+                let other_emitters: Vec<&dyn HarnessEmitter> = emitters
+                    .iter()
+                    .copied()
+                    .filter(|emitter| !emitter.is_bin() && emitter.dev_dependency().is_none())
+                    .collect();
+                helper.write_files(&api_dependency_graph, &other_emitters);
+            } else {
+                helper.write_files(&api_dependency_graph, &emitters);
+                features::write_manifest(&api_dependency_graph, &helper, &emitters)?;
+                if self.context.options.runtime_crate {
+                    runtime_crate::write_runtime_crate(
+                        &api_dependency_graph,
+                        &helper.chosen_sequences,
+                        Path::new(&helper.test_dir),
+                    )?;
+                }
+            }
+            if emitters.iter().any(|emitter| emitter.name() == LibAflEmitter.name()) {
+                libafl_harness::write_manifest(&api_dependency_graph, &helper)?;
+            }
+            corpus::write_seeds(
+                &self.seed_literals,
+                &helper.chosen_sequences,
+                &helper.target_names,
+                &helper.regeneration,
+                self.context.options.naming,
+                Path::new(&helper.test_dir),
+            )?;
+            if self.context.options.dictionaries {
+                dictionary::write_dictionaries(
+                    &self.dictionary,
+                    &helper.chosen_sequences,
+                    &helper.target_names,
+                    self.context.options.naming,
+                    Path::new(&helper.test_dir),
+                )?;
+            }
+            if self.context.options.const_probes {
+                const_probe::write_const_probes(
+                    &api_dependency_graph,
+                    Path::new(&helper.test_dir),
+                )?;
+            }
+            if self.context.options.run_manifest {
+                run_manifest::write_run_manifests(
+                    &api_dependency_graph,
+                    &helper,
+                    Path::new(&helper.test_dir),
+                )?;
+            }
+            if self.context.options.ossfuzz {
+                ossfuzz::write_ossfuzz(
+                    &api_dependency_graph,
+                    &helper,
+                    Path::new(&helper.test_dir),
+                )?;
+            }
+            if self.context.options.kani {
+                kani::write_kani(&api_dependency_graph, &helper, Path::new(&helper.test_dir))?;
+            }
+            file_helper = Some(helper);
+        }
+        if let Some(crash_to_test) = &self.context.options.crash_to_test {
+            //the crashed target is found by its name, so the sequences are chosen again in the
+            //same way when no files are written
+            let chosen_sequences;
+            let sequences = match &file_helper {
+                Some(helper) => &helper.chosen_sequences,
+                None => {
+                    chosen_sequences =
+                        file_util::choose_sequences(&api_dependency_graph, random_strategy);
+                    &chosen_sequences
+                }
+            };
+            if random_strategy {
+                println!("warning: sequences of a random strategy may differ from the fuzzed ones");
             }
+            crash_to_test::write_crash_test(
+                &api_dependency_graph,
+                sequences,
+                crash_to_test,
+                &self.context.output_dir,
+            )?;
+        }
+        report::write_report(
+            &api_dependency_graph,
+            file_helper.as_ref(),
+            &self.context.output_dir,
+        )?;
+        if self.context.options.emit_metrics {
+            metrics::write_metrics(
+                &api_dependency_graph,
+                strategy,
+                file_helper.as_ref(),
+                generation_time,
+                strategy_breakdown,
+                &self.context.output_dir,
+            )?;
         }
 
         // Flush pending errors.
@@ -195,3 +450,19 @@ fn cache(&self) -> &Cache {
         &self.context.cache
     }
 }
+
+//读取cargo metadata中lib target的名字，它可能和包名或者传给rustdoc的crate名不同
+fn lib_name(crate_name: &String, options: &FuzzTargetOptions) -> String {
+    match &options.lib_target {
+        Some(lib_target) => {
+            println!(
+                "lib target `{}` of package `{}`: {}",
+                lib_target.name,
+                lib_target.package_name,
+                lib_target.src_path.display()
+            );
+            lib_target.name.clone()
+        }
+        None => crate_name.clone(),
+    }
+}
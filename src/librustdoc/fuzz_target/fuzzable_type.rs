@@ -2,9 +2,14 @@
 use crate::formats::cache::Cache;
 use rustc_hir::Mutability;
 
+use crate::fuzz_target::api_util::{self, IoAdapter};
 use crate::fuzz_target::call_type::CallType;
 use crate::fuzz_target::impl_util::FullNameMap;
-use crate::fuzz_target::prelude_type::PreludeType;
+use crate::fuzz_target::prelude_type::{PreludeType, Wrapper};
+use crate::fuzz_target::std_value::StdValue;
+
+//数组按元素逐个生成，太长的数组会让生成的代码和输入都过大
+static MAX_ARRAY_LENGTH: usize = 1024;
 
 //如果构造一个fuzzable的变量
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -13,13 +18,103 @@ pub(crate) enum FuzzableCallType {
     Primitive(PrimitiveType),
     Tuple(Vec<Box<FuzzableCallType>>),
     Slice(Box<FuzzableCallType>),
-    Array(Box<FuzzableCallType>),
+    Array(Box<FuzzableCallType>, usize),
     ConstRawPoiner(Box<FuzzableCallType>, clean::Type),
     MutRawPoiner(Box<FuzzableCallType>, clean::Type),
     STR,
     BorrowedRef(Box<FuzzableCallType>),
     MutBorrowedRef(Box<FuzzableCallType>),
     ToOption(Box<FuzzableCallType>),
+    //放到Box、Rc等包装类型中的值
+    ToWrapper(Wrapper, Box<FuzzableCallType>),
+    //enum的路径和可以构造的variant
+    Enum(String, Vec<FuzzableVariant<FuzzableCallType>>),
+    //字段都是public的struct
+    Struct(FuzzableVariant<FuzzableCallType>),
+    //迭代器的元素类型，以及是否迭代元素的引用
+    Iterator(Box<FuzzableCallType>, bool),
+    //`impl Read`, `impl Write`等std::io的参数
+    Io(IoAdapter),
+    //由整数构造的Duration等std类型
+    StdValue(StdValue),
+    //元素可以从输入解码的集合，map的元素是key和value的类型
+    Collection(Collection, Vec<FuzzableCallType>),
+    //元素是String的集合，从输入的文本中逐行得到
+    TextCollection(Collection),
+}
+
+//参数可以从输入构造的集合
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub(crate) enum Collection {
+    HashMap,
+    BTreeMap,
+    HashSet,
+    BTreeSet,
+}
+
+impl Collection {
+    fn from_full_name(full_name: &str) -> Option<Self> {
+        match full_name {
+            "std::collections::hash::map::HashMap" => Some(Collection::HashMap),
+            "alloc::collections::btree::map::BTreeMap" => Some(Collection::BTreeMap),
+            "std::collections::hash::set::HashSet" => Some(Collection::HashSet),
+            "alloc::collections::btree::set::BTreeSet" => Some(Collection::BTreeSet),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn is_map(&self) -> bool {
+        match self {
+            Collection::HashMap | Collection::BTreeMap => true,
+            Collection::HashSet | Collection::BTreeSet => false,
+        }
+    }
+
+    //collect的目标类型
+    pub(crate) fn type_name(&self) -> &'static str {
+        match self {
+            Collection::HashMap => "std::collections::HashMap<_, _>",
+            Collection::BTreeMap => "std::collections::BTreeMap<_, _>",
+            Collection::HashSet => "std::collections::HashSet<_>",
+            Collection::BTreeSet => "std::collections::BTreeSet<_>",
+        }
+    }
+}
+
+//构造enum的variant或struct的方式
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) enum ConstructorKind {
+    Unit,
+    Tuple,
+    //字段名
+    Named(Vec<String>),
+}
+
+//enum的一个variant或一个struct，字段都是fuzzable的
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct FuzzableVariant<T> {
+    //variant的名字，或者struct的路径
+    pub(crate) name: String,
+    pub(crate) kind: ConstructorKind,
+    pub(crate) fields: Vec<T>,
+}
+
+impl<T> FuzzableVariant<T> {
+    //用字段的表达式构造，例如`E::A`, `E::B(x, y)`, `S { x: x }`
+    pub(crate) fn _to_constructor(&self, path: &str, fields: &Vec<String>) -> String {
+        match &self.kind {
+            ConstructorKind::Unit => path.to_string(),
+            ConstructorKind::Tuple => format!("{}({})", path, fields.join(", ")),
+            ConstructorKind::Named(field_names) => {
+                let fields: Vec<String> = field_names
+                    .iter()
+                    .zip(fields.iter())
+                    .map(|(field_name, field)| format!("{}: {}", field_name, field))
+                    .collect();
+                format!("{} {{ {} }}", path, fields.join(", "))
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -29,6 +124,12 @@ pub(crate) enum FuzzableType {
     RefSlice(Box<FuzzableType>),
     RefStr,
     Tuple(Vec<Box<FuzzableType>>),
+    //[T; N]，只支持定长的T
+    Array(Box<FuzzableType>, usize),
+    //第一个字节选择variant，之后是variant的字段
+    Enum(String, Vec<FuzzableVariant<FuzzableType>>),
+    //字段依次排列
+    Struct(FuzzableVariant<FuzzableType>),
 }
 
 impl FuzzableCallType {
@@ -90,13 +191,21 @@ pub(crate) fn generate_fuzzable_type_and_call_type(&self) -> (FuzzableType, Call
                         slice_inner.generate_fuzzable_type_and_call_type();
                     if let FuzzableType::NoFuzzable = fuzzable_type {
                         return (FuzzableType::NoFuzzable, CallType::_NotCompatible);
-                    } else if let CallType::_NotCompatible = inner_call_type {
-                        return (FuzzableType::NoFuzzable, CallType::_NotCompatible);
                     }
-                    return (
-                        FuzzableType::RefSlice(Box::new(fuzzable_type)),
-                        CallType::_DirectCall,
-                    );
+                    //元素是从输入中逐个解码出来的，不能是引用。Option、Box和Duration等需要转换的元素，
+                    //在调用前逐个转换后收集到Vec中，解码出的元素需要可以复制
+                    let call_type = match inner_call_type {
+                        CallType::_DirectCall => CallType::_DirectCall,
+                        _ if fuzzable_type._is_plain_data()
+                            && inner_call_type._is_element_conversion() =>
+                        {
+                            CallType::_CollectSlice(Box::new(inner_call_type))
+                        }
+                        _ => {
+                            return (FuzzableType::NoFuzzable, CallType::_NotCompatible);
+                        }
+                    };
+                    return (FuzzableType::RefSlice(Box::new(fuzzable_type)), call_type);
                 }
                 //一般的引用
                 let (fuzzable_type, inner_call_type) =
@@ -110,6 +219,20 @@ pub(crate) fn generate_fuzzable_type_and_call_type(&self) -> (FuzzableType, Call
             }
             FuzzableCallType::MutBorrowedRef(type_) => {
                 let inner_type = &**type_;
+                //写入结果的缓冲区，例如`&mut [u8]`。只从输入中得到长度，元素都是默认值
+                if let FuzzableCallType::Slice(slice_inner) = inner_type {
+                    return match &**slice_inner {
+                        FuzzableCallType::Primitive(primitive)
+                            if FuzzableType::Primitive(*primitive)._min_length() > 0 =>
+                        {
+                            (
+                                FuzzableType::Primitive(clean::PrimitiveType::U16),
+                                CallType::_ScratchBuffer(*primitive),
+                            )
+                        }
+                        _ => (FuzzableType::NoFuzzable, CallType::_NotCompatible),
+                    };
+                }
                 let (fuzzable_type, inner_call_type) =
                     inner_type.generate_fuzzable_type_and_call_type();
                 if let FuzzableType::NoFuzzable = fuzzable_type {
@@ -132,8 +255,165 @@ pub(crate) fn generate_fuzzable_type_and_call_type(&self) -> (FuzzableType, Call
                 }
                 return (fuzzable_type, CallType::_ToOption(Box::new(inner_call_type)));
             }
-            FuzzableCallType::Array(_) | FuzzableCallType::Slice(_) => {
+            FuzzableCallType::ToWrapper(wrapper, inner_fuzzable_call_type) => {
+                let (fuzzable_type, inner_call_type) =
+                    inner_fuzzable_call_type.generate_fuzzable_type_and_call_type();
+                if let FuzzableType::NoFuzzable = fuzzable_type {
+                    return (FuzzableType::NoFuzzable, CallType::_NotCompatible);
+                } else if let CallType::_NotCompatible = inner_call_type {
+                    return (FuzzableType::NoFuzzable, CallType::_NotCompatible);
+                }
+                return (fuzzable_type, CallType::_ToWrapper(Box::new(inner_call_type), *wrapper));
+            }
+            FuzzableCallType::Array(inner_fuzzable_call_type, length) => {
+                let (fuzzable_type, inner_call_type) =
+                    inner_fuzzable_call_type.generate_fuzzable_type_and_call_type();
+                if let FuzzableType::NoFuzzable = fuzzable_type {
+                    return (FuzzableType::NoFuzzable, CallType::_NotCompatible);
+                }
+                match inner_call_type {
+                    CallType::_DirectCall => {}
+                    _ => {
+                        return (FuzzableType::NoFuzzable, CallType::_NotCompatible);
+                    }
+                }
+                if !fuzzable_type._is_fixed_length()
+                    || fuzzable_type._min_length() * length > MAX_ARRAY_LENGTH
+                {
+                    return (FuzzableType::NoFuzzable, CallType::_NotCompatible);
+                }
+                return (
+                    FuzzableType::Array(Box::new(fuzzable_type), *length),
+                    CallType::_DirectCall,
+                );
+            }
+            FuzzableCallType::Enum(full_name, variants) => {
+                //字段不是定长的variant不会被构造
+                let mut fuzzable_variants = Vec::new();
+                'variants: for variant in variants {
+                    let mut fields = Vec::new();
+                    for field in &variant.fields {
+                        let (fuzzable_type, call_type) =
+                            field.generate_fuzzable_type_and_call_type();
+                        match (&fuzzable_type, call_type) {
+                            (FuzzableType::NoFuzzable, _) => continue 'variants,
+                            (_, CallType::_DirectCall) if fuzzable_type._is_fixed_length() => {}
+                            _ => continue 'variants,
+                        }
+                        fields.push(fuzzable_type);
+                    }
+                    fuzzable_variants.push(FuzzableVariant {
+                        name: variant.name.clone(),
+                        kind: variant.kind.clone(),
+                        fields,
+                    });
+                }
+                if fuzzable_variants.is_empty() {
+                    return (FuzzableType::NoFuzzable, CallType::_NotCompatible);
+                }
+                return (
+                    FuzzableType::Enum(full_name.clone(), fuzzable_variants),
+                    CallType::_DirectCall,
+                );
+            }
+            FuzzableCallType::Struct(variant) => {
+                //和enum的variant一样，字段需要是定长的
+                let mut fields = Vec::new();
+                for field in &variant.fields {
+                    let (fuzzable_type, call_type) = field.generate_fuzzable_type_and_call_type();
+                    match (&fuzzable_type, call_type) {
+                        (FuzzableType::NoFuzzable, _) => {
+                            return (FuzzableType::NoFuzzable, CallType::_NotCompatible);
+                        }
+                        (_, CallType::_DirectCall) if fuzzable_type._is_fixed_length() => {}
+                        _ => return (FuzzableType::NoFuzzable, CallType::_NotCompatible),
+                    }
+                    fields.push(fuzzable_type);
+                }
+                return (
+                    FuzzableType::Struct(FuzzableVariant {
+                        name: variant.name.clone(),
+                        kind: variant.kind.clone(),
+                        fields,
+                    }),
+                    CallType::_DirectCall,
+                );
+            }
+            FuzzableCallType::Slice(_) => {
                 return (FuzzableType::NoFuzzable, CallType::_NotCompatible);
+            }
+            FuzzableCallType::Iterator(item, by_ref) => {
+                //元素和切片的元素一样解码，按值迭代时元素需要可以复制
+                let (fuzzable_type, inner_call_type) = item.generate_fuzzable_type_and_call_type();
+                match (&fuzzable_type, inner_call_type) {
+                    (FuzzableType::NoFuzzable, _) => {
+                        return (FuzzableType::NoFuzzable, CallType::_NotCompatible);
+                    }
+                    (_, CallType::_DirectCall)
+                        if fuzzable_type._is_fixed_length()
+                            && (*by_ref || fuzzable_type._is_plain_data()) => {}
+                    _ => return (FuzzableType::NoFuzzable, CallType::_NotCompatible),
+                }
+                let call_type = if *by_ref {
+                    CallType::_Iter(Box::new(CallType::_DirectCall))
+                } else {
+                    CallType::_IntoIter(Box::new(CallType::_DirectCall))
+                };
+                return (FuzzableType::RefSlice(Box::new(fuzzable_type)), call_type);
+            }
+            FuzzableCallType::Io(adapter) => {
+                //reader读输入的字节，writer不需要输入，用空的tuple占位
+                let fuzzable_type = match adapter {
+                    IoAdapter::Reader => {
+                        FuzzableType::RefSlice(Box::new(FuzzableType::Primitive(PrimitiveType::U8)))
+                    }
+                    IoAdapter::Writer | IoAdapter::SeekableWriter => {
+                        FuzzableType::Tuple(Vec::new())
+                    }
+                };
+                return (fuzzable_type, CallType::_ToIo(Box::new(CallType::_DirectCall), *adapter));
+            }
+            FuzzableCallType::TextCollection(collection) => {
+                return (FuzzableType::RefStr, CallType::_ToTextCollection(*collection));
+            }
+            FuzzableCallType::Collection(collection, elements) => {
+                //元素和需要转换的切片元素一样，从复制出的元素构造
+                let mut fuzzable_types = Vec::new();
+                let mut call_types = Vec::new();
+                for element in elements {
+                    let (fuzzable_type, call_type) = element.generate_fuzzable_type_and_call_type();
+                    if let FuzzableType::NoFuzzable = fuzzable_type {
+                        return (FuzzableType::NoFuzzable, CallType::_NotCompatible);
+                    }
+                    if !fuzzable_type._is_plain_data() || !call_type._is_element_conversion() {
+                        return (FuzzableType::NoFuzzable, CallType::_NotCompatible);
+                    }
+                    fuzzable_types.push(Box::new(fuzzable_type));
+                    call_types.push(call_type);
+                }
+                if !_is_hashable_key(&fuzzable_types[0], &call_types[0]) {
+                    return (FuzzableType::NoFuzzable, CallType::_NotCompatible);
+                }
+                let element_type = if fuzzable_types.len() == 1 {
+                    fuzzable_types.pop().unwrap()
+                } else {
+                    Box::new(FuzzableType::Tuple(fuzzable_types))
+                };
+                return (
+                    FuzzableType::RefSlice(element_type),
+                    CallType::_ToCollection(*collection, call_types),
+                );
+            }
+            FuzzableCallType::StdValue(std_value) => {
+                let fields = std_value
+                    .fields()
+                    .into_iter()
+                    .map(|field| Box::new(FuzzableType::Primitive(field)))
+                    .collect();
+                return (
+                    FuzzableType::Tuple(fields),
+                    CallType::_ToStdValue(Box::new(CallType::_DirectCall), *std_value),
+                );
             } //_ => {
               //    return (FuzzableType::NoFuzzable, CallType::_NotCompatible);
               //}
@@ -156,6 +436,49 @@ pub(crate) fn _is_fixed_length(&self) -> bool {
                 }
                 return true;
             }
+            FuzzableType::Array(inner_fuzzable, _) => inner_fuzzable._is_fixed_length(),
+            FuzzableType::Enum(..) => true,
+            FuzzableType::Struct(..) => true,
+        }
+    }
+
+    //包含的enum和struct的全名，包括字段中的
+    pub(crate) fn _local_type_names(&self) -> Vec<String> {
+        match self {
+            FuzzableType::NoFuzzable | FuzzableType::Primitive(_) | FuzzableType::RefStr => {
+                Vec::new()
+            }
+            FuzzableType::RefSlice(inner_fuzzable) | FuzzableType::Array(inner_fuzzable, _) => {
+                inner_fuzzable._local_type_names()
+            }
+            FuzzableType::Tuple(inner_fuzzables) => inner_fuzzables
+                .iter()
+                .flat_map(|inner_fuzzable| inner_fuzzable._local_type_names())
+                .collect(),
+            FuzzableType::Enum(full_name, variants) => {
+                let mut res = vec![full_name.clone()];
+                for variant in variants {
+                    res.extend(variant.fields.iter().flat_map(|field| field._local_type_names()));
+                }
+                res
+            }
+            FuzzableType::Struct(variant) => {
+                let mut res = vec![variant.name.clone()];
+                res.extend(variant.fields.iter().flat_map(|field| field._local_type_names()));
+                res
+            }
+        }
+    }
+
+    //只由基本类型组成，可以复制
+    pub(crate) fn _is_plain_data(&self) -> bool {
+        match self {
+            FuzzableType::Primitive(_) => true,
+            FuzzableType::Tuple(inner_fuzzables) => {
+                inner_fuzzables.iter().all(|inner_fuzzable| inner_fuzzable._is_plain_data())
+            }
+            FuzzableType::Array(inner_fuzzable, _) => inner_fuzzable._is_plain_data(),
+            _ => false,
         }
     }
 
@@ -193,6 +516,21 @@ pub(crate) fn _min_length(&self) -> usize {
                 }
                 total_length
             }
+            FuzzableType::Array(inner_fuzzable, length) => inner_fuzzable._min_length() * length,
+            //所有variant的字段共用同一段数据
+            FuzzableType::Enum(_, variants) => {
+                let fields_length = variants
+                    .iter()
+                    .map(|variant| {
+                        variant.fields.iter().map(|field| field._min_length()).sum::<usize>()
+                    })
+                    .max()
+                    .unwrap_or(0);
+                1 + fields_length
+            }
+            FuzzableType::Struct(variant) => {
+                variant.fields.iter().map(|field| field._min_length()).sum::<usize>()
+            }
         }
     }
 
@@ -256,6 +594,7 @@ pub(crate) fn _is_multiple_dynamic_length(&self) -> bool {
                 }
                 return false;
             }
+            FuzzableType::Array(inner_fuzzable, _) => inner_fuzzable._is_multiple_dynamic_length(),
             _ => false,
         }
     }
@@ -288,17 +627,80 @@ pub(crate) fn _to_type_string(&self) -> String {
                     let type_string = inner_types[i]._to_type_string();
                     res.push_str(type_string.as_str());
                 }
+                //只有一个元素的元组需要加逗号
+                if types_len == 1 {
+                    res.push_str(",");
+                }
                 res.push_str(")");
                 res
             }
+            FuzzableType::Array(inner_type, length) => {
+                format!("[{}; {}]", inner_type._to_type_string(), length)
+            }
+            FuzzableType::Enum(full_name, _) => full_name.clone(),
+            FuzzableType::Struct(variant) => variant.name.clone(),
         }
     }
 }
 
 //判断一个类型是不是fuzzable的，以及如何调用相应的fuzzable变量
 pub(crate) fn fuzzable_call_type(ty_: &clean::Type, full_name_map: &FullNameMap, cache: &Cache) -> FuzzableCallType {
+    fuzzable_call_type_at_depth(ty_, full_name_map, cache, 0)
+}
+
+//depth是类型所在的enum和struct的嵌套层数
+pub(crate) fn fuzzable_call_type_at_depth(
+    ty_: &clean::Type,
+    full_name_map: &FullNameMap,
+    cache: &Cache,
+    depth: usize,
+) -> FuzzableCallType {
     match ty_ {
-        clean::Type::Path { .. } => {
+        clean::Type::Path { path } => {
+            let local_types = &full_name_map.local_types;
+            if let Some(enum_definition) =
+                path.res.opt_def_id().and_then(|def_id| local_types.enums.get(&def_id))
+            {
+                let mut variants = Vec::new();
+                for variant in &enum_definition.variants {
+                    let fields: Vec<FuzzableCallType> = variant
+                        .fields
+                        .iter()
+                        .map(|field| {
+                            fuzzable_call_type_at_depth(field, full_name_map, cache, depth + 1)
+                        })
+                        .collect();
+                    if fields.contains(&FuzzableCallType::NoFuzzable) {
+                        continue;
+                    }
+                    variants.push(FuzzableVariant {
+                        name: variant.name.clone(),
+                        kind: variant.kind.clone(),
+                        fields,
+                    });
+                }
+                if variants.is_empty() {
+                    return FuzzableCallType::NoFuzzable;
+                }
+                return FuzzableCallType::Enum(enum_definition.full_name.clone(), variants);
+            }
+            if let Some(struct_definition) =
+                path.res.opt_def_id().and_then(|def_id| local_types.structs.get(&def_id))
+            {
+                return api_util::construct_struct(struct_definition, full_name_map, cache, depth);
+            }
+            let full_name =
+                path.res.opt_def_id().and_then(|def_id| full_name_map._get_full_name(def_id));
+            if let Some(std_value) =
+                full_name.and_then(|full_name| StdValue::from_full_name(full_name))
+            {
+                return FuzzableCallType::StdValue(std_value);
+            }
+            if let Some(collection) =
+                full_name.and_then(|full_name| Collection::from_full_name(full_name))
+            {
+                return collection_call_type(collection, path, full_name_map, cache, depth);
+            }
             let prelude_type = PreludeType::from_type(ty_, full_name_map, cache);
             //result类型的变量不应该作为fuzzable的变量。只考虑作为别的函数的返回值
             match &prelude_type {
@@ -306,7 +708,8 @@ pub(crate) fn fuzzable_call_type(ty_: &clean::Type, full_name_map: &FullNameMap,
                     FuzzableCallType::NoFuzzable
                 }
                 PreludeType::PreludeOption(inner_type_) => {
-                    let inner_fuzzable_call_type = fuzzable_call_type(inner_type_, full_name_map, cache);
+                    let inner_fuzzable_call_type =
+                        fuzzable_call_type_at_depth(inner_type_, full_name_map, cache, depth);
                     match inner_fuzzable_call_type {
                         FuzzableCallType::NoFuzzable => {
                             return FuzzableCallType::NoFuzzable;
@@ -316,6 +719,18 @@ pub(crate) fn fuzzable_call_type(ty_: &clean::Type, full_name_map: &FullNameMap,
                         }
                     }
                 }
+                //Cow<str>由&str构造，Box<T>等由T构造
+                PreludeType::PreludeWrapper(wrapper, inner_type_) => {
+                    let inner_fuzzable_call_type =
+                        fuzzable_call_type_at_depth(inner_type_, full_name_map, cache, depth);
+                    match inner_fuzzable_call_type {
+                        FuzzableCallType::NoFuzzable => FuzzableCallType::NoFuzzable,
+                        _ => FuzzableCallType::ToWrapper(
+                            *wrapper,
+                            Box::new(inner_fuzzable_call_type),
+                        ),
+                    }
+                }
             }
         }
         clean::Type::Generic(s) => {
@@ -328,7 +743,8 @@ pub(crate) fn fuzzable_call_type(ty_: &clean::Type, full_name_map: &FullNameMap,
         clean::Type::Tuple(types) => {
             let mut vec = Vec::new();
             for inner_type in types {
-                let inner_fuzzable = fuzzable_call_type(inner_type, full_name_map, cache);
+                let inner_fuzzable =
+                    fuzzable_call_type_at_depth(inner_type, full_name_map, cache, depth);
                 match inner_fuzzable {
                     FuzzableCallType::NoFuzzable => {
                         return FuzzableCallType::NoFuzzable;
@@ -342,7 +758,8 @@ pub(crate) fn fuzzable_call_type(ty_: &clean::Type, full_name_map: &FullNameMap,
         }
         clean::Type::Slice(inner_type) => {
             let inner_ty_ = &**inner_type;
-            let inner_fuzzable = fuzzable_call_type(inner_ty_, full_name_map, cache);
+            let inner_fuzzable =
+                fuzzable_call_type_at_depth(inner_ty_, full_name_map, cache, depth);
             match inner_fuzzable {
                 FuzzableCallType::NoFuzzable => {
                     return FuzzableCallType::NoFuzzable;
@@ -352,21 +769,28 @@ pub(crate) fn fuzzable_call_type(ty_: &clean::Type, full_name_map: &FullNameMap,
                 }
             }
         }
-        clean::Type::Array(inner_type, ..) => {
+        clean::Type::Array(inner_type, length) => {
             let inner_ty_ = &**inner_type;
-            let inner_fuzzable = fuzzable_call_type(inner_ty_, full_name_map, cache);
+            //长度是常量表达式时无法确定长度
+            let length = match length.parse::<usize>() {
+                Ok(length) => length,
+                Err(_) => return FuzzableCallType::NoFuzzable,
+            };
+            let inner_fuzzable =
+                fuzzable_call_type_at_depth(inner_ty_, full_name_map, cache, depth);
             match inner_fuzzable {
                 FuzzableCallType::NoFuzzable => {
                     return FuzzableCallType::NoFuzzable;
                 }
                 _ => {
-                    return FuzzableCallType::Array(Box::new(inner_fuzzable));
+                    return FuzzableCallType::Array(Box::new(inner_fuzzable), length);
                 }
             }
         }
         clean::Type::RawPointer(mutability, type_) => {
             let inner_type = &**type_;
-            let inner_fuzzable = fuzzable_call_type(inner_type, full_name_map, cache);
+            let inner_fuzzable =
+                fuzzable_call_type_at_depth(inner_type, full_name_map, cache, depth);
             match inner_fuzzable {
                 FuzzableCallType::NoFuzzable => {
                     return FuzzableCallType::NoFuzzable;
@@ -402,7 +826,8 @@ pub(crate) fn fuzzable_call_type(ty_: &clean::Type, full_name_map: &FullNameMap,
                 }
                 return FuzzableCallType::STR;
             }
-            let inner_fuzzable = fuzzable_call_type(inner_type, full_name_map, cache);
+            let inner_fuzzable =
+                fuzzable_call_type_at_depth(inner_type, full_name_map, cache, depth);
             match inner_fuzzable {
                 FuzzableCallType::NoFuzzable => {
                     return FuzzableCallType::NoFuzzable;
@@ -420,7 +845,28 @@ pub(crate) fn fuzzable_call_type(ty_: &clean::Type, full_name_map: &FullNameMap,
         clean::Type::QPath { .. } => {
             return FuzzableCallType::NoFuzzable;
         }
-        clean::Type::ImplTrait(..)| clean::Type::DynTrait(..) => {
+        clean::Type::ImplTrait(bounds) => {
+            if let Some(adapter) = api_util::_io_adapter(bounds) {
+                return FuzzableCallType::Io(adapter);
+            }
+            //迭代器参数，元素从输入中得到
+            let item = match api_util::_iterator_item_type(bounds) {
+                Some(item) => item,
+                None => return FuzzableCallType::NoFuzzable,
+            };
+            let (item, by_ref) = match &item {
+                clean::Type::BorrowedRef { mutability: Mutability::Not, type_, .. } => {
+                    ((**type_).clone(), true)
+                }
+                _ => (item, false),
+            };
+            let item_call_type = fuzzable_call_type_at_depth(&item, full_name_map, cache, depth);
+            if let FuzzableCallType::NoFuzzable = item_call_type {
+                return FuzzableCallType::NoFuzzable;
+            }
+            return FuzzableCallType::Iterator(Box::new(item_call_type), by_ref);
+        }
+        clean::Type::DynTrait(..) => {
             return FuzzableCallType::NoFuzzable;
         }
         clean::Type::Infer => {
@@ -431,3 +877,52 @@ pub(crate) fn fuzzable_call_type(ty_: &clean::Type, full_name_map: &FullNameMap,
         }
     }
 }
+
+//HashMap<K, V>等集合的元素都是String，或者都可以从输入解码。指定了hasher的集合不能用collect构造
+fn collection_call_type(
+    collection: Collection,
+    path: &clean::Path,
+    full_name_map: &FullNameMap,
+    cache: &Cache,
+    depth: usize,
+) -> FuzzableCallType {
+    let element_number = if collection.is_map() { 2 } else { 1 };
+    let element_types = match path.generics() {
+        Some(element_types) if element_types.len() == element_number => element_types,
+        _ => return FuzzableCallType::NoFuzzable,
+    };
+    let is_string = |ty_: &clean::Type| {
+        ty_.def_id(cache)
+            .and_then(|def_id| full_name_map._get_full_name(def_id))
+            .map_or(false, |full_name| full_name == "alloc::string::String")
+    };
+    if element_types.iter().all(|ty_| is_string(ty_)) {
+        return FuzzableCallType::TextCollection(collection);
+    }
+    let mut elements = Vec::new();
+    for ty_ in element_types {
+        match fuzzable_call_type_at_depth(ty_, full_name_map, cache, depth) {
+            FuzzableCallType::NoFuzzable => return FuzzableCallType::NoFuzzable,
+            element => elements.push(element),
+        }
+    }
+    FuzzableCallType::Collection(collection, elements)
+}
+
+//key需要Hash和Ord，浮点数和Cell、RefCell都不行
+fn _is_hashable_key(fuzzable_type: &FuzzableType, call_type: &CallType) -> bool {
+    let has_float = match fuzzable_type {
+        FuzzableType::Primitive(primitive) => {
+            matches!(primitive, PrimitiveType::F32 | PrimitiveType::F64)
+        }
+        FuzzableType::Tuple(inner_types) => inner_types
+            .iter()
+            .any(|inner_type| !_is_hashable_key(inner_type, &CallType::_DirectCall)),
+        FuzzableType::Array(inner_type, _) => !_is_hashable_key(inner_type, &CallType::_DirectCall),
+        _ => false,
+    };
+    !has_float
+        && !call_type._call_type_to_array().iter().any(|call_type| {
+            matches!(call_type, CallType::_ToWrapper(_, Wrapper::Cell | Wrapper::RefCell))
+        })
+}
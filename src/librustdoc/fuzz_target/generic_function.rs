@@ -1,5 +1,17 @@
 use crate::clean;
-use rustc_data_structures::fx::{FxHashMap};
+use crate::formats::item_type::ItemType;
+use crate::fuzz_target::api_function;
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_util;
+use crate::fuzz_target::where_clause;
+use rustc_data_structures::fx::FxHashMap;
+use rustc_hir as hir;
+use rustc_hir::Mutability;
+use rustc_hir::def::{DefKind, Res};
+use rustc_hir::def_id::DefId;
+use rustc_middle::ty::TyCtxt;
+use rustc_span::symbol::{Symbol, sym};
+use thin_vec::ThinVec;
 
 use super::api_function::ApiFunction;
 
@@ -14,3 +26,469 @@ fn from(api_function: ApiFunction) -> Self {
         GenericFunction { api_function, generic_substitute: FxHashMap::default() }
     }
 }
+
+//Concrete types for the type parameters of generic apis, from `[generic-hints]` of rulf.toml.
+//A bound hint (e.g. `Hasher = "std::collections::hash_map::DefaultHasher"`) is used for every
+//type parameter with that trait bound, a function hint for one parameter of one api, and takes
+//precedence. The types are written as in code and resolved when the graph is built.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct GenericHints {
+    //(trait, type)
+    pub(crate) bounds: Vec<(String, String)>,
+    //(api, type parameter, type)
+    pub(crate) functions: Vec<(String, String, String)>,
+}
+
+impl GenericHints {
+    pub(crate) fn from_toml(value: &toml::Value) -> Result<Self, String> {
+        let mut hints = GenericHints::default();
+        let table = value.as_table().ok_or("expected a table")?;
+        for (key, value) in table {
+            let entries = value.as_table().ok_or(format!("`{}` is not a table", key))?;
+            for (name, value) in entries {
+                match (key.as_str(), value) {
+                    ("bounds", toml::Value::String(ty)) => {
+                        hints.bounds.push((name.clone(), ty.clone()))
+                    }
+                    ("functions", toml::Value::Table(params)) => {
+                        for (param, ty) in params {
+                            let ty = ty.as_str().ok_or(format!("`{}` is not a type", param))?;
+                            hints.functions.push((name.clone(), param.clone(), ty.to_string()));
+                        }
+                    }
+                    ("bounds" | "functions", _) => {
+                        return Err(format!("unexpected value of `{}.{}`", key, name));
+                    }
+                    _ => return Err(format!("unknown table `{}`", key)),
+                }
+            }
+        }
+        Ok(hints)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.bounds.is_empty() && self.functions.is_empty()
+    }
+}
+
+//the hints with their types resolved
+#[derive(Debug, Clone, Default)]
+pub(crate) struct HintTypes {
+    bounds: Vec<(String, clean::Type)>,
+    functions: FxHashMap<(String, String), clean::Type>,
+}
+
+//the types of the hints, hints whose type can't be resolved are skipped with a warning
+pub(crate) fn resolve_hints(api_graph: &mut ApiGraph<'_>) -> HintTypes {
+    let hints = api_graph.cx.options.generic_hints.clone();
+    let mut hint_types = HintTypes::default();
+    for (bound, ty) in &hints.bounds {
+        match parse_type(ty, api_graph) {
+            Ok(ty) => hint_types.bounds.push((bound.clone(), ty)),
+            Err(e) => println!("warning: hint of `{}` is skipped, {}", bound, e),
+        }
+    }
+    for (function, param, ty) in &hints.functions {
+        match parse_type(ty, api_graph) {
+            Ok(ty) => {
+                hint_types.functions.insert((function.clone(), param.clone()), ty);
+            }
+            Err(e) => println!("warning: hint of `{}::{}` is skipped, {}", function, param, e),
+        }
+    }
+    hint_types
+}
+
+impl GenericFunction {
+    //the hinted type of every type parameter, None if a parameter has no hint or its hint doesn't
+    //satisfy its bounds. Parameters only used by the output are not substituted, their type can't
+    //be inferred from the call
+    pub(crate) fn find_substitutes(
+        &self,
+        tcx: TyCtxt<'_>,
+        hint_types: &HintTypes,
+    ) -> Option<FxHashMap<String, clean::Type>> {
+        let api_function = &self.api_function;
+        let mut res = FxHashMap::default();
+        for param in &api_function.generics.params {
+            match &param.kind {
+                clean::GenericParamDefKind::Lifetime { .. } => continue,
+                clean::GenericParamDefKind::Const { .. } => return None,
+                //`impl Trait` parameters are substituted in the inputs directly
+                clean::GenericParamDefKind::Type { synthetic: true, .. } => continue,
+                clean::GenericParamDefKind::Type { .. } => {}
+            }
+            let bounds = generic_bounds(&api_function.generics, param.name);
+            let name = param.name.to_string();
+            let key = (api_function.full_name.clone(), name.clone());
+            let ty = match hint_types.functions.get(&key) {
+                Some(ty) => ty.clone(),
+                None => bound_hint(&bounds, hint_types)?,
+            };
+            if !where_clause::satisfies_bounds(tcx, &ty, &bounds) {
+                return None;
+            }
+            let param_substitute: FxHashMap<Symbol, clean::Type> =
+                [(param.name, ty.clone())].into_iter().collect();
+            let used_by_inputs = api_function
+                .inputs
+                .iter()
+                .any(|input| *input != substitute(input, &param_substitute));
+            if !used_by_inputs {
+                return None;
+            }
+            res.insert(name, ty);
+        }
+        for input in &api_function.inputs {
+            if let clean::Type::ImplTrait(bounds) = input {
+                let ty = impl_trait_substitute(bounds, hint_types)?;
+                if !where_clause::satisfies_bounds(tcx, &ty, bounds) {
+                    return None;
+                }
+            }
+        }
+        Some(res)
+    }
+
+    //the api with the substitutes of the type parameters, None if a type parameter is still used
+    pub(crate) fn monomorphized(
+        &self,
+        substitutes: &FxHashMap<String, clean::Type>,
+        hint_types: &HintTypes,
+    ) -> Option<ApiFunction> {
+        let substitutes: FxHashMap<Symbol, clean::Type> =
+            substitutes.iter().map(|(name, ty)| (Symbol::intern(name), ty.clone())).collect();
+        let mut api_function = self.api_function.clone();
+        for input in api_function.inputs.iter_mut() {
+            *input = match input {
+                clean::Type::ImplTrait(bounds) => impl_trait_substitute(bounds, hint_types)?,
+                _ => substitute(input, &substitutes),
+            };
+        }
+        api_function.output = api_function.output.map(|output| substitute(&output, &substitutes));
+        let still_generic =
+            api_function.inputs.iter().chain(api_function.output.iter()).any(|ty| {
+                !ty.is_impl_trait()
+                    && substitutes.keys().any(|name| api_util::_mentions_generic(ty, *name))
+            });
+        if still_generic {
+            return None;
+        }
+        api_function.generics = clean::Generics::default();
+        Some(api_function)
+    }
+}
+
+//A small trait solver for `T: Into<Foo>` and `impl Into<Foo>` parameters. Without it, such a
+//parameter takes a `Foo`, which another api has to build first. The `From` impls of `Foo` in the
+//crate give the types it converts from, blanket ones like `impl<T: AsRef<str>> From<T> for Foo`
+//included: the type parameter of a blanket impl is solved like an `impl Trait` parameter, e.g.
+//`&str` for `AsRef<str>`. The first type that can be built from the fuzz data is passed instead,
+//and the type parameter is removed. A parameter with other bounds, or used by other types of the
+//api, is left to the exact match of `_resolve_impl_trait_inputs`.
+pub(crate) fn resolve_into_bounds(api_function: &mut ApiFunction, api_graph: &ApiGraph<'_>) {
+    for input in api_function.inputs.iter_mut() {
+        if let clean::Type::ImplTrait(bounds) = input {
+            if let Some(source) =
+                into_target(bounds).and_then(|target| into_source(&target, api_graph))
+            {
+                *input = source;
+            }
+        }
+    }
+    let mut solved_params = Vec::new();
+    for param in &api_function.generics.params {
+        if !matches!(param.kind, clean::GenericParamDefKind::Type { synthetic: false, .. }) {
+            continue;
+        }
+        let generic = clean::Type::Generic(param.name);
+        let used_elsewhere = api_function
+            .output
+            .iter()
+            .chain(api_function.inputs.iter().filter(|input| **input != generic))
+            .any(|ty| api_util::_mentions_generic(ty, param.name));
+        if used_elsewhere {
+            continue;
+        }
+        let source = into_target(&generic_bounds(&api_function.generics, param.name))
+            .and_then(|target| into_source(&target, api_graph));
+        if let Some(source) = source {
+            for input in api_function.inputs.iter_mut().filter(|input| **input == generic) {
+                *input = source.clone();
+            }
+            solved_params.push(param.name);
+        }
+    }
+    //the synthetic parameters go with the last `impl Trait` input
+    let has_impl_trait = api_function.inputs.iter().any(|input| match input {
+        clean::Type::BorrowedRef { type_, .. } => type_.is_impl_trait(),
+        _ => input.is_impl_trait(),
+    });
+    api_function.generics.params.retain(|param| {
+        !solved_params.contains(&param.name) && (has_impl_trait || !param.is_synthetic_type_param())
+    });
+    api_function.generics.where_predicates.retain(|predicate| match predicate {
+        clean::WherePredicate::BoundPredicate { ty: clean::Type::Generic(name), .. } => {
+            !solved_params.contains(name)
+        }
+        _ => true,
+    });
+}
+
+//`Foo` of a single `Into<Foo>` bound
+fn into_target(bounds: &[clean::GenericBound]) -> Option<clean::Type> {
+    let mut traits = bounds.iter().filter_map(|bound| match bound {
+        clean::GenericBound::TraitBound(poly_trait, hir::TraitBoundModifier::None) => {
+            Some(&poly_trait.trait_)
+        }
+        _ => None,
+    });
+    match (traits.next(), traits.next()) {
+        (Some(trait_), None) if trait_.last() == sym::Into => {
+            trait_.generics()?.first().map(|target| (*target).clone())
+        }
+        _ => None,
+    }
+}
+
+//the first fuzzable type with a `From` impl for the target
+fn into_source(target: &clean::Type, api_graph: &ApiGraph<'_>) -> Option<clean::Type> {
+    let from_trait = api_graph.tcx().get_diagnostic_item(sym::From)?;
+    let impls = match target {
+        clean::Type::Path { path } => api_graph.cache().impls.get(&path.def_id())?,
+        _ => return None,
+    };
+    impls.iter().find_map(|impl_| {
+        let inner_impl = impl_.inner_impl();
+        let trait_ = inner_impl.trait_.as_ref().filter(|trait_| trait_.def_id() == from_trait)?;
+        let source = match trait_.generics()?.first()? {
+            clean::Type::Generic(name) => {
+                api_function::_impl_trait_substitute(&generic_bounds(&inner_impl.generics, *name))?
+            }
+            source => (*source).clone(),
+        };
+        let is_fuzzable =
+            api_util::is_fuzzable_type(&source, &api_graph.full_name_map, api_graph.cache());
+        (is_fuzzable && !api_util::_is_generic_type(&source)).then(|| source)
+    })
+}
+
+//the bounds of a type parameter, in its declaration and in the where clause
+fn generic_bounds(generics: &clean::Generics, name: Symbol) -> Vec<clean::GenericBound> {
+    let mut res = Vec::new();
+    for param in &generics.params {
+        if let clean::GenericParamDefKind::Type { bounds, .. } = &param.kind {
+            if param.name == name {
+                res.extend(bounds.iter().cloned());
+            }
+        }
+    }
+    for predicate in &generics.where_predicates {
+        if let clean::WherePredicate::BoundPredicate {
+            ty: clean::Type::Generic(generic),
+            bounds,
+            ..
+        } = predicate
+        {
+            if *generic == name {
+                res.extend(bounds.iter().cloned());
+            }
+        }
+    }
+    res
+}
+
+//iterator parameters are built by fuzzable_type without a hint
+fn impl_trait_substitute(
+    bounds: &Vec<clean::GenericBound>,
+    hint_types: &HintTypes,
+) -> Option<clean::Type> {
+    match bound_hint(bounds, hint_types) {
+        Some(ty) => Some(ty),
+        None if api_util::_iterator_item_type(bounds).is_some() => {
+            Some(clean::Type::ImplTrait(bounds.clone()))
+        }
+        None => None,
+    }
+}
+
+//the hint of the first bound that has one
+fn bound_hint(bounds: &Vec<clean::GenericBound>, hint_types: &HintTypes) -> Option<clean::Type> {
+    bounds.iter().find_map(|bound| match bound {
+        clean::GenericBound::TraitBound(poly_trait, _) => {
+            let trait_name = poly_trait.trait_.last();
+            hint_types.bounds.iter().find_map(|(hint_bound, ty)| {
+                let hint_name = hint_bound.rsplit("::").next().unwrap_or(hint_bound);
+                if hint_name == trait_name.as_str() { Some(ty.clone()) } else { None }
+            })
+        }
+        clean::GenericBound::Outlives(_) => None,
+    })
+}
+
+fn substitute(ty: &clean::Type, substitutes: &FxHashMap<Symbol, clean::Type>) -> clean::Type {
+    match ty {
+        clean::Type::Generic(name) => substitutes.get(name).cloned().unwrap_or(ty.clone()),
+        clean::Type::Path { path } => {
+            let mut path = path.clone();
+            for segment in path.segments.iter_mut() {
+                if let clean::GenericArgs::AngleBracketed { args, .. } = &mut segment.args {
+                    let new_args: Vec<clean::GenericArg> = args
+                        .iter()
+                        .map(|arg| match arg {
+                            clean::GenericArg::Type(inner_ty) => {
+                                clean::GenericArg::Type(substitute(inner_ty, substitutes))
+                            }
+                            _ => arg.clone(),
+                        })
+                        .collect();
+                    *args = new_args.into();
+                }
+            }
+            clean::Type::Path { path }
+        }
+        clean::Type::Tuple(types) => {
+            clean::Type::Tuple(types.iter().map(|ty| substitute(ty, substitutes)).collect())
+        }
+        clean::Type::Slice(type_) => clean::Type::Slice(Box::new(substitute(type_, substitutes))),
+        clean::Type::Array(type_, len) => {
+            clean::Type::Array(Box::new(substitute(type_, substitutes)), len.clone())
+        }
+        clean::Type::RawPointer(mutability, type_) => {
+            clean::Type::RawPointer(*mutability, Box::new(substitute(type_, substitutes)))
+        }
+        clean::Type::BorrowedRef { lifetime, mutability, type_ } => clean::Type::BorrowedRef {
+            lifetime: lifetime.clone(),
+            mutability: *mutability,
+            type_: Box::new(substitute(type_, substitutes)),
+        },
+        _ => ty.clone(),
+    }
+}
+
+//a type of a hint: paths with generic arguments, references, slices, arrays and tuples
+fn parse_type(text: &str, api_graph: &mut ApiGraph<'_>) -> Result<clean::Type, String> {
+    let text = text.trim();
+    if let Some(inner) = text.strip_prefix('&') {
+        let inner = inner.trim_start();
+        let (mutability, inner) = match inner.strip_prefix("mut ") {
+            Some(inner) => (Mutability::Mut, inner),
+            None => (Mutability::Not, inner),
+        };
+        let type_ = Box::new(parse_type(inner, api_graph)?);
+        return Ok(clean::Type::BorrowedRef { lifetime: None, mutability, type_ });
+    }
+    if let Some(inner) = text.strip_prefix('[').and_then(|text| text.strip_suffix(']')) {
+        return match split_top_level(inner, ';').as_slice() {
+            [element] => Ok(clean::Type::Slice(Box::new(parse_type(element, api_graph)?))),
+            [element, len] => Ok(clean::Type::Array(
+                Box::new(parse_type(element, api_graph)?),
+                len.trim().to_string(),
+            )),
+            _ => Err(format!("`{}` is not a type", text)),
+        };
+    }
+    if let Some(inner) = text.strip_prefix('(').and_then(|text| text.strip_suffix(')')) {
+        let mut types = Vec::new();
+        for element in split_top_level(inner, ',') {
+            if !element.trim().is_empty() {
+                types.push(parse_type(element, api_graph)?);
+            }
+        }
+        return Ok(clean::Type::Tuple(types));
+    }
+    let (path, args) = match text.find('<') {
+        Some(start) if text.ends_with('>') => {
+            (&text[..start], Some(&text[start + 1..text.len() - 1]))
+        }
+        Some(_) => return Err(format!("`{}` is not a type", text)),
+        None => (text, None),
+    };
+    let path = path.trim();
+    if args.is_none() && !path.contains("::") {
+        if let Some(primitive) = clean::PrimitiveType::from_symbol(Symbol::intern(path)) {
+            return Ok(clean::Type::Primitive(primitive));
+        }
+    }
+    let (def_kind, def_id) =
+        resolve_path(path, api_graph).ok_or(format!("type `{}` is not found", path))?;
+    let mut generic_args = Vec::new();
+    for arg in args.map(|args| split_top_level(args, ',')).unwrap_or_default() {
+        generic_args.push(clean::GenericArg::Type(parse_type(arg, api_graph)?));
+    }
+    let mut segments: Vec<clean::PathSegment> = path
+        .split("::")
+        .map(|name| clean::PathSegment {
+            name: Symbol::intern(name.trim()),
+            args: clean::GenericArgs::AngleBracketed {
+                args: Vec::new().into(),
+                bindings: ThinVec::new(),
+            },
+        })
+        .collect();
+    if let Some(last) = segments.last_mut() {
+        last.args = clean::GenericArgs::AngleBracketed {
+            args: generic_args.into(),
+            bindings: ThinVec::new(),
+        };
+    }
+    Ok(clean::Type::Path { path: clean::Path { res: Res::Def(def_kind, def_id), segments } })
+}
+
+//types of the crate are found by their full name, the others by following the path from the
+//root of the crate they are in. Found types are named by the path in the generated code
+fn resolve_path(path: &str, api_graph: &mut ApiGraph<'_>) -> Option<(DefKind, DefId)> {
+    let tcx = api_graph.tcx();
+    let known = api_graph.full_name_map.map.iter().find(|(_, (full_name, _))| full_name == path);
+    if let Some((def_id, _)) = known {
+        return Some((tcx.def_kind(*def_id), *def_id));
+    }
+    let mut segments = path.split("::").map(|segment| segment.trim());
+    let crate_name = segments.next()?;
+    let crate_num = tcx
+        .crates(())
+        .iter()
+        .cloned()
+        .find(|crate_num| tcx.crate_name(*crate_num).as_str() == crate_name)?;
+    let mut res = (DefKind::Mod, crate_num.as_def_id());
+    for segment in segments {
+        if res.0 != DefKind::Mod {
+            return None;
+        }
+        res = tcx.module_children(res.1).iter().find_map(|child| match child.res {
+            Res::Def(def_kind, def_id) if child.ident.name.as_str() == segment => {
+                Some((def_kind, def_id))
+            }
+            _ => None,
+        })?;
+    }
+    let item_type = match res.0 {
+        DefKind::Struct => ItemType::Struct,
+        DefKind::Enum => ItemType::Enum,
+        DefKind::Union => ItemType::Union,
+        DefKind::TyAlias => ItemType::Typedef,
+        _ => return None,
+    };
+    api_graph.full_name_map.push_mapping(res.1, &path.to_string(), item_type);
+    Some(res)
+}
+
+//split at the separator outside of brackets
+fn split_top_level(text: &str, separator: char) -> Vec<&str> {
+    let mut res = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (index, c) in text.char_indices() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' | ')' | ']' => depth -= 1,
+            c if c == separator && depth == 0 => {
+                res.push(&text[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    res.push(&text[start..]);
+    res
+}
@@ -0,0 +1,220 @@
+//Evolve api sequences with a genetic algorithm.
+//Individuals are api sequences. They are mutated (insert call, swap producer, truncate) and
+//crossed over, and every candidate is rebuilt through `is_fun_satisfied` so that only valid
+//sequences survive. Fitness combines api coverage(maximize) and sequence length(minimize),
+//and the final pareto front is used as the generated sequences.
+use crate::fuzz_target::api_graph::{ApiGraph, ApiType};
+use crate::fuzz_target::api_sequence::ApiSequence;
+use rand::seq::SliceRandom;
+use rand::{self, Rng};
+use rustc_data_structures::fx::FxHashSet;
+
+static POPULATION_SIZE: usize = 200;
+static GENERATIONS: usize = 100;
+static MUTATION_RATE: f64 = 0.8;
+static CROSSOVER_RATE: f64 = 0.3;
+static TOURNAMENT_SIZE: usize = 3;
+static MAX_SEQUENCE_LEN: usize = 10;
+
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+enum Mutation {
+    InsertCall,
+    SwapProducer,
+    Truncate,
+}
+
+static MUTATIONS: [Mutation; 3] =
+    [Mutation::InsertCall, Mutation::SwapProducer, Mutation::Truncate];
+
+#[derive(Debug, Clone)]
+struct Individual {
+    sequence: ApiSequence,
+    covered_apis: FxHashSet<usize>,
+}
+
+impl Individual {
+    fn new(sequence: ApiSequence) -> Self {
+        let covered_apis = sequence._get_contained_api_functions().into_iter().collect();
+        Individual { sequence, covered_apis }
+    }
+
+    fn coverage(&self) -> usize {
+        self.covered_apis.len()
+    }
+
+    fn len(&self) -> usize {
+        self.sequence.len()
+    }
+
+    //a dominates b: covers a superset of b's apis with a sequence no longer than b's,
+    //and is strictly better in at least one of the two objectives
+    fn dominates(&self, other: &Individual) -> bool {
+        if !other.covered_apis.is_subset(&self.covered_apis) || self.len() > other.len() {
+            return false;
+        }
+        self.coverage() > other.coverage() || self.len() < other.len()
+    }
+
+    //scalar fitness used by tournament selection
+    fn fitness(&self) -> f64 {
+        self.coverage() as f64 - 0.1 * self.len() as f64
+    }
+}
+
+pub(crate) fn genetic_algorithm(graph: &mut ApiGraph<'_>) {
+    graph.api_sequences.clear();
+    graph.reset_visited();
+    if graph.api_functions.len() <= 0 {
+        return;
+    }
+    let mut rng = rand::thread_rng();
+
+    let mut population = initial_population(graph);
+    if population.len() <= 0 {
+        return;
+    }
+    let mut archive: Vec<Individual> = Vec::new();
+    for individual in &population {
+        add_to_archive(&mut archive, individual);
+    }
+
+    //with a time budget, the archive of the last complete generation is kept
+    for _ in 0..GENERATIONS {
+        if graph.is_out_of_time() {
+            break;
+        }
+        let mut offspring = Vec::new();
+        while offspring.len() < POPULATION_SIZE {
+            let parent = tournament_select(&population, &mut rng);
+            let child = if rng.gen::<f64>() < CROSSOVER_RATE {
+                let other_parent = tournament_select(&population, &mut rng);
+                crossover(graph, parent, other_parent, &mut rng)
+            } else {
+                Some(parent.sequence.clone())
+            };
+            let child = match child {
+                Some(child) if rng.gen::<f64>() < MUTATION_RATE => {
+                    let mutation = MUTATIONS.choose(&mut rng).unwrap();
+                    mutate(graph, &child, *mutation, &mut rng)
+                }
+                child => child,
+            };
+            match child {
+                Some(child) if child.len() > 0 && child.len() <= MAX_SEQUENCE_LEN => {
+                    offspring.push(Individual::new(child));
+                }
+                _ => {
+                    //invalid child, keep the parent so that the loop always terminates
+                    offspring.push(parent.clone());
+                }
+            }
+        }
+        for individual in &offspring {
+            add_to_archive(&mut archive, individual);
+        }
+        population = offspring;
+    }
+
+    println!("genetic algorithm found {} pareto-optimal sequences", archive.len());
+    for individual in archive {
+        for api_index in &individual.covered_apis {
+            graph.api_functions_visited[*api_index] = true;
+        }
+        graph.api_sequences.push(individual.sequence);
+    }
+}
+
+//every api that can be called on an empty sequence forms an individual of length 1, and every
+//mined sequence an individual of its own
+fn initial_population(graph: &ApiGraph<'_>) -> Vec<Individual> {
+    let mut population = Vec::new();
+    let empty_sequence = ApiSequence::new();
+    let api_type = ApiType::BareFunction;
+    for api_index in 0..graph.api_functions.len() {
+        if let Some(sequence) = graph.is_fun_satisfied(&api_type, api_index, &empty_sequence) {
+            population.push(Individual::new(sequence));
+        }
+    }
+    population.extend(graph.mined_sequences.iter().cloned().map(Individual::new));
+    population
+}
+
+fn add_to_archive(archive: &mut Vec<Individual>, individual: &Individual) {
+    if individual.sequence._has_no_fuzzables() {
+        return;
+    }
+    for archived in archive.iter() {
+        if archived.dominates(individual)
+            || (archived.covered_apis == individual.covered_apis
+                && archived.len() == individual.len())
+        {
+            return;
+        }
+    }
+    archive.retain(|archived| !individual.dominates(archived));
+    archive.push(individual.clone());
+}
+
+fn tournament_select<'a, R: Rng>(population: &'a Vec<Individual>, rng: &mut R) -> &'a Individual {
+    let mut best = &population[rng.gen_range(0, population.len())];
+    for _ in 1..TOURNAMENT_SIZE {
+        let candidate = &population[rng.gen_range(0, population.len())];
+        if candidate.fitness() > best.fitness() {
+            best = candidate;
+        }
+    }
+    best
+}
+
+fn crossover<R: Rng>(
+    graph: &ApiGraph<'_>,
+    first: &Individual,
+    second: &Individual,
+    rng: &mut R,
+) -> Option<ApiSequence> {
+    let first_functions = first.sequence.function_indexes();
+    let second_functions = second.sequence.function_indexes();
+    let cut_first = rng.gen_range(0, first_functions.len() + 1);
+    let cut_second = rng.gen_range(0, second_functions.len() + 1);
+    let mut functions = first_functions[..cut_first].to_vec();
+    functions.extend_from_slice(&second_functions[cut_second..]);
+    graph.rebuild_sequence(&functions)
+}
+
+fn mutate<R: Rng>(
+    graph: &ApiGraph<'_>,
+    sequence: &ApiSequence,
+    mutation: Mutation,
+    rng: &mut R,
+) -> Option<ApiSequence> {
+    let mut functions = sequence.function_indexes();
+    match mutation {
+        Mutation::InsertCall => {
+            let api_index = rng.gen_range(0, graph.api_functions.len());
+            let position = rng.gen_range(0, functions.len() + 1);
+            functions.insert(position, api_index);
+        }
+        Mutation::SwapProducer => {
+            if functions.len() <= 0 {
+                return None;
+            }
+            //replace one call with another api returning the same type
+            let position = rng.gen_range(0, functions.len());
+            let output = &graph.api_functions[functions[position]].output;
+            let producers: Vec<usize> = (0..graph.api_functions.len())
+                .filter(|index| {
+                    *index != functions[position] && graph.api_functions[*index].output == *output
+                })
+                .collect();
+            functions[position] = *producers.choose(rng)?;
+        }
+        Mutation::Truncate => {
+            if functions.len() <= 1 {
+                return None;
+            }
+            let new_len = rng.gen_range(1, functions.len());
+            functions.truncate(new_len);
+        }
+    }
+    graph.rebuild_sequence(&functions)
+}
@@ -0,0 +1,124 @@
+//Cache of the dependencies of the api graph in `target/rulf-cache/`, keyed by the hash of the
+//crate. Finding the dependencies compares the output of every api with every parameter of the
+//other apis, which takes most of the analysis of large crates. When the generator runs again on
+//an unchanged crate, e.g. with another strategy or max length, only this comparison is skipped.
+//Nothing else is cached: rustdoc still compiles and cleans the crate, and the apis are collected,
+//monomorphized and filtered again. The types can't be serialized, so the cache only records
+//which output goes to which parameter, as index triples, and the call types of these edges are
+//computed again. The apis are matched by their signatures, not only their names, because
+//resolving the bounds of a generic or an impl Trait changes the inputs of an api but not its name.
+//The cache is only used with `--graph-cache`, a stale cache would silently change the targets.
+use crate::fuzz_target::api_graph::ApiGraph;
+use rustc_span::def_id::LOCAL_CRATE;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+
+static CACHE_DIR: &'static str = "rulf-cache";
+//changed when the format or the way dependencies are found changes
+static CACHE_VERSION: u32 = 2;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct GraphCache {
+    version: u32,
+    //the options the apis of the graph depend on
+    options: String,
+    //signatures of the apis, the dependencies refer to them by index
+    api_functions: Vec<String>,
+    //(output function, input function, input param)
+    dependencies: Vec<(usize, usize, usize)>,
+}
+
+//find the dependencies of the graph, or load them from the cache
+pub(crate) fn find_all_dependencies(api_graph: &mut ApiGraph<'_>) {
+    if !api_graph.cx.options.graph_cache {
+        api_graph.find_all_dependencies();
+        return;
+    }
+    let path = cache_path(api_graph);
+    let options = graph_options(api_graph);
+    let api_functions = api_signatures(api_graph);
+    let cached = fs::read(&path)
+        .ok()
+        .and_then(|content| serde_json::from_slice::<GraphCache>(&content).ok());
+    if let Some(cached) = cached {
+        //the functions are filtered by options that are not part of the crate hash
+        if cached.version == CACHE_VERSION
+            && cached.options == options
+            && cached.api_functions == api_functions
+        {
+            api_graph.restore_dependencies(&cached.dependencies);
+            println!("dependencies of the api graph are loaded from {}", path.display());
+            return;
+        }
+    }
+
+    api_graph.find_all_dependencies();
+    let dependencies = api_graph
+        .api_dependencies
+        .iter()
+        .map(|dependency| {
+            (dependency.output_fun.1, dependency.input_fun.1, dependency.input_param_index)
+        })
+        .collect();
+    let cache = GraphCache { version: CACHE_VERSION, options, api_functions, dependencies };
+    let content = match serde_json::to_string(&cache) {
+        Ok(content) => content,
+        Err(e) => {
+            println!("warning: failed to serialize the api graph: {}", e);
+            return;
+        }
+    };
+    let written = match path.parent() {
+        Some(dir) => fs::create_dir_all(dir).and_then(|_| fs::write(&path, content)),
+        None => fs::write(&path, content),
+    };
+    match written {
+        Ok(_) => println!("dependencies of the api graph are cached in {}", path.display()),
+        Err(e) => println!("warning: failed to write {}: {}", path.display(), e),
+    }
+}
+
+//`./target` unless the manifest is known
+pub(crate) fn target_dir(api_graph: &ApiGraph<'_>) -> PathBuf {
+    match &api_graph.cx.options.lib_target {
+        Some(lib_target) => lib_target.target_directory.clone(),
+        None => PathBuf::from("target"),
+    }
+}
+
+//`<target dir>/rulf-cache/<crate>-<crate hash>.json`
+fn cache_path(api_graph: &ApiGraph<'_>) -> PathBuf {
+    let crate_hash = api_graph.tcx().crate_hash(LOCAL_CRATE);
+    target_dir(api_graph)
+        .join(CACHE_DIR)
+        .join(format!("{}-{}.json", api_graph._crate_name, crate_hash))
+}
+
+//the full name of every api and a digest of the types of its inputs and output
+fn api_signatures(api_graph: &ApiGraph<'_>) -> Vec<String> {
+    api_graph
+        .api_functions
+        .iter()
+        .map(|api_function| {
+            //a mismatch of the digests only makes the cache miss
+            let mut hasher = DefaultHasher::new();
+            format!("{:?} -> {:?}", api_function.inputs, api_function.output).hash(&mut hasher);
+            format!("{} {:016x}", api_function.full_name, hasher.finish())
+        })
+        .collect()
+}
+
+fn graph_options(api_graph: &ApiGraph<'_>) -> String {
+    let options = &api_graph.cx.options;
+    format!(
+        "lib_name={} features={:?} dependency_crates={:?} msrv={:?} max_struct_depth={}",
+        api_graph.cx.lib_name,
+        options.features,
+        options.dependency_crates,
+        options.msrv,
+        options.max_struct_depth
+    )
+}
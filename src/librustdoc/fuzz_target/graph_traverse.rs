@@ -0,0 +1,567 @@
+//Enumerate api sequences on a thread pool. The graph holds the type context, which can't be
+//shared between threads, so the parts needed to extend a sequence with a call are borrowed by a
+//`SequenceExtender`. Every sequence starts with an entry api, one whose parameters are all
+//fuzzable, and the sequences starting with different entry apis are enumerated independently.
+//The levels of all entry apis are merged in the order of the entry apis, which gives exactly the
+//sequences of the sequential bfs, in the same order, whatever the number of threads.
+//
+//The algorithms are `SequenceStrategy`s registered by name in a `StrategyRegistry`, `--strategy`
+//selects one of them. A strategy only reads the graph and returns its sequences, the graph marks
+//the apis they call as covered, so a new algorithm doesn't touch the graph.
+use crate::formats::cache::Cache;
+use crate::fuzz_target::api_function::ApiFunction;
+use crate::fuzz_target::api_graph::{ApiDependency, ApiGraph, ApiType, GraphTraverseAlgorithm};
+use crate::fuzz_target::api_sequence::{ApiCall, ApiSequence, ParamType};
+use crate::fuzz_target::api_util;
+use crate::fuzz_target::fuzzable_type::{self, FuzzableType};
+use crate::fuzz_target::impl_util::FullNameMap;
+use crate::fuzz_target::multi_object::MultiObjectStrategy;
+use crate::fuzz_target::sink_search::SinkStrategy;
+use rayon::prelude::*;
+use rustc_data_structures::fx::FxHashSet;
+use std::cmp::Reverse;
+use std::time::Instant;
+
+pub(crate) struct SequenceExtender<'a> {
+    pub(crate) api_functions: &'a Vec<ApiFunction>,
+    pub(crate) api_dependencies: &'a Vec<ApiDependency>,
+    pub(crate) full_name_map: &'a FullNameMap,
+    pub(crate) cache: &'a Cache,
+    //the end of the time budget, the sequences stop being extended after it
+    pub(crate) deadline: Option<Instant>,
+    //apis whose result should be consumed rather than dropped
+    pub(crate) must_use_apis: &'a FxHashSet<String>,
+    //sequences mined from the crates using the crate, extended like the entry sequences
+    pub(crate) mined_sequences: &'a Vec<ApiSequence>,
+}
+
+impl<'a> SequenceExtender<'a> {
+    //判断一个函数能否加入给定的序列中,如果可以加入，返回Some(new_sequence),new_sequence是将新的调用加进去之后的情况，否则返回None
+    pub(crate) fn is_fun_satisfied(
+        &self,
+        input_type: &ApiType,
+        input_fun_index: usize,
+        sequence: &ApiSequence,
+    ) -> Option<ApiSequence> {
+        self.add_call(input_type, input_fun_index, sequence, false)
+    }
+
+    //同is_fun_satisfied，但权重相同时优先使用本次调用还没用过的返回值，
+    //需要两个实例的api会用到两个对象，只用于multi-object策略
+    pub(crate) fn is_fun_satisfied_by_distinct_objects(
+        &self,
+        input_type: &ApiType,
+        input_fun_index: usize,
+        sequence: &ApiSequence,
+    ) -> Option<ApiSequence> {
+        self.add_call(input_type, input_fun_index, sequence, true)
+    }
+
+    fn add_call(
+        &self,
+        input_type: &ApiType,
+        input_fun_index: usize,
+        sequence: &ApiSequence,
+        distinct_objects: bool,
+    ) -> Option<ApiSequence> {
+        //判断一个给定的函数能否加入到一个sequence中去
+        match input_type {
+            ApiType::BareFunction => {
+                let mut new_sequence = sequence.clone();
+                let mut api_call = ApiCall::_new(input_fun_index);
+                let mut _moved_indexes = FxHashSet::default(); //用来保存发生move的那些语句的index
+                //用来保存会被多次可变引用的情况
+                let mut _multi_mut = FxHashSet::default();
+                let mut _immutable_borrow = FxHashSet::default();
+                //本次调用使用了哪些之前的返回值(参数位置，返回值位置，是否独占使用)
+                let mut _used_function_returns = Vec::new();
+
+                let input_function = &self.api_functions[input_fun_index];
+                //如果是个unsafe函数，给sequence添加unsafe标记
+                if input_function._unsafe_tag._is_unsafe() {
+                    new_sequence.set_unsafe();
+                }
+                //如果是个async函数，整个sequence需要在async块中执行
+                if input_function._async_tag._is_async() {
+                    new_sequence.set_async();
+                }
+                if input_function._trait_full_path.is_some() {
+                    let trait_full_path = input_function._trait_full_path.as_ref().unwrap();
+                    new_sequence.add_trait(trait_full_path);
+                }
+                let input_params = &input_function.inputs;
+                let input_params_num = input_params.len();
+                if input_params_num == 0 {
+                    //无需输入参数，直接是可满足的
+                    new_sequence._add_fn(api_call);
+                    return Some(new_sequence);
+                }
+
+                for i in 0..input_params_num {
+                    let current_ty = &input_params[i];
+                    if api_util::is_fuzzable_type(current_ty, &self.full_name_map, self.cache) {
+                        //如果当前参数是fuzzable的
+                        let current_fuzzable_index = new_sequence.fuzzable_params.len();
+                        let fuzzable_call_type = fuzzable_type::fuzzable_call_type(
+                            current_ty,
+                            &self.full_name_map,
+                            self.cache,
+                        );
+                        let (fuzzable_type, call_type) =
+                            fuzzable_call_type.generate_fuzzable_type_and_call_type();
+
+                        //如果出现了下面这段话，说明出现了Fuzzable参数但不知道如何参数化的
+                        //典型例子是tuple里面出现了引用（&usize），这种情况不再去寻找dependency，直接返回无法添加即可
+                        match &fuzzable_type {
+                            FuzzableType::NoFuzzable => {
+                                //println!("Fuzzable Type Error Occurs!");
+                                //println!("type = {:?}", current_ty);
+                                //println!("fuzzable_call_type = {:?}", fuzzable_call_type);
+                                //println!("fuzzable_type = {:?}", fuzzable_type);
+                                return None;
+                            }
+                            _ => {}
+                        }
+
+                        //判断要不要加mut tag
+                        if api_util::_need_mut_tag(&call_type) {
+                            new_sequence._insert_fuzzable_mut_tag(current_fuzzable_index);
+                        }
+
+                        //添加到sequence中去
+                        new_sequence.fuzzable_params.push(fuzzable_type);
+                        api_call._add_param(
+                            ParamType::_FuzzableType,
+                            current_fuzzable_index,
+                            call_type,
+                        );
+                        continue;
+                    }
+                    //如果当前参数不是fuzzable的，那么就去api sequence寻找是否有这个依赖
+                    //TODO:处理move的情况
+                    let functions_in_sequence_len = sequence.functions.len();
+                    let mut dependency_flag = false;
+
+                    //可以满足这个参数的返回值，优先使用在crate中被调用得更多的函数的返回值
+                    let mut producers = Vec::new();
+                    for function_index in 0..functions_in_sequence_len {
+                        //如果这个sequence里面的该函数返回值已经被move掉了，那么就跳过，不再能被使用了
+                        if new_sequence._is_moved(function_index)
+                            || _moved_indexes.contains(&function_index)
+                        {
+                            continue;
+                        }
+                        let found_function = &new_sequence.functions[function_index];
+                        let (api_type, index) = &found_function.func;
+                        if let Some(dependency_index) =
+                            self.check_dependency(api_type, *index, input_type, input_fun_index, i)
+                        {
+                            producers.push((function_index, dependency_index));
+                        }
+                    }
+                    producers.sort_by_key(|(function_index, dependency_index)| {
+                        let used = distinct_objects
+                            && _used_function_returns
+                                .iter()
+                                .any(|(_, used_index, _)| used_index == function_index);
+                        (Reverse(self.api_dependencies[*dependency_index].weight), used)
+                    });
+                    for (function_index, dependency_index) in producers {
+                        let dependency_ = self.api_dependencies[dependency_index].clone();
+                        //将覆盖到的边加入到新的sequence中去
+                        new_sequence._add_dependency(dependency_index);
+                        //找到了依赖，当前参数是可以被满足的，设置flag并退出循环
+                        dependency_flag = true;
+                        //如果满足move发生的条件，那么
+                        if api_util::_move_condition(current_ty, &dependency_.call_type) {
+                            if _multi_mut.contains(&function_index)
+                                || _immutable_borrow.contains(&function_index)
+                            {
+                                dependency_flag = false;
+                                continue;
+                            } else {
+                                _moved_indexes.insert(function_index);
+                            }
+                        }
+                        //如果当前调用是可变借用
+                        if api_util::_is_mutable_borrow_occurs(current_ty, &dependency_.call_type) {
+                            //如果之前已经被借用过了
+                            if _multi_mut.contains(&function_index)
+                                || _immutable_borrow.contains(&function_index)
+                            {
+                                dependency_flag = false;
+                                continue;
+                            } else {
+                                _multi_mut.insert(function_index);
+                            }
+                        }
+                        //如果当前调用是引用，且之前已经被可变引用过，那么这个引用是非法的
+                        if api_util::_is_immutable_borrow_occurs(current_ty, &dependency_.call_type)
+                        {
+                            if _multi_mut.contains(&function_index) {
+                                dependency_flag = false;
+                                continue;
+                            } else {
+                                _immutable_borrow.insert(function_index);
+                            }
+                        }
+                        //参数需要加mut 标记的话
+                        if api_util::_need_mut_tag(&dependency_.call_type) {
+                            new_sequence._insert_function_mut_tag(function_index);
+                        }
+                        //如果call type是unsafe的，那么给sequence加上unsafe标记
+                        if dependency_.call_type.unsafe_call_type()._is_unsafe() {
+                            new_sequence.set_unsafe();
+                        }
+                        _used_function_returns.push((
+                            i,
+                            function_index,
+                            api_util::_is_exclusive_use(current_ty, &dependency_.call_type),
+                        ));
+                        api_call._add_param(
+                            ParamType::_FunctionReturn,
+                            function_index,
+                            dependency_.call_type,
+                        );
+                        break;
+                    }
+                    if !dependency_flag {
+                        //如果这个参数没有寻找到依赖，则这个函数不可以被加入到序列中
+                        return None;
+                    }
+                }
+                //借用检查：使用一个返回值会使与之冲突的借用失效，失效的借用不能在同一个调用中使用
+                let mut _expired_borrows = FxHashSet::default();
+                for (_, function_index, exclusive) in &_used_function_returns {
+                    let borrowers =
+                        new_sequence._conflicting_borrowers(*function_index, *exclusive);
+                    for borrower in borrowers {
+                        _expired_borrows.insert(borrower);
+                    }
+                }
+                for (_, function_index, _) in &_used_function_returns {
+                    if _expired_borrows.contains(function_index) {
+                        return None;
+                    }
+                }
+                //所有参数都可以找到依赖，那么这个函数就可以加入序列
+                new_sequence._add_fn(api_call);
+                let new_function_index = new_sequence.functions.len() - 1;
+                //返回值含有引用时，记录它借用了哪些返回值(包括被借用的返回值本身所借用的)。
+                //只继承借用的参数不需要比返回值活得久，可以被之后的调用move
+                let borrowed_inputs = input_function._borrowed_inputs();
+                for (param_index, function_index, _) in &_used_function_returns {
+                    if let Some((_, mutable, borrows_itself)) =
+                        borrowed_inputs.iter().find(|(index, ..)| index == param_index)
+                    {
+                        if *borrows_itself {
+                            new_sequence._add_borrow(new_function_index, *function_index, *mutable);
+                        }
+                        let owners = new_sequence._borrowed_owners(*function_index);
+                        for (owner, owner_mutable) in owners {
+                            new_sequence._add_borrow(new_function_index, owner, owner_mutable);
+                        }
+                    }
+                }
+                for move_index in _moved_indexes {
+                    new_sequence._insert_move_index(move_index);
+                }
+                //失效的借用不再能被使用
+                for expired_index in _expired_borrows {
+                    new_sequence._insert_move_index(expired_index);
+                }
+                if new_sequence._contains_multi_dynamic_length_fuzzable() {
+                    //如果新生成的序列包含多维可变的参数，就不把这个序列加进去
+                    return None;
+                }
+                return Some(new_sequence);
+            }
+        }
+    }
+
+    pub(crate) fn check_dependency(
+        &self,
+        output_type: &ApiType,
+        output_index: usize,
+        input_type: &ApiType,
+        input_index: usize,
+        input_param_index_: usize,
+    ) -> Option<usize> {
+        let dependency_num = self.api_dependencies.len();
+        for index in 0..dependency_num {
+            let dependency = &self.api_dependencies[index];
+            //TODO:直接比较每一项内容是否可以节省点时间？
+            let tmp_dependency = ApiDependency {
+                output_fun: (*output_type, output_index),
+                input_fun: (*input_type, input_index),
+                input_param_index: input_param_index_,
+                call_type: dependency.call_type.clone(),
+                weight: dependency.weight,
+            };
+            if tmp_dependency == *dependency {
+                //存在依赖
+                return Some(index);
+            }
+        }
+        //没找到依赖
+        return None;
+    }
+
+    //the apis taking the result of a `#[must_use]` call of the sequence that no later call takes
+    pub(crate) fn must_use_consumers(&self, sequence: &ApiSequence) -> Vec<usize> {
+        let mut consumed = FxHashSet::default();
+        for api_call in &sequence.functions {
+            for (param_type, index, _) in &api_call.params {
+                if let ParamType::_FunctionReturn = param_type {
+                    consumed.insert(*index);
+                }
+            }
+        }
+        let unconsumed: FxHashSet<usize> = sequence
+            .functions
+            .iter()
+            .enumerate()
+            .filter(|(k, api_call)| {
+                !consumed.contains(k)
+                    && self.must_use_apis.contains(&self.api_functions[api_call.func.1].full_name)
+            })
+            .map(|(_, api_call)| api_call.func.1)
+            .collect();
+        if unconsumed.is_empty() {
+            return Vec::new();
+        }
+        let mut res: Vec<usize> = self
+            .api_dependencies
+            .iter()
+            .filter(|dependency| unconsumed.contains(&dependency.output_fun.1))
+            .map(|dependency| dependency.input_fun.1)
+            .collect();
+        res.sort_unstable();
+        res.dedup();
+        res
+    }
+
+    //the apis to try after the sequence: the consumers of its unused `#[must_use]` results first,
+    //so the result is used instead of dropped by `let _`, then the others in order
+    pub(crate) fn successors(&self, sequence: &ApiSequence) -> Vec<usize> {
+        let mut res = self.must_use_consumers(sequence);
+        let consumers: FxHashSet<usize> = res.iter().copied().collect();
+        res.extend((0..self.api_functions.len()).filter(|index| !consumers.contains(index)));
+        res
+    }
+
+    pub(crate) fn is_out_of_time(&self) -> bool {
+        self.deadline.map_or(false, |deadline| Instant::now() >= deadline)
+    }
+
+    //判断一个调用序列是否已经到达终止端点
+    pub(crate) fn is_sequence_ended(&self, api_sequence: &ApiSequence) -> bool {
+        let functions = &api_sequence.functions;
+        let last_fun = functions.last();
+        let cache = self.cache;
+        match last_fun {
+            None => false,
+            Some(api_call) => {
+                let (api_type, index) = &api_call.func;
+                match api_type {
+                    ApiType::BareFunction => {
+                        let last_func = &self.api_functions[*index];
+                        if last_func._is_end_function(&self.full_name_map, cache) {
+                            return true;
+                        } else {
+                            return false;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    //sequences starting with the entry sequence, by length. levels[k] has the length of the entry
+    //sequence plus k. Out of time, the last level is partial and the longer ones are missing
+    fn bfs_from(
+        &self,
+        entry_sequence: &ApiSequence,
+        max_len: usize,
+        stop_at_end_function: bool,
+    ) -> Vec<Vec<ApiSequence>> {
+        let api_type = ApiType::BareFunction;
+        let mut levels = vec![vec![entry_sequence.clone()]];
+        for len in 1..(max_len + 1).saturating_sub(entry_sequence.len()) {
+            if self.is_out_of_time() {
+                break;
+            }
+            let mut next_level = Vec::new();
+            for sequence in &levels[len - 1] {
+                if self.is_out_of_time() {
+                    break;
+                }
+                if stop_at_end_function && self.is_sequence_ended(sequence) {
+                    continue;
+                }
+                for api_func_index in self.successors(sequence) {
+                    if let Some(new_sequence) =
+                        self.is_fun_satisfied(&api_type, api_func_index, sequence)
+                    {
+                        next_level.push(new_sequence);
+                    }
+                }
+            }
+            levels.push(next_level);
+        }
+        levels
+    }
+}
+
+//all sequences up to max_len, starting with the empty one, in the order of the sequential bfs.
+//The mined sequences are extended after the entry apis. None jobs means one thread per cpu
+pub(crate) fn parallel_bfs(
+    extender: &SequenceExtender<'_>,
+    max_len: usize,
+    stop_at_end_function: bool,
+    jobs: Option<usize>,
+) -> Vec<ApiSequence> {
+    let empty_sequence = ApiSequence::new();
+    let mut res = vec![empty_sequence.clone()];
+    if max_len < 1 {
+        return res;
+    }
+    let api_type = ApiType::BareFunction;
+    let entry_sequences: Vec<ApiSequence> = (0..extender.api_functions.len())
+        .filter_map(|index| extender.is_fun_satisfied(&api_type, index, &empty_sequence))
+        .chain(extender.mined_sequences.iter().cloned())
+        .collect();
+    let bfs_from = |entry_sequence: &ApiSequence| {
+        extender.bfs_from(entry_sequence, max_len, stop_at_end_function)
+    };
+    //collect keeps the order of the entry apis
+    let thread_pool = rayon::ThreadPoolBuilder::new().num_threads(jobs.unwrap_or(0)).build();
+    let mut levels_of_entries: Vec<Vec<Vec<ApiSequence>>> = match thread_pool {
+        Ok(thread_pool) => {
+            thread_pool.install(|| entry_sequences.par_iter().map(bfs_from).collect())
+        }
+        Err(e) => {
+            println!("warning: the sequences are enumerated on one thread: {}", e);
+            entry_sequences.iter().map(bfs_from).collect()
+        }
+    };
+    for len in 0..max_len {
+        for levels in levels_of_entries.iter_mut() {
+            if let Some(level) = levels.get_mut(len) {
+                res.append(level);
+            }
+        }
+    }
+    res
+}
+
+pub(crate) static DEFAULT_STRATEGY: &'static str = "default";
+
+pub(crate) trait SequenceStrategy {
+    //the name selecting it with `--strategy`
+    fn name(&self) -> &'static str;
+
+    //the targets of a random strategy are chosen at random too
+    fn is_random(&self) -> bool {
+        false
+    }
+
+    //the strategy enumerates every sequence up to the max length, which takes hours on large
+    //crates unless `--beam-width` bounds it
+    fn is_exhaustive(&self) -> bool {
+        false
+    }
+
+    fn generate(&self, graph: &ApiGraph<'_>) -> Vec<ApiSequence>;
+}
+
+//bfs stopping at end functions, then the backward search for the apis bfs didn't reach
+struct DefaultStrategy;
+
+impl SequenceStrategy for DefaultStrategy {
+    fn name(&self) -> &'static str {
+        DEFAULT_STRATEGY
+    }
+
+    fn generate(&self, graph: &ApiGraph<'_>) -> Vec<ApiSequence> {
+        let mut graph = graph.clone();
+        graph.default_generate_sequences();
+        graph.api_sequences
+    }
+}
+
+//the algorithms of the graph keep their state in it, so they run on a copy
+impl SequenceStrategy for GraphTraverseAlgorithm {
+    fn name(&self) -> &'static str {
+        match self {
+            GraphTraverseAlgorithm::_Bfs => "bfs",
+            GraphTraverseAlgorithm::_FastBfs => "fast-bfs",
+            GraphTraverseAlgorithm::_BfsEndPoint => "bfs-end-point",
+            GraphTraverseAlgorithm::_FastBfsEndPoint => "fast-bfs-end-point",
+            GraphTraverseAlgorithm::_RandomWalk => "random-walk",
+            GraphTraverseAlgorithm::_RandomWalkEndPoint => "random-walk-end-point",
+            GraphTraverseAlgorithm::_TryDeepBfs => "try-deep-bfs",
+            GraphTraverseAlgorithm::_DirectBackwardSearch => "backward",
+            GraphTraverseAlgorithm::_GeneticAlgorithm => "ga",
+        }
+    }
+
+    fn is_random(&self) -> bool {
+        match self {
+            GraphTraverseAlgorithm::_RandomWalk | GraphTraverseAlgorithm::_RandomWalkEndPoint => {
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn is_exhaustive(&self) -> bool {
+        match self {
+            GraphTraverseAlgorithm::_Bfs | GraphTraverseAlgorithm::_BfsEndPoint => true,
+            _ => false,
+        }
+    }
+
+    fn generate(&self, graph: &ApiGraph<'_>) -> Vec<ApiSequence> {
+        let mut graph = graph.clone();
+        graph.generate_all_possoble_sequences(*self);
+        graph.api_sequences
+    }
+}
+
+pub(crate) struct StrategyRegistry {
+    strategies: Vec<Box<dyn SequenceStrategy>>,
+}
+
+impl StrategyRegistry {
+    //the default strategy, the algorithms of the graph, the backward search from sink apis and
+    //the sequences interleaving several objects
+    pub(crate) fn new() -> Self {
+        let mut registry = StrategyRegistry { strategies: Vec::new() };
+        registry.register(Box::new(DefaultStrategy));
+        for algorithm in GraphTraverseAlgorithm::ALL {
+            registry.register(Box::new(algorithm));
+        }
+        registry.register(Box::new(SinkStrategy));
+        registry.register(Box::new(MultiObjectStrategy));
+        registry
+    }
+
+    //a strategy replaces the registered one of the same name
+    pub(crate) fn register(&mut self, strategy: Box<dyn SequenceStrategy>) {
+        self.strategies.retain(|registered| registered.name() != strategy.name());
+        self.strategies.push(strategy);
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<&dyn SequenceStrategy> {
+        self.strategies.iter().find(|strategy| strategy.name() == name).map(|s| s.as_ref())
+    }
+
+    pub(crate) fn strategies(&self) -> impl Iterator<Item = &dyn SequenceStrategy> {
+        self.strategies.iter().map(|strategy| strategy.as_ref())
+    }
+
+    pub(crate) fn names(&self) -> Vec<&'static str> {
+        self.strategies.iter().map(|strategy| strategy.name()).collect()
+    }
+}
@@ -0,0 +1,228 @@
+//The code a sequence is emitted as. Every backend is a `HarnessEmitter` registered by name in an
+//`EmitterRegistry`, and `--emit-harness` selects the ones written in a run, so a new fuzzer is
+//supported by adding an emitter without touching the sequences or the file writing. Without the
+//option the AFL targets and their replay files are written, and the libFuzzer targets for the
+//crates with a libFuzzer directory, as before.
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::fuzz_target::code_ast::{Block, Expr, FnItem, Item, Stmt};
+use crate::fuzz_target::file_util::{self, FileHelper};
+use crate::fuzz_target::fuzz_target_options::Naming;
+use crate::fuzz_target::libafl_harness::LibAflEmitter;
+use crate::fuzz_target::no_std;
+use crate::fuzz_target::proptest_harness::ProptestEmitter;
+use std::path::PathBuf;
+
+pub(crate) static AFL_EXTERN_CRATE: &'static str = "#[macro_use]\nextern crate afl;\n";
+//the directory cargo finds the tests of a package in
+pub(crate) static TESTS_DIR: &'static str = "tests";
+
+pub(crate) trait HarnessEmitter {
+    //the name selecting it with `--emit-harness`
+    fn name(&self) -> &'static str;
+
+    //the directory of the files under the test directory of the crate
+    fn dir_name(&self) -> &'static str;
+
+    //`test` of `test_url0.rs`. The AFL and libFuzzer targets are named `fuzz_...` instead unless
+    //`--naming legacy`, see `file_util::test_file_name`
+    fn file_prefix(&self) -> &'static str;
+
+    fn output_dir(&self, helper: &FileHelper) -> PathBuf {
+        PathBuf::from(&helper.test_dir).join(self.dir_name())
+    }
+
+    //the line of the crate the files use in `[dependencies]`, e.g. `afl = "*"`
+    fn dependency(&self) -> Option<&'static str> {
+        None
+    }
+
+    //whether the files are bins of the Cargo.toml of the targets
+    fn is_bin(&self) -> bool {
+        true
+    }
+
+    //the line of the crate the files use in `[dev-dependencies]`, for files that are the tests of
+    //the Cargo.toml of the targets in its `tests/`
+    fn dev_dependency(&self) -> Option<&'static str> {
+        None
+    }
+
+    //the file of the `index`-th chosen sequence
+    fn emit(&self, sequence: &ApiSequence, api_graph: &ApiGraph<'_>, index: usize) -> String;
+}
+
+pub(crate) struct AflEmitter;
+
+impl HarnessEmitter for AflEmitter {
+    fn name(&self) -> &'static str {
+        "afl"
+    }
+
+    fn dir_name(&self) -> &'static str {
+        file_util::_TEST_FILE_DIR
+    }
+
+    fn file_prefix(&self) -> &'static str {
+        "test"
+    }
+
+    fn dependency(&self) -> Option<&'static str> {
+        Some("afl = \"*\"")
+    }
+
+    fn emit(&self, sequence: &ApiSequence, api_graph: &ApiGraph<'_>, index: usize) -> String {
+        sequence._to_afl_test_file(api_graph, index)
+    }
+}
+
+//a `fn main` running the target once on the file given as its argument
+pub(crate) struct ReplayEmitter;
+
+impl HarnessEmitter for ReplayEmitter {
+    fn name(&self) -> &'static str {
+        "replay"
+    }
+
+    fn dir_name(&self) -> &'static str {
+        file_util::_REPRODUCE_FILE_DIR
+    }
+
+    fn file_prefix(&self) -> &'static str {
+        "replay"
+    }
+
+    fn emit(&self, sequence: &ApiSequence, api_graph: &ApiGraph<'_>, index: usize) -> String {
+        sequence._to_replay_crash_file(api_graph, index)
+    }
+}
+
+pub(crate) struct LibFuzzerEmitter;
+
+impl HarnessEmitter for LibFuzzerEmitter {
+    fn name(&self) -> &'static str {
+        "libfuzzer"
+    }
+
+    fn dir_name(&self) -> &'static str {
+        file_util::_LIBFUZZER_DIR_NAME
+    }
+
+    fn file_prefix(&self) -> &'static str {
+        "fuzz_target"
+    }
+
+    fn dependency(&self) -> Option<&'static str> {
+        Some("libfuzzer-sys = \"0.4\"")
+    }
+
+    //the crates with a libFuzzer directory keep their targets there
+    fn output_dir(&self, helper: &FileHelper) -> PathBuf {
+        file_util::libfuzzer_files_dir(&helper.crate_name)
+            .unwrap_or_else(|| PathBuf::from(&helper.test_dir).join(self.dir_name()))
+    }
+
+    fn emit(&self, sequence: &ApiSequence, api_graph: &ApiGraph<'_>, index: usize) -> String {
+        sequence._to_libfuzzer_test_file(api_graph, index)
+    }
+}
+
+//`cargo hfuzz run`, the closure of the AFL target in honggfuzz's loop
+pub(crate) struct HonggfuzzEmitter;
+
+impl HarnessEmitter for HonggfuzzEmitter {
+    fn name(&self) -> &'static str {
+        "honggfuzz"
+    }
+
+    fn dir_name(&self) -> &'static str {
+        "honggfuzz_files"
+    }
+
+    fn file_prefix(&self) -> &'static str {
+        "hfuzz_target"
+    }
+
+    fn dependency(&self) -> Option<&'static str> {
+        Some("honggfuzz = \"0.5\"")
+    }
+
+    fn emit(&self, sequence: &ApiSequence, api_graph: &ApiGraph<'_>, index: usize) -> String {
+        let mut res = sequence._to_afl_except_main(api_graph, index).replacen(
+            AFL_EXTERN_CRATE,
+            "#[macro_use]\nextern crate honggfuzz;\n",
+            1,
+        );
+        let fuzz = Expr::MacroCall {
+            name: "fuzz".to_string(),
+            args: vec![sequence._fuzz_closure(api_graph, index)],
+        };
+        let mut body = Block::new();
+        body.push(Stmt::Loop(Block { stmts: vec![Stmt::Expr(fuzz)] }));
+        let main = FnItem { attrs: Vec::new(), name: "main".to_string(), params: Vec::new(), body };
+        res.push_str(Item::Fn(main).to_code(0).as_str());
+        if api_graph.cx.options.no_std {
+            res = no_std::to_no_std_target(res);
+        }
+        res
+    }
+}
+
+//the name of the bin of the target, the name of its file. AFL and libFuzzer targets have the same
+//file name, so the bin of another emitter gets the name of the emitter as a suffix when its file
+//is named like the AFL target
+pub(crate) fn bin_name(emitter: &dyn HarnessEmitter, naming: Naming, target_name: &str) -> String {
+    let file_name = file_util::test_file_name(naming, emitter.file_prefix(), target_name);
+    let name = file_name.trim_end_matches(".rs");
+    let afl_file_name = file_util::test_file_name(naming, AflEmitter.file_prefix(), target_name);
+    if emitter.name() != AflEmitter.name() && file_name == afl_file_name {
+        format!("{}_{}", name, emitter.name())
+    } else {
+        name.to_string()
+    }
+}
+
+pub(crate) struct EmitterRegistry {
+    emitters: Vec<Box<dyn HarnessEmitter>>,
+}
+
+impl EmitterRegistry {
+    pub(crate) fn new() -> Self {
+        let mut registry = EmitterRegistry { emitters: Vec::new() };
+        registry.register(Box::new(AflEmitter));
+        registry.register(Box::new(ReplayEmitter));
+        registry.register(Box::new(LibFuzzerEmitter));
+        registry.register(Box::new(HonggfuzzEmitter));
+        registry.register(Box::new(ProptestEmitter));
+        registry.register(Box::new(LibAflEmitter));
+        registry
+    }
+
+    //an emitter replaces the registered one of the same name
+    pub(crate) fn register(&mut self, emitter: Box<dyn HarnessEmitter>) {
+        self.emitters.retain(|registered| registered.name() != emitter.name());
+        self.emitters.push(emitter);
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<&dyn HarnessEmitter> {
+        self.emitters.iter().find(|emitter| emitter.name() == name).map(|e| e.as_ref())
+    }
+
+    pub(crate) fn names(&self) -> Vec<&'static str> {
+        self.emitters.iter().map(|emitter| emitter.name()).collect()
+    }
+
+    //the emitters of `--emit-harness`, by default afl and replay, and libfuzzer if the crate has
+    //a libFuzzer directory
+    pub(crate) fn selected(&self, api_graph: &ApiGraph<'_>) -> Vec<&dyn HarnessEmitter> {
+        let names = &api_graph.cx.options.harness_emitters;
+        if !names.is_empty() {
+            return names.iter().filter_map(|name| self.get(name)).collect();
+        }
+        let mut res = vec![self.get("afl").unwrap(), self.get("replay").unwrap()];
+        if file_util::can_generate_libfuzzer_target(&api_graph._crate_name) {
+            res.push(self.get("libfuzzer").unwrap());
+        }
+        res
+    }
+}
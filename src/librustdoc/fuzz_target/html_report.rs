@@ -0,0 +1,299 @@
+//A self-contained HTML page(rulf-report.html) of the generation result, with `--html-report`, so
+//that the maintainers of a library can audit the targets without reading the generated code. The
+//page lists the apis with their coverage and why the uncovered ones aren't reached, draws the api
+//graph, and shows the calls of every target with where their arguments come from. The data is the
+//json report plus the graph and the targets, embedded with the script rendering it, so the page
+//works offline and can be attached to an issue.
+use crate::docfs::PathError;
+use crate::error::Error;
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::{ApiSequence, ParamType};
+use crate::fuzz_target::file_util::{self, FileHelper};
+use crate::try_err;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+static HTML_REPORT_FILE_NAME: &'static str = "rulf-report.html";
+//replaced by the data in PAGE
+static DATA_PLACEHOLDER: &'static str = "/*RULF_DATA*/null";
+
+#[derive(Debug, Clone, Serialize)]
+struct GraphNode {
+    name: String,
+    covered: bool,
+}
+
+//the return value of `producer` can be param `param` of `consumer`
+#[derive(Debug, Clone, Serialize)]
+struct GraphEdge {
+    producer: usize,
+    consumer: usize,
+    param: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct TargetCall {
+    //index of the node
+    api: usize,
+    //`input 0` for a fuzzable param, `call 1` for the return value of an earlier call
+    args: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Target {
+    file: String,
+    calls: Vec<TargetCall>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct PageData<'a> {
+    report: &'a serde_json::Value,
+    nodes: Vec<GraphNode>,
+    edges: Vec<GraphEdge>,
+    targets: Vec<Target>,
+}
+
+pub(crate) fn write_html_report(
+    graph: &ApiGraph<'_>,
+    file_helper: Option<&FileHelper>,
+    report: &serde_json::Value,
+    output_dir: &Path,
+) -> Result<(), Error> {
+    let nodes = graph
+        .api_functions
+        .iter()
+        .enumerate()
+        .map(|(i, api_function)| GraphNode {
+            name: api_function.full_name.clone(),
+            covered: graph.api_functions_visited.get(i).cloned().unwrap_or(false),
+        })
+        .collect();
+    let edges = graph
+        .api_dependencies
+        .iter()
+        .map(|dependency| GraphEdge {
+            producer: dependency.output_fun.1,
+            consumer: dependency.input_fun.1,
+            param: dependency.input_param_index,
+        })
+        .collect();
+    let mut targets = Vec::new();
+    if let Some(file_helper) = file_helper {
+        let naming = graph.cx.options.naming;
+        for (i, sequence) in file_helper.chosen_sequences.iter().enumerate() {
+            let file = file_util::test_file_name(naming, "test", &file_helper.target_names[i]);
+            targets.push(Target { file, calls: target_calls(sequence) });
+        }
+    }
+    let data = PageData { report, nodes, edges, targets };
+    //the json is inside a script element, it must not close the element
+    let data = serde_json::to_string(&data).unwrap().replace("</", "<\\/");
+    let page = PAGE.replace(DATA_PLACEHOLDER, data.as_str());
+    let report_path = output_dir.join(HTML_REPORT_FILE_NAME);
+    try_err!(fs::write(&report_path, page), &report_path);
+    println!("html report is written to {}", report_path.display());
+    Ok(())
+}
+
+fn target_calls(sequence: &ApiSequence) -> Vec<TargetCall> {
+    sequence
+        .functions
+        .iter()
+        .map(|api_call| TargetCall {
+            api: api_call.func.1,
+            args: api_call
+                .params
+                .iter()
+                .map(|(param_type, index, _)| match param_type {
+                    ParamType::_FuzzableType => format!("input {}", index),
+                    ParamType::_FunctionReturn => format!("call {}", index),
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+static PAGE: &'static str = r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>RULF report</title>
+<style>
+body { font-family: sans-serif; margin: 0 2em 2em; color: #222; }
+h2 { border-bottom: 1px solid #ccc; }
+code, td.name { font-family: monospace; }
+table { border-collapse: collapse; width: 100%; }
+td, th { border-bottom: 1px solid #eee; padding: 2px 6px; text-align: left; vertical-align: top; }
+.covered { color: #1a7f37; }
+.uncovered { color: #cf222e; }
+#graph { width: 100%; height: 600px; border: 1px solid #ccc; }
+#graph line { stroke: #bbb; }
+#graph line.active { stroke: #0969da; stroke-width: 2; }
+#graph circle { cursor: pointer; }
+#graph-label { font-family: monospace; min-height: 1.2em; }
+.controls { margin: 0.5em 0; }
+ol.calls li { font-family: monospace; margin: 2px 0; }
+</style>
+</head>
+<body>
+<h1>RULF report: <span id="crate"></span></h1>
+<div id="summary"></div>
+
+<h2>Apis</h2>
+<div class="controls">
+<input id="search" placeholder="filter by name" size="40">
+<select id="coverage">
+<option value="all">all</option>
+<option value="covered">covered</option>
+<option value="uncovered">not covered</option>
+</select>
+</div>
+<table>
+<thead><tr><th>api</th><th>covered</th><th>reason</th><th>suggestion</th><th>targets</th></tr></thead>
+<tbody id="apis"></tbody>
+</table>
+
+<h2>Api graph</h2>
+<p>An edge goes from an api to the api taking its return value. Click an api to show its edges.</p>
+<div id="graph-label"></div>
+<svg id="graph"></svg>
+
+<h2>Targets</h2>
+<div class="controls"><select id="target"></select></div>
+<ol class="calls" id="calls" start="0"></ol>
+
+<script>
+const DATA = /*RULF_DATA*/null;
+const report = DATA.report;
+
+function element(tag, text, className) {
+  const e = document.createElement(tag);
+  if (text !== undefined) e.textContent = text;
+  if (className) e.className = className;
+  return e;
+}
+
+document.getElementById("crate").textContent = report.crate_name;
+const statistics = report.statistics;
+const percent = statistics.total_apis ? (100 * statistics.covered_apis / statistics.total_apis) : 0;
+document.getElementById("summary").textContent =
+  `${statistics.covered_apis} of ${statistics.total_apis} apis covered (${percent.toFixed(1)}%), ` +
+  `${statistics.generated_sequences} sequences, ${statistics.target_files} targets, ` +
+  `strategy ${report.strategy}, average sequence length ` +
+  `${statistics.average_sequence_len.toFixed(2)}`;
+
+function renderApis() {
+  const query = document.getElementById("search").value;
+  const coverage = document.getElementById("coverage").value;
+  const body = document.getElementById("apis");
+  body.textContent = "";
+  for (const api of report.apis) {
+    if (query && !api.name.includes(query)) continue;
+    if (coverage === "covered" && !api.covered) continue;
+    if (coverage === "uncovered" && api.covered) continue;
+    const row = element("tr");
+    row.appendChild(element("td", api.name, "name"));
+    row.appendChild(element("td", api.covered ? "yes" : "no", api.covered ? "covered" : "uncovered"));
+    row.appendChild(element("td", api.refusal_reason || ""));
+    row.appendChild(element("td", api.suggestion || ""));
+    row.appendChild(element("td", api.target_files.join(", ")));
+    body.appendChild(row);
+  }
+}
+document.getElementById("search").addEventListener("input", renderApis);
+document.getElementById("coverage").addEventListener("change", renderApis);
+renderApis();
+
+//a force layout run for a fixed number of steps, then drawn once
+function renderGraph() {
+  const svg = document.getElementById("graph");
+  const width = svg.clientWidth || 1000, height = svg.clientHeight || 600;
+  const nodes = DATA.nodes.map((node, i) => ({
+    x: width / 2 + Math.cos(i) * width / 3 * Math.random(),
+    y: height / 2 + Math.sin(i) * height / 3 * Math.random(),
+  }));
+  const pairs = new Map();
+  for (const edge of DATA.edges) {
+    if (edge.producer !== edge.consumer) pairs.set(edge.producer + "," + edge.consumer, edge);
+  }
+  const edges = [...pairs.values()];
+  const steps = nodes.length > 500 ? 50 : 200;
+  for (let step = 0; step < steps; step++) {
+    const forces = nodes.map(() => ({ x: 0, y: 0 }));
+    for (let i = 0; i < nodes.length; i++) {
+      for (let j = i + 1; j < nodes.length; j++) {
+        const dx = nodes[i].x - nodes[j].x, dy = nodes[i].y - nodes[j].y;
+        const distance2 = Math.max(dx * dx + dy * dy, 1);
+        const repulsion = 400 / distance2;
+        forces[i].x += dx * repulsion; forces[i].y += dy * repulsion;
+        forces[j].x -= dx * repulsion; forces[j].y -= dy * repulsion;
+      }
+    }
+    for (const edge of edges) {
+      const a = nodes[edge.producer], b = nodes[edge.consumer];
+      const dx = b.x - a.x, dy = b.y - a.y;
+      forces[edge.producer].x += dx * 0.01; forces[edge.producer].y += dy * 0.01;
+      forces[edge.consumer].x -= dx * 0.01; forces[edge.consumer].y -= dy * 0.01;
+    }
+    nodes.forEach((node, i) => {
+      node.x += (width / 2 - node.x) * 0.005 + Math.max(-10, Math.min(10, forces[i].x));
+      node.y += (height / 2 - node.y) * 0.005 + Math.max(-10, Math.min(10, forces[i].y));
+      node.x = Math.max(5, Math.min(width - 5, node.x));
+      node.y = Math.max(5, Math.min(height - 5, node.y));
+    });
+  }
+  const ns = "http://www.w3.org/2000/svg";
+  const lines = edges.map((edge) => {
+    const line = document.createElementNS(ns, "line");
+    line.setAttribute("x1", nodes[edge.producer].x); line.setAttribute("y1", nodes[edge.producer].y);
+    line.setAttribute("x2", nodes[edge.consumer].x); line.setAttribute("y2", nodes[edge.consumer].y);
+    svg.appendChild(line);
+    return line;
+  });
+  const label = document.getElementById("graph-label");
+  DATA.nodes.forEach((node, i) => {
+    const circle = document.createElementNS(ns, "circle");
+    circle.setAttribute("cx", nodes[i].x); circle.setAttribute("cy", nodes[i].y);
+    circle.setAttribute("r", 5);
+    circle.setAttribute("fill", node.covered ? "#1a7f37" : "#cf222e");
+    const title = document.createElementNS(ns, "title");
+    title.textContent = node.name;
+    circle.appendChild(title);
+    circle.addEventListener("click", () => {
+      const producers = [], consumers = [];
+      edges.forEach((edge, k) => {
+        const active = edge.producer === i || edge.consumer === i;
+        lines[k].classList.toggle("active", active);
+        if (edge.consumer === i) producers.push(DATA.nodes[edge.producer].name);
+        if (edge.producer === i) consumers.push(DATA.nodes[edge.consumer].name);
+      });
+      label.textContent = `${node.name} (${node.covered ? "covered" : "not covered"}): ` +
+        `produced by [${producers.join(", ")}], used by [${consumers.join(", ")}]`;
+    });
+    svg.appendChild(circle);
+  });
+}
+renderGraph();
+
+function renderTarget() {
+  const calls = document.getElementById("calls");
+  calls.textContent = "";
+  const target = DATA.targets[document.getElementById("target").value];
+  if (!target) return;
+  for (const call of target.calls) {
+    calls.appendChild(element("li", `${DATA.nodes[call.api].name}(${call.args.join(", ")})`));
+  }
+}
+const targetSelect = document.getElementById("target");
+DATA.targets.forEach((target, i) => {
+  const option = element("option", `${target.file} (${target.calls.length} calls)`);
+  option.value = i;
+  targetSelect.appendChild(option);
+});
+targetSelect.addEventListener("change", renderTarget);
+renderTarget();
+</script>
+</body>
+</html>
+"##;
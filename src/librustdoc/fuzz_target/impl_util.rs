@@ -1,12 +1,15 @@
 use crate::clean::{self, ItemKind};
-use crate::formats::item_type::ItemType;
+use crate::formats::{self, item_type::ItemType};
 use crate::fuzz_target::api_function::ApiFunction;
-use crate::fuzz_target::api_function::ApiUnsafety;
+use crate::fuzz_target::api_function::{ApiAsyncness, ApiUnsafety};
 use crate::fuzz_target::api_graph::ApiGraph;
 use crate::fuzz_target::api_util;
+use crate::fuzz_target::dependency;
+use crate::fuzz_target::local_type::LocalTypes;
 use crate::fuzz_target::prelude_type;
 use crate::html::format::join_with_double_colon;
 use rustc_hir::def_id::DefId;
+use rustc_span::symbol::{sym, Symbol};
 use rustc_data_structures::fx::{FxHashMap};
 //use rustdoc_json_types::Type::Path;
 //TODO:是否需要为impl里面的method重新设计数据结构？目前沿用了ApiFunction,或者直接对ApiFunction进行扩展
@@ -56,12 +59,16 @@ pub(crate) fn add_impl(&mut self, impl_: &clean::Impl) {
 #[derive(Debug, Clone)]
 pub(crate) struct FullNameMap {
     pub(crate) map: FxHashMap<DefId, (String, ItemType)>,
+    //可以直接从fuzz数据构造的enum和struct
+    pub(crate) local_types: LocalTypes,
+    //struct的字段中嵌套构造struct的最大层数
+    pub(crate) max_struct_depth: usize,
 }
 
 impl FullNameMap {
     pub(crate) fn new() -> Self {
         let map = FxHashMap::default();
-        FullNameMap { map }
+        FullNameMap { map, local_types: LocalTypes::default(), max_struct_depth: 0 }
     }
 
     pub(crate) fn push_mapping(&mut self, def_id: DefId, full_name: &String, item_type: ItemType) {
@@ -94,7 +101,9 @@ pub(crate) fn extract_impls_from_cache(
     let extertal_paths = &api_graph.cache().external_paths;
     for (did, (syms, item_type)) in extertal_paths {
         let full_name = join_with_double_colon(syms);
-        if prelude_type::is_preluded_type(&full_name) {
+        if prelude_type::is_preluded_type(&full_name)
+            || dependency::is_included_path(&full_name, &api_graph.cx.options.dependency_crates)
+        {
             full_name_map.push_mapping(*did, &full_name, *item_type);
         }
     }
@@ -107,7 +116,9 @@ pub(crate) fn extract_impls_from_cache(
         if full_name_map._get_full_name(*did) != None {
             for impl_ in impls {
                 //println!("full_name = {:?}", full_name_map._get_full_name(did).unwrap());
-                crate_impl_collection.add_impl(impl_.inner_impl());
+                if is_callable_impl(impl_, api_graph) {
+                    crate_impl_collection.add_impl(impl_.inner_impl());
+                }
             }
         }
     }
@@ -158,6 +169,9 @@ pub(crate) fn _analyse_impl(
             let trait_full_name = full_name_map._get_full_name(trait_ty_def_id);
             if let Some(trait_name) = trait_full_name {
                 Some(trait_name.clone())
+            } else if !trait_ty_def_id.is_local() {
+                //std的trait(FromStr, Iterator等)用可见的路径调用
+                Some(api_graph.tcx().def_path_str(trait_ty_def_id))
             } else {
                 None
             }
@@ -165,6 +179,12 @@ pub(crate) fn _analyse_impl(
     };
 
     let impl_ty_def_id = impl_.for_.def_id(api_graph.cache());
+    //methods of a deprecated type are deprecated as well
+    let is_deprecated_type =
+        impl_ty_def_id.map_or(false, |def_id| api_graph.tcx().lookup_deprecation(def_id).is_some());
+    if is_deprecated_type && !api_graph.cx.options.include_deprecated {
+        return;
+    }
     let type_full_name = if let Some(def_id) = impl_ty_def_id {
         let type_name = full_name_map._get_full_name(def_id);
         if let Some(real_type_name) = type_name {
@@ -176,7 +196,22 @@ pub(crate) fn _analyse_impl(
         None
     };
 
-    for item in inner_items {
+    //impl中关联类型的定义，用来替换`Self::Item`这样的类型
+    let assoc_types: FxHashMap<Symbol, clean::Type> = inner_items
+        .iter()
+        .filter_map(|item| match &*item.kind {
+            ItemKind::AssocTypeItem(typedef, _) => Some((item.name?, typedef.type_.clone())),
+            _ => None,
+        })
+        .collect();
+
+    //trait中有默认实现的方法不在impl块里，为每个实现trait的类型加入
+    let provided_methods = match &impl_.trait_ {
+        Some(trait_) => provided_methods(impl_, trait_.def_id(), api_graph),
+        None => Vec::new(),
+    };
+
+    for item in inner_items.iter().chain(provided_methods.iter()) {
         //println!("item_name, {:?}", item.name.as_ref().unwrap());
         match &*item.kind {
             //TODO:这段代码暂时没用了，impl块里面的是method item，而不是function item,暂时留着，看里面是否会出现function item
@@ -190,11 +225,18 @@ pub(crate) fn _analyse_impl(
                 //println!("function name in impl:{:?}", function_name);
             }
             ItemKind::MethodItem(_method, _) => {
+                if api_graph.is_excluded_item(item) {
+                    continue;
+                }
                 let decl = _method.decl.clone();
-                let clean::FnDecl { inputs, output, .. } = decl;
+                let clean::FnDecl { inputs: arguments, output, .. } = decl;
                 let generics = _method.generics.clone();
-                let mut inputs = api_util::_extract_input_types(&inputs);
-                let output = api_util::_extract_output_type(&output);
+                let mut inputs: Vec<clean::Type> = api_util::_extract_input_types(&arguments)
+                    .iter()
+                    .map(|input| replace_assoc_types(input, &assoc_types))
+                    .collect();
+                let output = api_util::_extract_output_type(&output)
+                    .map(|output| replace_assoc_types(&output, &assoc_types));
                 //println!("input types = {:?}", inputs);
 
                 let mut contains_self_type = false;
@@ -249,9 +291,9 @@ pub(crate) fn _analyse_impl(
                 method_name.push_str(item.name.as_ref().unwrap().as_str());
                 //println!("method name in impl:{:?}", method_name);
 
-                let api_unsafety = ApiUnsafety::_get_unsafety_from_fnheader(
-                    &item.fn_header(api_graph.tcx().clone()).unwrap(),
-                );
+                let fn_header = item.fn_header(api_graph.tcx().clone()).unwrap();
+                let api_unsafety = ApiUnsafety::_get_unsafety_from_fnheader(&fn_header);
+                let api_asyncness = ApiAsyncness::_get_asyncness_from_fnheader(&fn_header);
                 //生成api function
                 //如果是实现了trait的话，需要把trait的全路径也包括进去
                 let api_function = match &impl_.trait_ {
@@ -262,6 +304,7 @@ pub(crate) fn _analyse_impl(
                         output,
                         _trait_full_path: None,
                         _unsafe_tag: api_unsafety,
+                        _async_tag: api_asyncness,
                     },
                     Some(_) => {
                         if let Some(ref real_trait_name) = trait_full_name {
@@ -272,6 +315,7 @@ pub(crate) fn _analyse_impl(
                                 output,
                                 _trait_full_path: Some(real_trait_name.clone()),
                                 _unsafe_tag: api_unsafety,
+                                _async_tag: api_asyncness,
                             }
                         } else {
                             //println!("Trait not found in current crate.");
@@ -279,6 +323,17 @@ pub(crate) fn _analyse_impl(
                         }
                     }
                 };
+                api_graph.add_api_features(&api_function.full_name, item.cfg.as_deref());
+                api_graph.add_ffi_api(&api_function.full_name, item.item_id.as_def_id());
+                api_graph.add_const_api(&api_function.full_name, item.item_id.as_def_id());
+                api_graph.add_api_risks(&api_function.full_name, item.item_id.as_def_id());
+                api_graph.add_must_use_api(&api_function.full_name, item.item_id.as_def_id());
+                api_graph.add_api_def_id(&api_function.full_name, item.item_id.as_def_id());
+                let doc = item.collapsed_doc_value();
+                api_graph.add_api_param_bounds(&api_function.full_name, doc.clone(), &arguments);
+                api_graph.add_api_panics(&api_function.full_name, doc, &arguments);
+                let def_id = item.item_id.as_def_id();
+                api_graph.add_api_assertions(&api_function.full_name, def_id, &arguments);
                 api_graph.add_api_function(api_function);
             }
             _ => {
@@ -288,6 +343,157 @@ pub(crate) fn _analyse_impl(
     }
 }
 
+//写出的trait impl才是可以调用的方法：自动实现的trait不加入。derive生成的impl只加入Default和Clone
+//的，它们能构造值，PartialEq、Debug等derive出来的方法不产生新的值。Drop::drop不能直接调用。
+//blanket impl由rustdoc为每个满足约束的类型生成一份，for_就是这个类型，只加入当前crate的trait的，
+//std的blanket impl(From<T> for T、ToString等)对每个类型都有，只会带来大量重复的api
+fn is_callable_impl(impl_: &formats::Impl, api_graph: &ApiGraph<'_>) -> bool {
+    let tcx = api_graph.tcx();
+    let inner_impl = impl_.inner_impl();
+    if inner_impl.kind.is_auto() || inner_impl.kind.is_fake_variadic() {
+        return false;
+    }
+    if inner_impl.kind.is_blanket()
+        && !inner_impl.trait_.as_ref().map_or(false, |trait_| trait_.def_id().is_local())
+    {
+        return false;
+    }
+    if let Some(impl_def_id) = impl_.impl_item.item_id.as_def_id() {
+        let is_producer_trait = inner_impl.trait_.as_ref().map_or(false, |trait_| {
+            tcx.is_diagnostic_item(sym::Default, trait_.def_id())
+                || tcx.lang_items().clone_trait() == Some(trait_.def_id())
+        });
+        if tcx.has_attr(impl_def_id, sym::automatically_derived) && !is_producer_trait {
+            return false;
+        }
+    }
+    match &inner_impl.trait_ {
+        Some(trait_) => tcx.lang_items().drop_trait() != Some(trait_.def_id()),
+        None => true,
+    }
+}
+
+//trait中有默认实现、impl块没有覆盖的方法，Self就是实现trait的类型。当前crate的trait加入所有方法，
+//扩展trait的功能大多在默认方法里。外部trait的默认方法太多(例如Iterator)，只加入构造函数，即不以Self
+//为参数、返回Self的关联函数，例如`fn new() -> Self`。方法对Self的约束(`where Self: Sized`)需要
+//实现的类型满足，满足的约束去掉以免被当成泛型函数
+fn provided_methods(
+    impl_: &clean::Impl,
+    trait_def_id: DefId,
+    api_graph: &ApiGraph<'_>,
+) -> Vec<clean::Item> {
+    let trait_ = match api_graph.cache().traits.get(&trait_def_id) {
+        Some(trait_) => trait_,
+        None => return Vec::new(),
+    };
+    let overridden: Vec<Symbol> = impl_.items.iter().filter_map(|item| item.name).collect();
+    let mut res = Vec::new();
+    for item in &trait_.items {
+        let (method, defaultness) = match &*item.kind {
+            ItemKind::MethodItem(method, defaultness) => (method, defaultness),
+            _ => continue,
+        };
+        if item.name.map_or(true, |name| overridden.contains(&name)) {
+            continue;
+        }
+        let takes_self =
+            method.decl.inputs.values.iter().any(|input| is_param_self_type(&input.type_));
+        let returns_self = match &method.decl.output {
+            clean::FnRetTy::Return(ty_) => is_param_self_type(ty_),
+            clean::FnRetTy::DefaultReturn => false,
+        };
+        if !trait_def_id.is_local() && (takes_self || !returns_self) {
+            continue;
+        }
+        let mut method = method.clone();
+        let mut is_satisfied = true;
+        method.generics.where_predicates.retain(|predicate| match predicate {
+            clean::WherePredicate::BoundPredicate { ty, bounds, .. } if ty.is_self_type() => {
+                is_satisfied &=
+                    bounds.iter().all(|bound| is_bound_satisfied(bound, &impl_.for_, api_graph));
+                false
+            }
+            _ => true,
+        });
+        if !is_satisfied {
+            continue;
+        }
+        let mut provided_method = item.clone();
+        provided_method.kind = Box::new(ItemKind::MethodItem(method, *defaultness));
+        res.push(provided_method);
+    }
+    res
+}
+
+//实现trait的类型是否满足Self的约束。str、切片和trait object不是Sized，其他trait要在cache中有impl
+fn is_bound_satisfied(
+    bound: &clean::GenericBound,
+    impl_type: &clean::Type,
+    api_graph: &ApiGraph<'_>,
+) -> bool {
+    let trait_def_id = match bound {
+        clean::GenericBound::TraitBound(poly_trait, _) => poly_trait.trait_.def_id(),
+        clean::GenericBound::Outlives(_) => return true,
+    };
+    if api_graph.tcx().lang_items().sized_trait() == Some(trait_def_id) {
+        return !matches!(
+            impl_type,
+            clean::Type::Slice(..)
+                | clean::Type::Primitive(clean::PrimitiveType::Str)
+                | clean::Type::DynTrait(..)
+        );
+    }
+    match impl_type {
+        clean::Type::Path { path } => api_graph.implements_trait_def_id(path, trait_def_id),
+        _ => false,
+    }
+}
+
+//把`Self::Item`和`<Self as Trait>::Item`替换为impl中定义的关联类型
+fn replace_assoc_types(
+    ty_: &clean::Type,
+    assoc_types: &FxHashMap<Symbol, clean::Type>,
+) -> clean::Type {
+    match ty_ {
+        clean::Type::QPath(qpath) if qpath.self_type.is_self_type() => {
+            match assoc_types.get(&qpath.assoc.name) {
+                Some(assoc_type) => assoc_type.clone(),
+                None => ty_.clone(),
+            }
+        }
+        clean::Type::BorrowedRef { lifetime, mutability, type_ } => clean::Type::BorrowedRef {
+            lifetime: lifetime.clone(),
+            mutability: *mutability,
+            type_: Box::new(replace_assoc_types(type_, assoc_types)),
+        },
+        clean::Type::Tuple(types) => clean::Type::Tuple(
+            types.iter().map(|inner_ty| replace_assoc_types(inner_ty, assoc_types)).collect(),
+        ),
+        clean::Type::Slice(type_) => {
+            clean::Type::Slice(Box::new(replace_assoc_types(type_, assoc_types)))
+        }
+        clean::Type::Path { path } => {
+            let mut path = path.clone();
+            for segment in path.segments.iter_mut() {
+                if let clean::GenericArgs::AngleBracketed { args, .. } = &mut segment.args {
+                    let new_args: Vec<clean::GenericArg> = args
+                        .iter()
+                        .map(|arg| match arg {
+                            clean::GenericArg::Type(inner_ty) => {
+                                clean::GenericArg::Type(replace_assoc_types(inner_ty, assoc_types))
+                            }
+                            _ => arg.clone(),
+                        })
+                        .collect();
+                    *args = new_args.into();
+                }
+            }
+            clean::Type::Path { path }
+        }
+        _ => ty_.clone(),
+    }
+}
+
 //递归判断一个参数是否是self类型的
 //TODO：考虑在resolved path里面的括号里面可能存在self type
 fn is_param_self_type(ty_: &clean::Type) -> bool {
@@ -0,0 +1,241 @@
+//Partial regeneration, with `--incremental`. Generating the targets again rewrites every file and
+//empties the seed corpus, although after a small change of the crate most targets are the same as
+//before, and a fuzzer restarted on them would have to find its corpus again. With
+//`--incremental`, the targets are compared with the ones of the previous `rulf-report.json` in the
+//output directory: a target of the same name and digest is left as it is, with its corpus
+//directory, and only the new and changed targets are written. The digest covers the code of the
+//target, the signatures of the apis it calls and the options changing the code, so a target is
+//written again when the signature of one of its apis changes in a way its code doesn't show, e.g.
+//the type of a result it only drops. Targets of the previous report that are not generated anymore
+//are removed. With `--graph-cache` and the stable target names, a run on an unchanged crate
+//rewrites nothing.
+use crate::clean;
+use crate::fuzz_target::api_function::ApiFunction;
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::fuzz_target::api_util;
+use crate::fuzz_target::file_util;
+use crate::fuzz_target::fuzz_target_options::FuzzTargetOptions;
+use crate::fuzz_target::report;
+use rustc_data_structures::fx::FxHashMap;
+use serde::Deserialize;
+use std::fmt;
+use std::fs;
+
+#[derive(Debug, Deserialize)]
+struct PreviousTarget {
+    name: String,
+    digest: String,
+}
+
+//only the targets of the previous report are read
+#[derive(Debug, Deserialize)]
+struct PreviousReport {
+    #[serde(default)]
+    targets: Vec<PreviousTarget>,
+}
+
+//what became of the targets of the previous generation
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Regeneration {
+    //there is a previous report to compare with, otherwise everything is written from scratch
+    pub(crate) incremental: bool,
+    //the i-th target is the same as before, its files are kept if they exist
+    pub(crate) unchanged: Vec<bool>,
+    //names of the previous targets that are not generated anymore
+    pub(crate) removed: Vec<String>,
+}
+
+impl Regeneration {
+    pub(crate) fn is_unchanged(&self, index: usize) -> bool {
+        self.unchanged.get(index).cloned().unwrap_or(false)
+    }
+}
+
+pub(crate) fn target_digest(sequence: &ApiSequence, api_graph: &ApiGraph<'_>) -> String {
+    let mut content = sequence._to_well_written_function(api_graph, 0, 0);
+    for api_index in sequence._get_contained_api_functions() {
+        content.push_str(signature(&api_graph.api_functions[api_index], api_graph).as_str());
+    }
+    content.push_str(code_options(&api_graph.cx.options).as_str());
+    format!("{:016x}", file_util::stable_hash(content.as_bytes()))
+}
+
+//the options changing the code of a target. The other ones choose the targets, where they are
+//written or how the run goes, e.g. `--jobs` or `--html-report`, or hold the `DefId`s of the crate,
+//which change when any item is added. The options are destructured without `..`, so a new option
+//doesn't build until it is sorted into one of the two groups
+fn code_options(options: &FuzzTargetOptions) -> String {
+    let FuzzTargetOptions {
+        async_runtime,
+        error_policy,
+        msrv,
+        format_harness,
+        features,
+        max_struct_depth,
+        differential,
+        round_trip,
+        round_trip_equivalence,
+        panic_policy,
+        ignore_panics,
+        no_std,
+        workspace,
+        operator_harness,
+        drop_harness,
+        contract_harness,
+        sanitizers,
+        kani,
+        assert_guards,
+        char_mutator,
+        harness_emitters,
+        default_producer,
+        serde_producer,
+        afl_persistent_iterations,
+        afl_deferred_init,
+        runtime_crate,
+        assert_buffer_bounds,
+        check_invariants,
+        exercise_format,
+        exercise_clone,
+        //the options that don't change the code
+        strategy: _,
+        selection: _,
+        jobs: _,
+        max_seq_len: _,
+        max_targets: _,
+        mega_sequence: _,
+        tail_variants: _,
+        max_targets_per_api: _,
+        min_targets_per_api: _,
+        lib_target: _,
+        crash_to_test: _,
+        compile_check: _,
+        include_deps: _,
+        dependency_producers: _,
+        dependency_apis: _,
+        dependency_crates: _,
+        macro_apis: _,
+        graph_cache: _,
+        run_manifest: _,
+        ossfuzz: _,
+        usage_paths: _,
+        usage_dump: _,
+        include_hidden: _,
+        include_deprecated: _,
+        include_apis: _,
+        exclude_apis: _,
+        entry_apis: _,
+        generic_hints: _,
+        ffi: _,
+        validation: _,
+        explain: _,
+        html_report: _,
+        emit_metrics: _,
+        naming: _,
+        incremental: _,
+        const_probes: _,
+        rank_targets: _,
+        time_budget: _,
+        beam_width: _,
+        sink_apis: _,
+        dictionaries: _,
+        generate_targets: _,
+    } = options;
+    let code_options: &[&dyn fmt::Debug] = &[
+        async_runtime,
+        error_policy,
+        msrv,
+        format_harness,
+        features,
+        max_struct_depth,
+        differential,
+        round_trip,
+        round_trip_equivalence,
+        panic_policy,
+        ignore_panics,
+        no_std,
+        workspace,
+        operator_harness,
+        drop_harness,
+        contract_harness,
+        sanitizers,
+        kani,
+        assert_guards,
+        char_mutator,
+        harness_emitters,
+        default_producer,
+        serde_producer,
+        afl_persistent_iterations,
+        afl_deferred_init,
+        runtime_crate,
+        assert_buffer_bounds,
+        check_invariants,
+        exercise_format,
+        exercise_clone,
+    ];
+    code_options.iter().map(|option| format!("{:?}", option)).collect::<Vec<_>>().join(" ")
+}
+
+//the tags, the name and the param and output type names of an api
+fn signature(api_function: &ApiFunction, api_graph: &ApiGraph<'_>) -> String {
+    let type_name =
+        |ty: &clean::Type| api_util::_type_name(ty, &api_graph.full_name_map, api_graph.cache());
+    let inputs: Vec<String> = api_function.inputs.iter().map(type_name).collect();
+    let output = api_function.output.as_ref().map(type_name).unwrap_or_default();
+    format!(
+        "{:?} {:?} {}({}) -> {}\n",
+        api_function._unsafe_tag,
+        api_function._async_tag,
+        api_function.full_name,
+        inputs.join(", "),
+        output
+    )
+}
+
+//every target is changed without `--incremental` or a previous report
+pub(crate) fn compare(
+    api_graph: &ApiGraph<'_>,
+    sequences: &Vec<ApiSequence>,
+    target_names: &Vec<String>,
+) -> Regeneration {
+    let mut res = Regeneration {
+        incremental: false,
+        unchanged: vec![false; sequences.len()],
+        removed: Vec::new(),
+    };
+    if !api_graph.cx.options.incremental {
+        return res;
+    }
+    let report_path = api_graph.cx.output_dir.join(report::REPORT_FILE_NAME);
+    let previous = fs::read(&report_path)
+        .ok()
+        .and_then(|content| serde_json::from_slice::<PreviousReport>(&content).ok());
+    let previous = match previous {
+        Some(previous) => previous,
+        None => {
+            println!("no previous report in {}, every target is written", report_path.display());
+            return res;
+        }
+    };
+    res.incremental = true;
+    let previous_digests: FxHashMap<&String, &String> =
+        previous.targets.iter().map(|target| (&target.name, &target.digest)).collect();
+    for (i, sequence) in sequences.iter().enumerate() {
+        if let Some(previous_digest) = previous_digests.get(&target_names[i]) {
+            res.unchanged[i] = **previous_digest == target_digest(sequence, api_graph);
+        }
+    }
+    for target in previous.targets {
+        if !target_names.contains(&target.name) {
+            res.removed.push(target.name);
+        }
+    }
+    let unchanged_number = res.unchanged.iter().filter(|unchanged| **unchanged).count();
+    println!(
+        "{} targets are unchanged, {} are written and {} are removed",
+        unchanged_number,
+        sequences.len() - unchanged_number,
+        res.removed.len()
+    );
+    res
+}
@@ -0,0 +1,166 @@
+//Light property checks on the values the apis return, with `--check-invariants`. A plain target
+//only finds crashes, a wrong result goes unnoticed. The assertions after a call come from its
+//signature, no spec is needed:
+//- a `usize` or `Option<usize>` returned by an api named like a search of std, e.g. `find` or
+//  `position`, that takes one fuzzed slice or str, is at most the length of that input
+//- a returned `&str` or `String` is valid UTF-8, which catches `from_utf8_unchecked` on bytes
+//  that are not
+//- a returned value of a crate type with `len` and `is_empty` methods is empty iff its length is 0
+use crate::clean::{self, types::PrimitiveType};
+use crate::fuzz_target::api_function::ApiFunction;
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::{ApiCall, ParamType};
+use crate::fuzz_target::api_util;
+use crate::fuzz_target::call_type::CallType;
+use crate::fuzz_target::code_ast::{Expr, Stmt};
+use crate::fuzz_target::prelude_type::PreludeType;
+use crate::fuzz_target::sequence_ir::{SequenceIr, ValueSource};
+
+//names of apis returning an index into their input. The whole name is compared, apis like
+//`offset_of` or `hash_index` return other values
+static INDEX_NAMES: [&'static str; 6] =
+    ["find", "rfind", "position", "rposition", "search", "index_of"];
+
+//the assertions after the call, `value` is the name the result is bound to
+pub(crate) fn invariant_stmts(
+    api_graph: &ApiGraph<'_>,
+    api_call: &ApiCall,
+    ir: &SequenceIr,
+    value: &str,
+) -> Vec<Stmt> {
+    let api_function = &api_graph.api_functions[api_call.func.1];
+    let output = match &api_function.output {
+        Some(output) => output,
+        None => return Vec::new(),
+    };
+    let mut res = Vec::new();
+    res.extend(index_bound(api_graph, api_call, ir, output, value));
+    res.extend(utf8_check(api_function, output, value));
+    res.extend(len_consistency(api_graph, output, value));
+    res
+}
+
+fn index_bound(
+    api_graph: &ApiGraph<'_>,
+    api_call: &ApiCall,
+    ir: &SequenceIr,
+    output: &clean::Type,
+    value: &str,
+) -> Option<Stmt> {
+    let api_function = &api_graph.api_functions[api_call.func.1];
+    if !INDEX_NAMES.contains(&short_name(api_function)) {
+        return None;
+    }
+    let mut inputs =
+        api_call.params.iter().enumerate().filter(|(j, (param_type, _, call_type))| {
+            matches!(param_type, ParamType::_FuzzableType)
+                && *call_type == CallType::_DirectCall
+                && is_sequence_ref(&api_function.inputs[*j])
+        });
+    let input = match (inputs.next(), inputs.next()) {
+        (Some((_, (_, index, _))), None) => ir.value(&ValueSource::FuzzableParam(*index))?,
+        _ => return None,
+    };
+    let length = Expr::method_call(Expr::path(input.name.as_str()), "len", Vec::new());
+    let usize_type = clean::Type::Primitive(PrimitiveType::Usize);
+    let cond = if *output == usize_type {
+        Expr::binary(Expr::path(value), "<=", length)
+    } else {
+        match PreludeType::from_type(output, &api_graph.full_name_map, api_graph.cache()) {
+            PreludeType::PreludeOption(inner) if inner == usize_type => {
+                let in_bounds = Expr::Raw(format!("|_index| _index <= {}", length.to_code(0)));
+                Expr::method_call(
+                    Expr::path(value),
+                    "map_or",
+                    vec![Expr::Lit("true".to_string()), in_bounds],
+                )
+            }
+            _ => return None,
+        }
+    };
+    let message = format!("{} returns an index past the end of its input", api_function.full_name);
+    Some(assert(cond, &message))
+}
+
+fn utf8_check(api_function: &ApiFunction, output: &clean::Type, value: &str) -> Option<Stmt> {
+    let is_text = match output {
+        clean::Type::BorrowedRef { type_, .. } => {
+            **type_ == clean::Type::Primitive(PrimitiveType::Str)
+        }
+        clean::Type::Path { path } => path.last().as_str() == "String",
+        _ => false,
+    };
+    if !is_text {
+        return None;
+    }
+    //core works in no_std targets too
+    let bytes = Expr::method_call(Expr::path(value), "as_bytes", Vec::new());
+    let decoded = Expr::call(Expr::path("core::str::from_utf8"), vec![bytes]);
+    let message = format!("{} returns a str that is not valid UTF-8", api_function.full_name);
+    Some(assert(Expr::method_call(decoded, "is_ok", Vec::new()), &message))
+}
+
+//a value borrowing its inputs would keep them borrowed, it is only checked when it owns its data
+fn len_consistency(api_graph: &ApiGraph<'_>, output: &clean::Type, value: &str) -> Option<Stmt> {
+    if !matches!(output, clean::Type::Path { .. }) || api_util::_contains_reference(output) {
+        return None;
+    }
+    let len = find_method(api_graph, output, "len", PrimitiveType::Usize)?;
+    let is_empty = find_method(api_graph, output, "is_empty", PrimitiveType::Bool)?;
+    let method_call = |method: &ApiFunction| {
+        Expr::call(
+            Expr::path(method.full_name.as_str()),
+            vec![Expr::reference(false, Expr::path(value))],
+        )
+    };
+    let is_zero = Expr::binary(method_call(len), "==", Expr::Lit("0".to_string()));
+    let message = format!("{} disagrees with {}", is_empty.full_name, len.full_name);
+    Some(assert(Expr::binary(method_call(is_empty), "==", is_zero), &message))
+}
+
+//a safe inherent method taking `&self` and returning `ret`
+fn find_method<'a>(
+    api_graph: &'a ApiGraph<'_>,
+    ty: &clean::Type,
+    name: &str,
+    ret: PrimitiveType,
+) -> Option<&'a ApiFunction> {
+    let self_ref = CallType::_BorrowedRef(Box::new(CallType::_DirectCall));
+    api_graph.api_functions.iter().find(|method| {
+        short_name(method) == name
+            && method.inputs.len() == 1
+            && method.output == Some(clean::Type::Primitive(ret))
+            && method._trait_full_path.is_none()
+            && !method._unsafe_tag._is_unsafe()
+            && !method._async_tag._is_async()
+            && !method._is_generic_function()
+            && api_util::_same_type(
+                ty,
+                &method.inputs[0],
+                true,
+                &api_graph.full_name_map,
+                api_graph.cache(),
+            ) == self_ref
+    })
+}
+
+fn short_name(api_function: &ApiFunction) -> &str {
+    api_function.full_name.rsplit("::").next().unwrap_or(&api_function.full_name)
+}
+
+//`&[T]` or `&str`
+fn is_sequence_ref(ty: &clean::Type) -> bool {
+    match ty {
+        clean::Type::BorrowedRef { type_, .. } => {
+            matches!(**type_, clean::Type::Slice(..) | clean::Type::Primitive(PrimitiveType::Str))
+        }
+        _ => false,
+    }
+}
+
+fn assert(cond: Expr, message: &str) -> Stmt {
+    Stmt::Expr(Expr::MacroCall {
+        name: "assert".to_string(),
+        args: vec![cond, Expr::Lit(format!("{:?}", message))],
+    })
+}
@@ -0,0 +1,253 @@
+//Proof harnesses for the Kani model checker, with `--emit-kani`. A fuzzer only runs a target on
+//the inputs it happens to find, Kani checks a harness for every value of its nondeterministic
+//inputs, so a harness passing is a proof that the sequence has no panic, overflow or memory error.
+//Checking is exponential in the size of the program, so only the targets with a few calls get a
+//harness. The fuzzable params are `kani::any()` values instead of bytes split from the fuzz data,
+//and `&str` and slices are bounded to a few elements. `kani/` is a crate depending on the package
+//with a module for every harness, named after its target, e.g.
+//`cargo kani --harness check_fuzz_url_url_parse_1f2e3d4c5b6a7988`. Async targets and targets
+//calling into C are skipped, Kani supports neither.
+use crate::docfs::PathError;
+use crate::error::Error;
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::fuzz_target::code_ast::{Block, Expr, FnItem, Item, Pat, Stmt};
+use crate::fuzz_target::dependency;
+use crate::fuzz_target::features;
+use crate::fuzz_target::ffi;
+use crate::fuzz_target::file_util::{self, FileHelper};
+use crate::fuzz_target::fuzzable_type::FuzzableType;
+use crate::fuzz_target::serde_producer;
+use crate::try_err;
+use std::fs;
+use std::path::Path;
+
+static KANI_DIR: &'static str = "kani";
+//the longest sequence a harness is written for
+static MAX_KANI_SEQUENCE_LEN: usize = 3;
+//the most bytes of a `&str` and elements of a slice
+static NONDET_BOUND: usize = 4;
+//loops over the bounded values run at most NONDET_BOUND times, and one more to exit
+static UNWIND: usize = 5;
+
+pub(crate) fn write_kani(
+    api_graph: &ApiGraph<'_>,
+    helper: &FileHelper,
+    test_dir: &Path,
+) -> Result<(), Error> {
+    let lib_target = match &api_graph.cx.options.lib_target {
+        Some(lib_target) => lib_target,
+        None => {
+            println!("note: Kani harnesses are not written, they need `--manifest-path`");
+            return Ok(());
+        }
+    };
+    let kani_dir = test_dir.join(KANI_DIR);
+    if kani_dir.exists() {
+        try_err!(fs::remove_dir_all(&kani_dir), &kani_dir);
+    }
+    let src_dir = kani_dir.join("src");
+    try_err!(fs::create_dir_all(&src_dir), &src_dir);
+
+    //the harnesses are only compiled by `cargo kani`, which sets `--cfg kani`
+    let mut lib_file = String::new();
+    let mut harness_number = 0;
+    let naming = api_graph.cx.options.naming;
+    for (i, sequence) in helper.chosen_sequences.iter().enumerate() {
+        let file_name = file_util::test_file_name(naming, "test", &helper.target_names[i]);
+        let harness_file = match to_harness_file(sequence, api_graph, i, &file_name) {
+            Some(harness_file) => harness_file,
+            None => continue,
+        };
+        let module_name = file_name.trim_end_matches(".rs");
+        write_file(&src_dir.join(&file_name), harness_file)?;
+        lib_file.push_str(format!("#[cfg(kani)]\nmod {};\n", module_name).as_str());
+        harness_number += 1;
+    }
+    write_file(&src_dir.join("lib.rs"), lib_file)?;
+
+    let package_dir = lib_target.manifest_path.parent().unwrap_or(Path::new("."));
+    let forwarded_features = features::dependency_features(api_graph);
+    let mut manifest = format!(
+        "[package]\nname = \"{}-rulf-kani\"\nversion = \"0.0.0\"\nedition = \"2018\"\n\
+         publish = false\n\n[dependencies]\n",
+        lib_target.package_name
+    );
+    manifest.push_str(
+        format!("{} = {{ path = {:?}", lib_target.package_name, package_dir.display().to_string())
+            .as_str(),
+    );
+    if !forwarded_features.is_empty() {
+        manifest.push_str(
+            format!(", features = {}", features::toml_list(&forwarded_features)).as_str(),
+        );
+    }
+    manifest.push_str(" }\n");
+    let sequences = &helper.chosen_sequences;
+    if let Some(serde_dependency) = serde_producer::serde_dependency(api_graph, sequences) {
+        manifest.push_str(serde_dependency);
+        manifest.push('\n');
+    }
+    manifest.push_str(dependency::manifest_lines(api_graph, sequences).as_str());
+    manifest.push_str("\n[workspace]\nmembers = [\".\"]\n");
+    write_file(&kani_dir.join("Cargo.toml"), manifest)?;
+    println!("{} Kani harnesses are written to {}", harness_number, kani_dir.display());
+    Ok(())
+}
+
+//the test function of the target and the harness calling it, None if the target can't be checked
+fn to_harness_file(
+    sequence: &ApiSequence,
+    api_graph: &ApiGraph<'_>,
+    test_index: usize,
+    file_name: &str,
+) -> Option<String> {
+    if sequence.len() > MAX_KANI_SEQUENCE_LEN
+        || sequence._async_tag
+        || ffi::calls_ffi(sequence, api_graph)
+    {
+        return None;
+    }
+    let mut body = Block::new();
+    let mut args = Vec::new();
+    for (i, fuzzable_param) in sequence.fuzzable_params.iter().enumerate() {
+        let param_name = format!("_param{}", i);
+        let value = nondet_value(fuzzable_param, param_name.as_str(), &mut body)?;
+        body.push(Stmt::Let { pat: Pat::ident(param_name.as_str(), false), init: value });
+        args.push(Expr::Path(param_name));
+    }
+    let test_function = Expr::Path(format!("test_function{}", test_index));
+    body.push(Stmt::Expr(Expr::call(test_function, args)));
+
+    let mut res = String::new();
+    if let Some(prelude_functions) = sequence._prelude_helper_functions(api_graph) {
+        res.push_str(prelude_functions.as_str());
+    }
+    res.push_str(sequence._to_well_written_function(api_graph, test_index, 0).as_str());
+    res.push('\n');
+    let harness = FnItem {
+        attrs: vec!["kani::proof".to_string(), format!("kani::unwind({})", UNWIND)],
+        name: format!("check_{}", file_name.trim_end_matches(".rs")),
+        params: Vec::new(),
+        body,
+    };
+    res.push_str(Item::Fn(harness).to_code(0).as_str());
+    Some(res)
+}
+
+//an expression of any value of the type. Bounded `&str` and slices borrow from buffers declared by
+//statements added to `body`, named after `name`
+fn nondet_value(ty: &FuzzableType, name: &str, body: &mut Block) -> Option<Expr> {
+    let res = match ty {
+        FuzzableType::NoFuzzable => return None,
+        FuzzableType::Primitive(..) => {
+            Expr::Raw(format!("kani::any::<{}>()", ty._to_type_string()))
+        }
+        FuzzableType::Tuple(inner_types) => {
+            let mut elements = Vec::new();
+            for (k, inner_type) in inner_types.iter().enumerate() {
+                let element = nondet_value(inner_type, format!("{}_{}", name, k).as_str(), body)?;
+                elements.push(element.to_code(0));
+            }
+            //a tuple of one element needs a trailing comma
+            let trailing_comma = if elements.len() == 1 { "," } else { "" };
+            Expr::Raw(format!("({}{})", elements.join(", "), trailing_comma))
+        }
+        FuzzableType::Array(inner_type, length) => {
+            let element = fixed_nondet_value(inner_type)?;
+            Expr::Raw(format!(
+                "core::array::from_fn::<_, {}, _>(|_| {})",
+                length,
+                element.to_code(0)
+            ))
+        }
+        FuzzableType::RefStr => {
+            let bytes = format!("{}_bytes", name);
+            let len = bounded_len(name, body);
+            let utf8 = format!("{}_utf8", name);
+            body.push(Stmt::Let {
+                pat: Pat::ident(bytes.as_str(), false),
+                init: Expr::Raw(format!("kani::any::<[u8; {}]>()", NONDET_BOUND)),
+            });
+            body.push(Stmt::Let {
+                pat: Pat::ident(utf8.as_str(), false),
+                init: Expr::Raw(format!("core::str::from_utf8(&{}[..{}])", bytes, len)),
+            });
+            body.push(Stmt::Expr(Expr::call(
+                Expr::path("kani::assume"),
+                vec![Expr::method_call(Expr::path(utf8.as_str()), "is_ok", Vec::new())],
+            )));
+            Expr::method_call(Expr::path(utf8.as_str()), "unwrap", Vec::new())
+        }
+        FuzzableType::RefSlice(inner_type) => {
+            let element = fixed_nondet_value(inner_type)?;
+            let elements = format!("{}_elements", name);
+            let len = bounded_len(name, body);
+            body.push(Stmt::Let {
+                pat: Pat::ident(elements.as_str(), false),
+                init: Expr::Raw(format!(
+                    "core::array::from_fn::<_, {}, _>(|_| {})",
+                    NONDET_BOUND,
+                    element.to_code(0)
+                )),
+            });
+            Expr::Raw(format!("&{}[..{}]", elements, len))
+        }
+        FuzzableType::Enum(full_name, variants) => {
+            //match kani::any::<u8>() % n { 0 => E::A, 1 => E::B(..), _ => E::C { .. } }
+            let mut arms = Vec::new();
+            for (k, variant) in variants.iter().enumerate() {
+                let mut fields = Vec::new();
+                for field in &variant.fields {
+                    fields.push(fixed_nondet_value(field)?.to_code(0));
+                }
+                let path = format!("{}::{}", full_name, variant.name);
+                let constructor = variant._to_constructor(&path, &fields);
+                let pattern = if k == variants.len() - 1 { "_".to_string() } else { k.to_string() };
+                arms.push(format!("{} => {}", pattern, constructor));
+            }
+            Expr::Raw(format!(
+                "match kani::any::<u8>() % {} {{ {} }}",
+                variants.len(),
+                arms.join(", ")
+            ))
+        }
+        FuzzableType::Struct(variant) => {
+            let mut fields = Vec::new();
+            for field in &variant.fields {
+                fields.push(fixed_nondet_value(field)?.to_code(0));
+            }
+            Expr::Raw(variant._to_constructor(&variant.name, &fields))
+        }
+    };
+    Some(res)
+}
+
+//elements of arrays and fields are built in closures and match arms, so they can't borrow from
+//buffers of the harness
+fn fixed_nondet_value(ty: &FuzzableType) -> Option<Expr> {
+    if !ty._is_fixed_length() {
+        return None;
+    }
+    let mut unused_body = Block::new();
+    nondet_value(ty, "", &mut unused_body)
+}
+
+//a nondeterministic length of at most NONDET_BOUND, the name of the variable holding it
+fn bounded_len(name: &str, body: &mut Block) -> String {
+    let len = format!("{}_len", name);
+    body.push(Stmt::Let {
+        pat: Pat::ident(len.as_str(), false),
+        init: Expr::Raw("kani::any::<usize>()".to_string()),
+    });
+    body.push(Stmt::Expr(Expr::call(
+        Expr::path("kani::assume"),
+        vec![Expr::binary(Expr::path(len.as_str()), "<=", Expr::Lit(NONDET_BOUND.to_string()))],
+    )));
+    len
+}
+
+fn write_file(path: &Path, content: String) -> Result<(), Error> {
+    try_err!(fs::write(path, content), path);
+    Ok(())
+}
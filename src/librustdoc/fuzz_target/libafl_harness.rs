@@ -0,0 +1,264 @@
+//LibAFL fuzzers, with `--emit-harness libafl`, for the users who want an all-Rust fuzzing stack.
+//Every target becomes a binary running its closure in an in-process executor, with the edges of
+//`libafl_targets` as coverage. Besides the havoc mutations of LibAFL, the fuzzer has a mutator
+//derived from the layout of the fuzz data: it resizes the input to a length the closure accepts,
+//and fills a whole fixed length param with a boundary value, which byte level mutations rarely
+//produce for integers wider than a byte. Crashes are written to `crashes/`. The fuzzers use the
+//API of LibAFL 0.10, so `libafl_files/` is a crate of its own pinning that version. The edges map
+//is only filled by the `sancov_pcguard_edges` feature of `libafl_targets` with the code compiled
+//by the sancov passes, whose flags are set by `libafl_files/.cargo/config.toml`, so the fuzzers
+//are built with `cargo build --release` in that directory.
+use crate::docfs::PathError;
+use crate::error::Error;
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::fuzz_target::dependency;
+use crate::fuzz_target::features;
+use crate::fuzz_target::file_util::{self, FileHelper};
+use crate::fuzz_target::harness_emitter::{AFL_EXTERN_CRATE, HarnessEmitter};
+use crate::fuzz_target::runtime_crate::{self, RUNTIME_CRATE};
+use crate::fuzz_target::serde_producer;
+use crate::try_err;
+use std::fs;
+use std::path::Path;
+
+static LIBAFL_VERSION: &'static str = "=0.10.1";
+//the flags of the sancov passes filling the edges map of `sancov_pcguard_edges`
+static SANCOV_RUSTFLAGS: [&'static str; 3] = [
+    "-Cpasses=sancov-module",
+    "-Cllvm-args=-sanitizer-coverage-level=3",
+    "-Cllvm-args=-sanitizer-coverage-trace-pc-guard",
+];
+
+static LIBAFL_PRELUDE: &'static str = "extern crate libafl;
+extern crate libafl_targets;
+use libafl::bolts::current_nanos;
+use libafl::bolts::rands::{Rand, StdRand};
+use libafl::bolts::tuples::{tuple_list, Merge, Named};
+use libafl::bolts::AsSlice;
+use libafl::corpus::{InMemoryCorpus, OnDiskCorpus};
+use libafl::events::SimpleEventManager;
+use libafl::executors::{ExitKind, InProcessExecutor};
+use libafl::feedbacks::{CrashFeedback, MaxMapFeedback};
+use libafl::generators::RandBytesGenerator;
+use libafl::inputs::{BytesInput, HasBytesVec, HasTargetBytes};
+use libafl::monitors::SimpleMonitor;
+use libafl::mutators::{havoc_mutations, MutationResult, Mutator, StdScheduledMutator};
+use libafl::observers::StdMapObserver;
+use libafl::schedulers::QueueScheduler;
+use libafl::stages::StdMutationalStage;
+use libafl::state::{HasRand, StdState};
+use libafl::{Error, Fuzzer, StdFuzzer};
+use libafl_targets::{EDGES_MAP, MAX_EDGES_NUM};
+";
+
+static LAYOUT_MUTATOR: &'static str = "struct LayoutMutator;
+
+impl<S: HasRand> Mutator<BytesInput, S> for LayoutMutator {
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut BytesInput,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        //zero, one and the bytes of the extremes of signed and unsigned integers
+        const FILLS: [u8; 5] = [0x00, 0x01, 0x7f, 0x80, 0xff];
+        let bytes = input.bytes_mut();
+        if bytes.len() < MIN_LEN || (FIXED_LENGTH && bytes.len() != MIN_LEN) {
+            bytes.resize(MIN_LEN, 0);
+            return Ok(MutationResult::Mutated);
+        }
+        if FIELDS.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        let (offset, len) = FIELDS[state.rand_mut().below(FIELDS.len() as u64) as usize];
+        let fill = state.rand_mut().below(FILLS.len() as u64 + 1) as usize;
+        for byte in &mut bytes[offset..offset + len] {
+            *byte = if fill < FILLS.len() { FILLS[fill] } else { state.rand_mut().next() as u8 };
+        }
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for LayoutMutator {
+    fn name(&self) -> &str {
+        \"LayoutMutator\"
+    }
+}
+";
+
+static LIBAFL_MAIN: &'static str = "fn main() {
+    let target = TARGET;
+    let mut harness = |input: &BytesInput| {
+        let bytes = input.target_bytes();
+        target(bytes.as_slice());
+        ExitKind::Ok
+    };
+    let observer = unsafe { StdMapObserver::new(\"edges\", &mut EDGES_MAP[0..MAX_EDGES_NUM]) };
+    let mut feedback = MaxMapFeedback::new(&observer);
+    let mut objective = CrashFeedback::new();
+    let mut state = StdState::new(
+        StdRand::with_seed(current_nanos()),
+        InMemoryCorpus::new(),
+        OnDiskCorpus::new(std::path::PathBuf::from(\"./crashes\")).unwrap(),
+        &mut feedback,
+        &mut objective,
+    )
+    .unwrap();
+    let mut manager = SimpleEventManager::new(SimpleMonitor::new(|s| println!(\"{}\", s)));
+    let mut fuzzer = StdFuzzer::new(QueueScheduler::new(), feedback, objective);
+    let mut executor = InProcessExecutor::new(
+        &mut harness,
+        tuple_list!(observer),
+        &mut fuzzer,
+        &mut state,
+        &mut manager,
+    )
+    .expect(\"failed to create the executor\");
+    let mut generator = RandBytesGenerator::new(MIN_LEN.max(1) * 2);
+    state
+        .generate_initial_inputs(&mut fuzzer, &mut executor, &mut generator, &mut manager, 8)
+        .expect(\"failed to generate the initial inputs\");
+    let mutator = StdScheduledMutator::new(havoc_mutations().merge(tuple_list!(LayoutMutator)));
+    let mut stages = tuple_list!(StdMutationalStage::new(mutator));
+    fuzzer
+        .fuzz_loop(&mut stages, &mut executor, &mut state, &mut manager)
+        .expect(\"error in the fuzzing loop\");
+}
+";
+
+pub(crate) struct LibAflEmitter;
+
+impl HarnessEmitter for LibAflEmitter {
+    fn name(&self) -> &'static str {
+        "libafl"
+    }
+
+    fn dir_name(&self) -> &'static str {
+        "libafl_files"
+    }
+
+    fn file_prefix(&self) -> &'static str {
+        "libafl_target"
+    }
+
+    //the fuzzers need the sancov passes, they are bins of a crate of their own
+    fn is_bin(&self) -> bool {
+        false
+    }
+
+    fn emit(&self, sequence: &ApiSequence, api_graph: &ApiGraph<'_>, index: usize) -> String {
+        let mut res = sequence._to_afl_except_main(api_graph, index).replacen(
+            AFL_EXTERN_CRATE,
+            LIBAFL_PRELUDE,
+            1,
+        );
+        res.push('\n');
+        res.push_str(layout(sequence).as_str());
+        res.push('\n');
+        res.push_str(LAYOUT_MUTATOR);
+        res.push('\n');
+        let closure = sequence._fuzz_closure(api_graph, index).to_code(4);
+        res.push_str(LIBAFL_MAIN.replacen("TARGET", closure.as_str(), 1).as_str());
+        res
+    }
+}
+
+//the statics the mutator reads: the offset and length of each fixed length param, and the length
+//of the inputs the closure accepts
+fn layout(sequence: &ApiSequence) -> String {
+    let mut fields = Vec::new();
+    let mut offset = 0;
+    for fuzzable_param in &sequence.fuzzable_params {
+        let len = fuzzable_param._fixed_part_length();
+        if len > 0 {
+            fields.push(format!("({}, {})", offset, len));
+        }
+        offset += len;
+    }
+    format!(
+        "static FIELDS: &[(usize, usize)] = &[{}];\nstatic MIN_LEN: usize = {};\n\
+         static FIXED_LENGTH: bool = {};\n",
+        fields.join(", "),
+        sequence._fuzzables_min_length(),
+        sequence._is_fuzzables_fixed_length()
+    )
+}
+
+//the Cargo.toml of the fuzzers, with a bin for every target, and the config of cargo setting the
+//flags of the sancov passes
+pub(crate) fn write_manifest(api_graph: &ApiGraph<'_>, helper: &FileHelper) -> Result<(), Error> {
+    let lib_target = match &api_graph.cx.options.lib_target {
+        Some(lib_target) => lib_target,
+        None => {
+            println!(
+                "note: Cargo.toml of the LibAFL fuzzers is not written, it needs `--manifest-path`"
+            );
+            return Ok(());
+        }
+    };
+    let libafl_dir = LibAflEmitter.output_dir(helper);
+    let sequences = &helper.chosen_sequences;
+    let package_dir = lib_target.manifest_path.parent().unwrap_or(Path::new("."));
+    let forwarded_features = features::dependency_features(api_graph);
+    let mut manifest = format!(
+        "[package]\nname = \"{}-rulf-libafl\"\nversion = \"0.0.0\"\nedition = \"2018\"\n\
+         publish = false\n\n[dependencies]\n",
+        lib_target.package_name
+    );
+    manifest.push_str(format!("libafl = {:?}\n", LIBAFL_VERSION).as_str());
+    manifest.push_str(
+        format!(
+            "libafl_targets = {{ version = {:?}, features = [\"sancov_pcguard_edges\"] }}\n",
+            LIBAFL_VERSION
+        )
+        .as_str(),
+    );
+    manifest.push_str(
+        format!(
+            "{} = {{ path = {:?}, features = {} }}\n",
+            lib_target.package_name,
+            package_dir.display().to_string(),
+            features::toml_list(&forwarded_features)
+        )
+        .as_str(),
+    );
+    if api_graph.cx.options.runtime_crate {
+        manifest.push_str(runtime_crate::dependency(RUNTIME_CRATE).as_str());
+        runtime_crate::write_runtime_crate(api_graph, sequences, &libafl_dir)?;
+    }
+    if sequences.iter().any(|sequence| sequence._async_tag) {
+        manifest.push_str(features::async_runtime_dependency(api_graph));
+        manifest.push('\n');
+    }
+    if let Some(serde_dependency) = serde_producer::serde_dependency(api_graph, sequences) {
+        manifest.push_str(serde_dependency);
+        manifest.push('\n');
+    }
+    manifest.push_str(dependency::manifest_lines(api_graph, sequences).as_str());
+    let naming = api_graph.cx.options.naming;
+    for target_name in &helper.target_names {
+        let file_name = file_util::test_file_name(naming, LibAflEmitter.file_prefix(), target_name);
+        manifest.push_str(
+            format!(
+                "\n[[bin]]\nname = {:?}\npath = {:?}\n",
+                file_name.trim_end_matches(".rs"),
+                file_name
+            )
+            .as_str(),
+        );
+    }
+    //keep the crate out of any workspace around the test directory
+    manifest.push_str("\n[workspace]\n");
+    let manifest_path = libafl_dir.join("Cargo.toml");
+    try_err!(fs::write(&manifest_path, manifest), &manifest_path);
+
+    let rustflags: Vec<String> = SANCOV_RUSTFLAGS.iter().map(|flag| flag.to_string()).collect();
+    let config = format!("[build]\nrustflags = {}\n", features::toml_list(&rustflags));
+    let config_dir = libafl_dir.join(".cargo");
+    try_err!(fs::create_dir_all(&config_dir), &config_dir);
+    let config_path = config_dir.join("config.toml");
+    try_err!(fs::write(&config_path, config), &config_path);
+    println!("Cargo.toml of the LibAFL fuzzers is written to {}", manifest_path.display());
+    Ok(())
+}
@@ -0,0 +1,170 @@
+//Public types defined by the crate that can be built from the fuzz data directly, without a
+//producer api in the graph. Plain enums (field-less or with fuzzable fields) are common in
+//configuration parameters like encoding modes, and structs with public fields are often plain
+//option bags. Apis taking them were unreachable before.
+use crate::clean::{self, ItemKind, Variant};
+use crate::formats::cache::Cache;
+use crate::fuzz_target::fuzzable_type::ConstructorKind;
+use crate::html::format::join_with_double_colon;
+use crate::visit::DocVisitor;
+use rustc_data_structures::fx::FxHashMap;
+use rustc_hir::def::CtorKind;
+use rustc_hir::def_id::DefId;
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LocalTypes {
+    pub(crate) enums: FxHashMap<DefId, EnumDefinition>,
+    pub(crate) structs: FxHashMap<DefId, StructDefinition>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct EnumDefinition {
+    //the path used in the generated code, starting with the lib name
+    pub(crate) full_name: String,
+    pub(crate) variants: Vec<VariantDefinition>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct VariantDefinition {
+    pub(crate) name: String,
+    pub(crate) kind: ConstructorKind,
+    pub(crate) fields: Vec<clean::Type>,
+}
+
+//a struct whose fields are all public
+#[derive(Debug, Clone)]
+pub(crate) struct StructDefinition {
+    pub(crate) full_name: String,
+    pub(crate) kind: ConstructorKind,
+    pub(crate) fields: Vec<clean::Type>,
+    //`#[non_exhaustive]`, the targets are other crates so a struct literal of it doesn't compile.
+    //It's kept to tell why the apis taking it need a producer
+    pub(crate) non_exhaustive: bool,
+}
+
+//enums and structs without generic parameters, with the parts that can be constructed outside
+//the crate
+pub(crate) fn collect_local_types(
+    krate: &clean::Crate,
+    cache: &Cache,
+    crate_name: &str,
+    lib_name: &str,
+) -> LocalTypes {
+    let mut collector =
+        LocalTypeCollector { cache, crate_name, lib_name, local_types: LocalTypes::default() };
+    collector.visit_item(&krate.module);
+    collector.local_types
+}
+
+struct LocalTypeCollector<'a> {
+    cache: &'a Cache,
+    crate_name: &'a str,
+    lib_name: &'a str,
+    local_types: LocalTypes,
+}
+
+impl<'a> LocalTypeCollector<'a> {
+    fn full_name(&self, def_id: DefId) -> Option<String> {
+        let (syms, _) = self.cache.paths.get(&def_id)?;
+        let full_name = join_with_double_colon(syms);
+        if syms[0].as_str() == self.crate_name {
+            Some(format!("{}{}", self.lib_name, &full_name[self.crate_name.len()..]))
+        } else {
+            Some(full_name)
+        }
+    }
+
+    fn enum_definition(&self, def_id: DefId, enum_: &clean::Enum) -> Option<EnumDefinition> {
+        if !enum_.generics.params.is_empty() {
+            return None;
+        }
+        let full_name = self.full_name(def_id)?;
+        let variants: Vec<VariantDefinition> =
+            enum_.variants.iter().filter_map(variant_definition).collect();
+        if variants.is_empty() {
+            return None;
+        }
+        Some(EnumDefinition { full_name, variants })
+    }
+
+    fn struct_definition(
+        &self,
+        def_id: DefId,
+        struct_: &clean::Struct,
+        non_exhaustive: bool,
+    ) -> Option<StructDefinition> {
+        if !struct_.generics.params.is_empty() || struct_.has_stripped_entries() {
+            return None;
+        }
+        let full_name = self.full_name(def_id)?;
+        let (kind, fields) = match struct_.struct_type {
+            CtorKind::Const => (ConstructorKind::Unit, Vec::new()),
+            CtorKind::Fn => (ConstructorKind::Tuple, struct_.fields.iter().collect()),
+            CtorKind::Fictive => (
+                ConstructorKind::Named(field_names(&struct_.fields)?),
+                struct_.fields.iter().collect(),
+            ),
+        };
+        Some(StructDefinition { full_name, kind, fields: field_types(fields)?, non_exhaustive })
+    }
+}
+
+impl<'a> DocVisitor for LocalTypeCollector<'a> {
+    fn visit_item(&mut self, item: &clean::Item) {
+        if let Some(def_id) = item.item_id.as_def_id() {
+            match *item.kind {
+                ItemKind::EnumItem(ref enum_) => {
+                    if let Some(enum_definition) = self.enum_definition(def_id, enum_) {
+                        self.local_types.enums.insert(def_id, enum_definition);
+                    }
+                }
+                ItemKind::StructItem(ref struct_) => {
+                    let non_exhaustive = item.is_non_exhaustive();
+                    if let Some(struct_definition) =
+                        self.struct_definition(def_id, struct_, non_exhaustive)
+                    {
+                        self.local_types.structs.insert(def_id, struct_definition);
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.visit_item_recur(item)
+    }
+}
+
+//`#[non_exhaustive]` variants and variants with private fields can't be constructed. A
+//`#[non_exhaustive]` enum only can't be matched exhaustively, its variants are still constructed
+fn variant_definition(variant: &clean::Item) -> Option<VariantDefinition> {
+    if variant.is_stripped() || variant.is_non_exhaustive() {
+        return None;
+    }
+    let name = variant.name?.to_string();
+    let (kind, field_items) = match &*variant.kind {
+        ItemKind::VariantItem(Variant::CLike(_)) => (ConstructorKind::Unit, Vec::new()),
+        ItemKind::VariantItem(Variant::Tuple(fields)) => {
+            (ConstructorKind::Tuple, fields.iter().collect())
+        }
+        ItemKind::VariantItem(Variant::Struct(variant_struct)) => (
+            ConstructorKind::Named(field_names(&variant_struct.fields)?),
+            variant_struct.fields.iter().collect(),
+        ),
+        _ => return None,
+    };
+    Some(VariantDefinition { name, kind, fields: field_types(field_items)? })
+}
+
+fn field_names(fields: &Vec<clean::Item>) -> Option<Vec<String>> {
+    fields.iter().map(|field| field.name.map(|name| name.to_string())).collect()
+}
+
+//None if a field is stripped, i.e. private
+fn field_types(fields: Vec<&clean::Item>) -> Option<Vec<clean::Type>> {
+    fields
+        .into_iter()
+        .map(|field| match &*field.kind {
+            ItemKind::StructFieldItem(ty) => Some(ty.clone()),
+            _ => None,
+        })
+        .collect()
+}
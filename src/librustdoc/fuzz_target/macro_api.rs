@@ -0,0 +1,103 @@
+//Apis defined by macro expansions that the doc tree misses. rustdoc only collects the inherent
+//impls written as items of a module, so an impl a macro hides in an anonymous const, e.g.
+//`const _: () = { impl Foo { pub fn new() -> Self { .. } } };`, or in the body of a function is
+//never cleaned, although its methods are exported. The exported functions and inherent methods of
+//the crate are looked up in the HIR after expansion instead, and the ones defined by an expansion
+//are cleaned before the format cache is built. After rendering, those the doc tree has no item for
+//are added to the graph, named by the path of their module or type.
+use crate::core::DocContext;
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::dependency::{self, DependencyApi};
+use rustc_hir::def::DefKind;
+use rustc_hir::def_id::DefId;
+
+#[derive(Debug, Clone)]
+pub(crate) struct MacroApi {
+    pub(crate) def_id: DefId,
+    //the name of the api is only known after rendering
+    pub(crate) api: DependencyApi,
+}
+
+pub(crate) fn load_macro_apis(cx: &mut DocContext<'_>) {
+    let options = &cx.render_options.fuzz_target_options;
+    if !options.generate_targets {
+        return;
+    }
+    let tcx = cx.tcx;
+    let (include_hidden, include_deprecated) = (options.include_hidden, options.include_deprecated);
+    let mut def_ids = Vec::new();
+    let effective_visibilities = tcx.effective_visibilities(());
+    for (local_def_id, _) in effective_visibilities.iter() {
+        let def_id = local_def_id.to_def_id();
+        let is_function = match tcx.def_kind(def_id) {
+            DefKind::Fn => true,
+            //methods of trait impls are cleaned with their impl
+            DefKind::AssocFn => tcx
+                .impl_of_method(def_id)
+                .map_or(false, |impl_def_id| tcx.trait_id_of_impl(impl_def_id).is_none()),
+            _ => false,
+        };
+        if !is_function
+            || !effective_visibilities.is_exported(*local_def_id)
+            || !tcx.def_span(def_id).from_expansion()
+            || (!include_hidden && tcx.is_doc_hidden(def_id))
+            || (!include_deprecated && tcx.lookup_deprecation(def_id).is_some())
+        {
+            continue;
+        }
+        def_ids.push(def_id);
+    }
+    let mut macro_apis = Vec::new();
+    for def_id in def_ids {
+        let api = dependency::dependency_api(cx, def_id, String::new());
+        macro_apis.push(MacroApi { def_id, api });
+    }
+    cx.render_options.fuzz_target_options.macro_apis = macro_apis;
+}
+
+//the apis rendering didn't add
+pub(crate) fn add_macro_apis(api_graph: &mut ApiGraph<'_>) {
+    let cx = api_graph.cx.clone();
+    let mut added_number = 0;
+    for macro_api in &cx.options.macro_apis {
+        if let Some(full_name) = unrendered_api_name(api_graph, macro_api.def_id) {
+            let mut api = macro_api.api.clone();
+            api.full_name = full_name;
+            api_graph.add_const_api(&api.full_name, Some(macro_api.def_id));
+            api_graph.add_api_risks(&api.full_name, Some(macro_api.def_id));
+            api_graph.add_must_use_api(&api.full_name, Some(macro_api.def_id));
+            api_graph.add_api_function(api.to_api_function());
+            added_number += 1;
+        }
+    }
+    if added_number > 0 {
+        println!("{} apis defined by macros are added", added_number);
+    }
+}
+
+//functions of the doc tree are in the paths of the cache, and methods are in the impls of their
+//type. None for a rendered api, or one whose module or type has no public path
+fn unrendered_api_name(api_graph: &ApiGraph<'_>, def_id: DefId) -> Option<String> {
+    let tcx = api_graph.tcx();
+    let full_name_map = &api_graph.full_name_map;
+    let parent_name = match tcx.impl_of_method(def_id) {
+        None => {
+            if full_name_map._get_full_name(def_id).is_some() {
+                return None;
+            }
+            full_name_map._get_full_name(tcx.parent(def_id))?
+        }
+        Some(impl_def_id) => {
+            let type_def_id = tcx.type_of(impl_def_id).ty_adt_def()?.did();
+            let impls = api_graph.cache().impls.get(&type_def_id);
+            let is_rendered = impls.map_or(false, |impls| {
+                impls.iter().any(|impl_| impl_.impl_item.item_id.as_def_id() == Some(impl_def_id))
+            });
+            if is_rendered {
+                return None;
+            }
+            full_name_map._get_full_name(type_def_id)?
+        }
+    };
+    Some(format!("{}::{}", parent_name, tcx.item_name(def_id)))
+}
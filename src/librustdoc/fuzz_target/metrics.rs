@@ -0,0 +1,140 @@
+//The metrics of the RULF paper as csv(rulf-metrics.csv), with `--emit-metrics`, so that the tables
+//comparing against RULF can be reproduced by a script. The first row is the run itself, the others
+//break the metrics down by strategy: every registered strategy runs on a copy of the graph before
+//the chosen one, so the rows are measured on the same graph. The targets of these rows are the
+//selection only, without variants, compile check or miri, and the exhaustive strategies are left
+//out unless `--beam-width` bounds them. The api coverage counts the apis refused before the graph
+//was built too, like the json report.
+use crate::docfs::PathError;
+use crate::error::Error;
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::file_util::{self, FileHelper};
+use crate::fuzz_target::graph_traverse::{SequenceStrategy, StrategyRegistry};
+use crate::try_err;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+static METRICS_FILE_NAME: &'static str = "rulf-metrics.csv";
+static METRICS_HEADER: &'static str = "crate,strategy,run,total_apis,covered_apis,\
+    api_coverage_percent,sequences,average_sequence_len,max_sequence_len,targets,\
+    generation_seconds";
+
+#[derive(Debug, Clone)]
+pub(crate) struct MetricsRow {
+    strategy: String,
+    //`selected` for the run itself, `breakdown` for the strategies run on copies
+    run: &'static str,
+    total_apis: usize,
+    covered_apis: usize,
+    sequences: usize,
+    average_sequence_len: f64,
+    max_sequence_len: usize,
+    targets: usize,
+    generation_time: Duration,
+}
+
+impl MetricsRow {
+    fn measure(
+        graph: &ApiGraph<'_>,
+        strategy: String,
+        run: &'static str,
+        targets: usize,
+        generation_time: Duration,
+    ) -> Self {
+        let total_apis = graph.api_functions.len()
+            + graph.generic_functions.len()
+            + graph.functions_with_unsupported_fuzzable_types.len()
+            + graph.functions_newer_than_msrv.len();
+        let covered_apis = graph.api_functions_visited.iter().filter(|visited| **visited).count();
+        let sequences = graph.api_sequences.len();
+        let total_sequence_len: usize = graph.api_sequences.iter().map(|s| s.len()).sum();
+        let average_sequence_len =
+            if sequences > 0 { total_sequence_len as f64 / sequences as f64 } else { 0.0 };
+        let max_sequence_len = graph.api_sequences.iter().map(|s| s.len()).max().unwrap_or(0);
+        MetricsRow {
+            strategy,
+            run,
+            total_apis,
+            covered_apis,
+            sequences,
+            average_sequence_len,
+            max_sequence_len,
+            targets,
+            generation_time,
+        }
+    }
+
+    fn to_csv_line(&self, crate_name: &str) -> String {
+        let coverage = if self.total_apis > 0 {
+            100.0 * self.covered_apis as f64 / self.total_apis as f64
+        } else {
+            0.0
+        };
+        format!(
+            "{},{},{},{},{},{:.2},{},{:.2},{},{},{:.3}",
+            crate_name,
+            self.strategy,
+            self.run,
+            self.total_apis,
+            self.covered_apis,
+            coverage,
+            self.sequences,
+            self.average_sequence_len,
+            self.max_sequence_len,
+            self.targets,
+            self.generation_time.as_secs_f64()
+        )
+    }
+}
+
+//run every registered strategy on a copy of the graph, whose dependencies are found but has no
+//sequences yet
+pub(crate) fn strategy_breakdown(
+    graph: &ApiGraph<'_>,
+    strategies: &StrategyRegistry,
+) -> Vec<MetricsRow> {
+    let mut res = Vec::new();
+    for strategy in strategies.strategies() {
+        if strategy.is_exhaustive() && graph.cx.options.beam_width.is_none() {
+            println!("the metrics of `{}` are skipped, it needs `--beam-width`", strategy.name());
+            continue;
+        }
+        let mut copy = graph.clone();
+        let start = Instant::now();
+        copy.generate_sequences(strategy);
+        let generation_time = start.elapsed();
+        let targets = file_util::select_sequences(&copy, strategy.is_random()).len();
+        let name = strategy.name().to_string();
+        res.push(MetricsRow::measure(&copy, name, "breakdown", targets, generation_time));
+    }
+    res
+}
+
+pub(crate) fn write_metrics(
+    graph: &ApiGraph<'_>,
+    strategy: &dyn SequenceStrategy,
+    file_helper: Option<&FileHelper>,
+    generation_time: Duration,
+    breakdown: Vec<MetricsRow>,
+    output_dir: &Path,
+) -> Result<(), Error> {
+    //the targets are chosen in the same way when no files are written
+    let targets = match file_helper {
+        Some(file_helper) => file_helper.chosen_sequences.len(),
+        None => file_util::choose_sequences(graph, strategy.is_random()).len(),
+    };
+    let name = strategy.name().to_string();
+    let selected = MetricsRow::measure(graph, name, "selected", targets, generation_time);
+    let mut content = String::from(METRICS_HEADER);
+    content.push('\n');
+    for row in std::iter::once(&selected).chain(breakdown.iter()) {
+        content.push_str(row.to_csv_line(&graph._crate_name).as_str());
+        content.push('\n');
+    }
+    try_err!(fs::create_dir_all(output_dir), output_dir);
+    let metrics_path = output_dir.join(METRICS_FILE_NAME);
+    try_err!(fs::write(&metrics_path, content), &metrics_path);
+    println!("metrics are written to {}", metrics_path.display());
+    Ok(())
+}
@@ -0,0 +1,205 @@
+//Run every target once under Miri before it is written, with `--validate miri`. The decoding of
+//the fuzz data and the adaptations between calls are generated code, and undefined behavior in
+//them (slices built from invalid pointers, unaligned casts) would make every crash found by the
+//fuzzer worthless. The replay file of each target runs with an input of zeros. A target whose
+//undefined behavior is reported in the target itself is removed, undefined behavior reported in
+//the library is a finding and the target is kept, and so is a target that panics. A target that
+//doesn't compile is removed. Operations Miri doesn't support, like ffi calls, and runs longer
+//than the timeout leave the target unchecked: it is kept but doesn't count as valid.
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::fuzz_target::compile_check::{self, CHECK_BIN_PREFIX};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+static MIRI_DIR_NAME: &'static str = "rulf-miri-check";
+static MIRI_TOOLCHAIN: &'static str = "nightly";
+//the replay files read the input from a file
+static MIRI_FLAGS: &'static str = "-Zmiri-disable-isolation";
+static INPUT_FILE_NAME: &'static str = "zeros";
+//dynamic length params get some bytes beyond the minimal length
+static MIN_INPUT_LEN: usize = 64;
+static UB_ERROR: &'static str = "error: Undefined Behavior";
+static UNSUPPORTED_ERROR: &'static str = "error: unsupported operation";
+static COMPILE_ERROR: &'static str = "could not compile";
+//the first run also builds the library and std for Miri
+static MIRI_TIMEOUT: Duration = Duration::from_secs(600);
+static POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+enum MiriResult {
+    //the target runs to the end or panics, the panic is a crash the fuzzer finds too
+    Passed,
+    //undefined behavior reported in the target itself
+    GlueError(String),
+    //undefined behavior reported in the library or its dependencies
+    LibraryError(String),
+    //the target doesn't compile
+    CompileError(String),
+    //an operation Miri doesn't support, a timeout or another error of Miri
+    Unchecked(String),
+}
+
+pub(crate) fn check_sequences(
+    api_graph: &ApiGraph<'_>,
+    sequences: Vec<ApiSequence>,
+) -> Vec<ApiSequence> {
+    let lib_target = match &api_graph.cx.options.lib_target {
+        Some(lib_target) => lib_target,
+        None => {
+            println!("warning: miri validation is skipped, it needs `--manifest-path`");
+            return sequences;
+        }
+    };
+    let miri_dir = api_graph.cx.output_dir.join(MIRI_DIR_NAME);
+    let manifest_path =
+        match compile_check::write_crate(api_graph, lib_target, &miri_dir, &sequences, false) {
+            Ok((manifest_path, _)) => manifest_path,
+            Err(e) => {
+                println!("warning: miri validation is skipped, {}", e);
+                return sequences;
+            }
+        };
+    let total_number = sequences.len();
+    let mut res = Vec::new();
+    let mut library_errors = 0;
+    let mut unchecked_number = 0;
+    let mut stopped = false;
+    for (i, sequence) in sequences.into_iter().enumerate() {
+        if stopped {
+            res.push(sequence);
+            continue;
+        }
+        let input_len = sequence._fuzzables_min_length().max(MIN_INPUT_LEN);
+        match run_miri(&manifest_path, &miri_dir, i, input_len) {
+            Ok(MiriResult::Passed) => res.push(sequence),
+            Ok(MiriResult::GlueError(error)) => {
+                println!("target {} has undefined behavior and is removed:\n{}", i, error);
+            }
+            Ok(MiriResult::LibraryError(error)) => {
+                println!("target {} finds undefined behavior in the library:\n{}", i, error);
+                library_errors += 1;
+                res.push(sequence);
+            }
+            Ok(MiriResult::CompileError(error)) => {
+                println!("target {} does not compile and is removed:\n{}", i, error);
+            }
+            Ok(MiriResult::Unchecked(reason)) => {
+                println!("target {} is not checked by miri, {}", i, reason);
+                unchecked_number += 1;
+                res.push(sequence);
+            }
+            Err(e) => {
+                println!("warning: miri validation is stopped, {}", e);
+                stopped = true;
+                res.push(sequence);
+            }
+        }
+    }
+    println!(
+        "miri validation: {}/{} targets are valid, {} of them find undefined behavior, \
+         {} targets are kept unchecked",
+        res.len() - unchecked_number,
+        total_number,
+        library_errors,
+        unchecked_number
+    );
+    res
+}
+
+fn run_miri(
+    manifest_path: &Path,
+    miri_dir: &Path,
+    index: usize,
+    input_len: usize,
+) -> Result<MiriResult, String> {
+    let input_path = miri_dir.join(INPUT_FILE_NAME);
+    fs::write(&input_path, vec![0u8; input_len])
+        .map_err(|e| format!("failed to write {:?}: {}", input_path, e))?;
+    let bin_name = format!("{}{}", CHECK_BIN_PREFIX, index);
+    let mut child = Command::new("cargo")
+        .arg(format!("+{}", MIRI_TOOLCHAIN))
+        .args(["miri", "run", "--quiet", "--manifest-path"])
+        .arg(manifest_path)
+        .args(["--bin", bin_name.as_str(), "--"])
+        .arg(&input_path)
+        .env("MIRIFLAGS", MIRI_FLAGS)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to run `cargo miri`: {}", e))?;
+    //stderr is read while waiting, Miri would block on a full pipe
+    let mut child_stderr = child.stderr.take().unwrap();
+    let reader = thread::spawn(move || {
+        let mut stderr = String::new();
+        let _ = child_stderr.read_to_string(&mut stderr);
+        stderr
+    });
+    let start = Instant::now();
+    let status = loop {
+        match child.try_wait().map_err(|e| format!("failed to wait for `cargo miri`: {}", e))? {
+            Some(status) => break Some(status),
+            None if start.elapsed() >= MIRI_TIMEOUT => {
+                let _ = child.kill();
+                let _ = child.wait();
+                break None;
+            }
+            None => thread::sleep(POLL_INTERVAL),
+        }
+    };
+    let stderr = reader.join().unwrap_or_default();
+    if stderr.contains("no such command: `miri`") {
+        return Err(format!(
+            "miri is not installed, see `rustup +{} component add miri`",
+            MIRI_TOOLCHAIN
+        ));
+    }
+    let status = match status {
+        Some(status) => status,
+        None => {
+            return Ok(MiriResult::Unchecked(format!(
+                "it runs longer than {} seconds",
+                MIRI_TIMEOUT.as_secs()
+            )));
+        }
+    };
+    if let Some(error_start) = stderr.find(UB_ERROR) {
+        let error = first_error(&stderr[error_start..]);
+        //the first location of the error, ` --> src/bin/check0.rs:12:5` when it is in the target
+        let location = error.lines().find_map(|line| line.trim().strip_prefix("--> "));
+        let bin_path = format!("src/bin/{}.rs", bin_name);
+        return match location {
+            Some(location) if location.starts_with(bin_path.as_str()) => {
+                Ok(MiriResult::GlueError(error))
+            }
+            _ => Ok(MiriResult::LibraryError(error)),
+        };
+    }
+    if let Some(error_start) = stderr.find(UNSUPPORTED_ERROR) {
+        return Ok(MiriResult::Unchecked(first_error(&stderr[error_start..])));
+    }
+    if let Some(error_start) = stderr.find(COMPILE_ERROR) {
+        //the library is compiled before the bins, an error in it breaks every target
+        let package_name = miri_dir.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        if !stderr[error_start..].contains(format!("`{}`", package_name).as_str()) {
+            return Err(first_error(&stderr[error_start..]));
+        }
+        let error_start = stderr.find("error").unwrap_or(error_start);
+        return Ok(MiriResult::CompileError(first_error(&stderr[error_start..])));
+    }
+    //a panic exits with 101 like an error of Miri, but always reports where it happened
+    if status.success() || stderr.contains("panicked at") {
+        Ok(MiriResult::Passed)
+    } else {
+        Ok(MiriResult::Unchecked(format!("miri exits with {}:\n{}", status, stderr.trim())))
+    }
+}
+
+//the lines of an error up to the first empty line
+fn first_error(stderr: &str) -> String {
+    let error_lines: Vec<&str> = stderr.lines().take_while(|line| !line.is_empty()).collect();
+    error_lines.join("\n")
+}
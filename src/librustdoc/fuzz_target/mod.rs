@@ -1,15 +1,78 @@
 mod afl_util;
+mod api_filter;
 mod api_function;
 mod api_graph;
 mod api_sequence;
+mod api_usage;
 mod api_util;
+mod assert_guard;
+mod beam_search;
+mod bin_crate;
+mod borrow_check;
+mod call_frequency;
 mod call_type;
+mod char_mutator;
+mod clone_check;
+mod cargo_metadata;
+mod code_ast;
+mod compile_check;
+mod const_probe;
+mod contract;
+mod conversion;
+mod corpus;
+mod crash_to_test;
+mod default_producer;
+pub(crate) mod dependency;
+mod dictionary;
+mod differential;
+mod drop_order;
+mod explain;
+pub(crate) mod features;
+mod ffi;
 mod file_util;
+mod format_harness;
+pub(crate) mod fuzz_target_options;
 pub mod fuzz_target_renderer;
 mod fuzzable_type;
 mod generic_function;
+mod genetic_algorithm;
+mod graph_cache;
+mod graph_traverse;
+mod harness_emitter;
+mod html_report;
 mod impl_util;
+mod incremental;
+mod invariant;
+mod kani;
+mod libafl_harness;
+mod local_type;
+pub(crate) mod macro_api;
+mod metrics;
+mod miri_check;
 mod mod_visibility;
+mod multi_object;
+mod no_std;
+mod operator;
+mod ossfuzz;
+mod panic_doc;
+mod param_bounds;
 mod prelude_type;
 mod print_message;
+mod proptest_harness;
 mod replay_util;
+mod report;
+mod risk_rank;
+mod runtime_borrow;
+mod round_trip;
+pub(crate) mod rulf_config;
+mod run_manifest;
+mod runtime_crate;
+mod rust_version;
+mod sequence_ir;
+mod sequence_select;
+mod serde_producer;
+mod sink_search;
+mod std_value;
+mod usage_mining;
+mod where_clause;
+mod workspace;
@@ -1,17 +1,29 @@
 use crate::clean::Visibility;
-use rustc_data_structures::fx::{FxHashMap};
+use rustc_data_structures::fx::{FxHashMap, FxHashSet};
+
+#[cfg(test)]
+mod tests;
+
+//re-exports of re-exports are followed until there are this many paths of an item
+static MAX_ITEM_PATHS: usize = 64;
 
 #[derive(Debug, Clone)]
 pub(crate) struct ModVisibity {
     pub(crate) crate_name: String,
     pub(crate) inner: FxHashMap<String, Visibility>,
+    //the other paths of items and mods, from `pub use` and the re-exports rustdoc inlines
+    pub(crate) reexports: FxHashMap<String, Vec<String>>,
+    //the mods reachable from the crate root, set by `resolve_visible_mods`
+    pub(crate) visible_mods: FxHashSet<String>,
 }
 
 impl ModVisibity {
     pub(crate) fn new(crate_name_: &String) -> Self {
         let crate_name = crate_name_.clone();
         let inner = FxHashMap::default();
-        ModVisibity { crate_name, inner }
+        let reexports = FxHashMap::default();
+        let visible_mods = FxHashSet::default();
+        ModVisibity { crate_name, inner, reexports, visible_mods }
     }
 
     pub(crate) fn add_one_mod(&mut self, mod_name: &String, visibility: &Visibility) {
@@ -19,7 +31,83 @@ pub(crate) fn add_one_mod(&mut self, mod_name: &String, visibility: &Visibility)
         self.inner.insert(mod_name.clone(), visibility.clone());
     }
 
+    //`path` names the item or mod at `reexport_path` too
+    pub(crate) fn add_reexport(&mut self, path: &String, reexport_path: String) {
+        if *path == reexport_path {
+            return;
+        }
+        let reexport_paths = self.reexports.entry(path.clone()).or_default();
+        if !reexport_paths.contains(&reexport_path) {
+            reexport_paths.push(reexport_path);
+        }
+    }
+
     pub(crate) fn get_invisible_mods(&self) -> Vec<String> {
+        let mut res = Vec::new();
+        for (mod_name, visibility) in &self.mod_visibility_map() {
+            if !*visibility {
+                res.push(mod_name.clone());
+            }
+        }
+        res
+    }
+
+    pub(crate) fn resolve_visible_mods(&mut self) {
+        self.visible_mods = self
+            .mod_visibility_map()
+            .into_iter()
+            .filter(|(_, visibility)| *visibility)
+            .map(|(mod_name, _)| mod_name)
+            .collect();
+    }
+
+    //the shortest path of the item going only through visible mods, among its own path and the
+    //paths of the re-exports of the item or of the mods it is in. A private path stays as it is
+    //when there is no other, e.g. for the items of other crates
+    pub(crate) fn canonical_path(&self, path: &String) -> String {
+        let mut paths = vec![path.clone()];
+        let mut i = 0;
+        while i < paths.len() && paths.len() < MAX_ITEM_PATHS {
+            let segments: Vec<String> = paths[i].split("::").map(String::from).collect();
+            for end in 1..=segments.len() {
+                let reexport_paths = match self.reexports.get(&segments[..end].join("::")) {
+                    Some(reexport_paths) => reexport_paths,
+                    None => continue,
+                };
+                for reexport_path in reexport_paths {
+                    let mut item_path = reexport_path.clone();
+                    for segment in &segments[end..] {
+                        item_path.push_str("::");
+                        item_path.push_str(segment);
+                    }
+                    if !paths.contains(&item_path) {
+                        paths.push(item_path);
+                    }
+                }
+            }
+            i += 1;
+        }
+        //the first of the shortest ones, the path itself if it is one of them
+        paths
+            .into_iter()
+            .filter(|item_path| self.is_visible_path(item_path))
+            .min_by_key(|item_path| item_path.matches("::").count())
+            .unwrap_or_else(|| path.clone())
+    }
+
+    //the innermost mod of the path is visible, the parents of an item may be types or traits
+    fn is_visible_path(&self, path: &String) -> bool {
+        let mut parent = get_parent_mod_name(path);
+        while let Some(parent_name) = parent {
+            if self.inner.contains_key(&parent_name) {
+                return self.visible_mods.contains(&parent_name);
+            }
+            parent = get_parent_mod_name(&parent_name);
+        }
+        false
+    }
+
+    fn mod_visibility_map(&self) -> FxHashMap<String, bool> {
         let mod_number = self.inner.len();
 
         let mut new_mod_visibility = FxHashMap::default();
@@ -50,14 +138,7 @@ pub(crate) fn get_invisible_mods(&self) -> Vec<String> {
                 break;
             }
         }
-
-        let mut res = Vec::new();
-        for (mod_name, visibility) in &new_mod_visibility {
-            if !*visibility {
-                res.push(mod_name.clone());
-            }
-        }
-        res
+        new_mod_visibility
     }
 }
 
@@ -0,0 +1,74 @@
+use super::ModVisibity;
+use crate::clean::Visibility;
+
+//`mycrate` with a private `imp` holding a public `deep`, and a public `api`
+fn mods() -> ModVisibity {
+    let mut mods = ModVisibity::new(&"mycrate".to_string());
+    mods.add_one_mod(&"mycrate".to_string(), &Visibility::Public);
+    mods.add_one_mod(&"mycrate::imp".to_string(), &Visibility::Inherited);
+    mods.add_one_mod(&"mycrate::imp::deep".to_string(), &Visibility::Public);
+    mods.add_one_mod(&"mycrate::api".to_string(), &Visibility::Public);
+    mods
+}
+
+fn canonical_path(mods: &ModVisibity, path: &str) -> String {
+    mods.canonical_path(&path.to_string())
+}
+
+#[test]
+fn visible_path() {
+    let mut mods = mods();
+    mods.resolve_visible_mods();
+    assert_eq!(canonical_path(&mods, "mycrate::api::Foo"), "mycrate::api::Foo");
+}
+
+#[test]
+fn empty_path() {
+    let mut mods = mods();
+    mods.resolve_visible_mods();
+    assert_eq!(canonical_path(&mods, ""), "");
+}
+
+#[test]
+fn private_path_without_reexport() {
+    let mut mods = mods();
+    mods.resolve_visible_mods();
+    assert_eq!(canonical_path(&mods, "mycrate::imp::deep::Foo"), "mycrate::imp::deep::Foo");
+    assert_eq!(canonical_path(&mods, "std::vec::Vec"), "std::vec::Vec");
+}
+
+#[test]
+fn reexport_chain() {
+    let mut mods = mods();
+    mods.add_reexport(&"mycrate::imp::deep::Foo".to_string(), "mycrate::imp::Foo".to_string());
+    mods.add_reexport(&"mycrate::imp::Foo".to_string(), "mycrate::api::Foo".to_string());
+    mods.resolve_visible_mods();
+    assert_eq!(canonical_path(&mods, "mycrate::imp::deep::Foo"), "mycrate::api::Foo");
+}
+
+#[test]
+fn shortest_reexport() {
+    let mut mods = mods();
+    mods.add_reexport(&"mycrate::imp::Foo".to_string(), "mycrate::api::Foo".to_string());
+    mods.add_reexport(&"mycrate::imp::Foo".to_string(), "mycrate::Foo".to_string());
+    mods.resolve_visible_mods();
+    assert_eq!(canonical_path(&mods, "mycrate::imp::Foo"), "mycrate::Foo");
+    assert_eq!(canonical_path(&mods, "mycrate::api::Foo"), "mycrate::api::Foo");
+}
+
+#[test]
+fn reexported_mod() {
+    let mut mods = mods();
+    mods.add_reexport(&"mycrate::imp".to_string(), "mycrate::api::imp".to_string());
+    mods.resolve_visible_mods();
+    assert_eq!(canonical_path(&mods, "mycrate::imp::Bar"), "mycrate::api::imp::Bar");
+}
+
+#[test]
+fn reexport_cycle() {
+    let mut mods = mods();
+    mods.add_reexport(&"mycrate::imp::Foo".to_string(), "mycrate::imp::deep::Foo".to_string());
+    mods.add_reexport(&"mycrate::imp::deep::Foo".to_string(), "mycrate::imp::Foo".to_string());
+    mods.resolve_visible_mods();
+    assert_eq!(canonical_path(&mods, "mycrate::imp::Foo"), "mycrate::imp::Foo");
+}
@@ -0,0 +1,123 @@
+//Sequences building two or more independent objects and passing them to one api, with
+//`--strategy multi-object`. The other strategies mostly extend a single chain of calls, so an api
+//like `a.merge(&b)`, `a.diff(&b)` or `cmp(&a, &b)` gets the same object twice, or no sequence at
+//all. Every api taking several params that can't be fuzzed gets the shortest producer chain of
+//each param, found like for the sink strategy, so each param has its own object. The calls on the
+//objects are interleaved: the objects are built one after the other, each one but the first
+//followed by a call of a method mutating it, then the first one is mutated, and the api is called
+//last. Among producers of the same weight, each param of the api takes a return the call doesn't
+//take yet, which the other strategies don't do. An api only gets a sequence when its params are
+//bound to at least two different objects.
+use crate::fuzz_target::api_graph::{ApiGraph, ApiType};
+use crate::fuzz_target::api_sequence::{ApiSequence, ParamType};
+use crate::fuzz_target::api_util;
+use crate::fuzz_target::graph_traverse::SequenceStrategy;
+use crate::fuzz_target::sink_search::ProducerChains;
+use rustc_data_structures::fx::FxHashSet;
+
+pub(crate) struct MultiObjectStrategy;
+
+impl SequenceStrategy for MultiObjectStrategy {
+    fn name(&self) -> &'static str {
+        "multi-object"
+    }
+
+    fn generate(&self, graph: &ApiGraph<'_>) -> Vec<ApiSequence> {
+        let multi_object_apis: Vec<usize> = (0..graph.api_functions.len())
+            .filter(|api_index| unfuzzable_param_number(graph, *api_index) >= 2)
+            .collect();
+        let chains = ProducerChains::new(graph, &multi_object_apis);
+        let mut res = Vec::new();
+        for api_index in multi_object_apis.iter().copied() {
+            if graph.is_out_of_time() {
+                break;
+            }
+            let sequence = chains
+                .param_chains(graph, api_index)
+                .and_then(|param_chains| interleave(graph, &param_chains))
+                .and_then(|prefix| {
+                    graph.is_fun_satisfied_by_distinct_objects(
+                        &ApiType::BareFunction,
+                        api_index,
+                        &prefix,
+                    )
+                });
+            match sequence {
+                Some(sequence) if last_call_objects(&sequence).len() >= 2 => res.push(sequence),
+                _ => {}
+            }
+        }
+        println!(
+            "{} of the {} apis taking several objects get sequences interleaving them",
+            res.len(),
+            multi_object_apis.len()
+        );
+        res
+    }
+}
+
+fn unfuzzable_param_number(graph: &ApiGraph<'_>, api_index: usize) -> usize {
+    graph.api_functions[api_index]
+        .inputs
+        .iter()
+        .filter(|input| !api_util::is_fuzzable_type(input, &graph.full_name_map, graph.cache()))
+        .count()
+}
+
+//the chains one after the other, each but the first followed by a mutator of its object, then a
+//mutator of the first object. None without chains
+fn interleave(graph: &ApiGraph<'_>, chains: &[ApiSequence]) -> Option<ApiSequence> {
+    let (first_chain, other_chains) = chains.split_first()?;
+    let mut parts = vec![first_chain.clone()];
+    for chain in other_chains {
+        parts.push(with_mutator(graph, chain, chain.len() - 1).unwrap_or_else(|| chain.clone()));
+    }
+    let merged = ApiSequence::_merge_sequences(&parts);
+    //the mutator may take another object of the same type, it is only kept on the first one
+    let first_object = first_chain.len() - 1;
+    match with_mutator(graph, &merged, first_object) {
+        Some(sequence) if last_call_objects(&sequence).contains(&first_object) => Some(sequence),
+        _ => Some(merged),
+    }
+}
+
+//the sequence with a call taking the object by `&mut` and fuzzable params otherwise, the one
+//whose dependency is the most frequent in the crate
+fn with_mutator(
+    graph: &ApiGraph<'_>,
+    sequence: &ApiSequence,
+    object: usize,
+) -> Option<ApiSequence> {
+    let producer_index = sequence.functions.get(object)?.func.1;
+    let mutator_index = graph
+        .api_dependencies
+        .iter()
+        .filter(|dependency| dependency.output_fun.1 == producer_index)
+        .filter(|dependency| {
+            let inputs = &graph.api_functions[dependency.input_fun.1].inputs;
+            api_util::_is_mutable_borrow_occurs(
+                &inputs[dependency.input_param_index],
+                &dependency.call_type,
+            ) && inputs.iter().enumerate().all(|(param_index, input)| {
+                param_index == dependency.input_param_index
+                    || api_util::is_fuzzable_type(input, &graph.full_name_map, graph.cache())
+            })
+        })
+        .max_by_key(|dependency| dependency.weight)?
+        .input_fun
+        .1;
+    graph.is_fun_satisfied(&ApiType::BareFunction, mutator_index, sequence)
+}
+
+//the returns of earlier calls the last call takes
+fn last_call_objects(sequence: &ApiSequence) -> FxHashSet<usize> {
+    let mut res = FxHashSet::default();
+    if let Some(api_call) = sequence.functions.last() {
+        for (param_type, index, _) in &api_call.params {
+            if matches!(param_type, ParamType::_FunctionReturn) {
+                res.insert(*index);
+            }
+        }
+    }
+    res
+}
@@ -0,0 +1,32 @@
+//Targets for crates that are `no_std`. With `--no-std` the target files are `#![no_std]`, so the
+//test function calling the crate only sees the core prelude, and the alloc types it may need are
+//imported from alloc. The fuzzer runtime and the decoding of the input still run on the host:
+//they get std from `extern crate std`, which also provides the panic handler and the allocator
+//of alloc. The binaries link std, so the targets check that the calls to the crate don't need
+//it, not that the crate runs without it. Replay files stay ordinary programs, they read the crash
+//file and print the decoded parameters. Panics can't be caught and the early return helpers exit
+//the process, so `--no-std` defaults to the match-both error policy and rejects
+//`--ignore-panics`.
+
+static NO_STD_ATTRIBUTE: &'static str = "#![no_std]\n";
+static NO_STD_PROLOGUE: &'static str = "extern crate alloc;
+extern crate std;
+#[allow(unused_imports)]
+use alloc::{borrow::ToOwned, boxed::Box, format, string::{String, ToString}, vec, vec::Vec};
+";
+
+//add `#![no_std]` after the inner attributes of the target file, and the prologue before its
+//first item
+pub(crate) fn to_no_std_target(file: String) -> String {
+    let mut res = String::new();
+    let mut in_header = true;
+    for line in file.split_inclusive('\n') {
+        if in_header && !line.starts_with("//") && !line.starts_with("#![") {
+            res.push_str(NO_STD_ATTRIBUTE);
+            res.push_str(NO_STD_PROLOGUE);
+            in_header = false;
+        }
+        res.push_str(line);
+    }
+    res
+}
@@ -0,0 +1,253 @@
+//Operator traits of std. Their impls are in the graph like any other trait method, but users
+//call them as `a + b`, `-a`, `x[i]` or `a < b`, so a call to `Add::add` is written with the
+//operator, which calls exactly the same impl. Ord and Hash impls are mostly used by sorting and
+//hashing, which never call them by name: with `--operator-harness`, a target whose result
+//implements Ord or Hash is followed by a variant that sorts and searches a Vec of the values of
+//the same type in the sequence, and hashes each of them.
+use crate::clean;
+use crate::fuzz_target::api_function::ApiFunction;
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::fuzz_target::code_ast::{Expr, Pat, Stmt};
+use crate::fuzz_target::sequence_ir::{IrType, SequenceIr};
+use rustc_span::symbol::sym;
+
+//crates defining the operator traits, a trait of the same name elsewhere is not an operator
+static STD_CRATES: [&'static str; 3] = ["std", "core", "alloc"];
+//trait, method and operator
+static BINARY_OPERATORS: [(&'static str, &'static str, &'static str); 10] = [
+    ("Add", "add", "+"),
+    ("Sub", "sub", "-"),
+    ("Mul", "mul", "*"),
+    ("Div", "div", "/"),
+    ("Rem", "rem", "%"),
+    ("BitAnd", "bitand", "&"),
+    ("BitOr", "bitor", "|"),
+    ("BitXor", "bitxor", "^"),
+    ("Shl", "shl", "<<"),
+    ("Shr", "shr", ">>"),
+];
+static ASSIGN_OPERATORS: [(&'static str, &'static str, &'static str); 10] = [
+    ("AddAssign", "add_assign", "+="),
+    ("SubAssign", "sub_assign", "-="),
+    ("MulAssign", "mul_assign", "*="),
+    ("DivAssign", "div_assign", "/="),
+    ("RemAssign", "rem_assign", "%="),
+    ("BitAndAssign", "bitand_assign", "&="),
+    ("BitOrAssign", "bitor_assign", "|="),
+    ("BitXorAssign", "bitxor_assign", "^="),
+    ("ShlAssign", "shl_assign", "<<="),
+    ("ShrAssign", "shr_assign", ">>="),
+];
+static COMPARISON_OPERATORS: [(&'static str, &'static str, &'static str); 6] = [
+    ("PartialEq", "eq", "=="),
+    ("PartialEq", "ne", "!="),
+    ("PartialOrd", "lt", "<"),
+    ("PartialOrd", "le", "<="),
+    ("PartialOrd", "gt", ">"),
+    ("PartialOrd", "ge", ">="),
+];
+static UNARY_OPERATORS: [(&'static str, &'static str, &'static str); 2] =
+    [("Neg", "neg", "-"), ("Not", "not", "!")];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Operator {
+    //`a + b`, both by value
+    Binary(&'static str),
+    //`a += b`, `a` is `&mut self`
+    Assign(&'static str),
+    //`a == b`, both by reference
+    Comparison(&'static str),
+    //`-a`
+    Unary(&'static str),
+    //`&a[i]` or `&mut a[i]`
+    Index { mutable: bool },
+}
+
+impl Operator {
+    //the operator an api is called with, None if it is called by its path
+    pub(crate) fn of(api_function: &ApiFunction) -> Option<Operator> {
+        let trait_path = api_function._trait_full_path.as_ref()?;
+        let mut trait_segments = trait_path.split("::");
+        if !trait_segments.next().map_or(false, |root| STD_CRATES.contains(&root)) {
+            return None;
+        }
+        let trait_name = trait_segments.last()?;
+        let method = api_function.full_name.rsplit("::").next()?;
+        let find = |operators: &[(&'static str, &'static str, &'static str)]| {
+            operators
+                .iter()
+                .find(|(name, method_name, _)| *name == trait_name && *method_name == method)
+                .map(|(_, _, operator)| *operator)
+        };
+        if let Some(operator) = find(&BINARY_OPERATORS) {
+            return Some(Operator::Binary(operator));
+        }
+        if let Some(operator) = find(&ASSIGN_OPERATORS) {
+            return Some(Operator::Assign(operator));
+        }
+        if let Some(operator) = find(&COMPARISON_OPERATORS) {
+            return Some(Operator::Comparison(operator));
+        }
+        if let Some(operator) = find(&UNARY_OPERATORS) {
+            return Some(Operator::Unary(operator));
+        }
+        match (trait_name, method) {
+            ("Index", "index") => Some(Operator::Index { mutable: false }),
+            ("IndexMut", "index_mut") => Some(Operator::Index { mutable: true }),
+            _ => None,
+        }
+    }
+
+    //the call with the arguments the api would be called with
+    pub(crate) fn to_expr(&self, mut args: Vec<Expr>) -> Option<Expr> {
+        let expected_args = if let Operator::Unary(..) = self { 1 } else { 2 };
+        if args.len() != expected_args {
+            return None;
+        }
+        let rhs = args.pop()?;
+        let lhs = args.pop();
+        let res = match (*self, lhs) {
+            (Operator::Unary(operator), None) => Expr::Unary { op: operator, expr: Box::new(rhs) },
+            (Operator::Binary(operator), Some(lhs)) => Expr::binary(lhs, operator, rhs),
+            (Operator::Assign(operator), Some(lhs)) => Expr::binary(place(lhs), operator, rhs),
+            (Operator::Comparison(operator), Some(lhs)) => {
+                Expr::binary(place(lhs), operator, place(rhs))
+            }
+            (Operator::Index { mutable }, Some(lhs)) => Expr::reference(
+                mutable,
+                Expr::Index { expr: Box::new(place(lhs)), index: Box::new(rhs) },
+            ),
+            _ => return None,
+        };
+        Some(res)
+    }
+}
+
+//the value a `&self` or `&mut self` argument refers to, `&x` is written as `x`
+fn place(expr: Expr) -> Expr {
+    match expr {
+        Expr::Ref { expr, .. } => *expr,
+        expr => Expr::deref(expr),
+    }
+}
+
+//the values after `call` are sorted if they implement Ord, and hashed if they implement Hash
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub(crate) struct OperatorHarness {
+    pub(crate) call: usize,
+    //the calls whose results have the type of the result of `call`, `call` included
+    pub(crate) operands: Vec<usize>,
+    pub(crate) sort: bool,
+    pub(crate) hash: bool,
+}
+
+impl OperatorHarness {
+    //the statements after the call, with the names the results are bound to
+    pub(crate) fn to_stmts(&self, ir: &SequenceIr) -> Vec<Stmt> {
+        let operands: Vec<Expr> = self
+            .operands
+            .iter()
+            .filter_map(|operand| ir.binding(*operand))
+            .map(|value| Expr::reference(false, Expr::path(value.name.as_str())))
+            .collect();
+        let mut res = Vec::new();
+        if self.sort && operands.len() > 1 {
+            let sorted = Expr::path("_sorted");
+            res.push(Stmt::Let {
+                pat: Pat::ident("_sorted", true),
+                init: Expr::MacroCall { name: "vec".to_string(), args: operands.clone() },
+            });
+            res.push(Stmt::Expr(Expr::method_call(sorted.clone(), "sort", Vec::new())));
+            //binary search calls `Ord::cmp`, sorting only `PartialOrd::lt`
+            let searched = Expr::reference(false, operands[0].clone());
+            res.push(Stmt::Let {
+                pat: Pat::Wild,
+                init: Expr::method_call(sorted, "binary_search", vec![searched]),
+            });
+        }
+        if self.hash {
+            res.push(Stmt::Let {
+                pat: Pat::ident("_hasher", true),
+                init: Expr::call(
+                    Expr::path("std::collections::hash_map::DefaultHasher::new"),
+                    Vec::new(),
+                ),
+            });
+            for operand in operands {
+                res.push(Stmt::Expr(Expr::call(
+                    Expr::path("std::hash::Hash::hash"),
+                    vec![operand, Expr::reference(true, Expr::path("_hasher"))],
+                )));
+            }
+            res.push(Stmt::Let {
+                pat: Pat::Wild,
+                init: Expr::call(
+                    Expr::path("std::hash::Hasher::finish"),
+                    vec![Expr::reference(false, Expr::path("_hasher"))],
+                ),
+            });
+        }
+        res
+    }
+}
+
+//each target whose result implements Ord or Hash is followed by its operator variant
+pub(crate) fn add_operator_variants(
+    api_graph: &ApiGraph<'_>,
+    sequences: Vec<ApiSequence>,
+) -> Vec<ApiSequence> {
+    if !api_graph.cx.options.operator_harness {
+        return sequences;
+    }
+    let mut res = Vec::new();
+    let mut variant_number = 0;
+    for sequence in sequences {
+        let variant = operator_variant(&sequence, api_graph);
+        res.push(sequence);
+        if let Some(variant) = variant {
+            res.push(variant);
+            variant_number += 1;
+        }
+    }
+    println!("{} operator harnesses are added", variant_number);
+    res
+}
+
+fn operator_variant(sequence: &ApiSequence, api_graph: &ApiGraph<'_>) -> Option<ApiSequence> {
+    if sequence._has_harness() || api_graph.cx.options.no_std {
+        return None;
+    }
+    let call = sequence.len().checked_sub(1)?;
+    let ir = SequenceIr::lower(sequence, api_graph, "_param", "_local");
+    let ty = match &ir.values[ir.calls[call].result?].ty {
+        IrType::Api(ty @ clean::Type::Path { .. }) => ty.clone(),
+        _ => return None,
+    };
+    //the results still alive and not mutably borrowed by other results
+    let operands: Vec<usize> = (0..=call)
+        .filter(|operand| {
+            let same_type = ir.calls[*operand].result.map_or(
+                false,
+                |value| matches!(&ir.values[value].ty, IrType::Api(value_ty) if *value_ty == ty),
+            );
+            same_type
+                && (*operand == call || !sequence._is_moved(*operand))
+                && !(0..=call)
+                    .any(|borrower| sequence._borrowed_owners(borrower).contains(&(*operand, true)))
+        })
+        .rev()
+        .collect();
+    if !operands.contains(&call) {
+        return None;
+    }
+    //sorting a single value compares nothing
+    let sort = operands.len() > 1 && api_graph.implements_trait(&ty, sym::Ord);
+    let hash = api_graph.implements_trait(&ty, sym::Hash);
+    if !sort && !hash {
+        return None;
+    }
+    let mut variant = sequence.clone();
+    variant._operator_harness = Some(OperatorHarness { call, operands, sort, hash });
+    Some(variant)
+}
@@ -0,0 +1,137 @@
+//Scaffolding to onboard the crate to OSS-Fuzz with the libfuzzer targets. `ossfuzz/` holds the
+//`project.yaml`, `Dockerfile` and `build.sh` of the project, and a cargo-fuzz crate with the
+//targets. The Dockerfile clones the repository of the package and copies the cargo-fuzz crate
+//into it as `rulf-fuzz/`, so the crate depends on the package by the path `..`. The files are
+//stubs: the contact and the sanitizers should be checked before the project is submitted.
+use crate::docfs::PathError;
+use crate::error::Error;
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::dependency;
+use crate::fuzz_target::features;
+use crate::fuzz_target::file_util::{self, FileHelper};
+use crate::fuzz_target::harness_emitter::LibFuzzerEmitter;
+use crate::fuzz_target::runtime_crate::{self, RUNTIME_CRATE};
+use crate::fuzz_target::serde_producer;
+use crate::try_err;
+use std::fs;
+use std::path::Path;
+
+static OSSFUZZ_DIR: &'static str = "ossfuzz";
+//the cargo-fuzz crate in `ossfuzz/`, and its name in the cloned repository
+static FUZZ_CRATE_DIR: &'static str = "fuzz";
+static FUZZ_DIR_IN_PROJECT: &'static str = "rulf-fuzz";
+static FUZZ_TARGETS_DIR: &'static str = "fuzz_targets";
+static BASE_IMAGE: &'static str = "gcr.io/oss-fuzz-base/base-builder-rust";
+static REPOSITORY_PLACEHOLDER: &'static str = "<repository of the crate>";
+
+pub(crate) fn write_ossfuzz(
+    api_graph: &ApiGraph<'_>,
+    helper: &FileHelper,
+    test_dir: &Path,
+) -> Result<(), Error> {
+    let lib_target = match &api_graph.cx.options.lib_target {
+        Some(lib_target) => lib_target,
+        None => {
+            println!("note: OSS-Fuzz project is not written, it needs `--manifest-path`");
+            return Ok(());
+        }
+    };
+    let project = lib_target.package_name.clone();
+    let ossfuzz_dir = test_dir.join(OSSFUZZ_DIR);
+    if ossfuzz_dir.exists() {
+        try_err!(fs::remove_dir_all(&ossfuzz_dir), &ossfuzz_dir);
+    }
+    let targets_dir = ossfuzz_dir.join(FUZZ_CRATE_DIR).join(FUZZ_TARGETS_DIR);
+    try_err!(fs::create_dir_all(&targets_dir), &targets_dir);
+
+    let forwarded_features = features::dependency_features(api_graph);
+    let mut manifest = format!("[package]\nname = \"{}-rulf-fuzz\"\n", project);
+    manifest.push_str("version = \"0.0.0\"\nedition = \"2018\"\npublish = false\n\n");
+    manifest.push_str("[package.metadata]\ncargo-fuzz = true\n\n");
+    manifest.push_str("[dependencies]\nlibfuzzer-sys = \"0.4\"\n");
+    manifest.push_str(format!("{} = {{ path = \"..\"", project).as_str());
+    if !forwarded_features.is_empty() {
+        manifest.push_str(
+            format!(", features = {}", features::toml_list(&forwarded_features)).as_str(),
+        );
+    }
+    manifest.push_str(" }\n");
+    if helper.chosen_sequences.iter().any(|sequence| sequence._async_tag) {
+        manifest.push_str(features::async_runtime_dependency(api_graph));
+        manifest.push('\n');
+    }
+    let sequences = &helper.chosen_sequences;
+    if let Some(serde_dependency) = serde_producer::serde_dependency(api_graph, sequences) {
+        manifest.push_str(serde_dependency);
+        manifest.push('\n');
+    }
+    manifest.push_str(dependency::manifest_lines(api_graph, sequences).as_str());
+    if api_graph.cx.options.runtime_crate {
+        manifest.push_str(runtime_crate::dependency(RUNTIME_CRATE).as_str());
+        let fuzz_crate_dir = ossfuzz_dir.join(FUZZ_CRATE_DIR);
+        runtime_crate::write_runtime_crate(api_graph, &helper.chosen_sequences, &fuzz_crate_dir)?;
+    }
+    let mut target_names = Vec::new();
+    let libfuzzer_files = helper.emit_files(api_graph, &LibFuzzerEmitter);
+    let naming = api_graph.cx.options.naming;
+    for (i, libfuzzer_file) in libfuzzer_files.iter().enumerate() {
+        let file_name = file_util::test_file_name(naming, "fuzz_target", &helper.target_names[i]);
+        let target_name = file_name.trim_end_matches(".rs").to_string();
+        let target_path = targets_dir.join(&file_name);
+        try_err!(fs::write(&target_path, libfuzzer_file), &target_path);
+        manifest.push_str(
+            format!(
+                "\n[[bin]]\nname = {:?}\npath = \"{}/{}\"\ntest = false\ndoc = false\n",
+                target_name, FUZZ_TARGETS_DIR, file_name
+            )
+            .as_str(),
+        );
+        target_names.push(target_name);
+    }
+    //keep the crate out of the workspace of the package
+    manifest.push_str("\n[workspace]\nmembers = [\".\"]\n");
+    write_file(&ossfuzz_dir.join(FUZZ_CRATE_DIR).join("Cargo.toml"), manifest)?;
+
+    let repository = lib_target.repository.clone().unwrap_or(REPOSITORY_PLACEHOLDER.to_string());
+    let homepage = lib_target.homepage.clone().unwrap_or(repository.clone());
+    let project_yaml = format!(
+        "homepage: {:?}\nlanguage: rust\nprimary_contact: \"\"\nmain_repo: {:?}\n\
+         sanitizers:\n  - address\nfuzzing_engines:\n  - libfuzzer\n",
+        homepage, repository
+    );
+    write_file(&ossfuzz_dir.join("project.yaml"), project_yaml)?;
+
+    let dockerfile = format!(
+        "FROM {image}\nRUN git clone --depth 1 {repository} {project}\n\
+         COPY {fuzz_crate} $SRC/{project}/{fuzz_dir}\n\
+         WORKDIR $SRC/{project}\nCOPY build.sh $SRC/\n",
+        image = BASE_IMAGE,
+        repository = repository,
+        project = project,
+        fuzz_crate = FUZZ_CRATE_DIR,
+        fuzz_dir = FUZZ_DIR_IN_PROJECT,
+    );
+    write_file(&ossfuzz_dir.join("Dockerfile"), dockerfile)?;
+
+    let build_sh = format!(
+        "#!/bin/bash -eu\ncd $SRC/{project}\ncargo fuzz build -O --fuzz-dir {fuzz_dir}\n\
+         FUZZ_TARGET_OUTPUT_DIR={fuzz_dir}/target/x86_64-unknown-linux-gnu/release\n\
+         for target in {targets}; do\n    cp $FUZZ_TARGET_OUTPUT_DIR/$target $OUT/\ndone\n",
+        project = project,
+        fuzz_dir = FUZZ_DIR_IN_PROJECT,
+        targets = target_names.join(" "),
+    );
+    write_file(&ossfuzz_dir.join("build.sh"), build_sh)?;
+    if lib_target.repository.is_none() {
+        println!(
+            "note: the package has no `repository`, fill it in the Dockerfile and project.yaml"
+        );
+    }
+    println!("OSS-Fuzz project is written to {}", ossfuzz_dir.display());
+    Ok(())
+}
+
+fn write_file(path: &Path, content: String) -> Result<(), Error> {
+    try_err!(fs::write(path, content), path);
+    Ok(())
+}
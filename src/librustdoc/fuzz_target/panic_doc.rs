@@ -0,0 +1,220 @@
+//Preconditions documented in the `# Panics` section of the apis. Many crashes found by the
+//targets are just panics the api documents, e.g. "Panics if `index > len`". A precondition
+//written as a condition on the parameters is turned into a guard that returns before the call
+//when the condition holds. Other preconditions can't be checked, and the targets calling such
+//apis are tagged as expected to panic, in the target file and in the report.
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::fuzz_target::code_ast::Expr;
+
+static PANICS_HEADING: &'static str = "panics";
+static COMPARISON_OPERATORS: [&'static str; 6] = ["==", "!=", "<=", ">=", "<", ">"];
+//words of the condition that are not parameters
+static KEYWORDS: [&'static str; 3] = ["true", "false", "as"];
+
+#[derive(Debug, Clone)]
+pub(crate) struct PanicPrecondition {
+    //the section joined into one line
+    pub(crate) text: String,
+    //the api panics if any of the conditions holds
+    pub(crate) guards: Vec<Vec<GuardPiece>>,
+    //every sentence of the section has a guard, so the call doesn't panic as documented
+    pub(crate) complete: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum GuardPiece {
+    Code(String),
+    //the argument passed for the parameter
+    Param(usize),
+}
+
+impl PanicPrecondition {
+    //None if the doc has no `# Panics` section. `float_params` tells which parameters are floats,
+    //whose zero is `0.0`
+    pub(crate) fn parse(
+        doc: &str,
+        param_names: &Vec<String>,
+        float_params: &Vec<bool>,
+    ) -> Option<Self> {
+        let text = panics_section(doc)?;
+        let mut guards = Vec::new();
+        let mut complete = true;
+        for sentence in text.split(". ") {
+            let mut guarded = false;
+            //the code spans are the odd parts, each followed by the text after it
+            let parts: Vec<&str> = sentence.split('`').collect();
+            for k in (1..parts.len()).step_by(2) {
+                let span = parts[k];
+                let condition = if COMPARISON_OPERATORS.iter().any(|op| span.contains(op)) {
+                    Some(span.to_string())
+                } else {
+                    //"if `x` is zero", "if `x` is empty"
+                    let rest = parts.get(k + 1).map_or("", |rest| rest.trim_start());
+                    if rest.starts_with("is zero") || rest.starts_with("is 0") {
+                        zero_of(span, param_names, float_params)
+                            .map(|zero| format!("{} == {}", span, zero))
+                    } else if rest.starts_with("is empty") {
+                        Some(format!("{}.is_empty()", span))
+                    } else {
+                        None
+                    }
+                };
+                if let Some(guard) = condition.and_then(|c| guard_pieces(&c, param_names)) {
+                    guarded = true;
+                    if !guards.contains(&guard) {
+                        guards.push(guard);
+                    }
+                }
+            }
+            complete &= guarded;
+        }
+        Some(PanicPrecondition { text, guards, complete })
+    }
+}
+
+//the zero literal of the type of `span`, `0.0` for a float parameter. None for an expression using
+//a float parameter, its type is not known
+fn zero_of(
+    span: &str,
+    param_names: &Vec<String>,
+    float_params: &Vec<bool>,
+) -> Option<&'static str> {
+    let is_float = |index: usize| float_params.get(index).copied().unwrap_or(false);
+    if let Some(index) = param_names.iter().position(|name| name == span.trim()) {
+        return Some(if is_float(index) { "0.0" } else { "0" });
+    }
+    let uses_float = span
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter_map(|word| param_names.iter().position(|name| name == word))
+        .any(is_float);
+    if uses_float { None } else { Some("0") }
+}
+
+//`cond1 || cond2` with the arguments of the call, None if there is no guard
+pub(crate) fn guard_expr(guards: &Vec<Vec<GuardPiece>>, args: &Vec<Expr>) -> Option<Expr> {
+    let conditions: Vec<String> = guards
+        .iter()
+        .filter_map(|guard| {
+            let mut res = String::new();
+            for piece in guard {
+                match piece {
+                    GuardPiece::Code(code) => res.push_str(code),
+                    //the condition talks about the value, not the reference passed
+                    GuardPiece::Param(index) => {
+                        let mut arg = args.get(*index)?;
+                        while let Expr::Ref { expr, .. } = arg {
+                            arg = &**expr;
+                        }
+                        res.push_str(format!("({})", arg.to_code(0)).as_str());
+                    }
+                }
+            }
+            Some(format!("({})", res))
+        })
+        .collect();
+    if conditions.is_empty() {
+        return None;
+    }
+    Some(Expr::Raw(conditions.join(" || ")))
+}
+
+//apis of the sequence whose documented panics are not all guarded, with the precondition
+pub(crate) fn expected_panics(sequence: &ApiSequence, api_graph: &ApiGraph<'_>) -> Vec<String> {
+    let mut res = Vec::new();
+    for api_call in &sequence.functions {
+        let full_name = &api_graph.api_functions[api_call.func.1].full_name;
+        if let Some(precondition) = api_graph.api_panics.get(full_name) {
+            if !precondition.complete || !api_graph.cx.options.panic_policy.guards() {
+                let expected_panic = format!("{}: {}", full_name, precondition.text);
+                if !res.contains(&expected_panic) {
+                    res.push(expected_panic);
+                }
+            }
+        }
+    }
+    res
+}
+
+//the lines after a `# Panics` heading, up to the next heading. Lines of code blocks may start
+//with `#` as well
+fn panics_section(doc: &str) -> Option<String> {
+    let mut lines = Vec::new();
+    let mut found = false;
+    let mut in_section = false;
+    let mut in_code_block = false;
+    for line in doc.lines() {
+        let line = line.trim();
+        if line.starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if !in_code_block && line.starts_with('#') {
+            if in_section {
+                break;
+            }
+            in_section = line.trim_start_matches('#').trim().to_lowercase() == PANICS_HEADING;
+            found |= in_section;
+            continue;
+        }
+        if in_section && !in_code_block && !line.is_empty() {
+            lines.push(line);
+        }
+    }
+    if !found || lines.is_empty() {
+        return None;
+    }
+    Some(lines.join(" "))
+}
+
+//split the condition into code and parameters. None if the condition uses a name that is not a
+//parameter, e.g. a local variable of the api
+pub(crate) fn guard_pieces(condition: &str, param_names: &Vec<String>) -> Option<Vec<GuardPiece>> {
+    let mut res = Vec::new();
+    let mut code = String::new();
+    let mut chars = condition.char_indices().peekable();
+    let mut uses_param = false;
+    //the word after `as` is a type, e.g. `index as usize`
+    let mut after_as = false;
+    while let Some((start, c)) = chars.next() {
+        if !(c.is_alphabetic() || c == '_') {
+            code.push(c);
+            continue;
+        }
+        let mut end = start + c.len_utf8();
+        while let Some((i, c)) = chars.peek() {
+            if !(c.is_alphanumeric() || *c == '_') {
+                break;
+            }
+            end = i + c.len_utf8();
+            chars.next();
+        }
+        let word = &condition[start..end];
+        let rest = &condition[end..];
+        //methods, fields, paths like `usize::MAX` and suffixes like `1u8`
+        let is_root = !code.ends_with('.')
+            && !code.ends_with("::")
+            && !code.ends_with(|c: char| c.is_ascii_digit());
+        let is_cast_type = after_as;
+        after_as = word == "as";
+        if !is_root || is_cast_type || rest.starts_with("::") || KEYWORDS.contains(&word) {
+            code.push_str(word);
+            continue;
+        }
+        match param_names.iter().position(|name| name == word) {
+            Some(index) => {
+                if !code.is_empty() {
+                    res.push(GuardPiece::Code(code.clone()));
+                    code.clear();
+                }
+                res.push(GuardPiece::Param(index));
+                uses_param = true;
+            }
+            None => return None,
+        }
+    }
+    if !code.is_empty() {
+        res.push(GuardPiece::Code(code));
+    }
+    if uses_param { Some(res) } else { None }
+}
@@ -0,0 +1,163 @@
+//Bounds of the integer parameters stated in the doc of the apis, e.g. "`level` must be between 0
+//and 9", "`n` must be in the range `1..=64`" or "`width` must be at least 1". An argument out of
+//its bounds is usually rejected before the api does any work, so most of the random arguments
+//would only test the rejection. The arguments of such parameters are clamped into the bounds.
+use crate::clean::{self, PrimitiveType};
+use crate::fuzz_target::code_ast::Expr;
+
+//words before the bound, "`n` must be less than 10"
+static MODAL_PREFIXES: [&'static str; 6] =
+    ["must be ", "should be ", "needs to be ", "has to be ", "is expected to be ", "is "];
+//the bound is the number after the phrase, inclusive or not
+static MIN_PHRASES: [(&'static str, bool); 5] = [
+    ("greater than or equal to ", true),
+    ("at least ", true),
+    ("no less than ", true),
+    ("not less than ", true),
+    ("greater than ", false),
+];
+static MAX_PHRASES: [(&'static str, bool); 6] = [
+    ("less than or equal to ", true),
+    ("at most ", true),
+    ("no greater than ", true),
+    ("not greater than ", true),
+    ("no more than ", true),
+    ("less than ", false),
+];
+static RANGE_PHRASES: [&'static str; 4] = ["in the range ", "in range ", "within ", "in "];
+
+//the inclusive bounds of a parameter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ParamBound {
+    pub(crate) param: usize,
+    pub(crate) min: Option<i128>,
+    pub(crate) max: Option<i128>,
+}
+
+//the bounds of the parameters named in the doc, the bounds of a parameter in several sentences
+//are merged
+pub(crate) fn parse(doc: &str, param_names: &Vec<String>) -> Vec<ParamBound> {
+    let text = doc.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut res: Vec<ParamBound> = Vec::new();
+    for sentence in text.split(". ") {
+        //the code spans are the odd parts
+        let parts: Vec<&str> = sentence.split('`').collect();
+        for k in (1..parts.len()).step_by(2) {
+            let param = match param_names.iter().position(|name| name == parts[k]) {
+                Some(param) => param,
+                None => continue,
+            };
+            //the numbers and ranges after the name may be code spans too
+            let rest = parts[k + 1..].concat().to_lowercase();
+            let (min, max) = match stated_bounds(rest.trim_start()) {
+                Some(bounds) => bounds,
+                None => continue,
+            };
+            match res.iter_mut().find(|bound| bound.param == param) {
+                Some(bound) => {
+                    bound.min = min.or(bound.min);
+                    bound.max = max.or(bound.max);
+                }
+                None => res.push(ParamBound { param, min, max }),
+            }
+        }
+    }
+    res
+}
+
+//clamp the arguments of the integer parameters into their bounds, e.g. `_param0.clamp(1, 64)`.
+//The arguments are the ones of a call, or the operands of an operator
+pub(crate) fn clamp_args(
+    bounds: &Vec<ParamBound>,
+    inputs: &Vec<clean::Type>,
+    mut args: Vec<&mut Expr>,
+) {
+    for bound in bounds {
+        let (type_min, type_max) = match inputs.get(bound.param) {
+            Some(clean::Type::Primitive(primitive)) => match integer_range(*primitive) {
+                Some(range) => range,
+                None => continue,
+            },
+            _ => continue,
+        };
+        let arg: &mut Expr = match args.get_mut(bound.param) {
+            Some(arg) if matches!(**arg, Expr::Path(..)) => arg,
+            _ => continue,
+        };
+        //bounds the type can't hold are dropped, the literals wouldn't compile
+        let min = bound.min.filter(|min| *min > type_min && *min <= type_max);
+        let max = bound.max.filter(|max| *max >= type_min && *max < type_max);
+        let lit = |value: i128| Expr::Lit(value.to_string());
+        let clamped = match (min, max) {
+            (Some(min), Some(max)) if min <= max => {
+                Expr::method_call(arg.clone(), "clamp", vec![lit(min), lit(max)])
+            }
+            (Some(min), None) => Expr::method_call(arg.clone(), "max", vec![lit(min)]),
+            (None, Some(max)) => Expr::method_call(arg.clone(), "min", vec![lit(max)]),
+            _ => continue,
+        };
+        *arg = clamped;
+    }
+}
+
+//the inclusive bounds after the name of the parameter
+fn stated_bounds(rest: &str) -> Option<(Option<i128>, Option<i128>)> {
+    let rest = MODAL_PREFIXES.iter().find_map(|prefix| rest.strip_prefix(prefix)).unwrap_or(rest);
+    //"between 1 and 64"
+    if let Some(rest) = rest.strip_prefix("between ") {
+        let (min, rest) = leading_integer(rest)?;
+        let (max, _) = leading_integer(rest.trim_start().strip_prefix("and ")?)?;
+        return Some((Some(min), Some(max)));
+    }
+    for (phrase, inclusive) in MIN_PHRASES {
+        if let Some(rest) = rest.strip_prefix(phrase) {
+            let (min, _) = leading_integer(rest)?;
+            return Some((Some(if inclusive { min } else { min.saturating_add(1) }), None));
+        }
+    }
+    for (phrase, inclusive) in MAX_PHRASES {
+        if let Some(rest) = rest.strip_prefix(phrase) {
+            let (max, _) = leading_integer(rest)?;
+            return Some((None, Some(if inclusive { max } else { max.saturating_sub(1) })));
+        }
+    }
+    //"in the range 1..=64", "in 0..10"
+    let rest = RANGE_PHRASES.iter().find_map(|phrase| rest.strip_prefix(phrase))?;
+    let (min, rest) = leading_integer(rest)?;
+    let rest = rest.strip_prefix("..")?;
+    let (rest, inclusive) = match rest.strip_prefix('=') {
+        Some(rest) => (rest, true),
+        None => (rest, false),
+    };
+    let (max, _) = leading_integer(rest)?;
+    Some((Some(min), Some(if inclusive { max } else { max.saturating_sub(1) })))
+}
+
+//a decimal integer at the start, and the text after it
+fn leading_integer(text: &str) -> Option<(i128, &str)> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let end = digits.find(|c: char| !(c.is_ascii_digit() || c == '_')).unwrap_or(digits.len());
+    let value = digits[..end].replace('_', "").parse::<i128>().ok()?;
+    Some((if negative { -value } else { value }, &digits[end..]))
+}
+
+//the values of an integer type, usize and isize are taken as 64 bits
+fn integer_range(primitive: PrimitiveType) -> Option<(i128, i128)> {
+    let range = match primitive {
+        PrimitiveType::U8 => (0, u8::MAX as i128),
+        PrimitiveType::U16 => (0, u16::MAX as i128),
+        PrimitiveType::U32 => (0, u32::MAX as i128),
+        PrimitiveType::U64 | PrimitiveType::Usize => (0, u64::MAX as i128),
+        PrimitiveType::U128 => (0, i128::MAX),
+        PrimitiveType::I8 => (i8::MIN as i128, i8::MAX as i128),
+        PrimitiveType::I16 => (i16::MIN as i128, i16::MAX as i128),
+        PrimitiveType::I32 => (i32::MIN as i128, i32::MAX as i128),
+        PrimitiveType::I64 | PrimitiveType::Isize => (i64::MIN as i128, i64::MAX as i128),
+        PrimitiveType::I128 => (i128::MIN, i128::MAX),
+        _ => return None,
+    };
+    Some(range)
+}
@@ -6,6 +6,7 @@
 use crate::fuzz_target::impl_util::FullNameMap;
 use lazy_static::lazy_static;
 use rustc_data_structures::fx::{FxHashMap, FxHashSet};
+use rustc_hir::Mutability;
 
 lazy_static! {
     static ref PRELUDED_TYPE: FxHashMap<&'static str, &'static str> = {
@@ -13,7 +14,12 @@
         m.insert("core::option::Option", "Option");
         m.insert("core::result::Result", "Result");
         m.insert("alloc::string::String", "String");
-        //m.insert("alloc::boxed::Box", "Box");
+        m.insert("alloc::boxed::Box", "Box");
+        m.insert("alloc::rc::Rc", "Rc");
+        m.insert("alloc::sync::Arc", "Arc");
+        m.insert("core::cell::Cell", "Cell");
+        m.insert("core::cell::RefCell", "RefCell");
+        m.insert("alloc::borrow::Cow", "Cow");
         m
     };
 }
@@ -60,14 +66,64 @@ pub(crate) fn to_strip_type_name(type_name: &String) -> String {
     }
 }
 
+//智能指针和Cell，以及借用值的Cow，参数可以由里面的值构造
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub(crate) enum Wrapper {
+    Box,
+    Rc,
+    Arc,
+    Cell,
+    RefCell,
+    Cow,
+}
+
+impl Wrapper {
+    fn from_name(strip_type_name: &str) -> Option<Self> {
+        match strip_type_name {
+            "Box" => Some(Wrapper::Box),
+            "Rc" => Some(Wrapper::Rc),
+            "Arc" => Some(Wrapper::Arc),
+            "Cell" => Some(Wrapper::Cell),
+            "RefCell" => Some(Wrapper::RefCell),
+            "Cow" => Some(Wrapper::Cow),
+            _ => None,
+        }
+    }
+
+    //由里面的值构造，Cow借用值
+    pub(crate) fn constructor(&self) -> &'static str {
+        match self {
+            Wrapper::Box => "Box::new",
+            Wrapper::Rc => "std::rc::Rc::new",
+            Wrapper::Arc => "std::sync::Arc::new",
+            Wrapper::Cell => "std::cell::Cell::new",
+            Wrapper::RefCell => "std::cell::RefCell::new",
+            Wrapper::Cow => "std::borrow::Cow::Borrowed",
+        }
+    }
+
+    pub(crate) fn moves_value(&self) -> bool {
+        *self != Wrapper::Cow
+    }
+
+    //里面的值可以通过`&*x`借用，Cell和RefCell不行
+    pub(crate) fn derefs(&self) -> bool {
+        match self {
+            Wrapper::Box | Wrapper::Rc | Wrapper::Arc | Wrapper::Cow => true,
+            Wrapper::Cell | Wrapper::RefCell => false,
+        }
+    }
+}
+
 //TODO:目前只考虑引用、裸指针的情况，元组，切片，数组都暂时不考虑
-//暂时只考虑Result和Option
-//TODO:Box,...
+//暂时只考虑Result和Option，以及Box、Rc等包装类型
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub(crate) enum PreludeType {
     NotPrelude(clean::Type),
     PreludeOption(clean::Type),
     PreludeResult { ok_type: clean::Type, err_type: clean::Type },
+    //包装的类型，Cow<B>是借用的&B
+    PreludeWrapper(Wrapper, clean::Type),
 }
 
 impl PreludeType {
@@ -87,6 +143,8 @@ pub(crate) fn from_type(
                         extract_option(path, type_)
                     } else if _RESULT == strip_type_name {
                         extract_result(path, type_)
+                    } else if let Some(wrapper) = Wrapper::from_name(strip_type_name) {
+                        extract_wrapper(wrapper, path, type_)
                     } else {
                         //println!("other prelude type");
                         PreludeType::NotPrelude(type_.clone())
@@ -111,13 +169,30 @@ pub(crate) fn _to_type_name(&self, full_name_map: &FullNameMap, cache: &Cache) -
                 let err_type_name = api_util::_type_name(err_type, full_name_map, cache);
                 format!("Result<{}, {}>", ok_type_name, err_type_name)
             }
+            PreludeType::PreludeWrapper(wrapper, type_) => {
+                let inner_type = match (wrapper, type_) {
+                    (Wrapper::Cow, clean::Type::BorrowedRef { type_, .. }) => &**type_,
+                    _ => type_,
+                };
+                let inner_type_name = api_util::_type_name(inner_type, full_name_map, cache);
+                format!("{:?}<{}>", wrapper, inner_type_name)
+            }
         }
     }
 
     pub(crate) fn _is_final_type(&self) -> bool {
         match self {
             PreludeType::NotPrelude(..) => true,
-            PreludeType::PreludeResult { .. } | PreludeType::PreludeOption(..) => false,
+            PreludeType::PreludeResult { .. }
+            | PreludeType::PreludeOption(..)
+            | PreludeType::PreludeWrapper(..) => false,
+        }
+    }
+
+    pub(crate) fn _is_wrapper(&self) -> bool {
+        match self {
+            PreludeType::PreludeWrapper(..) => true,
+            _ => false,
         }
     }
 
@@ -130,6 +205,7 @@ pub(crate) fn _get_final_type(&self) -> clean::Type {
                 //Result只取ok的那部分
                 ok_type.clone()
             }
+            PreludeType::PreludeWrapper(_, type_) => type_.clone(),
         }
     }
 
@@ -143,6 +219,8 @@ pub(crate) fn _unwrap_call_type(&self, inner_call_type: &CallType) -> CallType {
             PreludeType::PreludeResult { .. } => {
                 CallType::_UnwrapResult(Box::new(inner_call_type.clone()))
             }
+            //不能从Rc等类型中move出里面的值，只能通过引用借用，见api_util::_borrowed_ref_in_same_type
+            PreludeType::PreludeWrapper(..) => CallType::_NotCompatible,
         }
     }
 
@@ -155,6 +233,9 @@ pub(crate) fn _to_call_type(&self, inner_call_type: &CallType) -> CallType {
             PreludeType::PreludeResult { .. } => {
                 CallType::_ToResult(Box::new(inner_call_type.clone()))
             }
+            PreludeType::PreludeWrapper(wrapper, _) => {
+                CallType::_ToWrapper(Box::new(inner_call_type.clone()), *wrapper)
+            }
         }
     }
 }
@@ -205,6 +286,30 @@ fn extract_result(path: &clean::Path, type_: &clean::Type) -> PreludeType {
     return PreludeType::NotPrelude(type_.clone());
 }
 
+fn extract_wrapper(wrapper: Wrapper, path: &clean::Path, type_: &clean::Type) -> PreludeType {
+    let args = match path.segments.last().map(|segment| &segment.args) {
+        Some(clean::GenericArgs::AngleBracketed { args, .. }) => args,
+        _ => return PreludeType::NotPrelude(type_.clone()),
+    };
+    //Cow<'a, B>的第一个参数是生命周期
+    let inner_type = args.iter().find_map(|arg| match arg {
+        clean::GenericArg::Type(inner_type) => Some(inner_type.clone()),
+        _ => None,
+    });
+    match (wrapper, inner_type) {
+        (Wrapper::Cow, Some(inner_type)) => PreludeType::PreludeWrapper(
+            wrapper,
+            clean::Type::BorrowedRef {
+                lifetime: None,
+                mutability: Mutability::Not,
+                type_: Box::new(inner_type),
+            },
+        ),
+        (_, Some(inner_type)) => PreludeType::PreludeWrapper(wrapper, inner_type),
+        (_, None) => PreludeType::NotPrelude(type_.clone()),
+    }
+}
+
 pub(crate) fn _prelude_type_need_special_dealing(
     type_: &clean::Type,
     full_name_map: &FullNameMap,
@@ -228,9 +333,16 @@ pub(crate) enum _PreludeHelper {
 impl _PreludeHelper {
     pub(crate) fn _from_call_type(call_type: &CallType) -> FxHashSet<_PreludeHelper> {
         match call_type {
-            CallType::_DirectCall | CallType::_NotCompatible | CallType::_AsConvert(_) => {
-                FxHashSet::default()
-            }
+            CallType::_DirectCall
+            | CallType::_NotCompatible
+            | CallType::_AsConvert(_)
+            | CallType::_ToTextCollection(_)
+            | CallType::_ScratchBuffer(_) => FxHashSet::default(),
+            //元素的转换不会unwrap
+            CallType::_ToCollection(_, element_call_types) => element_call_types
+                .iter()
+                .flat_map(|call_type| _PreludeHelper::_from_call_type(call_type))
+                .collect(),
             CallType::_BorrowedRef(inner_call_type)
             | CallType::_ConstRawPointer(inner_call_type, _)
             | CallType::_MutBorrowedRef(inner_call_type)
@@ -238,7 +350,17 @@ pub(crate) fn _from_call_type(call_type: &CallType) -> FxHashSet<_PreludeHelper>
             | CallType::_Deref(inner_call_type)
             | CallType::_ToOption(inner_call_type)
             | CallType::_ToResult(inner_call_type)
-            | CallType::_UnsafeDeref(inner_call_type) => {
+            | CallType::_ToWrapper(inner_call_type, _)
+            | CallType::_UnsafeDeref(inner_call_type)
+            | CallType::_AsRef(inner_call_type)
+            | CallType::_Into(inner_call_type)
+            | CallType::_TryInto(inner_call_type)
+            | CallType::_Iter(inner_call_type)
+            | CallType::_IntoIter(inner_call_type)
+            | CallType::_ToIo(inner_call_type, _)
+            | CallType::_ToStdValue(inner_call_type, _)
+            | CallType::_CollectSlice(inner_call_type)
+            | CallType::_CollectIter(inner_call_type) => {
                 _PreludeHelper::_from_call_type(&**inner_call_type)
             }
             CallType::_UnwrapOption(inner_call_type) => {
@@ -0,0 +1,269 @@
+//Property tests of the targets, with `--emit-harness proptest`, for the projects that can't run
+//AFL or cargo-fuzz in their CI. Every target becomes a `proptest!` test calling its test function,
+//and its fuzzable params are drawn by strategies derived from their types instead of being split
+//from the fuzz data, so a failing case is shrunk to small values. Drawn values must be `Debug`, so
+//they are std types: enums and structs are drawn as the tuple of their fields and built in the
+//test. The targets whose params have no strategy, e.g. an enum with too many variants for a tuple,
+//draw the bytes of the fuzz data and run the closure of the AFL target. The files go to `tests/`
+//next to the Cargo.toml of the targets, which has proptest in its dev-dependencies, so they run
+//with `cargo test`. In the workspace of `--workspace`, every target crate has its test.
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::fuzz_target::code_ast::{Block, Expr, Pat, Stmt};
+use crate::fuzz_target::fuzzable_type::FuzzableType;
+use crate::fuzz_target::harness_emitter::{HarnessEmitter, TESTS_DIR};
+
+//the most elements of a drawn slice, and bytes of the fuzz data
+static MAX_COLLECTION_LEN: usize = 64;
+//proptest implements `Strategy` for tuples of at most 12 strategies
+static MAX_TUPLE_LEN: usize = 12;
+static TEST_INDENT: usize = 4;
+
+pub(crate) struct ProptestEmitter;
+
+impl HarnessEmitter for ProptestEmitter {
+    fn name(&self) -> &'static str {
+        "proptest"
+    }
+
+    fn dir_name(&self) -> &'static str {
+        TESTS_DIR
+    }
+
+    fn file_prefix(&self) -> &'static str {
+        "proptest"
+    }
+
+    //the tests are not bins
+    fn is_bin(&self) -> bool {
+        false
+    }
+
+    fn dev_dependency(&self) -> Option<&'static str> {
+        Some("proptest = \"1\"")
+    }
+
+    fn emit(&self, sequence: &ApiSequence, api_graph: &ApiGraph<'_>, index: usize) -> String {
+        let mut res = String::from("extern crate proptest;\n");
+        res.push_str(sequence._extern_crates(api_graph).as_str());
+        res.push_str("use proptest::prelude::*;\n\n");
+        if let Some(prelude_functions) = sequence._prelude_helper_functions(api_graph) {
+            res.push_str(prelude_functions.as_str());
+        }
+        let (inputs, body) = match drawn_params(sequence, index) {
+            Some(drawn) => drawn,
+            None => {
+                //the closure decodes the fuzz data with the helpers of the AFL target
+                if let Some(afl_functions) = sequence._afl_helper_functions(api_graph) {
+                    res.push_str(afl_functions.as_str());
+                }
+                fuzz_data_input(sequence, api_graph, index)
+            }
+        };
+        res.push_str(sequence._to_well_written_function(api_graph, index, 0).as_str());
+        res.push('\n');
+        //the test function is only named in its own file, the index is enough
+        let test_name = format!("{}_{}{}", self.file_prefix(), api_graph._crate_name, index);
+        res.push_str("proptest! {\n");
+        res.push_str(format!("{}#[test]\n", " ".repeat(TEST_INDENT)).as_str());
+        res.push_str(
+            format!(
+                "{}fn {}({}) {}\n",
+                " ".repeat(TEST_INDENT),
+                test_name,
+                inputs.join(", "),
+                body.to_code(TEST_INDENT)
+            )
+            .as_str(),
+        );
+        res.push_str("}\n");
+        res
+    }
+}
+
+//the inputs of the test, e.g. `_input0 in any::<u8>()`, and the body building the params from them
+//and calling the test function. None if a param has no strategy
+fn drawn_params(sequence: &ApiSequence, test_index: usize) -> Option<(Vec<String>, Block)> {
+    let mut inputs = Vec::new();
+    let mut body = Block::new();
+    let mut args = Vec::new();
+    for (i, fuzzable_param) in sequence.fuzzable_params.iter().enumerate() {
+        let input_name = format!("_input{}", i);
+        let param_name = format!("_param{}", i);
+        let (strategy, value) =
+            strategy(fuzzable_param, input_name.as_str(), param_name.as_str(), &mut body)?;
+        inputs.push(format!("{} in {}", input_name, strategy));
+        body.push(Stmt::Let { pat: Pat::ident(param_name.as_str(), false), init: value });
+        args.push(Expr::Path(param_name));
+    }
+    let test_function = Expr::Path(format!("test_function{}", test_index));
+    body.push(Stmt::Expr(Expr::call(test_function, args)));
+    Some((inputs, body))
+}
+
+//draw the bytes the AFL target would get and run its closure on them
+fn fuzz_data_input(
+    sequence: &ApiSequence,
+    api_graph: &ApiGraph<'_>,
+    test_index: usize,
+) -> (Vec<String>, Block) {
+    let input =
+        format!("data in proptest::collection::vec(any::<u8>(), 0..={})", MAX_COLLECTION_LEN);
+    let mut body = Block::new();
+    body.push(Stmt::Let {
+        pat: Pat::ident("target", false),
+        init: sequence._fuzz_closure(api_graph, test_index),
+    });
+    body.push(Stmt::Expr(Expr::call(
+        Expr::path("target"),
+        vec![Expr::reference(false, Expr::path("data"))],
+    )));
+    (vec![input], body)
+}
+
+//the strategy of the type, and the expression turning the value `drawn` by it into a value of the
+//type. `&str` and slices borrow from `drawn`, slices of built elements from a vector declared by a
+//statement added to `body`, named after `name`
+fn strategy(
+    ty: &FuzzableType,
+    drawn: &str,
+    name: &str,
+    body: &mut Block,
+) -> Option<(String, Expr)> {
+    let res = match ty {
+        FuzzableType::NoFuzzable => return None,
+        FuzzableType::Primitive(..) => {
+            (format!("any::<{}>()", ty._to_type_string()), Expr::path(drawn))
+        }
+        FuzzableType::RefStr => (
+            "any::<String>()".to_string(),
+            Expr::method_call(Expr::path(drawn), "as_str", Vec::new()),
+        ),
+        FuzzableType::RefSlice(inner_type) => {
+            let index = format!("{}_index", name);
+            let element = format!("{}[{}]", drawn, index);
+            let (element_strategy, element_value) =
+                fixed_strategy(inner_type, element.as_str(), format!("{}_element", name).as_str())?;
+            let strategy = format!(
+                "proptest::collection::vec({}, 0..={})",
+                element_strategy, MAX_COLLECTION_LEN
+            );
+            //the elements drawn are the elements of the slice
+            if element_value.to_code(0) == element {
+                return Some((strategy, Expr::Raw(format!("&{}[..]", drawn))));
+            }
+            let elements = format!("{}_elements", name);
+            body.push(Stmt::Let {
+                pat: Pat::ident(elements.as_str(), false),
+                init: Expr::Raw(format!(
+                    "(0..{}.len()).map(|{}| {}).collect::<Vec<_>>()",
+                    drawn,
+                    index,
+                    element_value.to_code(0)
+                )),
+            });
+            (strategy, Expr::Raw(format!("&{}[..]", elements)))
+        }
+        FuzzableType::Tuple(inner_types) => {
+            let mut strategies = Vec::new();
+            let mut values = Vec::new();
+            for (k, inner_type) in inner_types.iter().enumerate() {
+                let (inner_strategy, inner_value) = strategy(
+                    inner_type,
+                    format!("{}.{}", drawn, k).as_str(),
+                    format!("{}_{}", name, k).as_str(),
+                    body,
+                )?;
+                strategies.push(inner_strategy);
+                values.push(inner_value.to_code(0));
+            }
+            (tuple_strategy(strategies)?, Expr::Raw(tuple(values)))
+        }
+        FuzzableType::Array(inner_type, length) => {
+            let index = format!("{}_index", name);
+            let element = format!("{}[{}]", drawn, index);
+            let (element_strategy, element_value) =
+                fixed_strategy(inner_type, element.as_str(), format!("{}_element", name).as_str())?;
+            (
+                format!("proptest::collection::vec({}, {})", element_strategy, length),
+                Expr::Raw(format!(
+                    "core::array::from_fn::<_, {}, _>(|{}| {})",
+                    length,
+                    index,
+                    element_value.to_code(0)
+                )),
+            )
+        }
+        FuzzableType::Enum(full_name, variants) => {
+            //(0..n, (fields of variant 0), (fields of variant 1), ..), the first element chooses
+            //the variant
+            let mut strategies = vec![format!("0..{}u8", variants.len())];
+            let mut arms = Vec::new();
+            for (k, variant) in variants.iter().enumerate() {
+                let (fields_strategy, fields) = fields(
+                    &variant.fields,
+                    format!("{}.{}", drawn, k + 1).as_str(),
+                    format!("{}_{}", name, k).as_str(),
+                )?;
+                strategies.push(fields_strategy);
+                let path = format!("{}::{}", full_name, variant.name);
+                let constructor = variant._to_constructor(&path, &fields);
+                let pattern = if k == variants.len() - 1 { "_".to_string() } else { k.to_string() };
+                arms.push(format!("{} => {}", pattern, constructor));
+            }
+            (
+                tuple_strategy(strategies)?,
+                Expr::Raw(format!("match {}.0 {{ {} }}", drawn, arms.join(", "))),
+            )
+        }
+        FuzzableType::Struct(variant) => {
+            let (fields_strategy, fields) = fields(&variant.fields, drawn, name)?;
+            (fields_strategy, Expr::Raw(variant._to_constructor(&variant.name, &fields)))
+        }
+    };
+    Some(res)
+}
+
+//elements of arrays and fields are built in closures and match arms, so they can't borrow from
+//vectors declared in the test
+fn fixed_strategy(ty: &FuzzableType, drawn: &str, name: &str) -> Option<(String, Expr)> {
+    if !ty._is_fixed_length() {
+        return None;
+    }
+    let mut unused_body = Block::new();
+    strategy(ty, drawn, name, &mut unused_body)
+}
+
+//the fields of a variant or struct are drawn as a tuple
+fn fields(
+    field_types: &Vec<FuzzableType>,
+    drawn: &str,
+    name: &str,
+) -> Option<(String, Vec<String>)> {
+    let mut strategies = Vec::new();
+    let mut values = Vec::new();
+    for (j, field_type) in field_types.iter().enumerate() {
+        let (field_strategy, field_value) = fixed_strategy(
+            field_type,
+            format!("{}.{}", drawn, j).as_str(),
+            format!("{}_{}", name, j).as_str(),
+        )?;
+        strategies.push(field_strategy);
+        values.push(field_value.to_code(0));
+    }
+    Some((tuple_strategy(strategies)?, values))
+}
+
+fn tuple_strategy(strategies: Vec<String>) -> Option<String> {
+    match strategies.len() {
+        0 => Some("Just(())".to_string()),
+        len if len > MAX_TUPLE_LEN => None,
+        _ => Some(tuple(strategies)),
+    }
+}
+
+fn tuple(elements: Vec<String>) -> String {
+    //a tuple of one element needs a trailing comma
+    let trailing_comma = if elements.len() == 1 { "," } else { "" };
+    format!("({}{})", elements.join(", "), trailing_comma)
+}
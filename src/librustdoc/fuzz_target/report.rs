@@ -0,0 +1,409 @@
+//Write a machine readable report(rulf-report.json) of the generation result,
+//so that downstream tools don't need to scrape the printed messages.
+//Every api that is not covered gets a suggestion of what would unblock it, and the suggestions
+//are also printed, ordered by the number of apis they would unblock.
+use crate::clean::{self, GenericBound, GenericParamDefKind};
+use crate::docfs::PathError;
+use crate::error::Error;
+use crate::fuzz_target::api_function::ApiFunction;
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::fuzz_target::api_usage;
+use crate::fuzz_target::api_util;
+use crate::fuzz_target::file_util::{self, FileHelper};
+use crate::fuzz_target::html_report;
+use crate::fuzz_target::incremental;
+use crate::fuzz_target::panic_doc;
+use crate::fuzz_target::risk_rank::{self, RiskSignals};
+use crate::fuzz_target::rulf_config;
+use crate::fuzz_target::rust_version;
+use crate::try_err;
+use rustc_data_structures::fx::FxHashMap;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+pub(crate) static REPORT_FILE_NAME: &'static str = "rulf-report.json";
+
+//why an api is not covered by any target
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum RefusalReason {
+    UnsupportedParameterType,
+    Generic,
+    Unreachable,
+    NewerThanMsrv,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ApiReport {
+    name: String,
+    covered: bool,
+    refusal_reason: Option<RefusalReason>,
+    //what would make the api covered
+    suggestion: Option<String>,
+    //target files that call this api
+    target_files: Vec<String>,
+    //number of generated sequences that call this api
+    sequence_count: usize,
+    //the `# Panics` section of its doc
+    documented_panic: Option<String>,
+    //`#[non_exhaustive]` structs among its parameter types, they can't be built by struct
+    //literals so they are left to the apis producing them
+    non_exhaustive_params: Vec<String>,
+}
+
+//a target calling apis that panic as documented, its crashes may not be bugs
+#[derive(Debug, Clone, Serialize)]
+struct ExpectedPanicTarget {
+    file: String,
+    //`api: precondition`
+    apis: Vec<String>,
+}
+
+//read back by `--incremental`
+#[derive(Debug, Clone, Serialize)]
+struct TargetReport {
+    name: String,
+    digest: String,
+    //with `--rank-targets`
+    risk: Option<TargetRisk>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct TargetRisk {
+    score: usize,
+    signals: RiskSignals,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+struct SequenceStatistics {
+    total_apis: usize,
+    covered_apis: usize,
+    generated_sequences: usize,
+    target_files: usize,
+    max_sequence_len: usize,
+    average_sequence_len: f64,
+    //number of apis by the number of target files they appear in
+    targets_per_api_histogram: BTreeMap<usize, usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Report {
+    crate_name: String,
+    strategy: String,
+    statistics: SequenceStatistics,
+    apis: Vec<ApiReport>,
+    expected_panic_targets: Vec<ExpectedPanicTarget>,
+    targets: Vec<TargetReport>,
+}
+
+pub(crate) fn write_report(
+    graph: &ApiGraph<'_>,
+    file_helper: Option<&FileHelper>,
+    output_dir: &Path,
+) -> Result<(), Error> {
+    let report = generate_report(graph, file_helper);
+    try_err!(fs::create_dir_all(output_dir), output_dir);
+    let report_path = output_dir.join(REPORT_FILE_NAME);
+    let content = serde_json::to_string_pretty(&report).unwrap();
+    try_err!(fs::write(&report_path, content), &report_path);
+    print_suggestions(&report);
+    println!("report is written to {}", report_path.display());
+    if graph.cx.options.html_report {
+        let report = serde_json::to_value(&report).unwrap();
+        html_report::write_html_report(graph, file_helper, &report, output_dir)?;
+    }
+    Ok(())
+}
+
+fn print_suggestions(report: &Report) {
+    let mut api_numbers: FxHashMap<&String, usize> = FxHashMap::default();
+    for api in &report.apis {
+        if let Some(suggestion) = &api.suggestion {
+            *api_numbers.entry(suggestion).or_insert(0) += 1;
+        }
+    }
+    if api_numbers.is_empty() {
+        return;
+    }
+    let mut suggestions: Vec<(&String, usize)> = api_numbers.into_iter().collect();
+    suggestions.sort_by(|(a, a_number), (b, b_number)| b_number.cmp(a_number).then(a.cmp(b)));
+    println!("-----------SUGGESTIONS-----------");
+    for (suggestion, api_number) in suggestions {
+        println!("{} (blocks {} apis)", suggestion, api_number);
+    }
+    println!("---------------------------------");
+}
+
+fn target_risk(sequence: &ApiSequence, graph: &ApiGraph<'_>) -> Option<TargetRisk> {
+    if !graph.cx.options.rank_targets {
+        return None;
+    }
+    let signals = risk_rank::target_signals(sequence, graph);
+    Some(TargetRisk { score: signals.score(), signals })
+}
+
+fn generate_report(graph: &ApiGraph<'_>, file_helper: Option<&FileHelper>) -> Report {
+    let strategy = graph.cx.options.strategy.to_string();
+
+    let mut sequence_counts: FxHashMap<usize, usize> = FxHashMap::default();
+    let mut max_sequence_len = 0;
+    let mut total_sequence_len = 0;
+    for sequence in &graph.api_sequences {
+        for api_index in sequence._get_contained_api_functions() {
+            *sequence_counts.entry(api_index).or_insert(0) += 1;
+        }
+        max_sequence_len = max_sequence_len.max(sequence.len());
+        total_sequence_len += sequence.len();
+    }
+    let average_sequence_len = if graph.api_sequences.len() > 0 {
+        total_sequence_len as f64 / graph.api_sequences.len() as f64
+    } else {
+        0.0
+    };
+
+    let mut target_files: FxHashMap<usize, Vec<String>> = FxHashMap::default();
+    let mut target_file_number = 0;
+    let mut targets_per_api_histogram = BTreeMap::new();
+    let mut expected_panic_targets = Vec::new();
+    let mut targets = Vec::new();
+    if let Some(file_helper) = file_helper {
+        target_file_number = file_helper.chosen_sequences.len();
+        targets_per_api_histogram = api_usage::histogram(graph, &file_helper.chosen_sequences);
+        let naming = graph.cx.options.naming;
+        for (i, sequence) in file_helper.chosen_sequences.iter().enumerate() {
+            let file_name = file_util::test_file_name(naming, "test", &file_helper.target_names[i]);
+            for api_index in sequence._get_contained_api_functions() {
+                target_files.entry(api_index).or_default().push(file_name.clone());
+            }
+            let expected_panics = panic_doc::expected_panics(sequence, graph);
+            if !expected_panics.is_empty() {
+                expected_panic_targets
+                    .push(ExpectedPanicTarget { file: file_name, apis: expected_panics });
+            }
+            targets.push(TargetReport {
+                name: file_helper.target_names[i].clone(),
+                digest: incremental::target_digest(sequence, graph),
+                risk: target_risk(sequence, graph),
+            });
+        }
+    }
+
+    let mut apis = Vec::new();
+    let mut covered_apis = 0;
+    for (i, api_function) in graph.api_functions.iter().enumerate() {
+        let covered = graph.api_functions_visited.get(i).cloned().unwrap_or(false);
+        if covered {
+            covered_apis += 1;
+        }
+        apis.push(ApiReport {
+            name: api_function.full_name.clone(),
+            covered,
+            refusal_reason: if covered { None } else { Some(RefusalReason::Unreachable) },
+            suggestion: if covered { None } else { Some(unreachable_suggestion(graph, i)) },
+            target_files: target_files.remove(&i).unwrap_or_default(),
+            sequence_count: sequence_counts.get(&i).cloned().unwrap_or(0),
+            documented_panic: documented_panic(graph, &api_function.full_name),
+            non_exhaustive_params: non_exhaustive_params(graph, api_function),
+        });
+    }
+    for generic_function in &graph.generic_functions {
+        let api_function = &generic_function.api_function;
+        apis.push(refused_api(
+            graph,
+            &api_function.full_name,
+            RefusalReason::Generic,
+            generic_suggestion(api_function),
+        ));
+    }
+    let mut unsupported_functions: Vec<&String> =
+        graph.functions_with_unsupported_fuzzable_types.iter().collect();
+    unsupported_functions.sort();
+    for function_name in unsupported_functions {
+        let suggestion = "a parameter type can't be generated from fuzz input: add a custom \
+            producer for it, or a public constructor taking primitive types, slices or strings"
+            .to_string();
+        let refusal_reason = RefusalReason::UnsupportedParameterType;
+        apis.push(refused_api(graph, function_name, refusal_reason, suggestion));
+    }
+    let mut newer_functions: Vec<&String> = graph.functions_newer_than_msrv.iter().collect();
+    newer_functions.sort();
+    for function_name in newer_functions {
+        let suggestion = format!(
+            "async apis need rust {}: raise `--msrv`",
+            rust_version::ASYNC_AWAIT.toolchain()
+        );
+        apis.push(refused_api(graph, function_name, RefusalReason::NewerThanMsrv, suggestion));
+    }
+
+    let statistics = SequenceStatistics {
+        total_apis: apis.len(),
+        covered_apis,
+        generated_sequences: graph.api_sequences.len(),
+        target_files: target_file_number,
+        max_sequence_len,
+        average_sequence_len,
+        targets_per_api_histogram,
+    };
+    Report {
+        crate_name: graph._crate_name.clone(),
+        strategy,
+        statistics,
+        apis,
+        expected_panic_targets,
+        targets,
+    }
+}
+
+fn refused_api(
+    graph: &ApiGraph<'_>,
+    name: &String,
+    refusal_reason: RefusalReason,
+    suggestion: String,
+) -> ApiReport {
+    ApiReport {
+        name: name.clone(),
+        covered: false,
+        refusal_reason: Some(refusal_reason),
+        suggestion: Some(suggestion),
+        target_files: Vec::new(),
+        sequence_count: 0,
+        documented_panic: documented_panic(graph, name),
+        non_exhaustive_params: Vec::new(),
+    }
+}
+
+fn documented_panic(graph: &ApiGraph<'_>, name: &String) -> Option<String> {
+    graph.api_panics.get(name).map(|precondition| precondition.text.clone())
+}
+
+fn non_exhaustive_params(graph: &ApiGraph<'_>, api_function: &ApiFunction) -> Vec<String> {
+    let mut res = Vec::new();
+    for input in &api_function.inputs {
+        if let Some(name) = api_util::non_exhaustive_struct(input, &graph.full_name_map) {
+            if !res.contains(name) {
+                res.push(name.clone());
+            }
+        }
+    }
+    res
+}
+
+//the parameters without any producer are the ones blocking the api. If every parameter has a
+//producer, the producers are blocked themselves
+fn unreachable_suggestion(graph: &ApiGraph<'_>, api_index: usize) -> String {
+    let api_function = &graph.api_functions[api_index];
+    let mut unconstructible_types = Vec::new();
+    let mut non_exhaustive_types = Vec::new();
+    for (k, input) in api_function.inputs.iter().enumerate() {
+        if api_util::is_fuzzable_type(input, &graph.full_name_map, graph.cache()) {
+            continue;
+        }
+        let has_producer = graph.api_dependencies.iter().any(|dependency| {
+            dependency.input_fun.1 == api_index && dependency.input_param_index == k
+        });
+        if !has_producer {
+            if let Some(name) = api_util::non_exhaustive_struct(input, &graph.full_name_map) {
+                if !non_exhaustive_types.contains(name) {
+                    non_exhaustive_types.push(name.clone());
+                }
+                continue;
+            }
+            let type_name = api_util::_type_name(input, &graph.full_name_map, graph.cache());
+            if !unconstructible_types.contains(&type_name) {
+                unconstructible_types.push(type_name);
+            }
+        }
+    }
+    //struct literals of them would be the obvious way, tell why they aren't used
+    if let [type_name] = non_exhaustive_types.as_slice() {
+        if unconstructible_types.is_empty() {
+            return format!(
+                "type `{}` is `#[non_exhaustive]`, it can't be built by a struct literal outside \
+                 its crate: expose a public constructor or add a custom producer",
+                type_name
+            );
+        }
+    }
+    unconstructible_types.extend(non_exhaustive_types);
+    match unconstructible_types.as_slice() {
+        [] => "the apis producing its parameters are not covered either: unblock them first"
+            .to_string(),
+        [type_name] => format!(
+            "type `{}` unconstructible: expose a public constructor or add a custom producer",
+            type_name
+        ),
+        _ => format!(
+            "types {} unconstructible: expose public constructors or add custom producers",
+            unconstructible_types
+                .iter()
+                .map(|type_name| format!("`{}`", type_name))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+//e.g. add a type instantiation for parameter `T: Read` in the `[generic-hints]` table of rulf.toml,
+//with the entry of the api that would be written in the table
+pub(crate) fn generic_suggestion(api_function: &ApiFunction) -> String {
+    let generics = &api_function.generics;
+    let mut params = Vec::new();
+    let mut hints = Vec::new();
+    for param in &generics.params {
+        let mut bounds = match &param.kind {
+            GenericParamDefKind::Type { bounds, .. } => bound_names(bounds),
+            _ => continue,
+        };
+        for predicate in &generics.where_predicates {
+            if let clean::WherePredicate::BoundPredicate {
+                ty: clean::Type::Generic(name),
+                bounds: where_bounds,
+                ..
+            } = predicate
+            {
+                if *name == param.name {
+                    bounds.extend(bound_names(where_bounds));
+                }
+            }
+        }
+        if bounds.is_empty() {
+            params.push(format!("`{}`", param.name));
+        } else {
+            params.push(format!("`{}: {}`", param.name, bounds.join(" + ")));
+        }
+        hints.push(format!("{} = \"<type>\"", param.name));
+    }
+    //const parameters, or the type parameters of the impl of a method
+    if params.is_empty() {
+        return format!(
+            "the generic parameters of the api can't be given types in the `[generic-hints]` \
+            table of {}, e.g. const parameters or the ones of its impl: add a public wrapper \
+            api instantiating them",
+            rulf_config::CONFIG_FILE
+        );
+    }
+    let noun = if params.len() == 1 { "parameter" } else { "parameters" };
+    format!(
+        "add a type instantiation for {} {} in the `[generic-hints]` table of {}: \
+        `\"{}\" = {{ {} }}` under `[generic-hints.functions]`, or `<trait> = \"<type>\"` under \
+        `[generic-hints.bounds]` for all the apis with that bound",
+        noun,
+        params.join(", "),
+        rulf_config::CONFIG_FILE,
+        api_function.full_name,
+        hints.join(", ")
+    )
+}
+
+fn bound_names(bounds: &Vec<GenericBound>) -> Vec<String> {
+    bounds
+        .iter()
+        .filter_map(|bound| match bound {
+            GenericBound::TraitBound(poly_trait, _) => Some(poly_trait.trait_.last().to_string()),
+            GenericBound::Outlives(_) => None,
+        })
+        .collect()
+}
@@ -0,0 +1,194 @@
+//Targets ordered by how likely they are to find a bug, with `--rank-targets`, for users who can
+//only fuzz some of them. The score of a target comes from static signals of the bodies of the apis
+//it calls and of the functions of the crate they call by path, a few calls deep: unsafe blocks,
+//the density of `unwrap` and `expect`, arithmetic on lengths and parameters, which overflows on
+//untrusted sizes, and indexing, which panics out of bounds. The targets are written riskiest
+//first, and the report gives the score and the signals of each one.
+use crate::TyCtxt;
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::ApiSequence;
+use rustc_data_structures::fx::FxHashSet;
+use rustc_hir as hir;
+use rustc_hir::def::{DefKind, Res};
+use rustc_hir::intravisit::{self, Visitor};
+use rustc_middle::hir::nested_filter;
+use rustc_span::def_id::DefId;
+use serde::Serialize;
+use std::cmp::Reverse;
+
+static UNSAFE_BLOCK_WEIGHT: usize = 8;
+static UNTRUSTED_ARITHMETIC_WEIGHT: usize = 4;
+static INDEXING_WEIGHT: usize = 2;
+//the unwraps count by their percentage of the expressions
+static UNWRAP_DENSITY_WEIGHT: usize = 1;
+static MAX_CALL_DEPTH: usize = 3;
+static PRINTED_TARGET_NUMBER: usize = 5;
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub(crate) struct RiskSignals {
+    //an unsafe fn counts as one block
+    pub(crate) unsafe_blocks: usize,
+    pub(crate) unwraps: usize,
+    //`+`, `-`, `*` and `<<` with a `len()` or a parameter as an operand
+    pub(crate) untrusted_arithmetic: usize,
+    pub(crate) indexing: usize,
+    pub(crate) expressions: usize,
+}
+
+impl RiskSignals {
+    fn add(&mut self, other: &RiskSignals) {
+        self.unsafe_blocks += other.unsafe_blocks;
+        self.unwraps += other.unwraps;
+        self.untrusted_arithmetic += other.untrusted_arithmetic;
+        self.indexing += other.indexing;
+        self.expressions += other.expressions;
+    }
+
+    pub(crate) fn score(&self) -> usize {
+        self.unsafe_blocks * UNSAFE_BLOCK_WEIGHT
+            + self.untrusted_arithmetic * UNTRUSTED_ARITHMETIC_WEIGHT
+            + self.indexing * INDEXING_WEIGHT
+            + self.unwraps * 100 / self.expressions.max(1) * UNWRAP_DENSITY_WEIGHT
+    }
+}
+
+//the signals of the api and of the local functions it calls by path
+pub(crate) fn api_signals(tcx: TyCtxt<'_>, def_id: DefId) -> RiskSignals {
+    let mut res = RiskSignals::default();
+    let mut visited = FxHashSet::default();
+    let mut functions = vec![(def_id, 0)];
+    while let Some((def_id, depth)) = functions.pop() {
+        if !visited.insert(def_id) {
+            continue;
+        }
+        let body_id = def_id.as_local().and_then(|def_id| tcx.hir().maybe_body_owned_by(def_id));
+        let body_id = match body_id {
+            Some(body_id) => body_id,
+            None => continue,
+        };
+        let body = tcx.hir().body(body_id);
+        let mut visitor = RiskVisitor {
+            tcx,
+            signals: RiskSignals::default(),
+            params: FxHashSet::default(),
+            callees: Vec::new(),
+        };
+        for param in body.params {
+            param.pat.each_binding(|_, hir_id, _, _| {
+                visitor.params.insert(hir_id);
+            });
+        }
+        if tcx.fn_sig(def_id).unsafety() == hir::Unsafety::Unsafe {
+            visitor.signals.unsafe_blocks += 1;
+        }
+        visitor.visit_body(body);
+        res.add(&visitor.signals);
+        if depth < MAX_CALL_DEPTH {
+            functions.extend(visitor.callees.into_iter().map(|callee| (callee, depth + 1)));
+        }
+    }
+    res
+}
+
+//the signals of the apis the target calls, each api counted once
+pub(crate) fn target_signals(sequence: &ApiSequence, api_graph: &ApiGraph<'_>) -> RiskSignals {
+    let mut res = RiskSignals::default();
+    for api_index in sequence._get_contained_api_functions() {
+        if let Some(signals) =
+            api_graph.api_risks.get(&api_graph.api_functions[api_index].full_name)
+        {
+            res.add(signals);
+        }
+    }
+    res
+}
+
+//the riskiest targets first, targets of the same score keep their order
+pub(crate) fn rank(api_graph: &ApiGraph<'_>, sequences: Vec<ApiSequence>) -> Vec<ApiSequence> {
+    if !api_graph.cx.options.rank_targets {
+        return sequences;
+    }
+    let mut scored_sequences: Vec<(usize, ApiSequence)> = sequences
+        .into_iter()
+        .map(|sequence| (target_signals(&sequence, api_graph).score(), sequence))
+        .collect();
+    scored_sequences.sort_by_key(|(score, _)| Reverse(*score));
+    let top_scores: Vec<String> = scored_sequences
+        .iter()
+        .take(PRINTED_TARGET_NUMBER)
+        .map(|(score, _)| score.to_string())
+        .collect();
+    println!("targets are ranked by risk, the top scores are {}", top_scores.join(", "));
+    scored_sequences.into_iter().map(|(_, sequence)| sequence).collect()
+}
+
+struct RiskVisitor<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    signals: RiskSignals,
+    //the bindings of the parameters of the function
+    params: FxHashSet<hir::HirId>,
+    //functions of the crate called by path
+    callees: Vec<DefId>,
+}
+
+impl<'tcx> RiskVisitor<'tcx> {
+    fn is_untrusted(&self, ex: &hir::Expr<'_>) -> bool {
+        match ex.kind {
+            hir::ExprKind::MethodCall(segment, ..) => segment.ident.as_str() == "len",
+            hir::ExprKind::Path(hir::QPath::Resolved(_, path)) => {
+                matches!(path.res, Res::Local(hir_id) if self.params.contains(&hir_id))
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<'tcx> Visitor<'tcx> for RiskVisitor<'tcx> {
+    type NestedFilter = nested_filter::OnlyBodies;
+
+    fn nested_visit_map(&mut self) -> Self::Map {
+        self.tcx.hir()
+    }
+
+    fn visit_block(&mut self, block: &'tcx hir::Block<'tcx>) {
+        if let hir::BlockCheckMode::UnsafeBlock(hir::UnsafeSource::UserProvided) = block.rules {
+            self.signals.unsafe_blocks += 1;
+        }
+        intravisit::walk_block(self, block);
+    }
+
+    fn visit_expr(&mut self, ex: &'tcx hir::Expr<'tcx>) {
+        self.signals.expressions += 1;
+        match ex.kind {
+            hir::ExprKind::MethodCall(segment, ..) => {
+                if matches!(segment.ident.as_str(), "unwrap" | "expect" | "unwrap_unchecked") {
+                    self.signals.unwraps += 1;
+                }
+            }
+            hir::ExprKind::Index(..) => self.signals.indexing += 1,
+            hir::ExprKind::Binary(op, lhs, rhs) | hir::ExprKind::AssignOp(op, lhs, rhs) => {
+                let is_arithmetic = matches!(
+                    op.node,
+                    hir::BinOpKind::Add
+                        | hir::BinOpKind::Sub
+                        | hir::BinOpKind::Mul
+                        | hir::BinOpKind::Shl
+                );
+                if is_arithmetic && (self.is_untrusted(lhs) || self.is_untrusted(rhs)) {
+                    self.signals.untrusted_arithmetic += 1;
+                }
+            }
+            hir::ExprKind::Call(func, _) => {
+                if let hir::ExprKind::Path(hir::QPath::Resolved(_, path)) = func.kind {
+                    if let Res::Def(DefKind::Fn | DefKind::AssocFn, def_id) = path.res {
+                        if def_id.is_local() {
+                            self.callees.push(def_id);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        intravisit::walk_expr(self, ex);
+    }
+}
@@ -0,0 +1,345 @@
+//Round-trip targets for crates with serialize/deserialize or to_string/from_str pairs. With
+//`--round-trip`, a target whose last call returns a type with an encoder and a decoder is
+//followed by a variant that encodes the result, decodes it again and asserts that the decoded
+//value is equivalent to the result. Unlike the round trips of `--differential`, the value is
+//produced by the apis of the crate, so its invariants hold. A type that implements Display and
+//has a decoder taking `&str` is encoded with `to_string`.
+use crate::clean;
+use crate::fuzz_target::api_function::ApiFunction;
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::fuzz_target::api_util;
+use crate::fuzz_target::call_type::CallType;
+use crate::fuzz_target::code_ast::{Block, Expr, Pat, Stmt};
+use crate::fuzz_target::differential;
+use crate::fuzz_target::fuzz_target_options::Equivalence;
+use crate::fuzz_target::prelude_type::PreludeType;
+use rustc_span::symbol::sym;
+
+//prefixes of the names of encoders and decoders
+static ENCODER_NAMES: [&'static str; 5] =
+    ["serialize", "to_string", "to_bytes", "to_vec", "encode"];
+static DECODER_NAMES: [&'static str; 6] =
+    ["deserialize", "from_str", "from_bytes", "from_slice", "decode", "parse"];
+
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub(crate) enum Encoder {
+    //an index into the api functions
+    Api(usize),
+    //`ToString::to_string` of a Display type
+    Display,
+}
+
+#[derive(Debug, Clone)]
+struct RoundTrip {
+    ty: clean::Type,
+    encoder: Encoder,
+    decoder: usize,
+    //Eq or Debug
+    equivalence: Equivalence,
+}
+
+//the result of `call` to `api` is encoded and decoded again
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub(crate) struct RoundTripHarness {
+    pub(crate) call: usize,
+    pub(crate) api: usize,
+    pub(crate) encoder: Encoder,
+    pub(crate) decoder: usize,
+    pub(crate) equivalence: Equivalence,
+}
+
+impl RoundTripHarness {
+    //the apis called after the call, besides ToString
+    pub(crate) fn apis(&self) -> Vec<usize> {
+        match self.encoder {
+            Encoder::Api(encoder) => vec![encoder, self.decoder],
+            Encoder::Display => vec![self.decoder],
+        }
+    }
+
+    //the statements after the call, `value` is the name the result is bound to
+    pub(crate) fn to_stmts(&self, value: &str, api_graph: &ApiGraph<'_>) -> Vec<Stmt> {
+        let api = &api_graph.api_functions[self.api];
+        let decoder = &api_graph.api_functions[self.decoder];
+        let (encoded, encoded_arg, encoder_name) = match self.encoder {
+            Encoder::Display => (
+                Expr::method_call(Expr::path("_value"), "to_string", Vec::new()),
+                Expr::reference(false, Expr::path("_encoded")),
+                "to_string".to_string(),
+            ),
+            Encoder::Api(encoder_index) => {
+                let encoder = &api_graph.api_functions[encoder_index];
+                let value_arg = converted(
+                    &differential::final_output(api, api_graph),
+                    &encoder.inputs[0],
+                    "_value",
+                    api_graph,
+                );
+                let encoded_arg = converted(
+                    &differential::final_output(encoder, api_graph),
+                    &decoder.inputs[0],
+                    "_encoded",
+                    api_graph,
+                );
+                (
+                    Expr::call(Expr::path(encoder.full_name.as_str()), vec![value_arg]),
+                    encoded_arg,
+                    encoder.full_name.clone(),
+                )
+            }
+        };
+        let message = format!(
+            "the value decoded by `{}` differs from the value encoded by `{}`",
+            decoder.full_name, encoder_name
+        );
+        let rejected = format!("`{}` rejects the output of `{}`", decoder.full_name, encoder_name);
+        let decoded = Expr::call(Expr::path(decoder.full_name.as_str()), vec![encoded_arg]);
+        let comparison = self.comparison(&message);
+        let decode_stmts = if is_wrapped(decoder, api_graph) {
+            vec![Stmt::Match {
+                scrutinee: decoded,
+                arms: vec![
+                    (
+                        differential::ok_pat(decoder, api_graph, "_decoded"),
+                        Block { stmts: vec![comparison] },
+                    ),
+                    (Pat::Wild, Block { stmts: vec![differential::panic_stmt(&rejected)] }),
+                ],
+            }]
+        } else {
+            vec![Stmt::Let { pat: Pat::ident("_decoded", false), init: decoded }, comparison]
+        };
+        //an encoder may fail, e.g. on values the format can't represent
+        let wrapped_encoder = match self.encoder {
+            Encoder::Api(encoder) => Some(&api_graph.api_functions[encoder])
+                .filter(|encoder| is_wrapped(encoder, api_graph)),
+            Encoder::Display => None,
+        };
+        let encode_stmts = if let Some(encoder) = wrapped_encoder {
+            vec![Stmt::Match {
+                scrutinee: encoded,
+                arms: vec![
+                    (
+                        differential::ok_pat(encoder, api_graph, "_encoded"),
+                        Block { stmts: decode_stmts },
+                    ),
+                    (Pat::Wild, Block::new()),
+                ],
+            }]
+        } else {
+            let mut stmts = vec![Stmt::Let { pat: Pat::ident("_encoded", false), init: encoded }];
+            stmts.extend(decode_stmts);
+            stmts
+        };
+        if is_wrapped(api, api_graph) {
+            vec![differential::unwrap_match(
+                value,
+                "_value",
+                api,
+                api_graph,
+                Block { stmts: encode_stmts },
+            )]
+        } else {
+            let mut stmts =
+                vec![Stmt::Let { pat: Pat::ident("_value", false), init: Expr::path(value) }];
+            stmts.extend(encode_stmts);
+            stmts
+        }
+    }
+
+    fn comparison(&self, message: &str) -> Stmt {
+        match self.equivalence {
+            Equivalence::Debug => {
+                let debug_string = |name: &str| Expr::MacroCall {
+                    name: "format".to_string(),
+                    args: vec![Expr::Lit("\"{:?}\"".to_string()), Expr::path(name)],
+                };
+                differential::assertion(debug_string("_decoded"), debug_string("_value"), message)
+            }
+            Equivalence::Eq | Equivalence::Auto => {
+                differential::assertion(Expr::path("_decoded"), Expr::path("_value"), message)
+            }
+        }
+    }
+}
+
+//each target whose result can be encoded and decoded is followed by its round trip variant
+pub(crate) fn add_round_trip_variants(
+    api_graph: &ApiGraph<'_>,
+    sequences: Vec<ApiSequence>,
+) -> Vec<ApiSequence> {
+    if !api_graph.cx.options.round_trip {
+        return sequences;
+    }
+    let round_trips = find_round_trips(api_graph);
+    let mut res = Vec::new();
+    let mut variant_number = 0;
+    for sequence in sequences {
+        let variant = round_trip_variant(&sequence, &round_trips, api_graph);
+        res.push(sequence);
+        if let Some(variant) = variant {
+            res.push(variant);
+            variant_number += 1;
+        }
+    }
+    println!(
+        "{} types with an encoder and a decoder are found, {} round trip harnesses are added",
+        round_trips.len(),
+        variant_number
+    );
+    res
+}
+
+fn round_trip_variant(
+    sequence: &ApiSequence,
+    round_trips: &Vec<RoundTrip>,
+    api_graph: &ApiGraph<'_>,
+) -> Option<ApiSequence> {
+    if sequence._has_harness() {
+        return None;
+    }
+    let call = sequence.len().checked_sub(1)?;
+    let api_index = sequence.functions[call].func.1;
+    let api = &api_graph.api_functions[api_index];
+    api.output.as_ref()?;
+    let ty = differential::final_output(api, api_graph);
+    let round_trip = round_trips.iter().find(|round_trip| round_trip.ty == ty)?;
+    let mut variant = sequence.clone();
+    let harness = RoundTripHarness {
+        call,
+        api: api_index,
+        encoder: round_trip.encoder,
+        decoder: round_trip.decoder,
+        equivalence: round_trip.equivalence,
+    };
+    for function_index in harness.apis() {
+        let function = &api_graph.api_functions[function_index];
+        if function._unsafe_tag._is_unsafe() {
+            variant.set_unsafe();
+        }
+        if let Some(trait_full_path) = &function._trait_full_path {
+            variant.add_trait(trait_full_path);
+        }
+    }
+    variant._round_trip = Some(harness);
+    Some(variant)
+}
+
+//a type is decoded by the first decoder returning it, and encoded by the first encoder whose
+//output the decoder accepts, or by Display when the decoder takes a str
+fn find_round_trips(api_graph: &ApiGraph<'_>) -> Vec<RoundTrip> {
+    let requested_equivalence = api_graph.cx.options.round_trip_equivalence;
+    let functions = &api_graph.api_functions;
+    let mut res: Vec<RoundTrip> = Vec::new();
+    for (j, decoder) in functions.iter().enumerate() {
+        if !is_candidate(decoder, &DECODER_NAMES) {
+            continue;
+        }
+        let ty = differential::final_output(decoder, api_graph);
+        if !matches!(ty, clean::Type::Path { .. })
+            || res.iter().any(|round_trip| round_trip.ty == ty)
+        {
+            continue;
+        }
+        let equivalence = match equivalence(&ty, requested_equivalence, api_graph) {
+            Some(equivalence) => equivalence,
+            None => continue,
+        };
+        let encoder = functions.iter().position(|encoder| {
+            is_candidate(encoder, &ENCODER_NAMES)
+                && accepts(&ty, &encoder.inputs[0], api_graph, false)
+                && accepts(
+                    &differential::final_output(encoder, api_graph),
+                    &decoder.inputs[0],
+                    api_graph,
+                    true,
+                )
+        });
+        let encoder = match encoder {
+            Some(encoder) => Encoder::Api(encoder),
+            None if is_str_ref(&decoder.inputs[0])
+                && api_graph.implements_trait(&ty, sym::Display) =>
+            {
+                Encoder::Display
+            }
+            None => continue,
+        };
+        let encoder_name = match encoder {
+            Encoder::Api(encoder) => functions[encoder].full_name.clone(),
+            Encoder::Display => "to_string".to_string(),
+        };
+        println!(
+            "round trip: `{}` and `{}`, compared by {:?}",
+            encoder_name, decoder.full_name, equivalence
+        );
+        res.push(RoundTrip { ty, encoder, decoder: j, equivalence });
+    }
+    res
+}
+
+//non generic, non async apis with one input whose name starts with one of the names
+fn is_candidate(api: &ApiFunction, names: &[&'static str]) -> bool {
+    let name = api.full_name.rsplit("::").next().unwrap_or(&api.full_name);
+    names.iter().any(|prefix| name.starts_with(prefix))
+        && api.inputs.len() == 1
+        && api.output.is_some()
+        && !api._is_generic_function()
+        && !api._async_tag._is_async()
+}
+
+//the value can be passed to the input, without being moved unless `may_move`
+fn accepts(
+    ty: &clean::Type,
+    input_type: &clean::Type,
+    api_graph: &ApiGraph<'_>,
+    may_move: bool,
+) -> bool {
+    let call_type =
+        api_util::_same_type(ty, input_type, true, &api_graph.full_name_map, api_graph.cache());
+    call_type != CallType::_NotCompatible
+        && !call_type._contains_unwrap_call_type()
+        && (may_move || !api_util::_move_condition(input_type, &call_type))
+}
+
+fn converted(
+    ty: &clean::Type,
+    input_type: &clean::Type,
+    name: &str,
+    api_graph: &ApiGraph<'_>,
+) -> Expr {
+    let call_type =
+        api_util::_same_type(ty, input_type, true, &api_graph.full_name_map, api_graph.cache());
+    call_type._to_call_expr(Expr::path(name), &api_graph.full_name_map, api_graph.cache())
+}
+
+//the requested equivalence, if the type supports it
+fn equivalence(
+    ty: &clean::Type,
+    requested: Equivalence,
+    api_graph: &ApiGraph<'_>,
+) -> Option<Equivalence> {
+    let eq = differential::implements_partial_eq(ty, api_graph);
+    let debug = api_graph.implements_trait(ty, sym::Debug);
+    match requested {
+        Equivalence::Eq | Equivalence::Auto if eq => Some(Equivalence::Eq),
+        Equivalence::Debug | Equivalence::Auto if debug => Some(Equivalence::Debug),
+        _ => None,
+    }
+}
+
+fn is_str_ref(ty: &clean::Type) -> bool {
+    match ty {
+        clean::Type::BorrowedRef { type_, .. } => {
+            **type_ == clean::Type::Primitive(clean::PrimitiveType::Str)
+        }
+        _ => false,
+    }
+}
+
+fn is_wrapped(api: &ApiFunction, api_graph: &ApiGraph<'_>) -> bool {
+    match differential::output_prelude_type(api, api_graph) {
+        Some(PreludeType::NotPrelude(..)) | None => false,
+        Some(..) => true,
+    }
+}
@@ -0,0 +1,136 @@
+//Generation options from `rulf.toml`, so that a crate keeps the options it is fuzzed with next to
+//its code instead of in a long command line. The file is `--config`, or `rulf.toml` in the
+//directory of `--manifest-path`, or in the current directory. Every key is the name of a command
+//line option: `true` turns a flag on, `false` turns off a flag that is on by default with its
+//`--no-` form, strings and integers are the value, and arrays are joined with `,` or repeated for
+//the options that can be given more than once, e.g.
+//
+//    strategy = "bfs"
+//    max-seq-len = 4
+//    features = ["serde"]
+//    exclude-api = ["url::Url::set_*"]
+//    emit-workspace = true
+//    assert-guards = false
+//
+//Options given on the command line take precedence over the file. Tables are settings that have
+//no command line option, like the concrete types of generic parameters:
+//
+//    [generic-hints.bounds]
+//    Hasher = "std::collections::hash_map::DefaultHasher"
+//    [generic-hints.functions]
+//    "mycrate::Map::with_hasher" = { S = "std::collections::hash_map::RandomState" }
+use crate::fuzz_target::generic_function::GenericHints;
+use rustc_session::getopts;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub(crate) static CONFIG_FILE: &'static str = "rulf.toml";
+//options given once for every value instead of a comma separated list
+static MULTI_OPTIONS: [&'static str; 3] = ["include-api", "exclude-api", "entry-api"];
+static GENERIC_HINTS: &'static str = "generic-hints";
+
+//the config file and the command line arguments of its options, None if there is no config file
+pub(crate) fn config_args(
+    matches: &getopts::Matches,
+) -> Result<Option<(PathBuf, Vec<String>)>, String> {
+    let (config_path, table) = match read_config(matches)? {
+        Some(config) => config,
+        None => return Ok(None),
+    };
+    let mut res = Vec::new();
+    for (name, value) in &table {
+        let negated = format!("no-{}", name);
+        //the command line wins
+        if name == GENERIC_HINTS || matches.opts_present(&[name.clone(), negated.clone()]) {
+            continue;
+        }
+        let option = format!("--{}", name);
+        match value {
+            toml::Value::Boolean(true) => res.push(option),
+            //the flags that are off by default are left off
+            toml::Value::Boolean(false) if matches.opt_defined(&negated) => {
+                res.push(format!("--{}", negated))
+            }
+            toml::Value::Boolean(false) => {}
+            toml::Value::String(value) => res.extend([option, value.clone()]),
+            toml::Value::Integer(value) => res.extend([option, value.to_string()]),
+            toml::Value::Array(values) => {
+                let values = values
+                    .iter()
+                    .map(|value| match value {
+                        toml::Value::String(value) => Ok(value.clone()),
+                        toml::Value::Integer(value) => Ok(value.to_string()),
+                        _ => {
+                            Err(format!("`{}` in {} expects strings", name, config_path.display()))
+                        }
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if MULTI_OPTIONS.contains(&name.as_str()) {
+                    for value in values {
+                        res.extend([option.clone(), value]);
+                    }
+                } else if !values.is_empty() {
+                    res.extend([option, values.join(",")]);
+                }
+            }
+            _ => {
+                return Err(format!(
+                    "unsupported value of `{}` in {}",
+                    name,
+                    config_path.display()
+                ));
+            }
+        }
+    }
+    Ok(Some((config_path, res)))
+}
+
+//`[generic-hints]` of the config file
+pub(crate) fn generic_hints(matches: &getopts::Matches) -> Result<GenericHints, String> {
+    let (config_path, table) = match read_config(matches)? {
+        Some(config) => config,
+        None => return Ok(GenericHints::default()),
+    };
+    match table.get(GENERIC_HINTS) {
+        Some(hints) => GenericHints::from_toml(hints).map_err(|e| {
+            format!("invalid `{}` in {}: {}", GENERIC_HINTS, config_path.display(), e)
+        }),
+        None => Ok(GenericHints::default()),
+    }
+}
+
+fn read_config(
+    matches: &getopts::Matches,
+) -> Result<Option<(PathBuf, toml::value::Table)>, String> {
+    let config_path = match config_path(matches)? {
+        Some(config_path) => config_path,
+        None => return Ok(None),
+    };
+    let content = fs::read_to_string(&config_path)
+        .map_err(|e| format!("failed to read {}: {}", config_path.display(), e))?;
+    let table = content
+        .parse::<toml::Value>()
+        .map_err(|e| format!("failed to parse {}: {}", config_path.display(), e))?;
+    match table {
+        toml::Value::Table(table) => Ok(Some((config_path, table))),
+        _ => Err(format!("{} is not a table", config_path.display())),
+    }
+}
+
+fn config_path(matches: &getopts::Matches) -> Result<Option<PathBuf>, String> {
+    if let Some(config_path) = matches.opt_str("config") {
+        let config_path = PathBuf::from(config_path);
+        if !config_path.is_file() {
+            return Err(format!("config file {} does not exist", config_path.display()));
+        }
+        return Ok(Some(config_path));
+    }
+    let crate_root = match matches.opt_str("manifest-path") {
+        Some(manifest_path) => {
+            Path::new(&manifest_path).parent().map(|dir| dir.to_path_buf()).unwrap_or_default()
+        }
+        None => PathBuf::new(),
+    };
+    let config_path = crate_root.join(CONFIG_FILE);
+    if config_path.is_file() { Ok(Some(config_path)) } else { Ok(None) }
+}
@@ -0,0 +1,250 @@
+//Manifests telling CI systems how to run the targets, so that they don't need scripts of their
+//own. `targets.toml` describes every target: the apis it starts from, the features it needs, how
+//long it should be fuzzed, its seeds and its dictionary. `run-afl.toml` and `run-libfuzzer.toml`
+//give the commands building and running every target with each fuzzer, relative to the test
+//directory. Targets calling into C with `--ffi` are built with a sanitizer, and the workspace
+//targets also get a build and a run command for the profile of every `--sanitizer`.
+use crate::docfs::PathError;
+use crate::error::Error;
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::{ApiSequence, ParamType};
+use crate::fuzz_target::corpus;
+use crate::fuzz_target::dictionary;
+use crate::fuzz_target::features;
+use crate::fuzz_target::ffi;
+use crate::fuzz_target::file_util::{self, FileHelper};
+use crate::fuzz_target::workspace;
+use crate::try_err;
+use rustc_session::config::host_triple;
+use std::fs;
+use std::path::Path;
+
+static TARGETS_MANIFEST: &'static str = "targets.toml";
+static AFL_MANIFEST: &'static str = "run-afl.toml";
+static LIBFUZZER_MANIFEST: &'static str = "run-libfuzzer.toml";
+//seconds of fuzzing for every call of the sequence
+static TIMEOUT_PER_CALL: usize = 300;
+static MAX_TIMEOUT: usize = 3600;
+//afl needs an initial input, targets without seeds start from a zeroed input in this directory
+static DEFAULT_INPUT_DIR: &'static str = "in";
+static DEFAULT_INPUT_LEN: usize = 256;
+static OUTPUT_DIR: &'static str = "out";
+
+struct TargetEntry {
+    name: String,
+    source: String,
+    entry_apis: Vec<String>,
+    required_features: Vec<String>,
+    timeout: usize,
+    corpus: Option<String>,
+    dictionary: Option<String>,
+    sanitizer: Option<&'static str>,
+}
+
+pub(crate) fn write_run_manifests(
+    api_graph: &ApiGraph<'_>,
+    helper: &FileHelper,
+    test_dir: &Path,
+) -> Result<(), Error> {
+    let workspace = api_graph.cx.options.workspace;
+    let naming = api_graph.cx.options.naming;
+    let mut targets = Vec::new();
+    for (i, sequence) in helper.chosen_sequences.iter().enumerate() {
+        let file_name = file_util::test_file_name(naming, "test", &helper.target_names[i]);
+        let name = file_name.trim_end_matches(".rs").to_string();
+        let source = if workspace {
+            format!("{}/{}/src/main.rs", workspace::WORKSPACE_DIR, name)
+        } else {
+            format!("{}/{}", file_util::_TEST_FILE_DIR, file_name)
+        };
+        let corpus = format!("{}/{}", corpus::CORPUS_DIR, name);
+        let corpus = if test_dir.join(&corpus).is_dir() { Some(corpus) } else { None };
+        let dictionary = dictionary::dictionary_file_name(naming, &helper.target_names[i]);
+        let dictionary = if test_dir.join(&dictionary).is_file() { Some(dictionary) } else { None };
+        let sanitizer = if api_graph.cx.options.ffi && ffi::calls_ffi(sequence, api_graph) {
+            Some(ffi::SANITIZER)
+        } else {
+            None
+        };
+        targets.push(TargetEntry {
+            name,
+            source,
+            entry_apis: entry_apis(sequence, api_graph),
+            required_features: features::sequence_features(sequence, api_graph),
+            timeout: suggested_timeout(sequence),
+            corpus,
+            dictionary,
+            sanitizer,
+        });
+    }
+
+    let mut res = String::new();
+    for target in &targets {
+        res.push_str("[[target]]\n");
+        res.push_str(format!("name = {:?}\n", target.name).as_str());
+        res.push_str(format!("source = {:?}\n", target.source).as_str());
+        res.push_str(
+            format!("entry_apis = {}\n", features::toml_list(&target.entry_apis)).as_str(),
+        );
+        res.push_str(
+            format!("required_features = {}\n", features::toml_list(&target.required_features))
+                .as_str(),
+        );
+        res.push_str(format!("timeout = {}\n", target.timeout).as_str());
+        if let Some(corpus) = &target.corpus {
+            res.push_str(format!("corpus = {:?}\n", corpus).as_str());
+        }
+        if let Some(dictionary) = &target.dictionary {
+            res.push_str(format!("dictionary = {:?}\n", dictionary).as_str());
+        }
+        if let Some(sanitizer) = target.sanitizer {
+            res.push_str(format!("sanitizer = {:?}\n", sanitizer).as_str());
+        }
+        res.push('\n');
+    }
+    write_manifest(test_dir, TARGETS_MANIFEST, res)?;
+
+    //the Cargo.toml next to the loose files is only written with `--manifest-path`
+    let manifest_path = if workspace {
+        Some(format!("{}/Cargo.toml", workspace::WORKSPACE_DIR))
+    } else if api_graph.cx.options.lib_target.is_some() {
+        Some("Cargo.toml".to_string())
+    } else {
+        None
+    };
+    if let Some(manifest_path) = manifest_path {
+        let target_dir = match manifest_path.strip_suffix("Cargo.toml") {
+            Some(dir) => format!("{}target", dir),
+            None => "target".to_string(),
+        };
+        let mut res = String::new();
+        for target in &targets {
+            let input = target.corpus.clone().unwrap_or(DEFAULT_INPUT_DIR.to_string());
+            let mut build = format!(
+                "cargo afl build --release --manifest-path {} --bin {}",
+                manifest_path, target.name
+            );
+            //sanitizers need an explicit target, so that build scripts are not instrumented
+            let binary_dir = match target.sanitizer {
+                Some(_) => {
+                    build = format!(
+                        "RUSTFLAGS=\"{}\" {} --target {}",
+                        ffi::SANITIZER_FLAGS,
+                        build,
+                        host_triple()
+                    );
+                    format!("{}/{}/release", target_dir, host_triple())
+                }
+                None => format!("{}/release", target_dir),
+            };
+            let dictionary = match &target.dictionary {
+                Some(dictionary) => format!(" -x {}", dictionary),
+                None => String::new(),
+            };
+            let run = format!(
+                "cargo afl fuzz -i {} -o {}/{}{} -V {} {}/{}",
+                input, OUTPUT_DIR, target.name, dictionary, target.timeout, binary_dir, target.name
+            );
+            res.push_str("[[target]]\n");
+            res.push_str(
+                format!("name = {:?}\nbuild = {:?}\nrun = {:?}\n", target.name, build, run)
+                    .as_str(),
+            );
+            let sanitizers = if workspace { &api_graph.cx.options.sanitizers[..] } else { &[] };
+            for sanitizer in sanitizers {
+                let profile = sanitizer.profile();
+                let mut build = format!(
+                    "cargo afl build --profile {} --target {} --manifest-path {} --bin {}",
+                    profile,
+                    host_triple(),
+                    manifest_path,
+                    target.name
+                );
+                if sanitizer.needs_build_std() {
+                    build.push_str(" -Z build-std");
+                }
+                let run = format!(
+                    "cargo afl fuzz -i {} -o {}/{}-{}{} -V {} {}/{}/{}/{}",
+                    input,
+                    OUTPUT_DIR,
+                    target.name,
+                    profile,
+                    dictionary,
+                    target.timeout,
+                    target_dir,
+                    host_triple(),
+                    profile,
+                    target.name
+                );
+                res.push_str(
+                    format!("build_{} = {:?}\nrun_{} = {:?}\n", profile, build, profile, run)
+                        .as_str(),
+                );
+            }
+            res.push('\n');
+        }
+        write_manifest(test_dir, AFL_MANIFEST, res)?;
+        if targets.iter().any(|target| target.corpus.is_none()) {
+            let input_dir = test_dir.join(DEFAULT_INPUT_DIR);
+            try_err!(fs::create_dir_all(&input_dir), &input_dir);
+            let input_path = input_dir.join("zeros");
+            try_err!(fs::write(&input_path, vec![0u8; DEFAULT_INPUT_LEN]), &input_path);
+        }
+    }
+
+    //libfuzzer targets are written to their own directory, and are not part of the workspace
+    let libfuzzer_dir =
+        if workspace { None } else { file_util::libfuzzer_files_dir(&helper.crate_name) };
+    if let Some(libfuzzer_dir) = libfuzzer_dir {
+        let mut res = String::new();
+        for (i, target) in targets.iter().enumerate() {
+            let file_name =
+                file_util::test_file_name(naming, "fuzz_target", &helper.target_names[i]);
+            let name = file_name.trim_end_matches(".rs");
+            //cargo fuzz uses AddressSanitizer by default, the ffi targets need nothing more
+            let mut run = format!("cargo fuzz run {}", name);
+            if let Some(corpus) = &target.corpus {
+                run.push_str(format!(" {}", test_dir.join(corpus).display()).as_str());
+            }
+            run.push_str(format!(" -- -max_total_time={}", target.timeout).as_str());
+            if let Some(dictionary) = &target.dictionary {
+                run.push_str(format!(" -dict={}", test_dir.join(dictionary).display()).as_str());
+            }
+            res.push_str("[[target]]\n");
+            res.push_str(format!("name = {:?}\n", name).as_str());
+            res.push_str(
+                format!("source = {:?}\n", libfuzzer_dir.join(&file_name).display().to_string())
+                    .as_str(),
+            );
+            res.push_str(format!("run = {:?}\n\n", run).as_str());
+        }
+        write_manifest(test_dir, LIBFUZZER_MANIFEST, res)?;
+    }
+    println!("run manifests of the targets are written to {}", test_dir.display());
+    Ok(())
+}
+
+//the apis that only take fuzz data, the sequence starts from them
+fn entry_apis(sequence: &ApiSequence, api_graph: &ApiGraph<'_>) -> Vec<String> {
+    let mut res = Vec::new();
+    for api_call in &sequence.functions {
+        let is_entry =
+            api_call.params.iter().all(|(param_type, ..)| *param_type == ParamType::_FuzzableType);
+        let full_name = &api_graph.api_functions[api_call.func.1].full_name;
+        if is_entry && !res.contains(full_name) {
+            res.push(full_name.clone());
+        }
+    }
+    res
+}
+
+//longer sequences reach deeper states and need more time
+fn suggested_timeout(sequence: &ApiSequence) -> usize {
+    (sequence.len() * TIMEOUT_PER_CALL).min(MAX_TIMEOUT)
+}
+
+fn write_manifest(test_dir: &Path, file_name: &str, content: String) -> Result<(), Error> {
+    let path = test_dir.join(file_name);
+    try_err!(fs::write(&path, content), &path);
+    Ok(())
+}
@@ -0,0 +1,217 @@
+//A heuristic check for borrows that are only checked at runtime. Apis of types built on
+//`RefCell`, `Mutex` or `RwLock` hand out guards through `&self`, so the borrow checker accepts
+//two guards of the same receiver at the same time, while the target panics with
+//`already borrowed` or deadlocks. A conflict is reported when a call returns a guard from a
+//receiver that still has a live guard, and one of the two guards is exclusive. Such sequences
+//are reordered when the second guard is dropped right away, and split into one sequence per
+//guard otherwise.
+use crate::clean;
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::fuzz_target::api_util;
+use crate::fuzz_target::sequence_ir::{IrType, SequenceIr, ValueSource};
+
+//a sequence is split at most into this many sequences
+static MAX_SPLIT_SEQUENCES: usize = 4;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum GuardKind {
+    Shared,
+    Exclusive,
+}
+
+//guards of the std, parking_lot and lock_api, by their type name
+static SHARED_GUARDS: [&'static str; 3] = ["Ref", "RwLockReadGuard", "MappedRwLockReadGuard"];
+static EXCLUSIVE_GUARDS: [&'static str; 5] =
+    ["RefMut", "MutexGuard", "MappedMutexGuard", "RwLockWriteGuard", "MappedRwLockWriteGuard"];
+
+#[derive(Debug, Clone)]
+struct RuntimeConflict {
+    //the call returning the guard that is still alive
+    guard_call: usize,
+    //the call taking another guard of the same receiver
+    conflicting_call: usize,
+    message: String,
+}
+
+//a guard taken by `call` from the value passed as its receiver, alive up to and including `end`
+#[derive(Debug, Clone)]
+struct Guard {
+    call: usize,
+    receiver: ValueSource,
+    kind: GuardKind,
+    end: usize,
+}
+
+//the sequences without runtime borrow conflicts that replace the sequence
+pub(crate) fn resolve(sequence: ApiSequence, api_graph: &ApiGraph<'_>) -> Vec<ApiSequence> {
+    let mut res: Vec<ApiSequence> = Vec::new();
+    let mut worklist = vec![sequence];
+    while let Some(sequence) = worklist.pop() {
+        if res.len() + worklist.len() >= MAX_SPLIT_SEQUENCES {
+            break;
+        }
+        let ir = SequenceIr::lower(&sequence, api_graph, "_param", "_local");
+        let conflict = match find_conflict(&ir, api_graph) {
+            None => {
+                if !res.contains(&sequence) {
+                    res.push(sequence);
+                }
+                continue;
+            }
+            Some(conflict) => conflict,
+        };
+        if let Some(reordered_sequence) = reorder(&sequence, &ir, &conflict, api_graph) {
+            println!("runtime borrow: sequence is reordered, {}", conflict.message);
+            worklist.push(reordered_sequence);
+            continue;
+        }
+        println!("runtime borrow: sequence is split, {}", conflict.message);
+        for removed_call in [conflict.conflicting_call, conflict.guard_call] {
+            let split_sequence = sequence._remove_call_with_dependents(removed_call, api_graph);
+            if split_sequence.len() == 0 || split_sequence._has_no_fuzzables() {
+                continue;
+            }
+            let ir = SequenceIr::lower(&split_sequence, api_graph, "_param", "_local");
+            if ir.check(api_graph).is_ok() {
+                worklist.push(split_sequence);
+            }
+        }
+    }
+    res
+}
+
+fn find_conflict(ir: &SequenceIr, api_graph: &ApiGraph<'_>) -> Option<RuntimeConflict> {
+    let guards = guards(ir, api_graph);
+    for (k, guard) in guards.iter().enumerate() {
+        for earlier_guard in &guards[..k] {
+            if earlier_guard.receiver != guard.receiver || earlier_guard.end < guard.call {
+                continue;
+            }
+            if earlier_guard.kind == GuardKind::Shared && guard.kind == GuardKind::Shared {
+                continue;
+            }
+            let name_of =
+                |call: usize| api_graph.api_functions[ir.calls[call].function].full_name.clone();
+            return Some(RuntimeConflict {
+                guard_call: earlier_guard.call,
+                conflicting_call: guard.call,
+                message: format!(
+                    "call #{} to `{}` borrows `{}`, which is still borrowed since call #{} to `{}`",
+                    guard.call,
+                    name_of(guard.call),
+                    ir.value(&guard.receiver).map_or(String::new(), |v| v.name.clone()),
+                    earlier_guard.call,
+                    name_of(earlier_guard.call)
+                ),
+            });
+        }
+    }
+    None
+}
+
+fn guards(ir: &SequenceIr, api_graph: &ApiGraph<'_>) -> Vec<Guard> {
+    let body_end = ir.calls.len();
+    let mut res = Vec::new();
+    for (i, call) in ir.calls.iter().enumerate() {
+        let value = match call.result {
+            Some(value_id) => &ir.values[value_id],
+            None => continue,
+        };
+        let kind = match &value.ty {
+            IrType::Api(ty) => guard_kind(ty),
+            IrType::Fuzzable(_) => None,
+        };
+        let kind = match kind {
+            Some(kind) => kind,
+            None => continue,
+        };
+        //the receiver is the first argument, when the guard borrows from it
+        let receiver = match call.args.first() {
+            Some(arg) if value.borrows.iter().any(|(owner, _)| *owner == arg.source) => arg.source,
+            _ => continue,
+        };
+        //a bound guard is dropped at the end of the body, unless a later call takes it by value
+        let end = if !value.bound {
+            i
+        } else {
+            moved_at(ir, api_graph, value.source, i).unwrap_or(body_end)
+        };
+        res.push(Guard { call: i, receiver, kind, end });
+    }
+    res
+}
+
+fn moved_at(
+    ir: &SequenceIr,
+    api_graph: &ApiGraph<'_>,
+    source: ValueSource,
+    start: usize,
+) -> Option<usize> {
+    for (i, call) in ir.calls.iter().enumerate().skip(start + 1) {
+        let function = &api_graph.api_functions[call.function];
+        for (k, arg) in call.args.iter().enumerate() {
+            if arg.source == source
+                && api_util::_move_condition(&function.inputs[k], &arg.adaptation)
+            {
+                return Some(i);
+            }
+        }
+    }
+    None
+}
+
+//wrappers like `Option` or `LockResult` hold the guard as well
+fn guard_kind(ty: &clean::Type) -> Option<GuardKind> {
+    match ty {
+        clean::Type::Path { path } => {
+            let name = path.segments.last()?.name;
+            if SHARED_GUARDS.contains(&name.as_str()) {
+                return Some(GuardKind::Shared);
+            }
+            if EXCLUSIVE_GUARDS.contains(&name.as_str()) {
+                return Some(GuardKind::Exclusive);
+            }
+            strongest_kind(path.generics()?)
+        }
+        clean::Type::Tuple(types) => strongest_kind(types.iter().collect()),
+        _ => None,
+    }
+}
+
+fn strongest_kind(types: Vec<&clean::Type>) -> Option<GuardKind> {
+    let kinds: Vec<GuardKind> = types.into_iter().filter_map(guard_kind).collect();
+    if kinds.contains(&GuardKind::Exclusive) {
+        Some(GuardKind::Exclusive)
+    } else {
+        kinds.first().cloned()
+    }
+}
+
+//the second guard is dropped right after it is taken, so it can be taken before the first one,
+//as long as it only uses values defined before the first guard
+fn reorder(
+    sequence: &ApiSequence,
+    ir: &SequenceIr,
+    conflict: &RuntimeConflict,
+    api_graph: &ApiGraph<'_>,
+) -> Option<ApiSequence> {
+    if ir.binding(conflict.conflicting_call).is_some() {
+        return None;
+    }
+    let uses_later_values =
+        ir.calls[conflict.conflicting_call].args.iter().any(|arg| match arg.source {
+            ValueSource::CallResult(index) => index >= conflict.guard_call,
+            ValueSource::FuzzableParam(_) => false,
+        });
+    if uses_later_values {
+        return None;
+    }
+    let reordered_sequence =
+        sequence._move_call_before(conflict.conflicting_call, conflict.guard_call, api_graph);
+    let ir = SequenceIr::lower(&reordered_sequence, api_graph, "_param", "_local");
+    if ir.check(api_graph).is_err() {
+        return None;
+    }
+    Some(reordered_sequence)
+}
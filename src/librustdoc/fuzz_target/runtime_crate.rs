@@ -0,0 +1,70 @@
+//The data decoding helpers as the support crate `rulf_runtime`, instead of a copy of the helpers in
+//every target. With `--runtime-crate`, the crate is written next to the targets with the helpers
+//they use (splitting the data into slices, decoding primitives, UTF-8 strings, reading a crash
+//file), and the targets import it, so they shrink to the test function and its closure, and a
+//decoding bug is fixed in one place. Every Cargo.toml the targets are built with depends on it,
+//and the crates of the compile check, Miri and OSS-Fuzz get a copy of their own. The workspace of
+//`--workspace` always shares its helpers through the crate.
+use crate::docfs::PathError;
+use crate::error::Error;
+use crate::fuzz_target::afl_util;
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::fuzz_target::replay_util;
+use crate::try_err;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+pub(crate) static RUNTIME_CRATE: &'static str = "rulf_runtime";
+pub(crate) static RUNTIME_IMPORT: &'static str =
+    "extern crate rulf_runtime;\n#[allow(unused_imports)]\nuse rulf_runtime::*;\n";
+
+//`rulf_runtime = { path = "<path>" }`
+pub(crate) fn dependency(path: &str) -> String {
+    format!("{} = {{ path = {:?} }}\n", RUNTIME_CRATE, path)
+}
+
+//the crate under `parent_dir` with the helpers the sequences use, so that it only needs the
+//feature gates of these helpers, and none with `--msrv`
+pub(crate) fn write_runtime_crate(
+    api_graph: &ApiGraph<'_>,
+    sequences: &[ApiSequence],
+    parent_dir: &Path,
+) -> Result<(), Error> {
+    let msrv = api_graph.cx.options.msrv;
+    let mut feature_gates = BTreeSet::new();
+    let mut helper_functions = BTreeSet::new();
+    for sequence in sequences {
+        let fuzzable_params = &sequence.fuzzable_params;
+        if let Some(gates) = afl_util::_get_feature_gates_of_sequence(fuzzable_params, msrv) {
+            feature_gates.extend(gates);
+        }
+        if let Some(functions) =
+            afl_util::_get_afl_helpers_functions_of_sequence(fuzzable_params, msrv)
+        {
+            helper_functions.extend(functions);
+        }
+    }
+    helper_functions.insert(replay_util::_read_crash_file_data().to_string());
+
+    let mut lib = String::new();
+    for feature_gate in feature_gates {
+        lib.push_str(format!("{}\n", feature_gate).as_str());
+    }
+    for helper_function in helper_functions {
+        lib.push_str(format!("pub {}\n", helper_function).as_str());
+    }
+    let runtime_dir = parent_dir.join(RUNTIME_CRATE);
+    let src_dir = runtime_dir.join("src");
+    try_err!(fs::create_dir_all(&src_dir), &src_dir);
+    let manifest = format!(
+        "[package]\nname = {:?}\nversion = \"0.0.0\"\nedition = \"2018\"\npublish = false\n",
+        RUNTIME_CRATE
+    );
+    let manifest_path = runtime_dir.join("Cargo.toml");
+    try_err!(fs::write(&manifest_path, manifest), &manifest_path);
+    let lib_path = src_dir.join("lib.rs");
+    try_err!(fs::write(&lib_path, lib), &lib_path);
+    Ok(())
+}
@@ -0,0 +1,52 @@
+//Minimum supported Rust version(msrv) of the generated code.
+//Without a msrv the targets are built with the nightly toolchain used by afl.rs, so they may use
+//anything. With a msrv, every syntax or std api newer than it is replaced by an older equivalent,
+//or the api needing it is skipped. The code AST never prints let-else or inline format args,
+//so only the versions listed here need a check.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+pub(crate) struct RustVersion {
+    pub(crate) major: u32,
+    pub(crate) minor: u32,
+}
+
+//edition 2018, which the manifests of the targets ask for, and `slice::align_to`, used by the
+//slice helper since 1.30. Older toolchains are not supported at all
+pub(crate) static OLDEST_SUPPORTED: RustVersion = RustVersion { major: 1, minor: 31 };
+//async fn and `.await`
+pub(crate) static ASYNC_AWAIT: RustVersion = RustVersion { major: 1, minor: 39 };
+//`f32::from_le_bytes` and `f64::from_le_bytes`
+pub(crate) static FLOAT_FROM_BYTES: RustVersion = RustVersion { major: 1, minor: 40 };
+//`char::from_u32` as an associated function, before it `assoc_char_funcs` is a nightly feature
+pub(crate) static ASSOC_CHAR_FUNCS: RustVersion = RustVersion { major: 1, minor: 52 };
+
+impl RustVersion {
+    //accepts `1.56` and `1.56.1`, the patch version doesn't change the language
+    pub(crate) fn parse(version: &str) -> Option<Self> {
+        let mut parts = version.trim().split('.');
+        let major = parts.next()?.parse::<u32>().ok()?;
+        let minor = parts.next()?.parse::<u32>().ok()?;
+        match parts.next() {
+            None => {}
+            Some(patch) => {
+                patch.parse::<u32>().ok()?;
+            }
+        }
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(RustVersion { major, minor })
+    }
+
+    //the rustup toolchain name, used as `cargo +<toolchain>` when the targets are compiled
+    pub(crate) fn toolchain(&self) -> String {
+        format!("{}.{}", self.major, self.minor)
+    }
+}
+
+//whether the generated code may use a feature stabilized in `since`
+pub(crate) fn supports(msrv: Option<RustVersion>, since: RustVersion) -> bool {
+    match msrv {
+        None => true,
+        Some(msrv) => msrv >= since,
+    }
+}
@@ -0,0 +1,340 @@
+//An explicit intermediate representation between graph traversal and rendering.
+//A sequence is lowered into typed values, calls whose arguments carry explicit adaptations
+//(call types), and scopes. The checker validates the IR before anything is rendered, so that
+//bugs in the generator (use before definition, use after move, type mismatch, missing mut,
+//unsafe or async) are reported with context instead of showing up as rustc errors in emitted
+//files. Moves and borrows are checked by `borrow_check`.
+use crate::clean;
+use crate::fuzz_target::api_graph::{ApiGraph, ApiType};
+use crate::fuzz_target::api_sequence::{ApiSequence, ParamType};
+use crate::fuzz_target::api_util;
+use crate::fuzz_target::borrow_check;
+use crate::fuzz_target::call_type::CallType;
+use crate::fuzz_target::fuzzable_type::{self, FuzzableType};
+use rustc_data_structures::fx::{FxHashMap, FxHashSet};
+use std::fmt;
+
+pub(crate) type ValueId = usize;
+pub(crate) type ScopeId = usize;
+
+//where a value comes from
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub(crate) enum ValueSource {
+    FuzzableParam(usize),
+    CallResult(usize),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum IrType {
+    Fuzzable(FuzzableType),
+    Api(clean::Type),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct IrValue {
+    pub(crate) source: ValueSource,
+    pub(crate) ty: IrType,
+    pub(crate) name: String,
+    //false means the value is discarded with `let _ = `
+    pub(crate) bound: bool,
+    pub(crate) mutable: bool,
+    pub(crate) scope: ScopeId,
+    //values this value borrows from, and whether the borrow is mutable
+    pub(crate) borrows: Vec<(ValueSource, bool)>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct IrScope {
+    pub(crate) parent: Option<ScopeId>,
+    pub(crate) is_unsafe: bool,
+    pub(crate) is_async: bool,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct IrArgument {
+    pub(crate) source: ValueSource,
+    //how the value is adapted to the parameter type
+    pub(crate) adaptation: CallType,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct IrCall {
+    pub(crate) function: usize,
+    pub(crate) args: Vec<IrArgument>,
+    pub(crate) result: Option<ValueId>,
+    pub(crate) scope: ScopeId,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct SequenceIr {
+    pub(crate) values: Vec<IrValue>,
+    pub(crate) scopes: Vec<IrScope>,
+    pub(crate) calls: Vec<IrCall>,
+    value_ids: FxHashMap<ValueSource, ValueId>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct IrError {
+    pub(crate) call_index: usize,
+    pub(crate) function: String,
+    pub(crate) message: String,
+    //borrow errors are repaired instead of skipping the whole sequence
+    pub(crate) is_borrow_error: bool,
+}
+
+impl fmt::Display for IrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "call #{} to `{}`: {}", self.call_index, self.function, self.message)
+    }
+}
+
+impl SequenceIr {
+    pub(crate) fn lower(
+        sequence: &ApiSequence,
+        api_graph: &ApiGraph<'_>,
+        param_prefix: &str,
+        local_param_prefix: &str,
+    ) -> Self {
+        let mut ir = SequenceIr {
+            values: Vec::new(),
+            scopes: Vec::new(),
+            calls: Vec::new(),
+            value_ids: FxHashMap::default(),
+        };
+        //scope 0 is the body of the test function, the calls may live in an async block
+        //and an unsafe block
+        let function_scope = ir.add_scope(None, false, false);
+        let async_scope = if sequence._async_tag {
+            ir.add_scope(Some(function_scope), false, true)
+        } else {
+            function_scope
+        };
+        let body_scope = if sequence._unsafe_tag {
+            ir.add_scope(Some(async_scope), true, false)
+        } else {
+            async_scope
+        };
+
+        for (i, fuzzable_param) in sequence.fuzzable_params.iter().enumerate() {
+            ir.add_value(IrValue {
+                source: ValueSource::FuzzableParam(i),
+                ty: IrType::Fuzzable(fuzzable_param.clone()),
+                name: format!("{}{}", param_prefix, i),
+                bound: true,
+                mutable: sequence._is_fuzzable_need_mut_tag(i),
+                scope: function_scope,
+                borrows: Vec::new(),
+            });
+        }
+
+        let dead_code = sequence._dead_code(api_graph);
+        for (i, api_call) in sequence.functions.iter().enumerate() {
+            let function = match api_call.func.0 {
+                ApiType::BareFunction => api_call.func.1,
+            };
+            let mut args = Vec::new();
+            for (param_type, index, call_type) in &api_call.params {
+                let source = match param_type {
+                    ParamType::_FuzzableType => ValueSource::FuzzableParam(*index),
+                    ParamType::_FunctionReturn => ValueSource::CallResult(*index),
+                };
+                args.push(IrArgument { source, adaptation: call_type.clone() });
+            }
+            let result = match &api_graph.api_functions[function].output {
+                None => None,
+                Some(output) => Some(
+                    ir.add_value(IrValue {
+                        source: ValueSource::CallResult(i),
+                        ty: IrType::Api(output.clone()),
+                        name: format!("{}{}", local_param_prefix, i),
+                        bound: !dead_code[i]
+                            || sequence._is_formatted(i)
+                            || sequence._is_compared(i)
+                            || sequence._is_operand(i)
+                            || sequence._is_dropped(i),
+                        mutable: sequence._is_function_need_mut_tag(i),
+                        scope: body_scope,
+                        borrows: sequence
+                            ._borrowed_owners(i)
+                            .into_iter()
+                            .map(|(owner, mutable)| (ValueSource::CallResult(owner), mutable))
+                            .collect(),
+                    }),
+                ),
+            };
+            ir.calls.push(IrCall { function, args, result, scope: body_scope });
+        }
+        ir
+    }
+
+    fn add_scope(&mut self, parent: Option<ScopeId>, is_unsafe: bool, is_async: bool) -> ScopeId {
+        self.scopes.push(IrScope { parent, is_unsafe, is_async });
+        self.scopes.len() - 1
+    }
+
+    fn add_value(&mut self, value: IrValue) -> ValueId {
+        let value_id = self.values.len();
+        self.value_ids.insert(value.source, value_id);
+        self.values.push(value);
+        value_id
+    }
+
+    pub(crate) fn value(&self, source: &ValueSource) -> Option<&IrValue> {
+        self.value_ids.get(source).map(|value_id| &self.values[*value_id])
+    }
+
+    //the value bound by a call, None if the result is discarded or the function returns ()
+    pub(crate) fn binding(&self, call_index: usize) -> Option<&IrValue> {
+        let value = &self.values[self.calls[call_index].result?];
+        if value.bound { Some(value) } else { None }
+    }
+
+    fn in_unsafe_scope(&self, scope: ScopeId) -> bool {
+        let mut current = Some(scope);
+        while let Some(scope_id) = current {
+            if self.scopes[scope_id].is_unsafe {
+                return true;
+            }
+            current = self.scopes[scope_id].parent;
+        }
+        false
+    }
+
+    fn in_async_scope(&self, scope: ScopeId) -> bool {
+        let mut current = Some(scope);
+        while let Some(scope_id) = current {
+            if self.scopes[scope_id].is_async {
+                return true;
+            }
+            current = self.scopes[scope_id].parent;
+        }
+        false
+    }
+
+    //whether a value defined in `defined_scope` is visible from `use_scope`
+    fn is_visible(&self, defined_scope: ScopeId, use_scope: ScopeId) -> bool {
+        let mut current = Some(use_scope);
+        while let Some(scope_id) = current {
+            if scope_id == defined_scope {
+                return true;
+            }
+            current = self.scopes[scope_id].parent;
+        }
+        false
+    }
+
+    pub(crate) fn check(&self, api_graph: &ApiGraph<'_>) -> Result<(), IrError> {
+        let full_name_map = &api_graph.full_name_map;
+        let cache = api_graph.cache();
+        let mut defined = FxHashSet::default();
+        for value in &self.values {
+            if let ValueSource::FuzzableParam(_) = value.source {
+                defined.insert(value.source);
+            }
+        }
+
+        for (i, call) in self.calls.iter().enumerate() {
+            let function = &api_graph.api_functions[call.function];
+            let error = |message: String| IrError {
+                call_index: i,
+                function: function.full_name.clone(),
+                message,
+                is_borrow_error: false,
+            };
+            if function._unsafe_tag._is_unsafe() && !self.in_unsafe_scope(call.scope) {
+                return Err(error(
+                    "unsafe function is called outside of an unsafe block".to_string(),
+                ));
+            }
+            if function._async_tag._is_async() && !self.in_async_scope(call.scope) {
+                return Err(error(
+                    "async function is awaited outside of an async block".to_string(),
+                ));
+            }
+            if call.args.len() != function.inputs.len() {
+                return Err(error(format!(
+                    "expects {} arguments, but {} are given",
+                    function.inputs.len(),
+                    call.args.len()
+                )));
+            }
+
+            for (k, arg) in call.args.iter().enumerate() {
+                let expected = &function.inputs[k];
+                let value = match self.value(&arg.source) {
+                    Some(value) => value,
+                    None => {
+                        return Err(error(format!(
+                            "argument {} refers to a value that does not exist ({:?})",
+                            k, arg.source
+                        )));
+                    }
+                };
+                if !defined.contains(&arg.source) {
+                    return Err(error(format!(
+                        "argument {} uses `{}` before it is defined",
+                        k, value.name
+                    )));
+                }
+                if !value.bound {
+                    return Err(error(format!(
+                        "argument {} uses `{}`, which is discarded with `let _`",
+                        k, value.name
+                    )));
+                }
+                if !self.is_visible(value.scope, call.scope) {
+                    return Err(error(format!(
+                        "argument {} uses `{}`, which is out of scope",
+                        k, value.name
+                    )));
+                }
+
+                let type_checked = match &value.ty {
+                    IrType::Api(output) => {
+                        api_util::_same_type(output, expected, true, full_name_map, cache)
+                            == arg.adaptation
+                    }
+                    IrType::Fuzzable(fuzzable) => {
+                        let (fuzzable_type, call_type) =
+                            fuzzable_type::fuzzable_call_type(expected, full_name_map, cache)
+                                .generate_fuzzable_type_and_call_type();
+                        fuzzable_type == *fuzzable && call_type == arg.adaptation
+                    }
+                };
+                if !type_checked {
+                    return Err(error(format!(
+                        "argument {} passes `{}` by {:?}, which does not match parameter type `{}`",
+                        k,
+                        value.name,
+                        arg.adaptation,
+                        api_util::_type_name(expected, full_name_map, cache)
+                    )));
+                }
+
+                //unwrap call types introduce a helper variable, which carries its own mut tag
+                if api_util::_need_mut_tag(&arg.adaptation)
+                    && !arg.adaptation._contains_unwrap_call_type()
+                    && !value.mutable
+                {
+                    return Err(error(format!(
+                        "argument {} borrows `{}` mutably, but it is not declared as mut",
+                        k, value.name
+                    )));
+                }
+                if arg.adaptation.unsafe_call_type()._is_unsafe()
+                    && !self.in_unsafe_scope(call.scope)
+                {
+                    return Err(error(format!(
+                        "argument {} dereferences a raw pointer outside of an unsafe block",
+                        k
+                    )));
+                }
+            }
+
+            if let Some(value_id) = call.result {
+                defined.insert(self.values[value_id].source);
+            }
+        }
+        borrow_check::check(self, api_graph)
+    }
+}
@@ -0,0 +1,328 @@
+//Choose the sequences written to target files as a set cover: the reachable apis are the
+//elements, every valid sequence covers the apis it calls, and as few targets as possible should
+//cover all of them. The greedy solver takes the sequence covering the most uncovered apis, then
+//the most uncovered dependencies, until no sequence covers anything new, so the edges between
+//the apis are covered too like in the heuristic selection of RULF. The exact solver finds a
+//minimum cover of the 0-1 program `min sum(x_s) s.t. sum(x_s for s calling a) >= 1 for every api
+//a` by branch and bound, which is only feasible for small crates, so it gives up after a budget
+//of search nodes and keeps the best cover found by then, reporting whether that is still the
+//greedy one. The sequences covering uncovered edges are added to the exact cover greedily. In
+//mega sequence mode, the chosen sequences of every connected
+//component of the graph are merged into one long sequence, for fewer binaries.
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::fuzz_target::fuzz_target_options::SelectionMode;
+use rustc_data_structures::fx::{FxHashMap, FxHashSet};
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+
+static EXACT_SEARCH_BUDGET: usize = 1_000_000;
+
+//a sequence that may be chosen, with the apis and dependencies it covers
+struct Candidate {
+    sequence_index: usize,
+    apis: BTreeSet<usize>,
+    edges: BTreeSet<usize>,
+    dynamic_length: bool,
+    len: usize,
+}
+
+impl Candidate {
+    //sequences with dynamic length fuzzables are preferred, they take inputs of any size
+    //then the ones covering more edges, then the shorter ones
+    fn is_better_than(&self, other: &Candidate) -> bool {
+        let key = |candidate: &Candidate| {
+            (!candidate.dynamic_length, Reverse(candidate.edges.len()), candidate.len)
+        };
+        (key(self), self.sequence_index) < (key(other), other.sequence_index)
+    }
+}
+
+pub(crate) fn select_sequences(api_graph: &ApiGraph<'_>, max_size: usize) -> Vec<ApiSequence> {
+    let candidates = candidates(api_graph);
+    let universe: BTreeSet<usize> =
+        candidates.iter().flat_map(|candidate| candidate.apis.iter().cloned()).collect();
+    let chosen = match api_graph.cx.options.selection {
+        SelectionMode::Greedy => greedy_cover(&candidates, Vec::new(), true),
+        SelectionMode::Exact => {
+            //the minimum cover of the apis, then the edges
+            let api_cover = greedy_cover(&candidates, Vec::new(), false);
+            let api_cover = exact_cover(&candidates, &universe, api_cover);
+            greedy_cover(&candidates, api_cover, true)
+        }
+    };
+    let mut res: Vec<ApiSequence> = chosen
+        .into_iter()
+        .map(|k| api_graph.api_sequences[candidates[k].sequence_index].clone())
+        .collect();
+    if api_graph.cx.options.mega_sequence {
+        let chosen_number = res.len();
+        res = merge_components(api_graph, res);
+        println!("{} targets are merged into {} mega sequences", chosen_number, res.len());
+    }
+    if res.len() > max_size {
+        println!(
+            "note: {} targets are needed to cover all apis and edges, only {} are kept",
+            res.len(),
+            max_size
+        );
+        res.truncate(max_size);
+    }
+    print_statistics(api_graph, &res, universe.len());
+    res
+}
+
+//sequences with fuzzables and without dead code, the sequences calling the same apis through the
+//same dependencies are only kept once
+fn candidates(api_graph: &ApiGraph<'_>) -> Vec<Candidate> {
+    let mut res: Vec<Candidate> = Vec::new();
+    let mut by_coverage: FxHashMap<(BTreeSet<usize>, BTreeSet<usize>), usize> =
+        FxHashMap::default();
+    for (sequence_index, sequence) in api_graph.api_sequences.iter().enumerate() {
+        if sequence._has_no_fuzzables() || sequence._contains_dead_code_except_last_one(api_graph) {
+            continue;
+        }
+        let candidate = Candidate {
+            sequence_index,
+            apis: sequence._get_contained_api_functions().into_iter().collect(),
+            edges: sequence._covered_dependencies.iter().cloned().collect(),
+            dynamic_length: !sequence._is_fuzzables_fixed_length(),
+            len: sequence.len(),
+        };
+        let coverage = (candidate.apis.clone(), candidate.edges.clone());
+        match by_coverage.get(&coverage) {
+            Some(k) => {
+                if candidate.is_better_than(&res[*k]) {
+                    res[*k] = candidate;
+                }
+            }
+            None => {
+                by_coverage.insert(coverage, res.len());
+                res.push(candidate);
+            }
+        }
+    }
+    res
+}
+
+//add the candidate covering the most uncovered apis, then the most uncovered edges, to the chosen
+//ones, until no candidate covers a new api, or a new edge `with_edges`
+fn greedy_cover(candidates: &Vec<Candidate>, chosen: Vec<usize>, with_edges: bool) -> Vec<usize> {
+    let mut covered_apis = BTreeSet::new();
+    let mut covered_edges = BTreeSet::new();
+    for k in &chosen {
+        covered_apis.extend(candidates[*k].apis.iter().cloned());
+        covered_edges.extend(candidates[*k].edges.iter().cloned());
+    }
+    let mut res = chosen;
+    loop {
+        let mut best: Option<(usize, (usize, usize))> = None;
+        for (k, candidate) in candidates.iter().enumerate() {
+            let api_gain = candidate.apis.difference(&covered_apis).count();
+            let edge_gain =
+                if with_edges { candidate.edges.difference(&covered_edges).count() } else { 0 };
+            let gain = (api_gain, edge_gain);
+            if gain == (0, 0) {
+                continue;
+            }
+            let is_better = match best {
+                None => true,
+                Some((best_k, best_gain)) => {
+                    gain > best_gain
+                        || (gain == best_gain && candidate.is_better_than(&candidates[best_k]))
+                }
+            };
+            if is_better {
+                best = Some((k, gain));
+            }
+        }
+        let (k, _) = match best {
+            Some(best) => best,
+            None => break,
+        };
+        covered_apis.extend(candidates[k].apis.iter().cloned());
+        covered_edges.extend(candidates[k].edges.iter().cloned());
+        res.push(k);
+    }
+    res
+}
+
+//a minimum cover, starting from the greedy one as the upper bound
+fn exact_cover(
+    candidates: &Vec<Candidate>,
+    universe: &BTreeSet<usize>,
+    greedy_cover: Vec<usize>,
+) -> Vec<usize> {
+    //a candidate whose apis are all covered by another one is never needed in a minimum cover.
+    //Of the candidates calling the same apis through different edges, the first one is kept
+    let kept: Vec<usize> = (0..candidates.len())
+        .filter(|k| {
+            !candidates.iter().enumerate().any(|(other, candidate)| {
+                other != *k
+                    && candidates[*k].apis.is_subset(&candidate.apis)
+                    && (candidates[*k].apis != candidate.apis || other < *k)
+            })
+        })
+        .collect();
+    let mut covering: FxHashMap<usize, Vec<usize>> = FxHashMap::default();
+    for k in &kept {
+        for api in &candidates[*k].apis {
+            covering.entry(*api).or_default().push(*k);
+        }
+    }
+    let max_gain = kept.iter().map(|k| candidates[*k].apis.len()).max().unwrap_or(1);
+    let mut search = ExactSearch {
+        candidates,
+        covering,
+        max_gain,
+        best: greedy_cover.clone(),
+        budget: EXACT_SEARCH_BUDGET,
+    };
+    let mut chosen = Vec::new();
+    search.search(&mut chosen, universe.clone());
+    if search.budget == 0 {
+        if search.best.len() < greedy_cover.len() {
+            println!(
+                "note: exact selection stops after {} search nodes, the best cover found is used, \
+                 it may not be minimum",
+                EXACT_SEARCH_BUDGET
+            );
+        } else {
+            println!(
+                "note: exact selection stops after {} search nodes without a smaller cover, \
+                 it falls back to the greedy cover",
+                EXACT_SEARCH_BUDGET
+            );
+        }
+    }
+    if search.best.len() < greedy_cover.len() {
+        println!(
+            "exact selection needs {} targets, greedy selection needs {}",
+            search.best.len(),
+            greedy_cover.len()
+        );
+    }
+    search.best
+}
+
+struct ExactSearch<'a> {
+    candidates: &'a Vec<Candidate>,
+    //candidates covering each api
+    covering: FxHashMap<usize, Vec<usize>>,
+    max_gain: usize,
+    best: Vec<usize>,
+    budget: usize,
+}
+
+impl<'a> ExactSearch<'a> {
+    //branch on the candidates covering the uncovered api with the fewest of them
+    fn search(&mut self, chosen: &mut Vec<usize>, uncovered: BTreeSet<usize>) {
+        if self.budget == 0 {
+            return;
+        }
+        self.budget -= 1;
+        if uncovered.is_empty() {
+            if chosen.len() < self.best.len() {
+                self.best = chosen.clone();
+            }
+            return;
+        }
+        let lower_bound = chosen.len() + (uncovered.len() + self.max_gain - 1) / self.max_gain;
+        if lower_bound >= self.best.len() {
+            return;
+        }
+        let api = uncovered
+            .iter()
+            .min_by_key(|api| self.covering.get(api).map_or(0, |covering| covering.len()))
+            .cloned()
+            .unwrap();
+        //candidates covering more of the rest first, so that good covers are found early
+        let mut branches = self.covering.get(&api).cloned().unwrap_or_default();
+        branches
+            .sort_by_key(|k| Reverse(self.candidates[*k].apis.intersection(&uncovered).count()));
+        for k in branches {
+            let rest: BTreeSet<usize> =
+                uncovered.difference(&self.candidates[k].apis).cloned().collect();
+            chosen.push(k);
+            self.search(chosen, rest);
+            chosen.pop();
+        }
+    }
+}
+
+//one sequence for every connected component of the graph, calling the chosen sequences of the
+//component one after another. The apis of a sequence are connected even if they don't depend on
+//each other
+fn merge_components(api_graph: &ApiGraph<'_>, chosen: Vec<ApiSequence>) -> Vec<ApiSequence> {
+    let mut parent: Vec<usize> = (0..api_graph.api_functions.len()).collect();
+    for dependency in &api_graph.api_dependencies {
+        union(&mut parent, dependency.output_fun.1, dependency.input_fun.1);
+    }
+    for sequence in &chosen {
+        for pair in sequence.function_indexes().windows(2) {
+            union(&mut parent, pair[0], pair[1]);
+        }
+    }
+    //components in the order of their first sequence
+    let mut components: Vec<(usize, Vec<ApiSequence>)> = Vec::new();
+    for sequence in chosen {
+        let root = match sequence.functions.first() {
+            Some(api_call) => find(&mut parent, api_call.func.1),
+            None => continue,
+        };
+        match components.iter_mut().find(|(component, _)| *component == root) {
+            Some((_, sequences)) => sequences.push(sequence),
+            None => components.push((root, vec![sequence])),
+        }
+    }
+    components
+        .into_iter()
+        .map(|(_, sequences)| {
+            let mut merged = ApiSequence::_merge_sequences(&sequences);
+            for sequence in &sequences {
+                merged._covered_dependencies.extend(sequence._covered_dependencies.iter().cloned());
+            }
+            merged
+        })
+        .collect()
+}
+
+fn find(parent: &mut Vec<usize>, node: usize) -> usize {
+    let mut root = node;
+    while parent[root] != root {
+        root = parent[root];
+    }
+    let mut node = node;
+    while parent[node] != root {
+        let next = parent[node];
+        parent[node] = root;
+        node = next;
+    }
+    root
+}
+
+fn union(parent: &mut Vec<usize>, first: usize, second: usize) {
+    let first = find(parent, first);
+    let second = find(parent, second);
+    parent[second] = first;
+}
+
+fn print_statistics(api_graph: &ApiGraph<'_>, chosen: &Vec<ApiSequence>, reachable_apis: usize) {
+    let mut covered_nodes = FxHashSet::default();
+    let mut covered_edges = FxHashSet::default();
+    for sequence in chosen {
+        covered_nodes.extend(sequence._get_contained_api_functions());
+        covered_edges.extend(sequence._covered_dependencies.iter().cloned());
+    }
+    let total_length: usize = chosen.iter().map(|sequence| sequence.len()).sum();
+    println!("-----------STATISTICS-----------");
+    println!("total nodes: {}", api_graph.api_functions.len());
+    println!("total edges: {}", api_graph.api_dependencies.len());
+    println!("reachable nodes: {}", reachable_apis);
+    println!("covered nodes: {}", covered_nodes.len());
+    println!("covered edges: {}", covered_edges.len());
+    println!("total targets: {}", chosen.len());
+    println!("max length = {}", chosen.iter().map(|sequence| sequence.len()).max().unwrap_or(0));
+    println!("total length = {}", total_length);
+    println!("--------------------------------");
+}
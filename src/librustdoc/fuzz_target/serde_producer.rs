@@ -0,0 +1,120 @@
+//Deserializing from the fuzz input as the producer of types implementing `serde::Deserialize`, with
+//`--serde-producer json|bincode`. Nested config structs are often only built by deserializing
+//them, and a chain of constructors reaching every field doesn't exist. A type gets an api like
+//`serde_json::from_slice::<T>` taking a `&[u8]` from the fuzz input when it implements Deserialize
+//without type params and no api returns it, so it is preferred over `--allow-default-producer`.
+//The api returns a Result, unwrapped like the Result of any other api.
+use crate::clean;
+use crate::fuzz_target::api_function::{ApiAsyncness, ApiFunction, ApiUnsafety};
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::fuzz_target::default_producer;
+use rustc_hir::Mutability;
+use rustc_hir::def::{DefKind, Res};
+use rustc_hir::def_id::DefId;
+use rustc_middle::ty::TyCtxt;
+use rustc_span::symbol::sym;
+use thin_vec::ThinVec;
+
+pub(crate) fn add_serde_producers(api_graph: &mut ApiGraph<'_>) {
+    let serde_format = match api_graph.cx.options.serde_producer {
+        Some(serde_format) => serde_format,
+        None => return,
+    };
+    let tcx = api_graph.tcx();
+    let result_did = match tcx.get_diagnostic_item(sym::Result) {
+        Some(result_did) => result_did,
+        None => return,
+    };
+    let mut producers = Vec::new();
+    for (did, impls) in &api_graph.cache().impls {
+        let type_name = match api_graph.full_name_map._get_full_name(*did) {
+            Some(type_name) => type_name,
+            None => continue,
+        };
+        let deserialize_impl = impls
+            .iter()
+            .map(|impl_| impl_.inner_impl())
+            .find(|impl_| is_deserialize_impl(tcx, impl_));
+        let deserialize_impl = match deserialize_impl {
+            Some(deserialize_impl) => deserialize_impl,
+            None => continue,
+        };
+        if default_producer::has_producer(api_graph, &deserialize_impl.for_) {
+            continue;
+        }
+        let bytes = clean::Type::BorrowedRef {
+            lifetime: None,
+            mutability: Mutability::Not,
+            type_: Box::new(clean::Type::Slice(Box::new(clean::Type::Primitive(
+                clean::PrimitiveType::U8,
+            )))),
+        };
+        producers.push(ApiFunction {
+            full_name: format!("{}::<{}>", serde_format.deserialize_function(), type_name),
+            generics: clean::Generics::default(),
+            inputs: vec![bytes],
+            output: Some(result_of(deserialize_impl.for_.clone(), result_did)),
+            _trait_full_path: None,
+            _unsafe_tag: ApiUnsafety::Normal,
+            _async_tag: ApiAsyncness::NotAsync,
+        });
+    }
+    println!("{} types get a `{}` producer", producers.len(), serde_format.deserialize_function());
+    for producer in producers {
+        api_graph.add_api_function(producer);
+    }
+}
+
+//the sequence deserializes a value, its target depends on the crate of the format
+pub(crate) fn uses_serde_producer(sequence: &ApiSequence, api_graph: &ApiGraph<'_>) -> bool {
+    let serde_format = match api_graph.cx.options.serde_producer {
+        Some(serde_format) => serde_format,
+        None => return false,
+    };
+    let prefix = format!("{}::<", serde_format.deserialize_function());
+    sequence
+        .functions
+        .iter()
+        .any(|api_call| api_graph.api_functions[api_call.func.1].full_name.starts_with(&prefix))
+}
+
+//the dependency of the crate of the format, if one of the sequences deserializes a value
+pub(crate) fn serde_dependency(
+    api_graph: &ApiGraph<'_>,
+    sequences: &[ApiSequence],
+) -> Option<&'static str> {
+    let serde_format = api_graph.cx.options.serde_producer?;
+    if sequences.iter().any(|sequence| uses_serde_producer(sequence, api_graph)) {
+        Some(serde_format.dependency())
+    } else {
+        None
+    }
+}
+
+//`impl<'de> Deserialize<'de> for T` without type params
+fn is_deserialize_impl(tcx: TyCtxt<'_>, impl_: &clean::Impl) -> bool {
+    let is_deserialize = impl_.trait_.as_ref().map_or(false, |trait_| {
+        let trait_did = trait_.def_id();
+        tcx.crate_name(trait_did.krate).as_str() == "serde"
+            && tcx.item_name(trait_did).as_str() == "Deserialize"
+    });
+    is_deserialize
+        && impl_.generics.where_predicates.is_empty()
+        && impl_
+            .generics
+            .params
+            .iter()
+            .all(|param| matches!(param.kind, clean::GenericParamDefKind::Lifetime { .. }))
+}
+
+//`Result<T, _>`, the error type is never named in the targets
+fn result_of(ok_type: clean::Type, result_did: DefId) -> clean::Type {
+    let args = clean::GenericArgs::AngleBracketed {
+        args: vec![clean::GenericArg::Type(ok_type), clean::GenericArg::Type(clean::Type::Infer)]
+            .into(),
+        bindings: ThinVec::new(),
+    };
+    let segments = vec![clean::PathSegment { name: sym::Result, args }];
+    clean::Type::Path { path: clean::Path { res: Res::Def(DefKind::Enum, result_did), segments } }
+}
@@ -0,0 +1,160 @@
+//Sequences for chosen apis instead of the whole crate, with `--sink-api PATTERN`, which selects
+//the `sink` strategy. The search goes backwards from every api matching a pattern, e.g.
+//`url::Url::set_host`: the apis producing its params that can't be fuzzed, the apis producing
+//theirs, and so on. The length of the shortest chain ending with an api is one plus the lengths of
+//the shortest chains of the producers of its params, computed until it doesn't decrease anymore,
+//so cycles between producers are fine. Every sink gets the sequence merging the chains of its
+//best producers, which is as long as needed, `--max-seq-len` doesn't bound it.
+use crate::fuzz_target::api_graph::{ApiGraph, ApiType};
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::fuzz_target::api_util;
+use crate::fuzz_target::graph_traverse::SequenceStrategy;
+use rustc_data_structures::fx::{FxHashMap, FxHashSet};
+
+pub(crate) static SINK_STRATEGY: &'static str = "sink";
+
+pub(crate) struct SinkStrategy;
+
+impl SequenceStrategy for SinkStrategy {
+    fn name(&self) -> &'static str {
+        SINK_STRATEGY
+    }
+
+    fn generate(&self, graph: &ApiGraph<'_>) -> Vec<ApiSequence> {
+        let sink_indexes = sink_api_indexes(graph);
+        let chains = ProducerChains::new(graph, &sink_indexes);
+        let mut res = Vec::new();
+        for sink_index in sink_indexes {
+            let full_name = &graph.api_functions[sink_index].full_name;
+            match chains.sequence(graph, sink_index) {
+                Some(sequence) => {
+                    println!("the shortest chain of {} has {} calls", full_name, sequence.len());
+                    res.push(sequence);
+                }
+                None => println!("warning: no producer chain ends with the sink api {}", full_name),
+            }
+        }
+        res
+    }
+}
+
+//the apis matching `--sink-api`
+fn sink_api_indexes(graph: &ApiGraph<'_>) -> Vec<usize> {
+    let mut res = Vec::new();
+    for pattern in &graph.cx.options.sink_apis {
+        let matched: Vec<usize> = graph
+            .api_functions
+            .iter()
+            .enumerate()
+            .filter(|(_, api_function)| pattern.matches(&api_function.full_name))
+            .map(|(index, _)| index)
+            .collect();
+        if matched.is_empty() {
+            println!("warning: no api matches the sink api `{}`", pattern.pattern);
+        }
+        for index in matched {
+            if !res.contains(&index) {
+                res.push(index);
+            }
+        }
+    }
+    res
+}
+
+pub(crate) struct ProducerChains {
+    //(api, param) to the apis whose output it can take
+    producers: FxHashMap<(usize, usize), Vec<usize>>,
+    //the params of each api that need a producer
+    unfuzzable_params: FxHashMap<usize, Vec<usize>>,
+    //the length of the shortest chain ending with each api that has one
+    lengths: FxHashMap<usize, usize>,
+}
+
+impl ProducerChains {
+    pub(crate) fn new(graph: &ApiGraph<'_>, sink_indexes: &[usize]) -> Self {
+        let mut producers: FxHashMap<(usize, usize), Vec<usize>> = FxHashMap::default();
+        for dependency in &graph.api_dependencies {
+            producers
+                .entry((dependency.input_fun.1, dependency.input_param_index))
+                .or_default()
+                .push(dependency.output_fun.1);
+        }
+
+        //the apis the sinks depend on, directly or not
+        let mut unfuzzable_params = FxHashMap::default();
+        let mut apis = Vec::new();
+        let mut visited = FxHashSet::default();
+        let mut to_visit = sink_indexes.to_vec();
+        while let Some(api_index) = to_visit.pop() {
+            if !visited.insert(api_index) {
+                continue;
+            }
+            let params: Vec<usize> = graph.api_functions[api_index]
+                .inputs
+                .iter()
+                .enumerate()
+                .filter(|(_, input)| {
+                    !api_util::is_fuzzable_type(input, &graph.full_name_map, graph.cache())
+                })
+                .map(|(param_index, _)| param_index)
+                .collect();
+            for param_index in &params {
+                if let Some(param_producers) = producers.get(&(api_index, *param_index)) {
+                    to_visit.extend(param_producers.iter().copied());
+                }
+            }
+            unfuzzable_params.insert(api_index, params);
+            apis.push(api_index);
+        }
+
+        let mut lengths: FxHashMap<usize, usize> = FxHashMap::default();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            'apis: for api_index in &apis {
+                let mut length = 1;
+                for param_index in &unfuzzable_params[api_index] {
+                    let shortest =
+                        producers.get(&(*api_index, *param_index)).and_then(|param_producers| {
+                            param_producers.iter().filter_map(|index| lengths.get(index)).min()
+                        });
+                    match shortest {
+                        Some(shortest) => length += shortest,
+                        None => continue 'apis,
+                    }
+                }
+                if lengths.get(api_index).map_or(true, |old_length| length < *old_length) {
+                    lengths.insert(*api_index, length);
+                    changed = true;
+                }
+            }
+        }
+        ProducerChains { producers, unfuzzable_params, lengths }
+    }
+
+    //the chain of the best producer of every param that needs one. A producer's chain is shorter
+    //than the chain of the api it feeds, so this ends
+    pub(crate) fn param_chains(
+        &self,
+        graph: &ApiGraph<'_>,
+        api_index: usize,
+    ) -> Option<Vec<ApiSequence>> {
+        let mut res = Vec::new();
+        for param_index in self.unfuzzable_params.get(&api_index)? {
+            let producer = self
+                .producers
+                .get(&(api_index, *param_index))?
+                .iter()
+                .filter(|index| self.lengths.contains_key(*index))
+                .min_by_key(|index| self.lengths[*index])?;
+            res.push(self.sequence(graph, *producer)?);
+        }
+        Some(res)
+    }
+
+    //the chains of the params, then the api
+    pub(crate) fn sequence(&self, graph: &ApiGraph<'_>, api_index: usize) -> Option<ApiSequence> {
+        let prefix = ApiSequence::_merge_sequences(&self.param_chains(graph, api_index)?);
+        graph.is_fun_satisfied(&ApiType::BareFunction, api_index, &prefix)
+    }
+}
@@ -0,0 +1,172 @@
+//Values of std types built from fuzzable primitives, so that the apis taking them are not filtered
+//out when the crate has no api returning them. A value is decoded from the input as a tuple of
+//primitives and converted by an expression that can't panic: the nanoseconds of a `Duration` are
+//reduced below one second, and a `SystemTime` that can't be represented falls back to the epoch.
+//`Instant` is left out, its only base is the current time, so a crash wouldn't replay. Ip
+//addresses are built from their integer form, a flag chooses between v4 and v6 for `IpAddr` and
+//`SocketAddr`. A zero decoded for a `NonZeroU32` and the like becomes one.
+use crate::clean::PrimitiveType;
+use crate::fuzz_target::code_ast::Expr;
+
+static NANOS_PER_SEC: &'static str = "1_000_000_000";
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub(crate) enum StdValue {
+    Duration,
+    //the epoch plus a duration
+    SystemTime,
+    Ipv4Addr,
+    Ipv6Addr,
+    IpAddr,
+    SocketAddrV4,
+    SocketAddrV6,
+    SocketAddr,
+    //`NonZeroU32` and the like, of the integer type
+    NonZero(PrimitiveType),
+}
+
+impl StdValue {
+    pub(crate) fn from_full_name(full_name: &str) -> Option<Self> {
+        match full_name {
+            "core::time::Duration" | "std::time::Duration" => Some(StdValue::Duration),
+            "std::time::SystemTime" => Some(StdValue::SystemTime),
+            //the net types moved from std to core
+            "std::net::ip::Ipv4Addr" | "core::net::ip_addr::Ipv4Addr" => Some(StdValue::Ipv4Addr),
+            "std::net::ip::Ipv6Addr" | "core::net::ip_addr::Ipv6Addr" => Some(StdValue::Ipv6Addr),
+            "std::net::ip::IpAddr" | "core::net::ip_addr::IpAddr" => Some(StdValue::IpAddr),
+            "std::net::addr::SocketAddrV4" | "core::net::socket_addr::SocketAddrV4" => {
+                Some(StdValue::SocketAddrV4)
+            }
+            "std::net::addr::SocketAddrV6" | "core::net::socket_addr::SocketAddrV6" => {
+                Some(StdValue::SocketAddrV6)
+            }
+            "std::net::addr::SocketAddr" | "core::net::socket_addr::SocketAddr" => {
+                Some(StdValue::SocketAddr)
+            }
+            _ => {
+                let name = full_name
+                    .strip_prefix("core::num::nonzero::NonZero")
+                    .or_else(|| full_name.strip_prefix("std::num::NonZero"))?;
+                non_zero_integer(name).map(StdValue::NonZero)
+            }
+        }
+    }
+
+    //the primitives decoded from the input, the seconds and nanoseconds of a duration, the
+    //addresses and the port of a socket address
+    pub(crate) fn fields(&self) -> Vec<PrimitiveType> {
+        match self {
+            StdValue::Duration | StdValue::SystemTime => {
+                vec![PrimitiveType::U64, PrimitiveType::U32]
+            }
+            StdValue::Ipv4Addr => vec![PrimitiveType::U32],
+            StdValue::Ipv6Addr => vec![PrimitiveType::U128],
+            StdValue::IpAddr => vec![PrimitiveType::Bool, PrimitiveType::U32, PrimitiveType::U128],
+            StdValue::SocketAddrV4 => vec![PrimitiveType::U32, PrimitiveType::U16],
+            StdValue::SocketAddrV6 => vec![PrimitiveType::U128, PrimitiveType::U16],
+            StdValue::SocketAddr => vec![
+                PrimitiveType::Bool,
+                PrimitiveType::U32,
+                PrimitiveType::U128,
+                PrimitiveType::U16,
+            ],
+            StdValue::NonZero(integer) => vec![*integer],
+        }
+    }
+
+    //the value built from `fields`, the decoded tuple
+    pub(crate) fn to_expr(&self, fields: Expr) -> Expr {
+        let field = |index: usize| Expr::field(fields.clone(), index.to_string().as_str());
+        match self {
+            StdValue::Duration => duration(field(0), field(1)),
+            StdValue::SystemTime => {
+                let epoch = Expr::path("std::time::UNIX_EPOCH");
+                let time = Expr::method_call(
+                    epoch.clone(),
+                    "checked_add",
+                    vec![duration(field(0), field(1))],
+                );
+                Expr::method_call(time, "unwrap_or", vec![epoch])
+            }
+            StdValue::Ipv4Addr => ipv4_addr(field(0)),
+            StdValue::Ipv6Addr => ipv6_addr(field(0)),
+            StdValue::IpAddr => ip_addr(field(0), field(1), field(2)),
+            StdValue::SocketAddrV4 => Expr::call(
+                Expr::path("std::net::SocketAddrV4::new"),
+                vec![ipv4_addr(field(0)), field(1)],
+            ),
+            //no flow info and scope id
+            StdValue::SocketAddrV6 => Expr::call(
+                Expr::path("std::net::SocketAddrV6::new"),
+                vec![
+                    ipv6_addr(field(0)),
+                    field(1),
+                    Expr::Lit("0".to_string()),
+                    Expr::Lit("0".to_string()),
+                ],
+            ),
+            StdValue::SocketAddr => Expr::call(
+                Expr::path("std::net::SocketAddr::new"),
+                vec![ip_addr(field(0), field(1), field(2)), field(3)],
+            ),
+            //`NonZeroU32::new(x).unwrap_or(NonZeroU32::new(1).unwrap())`
+            StdValue::NonZero(integer) => {
+                let name = non_zero_name(*integer);
+                let new = format!("core::num::{}::new", name);
+                let one = Expr::call(Expr::path(new.as_str()), vec![Expr::Lit("1".to_string())]);
+                let one = Expr::method_call(one, "unwrap", Vec::new());
+                let value = Expr::call(Expr::path(new.as_str()), vec![field(0)]);
+                Expr::method_call(value, "unwrap_or", vec![one])
+            }
+        }
+    }
+}
+
+//the integer type of `NonZeroU32` from `U32`
+fn non_zero_integer(name: &str) -> Option<PrimitiveType> {
+    let integer = match name {
+        "U8" => PrimitiveType::U8,
+        "U16" => PrimitiveType::U16,
+        "U32" => PrimitiveType::U32,
+        "U64" => PrimitiveType::U64,
+        "U128" => PrimitiveType::U128,
+        "Usize" => PrimitiveType::Usize,
+        "I8" => PrimitiveType::I8,
+        "I16" => PrimitiveType::I16,
+        "I32" => PrimitiveType::I32,
+        "I64" => PrimitiveType::I64,
+        "I128" => PrimitiveType::I128,
+        "Isize" => PrimitiveType::Isize,
+        _ => return None,
+    };
+    Some(integer)
+}
+
+//`NonZeroU32` from `u32`
+fn non_zero_name(integer: PrimitiveType) -> String {
+    let integer = integer.as_sym().to_string();
+    format!("NonZero{}{}", integer[..1].to_uppercase(), &integer[1..])
+}
+
+fn duration(secs: Expr, nanos: Expr) -> Expr {
+    let nanos = Expr::binary(nanos, "%", Expr::Lit(NANOS_PER_SEC.to_string()));
+    Expr::call(Expr::path("core::time::Duration::new"), vec![secs, nanos])
+}
+
+fn ipv4_addr(bits: Expr) -> Expr {
+    Expr::call(Expr::path("std::net::Ipv4Addr::from"), vec![bits])
+}
+
+fn ipv6_addr(bits: Expr) -> Expr {
+    Expr::call(Expr::path("std::net::Ipv6Addr::from"), vec![bits])
+}
+
+//`if is_v4 { IpAddr::V4(..) } else { IpAddr::V6(..) }`
+fn ip_addr(is_v4: Expr, v4_bits: Expr, v6_bits: Expr) -> Expr {
+    Expr::Raw(format!(
+        "if {} {{ std::net::IpAddr::V4({}) }} else {{ std::net::IpAddr::V6({}) }}",
+        is_v4.to_code(0),
+        ipv4_addr(v4_bits).to_code(0),
+        ipv6_addr(v6_bits).to_code(0)
+    ))
+}
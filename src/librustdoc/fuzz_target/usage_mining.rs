@@ -0,0 +1,239 @@
+//Call sequences of the crate taken from the code of crates using it. The generator run on a
+//dependent crate with `--dump-usage FILE` writes the calls of every body of that crate to FILE,
+//resolved by its type check, so a method call is known to call the method of the type of its
+//receiver. `--usage-from FILE,...` reads these dumps when the targets of the crate are generated:
+//the calls to the apis of the crate in a body, in the order they are evaluated, are a template,
+//and a sequence is built by adding the calls of the template one after another as long as their
+//parameters can be satisfied. The strategy starts from the resulting sequences and extends them
+//like the ones it finds itself, so the usage of real users leads to longer targets.
+use crate::error::Error;
+use crate::fuzz_target::api_graph::{ApiGraph, ApiType};
+use crate::fuzz_target::api_sequence::ApiSequence;
+use crate::fuzz_target::graph_traverse::SequenceExtender;
+use crate::try_err;
+use crate::TyCtxt;
+use rustc_data_structures::fx::{FxHashMap, FxHashSet};
+use rustc_hir as hir;
+use rustc_hir::def::{DefKind, Res};
+use rustc_hir::intravisit::{self, Visitor};
+use rustc_middle::hir::nested_filter;
+use rustc_middle::ty::TypeckResults;
+use rustc_span::def_id::DefId;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::fs;
+use std::path::Path;
+
+//a name shared by more apis is too ambiguous to tell which one is called
+static MAX_CANDIDATES: usize = 8;
+
+//the apis each call of a template may be
+type Template = Vec<Vec<usize>>;
+
+//the calls of a dependent crate, written with `--dump-usage`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct UsageDump {
+    //the paths of the functions of other crates called in every body, in the order they are
+    //evaluated, e.g. `url::Url::parse`
+    bodies: Vec<Vec<String>>,
+}
+
+//the calls of every body of the crate, closures are part of the body they are written in
+pub(crate) fn dump_usage(tcx: TyCtxt<'_>, path: &Path) -> Result<(), Error> {
+    let mut dump = UsageDump::default();
+    for def_id in tcx.hir().body_owners() {
+        if tcx.is_typeck_child(def_id.to_def_id()) {
+            continue;
+        }
+        let mut finder = CallFinder { tcx, typeck_results: tcx.typeck(def_id), calls: Vec::new() };
+        finder.visit_body(tcx.hir().body(tcx.hir().body_owned_by(def_id)));
+        if finder.calls.len() > 1 {
+            dump.bodies.push(finder.calls);
+        }
+    }
+    let content = serde_json::to_string(&dump).unwrap();
+    try_err!(fs::write(path, content), path);
+    println!("the calls of {} bodies are written to {}", dump.bodies.len(), path.display());
+    Ok(())
+}
+
+//functions and methods of other crates called in a body, callees after their arguments
+struct CallFinder<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    typeck_results: &'tcx TypeckResults<'tcx>,
+    calls: Vec<String>,
+}
+
+impl<'tcx> Visitor<'tcx> for CallFinder<'tcx> {
+    type NestedFilter = nested_filter::OnlyBodies;
+
+    fn nested_visit_map(&mut self) -> Self::Map {
+        self.tcx.hir()
+    }
+
+    fn visit_expr(&mut self, ex: &'tcx hir::Expr<'tcx>) {
+        intravisit::walk_expr(self, ex);
+        let callee: Option<DefId> = match ex.kind {
+            hir::ExprKind::Call(func, _) => match &func.kind {
+                hir::ExprKind::Path(qpath) => {
+                    match self.typeck_results.qpath_res(qpath, func.hir_id) {
+                        Res::Def(DefKind::Fn | DefKind::AssocFn, def_id) => Some(def_id),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            },
+            hir::ExprKind::MethodCall(..) => self.typeck_results.type_dependent_def_id(ex.hir_id),
+            _ => None,
+        };
+        if let Some(def_id) = callee.filter(|def_id| !def_id.is_local()) {
+            self.calls.push(without_generic_args(&self.tcx.def_path_str(def_id)));
+        }
+    }
+}
+
+//`std::vec::Vec::<T>::push` is `std::vec::Vec::push`
+fn without_generic_args(path: &str) -> String {
+    let mut res = String::new();
+    let mut depth = 0;
+    for c in path.chars() {
+        match c {
+            '<' => depth += 1,
+            '>' if depth > 0 => depth -= 1,
+            _ if depth == 0 => res.push(c),
+            _ => {}
+        }
+    }
+    res.replace("::::", "::")
+}
+
+pub(crate) fn add_mined_sequences(api_graph: &mut ApiGraph<'_>) {
+    let usage_paths = api_graph.cx.options.usage_paths.clone();
+    if usage_paths.is_empty() {
+        return;
+    }
+    let full_names = api_full_names(api_graph);
+    let names = api_names(api_graph);
+    let mut templates = Vec::new();
+    for usage_path in &usage_paths {
+        let dump = fs::read(usage_path).map_err(|e| e.to_string()).and_then(|content| {
+            serde_json::from_slice::<UsageDump>(&content).map_err(|e| e.to_string())
+        });
+        let dump = match dump {
+            Ok(dump) => dump,
+            Err(e) => {
+                println!("warning: failed to read {}: {}", usage_path.display(), e);
+                continue;
+            }
+        };
+        for body in &dump.bodies {
+            let template: Template = body
+                .iter()
+                .filter_map(|path| called_apis(path, api_graph.lib_name(), &full_names, &names))
+                .collect();
+            if template.len() > 1 {
+                templates.push(template);
+            }
+        }
+    }
+
+    let extender = api_graph.extender();
+    let max_len = api_graph.cx.options.max_seq_len;
+    let mut seen = FxHashSet::default();
+    let mut mined_sequences = Vec::new();
+    for template in &templates {
+        if let Some(sequence) = replay(&extender, template, max_len) {
+            if seen.insert(sequence.function_indexes()) {
+                mined_sequences.push(sequence);
+            }
+        }
+    }
+    println!(
+        "{} call sequences are found in {} usage dumps, {} of them are extended",
+        templates.len(),
+        usage_paths.len(),
+        mined_sequences.len()
+    );
+    api_graph.mined_sequences = mined_sequences;
+}
+
+//the apis by their full name without the crate name
+fn api_full_names(api_graph: &ApiGraph<'_>) -> FxHashMap<String, Vec<usize>> {
+    let mut res: FxHashMap<String, Vec<usize>> = FxHashMap::default();
+    for (index, api_function) in api_graph.api_functions.iter().enumerate() {
+        if let Some((_, path)) = api_function.full_name.split_once("::") {
+            res.entry(path.to_string()).or_default().push(index);
+        }
+    }
+    res
+}
+
+//the apis by the last segment of their name, with the segment before it
+fn api_names(api_graph: &ApiGraph<'_>) -> FxHashMap<String, Vec<(usize, Option<String>)>> {
+    let mut res: FxHashMap<String, Vec<(usize, Option<String>)>> = FxHashMap::default();
+    for (index, api_function) in api_graph.api_functions.iter().enumerate() {
+        let mut segments = api_function.full_name.rsplit("::");
+        let name = match segments.next() {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        let qualifier = segments.next().map(|qualifier| qualifier.to_string());
+        res.entry(name).or_default().push((index, qualifier));
+    }
+    //apis called more in the crate are tried first
+    for candidates in res.values_mut() {
+        candidates.sort_by_key(|(index, _)| Reverse(api_graph.api_weight(*index)));
+    }
+    res
+}
+
+//the apis a resolved call may be, None if it doesn't call the crate. The path of a dump is the one
+//visible from the dependent crate, which can differ from the full name of the api when the api is
+//re-exported, so the type and name are compared when the full name doesn't match
+fn called_apis(
+    path: &str,
+    lib_name: &str,
+    full_names: &FxHashMap<String, Vec<usize>>,
+    names: &FxHashMap<String, Vec<(usize, Option<String>)>>,
+) -> Option<Vec<usize>> {
+    let (crate_name, path) = path.split_once("::")?;
+    if crate_name != lib_name {
+        return None;
+    }
+    if let Some(indexes) = full_names.get(path) {
+        return Some(indexes.clone());
+    }
+    let mut segments = path.rsplit("::");
+    let candidates = names.get(segments.next()?)?;
+    let qualifier = segments.next();
+    let res: Vec<usize> = candidates
+        .iter()
+        .filter(|(_, api_qualifier)| api_qualifier.as_deref() == qualifier)
+        .map(|(index, _)| *index)
+        .collect();
+    if res.is_empty() || res.len() > MAX_CANDIDATES { None } else { Some(res) }
+}
+
+//add the calls of the template whose parameters can be satisfied, up to the max length
+fn replay(
+    extender: &SequenceExtender<'_>,
+    template: &Template,
+    max_len: usize,
+) -> Option<ApiSequence> {
+    let mut sequence = ApiSequence::new();
+    for candidates in template {
+        if sequence.len() >= max_len {
+            break;
+        }
+        for candidate in candidates {
+            if let Some(new_sequence) =
+                extender.is_fun_satisfied(&ApiType::BareFunction, *candidate, &sequence)
+            {
+                sequence = new_sequence;
+                break;
+            }
+        }
+    }
+    //a single call is found by any strategy
+    if sequence.len() > 1 && !sequence._has_no_fuzzables() { Some(sequence) } else { None }
+}
@@ -0,0 +1,187 @@
+//The where clauses of a generic api checked against the types chosen for its type parameters.
+//The hints are matched to the bounds by the name of the trait only, so `T: Hash + Eq` may get a
+//type that is `Hash` but not `Eq`, or an associated type bound like `T: Iterator<Item = u8>` may
+//not hold, and the target doesn't compile. The predicates of the api, those of its impl included,
+//are instantiated with the substitutes and given to the trait selection of the compiler, like the
+//blanket impls of rustdoc are. A monomorphization is kept when they may hold. The api is not
+//checked when a type parameter has no substitute, e.g. one of its impl or the `Self` of a trait,
+//or when a substitute has no type of the compiler, e.g. a generic or a trait object. The hint of
+//every type parameter and `impl Trait` input is also checked against the trait bounds written on
+//it, which covers the apis whose where clauses can't be checked.
+use crate::clean::{self, types::PrimitiveType};
+use crate::rustc_trait_selection::traits::query::evaluate_obligation::InferCtxtExt;
+use rustc_data_structures::fx::FxHashMap;
+use rustc_hir as hir;
+use rustc_hir::Mutability;
+use rustc_hir::def::DefKind;
+use rustc_hir::def_id::DefId;
+use rustc_infer::infer::TyCtxtInferExt;
+use rustc_infer::traits;
+use rustc_middle::ty::subst::{GenericArg, InternalSubsts};
+use rustc_middle::ty::{self, GenericParamDefKind, ToPredicate, Ty, TyCtxt};
+use rustc_span::DUMMY_SP;
+
+//false if a predicate of the api can't hold with the substitutes
+pub(crate) fn satisfies_where_clauses<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    def_id: DefId,
+    substitutes: &FxHashMap<String, clean::Type>,
+) -> bool {
+    let infcx = tcx.infer_ctxt().build();
+    //the lifetimes are left to the inference
+    let fresh_substs = infcx.fresh_substs_for_item(DUMMY_SP, def_id);
+    let mut is_checked = true;
+    let substs = InternalSubsts::for_item(tcx, def_id, |param, _| match param.kind {
+        GenericParamDefKind::Lifetime => fresh_substs[param.index as usize],
+        GenericParamDefKind::Type { .. } => {
+            match substitutes.get(param.name.as_str()).and_then(|ty| middle_ty(tcx, ty)) {
+                Some(ty) => ty.into(),
+                None => {
+                    is_checked = false;
+                    tcx.mk_param_from_def(param)
+                }
+            }
+        }
+        GenericParamDefKind::Const { .. } => {
+            is_checked = false;
+            tcx.mk_param_from_def(param)
+        }
+    });
+    if !is_checked {
+        return true;
+    }
+    let predicates = tcx.predicates_of(def_id).instantiate(tcx, substs).predicates;
+    predicates.into_iter().all(|predicate| {
+        let obligation = traits::Obligation::new(
+            traits::ObligationCause::dummy(),
+            ty::ParamEnv::reveal_all(),
+            predicate,
+        );
+        infcx.predicate_may_hold(&obligation)
+    })
+}
+
+//false if the hinted type can't implement a trait bound, e.g. a hint of `Hash` given to a
+//`T: Hash + Eq`. Traits with generic parameters besides `Self`, e.g. `Into<Foo>`, are left to the
+//where clauses of the api
+pub(crate) fn satisfies_bounds<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    ty: &clean::Type,
+    bounds: &[clean::GenericBound],
+) -> bool {
+    let self_ty = match middle_ty(tcx, ty) {
+        Some(self_ty) => self_ty,
+        None => return true,
+    };
+    let infcx = tcx.infer_ctxt().build();
+    bounds.iter().all(|bound| {
+        let trait_def_id = match bound {
+            clean::GenericBound::TraitBound(poly_trait, hir::TraitBoundModifier::None) => {
+                poly_trait.trait_.def_id()
+            }
+            _ => return true,
+        };
+        if tcx.generics_of(trait_def_id).count() != 1 {
+            return true;
+        }
+        let trait_ref = ty::TraitRef::new(trait_def_id, tcx.mk_substs_trait(self_ty, &[]));
+        let predicate = ty::Binder::dummy(trait_ref)
+            .to_poly_trait_predicate()
+            .map_bound(ty::PredicateKind::Trait)
+            .to_predicate(tcx);
+        let obligation = traits::Obligation::new(
+            traits::ObligationCause::dummy(),
+            ty::ParamEnv::reveal_all(),
+            predicate,
+        );
+        infcx.predicate_may_hold(&obligation)
+    })
+}
+
+//the type of the compiler, references are `'static` like the literals of the targets
+fn middle_ty<'tcx>(tcx: TyCtxt<'tcx>, ty: &clean::Type) -> Option<Ty<'tcx>> {
+    match ty {
+        clean::Type::Primitive(primitive) => primitive_ty(tcx, *primitive),
+        clean::Type::BorrowedRef { mutability, type_, .. } => {
+            let type_ = middle_ty(tcx, type_)?;
+            let region = tcx.lifetimes.re_static;
+            match mutability {
+                Mutability::Mut => Some(tcx.mk_mut_ref(region, type_)),
+                Mutability::Not => Some(tcx.mk_imm_ref(region, type_)),
+            }
+        }
+        clean::Type::Slice(type_) => Some(tcx.mk_slice(middle_ty(tcx, type_)?)),
+        clean::Type::Array(type_, len) => {
+            Some(tcx.mk_array(middle_ty(tcx, type_)?, len.trim().parse().ok()?))
+        }
+        clean::Type::Tuple(types) => {
+            let types: Option<Vec<Ty<'tcx>>> = types.iter().map(|ty| middle_ty(tcx, ty)).collect();
+            Some(tcx.mk_tup(types?.into_iter()))
+        }
+        clean::Type::Path { path } => path_ty(tcx, path),
+        _ => None,
+    }
+}
+
+//an adt or a type alias, the missing generic arguments take their defaults
+fn path_ty<'tcx>(tcx: TyCtxt<'tcx>, path: &clean::Path) -> Option<Ty<'tcx>> {
+    let def_id = path.def_id();
+    if !matches!(
+        tcx.def_kind(def_id),
+        DefKind::Struct | DefKind::Enum | DefKind::Union | DefKind::TyAlias
+    ) {
+        return None;
+    }
+    let mut args = Vec::new();
+    for arg in path.generics().unwrap_or_default() {
+        args.push(middle_ty(tcx, arg)?);
+    }
+    let mut args = args.into_iter();
+    let mut is_complete = true;
+    let substs = InternalSubsts::for_item(tcx, def_id, |param, substs| -> GenericArg<'tcx> {
+        match param.kind {
+            GenericParamDefKind::Lifetime => tcx.lifetimes.re_static.into(),
+            GenericParamDefKind::Type { has_default, .. } => match args.next() {
+                Some(ty) => ty.into(),
+                None if has_default => tcx.bound_type_of(param.def_id).subst(tcx, substs).into(),
+                None => {
+                    is_complete = false;
+                    tcx.mk_param_from_def(param)
+                }
+            },
+            GenericParamDefKind::Const { .. } => {
+                is_complete = false;
+                tcx.mk_param_from_def(param)
+            }
+        }
+    });
+    if !is_complete {
+        return None;
+    }
+    Some(tcx.bound_type_of(def_id).subst(tcx, substs))
+}
+
+fn primitive_ty<'tcx>(tcx: TyCtxt<'tcx>, primitive: PrimitiveType) -> Option<Ty<'tcx>> {
+    let ty = match primitive {
+        PrimitiveType::Isize => tcx.types.isize,
+        PrimitiveType::I8 => tcx.types.i8,
+        PrimitiveType::I16 => tcx.types.i16,
+        PrimitiveType::I32 => tcx.types.i32,
+        PrimitiveType::I64 => tcx.types.i64,
+        PrimitiveType::I128 => tcx.types.i128,
+        PrimitiveType::Usize => tcx.types.usize,
+        PrimitiveType::U8 => tcx.types.u8,
+        PrimitiveType::U16 => tcx.types.u16,
+        PrimitiveType::U32 => tcx.types.u32,
+        PrimitiveType::U64 => tcx.types.u64,
+        PrimitiveType::U128 => tcx.types.u128,
+        PrimitiveType::F32 => tcx.types.f32,
+        PrimitiveType::F64 => tcx.types.f64,
+        PrimitiveType::Char => tcx.types.char,
+        PrimitiveType::Bool => tcx.types.bool,
+        PrimitiveType::Str => tcx.types.str_,
+        PrimitiveType::Unit => tcx.mk_unit(),
+        _ => return None,
+    };
+    Some(ty)
+}
@@ -0,0 +1,199 @@
+//The targets as a self-contained cargo workspace `rulf-fuzz/`, instead of loose files next to one
+//Cargo.toml. Every target is a crate of its own, with a bin for every emitter of `--emit-harness`
+//writing bins, by default the AFL target and its replay file, and only the features of the
+//package its apis need. The data decoding helpers the targets repeat are defined once in the
+//`rulf_runtime` crate, which every target imports. The package under test is a path dependency,
+//so the workspace can be built wherever it is copied to on the same machine. With `--sanitizer`,
+//the workspace has a profile for every sanitizer, e.g.
+//`cargo afl build --profile asan --target x86_64-unknown-linux-gnu`, so that the flags of the
+//sanitizer don't have to be set by hand.
+use crate::docfs::PathError;
+use crate::error::Error;
+use crate::fuzz_target::api_graph::ApiGraph;
+use crate::fuzz_target::dependency;
+use crate::fuzz_target::features;
+use crate::fuzz_target::file_util::{self, FileHelper};
+use crate::fuzz_target::harness_emitter::{self, AflEmitter, HarnessEmitter, TESTS_DIR};
+use crate::fuzz_target::replay_util;
+use crate::fuzz_target::runtime_crate::{self, RUNTIME_CRATE, RUNTIME_IMPORT};
+use crate::fuzz_target::serde_producer;
+use crate::try_err;
+use std::fs;
+use std::path::Path;
+
+pub(crate) static WORKSPACE_DIR: &'static str = "rulf-fuzz";
+
+pub(crate) fn write_workspace(
+    api_graph: &ApiGraph<'_>,
+    helper: &FileHelper,
+    emitters: &[&dyn HarnessEmitter],
+    test_dir: &Path,
+) -> Result<(), Error> {
+    let lib_target = match &api_graph.cx.options.lib_target {
+        Some(lib_target) => lib_target,
+        None => {
+            println!("note: the workspace is not written, it needs `--manifest-path`");
+            return Ok(());
+        }
+    };
+    let workspace_dir = test_dir.join(WORKSPACE_DIR);
+    let naming = api_graph.cx.options.naming;
+    if !helper.regeneration.incremental {
+        if workspace_dir.exists() {
+            try_err!(fs::remove_dir_all(&workspace_dir), &workspace_dir);
+        }
+    } else {
+        //the crates of unchanged targets are kept as they are
+        for target_name in &helper.regeneration.removed {
+            let crate_name = file_util::test_file_name(naming, "test", target_name);
+            let crate_dir = workspace_dir.join(crate_name.trim_end_matches(".rs"));
+            if crate_dir.exists() {
+                try_err!(fs::remove_dir_all(&crate_dir), &crate_dir);
+            }
+        }
+    }
+    let package_dir = lib_target.manifest_path.parent().unwrap_or(Path::new("."));
+    let package_dependency = |required_features: &Vec<String>| {
+        let mut res = format!(
+            "{} = {{ path = {:?}",
+            lib_target.package_name,
+            package_dir.display().to_string()
+        );
+        if !required_features.is_empty() {
+            res.push_str(
+                format!(", features = {}", features::toml_list(required_features)).as_str(),
+            );
+        }
+        res.push_str(" }\n");
+        res
+    };
+
+    //the tests are in `tests/` of the target crates, the files of the other emitters are written
+    //next to the workspace
+    let bin_emitters: Vec<&dyn HarnessEmitter> =
+        emitters.iter().copied().filter(|emitter| emitter.is_bin()).collect();
+    let test_emitters: Vec<&dyn HarnessEmitter> =
+        emitters.iter().copied().filter(|emitter| emitter.dev_dependency().is_some()).collect();
+    let mut members = vec![RUNTIME_CRATE.to_string()];
+    let forwarded_features = features::dependency_features(api_graph);
+    for (i, sequence) in helper.chosen_sequences.iter().enumerate() {
+        let target_name = file_util::test_file_name(naming, "test", &helper.target_names[i]);
+        let target_name = target_name.trim_end_matches(".rs").to_string();
+        if helper.regeneration.is_unchanged(i) && workspace_dir.join(&target_name).is_dir() {
+            members.push(target_name);
+            continue;
+        }
+        let required_features: Vec<String> = features::sequence_features(sequence, api_graph)
+            .into_iter()
+            .filter(|feature| forwarded_features.contains(feature))
+            .collect();
+
+        let mut manifest = package_header(&target_name);
+        manifest.push_str("\n[dependencies]\n");
+        for emitter in &bin_emitters {
+            if let Some(dependency) = emitter.dependency() {
+                manifest.push_str(format!("{}\n", dependency).as_str());
+            }
+        }
+        manifest.push_str(runtime_crate::dependency(&format!("../{}", RUNTIME_CRATE)).as_str());
+        manifest.push_str(package_dependency(&required_features).as_str());
+        if sequence._async_tag {
+            manifest.push_str(features::async_runtime_dependency(api_graph));
+            manifest.push('\n');
+        }
+        let serde_dependency =
+            serde_producer::serde_dependency(api_graph, std::slice::from_ref(sequence));
+        if let Some(serde_dependency) = serde_dependency {
+            manifest.push_str(serde_dependency);
+            manifest.push('\n');
+        }
+        let sequences = std::slice::from_ref(sequence);
+        manifest.push_str(dependency::manifest_lines(api_graph, sequences).as_str());
+        if !test_emitters.is_empty() {
+            manifest.push_str("\n[dev-dependencies]\n");
+            for emitter in &test_emitters {
+                manifest.push_str(format!("{}\n", emitter.dev_dependency().unwrap()).as_str());
+            }
+        }
+
+        //the helpers of the sequence are replaced by the ones of the runtime crate
+        let extern_crates = sequence._extern_crates(api_graph);
+        let shared_helpers = sequence._afl_helper_functions(api_graph).unwrap_or_default();
+        let use_common = |file: &String| {
+            file.replacen(shared_helpers.as_str(), "", 1).replacen(
+                extern_crates.as_str(),
+                format!("{}{}", extern_crates, RUNTIME_IMPORT).as_str(),
+                1,
+            )
+        };
+        let read_data = format!("{}\n", replay_util::_read_crash_file_data());
+        let src_dir = workspace_dir.join(&target_name).join("src");
+        try_err!(fs::create_dir_all(&src_dir), &src_dir);
+        for emitter in &bin_emitters {
+            let bin_name = harness_emitter::bin_name(*emitter, naming, &helper.target_names[i]);
+            let file_name = bin_file_name(*emitter);
+            manifest.push_str(
+                format!("\n[[bin]]\nname = {:?}\npath = \"src/{}\"\n", bin_name, file_name)
+                    .as_str(),
+            );
+            let file = use_common(&emitter.emit(sequence, api_graph, i));
+            write_file(&src_dir.join(file_name), file.replacen(read_data.as_str(), "", 1))?;
+        }
+        let tests_dir = workspace_dir.join(&target_name).join(TESTS_DIR);
+        for emitter in &test_emitters {
+            try_err!(fs::create_dir_all(&tests_dir), &tests_dir);
+            let file = emitter.emit(sequence, api_graph, i);
+            write_file(&tests_dir.join(format!("{}.rs", emitter.name())), file)?;
+        }
+        write_file(&workspace_dir.join(&target_name).join("Cargo.toml"), manifest)?;
+        members.push(target_name);
+    }
+
+    runtime_crate::write_runtime_crate(api_graph, &helper.chosen_sequences, &workspace_dir)?;
+    let sanitizers = &api_graph.cx.options.sanitizers;
+    let mut manifest = String::new();
+    //rustflags of profiles are unstable, the sanitizers need nightly anyway
+    if !sanitizers.is_empty() {
+        manifest.push_str("cargo-features = [\"profile-rustflags\"]\n\n");
+    }
+    manifest.push_str("[workspace]\nmembers = [\n");
+    for member in &members {
+        manifest.push_str(format!("    {:?},\n", member).as_str());
+    }
+    manifest.push_str("]\n");
+    for sanitizer in sanitizers {
+        let rustflags = sanitizer.rustflags().iter().map(|flag| flag.to_string()).collect();
+        manifest.push_str(
+            format!(
+                "\n[profile.{}]\ninherits = \"release\"\nrustflags = {}\n",
+                sanitizer.profile(),
+                features::toml_list(&rustflags)
+            )
+            .as_str(),
+        );
+    }
+    write_file(&workspace_dir.join("Cargo.toml"), manifest)?;
+    println!("workspace of the targets is written to {}", workspace_dir.display());
+    Ok(())
+}
+
+//the file of the bin in `src/`, `main.rs` for the AFL target
+fn bin_file_name(emitter: &dyn HarnessEmitter) -> String {
+    if emitter.name() == AflEmitter.name() {
+        "main.rs".to_string()
+    } else {
+        format!("{}.rs", emitter.name())
+    }
+}
+
+fn package_header(name: &str) -> String {
+    format!(
+        "[package]\nname = {:?}\nversion = \"0.0.0\"\nedition = \"2018\"\npublish = false\n",
+        name
+    )
+}
+
+fn write_file(path: &Path, content: String) -> Result<(), Error> {
+    try_err!(fs::write(path, content), path);
+    Ok(())
+}
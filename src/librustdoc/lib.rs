@@ -678,6 +678,524 @@ fn opts() -> Vec<RustcOptGroup> {
                 "path to function call information (for displaying examples in the documentation)",
             )
         }),
+        // fuzz target generator options
+        stable("strategy", |o| {
+            o.optopt(
+                "",
+                "strategy",
+                "algorithm used to generate api sequences (default: bfs + backward search)",
+                "[default|bfs|fast-bfs|bfs-end-point|fast-bfs-end-point|random-walk|\
+                random-walk-end-point|try-deep-bfs|backward|ga|sink|multi-object]",
+            )
+        }),
+        stable("selection", |o| {
+            o.optopt(
+                "",
+                "selection",
+                "how the targets are chosen to cover all apis (default greedy)",
+                "greedy|exact",
+            )
+        }),
+        stable("jobs", |o| {
+            o.optopt(
+                "",
+                "jobs",
+                "number of threads enumerating api sequences (default: one per cpu)",
+                "N",
+            )
+        }),
+        stable("max-seq-len", |o| {
+            o.optopt("", "max-seq-len", "the most calls of a bfs sequence (default 3)", "N")
+        }),
+        stable("max-targets", |o| {
+            o.optopt(
+                "",
+                "max-targets",
+                "the most targets chosen to cover the apis, before variants (default 300)",
+                "N",
+            )
+        }),
+        stable("mega-sequence", |o| {
+            o.optflag(
+                "",
+                "mega-sequence",
+                "merge the targets of every connected component of the api graph into one",
+            )
+        }),
+        stable("tail-variants", |o| {
+            o.optopt(
+                "",
+                "tail-variants",
+                "generate K sibling targets for each sequence that differ only in the final call",
+                "K",
+            )
+        }),
+        stable("max-targets-per-api", |o| {
+            o.optopt(
+                "",
+                "max-targets-per-api",
+                "limit the number of targets each api appears in, unless an api needs more targets",
+                "N",
+            )
+        }),
+        stable("min-targets-per-api", |o| {
+            o.optopt(
+                "",
+                "min-targets-per-api",
+                "add targets until each reachable api appears in at least N of them",
+                "N",
+            )
+        }),
+        stable("manifest-path", |o| {
+            o.optopt(
+                "",
+                "manifest-path",
+                "Cargo.toml of the crate, its lib (or with --crate-type bin, bin) target is used",
+                "PATH",
+            )
+        }),
+        stable("async-runtime", |o| {
+            o.optopt(
+                "",
+                "async-runtime",
+                "runtime used to drive async apis in fuzz targets (default: futures)",
+                "[futures|tokio]",
+            )
+        }),
+        stable("error-policy", |o| {
+            o.optopt(
+                "",
+                "error-policy",
+                "how Err and None results are handled in fuzz targets (default: early-return)",
+                "[early-return|unwrap|match-both]",
+            )
+        }),
+        stable("crash-target", |o| {
+            o.optopt(
+                "",
+                "crash-target",
+                "generated target that crashed on `--crash-file`, e.g. fuzz_url_url_parse_<hash>",
+                "NAME",
+            )
+        }),
+        stable("crash-file", |o| {
+            o.optopt(
+                "",
+                "crash-file",
+                "crash input to convert into a standalone #[test] with the inputs inlined",
+                "PATH",
+            )
+        }),
+        stable("msrv", |o| {
+            o.optopt(
+                "",
+                "msrv",
+                "minimum Rust version the generated code has to build with, e.g. 1.56",
+                "VERSION",
+            )
+        }),
+        stable("compile-check", |o| {
+            o.optflag(
+                "",
+                "compile-check",
+                "check the fuzz targets with cargo check, and remove or repair the broken ones",
+            )
+        }),
+        stable("format-harness", |o| {
+            o.optflag(
+                "",
+                "format-harness",
+                "also format the results of fuzz targets with fuzzed width, precision and flags",
+            )
+        }),
+        stable("include-deps", |o| {
+            o.optopt(
+                "",
+                "include-deps",
+                "direct dependencies, named as in the code, whose apis also produce parameters",
+                "CRATE,CRATE,...",
+            )
+        }),
+        stable("features", |o| {
+            o.optopt(
+                "",
+                "features",
+                "features of the crate to document and generate fuzz targets with",
+                "FEATURE,FEATURE,...",
+            )
+        }),
+        stable("all-features", |o| {
+            o.optflag("", "all-features", "enable all features of the crate of `--manifest-path`")
+        }),
+        stable("max-struct-depth", |o| {
+            o.optopt(
+                "",
+                "max-struct-depth",
+                "how deep structs with public fields are nested when built from fuzz data \
+                (default 3)",
+                "DEPTH",
+            )
+        }),
+        stable("differential", |o| {
+            o.optflag(
+                "",
+                "differential",
+                "also assert that apis with the same contract agree, e.g. encode and decode",
+            )
+        }),
+        stable("round-trip", |o| {
+            o.optflag(
+                "",
+                "round-trip",
+                "also assert that results survive an encode and decode round trip, e.g. to_string",
+            )
+        }),
+        stable("round-trip-equivalence", |o| {
+            o.optopt(
+                "",
+                "round-trip-equivalence",
+                "how decoded values are compared with the original ones (default auto)",
+                "auto|eq|debug",
+            )
+        }),
+        stable("documented-panics", |o| {
+            o.optopt(
+                "",
+                "documented-panics",
+                "guard the calls against the panics documented in `# Panics`, or only tag the \
+                targets as expected to panic (default tag)",
+                "guard|tag|ignore",
+            )
+        }),
+        stable("ignore-panics", |o| {
+            o.optflag(
+                "",
+                "ignore-panics",
+                "catch the panics of every call, only aborts like memory errors are crashes",
+            )
+        }),
+        stable("graph-cache", |o| {
+            o.optflag(
+                "",
+                "graph-cache",
+                "reuse the dependencies of the api graph cached in target/rulf-cache",
+            )
+        }),
+        stable("no-std", |o| {
+            o.optflag(
+                "",
+                "no-std",
+                "generate `#![no_std]` targets that only use core and alloc to call the crate",
+            )
+        }),
+        stable("emit-workspace", |o| {
+            o.optflag(
+                "",
+                "emit-workspace",
+                "write the fuzz targets as a cargo workspace with a crate for every target",
+            )
+        }),
+        stable("emit-run-manifest", |o| {
+            o.optflag(
+                "",
+                "emit-run-manifest",
+                "write targets.toml and the commands running every target with afl and libfuzzer",
+            )
+        }),
+        stable("emit-ossfuzz", |o| {
+            o.optflag(
+                "",
+                "emit-ossfuzz",
+                "write the build.sh, Dockerfile and project.yaml of an OSS-Fuzz project",
+            )
+        }),
+        stable("usage-from", |o| {
+            o.optopt(
+                "",
+                "usage-from",
+                "usage dumps of crates using the crate, whose call sequences are extended",
+                "PATH,PATH,...",
+            )
+        }),
+        stable("dump-usage", |o| {
+            o.optopt(
+                "",
+                "dump-usage",
+                "write the calls of the crate for `--usage-from` instead of generating targets",
+                "PATH",
+            )
+        }),
+        stable("include-hidden", |o| {
+            o.optflag("", "include-hidden", "also generate targets calling `#[doc(hidden)]` apis")
+        }),
+        stable("include-deprecated", |o| {
+            o.optflag(
+                "",
+                "include-deprecated",
+                "also generate targets calling `#[deprecated]` apis",
+            )
+        }),
+        stable("include-api", |o| {
+            o.optmulti(
+                "",
+                "include-api",
+                "only use the apis matching the path glob or `/regex/`, can be repeated",
+                "PATTERN",
+            )
+        }),
+        stable("exclude-api", |o| {
+            o.optmulti(
+                "",
+                "exclude-api",
+                "don't use the apis matching the path glob or `/regex/`, can be repeated",
+                "PATTERN",
+            )
+        }),
+        stable("entry-api", |o| {
+            o.optmulti(
+                "",
+                "entry-api",
+                "make every api matching the pattern the last call of at least one target",
+                "PATTERN",
+            )
+        }),
+        stable("config", |o| {
+            o.optopt(
+                "",
+                "config",
+                "read generation options from this file instead of rulf.toml in the crate root",
+                "PATH",
+            )
+        }),
+        stable("operator-harness", |o| {
+            o.optflag(
+                "",
+                "operator-harness",
+                "also sort and hash the results of fuzz targets whose type implements Ord or Hash",
+            )
+        }),
+        stable("drop-harness", |o| {
+            o.optflag(
+                "",
+                "drop-harness",
+                "also drop the results of fuzz targets in orders chosen by the fuzz data",
+            )
+        }),
+        stable("ffi", |o| {
+            o.optflag(
+                "",
+                "ffi",
+                "also fuzz re-exported extern functions, and build the targets calling into C \
+                with AddressSanitizer",
+            )
+        }),
+        stable("sanitizer", |o| {
+            o.optopt(
+                "",
+                "sanitizer",
+                "add cargo profiles building the workspace with these sanitizers: address, \
+                memory, leak",
+                "NAMES",
+            )
+        }),
+        stable("validate", |o| {
+            o.optopt(
+                "",
+                "validate",
+                "run every fuzz target once with the tool before it is written: miri",
+                "TOOL",
+            )
+        }),
+        stable("emit-kani", |o| {
+            o.optflag(
+                "",
+                "emit-kani",
+                "write Kani proof harnesses for the targets with at most 3 calls",
+            )
+        }),
+        stable("no-assert-guards", |o| {
+            o.optflag(
+                "",
+                "no-assert-guards",
+                "don't skip calls whose arguments fail the assertions at the top of the api",
+            )
+        }),
+        stable("char-mutator", |o| {
+            o.optflag(
+                "",
+                "char-mutator",
+                "add a mutator to the libFuzzer targets taking strings that edits whole chars",
+            )
+        }),
+        stable("explain", |o| {
+            o.optopt(
+                "",
+                "explain",
+                "print why an api is or isn't covered instead of writing the targets",
+                "PATH",
+            )
+        }),
+        stable("html-report", |o| {
+            o.optflag(
+                "",
+                "html-report",
+                "also write the report as an html page with the api graph and the targets",
+            )
+        }),
+        stable("emit-metrics", |o| {
+            o.optflag(
+                "",
+                "emit-metrics",
+                "write the api coverage, sequence lengths, targets and time per strategy as csv",
+            )
+        }),
+        stable("emit-harness", |o| {
+            o.optmulti(
+                "",
+                "emit-harness",
+                "harnesses written for every target, comma separated (default: afl,replay)",
+                "[afl|replay|libfuzzer|honggfuzz|proptest|libafl]",
+            )
+        }),
+        stable("allow-default-producer", |o| {
+            o.optflag(
+                "",
+                "allow-default-producer",
+                "use `T::default()` for params of types implementing Default that no api returns",
+            )
+        }),
+        stable("serde-producer", |o| {
+            o.optopt(
+                "",
+                "serde-producer",
+                "deserialize params of Deserialize types that no api returns from the fuzz input",
+                "[json|bincode]",
+            )
+        }),
+        stable("contract-harness", |o| {
+            o.optflag(
+                "",
+                "contract-harness",
+                "also check the laws of Eq, Ord and Hash on three results of a fuzz target",
+            )
+        }),
+        stable("afl-persistent-iterations", |o| {
+            o.optopt(
+                "",
+                "afl-persistent-iterations",
+                "inputs an AFL target runs in persistent mode before it is forked again",
+                "N",
+            )
+        }),
+        stable("afl-deferred-init", |o| {
+            o.optflag(
+                "",
+                "afl-deferred-init",
+                "run AFL targets once before the fork server starts, to initialize the crate once",
+            )
+        }),
+        stable("runtime-crate", |o| {
+            o.optflag(
+                "",
+                "runtime-crate",
+                "import the data decoding helpers of the targets from a rulf_runtime crate",
+            )
+        }),
+        stable("naming", |o| {
+            o.optopt(
+                "",
+                "naming",
+                "name the targets after their entry api and a hash of their code, or their index",
+                "[stable|legacy]",
+            )
+        }),
+        stable("incremental", |o| {
+            o.optflag(
+                "",
+                "incremental",
+                "only write the targets that changed since the rulf-report.json in the output dir",
+            )
+        }),
+        stable("dependency-producers", |o| {
+            o.optflag(
+                "",
+                "dependency-producers",
+                "search the direct dependencies for producers of their types the apis take",
+            )
+        }),
+        stable("const-probes", |o| {
+            o.optflag(
+                "",
+                "const-probes",
+                "add targets for every const fn and probes evaluating it at compile time",
+            )
+        }),
+        stable("rank-targets", |o| {
+            o.optflag(
+                "",
+                "rank-targets",
+                "write the targets reaching unsafe code, unwraps and unchecked indexing first",
+            )
+        }),
+        stable("time-budget", |o| {
+            o.optopt(
+                "",
+                "time-budget",
+                "stop generating sequences after this time and keep the ones found so far",
+                "120s|15m|2h",
+            )
+        }),
+        stable("beam-width", |o| {
+            o.optopt(
+                "",
+                "beam-width",
+                "extend only the N best sequences of each bfs level, spilling the others to disk",
+                "N",
+            )
+        }),
+        stable("sink-api", |o| {
+            o.optmulti(
+                "",
+                "sink-api",
+                "generate the shortest producer chain of every api matching the pattern",
+                "PATTERN",
+            )
+        }),
+        stable("assert-buffer-bounds", |o| {
+            o.optflag(
+                "",
+                "assert-buffer-bounds",
+                "assert that apis writing into a buffer report a length within the buffer",
+            )
+        }),
+        stable("check-invariants", |o| {
+            o.optflag(
+                "",
+                "check-invariants",
+                "assert that returned indexes, strs and lengths are consistent with the inputs",
+            )
+        }),
+        stable("exercise-format", |o| {
+            o.optflag(
+                "",
+                "exercise-format",
+                "format every returned value with `{:?}`, and `{}` when it implements Display",
+            )
+        }),
+        stable("exercise-clone", |o| {
+            o.optflag(
+                "",
+                "exercise-clone",
+                "assert that clones of the values at the end of a target equal the originals",
+            )
+        }),
+        stable("dictionaries", |o| {
+            o.optflag(
+                "",
+                "dictionaries",
+                "write afl and libfuzzer dictionaries of the literals and consts of the crate",
+            )
+        }),
         // deprecated / removed options
         stable("plugin-path", |o| {
             o.optmulti(
@@ -784,10 +1302,26 @@ fn fuzz_target_generator_main_args(at_args: &[String]) -> MainResult {
             early_error(ErrorOutputType::default(), &err.to_string());
         }
     };
+    // Options of `rulf.toml` are parsed as if they followed the command line.
+    let (args, matches) = match fuzz_target::rulf_config::config_args(&matches) {
+        Ok(None) => (args, matches),
+        Ok(Some((config_path, config_args))) => {
+            println!("options are read from {}", config_path.display());
+            let args = [args, config_args].concat();
+            match options.parse(&args[1..]) {
+                Ok(m) => (args, m),
+                Err(err) => {
+                    let msg = format!("{} (in {})", err, config_path.display());
+                    early_error(ErrorOutputType::default(), &msg);
+                }
+            }
+        }
+        Err(msg) => early_error(ErrorOutputType::default(), &msg),
+    };
 
     // Note that we discard any distinction between different non-zero exit
     // codes from `from_matches` here.
-    let (options, render_options) = match config::Options::from_matches(&matches, args) {
+    let (options, mut render_options) = match config::Options::from_matches(&matches, args) {
         Ok(opts) => opts,
         Err(code) => {
             return if code == 0 {
@@ -797,6 +1331,7 @@ fn fuzz_target_generator_main_args(at_args: &[String]) -> MainResult {
             };
         }
     };
+    render_options.fuzz_target_options.generate_targets = true;
 
     let diag = core::new_handler(
         options.error_format,